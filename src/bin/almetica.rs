@@ -1,8 +1,14 @@
 #![warn(clippy::all)]
 use almetica::config::{read_configuration, Configuration};
 use almetica::crypt::password_hash;
-use almetica::dataloader::load_opcode_mapping;
+use almetica::dataloader::abnormality::read_abnormality_registry;
+use almetica::dataloader::item::read_item_registry;
+use almetica::dataloader::npc::{read_npc_registry, read_territory_registry};
+use almetica::dataloader::skill::read_skill_registry;
+use almetica::dataloader::zone::read_zone_registry;
+use almetica::dataloader::{load_datacenter, load_opcode_mapping};
 use almetica::ecs::message::EcsMessage;
+use almetica::ecs::resource::GameData;
 use almetica::ecs::world::GlobalWorld;
 use almetica::model::entity::Account;
 use almetica::model::migrations;
@@ -141,6 +147,48 @@ async fn start_server(_matches: &ArgMatches, config: &Configuration) -> Result<(
             .count()
     );
 
+    info!("Reading datacenter file");
+    let datacenter =
+        load_datacenter(&config.data.path, &config.data.datacenter).context(format!(
+            "Can't read datacenter file {:?} in {:?}",
+            &config.data.datacenter, &config.data.path
+        ))?;
+    let zone_registry =
+        read_zone_registry(&datacenter).context("Can't read the zones from the datacenter")?;
+    info!("Loaded zone registry with {} zones", zone_registry.len());
+    let item_registry =
+        read_item_registry(&datacenter).context("Can't read the items from the datacenter")?;
+    info!("Loaded item registry with {} items", item_registry.len());
+    let skill_registry =
+        read_skill_registry(&datacenter).context("Can't read the skills from the datacenter")?;
+    info!("Loaded skill registry with {} skills", skill_registry.len());
+    let abnormality_registry = read_abnormality_registry(&datacenter)
+        .context("Can't read the abnormalities from the datacenter")?;
+    info!(
+        "Loaded abnormality registry with {} abnormalities",
+        abnormality_registry.len()
+    );
+    let npc_registry =
+        read_npc_registry(&datacenter).context("Can't read the NPCs from the datacenter")?;
+    info!("Loaded NPC registry with {} NPCs", npc_registry.len());
+    let territory_registry = read_territory_registry(&datacenter, &npc_registry)
+        .context("Can't read the territories from the datacenter")?;
+    info!(
+        "Loaded territory registry with {} territory groups",
+        territory_registry.len()
+    );
+
+    // All data is now available in the registries
+    drop(datacenter);
+    let game_data = GameData {
+        zones: zone_registry,
+        items: item_registry,
+        skills: skill_registry,
+        abnormalities: abnormality_registry,
+        npcs: npc_registry,
+        territories: territory_registry,
+    };
+
     info!("Updating database schema");
     migrations::apply(
         format!(
@@ -160,7 +208,8 @@ async fn start_server(_matches: &ArgMatches, config: &Configuration) -> Result<(
     let pool = sqlx_pool(&config).await?;
 
     info!("Starting the ECS");
-    let (global_world_handle, global_tx_channel) = start_global_world(config.clone(), pool.clone());
+    let (global_world_handle, global_tx_channel) =
+        start_global_world(config.clone(), pool.clone(), game_data);
 
     info!("Starting the web server");
     let web_handle = start_web_server(pool, config.clone());
@@ -187,8 +236,9 @@ async fn start_server(_matches: &ArgMatches, config: &Configuration) -> Result<(
 fn start_global_world(
     config: Configuration,
     pool: PgPool,
+    game_data: GameData,
 ) -> (JoinHandle<Result<()>>, Sender<EcsMessage>) {
-    let mut global_world = GlobalWorld::new(&config, &pool);
+    let mut global_world = GlobalWorld::new(&config, &pool, &game_data);
     let channel = global_world.channel.clone();
     let join_handle = task::spawn_blocking(move || {
         global_world.run();
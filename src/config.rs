@@ -34,11 +34,24 @@ pub struct DatabaseConfiguration {
 #[derive(Clone, Debug, Deserialize)]
 pub struct DataConfiguration {
     pub path: PathBuf,
+    #[serde(default = "default_datacenter")]
+    pub datacenter: String,
+}
+
+fn default_datacenter() -> String {
+    "DataCenter_Final_EUR.dat".to_string()
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct GameConfiguration {
     pub pvp: bool,
+    /// Maximal number of users in a channel of a field. 0 disables the cap.
+    #[serde(alias = "channel-user-cap", default = "default_channel_user_cap")]
+    pub channel_user_cap: u32,
+}
+
+fn default_channel_user_cap() -> u32 {
+    150
 }
 
 pub fn read_configuration(path: &PathBuf) -> Result<Configuration> {
@@ -64,8 +77,12 @@ impl Default for Configuration {
             },
             data: DataConfiguration {
                 path: Default::default(),
+                datacenter: default_datacenter(),
+            },
+            game: GameConfiguration {
+                pvp: false,
+                channel_user_cap: default_channel_user_cap(),
             },
-            game: GameConfiguration { pvp: false },
         }
     }
 }
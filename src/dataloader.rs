@@ -1,4 +1,11 @@
 /// Module to read data files
+pub mod abnormality;
+pub mod datacenter;
+pub mod item;
+pub mod npc;
+pub mod skill;
+pub mod zone;
+
 use crate::protocol::opcode::Opcode;
 use crate::*;
 use aes::Aes128;
@@ -6,7 +13,9 @@ use anyhow::ensure;
 use byteorder::{ByteOrder, LittleEndian};
 use cfb_mode::stream_cipher::{NewStreamCipher, StreamCipher};
 use cfb_mode::Cfb;
+use datacenter::DataCenter;
 use flate2::{Decompress, FlushDecompress};
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, Read};
@@ -40,6 +49,31 @@ pub fn read_datacenter_file(key: &[u8], iv: &[u8], mut data: Vec<u8>) -> Result<
     Ok(buffer)
 }
 
+/// The AES key and IV of the datacenter file.
+#[derive(Deserialize)]
+struct DataCenterKey {
+    key: String,
+    iv: String,
+}
+
+/// Load and parse the datacenter file with the given name. The key and IV are read from the
+/// key.yaml.
+pub fn load_datacenter(data_path: &PathBuf, file_name: &str) -> Result<DataCenter> {
+    let mut path = data_path.clone();
+    path.push("key.yaml");
+    let file = File::open(path)?;
+    let key: DataCenterKey = serde_yaml::from_reader(BufReader::new(file))?;
+
+    let mut path = data_path.clone();
+    path.push(file_name);
+    let mut file = File::open(path)?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+
+    let data = read_datacenter_file(&hex::decode(key.key)?, &hex::decode(key.iv)?, data)?;
+    DataCenter::parse(&data)
+}
+
 /// Load opcode mapping from a file (normal and reverse lookup)
 pub fn load_opcode_mapping(data_path: &PathBuf) -> Result<(Vec<Opcode>, HashMap<Opcode, u16>)> {
     let mut path = data_path.clone();
@@ -92,6 +126,7 @@ mod tests {
 
     use super::super::protocol::opcode::Opcode;
     use super::super::*;
+    use super::datacenter::tests::{create_test_datacenter, TestElement, TestValue};
     use super::*;
 
     #[test]
@@ -133,17 +168,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_load_datacenter() -> Result<()> {
+        let key = "1A8ED266690CCF664A741C4CA9D4944E";
+        let iv = "527DE56BB0A2C60DA879A01B8194DC12";
+
+        let mut data_path = std::env::temp_dir();
+        data_path.push(format!("almetica_test_{}", OsRng.next_u64()));
+        std::fs::create_dir_all(&data_path)?;
+
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "ContinentData",
+                vec![("id", TestValue::Int(7))],
+                vec![],
+            )],
+        );
+        let test_data = encrypt_test_data(
+            &hex::decode(key)?,
+            &hex::decode(iv)?,
+            create_test_datacenter(&root)?,
+        )?;
+
+        let mut path = data_path.clone();
+        path.push("key.yaml");
+        std::fs::write(path, format!("key: {}\niv: {}\n", key, iv))?;
+        let mut path = data_path.clone();
+        path.push("DataCenter_Test.dat");
+        std::fs::write(path, test_data)?;
+
+        let dc = load_datacenter(&data_path, "DataCenter_Test.dat");
+        std::fs::remove_dir_all(&data_path)?;
+        let dc = dc?;
+
+        assert_eq!(dc.query("ContinentData")[0].get_i32("id"), Some(7));
+        Ok(())
+    }
+
     // Creates some testdata in the same structure as the TERA datacenter files.
-    //
+    fn create_test_data(key: &[u8], iv: &[u8], size: usize) -> Result<Vec<u8>> {
+        let mut original_data = vec![0u8; size];
+        OsRng.fill_bytes(original_data.as_mut_slice());
+        encrypt_test_data(key, iv, original_data)
+    }
+
     // Write down the size of the original data as u32. Then use zlib deflate to compress the
     // data (with the zlib header) and append the data to the u32 size bytes.
     //
     // Then use AES CFB with the KEY and IV in the TERA client (changes every patch)
     // and crypt the data (CFB is a stream cipher).
-    fn create_test_data(key: &[u8], iv: &[u8], size: usize) -> Result<Vec<u8>> {
-        let mut original_data = vec![0u8; size];
-        OsRng.fill_bytes(original_data.as_mut_slice());
-
+    fn encrypt_test_data(key: &[u8], iv: &[u8], original_data: Vec<u8>) -> Result<Vec<u8>> {
+        let size = original_data.len();
         let mut cipher = Cfb::<Aes128>::new_var(key, iv).unwrap();
         let mut compressor = Compress::new(Compression::best(), true);
 
@@ -0,0 +1,130 @@
+/// Module that reads the abnormality templates out of the datacenter.
+///
+/// Expected structure of the abnormality data:
+///
+/// ```text
+/// AbnormalityData
+///   Abnormality id duration maxStack period hpPerPeriod persistent
+/// ```
+///
+/// `id` and `duration` are required. `duration` and `period` are given in milliseconds.
+/// Abnormalities without a `period` have no periodic effect. `hpPerPeriod` is applied once per
+/// stack, negative values deal damage. `maxStack` defaults to 1 (not stackable) and only
+/// `persistent` abnormalities survive a relog.
+use crate::dataloader::datacenter::{DataCenter, Element};
+use crate::dataloader::skill::millis;
+use crate::ecs::resource::{AbnormalityRegistry, AbnormalityTemplate};
+use crate::*;
+use anyhow::{ensure, Context};
+
+/// Creates the abnormality registry out of the abnormality data of the datacenter.
+pub fn read_abnormality_registry(dc: &DataCenter) -> Result<AbnormalityRegistry> {
+    let abnormalities = dc
+        .query("AbnormalityData/Abnormality")
+        .iter()
+        .map(read_abnormality)
+        .collect::<Result<Vec<AbnormalityTemplate>>>()?;
+    Ok(AbnormalityRegistry::new(abnormalities))
+}
+
+fn read_abnormality(abnormality: &Element) -> Result<AbnormalityTemplate> {
+    let id = abnormality
+        .get_i32("id")
+        .context("Abnormality doesn't have an ID")?;
+    let duration = millis(abnormality.get_i32("duration"));
+    ensure!(
+        duration.as_millis() > 0,
+        "Abnormality {} doesn't have a duration",
+        id
+    );
+
+    Ok(AbnormalityTemplate {
+        id,
+        duration,
+        max_stacks: abnormality.get_i32("maxStack").unwrap_or(1).max(1),
+        period: millis(abnormality.get_i32("period")),
+        hp_per_period: i64::from(abnormality.get_i32("hpPerPeriod").unwrap_or_default()),
+        is_persistent: abnormality.get_bool("persistent").unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataloader::datacenter::tests::{create_test_datacenter, TestElement, TestValue};
+    use std::time::Duration;
+
+    #[test]
+    fn test_read_abnormality_registry() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "AbnormalityData",
+                vec![],
+                vec![
+                    TestElement::new(
+                        "Abnormality",
+                        vec![
+                            ("id", TestValue::Int(4100)),
+                            ("duration", TestValue::Int(10000)),
+                            ("maxStack", TestValue::Int(3)),
+                            ("period", TestValue::Int(2000)),
+                            ("hpPerPeriod", TestValue::Int(-20)),
+                        ],
+                        vec![],
+                    ),
+                    TestElement::new(
+                        "Abnormality",
+                        vec![
+                            ("id", TestValue::Int(4000)),
+                            ("duration", TestValue::Int(3_600_000)),
+                            ("persistent", TestValue::Bool(true)),
+                        ],
+                        vec![],
+                    ),
+                ],
+            )],
+        );
+        let registry =
+            read_abnormality_registry(&DataCenter::parse(&create_test_datacenter(&root)?)?)?;
+        assert_eq!(registry.len(), 2);
+
+        let poison = registry.get(4100).unwrap();
+        assert_eq!(poison.duration, Duration::from_secs(10));
+        assert_eq!(poison.max_stacks, 3);
+        assert_eq!(poison.period, Duration::from_secs(2));
+        assert_eq!(poison.hp_per_period, -20);
+        assert!(!poison.is_persistent);
+
+        let crystal = registry.get(4000).unwrap();
+        assert_eq!(crystal.max_stacks, 1);
+        assert_eq!(crystal.period, Duration::from_secs(0));
+        assert_eq!(crystal.hp_per_period, 0);
+        assert!(crystal.is_persistent);
+
+        assert!(registry.get(1).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_abnormality_without_duration() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "AbnormalityData",
+                vec![],
+                vec![TestElement::new(
+                    "Abnormality",
+                    vec![("id", TestValue::Int(4100))],
+                    vec![],
+                )],
+            )],
+        );
+        let dc = DataCenter::parse(&create_test_datacenter(&root)?)?;
+        assert!(read_abnormality_registry(&dc).is_err());
+        Ok(())
+    }
+}
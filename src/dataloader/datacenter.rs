@@ -0,0 +1,774 @@
+/// Module that parses the decrypted and decompressed datacenter into an element tree.
+///
+/// The datacenter is a big tree of elements, each with a name, a list of typed attributes and
+/// a list of child elements. Names and string values are stored in their own string pools.
+///
+/// Layout of the datacenter (all values are little endian):
+///
+/// ```text
+/// header:     version u32, timestamp f64, 5x unknown u32
+/// attributes: segmented region of attributes (8 bytes each)
+/// elements:   segmented region of elements (16 bytes each)
+/// values:     string table with 1024 buckets
+/// names:      string table with 512 buckets
+/// footer:     unknown u32
+///
+/// segmented region: segment_count u32, segments[segment_count]
+/// segment:          full_count u32, used_count u32, entries[full_count]
+/// simple region:    count u32, entries[count]
+/// address:          segment_index u16, entry_index u16
+///
+/// string table:     characters (segmented region of u16), buckets[N] (simple region of
+///                   string entries), addresses (simple region of addresses)
+/// string entry:     hash u32, length u32, index u32, address
+///
+/// attribute:        name_index u16, type_info u16, value u32
+/// element:          name_index u16, unknown u16, attribute_count u16, child_count u16,
+///                   attribute address, child address
+/// ```
+///
+/// Name indexes are one based indexes into the addresses of the name table. Padding entries have
+/// the name index 0 and therefore no name. The first element of the first element segment is the
+/// root of the tree.
+use crate::*;
+use anyhow::{bail, ensure};
+use byteorder::{ByteOrder, LittleEndian};
+use std::fmt;
+
+const VALUE_TABLE_BUCKETS: usize = 1024;
+const NAME_TABLE_BUCKETS: usize = 512;
+const ATTRIBUTE_SIZE: usize = 8;
+const ELEMENT_SIZE: usize = 16;
+const STRING_ENTRY_SIZE: usize = 16;
+const ADDRESS_SIZE: usize = 4;
+
+/// The typed value of an attribute.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+    String(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(v) => write!(f, "{}", v),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Bool(v) => write!(f, "{}", v),
+            Value::String(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// The header of the datacenter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub version: u32,
+    pub timestamp: f64,
+}
+
+#[derive(Clone, Debug)]
+struct ElementData {
+    name: Option<usize>,
+    attributes: (usize, usize),
+    children: (usize, usize),
+}
+
+#[derive(Clone, Debug)]
+struct AttributeData {
+    name: Option<usize>,
+    value: Value,
+}
+
+/// The parsed datacenter. Elements are stored in a flat arena and are accessed with the
+/// lightweight `Element` handle.
+#[derive(Clone, Debug)]
+pub struct DataCenter {
+    pub header: Header,
+    names: Vec<String>,
+    elements: Vec<ElementData>,
+    attributes: Vec<AttributeData>,
+}
+
+/// Handle to an element inside the datacenter.
+#[derive(Clone, Copy)]
+pub struct Element<'a> {
+    dc: &'a DataCenter,
+    index: usize,
+}
+
+impl DataCenter {
+    /// Parses the decrypted and decompressed datacenter data.
+    pub fn parse(data: &[u8]) -> Result<DataCenter> {
+        let mut reader = Reader::new(data);
+
+        let header = Header {
+            version: reader.u32()?,
+            timestamp: reader.f64()?,
+        };
+        reader.skip(5 * 4)?;
+
+        let raw_attributes = reader.segmented_region(ATTRIBUTE_SIZE)?;
+        let raw_elements = reader.segmented_region(ELEMENT_SIZE)?;
+        let values = StringTable::read(&mut reader, VALUE_TABLE_BUCKETS)?;
+        let names = StringTable::read(&mut reader, NAME_TABLE_BUCKETS)?;
+        reader.skip(4)?;
+
+        let names = names
+            .addresses
+            .iter()
+            .map(|address| names.string(*address))
+            .collect::<Result<Vec<String>>>()?;
+
+        let mut attributes = Vec::with_capacity(raw_attributes.entries.len());
+        for entry in raw_attributes.entries.iter() {
+            let name = resolve_name_index(LittleEndian::read_u16(&entry[0..2]), names.len())?;
+            let type_info = LittleEndian::read_u16(&entry[2..4]);
+            let value = match type_info & 0b11 {
+                1 if (type_info >> 2) & 1 == 1 => {
+                    Value::Bool(LittleEndian::read_u32(&entry[4..8]) != 0)
+                }
+                1 => Value::Int(LittleEndian::read_i32(&entry[4..8])),
+                2 => Value::Float(LittleEndian::read_f32(&entry[4..8])),
+                3 => Value::String(values.string(Address::from_bytes(&entry[4..8]))?),
+                // Unused padding entries of a segment
+                _ => Value::Int(0),
+            };
+            attributes.push(AttributeData { name, value });
+        }
+
+        let mut elements = Vec::with_capacity(raw_elements.entries.len());
+        for entry in raw_elements.entries.iter() {
+            let name = resolve_name_index(LittleEndian::read_u16(&entry[0..2]), names.len())?;
+            let attribute_count = LittleEndian::read_u16(&entry[4..6]) as usize;
+            let child_count = LittleEndian::read_u16(&entry[6..8]) as usize;
+            let attribute_start = if attribute_count > 0 {
+                raw_attributes.index(Address::from_bytes(&entry[8..12]), attribute_count)?
+            } else {
+                0
+            };
+            let child_start = if child_count > 0 {
+                raw_elements.index(Address::from_bytes(&entry[12..16]), child_count)?
+            } else {
+                0
+            };
+            elements.push(ElementData {
+                name,
+                attributes: (attribute_start, attribute_start + attribute_count),
+                children: (child_start, child_start + child_count),
+            });
+        }
+        ensure!(
+            !elements.is_empty(),
+            "Datacenter doesn't contain a root element"
+        );
+
+        Ok(DataCenter {
+            header,
+            names,
+            elements,
+            attributes,
+        })
+    }
+
+    /// Returns the root element of the datacenter.
+    pub fn root(&self) -> Element {
+        Element { dc: self, index: 0 }
+    }
+
+    /// Returns all elements that match the given path (for example "ContinentData/Continent").
+    /// The path is relative to the root element.
+    pub fn query(&self, path: &str) -> Vec<Element> {
+        self.root().query(path)
+    }
+}
+
+impl<'a> Element<'a> {
+    /// Returns the name of the element. Padding elements don't have a name.
+    pub fn name(&self) -> Option<&'a str> {
+        self.data()
+            .name
+            .and_then(|name| self.dc.names.get(name))
+            .map(String::as_str)
+    }
+
+    /// Returns all attributes of the element.
+    pub fn attributes(&self) -> impl Iterator<Item = (&'a str, &'a Value)> {
+        let dc = self.dc;
+        let (start, end) = self.data().attributes;
+        dc.attributes[start..end]
+            .iter()
+            .filter_map(move |attribute| {
+                let name = dc.names.get(attribute.name?)?;
+                Some((name.as_str(), &attribute.value))
+            })
+    }
+
+    /// Returns the value of the attribute with the given name.
+    pub fn attribute(&self, name: &str) -> Option<&'a Value> {
+        self.attributes()
+            .find(|(attribute_name, _)| *attribute_name == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the value of an integer attribute.
+    pub fn get_i32(&self, name: &str) -> Option<i32> {
+        match self.attribute(name)? {
+            Value::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a float attribute. Integer values are converted.
+    pub fn get_f32(&self, name: &str) -> Option<f32> {
+        match self.attribute(name)? {
+            Value::Float(v) => Some(*v),
+            Value::Int(v) => Some(*v as f32),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a boolean attribute.
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.attribute(name)? {
+            Value::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a string attribute.
+    pub fn get_str(&self, name: &str) -> Option<&'a str> {
+        match self.attribute(name)? {
+            Value::String(v) => Some(v.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Returns all children of the element.
+    pub fn children(&self) -> impl Iterator<Item = Element<'a>> {
+        let dc = self.dc;
+        let (start, end) = self.data().children;
+        (start..end).map(move |index| Element { dc, index })
+    }
+
+    /// Returns all children with the given name.
+    pub fn children_by_name<'b>(&self, name: &'b str) -> impl Iterator<Item = Element<'a>> + 'b
+    where
+        'a: 'b,
+    {
+        self.children()
+            .filter(move |child| child.name() == Some(name))
+    }
+
+    /// Returns the first child with the given name.
+    pub fn child(&self, name: &str) -> Option<Element<'a>> {
+        self.children().find(|child| child.name() == Some(name))
+    }
+
+    /// Returns all descendants that match the given path relative to this element.
+    pub fn query(&self, path: &str) -> Vec<Element<'a>> {
+        path.split('/')
+            .filter(|part| !part.is_empty())
+            .fold(vec![*self], |elements, part| {
+                elements
+                    .iter()
+                    .flat_map(|element| element.children_by_name(part))
+                    .collect()
+            })
+    }
+
+    fn data(&self) -> &'a ElementData {
+        &self.dc.elements[self.index]
+    }
+}
+
+impl<'a> fmt::Debug for Element<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Element")
+            .field("name", &self.name())
+            .field("attributes", &self.attributes().collect::<Vec<_>>())
+            .field("children", &self.children().count())
+            .finish()
+    }
+}
+
+fn resolve_name_index(index: u16, name_count: usize) -> Result<Option<usize>> {
+    // Padding entries have the name index 0. They are never referenced by their parent.
+    if index == 0 {
+        return Ok(None);
+    }
+    let index = index as usize - 1;
+    ensure!(index < name_count, "Name index {} is out of bounds", index);
+    Ok(Some(index))
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Address {
+    segment: usize,
+    entry: usize,
+}
+
+impl Address {
+    fn from_bytes(data: &[u8]) -> Self {
+        Address {
+            segment: LittleEndian::read_u16(&data[0..2]) as usize,
+            entry: LittleEndian::read_u16(&data[2..4]) as usize,
+        }
+    }
+}
+
+/// A segmented region flattened into one list of raw entries.
+struct SegmentedRegion<'a> {
+    entries: Vec<&'a [u8]>,
+    offsets: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl<'a> SegmentedRegion<'a> {
+    /// Converts an address into the index of the flattened entry list and checks that `count`
+    /// entries are available inside the segment.
+    fn index(&self, address: Address, count: usize) -> Result<usize> {
+        if address.segment >= self.offsets.len()
+            || address.entry + count > self.sizes[address.segment]
+        {
+            bail!(
+                "Address {}:{} with {} entries is out of bounds",
+                address.segment,
+                address.entry,
+                count
+            );
+        }
+        Ok(self.offsets[address.segment] + address.entry)
+    }
+}
+
+struct StringTable<'a> {
+    characters: SegmentedRegion<'a>,
+    addresses: Vec<Address>,
+}
+
+impl<'a> StringTable<'a> {
+    fn read(reader: &mut Reader<'a>, buckets: usize) -> Result<StringTable<'a>> {
+        let characters = reader.segmented_region(2)?;
+        // The buckets are only needed for hash lookups.
+        for _ in 0..buckets {
+            reader.simple_region(STRING_ENTRY_SIZE)?;
+        }
+        let addresses = reader
+            .simple_region(ADDRESS_SIZE)?
+            .into_iter()
+            .map(Address::from_bytes)
+            .collect();
+        Ok(StringTable {
+            characters,
+            addresses,
+        })
+    }
+
+    /// Reads the null terminated UTF-16 string at the given address.
+    fn string(&self, address: Address) -> Result<String> {
+        let start = self.characters.index(address, 1)?;
+        let end = self.characters.offsets[address.segment] + self.characters.sizes[address.segment];
+        let chars = self.characters.entries[start..end]
+            .iter()
+            .map(|c| LittleEndian::read_u16(c))
+            .take_while(|c| *c != 0)
+            .collect::<Vec<u16>>();
+        Ok(String::from_utf16(&chars)?)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(
+            self.pos + len <= self.data.len(),
+            "Unexpected end of datacenter data at position {}",
+            self.pos
+        );
+        let slice = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.take(len)?;
+        Ok(())
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(LittleEndian::read_u32(self.take(4)?))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(LittleEndian::read_f64(self.take(8)?))
+    }
+
+    fn simple_region(&mut self, entry_size: usize) -> Result<Vec<&'a [u8]>> {
+        let count = self.u32()? as usize;
+        Ok(self.take(count * entry_size)?.chunks(entry_size).collect())
+    }
+
+    fn segmented_region(&mut self, entry_size: usize) -> Result<SegmentedRegion<'a>> {
+        let segment_count = self.u32()? as usize;
+        let mut region = SegmentedRegion {
+            entries: Vec::new(),
+            offsets: Vec::with_capacity(segment_count),
+            sizes: Vec::with_capacity(segment_count),
+        };
+        for _ in 0..segment_count {
+            let full_count = self.u32()? as usize;
+            let used_count = self.u32()? as usize;
+            ensure!(
+                used_count <= full_count,
+                "Segment uses more entries than it contains"
+            );
+            region.offsets.push(region.entries.len());
+            region.sizes.push(used_count);
+            region
+                .entries
+                .extend(self.take(full_count * entry_size)?.chunks(entry_size));
+        }
+        Ok(region)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use byteorder::WriteBytesExt;
+
+    pub enum TestValue {
+        Int(i32),
+        Float(f32),
+        Bool(bool),
+        String(&'static str),
+    }
+
+    /// Simple representation of an element to create test datacenter files.
+    pub struct TestElement {
+        pub name: &'static str,
+        pub attributes: Vec<(&'static str, TestValue)>,
+        pub children: Vec<TestElement>,
+    }
+
+    impl TestElement {
+        pub fn new(
+            name: &'static str,
+            attributes: Vec<(&'static str, TestValue)>,
+            children: Vec<TestElement>,
+        ) -> Self {
+            TestElement {
+                name,
+                attributes,
+                children,
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct Builder {
+        names: Vec<String>,
+        values: Vec<u16>,
+        attributes: Vec<u8>,
+        attribute_count: usize,
+        elements: Vec<[u8; ELEMENT_SIZE]>,
+    }
+
+    impl Builder {
+        fn name_index(&mut self, name: &str) -> u16 {
+            if let Some(pos) = self.names.iter().position(|n| n == name) {
+                return pos as u16 + 1;
+            }
+            self.names.push(name.to_string());
+            self.names.len() as u16
+        }
+
+        fn value_address(&mut self, value: &str) -> u16 {
+            let address = self.values.len() as u16;
+            self.values.extend(value.encode_utf16());
+            self.values.push(0);
+            address
+        }
+
+        // Writes the children of an element in a breadth first order, so that all children of
+        // an element are stored continuously.
+        fn write_element(&mut self, index: usize, element: &TestElement) -> Result<()> {
+            let name = self.name_index(element.name);
+            let attribute_start = self.attribute_count;
+            for (key, value) in element.attributes.iter() {
+                let key = self.name_index(key);
+                self.attributes.write_u16::<LittleEndian>(key)?;
+                match value {
+                    TestValue::Int(v) => {
+                        self.attributes.write_u16::<LittleEndian>(1)?;
+                        self.attributes.write_i32::<LittleEndian>(*v)?;
+                    }
+                    TestValue::Bool(v) => {
+                        self.attributes.write_u16::<LittleEndian>(5)?;
+                        self.attributes.write_u32::<LittleEndian>(*v as u32)?;
+                    }
+                    TestValue::Float(v) => {
+                        self.attributes.write_u16::<LittleEndian>(2)?;
+                        self.attributes.write_f32::<LittleEndian>(*v)?;
+                    }
+                    TestValue::String(v) => {
+                        let address = self.value_address(v);
+                        self.attributes.write_u16::<LittleEndian>(3)?;
+                        self.attributes.write_u16::<LittleEndian>(0)?;
+                        self.attributes.write_u16::<LittleEndian>(address)?;
+                    }
+                }
+                self.attribute_count += 1;
+            }
+
+            let child_start = self.elements.len();
+            for _ in element.children.iter() {
+                self.elements.push([0u8; ELEMENT_SIZE]);
+            }
+
+            let mut data = Vec::with_capacity(ELEMENT_SIZE);
+            data.write_u16::<LittleEndian>(name)?;
+            data.write_u16::<LittleEndian>(0)?;
+            data.write_u16::<LittleEndian>(element.attributes.len() as u16)?;
+            data.write_u16::<LittleEndian>(element.children.len() as u16)?;
+            data.write_u16::<LittleEndian>(0)?;
+            data.write_u16::<LittleEndian>(attribute_start as u16)?;
+            data.write_u16::<LittleEndian>(0)?;
+            data.write_u16::<LittleEndian>(child_start as u16)?;
+            self.elements[index].copy_from_slice(&data);
+
+            for (i, child) in element.children.iter().enumerate() {
+                self.write_element(child_start + i, child)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn write_string_table(
+        data: &mut Vec<u8>,
+        characters: &[u16],
+        addresses: &[u16],
+        buckets: usize,
+    ) -> Result<()> {
+        data.write_u32::<LittleEndian>(1)?;
+        data.write_u32::<LittleEndian>(characters.len() as u32)?;
+        data.write_u32::<LittleEndian>(characters.len() as u32)?;
+        for c in characters {
+            data.write_u16::<LittleEndian>(*c)?;
+        }
+        for _ in 0..buckets {
+            data.write_u32::<LittleEndian>(0)?;
+        }
+        data.write_u32::<LittleEndian>(addresses.len() as u32)?;
+        for address in addresses {
+            data.write_u16::<LittleEndian>(0)?;
+            data.write_u16::<LittleEndian>(*address)?;
+        }
+        Ok(())
+    }
+
+    /// Creates the decrypted and decompressed data of a datacenter with the given root element.
+    pub fn create_test_datacenter(root: &TestElement) -> Result<Vec<u8>> {
+        let mut builder = Builder::default();
+        builder.elements.push([0u8; ELEMENT_SIZE]);
+        builder.write_element(0, root)?;
+
+        let mut data = Vec::new();
+        data.write_u32::<LittleEndian>(6)?;
+        data.write_f64::<LittleEndian>(1590000000.0)?;
+        for _ in 0..5 {
+            data.write_u32::<LittleEndian>(0)?;
+        }
+
+        // Attributes with one padding entry at the end of the segment
+        data.write_u32::<LittleEndian>(1)?;
+        data.write_u32::<LittleEndian>(builder.attribute_count as u32 + 1)?;
+        data.write_u32::<LittleEndian>(builder.attribute_count as u32)?;
+        data.extend_from_slice(&builder.attributes);
+        data.extend_from_slice(&[0u8; ATTRIBUTE_SIZE]);
+
+        // Elements
+        data.write_u32::<LittleEndian>(1)?;
+        data.write_u32::<LittleEndian>(builder.elements.len() as u32)?;
+        data.write_u32::<LittleEndian>(builder.elements.len() as u32)?;
+        for element in builder.elements.iter() {
+            data.extend_from_slice(element);
+        }
+
+        // Values (addresses of the values are not needed, since attributes reference them)
+        write_string_table(&mut data, &builder.values, &[], VALUE_TABLE_BUCKETS)?;
+
+        // Names
+        let mut characters = Vec::new();
+        let mut addresses = Vec::new();
+        for name in builder.names.iter() {
+            addresses.push(characters.len() as u16);
+            characters.extend(name.encode_utf16());
+            characters.push(0);
+        }
+        write_string_table(&mut data, &characters, &addresses, NAME_TABLE_BUCKETS)?;
+
+        // Footer
+        data.write_u32::<LittleEndian>(0)?;
+
+        Ok(data)
+    }
+
+    fn get_test_tree() -> TestElement {
+        TestElement::new(
+            "__root__",
+            vec![],
+            vec![
+                TestElement::new(
+                    "ContinentData",
+                    vec![],
+                    vec![
+                        TestElement::new(
+                            "Continent",
+                            vec![
+                                ("id", TestValue::Int(1)),
+                                ("channelType", TestValue::String("field")),
+                            ],
+                            vec![TestElement::new(
+                                "Area",
+                                vec![("id", TestValue::Int(13)), ("scale", TestValue::Float(0.5))],
+                                vec![],
+                            )],
+                        ),
+                        TestElement::new(
+                            "Continent",
+                            vec![
+                                ("id", TestValue::Int(2)),
+                                ("isInstance", TestValue::Bool(true)),
+                            ],
+                            vec![],
+                        ),
+                    ],
+                ),
+                TestElement::new(
+                    "StrSheet_Item",
+                    vec![("huntingZoneId", TestValue::Int(0))],
+                    vec![],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_parse_header() -> Result<()> {
+        let data = create_test_datacenter(&get_test_tree())?;
+        let dc = DataCenter::parse(&data)?;
+
+        assert_eq!(dc.header.version, 6);
+        assert_eq!(dc.header.timestamp, 1590000000.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_element_tree() -> Result<()> {
+        let data = create_test_datacenter(&get_test_tree())?;
+        let dc = DataCenter::parse(&data)?;
+
+        let root = dc.root();
+        assert_eq!(root.name(), Some("__root__"));
+        assert_eq!(
+            root.children()
+                .map(|c| c.name())
+                .collect::<Vec<Option<&str>>>(),
+            vec![Some("ContinentData"), Some("StrSheet_Item")]
+        );
+
+        let continent_data = root.child("ContinentData").unwrap();
+        assert_eq!(continent_data.children().count(), 2);
+        assert_eq!(continent_data.attributes().count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_attributes() -> Result<()> {
+        let data = create_test_datacenter(&get_test_tree())?;
+        let dc = DataCenter::parse(&data)?;
+
+        let continents = dc.query("ContinentData/Continent");
+        assert_eq!(continents[0].get_i32("id"), Some(1));
+        assert_eq!(continents[0].get_str("channelType"), Some("field"));
+        assert_eq!(continents[0].get_bool("isInstance"), None);
+        assert_eq!(continents[1].get_i32("id"), Some(2));
+        assert_eq!(continents[1].get_bool("isInstance"), Some(true));
+        assert_eq!(
+            continents[1].attribute("isInstance"),
+            Some(&Value::Bool(true))
+        );
+
+        let area = continents[0].child("Area").unwrap();
+        assert_eq!(area.get_f32("scale"), Some(0.5));
+        assert_eq!(area.get_f32("id"), Some(13.0));
+        assert_eq!(area.get_str("id"), None);
+        assert_eq!(area.get_i32("unknown"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query() -> Result<()> {
+        let data = create_test_datacenter(&get_test_tree())?;
+        let dc = DataCenter::parse(&data)?;
+
+        assert_eq!(dc.query("ContinentData").len(), 1);
+        assert_eq!(dc.query("ContinentData/Continent").len(), 2);
+        assert_eq!(dc.query("/ContinentData/Continent/Area").len(), 1);
+        assert_eq!(dc.query("ContinentData/Area").len(), 0);
+        assert_eq!(dc.query("Unknown/Continent").len(), 0);
+
+        let continent = dc.query("ContinentData/Continent")[0];
+        assert_eq!(continent.query("Area")[0].get_i32("id"), Some(13));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_truncated_data() -> Result<()> {
+        let data = create_test_datacenter(&get_test_tree())?;
+        assert!(DataCenter::parse(&data[..data.len() / 2]).is_err());
+        assert!(DataCenter::parse(&[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_empty_name_table() -> Result<()> {
+        let mut data = Vec::new();
+        data.write_u32::<LittleEndian>(6)?;
+        data.write_f64::<LittleEndian>(1590000000.0)?;
+        for _ in 0..5 {
+            data.write_u32::<LittleEndian>(0)?;
+        }
+        // No attributes
+        data.write_u32::<LittleEndian>(0)?;
+        // A single padding element as root
+        data.write_u32::<LittleEndian>(1)?;
+        data.write_u32::<LittleEndian>(1)?;
+        data.write_u32::<LittleEndian>(1)?;
+        data.extend_from_slice(&[0u8; ELEMENT_SIZE]);
+        write_string_table(&mut data, &[], &[], VALUE_TABLE_BUCKETS)?;
+        write_string_table(&mut data, &[], &[], NAME_TABLE_BUCKETS)?;
+        data.write_u32::<LittleEndian>(0)?;
+
+        let dc = DataCenter::parse(&data)?;
+        assert_eq!(dc.root().name(), None);
+        assert_eq!(dc.root().attributes().count(), 0);
+        assert!(dc.query("ContinentData").is_empty());
+        Ok(())
+    }
+}
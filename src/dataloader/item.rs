@@ -0,0 +1,276 @@
+/// Module that reads the item templates out of the datacenter.
+///
+/// Expected structure of the item data:
+///
+/// ```text
+/// ItemData
+///   Item id maxStack destroyable combatItemType combatItemSubType requiredLevel requiredClass
+///        requiredRace attack defence impact balance maxHp maxMp
+/// ```
+///
+/// Only `id` is required. `maxStack` defaults to 1 (not stackable) and items are destroyable
+/// if not stated otherwise. The equipment slots of an item are derived from its
+/// `combatItemType` and `combatItemSubType`. `requiredClass` and `requiredRace` are lists
+/// separated by `;`. Items without them can be used by every class and race.
+use crate::dataloader::datacenter::{DataCenter, Element};
+use crate::ecs::resource::{ItemRegistry, ItemTemplate};
+use crate::model::{Class, EquipmentSlot, Race, Stats};
+use crate::*;
+use anyhow::{bail, Context};
+
+/// Creates the item registry out of the item data of the datacenter.
+pub fn read_item_registry(dc: &DataCenter) -> Result<ItemRegistry> {
+    let items = dc
+        .query("ItemData/Item")
+        .iter()
+        .map(read_item)
+        .collect::<Result<Vec<ItemTemplate>>>()?;
+    Ok(ItemRegistry::new(items))
+}
+
+fn read_item(item: &Element) -> Result<ItemTemplate> {
+    let id = item.get_i32("id").context("Item doesn't have an ID")?;
+
+    let required_classes = split_list(item.get_str("requiredClass"))
+        .map(parse_class)
+        .collect::<Result<Vec<Class>>>()
+        .context(format!("Can't read the required classes of item {}", id))?;
+    let required_races = split_list(item.get_str("requiredRace"))
+        .map(parse_race)
+        .collect::<Result<Vec<Race>>>()
+        .context(format!("Can't read the required races of item {}", id))?;
+
+    Ok(ItemTemplate {
+        id,
+        max_stack: item.get_i32("maxStack").unwrap_or(1).max(1),
+        destroyable: item.get_bool("destroyable").unwrap_or(true),
+        equipment_slots: equipment_slots(
+            item.get_str("combatItemType").unwrap_or_default(),
+            item.get_str("combatItemSubType").unwrap_or_default(),
+        ),
+        required_level: item.get_i32("requiredLevel").unwrap_or(1),
+        required_classes,
+        required_races,
+        stats: Stats {
+            attack: item.get_i32("attack").unwrap_or_default(),
+            defence: item.get_i32("defence").unwrap_or_default(),
+            impact: item.get_i32("impact").unwrap_or_default(),
+            balance: item.get_i32("balance").unwrap_or_default(),
+            max_hp: item.get_i32("maxHp").unwrap_or_default(),
+            max_mp: item.get_i32("maxMp").unwrap_or_default(),
+        },
+    })
+}
+
+/// Returns the slots an item can be equipped in. Items that can't be equipped have no slots.
+fn equipment_slots(combat_item_type: &str, combat_item_sub_type: &str) -> Vec<EquipmentSlot> {
+    match (combat_item_type, combat_item_sub_type) {
+        ("EQUIP_WEAPON", _) => vec![EquipmentSlot::Weapon],
+        ("EQUIP_ARMOR_BODY", _) => vec![EquipmentSlot::Body],
+        ("EQUIP_ARMOR_ARM", _) => vec![EquipmentSlot::Hand],
+        ("EQUIP_ARMOR_LEG", _) => vec![EquipmentSlot::Feet],
+        ("EQUIP_INNERWEAR", _) => vec![EquipmentSlot::Underwear],
+        ("EQUIP_ACCESSORY", "earring") => vec![EquipmentSlot::Earring1, EquipmentSlot::Earring2],
+        ("EQUIP_ACCESSORY", "ring") => vec![EquipmentSlot::Ring1, EquipmentSlot::Ring2],
+        ("EQUIP_ACCESSORY", "necklace") => vec![EquipmentSlot::Necklace],
+        ("EQUIP_ACCESSORY", "accessoryHair") => vec![EquipmentSlot::Head],
+        ("EQUIP_ACCESSORY", "accessoryFace") => vec![EquipmentSlot::Face],
+        ("EQUIP_STYLE_ACCESSORY", "accessoryHair") => vec![EquipmentSlot::StyleHead],
+        ("EQUIP_STYLE_ACCESSORY", "accessoryFace") => vec![EquipmentSlot::StyleFace],
+        ("EQUIP_STYLE_BACK", _) => vec![EquipmentSlot::StyleBack],
+        ("EQUIP_STYLE_WEAPON", _) => vec![EquipmentSlot::StyleWeapon],
+        ("EQUIP_STYLE_BODY", _) => vec![EquipmentSlot::StyleBody],
+        ("EQUIP_STYLE_EFFECT", _) => vec![EquipmentSlot::StyleFootprint],
+        _ => vec![],
+    }
+}
+
+pub fn split_list(list: Option<&str>) -> impl Iterator<Item = &str> {
+    list.unwrap_or_default()
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+}
+
+pub fn parse_class(name: &str) -> Result<Class> {
+    Ok(match name {
+        "warrior" => Class::Warrior,
+        "lancer" => Class::Lancer,
+        "slayer" => Class::Slayer,
+        "berserker" => Class::Berserker,
+        "sorcerer" => Class::Sorcerer,
+        "archer" => Class::Archer,
+        "priest" => Class::Priest,
+        "elementalist" => Class::Elementalist,
+        "soulless" => Class::Soulless,
+        "engineer" => Class::Engineer,
+        "fighter" => Class::Fighter,
+        "assassin" => Class::Ninja,
+        "glaiver" => Class::Valkyrie,
+        _ => bail!("Unknown class {}", name),
+    })
+}
+
+fn parse_race(name: &str) -> Result<Race> {
+    Ok(match name {
+        "human" => Race::Human,
+        "castanic" => Race::Castanic,
+        "aman" => Race::Aman,
+        "highElf" => Race::HighElf,
+        "popori" => Race::ElinPopori,
+        "baraka" => Race::Baraka,
+        _ => bail!("Unknown race {}", name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataloader::datacenter::tests::{create_test_datacenter, TestElement, TestValue};
+
+    #[test]
+    fn test_read_item_registry() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "ItemData",
+                vec![],
+                vec![
+                    TestElement::new(
+                        "Item",
+                        vec![
+                            ("id", TestValue::Int(8005)),
+                            ("maxStack", TestValue::Int(1000)),
+                        ],
+                        vec![],
+                    ),
+                    TestElement::new(
+                        "Item",
+                        vec![
+                            ("id", TestValue::Int(10001)),
+                            ("destroyable", TestValue::Bool(false)),
+                        ],
+                        vec![],
+                    ),
+                ],
+            )],
+        );
+        let registry = read_item_registry(&DataCenter::parse(&create_test_datacenter(&root)?)?)?;
+        assert_eq!(registry.len(), 2);
+
+        let potion = registry.get(8005).unwrap();
+        assert_eq!(potion.max_stack, 1000);
+        assert!(potion.destroyable);
+
+        let weapon = registry.get(10001).unwrap();
+        assert_eq!(weapon.max_stack, 1);
+        assert!(!weapon.destroyable);
+
+        assert!(registry.get(1).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_equipment() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "ItemData",
+                vec![],
+                vec![
+                    TestElement::new(
+                        "Item",
+                        vec![
+                            ("id", TestValue::Int(10001)),
+                            ("combatItemType", TestValue::String("EQUIP_WEAPON")),
+                            ("requiredLevel", TestValue::Int(20)),
+                            ("requiredClass", TestValue::String("warrior;assassin")),
+                            ("attack", TestValue::Int(120)),
+                            ("impact", TestValue::Int(40)),
+                        ],
+                        vec![],
+                    ),
+                    TestElement::new(
+                        "Item",
+                        vec![
+                            ("id", TestValue::Int(88888)),
+                            ("combatItemType", TestValue::String("EQUIP_ACCESSORY")),
+                            ("combatItemSubType", TestValue::String("ring")),
+                            ("requiredRace", TestValue::String("popori")),
+                            ("maxHp", TestValue::Int(500)),
+                        ],
+                        vec![],
+                    ),
+                ],
+            )],
+        );
+        let registry = read_item_registry(&DataCenter::parse(&create_test_datacenter(&root)?)?)?;
+
+        let weapon = registry.get(10001).unwrap();
+        assert_eq!(weapon.equipment_slots, vec![EquipmentSlot::Weapon]);
+        assert_eq!(weapon.required_level, 20);
+        assert_eq!(weapon.required_classes, vec![Class::Warrior, Class::Ninja]);
+        assert!(weapon.required_races.is_empty());
+        assert_eq!(weapon.stats.attack, 120);
+        assert_eq!(weapon.stats.impact, 40);
+        assert_eq!(weapon.stats.defence, 0);
+
+        let ring = registry.get(88888).unwrap();
+        assert_eq!(
+            ring.equipment_slots,
+            vec![EquipmentSlot::Ring1, EquipmentSlot::Ring2]
+        );
+        assert_eq!(ring.required_level, 1);
+        assert!(ring.required_classes.is_empty());
+        assert_eq!(ring.required_races, vec![Race::ElinPopori]);
+        assert_eq!(ring.stats.max_hp, 500);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_item_with_unknown_class() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "ItemData",
+                vec![],
+                vec![TestElement::new(
+                    "Item",
+                    vec![
+                        ("id", TestValue::Int(10001)),
+                        ("requiredClass", TestValue::String("bard")),
+                    ],
+                    vec![],
+                )],
+            )],
+        );
+        let dc = DataCenter::parse(&create_test_datacenter(&root)?)?;
+        assert!(read_item_registry(&dc).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_item_registry_without_id() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "ItemData",
+                vec![],
+                vec![TestElement::new(
+                    "Item",
+                    vec![("maxStack", TestValue::Int(10))],
+                    vec![],
+                )],
+            )],
+        );
+        let dc = DataCenter::parse(&create_test_datacenter(&root)?)?;
+        assert!(read_item_registry(&dc).is_err());
+        Ok(())
+    }
+}
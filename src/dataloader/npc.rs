@@ -0,0 +1,422 @@
+/// Module that reads the NPC templates and the spawn tables of the NPCs out of the datacenter.
+///
+/// Expected structure of the NPC and territory data:
+///
+/// ```text
+/// NpcData huntingZoneId
+///   Template id level maxHp walkSpeed runSpeed villager attack defence aggroRange leashRange exp
+///            boss
+///     Skill id damage range cooldown weight
+/// TerritoryData
+///   TerritoryGroup id continentId respawnTime
+///     Npc huntingZoneId templateId x y z heading respawnTime
+///       PatrolPoint x y z
+/// ```
+///
+/// The IDs of the NPC templates are only unique inside their hunting zone. NPCs without an
+/// `aggroRange` only fight back once they are attacked, NPCs without a `leashRange` follow their
+/// targets anywhere. `exp` is the experience the killer of a NPC receives. Killing a `boss` clears
+/// the dungeon it's spawned in. The skills of a NPC need an `id`, the `weight` defines how often a
+/// skill is chosen (defaults to 1). `respawnTime` and `cooldown` are given in milliseconds, the
+/// respawn time of a NPC overwrites the one of it's territory group. `heading` is given in
+/// degrees. Spawns of unknown NPC templates are rejected.
+use crate::dataloader::datacenter::{DataCenter, Element};
+use crate::dataloader::skill::millis;
+use crate::ecs::resource::{
+    NpcRegistry, NpcSkill, NpcSpawnTemplate, NpcTemplate, TerritoryGroup, TerritoryRegistry,
+};
+use crate::*;
+use anyhow::{ensure, Context};
+use nalgebra::{Point3, Rotation3, Vector3};
+
+/// Creates the NPC registry out of the NPC data of the datacenter.
+pub fn read_npc_registry(dc: &DataCenter) -> Result<NpcRegistry> {
+    let mut npcs = Vec::new();
+    for npc_data in dc.query("NpcData") {
+        let hunting_zone_id = npc_data
+            .get_i32("huntingZoneId")
+            .context("NPC data doesn't have a hunting zone ID")?;
+        for template in npc_data.children_by_name("Template") {
+            npcs.push(read_npc(hunting_zone_id, &template)?);
+        }
+    }
+    Ok(NpcRegistry::new(npcs))
+}
+
+/// Creates the territory registry out of the territory data of the datacenter.
+pub fn read_territory_registry(dc: &DataCenter, npcs: &NpcRegistry) -> Result<TerritoryRegistry> {
+    let territories = dc
+        .query("TerritoryData/TerritoryGroup")
+        .iter()
+        .map(|group| read_territory_group(group, npcs))
+        .collect::<Result<Vec<TerritoryGroup>>>()?;
+    Ok(TerritoryRegistry::new(territories))
+}
+
+fn read_npc(hunting_zone_id: i32, template: &Element) -> Result<NpcTemplate> {
+    let id = template.get_i32("id").context(format!(
+        "NPC template of hunting zone {} doesn't have an ID",
+        hunting_zone_id
+    ))?;
+
+    let skills = template
+        .children_by_name("Skill")
+        .map(|skill| {
+            Ok(NpcSkill {
+                id: skill.get_i32("id").context(format!(
+                    "Skill of NPC {} of hunting zone {} doesn't have an ID",
+                    id, hunting_zone_id
+                ))?,
+                damage: skill.get_i32("damage").unwrap_or_default(),
+                range: skill.get_f32("range").unwrap_or_default(),
+                cooldown: millis(skill.get_i32("cooldown")),
+                weight: skill.get_i32("weight").unwrap_or(1).max(0) as u32,
+            })
+        })
+        .collect::<Result<Vec<NpcSkill>>>()?;
+
+    Ok(NpcTemplate {
+        hunting_zone_id,
+        id,
+        level: template.get_i32("level").unwrap_or(1),
+        max_hp: i64::from(template.get_i32("maxHp").unwrap_or(1).max(1)),
+        walk_speed: template.get_i32("walkSpeed").unwrap_or(50) as i16,
+        run_speed: template.get_i32("runSpeed").unwrap_or(150) as i16,
+        is_villager: template.get_bool("villager").unwrap_or_default(),
+        attack: template.get_i32("attack").unwrap_or_default(),
+        defence: template.get_i32("defence").unwrap_or_default(),
+        aggro_range: template.get_f32("aggroRange").unwrap_or_default(),
+        leash_range: template.get_f32("leashRange").unwrap_or_default(),
+        exp: i64::from(template.get_i32("exp").unwrap_or_default().max(0)),
+        is_boss: template.get_bool("boss").unwrap_or_default(),
+        skills,
+    })
+}
+
+fn read_territory_group(group: &Element, npcs: &NpcRegistry) -> Result<TerritoryGroup> {
+    let id = group
+        .get_i32("id")
+        .context("Territory group doesn't have an ID")?;
+    let zone_id = group
+        .get_i32("continentId")
+        .context(format!("Territory group {} doesn't have a continent", id))?;
+    let respawn_time = group.get_i32("respawnTime");
+
+    let spawns = group
+        .children_by_name("Npc")
+        .map(|npc| {
+            let hunting_zone_id = npc.get_i32("huntingZoneId").context(format!(
+                "NPC of territory group {} doesn't have a hunting zone ID",
+                id
+            ))?;
+            let template_id = npc.get_i32("templateId").context(format!(
+                "NPC of territory group {} doesn't have a template ID",
+                id
+            ))?;
+            ensure!(
+                npcs.get(hunting_zone_id, template_id).is_some(),
+                "Territory group {} spawns unknown NPC {} of hunting zone {}",
+                id,
+                template_id,
+                hunting_zone_id
+            );
+
+            let patrol_points = npc
+                .children_by_name("PatrolPoint")
+                .map(|patrol_point| read_point(&patrol_point, id))
+                .collect::<Result<Vec<Point3<f32>>>>()?;
+
+            Ok(NpcSpawnTemplate {
+                hunting_zone_id,
+                template_id,
+                point: read_point(&npc, id)?,
+                rotation: Rotation3::from_axis_angle(
+                    &Vector3::z_axis(),
+                    npc.get_f32("heading").unwrap_or(0.0).to_radians(),
+                ),
+                respawn_time: millis(npc.get_i32("respawnTime").or(respawn_time)),
+                patrol_points,
+            })
+        })
+        .collect::<Result<Vec<NpcSpawnTemplate>>>()?;
+
+    Ok(TerritoryGroup {
+        id,
+        zone_id,
+        spawns,
+    })
+}
+
+fn read_point(element: &Element, group_id: i32) -> Result<Point3<f32>> {
+    Ok(Point3::new(
+        element
+            .get_f32("x")
+            .context(format!("Territory group {} has no x value", group_id))?,
+        element
+            .get_f32("y")
+            .context(format!("Territory group {} has no y value", group_id))?,
+        element
+            .get_f32("z")
+            .context(format!("Territory group {} has no z value", group_id))?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataloader::datacenter::tests::{create_test_datacenter, TestElement, TestValue};
+    use std::time::Duration;
+
+    fn get_npc_data() -> TestElement {
+        TestElement::new(
+            "NpcData",
+            vec![("huntingZoneId", TestValue::Int(13))],
+            vec![
+                TestElement::new(
+                    "Template",
+                    vec![
+                        ("id", TestValue::Int(1001)),
+                        ("level", TestValue::Int(20)),
+                        ("maxHp", TestValue::Int(5000)),
+                        ("walkSpeed", TestValue::Int(40)),
+                        ("runSpeed", TestValue::Int(120)),
+                        ("attack", TestValue::Int(30)),
+                        ("defence", TestValue::Int(10)),
+                        ("aggroRange", TestValue::Float(300.0)),
+                        ("leashRange", TestValue::Float(2000.0)),
+                        ("exp", TestValue::Int(850)),
+                        ("boss", TestValue::Bool(true)),
+                    ],
+                    vec![
+                        TestElement::new(
+                            "Skill",
+                            vec![
+                                ("id", TestValue::Int(1)),
+                                ("damage", TestValue::Int(100)),
+                                ("range", TestValue::Float(50.0)),
+                                ("cooldown", TestValue::Int(3000)),
+                                ("weight", TestValue::Int(3)),
+                            ],
+                            vec![],
+                        ),
+                        TestElement::new(
+                            "Skill",
+                            vec![("id", TestValue::Int(2)), ("damage", TestValue::Int(10))],
+                            vec![],
+                        ),
+                    ],
+                ),
+                TestElement::new(
+                    "Template",
+                    vec![
+                        ("id", TestValue::Int(2001)),
+                        ("villager", TestValue::Bool(true)),
+                    ],
+                    vec![],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_read_npc_registry() -> Result<()> {
+        let root = TestElement::new("__root__", vec![], vec![get_npc_data()]);
+        let registry = read_npc_registry(&DataCenter::parse(&create_test_datacenter(&root)?)?)?;
+        assert_eq!(registry.len(), 2);
+
+        let monster = registry.get(13, 1001).unwrap();
+        assert_eq!(monster.level, 20);
+        assert_eq!(monster.max_hp, 5000);
+        assert_eq!(monster.walk_speed, 40);
+        assert_eq!(monster.run_speed, 120);
+        assert!(!monster.is_villager);
+        assert_eq!(monster.attack, 30);
+        assert_eq!(monster.defence, 10);
+        assert_eq!(monster.aggro_range, 300.0);
+        assert_eq!(monster.leash_range, 2000.0);
+        assert_eq!(monster.exp, 850);
+        assert!(monster.is_boss);
+        assert_eq!(
+            monster.skills,
+            vec![
+                NpcSkill {
+                    id: 1,
+                    damage: 100,
+                    range: 50.0,
+                    cooldown: Duration::from_secs(3),
+                    weight: 3,
+                },
+                NpcSkill {
+                    id: 2,
+                    damage: 10,
+                    range: 0.0,
+                    cooldown: Duration::from_secs(0),
+                    weight: 1,
+                }
+            ]
+        );
+
+        let villager = registry.get(13, 2001).unwrap();
+        assert_eq!(villager.level, 1);
+        assert_eq!(villager.walk_speed, 50);
+        assert!(villager.is_villager);
+        assert!(!villager.is_boss);
+        assert_eq!(villager.aggro_range, 0.0);
+        assert_eq!(villager.exp, 0);
+        assert!(villager.skills.is_empty());
+
+        assert!(registry.get(14, 1001).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_territory_registry() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![
+                get_npc_data(),
+                TestElement::new(
+                    "TerritoryData",
+                    vec![],
+                    vec![
+                        TestElement::new(
+                            "TerritoryGroup",
+                            vec![
+                                ("id", TestValue::Int(1)),
+                                ("continentId", TestValue::Int(5)),
+                                ("respawnTime", TestValue::Int(30000)),
+                            ],
+                            vec![
+                                TestElement::new(
+                                    "Npc",
+                                    vec![
+                                        ("huntingZoneId", TestValue::Int(13)),
+                                        ("templateId", TestValue::Int(1001)),
+                                        ("x", TestValue::Float(1.0)),
+                                        ("y", TestValue::Float(2.0)),
+                                        ("z", TestValue::Float(3.0)),
+                                        ("heading", TestValue::Float(90.0)),
+                                    ],
+                                    vec![
+                                        TestElement::new(
+                                            "PatrolPoint",
+                                            vec![
+                                                ("x", TestValue::Float(10.0)),
+                                                ("y", TestValue::Float(2.0)),
+                                                ("z", TestValue::Float(3.0)),
+                                            ],
+                                            vec![],
+                                        ),
+                                        TestElement::new(
+                                            "PatrolPoint",
+                                            vec![
+                                                ("x", TestValue::Float(10.0)),
+                                                ("y", TestValue::Float(20.0)),
+                                                ("z", TestValue::Float(3.0)),
+                                            ],
+                                            vec![],
+                                        ),
+                                    ],
+                                ),
+                                TestElement::new(
+                                    "Npc",
+                                    vec![
+                                        ("huntingZoneId", TestValue::Int(13)),
+                                        ("templateId", TestValue::Int(2001)),
+                                        ("x", TestValue::Float(4.0)),
+                                        ("y", TestValue::Float(5.0)),
+                                        ("z", TestValue::Float(6.0)),
+                                        ("respawnTime", TestValue::Int(1000)),
+                                    ],
+                                    vec![],
+                                ),
+                            ],
+                        ),
+                        TestElement::new(
+                            "TerritoryGroup",
+                            vec![
+                                ("id", TestValue::Int(2)),
+                                ("continentId", TestValue::Int(5)),
+                            ],
+                            vec![],
+                        ),
+                        TestElement::new(
+                            "TerritoryGroup",
+                            vec![
+                                ("id", TestValue::Int(3)),
+                                ("continentId", TestValue::Int(9001)),
+                            ],
+                            vec![],
+                        ),
+                    ],
+                ),
+            ],
+        );
+        let dc = DataCenter::parse(&create_test_datacenter(&root)?)?;
+        let registry = read_territory_registry(&dc, &read_npc_registry(&dc)?)?;
+        assert_eq!(registry.len(), 3);
+        assert_eq!(registry.get(9001).len(), 1);
+        assert!(registry.get(1).is_empty());
+
+        let groups = registry.get(5);
+        assert_eq!(groups.len(), 2);
+        let group = groups.iter().find(|group| group.id == 1).unwrap();
+        assert_eq!(group.spawns.len(), 2);
+
+        let monster = &group.spawns[0];
+        assert_eq!(monster.hunting_zone_id, 13);
+        assert_eq!(monster.template_id, 1001);
+        assert_eq!(monster.point, Point3::new(1.0, 2.0, 3.0));
+        assert!((monster.rotation.angle() - 90f32.to_radians()).abs() < 0.0001);
+        assert_eq!(monster.respawn_time, Duration::from_secs(30));
+        assert_eq!(
+            monster.patrol_points,
+            vec![Point3::new(10.0, 2.0, 3.0), Point3::new(10.0, 20.0, 3.0)]
+        );
+
+        let villager = &group.spawns[1];
+        assert_eq!(villager.template_id, 2001);
+        assert_eq!(villager.respawn_time, Duration::from_secs(1));
+        assert!(villager.patrol_points.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_territory_registry_with_unknown_npc() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![
+                get_npc_data(),
+                TestElement::new(
+                    "TerritoryData",
+                    vec![],
+                    vec![TestElement::new(
+                        "TerritoryGroup",
+                        vec![
+                            ("id", TestValue::Int(1)),
+                            ("continentId", TestValue::Int(5)),
+                        ],
+                        vec![TestElement::new(
+                            "Npc",
+                            vec![
+                                ("huntingZoneId", TestValue::Int(14)),
+                                ("templateId", TestValue::Int(1001)),
+                                ("x", TestValue::Float(1.0)),
+                                ("y", TestValue::Float(2.0)),
+                                ("z", TestValue::Float(3.0)),
+                            ],
+                            vec![],
+                        )],
+                    )],
+                ),
+            ],
+        );
+        let dc = DataCenter::parse(&create_test_datacenter(&root)?)?;
+        assert!(read_territory_registry(&dc, &read_npc_registry(&dc)?).is_err());
+        Ok(())
+    }
+}
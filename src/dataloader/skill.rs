@@ -0,0 +1,147 @@
+/// Module that reads the skill templates out of the datacenter.
+///
+/// Expected structure of the skill data:
+///
+/// ```text
+/// SkillData
+///   Skill id class mpCost cooldown duration damage range selfAbnormality targetAbnormality
+/// ```
+///
+/// Only `id` is required. Skills without a `class` can be used by every class. `cooldown` and
+/// `duration` are given in milliseconds, `range` in units around the user of the skill. Skills
+/// without `damage` don't hit anyone. `selfAbnormality` and `targetAbnormality` are lists of
+/// abnormality IDs separated by `;`.
+use crate::dataloader::datacenter::{DataCenter, Element};
+use crate::dataloader::item::{parse_class, split_list};
+use crate::ecs::resource::{SkillRegistry, SkillTemplate};
+use crate::*;
+use anyhow::Context;
+use std::time::Duration;
+
+/// Creates the skill registry out of the skill data of the datacenter.
+pub fn read_skill_registry(dc: &DataCenter) -> Result<SkillRegistry> {
+    let skills = dc
+        .query("SkillData/Skill")
+        .iter()
+        .map(read_skill)
+        .collect::<Result<Vec<SkillTemplate>>>()?;
+    Ok(SkillRegistry::new(skills))
+}
+
+fn read_skill(skill: &Element) -> Result<SkillTemplate> {
+    let id = skill.get_i32("id").context("Skill doesn't have an ID")?;
+    let class = skill
+        .get_str("class")
+        .map(parse_class)
+        .transpose()
+        .context(format!("Can't read the class of skill {}", id))?;
+    let self_abnormalities = parse_ids(skill.get_str("selfAbnormality"))
+        .context(format!("Can't read the self abnormalities of skill {}", id))?;
+    let target_abnormalities = parse_ids(skill.get_str("targetAbnormality")).context(format!(
+        "Can't read the target abnormalities of skill {}",
+        id
+    ))?;
+
+    Ok(SkillTemplate {
+        id,
+        class,
+        mp_cost: skill.get_i32("mpCost").unwrap_or_default().max(0),
+        cooldown: millis(skill.get_i32("cooldown")),
+        duration: millis(skill.get_i32("duration")),
+        damage: skill.get_i32("damage").unwrap_or_default().max(0),
+        range: skill.get_f32("range").unwrap_or_default(),
+        self_abnormalities,
+        target_abnormalities,
+    })
+}
+
+fn parse_ids(list: Option<&str>) -> Result<Vec<i32>> {
+    split_list(list).map(|id| Ok(id.parse::<i32>()?)).collect()
+}
+
+pub fn millis(value: Option<i32>) -> Duration {
+    Duration::from_millis(value.unwrap_or_default().max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataloader::datacenter::tests::{create_test_datacenter, TestElement, TestValue};
+    use crate::model::Class;
+
+    #[test]
+    fn test_read_skill_registry() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "SkillData",
+                vec![],
+                vec![
+                    TestElement::new(
+                        "Skill",
+                        vec![
+                            ("id", TestValue::Int(10100)),
+                            ("class", TestValue::String("warrior")),
+                            ("mpCost", TestValue::Int(20)),
+                            ("cooldown", TestValue::Int(5000)),
+                            ("duration", TestValue::Int(800)),
+                            ("damage", TestValue::Int(120)),
+                            ("range", TestValue::Float(150.0)),
+                            ("selfAbnormality", TestValue::String("4000")),
+                            ("targetAbnormality", TestValue::String("4100; 4101")),
+                        ],
+                        vec![],
+                    ),
+                    TestElement::new("Skill", vec![("id", TestValue::Int(90100))], vec![]),
+                ],
+            )],
+        );
+        let registry = read_skill_registry(&DataCenter::parse(&create_test_datacenter(&root)?)?)?;
+        assert_eq!(registry.len(), 2);
+
+        let combo_attack = registry.get(10100).unwrap();
+        assert_eq!(combo_attack.class, Some(Class::Warrior));
+        assert_eq!(combo_attack.mp_cost, 20);
+        assert_eq!(combo_attack.cooldown, Duration::from_millis(5000));
+        assert_eq!(combo_attack.duration, Duration::from_millis(800));
+        assert_eq!(combo_attack.damage, 120);
+        assert!((combo_attack.range - 150.0).abs() < std::f32::EPSILON);
+        assert_eq!(combo_attack.self_abnormalities, vec![4000]);
+        assert_eq!(combo_attack.target_abnormalities, vec![4100, 4101]);
+
+        let common_skill = registry.get(90100).unwrap();
+        assert_eq!(common_skill.class, None);
+        assert_eq!(common_skill.mp_cost, 0);
+        assert_eq!(common_skill.cooldown, Duration::from_millis(0));
+        assert_eq!(common_skill.damage, 0);
+        assert!(common_skill.self_abnormalities.is_empty());
+
+        assert!(registry.get(1).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_skill_with_unknown_class() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "SkillData",
+                vec![],
+                vec![TestElement::new(
+                    "Skill",
+                    vec![
+                        ("id", TestValue::Int(10100)),
+                        ("class", TestValue::String("bard")),
+                    ],
+                    vec![],
+                )],
+            )],
+        );
+        let dc = DataCenter::parse(&create_test_datacenter(&root)?)?;
+        assert!(read_skill_registry(&dc).is_err());
+        Ok(())
+    }
+}
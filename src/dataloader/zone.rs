@@ -0,0 +1,181 @@
+/// Module that reads the zone information out of the datacenter.
+///
+/// Expected structure of the continent data:
+///
+/// ```text
+/// ContinentData
+///   Continent id channelType capacity topologyId
+///     SpawnPoint x y z heading
+/// ```
+///
+/// Only `id` is required. `channelType` defines the type of the local world ("dungeon",
+/// "arena" / "battleField" or a field for everything else). `topologyId` defaults to the ID
+/// of the continent and `heading` is given in degrees.
+use crate::dataloader::datacenter::{DataCenter, Element};
+use crate::ecs::component::LocalWorldType;
+use crate::ecs::resource::{SpawnPoint, Zone, ZoneRegistry};
+use crate::*;
+use anyhow::Context;
+use nalgebra::{Point3, Rotation3, Vector3};
+
+/// Creates the zone registry out of the continent data of the datacenter.
+pub fn read_zone_registry(dc: &DataCenter) -> Result<ZoneRegistry> {
+    let zones = dc
+        .query("ContinentData/Continent")
+        .iter()
+        .map(read_zone)
+        .collect::<Result<Vec<Zone>>>()?;
+    Ok(ZoneRegistry::new(zones))
+}
+
+fn read_zone(continent: &Element) -> Result<Zone> {
+    let id = continent
+        .get_i32("id")
+        .context("Continent doesn't have an ID")?;
+
+    let zone_type = match continent.get_str("channelType").unwrap_or_default() {
+        "dungeon" => LocalWorldType::Dungeon,
+        "arena" | "battleField" => LocalWorldType::Arena,
+        _ => LocalWorldType::Field,
+    };
+
+    let spawn_points = continent
+        .children_by_name("SpawnPoint")
+        .map(|spawn_point| {
+            Ok(SpawnPoint {
+                point: Point3::new(
+                    spawn_point
+                        .get_f32("x")
+                        .context(format!("Spawn point of continent {} has no x value", id))?,
+                    spawn_point
+                        .get_f32("y")
+                        .context(format!("Spawn point of continent {} has no y value", id))?,
+                    spawn_point
+                        .get_f32("z")
+                        .context(format!("Spawn point of continent {} has no z value", id))?,
+                ),
+                rotation: Rotation3::from_axis_angle(
+                    &Vector3::z_axis(),
+                    spawn_point.get_f32("heading").unwrap_or(0.0).to_radians(),
+                ),
+            })
+        })
+        .collect::<Result<Vec<SpawnPoint>>>()?;
+
+    Ok(Zone {
+        id,
+        zone_type,
+        channel_capacity: continent.get_i32("capacity").unwrap_or(0).max(0) as u32,
+        topology_id: continent.get_i32("topologyId").unwrap_or(id),
+        spawn_points,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataloader::datacenter::tests::{create_test_datacenter, TestElement, TestValue};
+
+    fn get_test_datacenter() -> Result<DataCenter> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "ContinentData",
+                vec![],
+                vec![
+                    TestElement::new(
+                        "Continent",
+                        vec![
+                            ("id", TestValue::Int(5)),
+                            ("channelType", TestValue::String("field")),
+                            ("capacity", TestValue::Int(150)),
+                        ],
+                        vec![
+                            TestElement::new(
+                                "SpawnPoint",
+                                vec![
+                                    ("x", TestValue::Float(1.0)),
+                                    ("y", TestValue::Float(2.0)),
+                                    ("z", TestValue::Float(3.0)),
+                                    ("heading", TestValue::Float(90.0)),
+                                ],
+                                vec![],
+                            ),
+                            TestElement::new(
+                                "SpawnPoint",
+                                vec![
+                                    ("x", TestValue::Float(4.0)),
+                                    ("y", TestValue::Float(5.0)),
+                                    ("z", TestValue::Float(6.0)),
+                                ],
+                                vec![],
+                            ),
+                        ],
+                    ),
+                    TestElement::new(
+                        "Continent",
+                        vec![
+                            ("id", TestValue::Int(9001)),
+                            ("channelType", TestValue::String("dungeon")),
+                            ("topologyId", TestValue::Int(9000)),
+                        ],
+                        vec![],
+                    ),
+                    TestElement::new(
+                        "Continent",
+                        vec![
+                            ("id", TestValue::Int(7001)),
+                            ("channelType", TestValue::String("battleField")),
+                        ],
+                        vec![],
+                    ),
+                ],
+            )],
+        );
+        DataCenter::parse(&create_test_datacenter(&root)?)
+    }
+
+    #[test]
+    fn test_read_zone_registry() -> Result<()> {
+        let registry = read_zone_registry(&get_test_datacenter()?)?;
+        assert_eq!(registry.len(), 3);
+
+        let field = registry.get(5).unwrap();
+        assert_eq!(field.zone_type, LocalWorldType::Field);
+        assert_eq!(field.channel_capacity, 150);
+        assert_eq!(field.topology_id, 5);
+        assert_eq!(field.spawn_points.len(), 2);
+        assert_eq!(field.spawn_points[0].point, Point3::new(1.0, 2.0, 3.0));
+        assert!((field.spawn_points[0].rotation.angle() - 90f32.to_radians()).abs() < 0.0001);
+        assert_eq!(field.spawn_points[1].point, Point3::new(4.0, 5.0, 6.0));
+
+        let dungeon = registry.get(9001).unwrap();
+        assert_eq!(dungeon.zone_type, LocalWorldType::Dungeon);
+        assert_eq!(dungeon.channel_capacity, 0);
+        assert_eq!(dungeon.topology_id, 9000);
+        assert!(dungeon.spawn_points.is_empty());
+
+        assert_eq!(registry.zone_type(7001), LocalWorldType::Arena);
+        assert_eq!(registry.zone_type(1), LocalWorldType::Field);
+        assert!(registry.get(1).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_zone_registry_without_id() -> Result<()> {
+        let root = TestElement::new(
+            "__root__",
+            vec![],
+            vec![TestElement::new(
+                "ContinentData",
+                vec![],
+                vec![TestElement::new("Continent", vec![], vec![])],
+            )],
+        );
+        let dc = DataCenter::parse(&create_test_datacenter(&root)?)?;
+        assert!(read_zone_registry(&dc).is_err());
+        Ok(())
+    }
+}
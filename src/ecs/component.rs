@@ -1,13 +1,15 @@
 /// Module holds the components that the ECS use.
 use crate::ecs::message::EcsMessage;
-use crate::model::Region;
+use crate::ecs::resource::{NpcSpawnTemplate, NpcTemplate};
+use crate::model::entity::{EquippedItem, Item};
+use crate::model::{Customization, EquipmentSlot, LootingMethod, Region, Role, Stats, TemplateID};
 use crate::Result;
 use async_std::sync::Sender;
 use async_std::task::JoinHandle;
 use nalgebra::{Point3, Rotation3};
 use shipyard::EntityId;
-use std::collections::HashSet;
-use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
 /// Tracks the connection and login information of a player for the global world.
 #[derive(Clone, Debug)]
@@ -38,6 +40,92 @@ pub struct Settings {
     pub visibility_range: u32,
 }
 
+/// Holds the chat information of a spawned user in the global world.
+#[derive(Clone, Debug)]
+pub struct Chatter {
+    pub user_name: String,
+    pub window_start: Instant, // Start of the current rate limit window
+    pub message_count: u32,    // Messages send in the current rate limit window
+}
+
+/// Holds the user IDs of the users an user has blocked in the global world.
+#[derive(Clone, Debug, Default)]
+pub struct BlockList {
+    pub blocked_users: HashSet<i32>,
+}
+
+/// Holds the user IDs of the friends of an user in the global world.
+#[derive(Clone, Debug, Default)]
+pub struct FriendList {
+    pub friends: HashSet<i32>,
+}
+
+/// Holds the private chat channels an user has joined in the global world.
+#[derive(Clone, Debug, Default)]
+pub struct PrivateChannels {
+    pub channels: HashMap<i32, i32>, // Slot of the channel (1-8) to the channel ID
+}
+
+/// A party or raid of users in the global world. Parties are entities of their own.
+#[derive(Clone, Debug)]
+pub struct Party {
+    pub leader_id: EntityId,    // connection_global_world_id of the leader
+    pub members: Vec<EntityId>, // connection_global_world_id of the members in the order they joined
+    pub looting_method: LootingMethod,
+    pub is_raid: bool,
+}
+
+/// Connects an user with it's party in the global world.
+#[derive(Clone, Copy, Debug)]
+pub struct PartyMember {
+    pub party_id: EntityId,
+}
+
+/// Holds the pending party invitation of an user in the global world.
+#[derive(Clone, Copy, Debug)]
+pub struct PartyInvitation {
+    pub inviter_id: EntityId, // connection_global_world_id of the inviting user
+    pub created_at: Instant,
+}
+
+/// Holds the pending guild invitation of an user in the global world.
+#[derive(Clone, Copy, Debug)]
+pub struct GuildInvitation {
+    pub inviter_id: EntityId, // connection_global_world_id of the inviting user
+    pub guild_id: i32,
+    pub created_at: Instant,
+}
+
+/// Connects a spawned user with it's guild in the global world.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UserGuild {
+    pub guild_id: i32,
+}
+
+/// An entry of the dungeon matching queue. Attached to the user that queued (the leader of a party).
+#[derive(Clone, Debug)]
+pub struct MatchingEntry {
+    pub zone_ids: Vec<i32>,             // Dungeons the entry is queued for
+    pub members: Vec<(EntityId, Role)>, // connection_global_world_id and role of the queued users
+    pub queued_at: Instant,
+    pub ready_check: Option<ReadyCheck>, // Set while the entry is part of a matched group
+}
+
+/// The ready check of a group that was formed by the dungeon matching.
+#[derive(Clone, Debug)]
+pub struct ReadyCheck {
+    pub group_id: EntityId, // ID of the entry of the group that was queued first
+    pub zone_id: i32,
+    pub ready: HashSet<EntityId>, // connection_global_world_id of the members that are ready
+    pub deadline: Instant,
+}
+
+/// Requests that the user is spawned into the instance of it's group instead of a shared local world.
+#[derive(Clone, Copy, Debug)]
+pub struct InstanceRequest {
+    pub group_id: EntityId,
+}
+
 /// Holds the global spawn information of an user.
 #[derive(Clone, Debug)]
 pub struct GlobalUserSpawn {
@@ -50,6 +138,8 @@ pub struct GlobalUserSpawn {
     pub local_world_channel: Option<Sender<EcsMessage>>,
     pub marked_for_deletion: bool,
     pub is_alive: bool,
+    pub channel_num: Option<i32>, // Requested or current channel of a field
+    pub is_relocating: bool,      // Set while the user changes the local world
 }
 
 /// Holds the local spawn information of an user.
@@ -71,6 +161,8 @@ pub enum UserSpawnStatus {
     Spawning,    // User has been given the command to spawn.
     Spawned,     // User is spawned in a local world.
     SpawnFailed, // Spawn wasn't successful
+    Relocating,  // User wants to be moved into another local world.
+    Despawning,  // User is being removed from it's current local world.
 }
 
 /// Holds information about a local world.
@@ -81,8 +173,9 @@ pub struct LocalWorld {
     pub zone_id: i32,
     pub channel: Sender<EcsMessage>,
     pub join_handle: JoinHandle<Result<()>>,
-    pub users: HashSet<EntityId>,  // connection_global_world_id
-    pub deadline: Option<Instant>, // Set when no users are present
+    pub users: HashSet<EntityId>,   // connection_global_world_id
+    pub deadline: Option<Instant>,  // Set when no users are present
+    pub group_id: Option<EntityId>, // Set for instances that are reserved for a group
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -98,3 +191,237 @@ pub struct Location {
     pub point: Point3<f32>,
     pub rotation: Rotation3<f32>,
 }
+
+/// Tracks which entities a connection can see in a local world.
+#[derive(Clone, Debug)]
+pub struct Visibility {
+    pub range: u32,
+    pub visible_entities: HashSet<EntityId>,
+}
+
+/// Holds the appearance of an user that other users need to see the user.
+#[derive(Clone, Debug)]
+pub struct UserAppearance {
+    pub name: String,
+    pub template_id: TemplateID,
+    pub level: i32,
+    pub details: Vec<u8>,
+    pub shape: Vec<u8>,
+    pub appearance: Customization,
+    pub appearance2: i32,
+    pub show_face: bool,
+    pub show_style: bool,
+    pub guild_name: String,
+    pub guild_rank: String,
+}
+
+/// Holds the inventory of an user in a local world. Changes are persisted right away.
+#[derive(Clone, Debug)]
+pub struct UserInventory {
+    pub size: i32,
+    pub money: i64,
+    pub items: HashMap<i32, Item>, // Slot to item
+    pub equipment_preset: i32,
+    pub equipment: HashMap<EquipmentSlot, EquippedItem>, // Equipped items of the active preset
+}
+
+/// Experience of an user in a local world. Other systems request experience, which is applied by
+/// the experience system in the same tick. The level of the user is part of it's appearance.
+#[derive(Clone, Debug)]
+pub struct UserExperience {
+    pub exp: i64, // Experience inside the current level
+    pub rest_bonus_exp: i64,
+    pub playtime: i64, // Playtime in seconds at the time of the spawn
+    pub spawned_at: Instant,
+    pub requested: Vec<ExperienceRequest>,
+}
+
+/// Request to give experience to an user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExperienceRequest {
+    pub exp: i64,
+    pub source: ExperienceSource,
+}
+
+/// Sources of experience. The rest bonus only applies to the experience of killed monsters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExperienceSource {
+    Kill,
+    Quest,
+}
+
+/// Stats of an user in a local world. Aggregated out of the stats of the level and the equipped
+/// items.
+#[derive(Clone, Debug)]
+pub struct UserStats {
+    pub base: Stats,
+    pub total: Stats,
+}
+
+/// Health of an entity in a local world. Entities without health points left are dead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Health {
+    pub hp: i64,
+    pub max_hp: i64,
+}
+
+/// Mana of an user in a local world. Mana is needed to use skills and regenerates over time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mana {
+    pub mp: i32,
+    pub max_mp: i32,
+    pub regenerates_at: Instant,
+}
+
+/// Tracks the skill usage of an user in a local world. Cooldowns are only kept while the user
+/// stays inside the local world.
+#[derive(Clone, Debug, Default)]
+pub struct SkillState {
+    pub cooldowns: HashMap<i32, Instant>, // Skill ID -> end of the cooldown
+    pub active_skill: Option<ActiveSkill>,
+    pub next_action_id: i32,
+}
+
+/// The skill an user is currently using.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActiveSkill {
+    pub skill_id: i32,
+    pub action_id: i32,
+    pub ends_at: Instant,
+    pub hits_applied: bool,
+}
+
+/// Holds the warehouse access of an user in a local world. The items of the warehouses are only
+/// kept inside the database.
+#[derive(Clone, Debug)]
+pub struct UserWarehouse {
+    pub commission_paid: bool, // Needed to move items in and out of the warehouse of the account
+}
+
+/// Holds the parcel an user is writing in a local world. The attachments stay inside the
+/// inventory until the parcel is send.
+#[derive(Clone, Debug, Default)]
+pub struct ParcelDraft {
+    pub money: i64,
+    pub items: Vec<ParcelDraftItem>,
+}
+
+/// An inventory item that is attached to a parcel draft.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParcelDraftItem {
+    pub db_id: i64,
+    pub inventory_slot: i32,
+    pub amount: i32,
+}
+
+/// Holds the trade of an user with an other user in a local world. Both users of a trade have
+/// a trade session attached. The offered items stay inside the inventory until the trade is
+/// committed.
+#[derive(Clone, Debug)]
+pub struct TradeSession {
+    pub contract_id: i32,     // User ID of the user that requested the trade
+    pub sender_id: EntityId,  // connection_local_world_id of the user that requested the trade
+    pub partner_id: EntityId, // connection_local_world_id of the other user
+    pub status: TradeStatus,
+    pub money: i64,
+    pub items: Vec<TradeItem>,
+    pub created_at: Instant,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TradeStatus {
+    Requested, // The requested user didn't accept the trade yet.
+    Open,      // The offer of the user can be changed.
+    Locked,    // The user locked it's offer.
+    Confirmed, // The user confirmed the trade after both offers were locked.
+}
+
+/// An inventory item that is offered in a trade.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TradeItem {
+    pub db_id: i64,
+    pub inventory_slot: i32,
+    pub amount: i32,
+}
+
+/// Abnormalities (buffs and debuffs) of an user in a local world. Other systems request new
+/// abnormalities, which are applied by the abnormality system in the same tick.
+#[derive(Clone, Debug, Default)]
+pub struct Abnormalities {
+    pub active: HashMap<i32, ActiveAbnormality>, // Abnormality ID -> active abnormality
+    pub requested: Vec<AbnormalityRequest>,
+}
+
+/// An abnormality that currently affects an user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActiveAbnormality {
+    pub source_id: EntityId,
+    pub stacks: i32,
+    pub remaining: Duration,
+    pub next_period: Duration, // Time until the next periodic effect
+    pub is_persistent: bool,
+}
+
+/// Request to apply an abnormality to an user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AbnormalityRequest {
+    pub abnormality_id: i32,
+    pub source_id: EntityId,
+}
+
+/// A NPC (or monster) in a local world.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Npc {
+    pub hunting_zone_id: i32,
+    pub template_id: i32,
+    pub spawn_id: EntityId, // The territory spawn the NPC belongs to
+    pub walk_speed: i16,
+    pub run_speed: i16,
+    pub is_villager: bool,
+    pub is_alive: bool,
+}
+
+/// A spawn of a NPC inside a territory of a local world. Dead NPCs are removed and spawned again
+/// once the respawn time passed.
+#[derive(Clone, Debug)]
+pub struct NpcSpawn {
+    pub spawn: NpcSpawnTemplate,
+    pub template: NpcTemplate,
+    pub npc_id: Option<EntityId>, // None while the NPC waits for it's respawn
+    pub respawns_at: Option<Instant>,
+}
+
+/// The AI of a monster in a local world. The aggro table holds the threat of all users that
+/// attacked the monster or came too close to it.
+#[derive(Clone, Debug)]
+pub struct Ai {
+    pub state: AiState,
+    pub aggro: Vec<(EntityId, i64)>,
+    pub patrol_index: usize,
+    pub next_action_at: Instant,
+    pub cooldowns: HashMap<i32, Instant>,
+    pub destination: Option<Point3<f32>>, // The last destination that was shown to the users
+}
+
+impl Ai {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            state: AiState::Idle,
+            aggro: Vec::new(),
+            patrol_index: 0,
+            next_action_at: now,
+            cooldowns: HashMap::new(),
+            destination: None,
+        }
+    }
+}
+
+/// States of the AI of a monster. Monsters fight as long as they have a target and return to
+/// their spawn point once they lost all targets or went too far away from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AiState {
+    Idle,
+    Patrol,
+    Combat,
+    Return,
+}
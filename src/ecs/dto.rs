@@ -1,7 +1,8 @@
 /// Module that holds data structures used by the ECS to transfer data.
 use crate::ecs::message::EcsMessage;
 use crate::model::entity;
-use crate::model::entity::UserLocation;
+use crate::model::entity::{EquippedItem, Inventory, Item, UserAbnormality, UserLocation};
+use crate::model::EquipmentSlot;
 use async_std::sync::Sender;
 use shipyard::EntityId;
 
@@ -13,6 +14,13 @@ pub struct UserInitializer {
     pub user: entity::User,
     pub location: UserLocation,
     pub is_alive: bool,
+    pub visibility_range: u32,
+    pub guild_name: String,
+    pub guild_rank: String,
+    pub inventory: Inventory,
+    pub items: Vec<Item>,
+    pub equipment: Vec<EquippedItem>, // Equipped items of the active preset
+    pub abnormalities: Vec<UserAbnormality>,
 }
 
 /// Used to send data from the Local World to the Global World when de-spawning an user.
@@ -22,4 +30,71 @@ pub struct UserFinalizer {
     pub user_id: i32,
     pub location: UserLocation,
     pub is_alive: bool,
+    pub abnormalities: Vec<UserAbnormality>, // Abnormalities that survive the logout
+    pub progress: Option<UserProgress>,
+}
+
+/// Level, experience and playtime of an user that are persisted when de-spawning an user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UserProgress {
+    pub level: i32,
+    pub xp: i64,
+    pub rest_bonus_xp: i64,
+    pub playtime: i64, // Playtime in seconds
+}
+
+/// Template IDs of the visible equipment of an user. Used to render the user in the lobby and
+/// inside the world. Empty slots have the template ID 0.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EquipmentLook {
+    pub weapon: i32,
+    pub body: i32,
+    pub hand: i32,
+    pub feet: i32,
+    pub earring1: i32,
+    pub earring2: i32,
+    pub ring1: i32,
+    pub ring2: i32,
+    pub underwear: i32,
+    pub head: i32,
+    pub face: i32,
+    pub style_head: i32,
+    pub style_face: i32,
+    pub style_back: i32,
+    pub style_weapon: i32,
+    pub style_body: i32,
+    pub style_footprint: i32,
+}
+
+impl EquipmentLook {
+    pub fn new<'a, I>(equipped_items: I) -> Self
+    where
+        I: IntoIterator<Item = &'a EquippedItem>,
+    {
+        let mut look = EquipmentLook::default();
+        for equipped_item in equipped_items {
+            let template_id = equipped_item.template_id;
+            match equipped_item.slot {
+                EquipmentSlot::Weapon => look.weapon = template_id,
+                EquipmentSlot::Body => look.body = template_id,
+                EquipmentSlot::Hand => look.hand = template_id,
+                EquipmentSlot::Feet => look.feet = template_id,
+                EquipmentSlot::Earring1 => look.earring1 = template_id,
+                EquipmentSlot::Earring2 => look.earring2 = template_id,
+                EquipmentSlot::Ring1 => look.ring1 = template_id,
+                EquipmentSlot::Ring2 => look.ring2 = template_id,
+                EquipmentSlot::Necklace => { /* Not visible */ }
+                EquipmentSlot::Underwear => look.underwear = template_id,
+                EquipmentSlot::Head => look.head = template_id,
+                EquipmentSlot::Face => look.face = template_id,
+                EquipmentSlot::StyleHead => look.style_head = template_id,
+                EquipmentSlot::StyleFace => look.style_face = template_id,
+                EquipmentSlot::StyleBack => look.style_back = template_id,
+                EquipmentSlot::StyleWeapon => look.style_weapon = template_id,
+                EquipmentSlot::StyleBody => look.style_body = template_id,
+                EquipmentSlot::StyleFootprint => look.style_footprint = template_id,
+            }
+        }
+        look
+    }
 }
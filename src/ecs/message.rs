@@ -16,6 +16,8 @@
 /// Network connections and ECS have async ```mpmc``` channels to write messages into.
 ///
 use crate::ecs::dto::{UserFinalizer, UserInitializer};
+use crate::ecs::resource::GuildWarRegistry;
+use crate::model::{ChatChannel, Vec3f};
 use crate::protocol::opcode::Opcode;
 use crate::protocol::packet::*;
 use crate::protocol::serde::{from_vec, to_vec};
@@ -183,11 +185,135 @@ macro_rules! assemble_message {
 assemble_message! {
     // Local packet messages (handled by the LOCAL_WORLD)
     Local Packet Messages {
+        RequestAbnormalityTooltipValue{packet: CRequestAbnormalityTooltipValue}, C_REQUEST_ABNORMALITY_TOOLTIP_VALUE, Local;
+        RequestAcceptContract{packet: CAcceptContract}, C_ACCEPT_CONTRACT, Local;
+        RequestAddTradeBag{packet: CAddTradeBag}, C_ADD_TRADE_BAG, Local;
+        RequestApplyInvenPocketSort{packet: CApplyInvenPocketSort}, C_APPLY_INVEN_POCKET_SORT, Local;
+        RequestCancelContract{packet: CCancelContract}, C_CANCEL_CONTRACT, Local;
+        RequestCancelSkill{packet: CCancelSkill}, C_CANCEL_SKILL, Local;
+        RequestChangeEquipPreset{packet: CChangeEquipPreset}, C_CHANGE_EQUIP_PRESET, Local;
+        RequestClearSendParcel{packet: CClearSendParcel}, C_CLEAR_SEND_PARCEL, Local;
+        RequestCloseSendParcel{packet: CCloseSendParcel}, C_CLOSE_SEND_PARCEL, Local;
+        RequestCommitVmTrade{packet: CCommitVmTrade}, C_COMMIT_VM_TRADE, Local;
+        RequestDelItem{packet: CDelItem}, C_DEL_ITEM, Local;
+        RequestDelTradeBag{packet: CDelTradeBag}, C_DEL_TRADE_BAG, Local;
+        RequestEquipItem{packet: CEquipItem}, C_EQUIP_ITEM, Local;
+        RequestExpandInvenPocket{packet: CExpandInvenPocket}, C_EXPAND_INVEN_POCKET, Local;
+        RequestGetWareItem{packet: CGetWareItem}, C_GET_WARE_ITEM, Local;
         RequestLoadTopoFin{packet: CLoadTopoFin}, C_LOAD_TOPO_FIN, Local;
+        RequestMoveInvenPos{packet: CMoveInvenPos}, C_MOVE_INVEN_POS, Local;
+        RequestMoveWarePos{packet: CMoveWarePos}, C_MOVE_WARE_POS, Local;
+        RequestNotifyLocationInAction{packet: CNotifyLocationInAction}, C_NOTIFY_LOCATION_IN_ACTION, Local;
+        RequestNotifyLocationInDash{packet: CNotifyLocationInDash}, C_NOTIFY_LOCATION_IN_DASH, Local;
+        RequestPayWarehouseCommision{packet: CPayWarehouseCommision}, C_PAY_WAREHOUSE_COMMISION, Local;
+        RequestPlayerLocation{packet: CPlayerLocation}, C_PLAYER_LOCATION, Local;
+        RequestPressSkill{packet: CPressSkill}, C_PRESS_SKILL, Local;
+        RequestPutWareItem{packet: CPutWareItem}, C_PUT_WARE_ITEM, Local;
+        RequestRecvParcel{packet: CRecvParcel}, C_RECV_PARCEL, Local;
+        RequestRejectContract{packet: CRejectContract}, C_REJECT_CONTRACT, Local;
+        RequestReviveNow{packet: CReviveNow}, C_REVIVE_NOW, Local;
+        RequestSendParcel{packet: CSendParcel}, C_SEND_PARCEL, Local;
+        RequestSetSendParcelItem{packet: CSetSendParcelItem}, C_SET_SEND_PARCEL_ITEM, Local;
+        RequestSetSendParcelMoney{packet: CSetSendParcelMoney}, C_SET_SEND_PARCEL_MONEY, Local;
+        RequestShowInven{packet: CShowInven}, C_SHOW_INVEN, Local;
+        RequestStartSkill{packet: CStartSkill}, C_START_SKILL, Local;
+        RequestTradeBagDone{packet: CTradeBagDone}, C_TRADE_BAG_DONE, Local;
+        RequestUnequipItem{packet: CUnequipItem}, C_UNEQUIP_ITEM, Local;
+        RequestViewWare{packet: CViewWare}, C_VIEW_WARE, Local;
+        ResponseAbnormalityBegin{packet: SAbnormalityBegin}, S_ABNORMALITY_BEGIN, Connection;
+        ResponseAbnormalityEnd{packet: SAbnormalityEnd}, S_ABNORMALITY_END, Connection;
+        ResponseAbnormalityRefresh{packet: SAbnormalityRefresh}, S_ABNORMALITY_REFRESH, Connection;
+        ResponseAbnormalityTooltipValue{packet: SAbnormalityTooltipValue}, S_ABNORMALITY_TOOLTIP_VALUE, Connection;
+        ResponseAcceptContract{packet: SAcceptContract}, S_ACCEPT_CONTRACT, Connection;
+        ResponseActionEnd{packet: SActionEnd}, S_ACTION_END, Connection;
+        ResponseActionStage{packet: SActionStage}, S_ACTION_STAGE, Connection;
+        ResponseCancelContract{packet: SCancelContract}, S_CANCEL_CONTRACT, Connection;
+        ResponseCannotStartSkill{packet: SCannotStartSkill}, S_CANNOT_START_SKILL, Connection;
+        ResponseCreatureChangeHp{packet: SCreatureChangeHp}, S_CREATURE_CHANGE_HP, Connection;
+        ResponseCreatureLife{packet: SCreatureLife}, S_CREATURE_LIFE, Connection;
+        ResponseDespawnNpc{packet: SDespawnNpc}, S_DESPAWN_NPC, Connection;
+        ResponseDespawnUser{packet: SDespawnUser}, S_DESPAWN_USER, Connection;
+        ResponseEachSkillResult{packet: SEachSkillResult}, S_EACH_SKILL_RESULT, Connection;
+        ResponseGuildName{packet: SGuildName}, S_GUILD_NAME, Connection;
+        ResponseItemlist{packet: SItemlist}, S_ITEMLIST, Connection;
+        ResponseNpcLocation{packet: SNpcLocation}, S_NPC_LOCATION, Connection;
+        ResponsePlayerChangeExp{packet: SPlayerChangeExp}, S_PLAYER_CHANGE_EXP, Connection;
+        ResponsePlayerChangeMp{packet: SPlayerChangeMp}, S_PLAYER_CHANGE_MP, Connection;
+        ResponseRecvParcel{packet: SRecvParcel}, S_RECV_PARCEL, Connection;
+        ResponseRejectContract{packet: SRejectContract}, S_REJECT_CONTRACT, Connection;
+        ResponseRequestContract{packet: SRequestContract}, S_REQUEST_CONTRACT, Connection;
+        ResponseSendParcel{packet: SSendParcel}, S_SEND_PARCEL, Connection;
+        ResponseSetSendParcelItem{packet: SSetSendParcelItem}, S_SET_SEND_PARCEL_ITEM, Connection;
+        ResponseSetSendParcelMoney{packet: SSetSendParcelMoney}, S_SET_SEND_PARCEL_MONEY, Connection;
         ResponseSpawnMe{packet: SSpawnMe}, S_SPAWN_ME, Connection;
+        ResponseSpawnNpc{packet: SSpawnNpc}, S_SPAWN_NPC, Connection;
+        ResponseSpawnUser{packet: SSpawnUser}, S_SPAWN_USER, Connection;
+        ResponseStartCooltimeSkill{packet: SStartCooltimeSkill}, S_START_COOLTIME_SKILL, Connection;
+        ResponseTradeAccept{packet: STradeAccept}, S_TRADE_ACCEPT, Connection;
+        ResponseTradeBagClose{packet: STradeBagClose}, S_TRADE_BAG_CLOSE, Connection;
+        ResponseTradeBagDone{packet: STradeBagDone}, S_TRADE_BAG_DONE, Connection;
+        ResponseTradeBox{packet: STradeBox}, S_TRADE_BOX, Connection;
+        ResponseUserExternalChange{packet: SUserExternalChange}, S_USER_EXTERNAL_CHANGE, Connection;
+        ResponseUserLevelup{packet: SUserLevelup}, S_USER_LEVELUP, Connection;
+        ResponseUserLocation{packet: SUserLocation}, S_USER_LOCATION, Connection;
+        ResponseViewWareEx{packet: SViewWareEx}, S_VIEW_WARE_EX, Connection;
     }
     // Global packets that need an account ID and the user ID attached.
     Global User Packet Messages {
+        RequestAcceptFriend{packet: CAcceptFriend}, C_ACCEPT_FRIEND, Global;
+        RequestAcceptGuildWar{packet: CAcceptGuildWar}, C_ACCEPT_GUILD_WAR, Global;
+        RequestAddFriend{packet: CAddFriend}, C_ADD_FRIEND, Global;
+        RequestAddFriendGroup{packet: CAddFriendGroup}, C_ADD_FRIEND_GROUP, Global;
+        RequestAddInterPartyMatchPool{packet: CAddInterPartyMatchPool}, C_ADD_INTER_PARTY_MATCH_POOL, Global;
+        RequestChangeFriendMemo{packet: CChangeFriendMemo}, C_CHANGE_FRIEND_MEMO, Global;
+        RequestChangeGuildChief{packet: CChangeGuildChief}, C_CHANGE_GUILD_CHIEF, Global;
+        RequestChangeGuildGroup{packet: CChangeGuildGroup}, C_CHANGE_GUILDGROUP, Global;
+        RequestBanishGuildMember{packet: CBanishGuildMember}, C_BANISH_GUILD_MEMBER, Global;
+        RequestBanPartyMember{packet: CBanPartyMember}, C_BAN_PARTY_MEMBER, Global;
+        RequestBlockUser{packet: CBlockUser}, C_BLOCK_USER, Global;
+        RequestChangePartyManager{packet: CChangePartyManager}, C_CHANGE_PARTY_MANAGER, Global;
+        RequestChat{packet: CChat}, C_CHAT, Global;
+        RequestCheckToDeclareGuildWar{packet: CCheckToDeclareGuildWar}, C_CHECK_TO_DECLARE_GUILD_WAR, Global;
+        RequestCheckToReadyPartyAnswer{packet: CCheckToReadyPartyAnswer}, C_CHECK_TO_READY_PARTY_ANSWER, Global;
+        RequestContract{packet: CRequestContract}, C_REQUEST_CONTRACT, Global;
+        RequestCreateGuildGroup{packet: CCreateGuildGroup}, C_CREATE_GUILDGROUP, Global;
+        RequestCreatePrivateChannel{packet: CCreatePrivateChannel}, C_CREATE_PRIVATE_CHANNEL, Global;
+        RequestDeclareGuildWar{packet: CDeclareGuildWar}, C_DECLARE_GUILD_WAR, Global;
+        RequestDelInterPartyMatchPool{packet: CDelInterPartyMatchPool}, C_DEL_INTER_PARTY_MATCH_POOL, Global;
+        RequestDeleteFriend{packet: CDeleteFriend}, C_DELETE_FRIEND, Global;
+        RequestDeleteFriendGroup{packet: CDeleteFriendGroup}, C_DELETE_FRIEND_GROUP, Global;
+        RequestDeleteParcel{packet: CDeleteParcel}, C_DELETE_PARCEL, Global;
+        RequestDestroyGuild{packet: CDestroyGuild}, C_DESTROY_GUILD, Global;
+        RequestDismissParty{packet: CDismissParty}, C_DISMISS_PARTY, Global;
+        RequestDungeonClearCountList{packet: CDungeonClearCountList}, C_DUNGEON_CLEAR_COUNT_LIST, Global;
+        RequestDungeonCoolTimeList{packet: CDungeonCoolTimeList}, C_DUNGEON_COOL_TIME_LIST, Global;
+        RequestEditBlockedUserMemo{packet: CEditBlockedUserMemo}, C_EDIT_BLOCKED_USER_MEMO, Global;
+        RequestEditFriendGroup{packet: CEditFriendGroup}, C_EDIT_FRIEND_GROUP, Global;
+        RequestEditPrivateChannel{packet: CEditPrivateChannel}, C_EDIT_PRIVATE_CHANNEL, Global;
+        RequestEnterDungeon{packet: CEnterDungeon}, C_ENTER_DUNGEON, Global;
+        RequestGetUserGuildLogo{packet: CGetUserGuildLogo}, C_GET_USER_GUILD_LOGO, Global;
+        RequestGiveUpGuildWar{packet: CGiveUpGuildWar}, C_GIVE_UP_GUILD_WAR, Global;
+        RequestGuildInfo{packet: CRequestGuildInfo}, C_REQUEST_GUILD_INFO, Global;
+        RequestInviteUserToGuild{packet: CInviteUserToGuild}, C_INVITE_USER_TO_GUILD, Global;
+        RequestJoinPrivateChannel{packet: CJoinPrivateChannel}, C_JOIN_PRIVATE_CHANNEL, Global;
+        RequestKickChannelMember{packet: CKickChannelMember}, C_KICK_CHANNEL_MEMBER, Global;
+        RequestLeaveGuild{packet: CLeaveGuild}, C_LEAVE_GUILD, Global;
+        RequestLeaveParty{packet: CLeaveParty}, C_LEAVE_PARTY, Global;
+        RequestLeavePrivateChannel{packet: CLeavePrivateChannel}, C_LEAVE_PRIVATE_CHANNEL, Global;
+        RequestListChannel{packet: CListChannel}, C_LIST_CHANNEL, Global;
+        RequestListParcel{packet: CListParcel}, C_LIST_PARCEL, Global;
+        RequestMergePartyToRaid{packet: CMergePartyToRaid}, C_MERGE_PARTY_TO_RAID, Global;
+        RequestParcelReadRecvStatus{packet: CParcelReadRecvStatus}, C_PARCEL_READ_RECV_STATUS, Global;
+        RequestPartyLootingMethod{packet: CPartyLootingMethod}, C_PARTY_LOOTING_METHOD, Global;
+        RequestRemoveBlockedUser{packet: CRemoveBlockedUser}, C_REMOVE_BLOCKED_USER, Global;
+        RequestRemoveGuildGroup{packet: CRemoveGuildGroup}, C_REMOVE_GUILDGROUP, Global;
+        RequestReplyThroughArbiterContract{packet: CReplyThroughArbiterContract}, C_REPLY_THROUGH_ARBITER_CONTRACT, Global;
+        RequestReturnParcel{packet: CReturnParcel}, C_RETURN_PARCEL, Global;
+        RequestSelectChannel{packet: CSelectChannel}, C_SELECT_CHANNEL, Global;
+        RequestSetGuildGroupAuthority{packet: CSetGuildGroupAuthority}, C_SET_GUILDGROUP_AUTHORITY, Global;
+        RequestShowParcelMessage{packet: CShowParcelMessage}, C_SHOW_PARCEL_MESSAGE, Global;
+        RequestUpdateFriendInfo{packet: CUpdateFriendInfo}, C_UPDATE_FRIEND_INFO, Global;
+        RequestWhisper{packet: CWhisper}, C_WHISPER, Global;
         ResponseLogin{packet: SLogin}, S_LOGIN, Connection;
     }
     // Global packets that need an account ID attached.
@@ -207,18 +333,67 @@ assemble_message! {
         RequestLoginArbiter{packet: CLoginArbiter}, C_LOGIN_ARBITER, Global;
         RequestCheckVersion{packet: CCheckVersion}, C_CHECK_VERSION, Global;
         RequestPong{packet: CPong}, C_PONG, Global;
+        ResponseAddBlockedUser{packet: SAddBlockedUser}, S_ADD_BLOCKED_USER, Connection;
+        ResponseAddFriend{packet: SAddFriend}, S_ADD_FRIEND, Connection;
+        ResponseAddInterPartyMatchPool{packet: SAddInterPartyMatchPool}, S_ADD_INTER_PARTY_MATCH_POOL, Connection;
+        ResponseBanParty{packet: SBanParty}, S_BAN_PARTY, Connection;
+        ResponseBanPartyMember{packet: SBanPartyMember}, S_BAN_PARTY_MEMBER, Connection;
+        ResponseBeginThroughArbiterContract{packet: SBeginThroughArbiterContract}, S_BEGIN_THROUGH_ARBITER_CONTRACT, Connection;
         ResponseCanCreateUser{packet: SCanCreateUser}, S_CAN_CREATE_USER, Connection;
+        ResponseCancelSelectChannel{packet: SCancelSelectChannel}, S_CANCEL_SELECT_CHANNEL, Connection;
+        ResponseChangePartyManager{packet: SChangePartyManager}, S_CHANGE_PARTY_MANAGER, Connection;
+        ResponseChangeFriendState{packet: SChangeFriendState}, S_CHANGE_FRIEND_STATE, Connection;
+        ResponseChat{packet: SChat}, S_CHAT, Connection;
+        ResponseCheckToDeclareGuildWar{packet: SCheckToDeclareGuildWar}, S_CHECK_TO_DECLARE_GUILD_WAR, Connection;
+        ResponseCheckToReadyParty{packet: SCheckToReadyParty}, S_CHECK_TO_READY_PARTY, Connection;
+        ResponseCheckToReadyPartyFin{packet: SCheckToReadyPartyFin}, S_CHECK_TO_READY_PARTY_FIN, Connection;
         ResponseCheckUserName{packet: SCheckUserName}, S_CHECK_USERNAME, Connection;
         ResponseCheckVersion{packet: SCheckVersion}, S_CHECK_VERSION, Connection;
+        ResponseCreateGuildResult{packet: SCreateGuildResult}, S_CREATE_GUILD_RESULT, Connection;
         ResponseCreateUser{packet: SCreateUser}, S_CREATE_USER, Connection;
+        ResponseCurrentChannel{packet: SCurrentChannel}, S_CURRENT_CHANNEL, Connection;
+        ResponseDelInterPartyMatchPool{packet: SDelInterPartyMatchPool}, S_DEL_INTER_PARTY_MATCH_POOL, Connection;
+        ResponseDeleteFriend{packet: SDeleteFriend}, S_DELETE_FRIEND, Connection;
+        ResponseDeleteParcel{packet: SDeleteParcel}, S_DELETE_PARCEL, Connection;
         ResponseDeleteUser{packet: SDeleteUser}, S_DELETE_USER, Connection;
+        ResponseDestroyGuild{packet: SDestroyGuild}, S_DESTROY_GUILD, Connection;
+        ResponseDungeonClearCountList{packet: SDungeonClearCountList}, S_DUNGEON_CLEAR_COUNT_LIST, Connection;
+        ResponseDungeonCoolTimeList{packet: SDungeonCoolTimeList}, S_DUNGEON_COOL_TIME_LIST, Connection;
+        ResponseEndGuildWar{packet: SEndGuildWar}, S_END_GUILD_WAR, Connection;
+        ResponseFinInterPartyMatch{packet: SFinInterPartyMatch}, S_FIN_INTER_PARTY_MATCH, Connection;
+        ResponseFriendGroupList{packet: SFriendGroupList}, S_FRIEND_GROUP_LIST, Connection;
+        ResponseFriendList{packet: SFriendList}, S_FRIEND_LIST, Connection;
         ResponseGetUserList{packet: SGetUserList}, S_GET_USER_LIST, Connection;
+        ResponseGuildInfo{packet: SGuildInfo}, S_GUILD_INFO, Connection;
+        ResponseGuildMemberList{packet: SGuildMemberList}, S_GUILD_MEMBER_LIST, Connection;
+        ResponseImageData{packet: SImageData}, S_IMAGE_DATA, Connection;
+        ResponseJoinPrivateChannel{packet: SJoinPrivateChannel}, S_JOIN_PRIVATE_CHANNEL, Connection;
+        ResponseLeaveGuild{packet: SLeaveGuild}, S_LEAVE_GUILD, Connection;
+        ResponseLeaveParty{packet: SLeaveParty}, S_LEAVE_PARTY, Connection;
+        ResponseLeavePartyMember{packet: SLeavePartyMember}, S_LEAVE_PARTY_MEMBER, Connection;
+        ResponseLeavePrivateChannel{packet: SLeavePrivateChannel}, S_LEAVE_PRIVATE_CHANNEL, Connection;
+        ResponseListChannel{packet: SListChannel}, S_LIST_CHANNEL, Connection;
+        ResponseListParcelEx{packet: SListParcelEx}, S_LIST_PARCEL_EX, Connection;
         ResponseLoadHint{packet: SLoadHint}, S_LOAD_HINT, Connection;
         ResponseLoadTopo{packet: SLoadTopo}, S_LOAD_TOPO, Connection;
         ResponseLoadingScreenControlInfo{packet: SLoadingScreenControlInfo}, S_LOADING_SCREEN_CONTROL_INFO, Connection;
         ResponseLoginAccountInfo{packet: SLoginAccountInfo}, S_LOGIN_ACCOUNT_INFO, Connection;
+        ResponseNotifyGuildWarStatusChange{packet: SNotifyGuildWarStatusChange}, S_NOTIFY_GUILD_WAR_STATUS_CHANGE, Connection;
+        ResponseParcelReadRecvStatus{packet: SParcelReadRecvStatus}, S_PARCEL_READ_RECV_STATUS, Connection;
+        ResponsePartyLootingMethod{packet: SPartyLootingMethod}, S_PARTY_LOOTING_METHOD, Connection;
+        ResponsePartyMemberChangeHp{packet: SPartyMemberChangeHp}, S_PARTY_MEMBER_CHANGE_HP, Connection;
+        ResponsePartyMemberIntervalPosUpdate{packet: SPartyMemberIntervalPosUpdate}, S_PARTY_MEMBER_INTERVAL_POS_UPDATE, Connection;
+        ResponsePartyMemberList{packet: SPartyMemberList}, S_PARTY_MEMBER_LIST, Connection;
         ResponsePing{packet: SPing}, S_PING, Connection;
+        ResponsePrivateChannelNotice{packet: SPrivateChannelNotice}, S_PRIVATE_CHANNEL_NOTICE, Connection;
         ResponseRemainPlayTime{packet: SRemainPlayTime}, S_REMAIN_PLAY_TIME, Connection;
+        ResponseRemoveBlockedUser{packet: SRemoveBlockedUser}, S_REMOVE_BLOCKED_USER, Connection;
+        ResponseResultChangeFriendMemo{packet: SResultChangeFriendMemo}, S_RESULT_CHANGE_FRIEND_MEMO, Connection;
+        ResponseReturnParcel{packet: SReturnParcel}, S_RETURN_PARCEL, Connection;
+        ResponseShowParcelMessage{packet: SShowParcelMessage}, S_SHOW_PARCEL_MESSAGE, Connection;
+        ResponseStartGuildWar{packet: SStartGuildWar}, S_START_GUILD_WAR, Connection;
+        ResponseUserBlockList{packet: SUserBlockList}, S_USER_BLOCK_LIST, Connection;
+        ResponseWhisper{packet: SWhisper}, S_WHISPER, Connection;
     }
     // Special messages send between the global and local world and also the connections.
     Special Messages {
@@ -247,6 +422,28 @@ assemble_message! {
         // Messages used in the de-spawn process between the global and local world.
         UserDespawn{connection_local_world_id: EntityId}, Local;
         UserDespawned{user_finalizer: UserFinalizer}, Local;
+
+        // Chat messages of the local channels (say / area etc.) that the global world forwards to the local world.
+        LocalChat{connection_local_world_id: EntityId, channel: ChatChannel, message: String}, Local;
+
+        // Trade requests between users that the global world forwards to the local world both users are in.
+        TradeRequest{connection_local_world_id: EntityId, partner_connection_local_world_id: EntityId}, Local;
+
+        // Status of spawned users that the local worlds report to the global world (used for party members).
+        UserLocationReport{connection_global_world_id: EntityId, zone_id: i32, location: Vec3f}, Global;
+        UserHealthReport{connection_global_world_id: EntityId, hp: i64, max_hp: i64}, Global;
+
+        // Local worlds report cleared dungeons of an user to the global world, which tracks the clears.
+        DungeonCleared{connection_global_world_id: EntityId, zone_id: i32}, Global;
+
+        // The global world informs the local world about a changed guild name / rank of a spawned user.
+        UserGuildChanged{connection_local_world_id: EntityId, guild_name: String, guild_rank: String}, Local;
+
+        // The global world informs the local worlds about the active guild wars and the members of the guilds at war.
+        GuildWarsChanged{guild_wars: GuildWarRegistry}, Local;
+
+        // Local worlds report users that were killed by a member of an enemy guild.
+        GuildWarKill{killer_user_id: i32, victim_user_id: i32}, Global;
     }
 }
 
@@ -1,7 +1,14 @@
 /// Module that hold the definitions for Resources used by the ECS.
+use crate::ecs::component::LocalWorldType;
 use crate::ecs::message::EcsMessage;
+use crate::model::entity::EquippedItem;
+use crate::model::{Class, EquipmentSlot, Race, Stats};
 use async_std::sync::{Receiver, Sender};
+use nalgebra::{Point3, Rotation3};
+use rand::rngs::StdRng;
 use shipyard::EntityId;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 /// Holds the Receiver channel of a world.
@@ -29,6 +36,10 @@ pub enum ShutdownSignalStatus {
     Shutdown,
 }
 
+/// Random number generator of the NPC AI of a local world. Can be seeded, so that the AI behaves
+/// deterministic inside the tests.
+pub struct AiRng(pub StdRng);
+
 /// Keeps track of ticks and times.
 #[derive(Debug)]
 pub struct Tick {
@@ -36,3 +47,529 @@ pub struct Tick {
     pub delta: Duration,
     pub time: Instant,
 }
+
+/// Spatial grid of a local world. Sorts the entities into cells, so that the entities near
+/// a point can be found without checking all entities of the world.
+#[derive(Debug, Default)]
+pub struct VisibilityGrid {
+    cells: HashMap<(i32, i32), HashSet<EntityId>>,
+    entities: HashMap<EntityId, (i32, i32)>,
+}
+
+impl VisibilityGrid {
+    /// Edge length of a cell in game units.
+    const CELL_SIZE: f32 = 1000.0;
+
+    /// Inserts the entity into the cell of the given point or moves it there.
+    pub fn update(&mut self, entity_id: EntityId, point: &Point3<f32>) {
+        let cell = Self::cell_of(point.x, point.y);
+        match self.entities.insert(entity_id, cell) {
+            Some(old_cell) if old_cell == cell => return,
+            Some(old_cell) => self.remove_from_cell(entity_id, old_cell),
+            None => {}
+        }
+        self.cells
+            .entry(cell)
+            .or_insert_with(HashSet::new)
+            .insert(entity_id);
+    }
+
+    /// Removes the entity from the grid.
+    pub fn remove(&mut self, entity_id: EntityId) {
+        if let Some(cell) = self.entities.remove(&entity_id) {
+            self.remove_from_cell(entity_id, cell);
+        }
+    }
+
+    /// Returns all entities inside the cells that overlap the square around the given point.
+    /// The caller needs to check the exact distance.
+    pub fn query(&self, point: &Point3<f32>, range: f32) -> Vec<EntityId> {
+        let (min_x, min_y) = Self::cell_of(point.x - range, point.y - range);
+        let (max_x, max_y) = Self::cell_of(point.x + range, point.y + range);
+
+        let mut entities = Vec::new();
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    entities.extend(cell.iter());
+                }
+            }
+        }
+        entities
+    }
+
+    pub fn contains(&self, entity_id: EntityId) -> bool {
+        self.entities.contains_key(&entity_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    fn remove_from_cell(&mut self, entity_id: EntityId, cell: (i32, i32)) {
+        if let Some(entities) = self.cells.get_mut(&cell) {
+            entities.remove(&entity_id);
+            if entities.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    fn cell_of(x: f32, y: f32) -> (i32, i32) {
+        (
+            (x / Self::CELL_SIZE).floor() as i32,
+            (y / Self::CELL_SIZE).floor() as i32,
+        )
+    }
+}
+
+/// Caches the guild logos in the global world, so that popular logos don't need to be
+/// queried from the database on every request. Entries expire after some time, so that
+/// logos uploaded via the web server are picked up.
+#[derive(Debug, Default)]
+pub struct GuildLogoCache {
+    entries: HashMap<i32, GuildLogoCacheEntry>,
+}
+
+#[derive(Debug)]
+struct GuildLogoCacheEntry {
+    version: i32,
+    data: Arc<Vec<u8>>,
+    cached_at: Instant,
+}
+
+impl GuildLogoCache {
+    /// Time after which a cached logo needs to be queried again.
+    const TTL: Duration = Duration::from_secs(60);
+
+    /// Maximal number of cached logos.
+    const CAPACITY: usize = 1024;
+
+    /// Returns the version and data of the logo of a guild if it's cached and not expired.
+    pub fn get(&self, guild_id: i32) -> Option<(i32, Arc<Vec<u8>>)> {
+        self.entries
+            .get(&guild_id)
+            .filter(|entry| entry.cached_at.elapsed() < Self::TTL)
+            .map(|entry| (entry.version, entry.data.clone()))
+    }
+
+    /// Caches the logo of a guild. Expired entries are evicted once the cache is full. If the
+    /// cache is still full afterwards, the oldest entry is evicted.
+    pub fn insert(&mut self, guild_id: i32, version: i32, data: Vec<u8>) -> Arc<Vec<u8>> {
+        if self.entries.len() >= Self::CAPACITY && !self.entries.contains_key(&guild_id) {
+            self.entries
+                .retain(|_id, entry| entry.cached_at.elapsed() < Self::TTL);
+            if self.entries.len() >= Self::CAPACITY {
+                let oldest_id = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_id, entry)| entry.cached_at)
+                    .map(|(id, _entry)| *id);
+                if let Some(oldest_id) = oldest_id {
+                    self.entries.remove(&oldest_id);
+                }
+            }
+        }
+
+        let data = Arc::new(data);
+        self.entries.insert(
+            guild_id,
+            GuildLogoCacheEntry {
+                version,
+                data: data.clone(),
+                cached_at: Instant::now(),
+            },
+        );
+        data
+    }
+
+    /// Removes the logo of a guild from the cache.
+    pub fn remove(&mut self, guild_id: i32) {
+        self.entries.remove(&guild_id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Guilds that are at war with each other and the members of those guilds. The global world
+/// keeps it up to date and sends a copy to every local world.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GuildWarRegistry {
+    pub wars: HashSet<(i32, i32)>, // Guild IDs of the guilds at war, the lower ID first
+    pub members: HashMap<i32, i32>, // User ID to guild ID of the members of guilds at war
+}
+
+impl GuildWarRegistry {
+    /// Adds an active war between two guilds.
+    pub fn add_war(&mut self, guild_id: i32, other_guild_id: i32) {
+        self.wars.insert(Self::key(guild_id, other_guild_id));
+    }
+
+    /// Returns true if the guilds of both users are at war with each other.
+    pub fn are_enemies(&self, user_id: i32, other_user_id: i32) -> bool {
+        match (self.members.get(&user_id), self.members.get(&other_user_id)) {
+            (Some(guild_id), Some(other_guild_id)) => {
+                self.wars.contains(&Self::key(*guild_id, *other_guild_id))
+            }
+            _ => false,
+        }
+    }
+
+    fn key(guild_id: i32, other_guild_id: i32) -> (i32, i32) {
+        if guild_id < other_guild_id {
+            (guild_id, other_guild_id)
+        } else {
+            (other_guild_id, guild_id)
+        }
+    }
+}
+
+/// Bundles all registries that are read out of the datacenter, so that they can be handed
+/// to newly created worlds.
+#[derive(Clone, Debug, Default)]
+pub struct GameData {
+    pub zones: ZoneRegistry,
+    pub items: ItemRegistry,
+    pub skills: SkillRegistry,
+    pub abnormalities: AbnormalityRegistry,
+    pub npcs: NpcRegistry,
+    pub territories: TerritoryRegistry,
+}
+
+/// Holds the static information of all zones. Created once from the datacenter
+/// and shared between all worlds (cloning is cheap).
+#[derive(Clone, Debug, Default)]
+pub struct ZoneRegistry {
+    zones: Arc<HashMap<i32, Zone>>,
+}
+
+impl ZoneRegistry {
+    pub fn new(zones: Vec<Zone>) -> Self {
+        Self {
+            zones: Arc::new(zones.into_iter().map(|zone| (zone.id, zone)).collect()),
+        }
+    }
+
+    /// Returns the zone with the given ID.
+    pub fn get(&self, zone_id: i32) -> Option<&Zone> {
+        self.zones.get(&zone_id)
+    }
+
+    /// Returns the type of the local world that needs to be created for the given zone.
+    /// Zones that are not known are handled as fields.
+    pub fn zone_type(&self, zone_id: i32) -> LocalWorldType {
+        self.get(zone_id)
+            .map(|zone| zone.zone_type.clone())
+            .unwrap_or(LocalWorldType::Field)
+    }
+
+    /// Returns the maximal number of users of a channel in the given zone. Uses the given
+    /// default cap if the zone doesn't define a capacity. A default cap of 0 means uncapped.
+    pub fn channel_user_cap(&self, zone_id: i32, default_cap: u32) -> usize {
+        match self.get(zone_id) {
+            Some(zone) if zone.channel_capacity > 0 => zone.channel_capacity as usize,
+            _ if default_cap == 0 => usize::MAX,
+            _ => default_cap as usize,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.zones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+}
+
+/// Static information about a zone (continent).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Zone {
+    pub id: i32,
+    pub zone_type: LocalWorldType,
+    pub channel_capacity: u32, // 0 = no capacity defined
+    pub topology_id: i32,
+    pub spawn_points: Vec<SpawnPoint>,
+}
+
+/// A location where users can be spawned in a zone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpawnPoint {
+    pub point: Point3<f32>,
+    pub rotation: Rotation3<f32>,
+}
+
+/// Holds the templates of all items. Created once from the datacenter
+/// and shared between all worlds (cloning is cheap).
+#[derive(Clone, Debug, Default)]
+pub struct ItemRegistry {
+    items: Arc<HashMap<i32, ItemTemplate>>,
+}
+
+impl ItemRegistry {
+    pub fn new(items: Vec<ItemTemplate>) -> Self {
+        Self {
+            items: Arc::new(items.into_iter().map(|item| (item.id, item)).collect()),
+        }
+    }
+
+    /// Returns the item template with the given ID.
+    pub fn get(&self, template_id: i32) -> Option<&ItemTemplate> {
+        self.items.get(&template_id)
+    }
+
+    /// Sums up the stats of the given equipped items. Items with an unknown template don't
+    /// provide any stats.
+    pub fn equipment_stats<'a, I>(&self, equipped_items: I) -> Stats
+    where
+        I: IntoIterator<Item = &'a EquippedItem>,
+    {
+        let mut stats = Stats::default();
+        for equipped_item in equipped_items {
+            if let Some(template) = self.get(equipped_item.template_id) {
+                stats += template.stats;
+            }
+        }
+        stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Static information about an item.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ItemTemplate {
+    pub id: i32,
+    pub max_stack: i32, // 1 = not stackable
+    pub destroyable: bool,
+    pub equipment_slots: Vec<EquipmentSlot>, // Empty if the item can't be equipped
+    pub required_level: i32,
+    pub required_classes: Vec<Class>, // Empty if every class can use the item
+    pub required_races: Vec<Race>,    // Empty if every race can use the item
+    pub stats: Stats,
+}
+
+/// Holds the templates of all skills. Created once from the datacenter
+/// and shared between all worlds (cloning is cheap).
+#[derive(Clone, Debug, Default)]
+pub struct SkillRegistry {
+    skills: Arc<HashMap<i32, SkillTemplate>>,
+}
+
+impl SkillRegistry {
+    pub fn new(skills: Vec<SkillTemplate>) -> Self {
+        Self {
+            skills: Arc::new(skills.into_iter().map(|skill| (skill.id, skill)).collect()),
+        }
+    }
+
+    /// Returns the skill template with the given ID.
+    pub fn get(&self, skill_id: i32) -> Option<&SkillTemplate> {
+        self.skills.get(&skill_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.skills.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.skills.is_empty()
+    }
+}
+
+/// Static information about a skill.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SkillTemplate {
+    pub id: i32,
+    pub class: Option<Class>, // None if every class can use the skill
+    pub mp_cost: i32,
+    pub cooldown: Duration,
+    pub duration: Duration, // Time until the action of the skill ends
+    pub damage: i32,        // 0 if the skill doesn't hit anyone
+    pub range: f32,
+    pub self_abnormalities: Vec<i32>, // Applied to the user of the skill
+    pub target_abnormalities: Vec<i32>, // Applied to every target the skill hits
+}
+
+/// Holds the templates of all abnormalities (buffs and debuffs). Created once from the datacenter
+/// and shared between all worlds (cloning is cheap).
+#[derive(Clone, Debug, Default)]
+pub struct AbnormalityRegistry {
+    abnormalities: Arc<HashMap<i32, AbnormalityTemplate>>,
+}
+
+impl AbnormalityRegistry {
+    pub fn new(abnormalities: Vec<AbnormalityTemplate>) -> Self {
+        Self {
+            abnormalities: Arc::new(
+                abnormalities
+                    .into_iter()
+                    .map(|abnormality| (abnormality.id, abnormality))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns the abnormality template with the given ID.
+    pub fn get(&self, abnormality_id: i32) -> Option<&AbnormalityTemplate> {
+        self.abnormalities.get(&abnormality_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.abnormalities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.abnormalities.is_empty()
+    }
+}
+
+/// Static information about an abnormality. Abnormalities with a period change the health of
+/// their target every period by the given amount per stack (negative values deal damage).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AbnormalityTemplate {
+    pub id: i32,
+    pub duration: Duration,
+    pub max_stacks: i32,
+    pub period: Duration, // Zero if the abnormality has no periodic effect
+    pub hp_per_period: i64,
+    pub is_persistent: bool, // Survives a relog of the user
+}
+
+/// Holds the templates of all NPCs (including monsters). Created once from the datacenter
+/// and shared between all worlds (cloning is cheap).
+#[derive(Clone, Debug, Default)]
+pub struct NpcRegistry {
+    npcs: Arc<HashMap<(i32, i32), NpcTemplate>>,
+}
+
+impl NpcRegistry {
+    pub fn new(npcs: Vec<NpcTemplate>) -> Self {
+        Self {
+            npcs: Arc::new(
+                npcs.into_iter()
+                    .map(|npc| ((npc.hunting_zone_id, npc.id), npc))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns the NPC template with the given ID inside the given hunting zone.
+    pub fn get(&self, hunting_zone_id: i32, template_id: i32) -> Option<&NpcTemplate> {
+        self.npcs.get(&(hunting_zone_id, template_id))
+    }
+
+    pub fn len(&self) -> usize {
+        self.npcs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.npcs.is_empty()
+    }
+}
+
+/// Static information about a NPC. The IDs of the templates are only unique inside their
+/// hunting zone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NpcTemplate {
+    pub hunting_zone_id: i32,
+    pub id: i32,
+    pub level: i32,
+    pub max_hp: i64,
+    pub walk_speed: i16,
+    pub run_speed: i16,
+    pub is_villager: bool, // Villagers can't be attacked
+    pub attack: i32,
+    pub defence: i32,
+    pub aggro_range: f32, // 0 if the NPC only fights back once it's attacked
+    pub leash_range: f32, // Distance to the spawn point at which the NPC gives up a fight, 0 if never
+    pub exp: i64,         // Experience the killer of the NPC receives
+    pub is_boss: bool,    // Killing the boss of a dungeon clears the dungeon
+    pub skills: Vec<NpcSkill>,
+}
+
+/// A skill of a NPC. The AI of the NPC chooses randomly between the usable skills, skills with a
+/// higher weight are chosen more often.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NpcSkill {
+    pub id: i32,
+    pub damage: i32,
+    pub range: f32,
+    pub cooldown: Duration,
+    pub weight: u32,
+}
+
+/// Holds the spawn tables of the NPCs of all zones. Created once from the datacenter
+/// and shared between all worlds (cloning is cheap).
+#[derive(Clone, Debug, Default)]
+pub struct TerritoryRegistry {
+    territories: Arc<HashMap<i32, Vec<TerritoryGroup>>>,
+}
+
+impl TerritoryRegistry {
+    pub fn new(territories: Vec<TerritoryGroup>) -> Self {
+        let mut zones: HashMap<i32, Vec<TerritoryGroup>> = HashMap::new();
+        for territory in territories {
+            zones.entry(territory.zone_id).or_default().push(territory);
+        }
+        Self {
+            territories: Arc::new(zones),
+        }
+    }
+
+    /// Returns the territory groups of the given zone.
+    pub fn get(&self, zone_id: i32) -> &[TerritoryGroup] {
+        self.territories
+            .get(&zone_id)
+            .map(|territories| territories.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Returns the number of territory groups of all zones.
+    pub fn len(&self) -> usize {
+        self.territories
+            .values()
+            .map(|territories| territories.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.territories.is_empty()
+    }
+}
+
+/// A group of NPC spawns inside a zone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerritoryGroup {
+    pub id: i32,
+    pub zone_id: i32,
+    pub spawns: Vec<NpcSpawnTemplate>,
+}
+
+/// Static information about the spawn of a single NPC. The NPC is spawned again once the respawn
+/// time passed after it's death.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NpcSpawnTemplate {
+    pub hunting_zone_id: i32,
+    pub template_id: i32,
+    pub point: Point3<f32>,
+    pub rotation: Rotation3<f32>,
+    pub respawn_time: Duration,
+    pub patrol_points: Vec<Point3<f32>>, // Empty if the NPC stays at it's spawn point
+}
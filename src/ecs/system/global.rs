@@ -1,19 +1,46 @@
 /// All systems used by the global world
+mod block_manager;
+mod channel_manager;
+mod chat_manager;
 mod connection_manager;
+mod dungeon_manager;
+mod friend_manager;
+mod guild_manager;
+mod guild_war_manager;
 mod local_world_manager;
+mod matching_manager;
+mod parcel_manager;
+mod party_manager;
+mod private_channel_manager;
 mod settings_manager;
+mod trade_manager;
 mod user_manager;
 mod user_spawner;
 
+pub use block_manager::block_manager_system;
+pub use channel_manager::channel_manager_system;
+pub use chat_manager::chat_manager_system;
 pub use connection_manager::connection_manager_system;
+pub use dungeon_manager::dungeon_manager_system;
+pub use friend_manager::friend_manager_system;
+pub use guild_manager::guild_manager_system;
+pub use guild_war_manager::guild_war_manager_system;
 pub use local_world_manager::local_world_manager_system;
+pub use matching_manager::matching_manager_system;
+pub use parcel_manager::{parcel_manager_system, send_system_parcel};
+pub use party_manager::party_manager_system;
+pub use private_channel_manager::private_channel_manager_system;
 pub use settings_manager::settings_manager_system;
+pub use trade_manager::trade_manager_system;
 pub use user_manager::user_manager_system;
 pub use user_spawner::user_spawner_system;
 
-use crate::ecs::component::GlobalConnection;
+use crate::ecs::component::{
+    BlockList, Chatter, GlobalConnection, GlobalUserSpawn, UserSpawnStatus,
+};
 use crate::ecs::message::EcsMessage;
 use crate::ecs::system::send_message;
+use shipyard::*;
 use tracing::{debug, error};
 
 // FIXME refactor this and the local version with traits if possible. Maybe merge local and global Connection and refactor some global Connection variables into it's own Component
@@ -33,3 +60,53 @@ where
         error!("Message didn't had a global world ID attached");
     }
 }
+
+/// Finds a spawned user by it's name (case insensitive).
+pub fn find_online_user_by_name(
+    user_name: &str,
+    spawns: &View<GlobalUserSpawn>,
+    chatters: &ViewMut<Chatter>,
+) -> Option<EntityId> {
+    let user_name = user_name.to_lowercase();
+    (spawns, chatters)
+        .iter()
+        .with_id()
+        .filter(|(_id, (spawn, chatter))| {
+            spawn.status == UserSpawnStatus::Spawned
+                && !spawn.marked_for_deletion
+                && chatter.user_name.to_lowercase() == user_name
+        })
+        .map(|(id, _)| id)
+        .next()
+}
+
+/// Finds the connection of an online user.
+pub fn find_online_user(user_id: i32, spawns: &View<GlobalUserSpawn>) -> Option<EntityId> {
+    spawns
+        .iter()
+        .with_id()
+        .filter(|(_id, spawn)| spawn.user_id == user_id && !spawn.marked_for_deletion)
+        .map(|(id, _)| id)
+        .next()
+}
+
+/// Checks if one of the two users has blocked the other one. Interactions between users
+/// (whispers, invites, requests etc.) must not be delivered if this returns true.
+pub fn is_blocked<'a, T>(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    other_connection_global_world_id: EntityId,
+    other_user_id: i32,
+    block_lists: T,
+) -> bool
+where
+    T: shipyard::Get<Out = &'a BlockList> + Copy,
+{
+    let has_blocked = |id: EntityId, blocked_user_id: i32| {
+        block_lists.try_get(id).map_or(false, |block_list| {
+            block_list.blocked_users.contains(&blocked_user_id)
+        })
+    };
+    has_blocked(connection_global_world_id, other_user_id)
+        || has_blocked(other_connection_global_world_id, user_id)
+}
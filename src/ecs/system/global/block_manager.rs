@@ -0,0 +1,468 @@
+use crate::ecs::component::{BlockList, GlobalConnection};
+use crate::ecs::message::Message::{ResponseAddBlockedUser, ResponseRemoveBlockedUser};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::system::global::send_message_to_connection;
+use crate::model::entity::BlockedUser;
+use crate::model::repository::{blocked_user, user};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use chrono::Utc;
+use shipyard::*;
+use sqlx::PgPool;
+use tracing::{debug, error, info_span};
+
+/// Maximal number of users an user can block.
+const MAX_BLOCKED_USERS: i64 = 100;
+
+/// Maximal length of the memo of a blocked user.
+const MAX_MEMO_LENGTH: usize = 64;
+
+/// The block manager handles the block list of the users. The block list is loaded when the
+/// user is selected. Systems that deliver interactions between users need to check it with
+/// `is_blocked()`.
+pub fn block_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    mut block_lists: ViewMut<BlockList>,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestBlockUser {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_block_user(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &mut block_lists,
+                    &pool,
+                ) {
+                    error!("Ignoring block user request: {:?}", e);
+                }
+            }
+            Message::RequestRemoveBlockedUser {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_remove_blocked_user(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &mut block_lists,
+                    &pool,
+                ) {
+                    error!("Ignoring remove blocked user request: {:?}", e);
+                }
+            }
+            Message::RequestEditBlockedUserMemo {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_edit_blocked_user_memo(*user_id, &packet, &pool) {
+                    error!("Ignoring edit blocked user memo request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_block_user(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CBlockUser,
+    connections: &View<GlobalConnection>,
+    block_lists: &mut ViewMut<BlockList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestBlockUser incoming");
+
+    let mut block_list = block_lists
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find block list {:?}",
+            connection_global_world_id
+        ))?;
+
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let target = user::get_by_name(&mut conn, &packet.name)
+            .await
+            .context(format!("Can't find user {}", packet.name))?;
+        ensure!(target.id != user_id, "User {} can't block itself", user_id);
+        ensure!(
+            !blocked_user::is_blocked(&mut conn, user_id, target.id).await?,
+            "User {} is already blocked by user {}",
+            target.id,
+            user_id
+        );
+        ensure!(
+            blocked_user::get_blocked_user_count(&mut conn, user_id).await? < MAX_BLOCKED_USERS,
+            "User {} already blocked {} users",
+            user_id,
+            MAX_BLOCKED_USERS
+        );
+
+        let entry = blocked_user::create(
+            &mut conn,
+            &BlockedUser {
+                user_id,
+                blocked_user_id: target.id,
+                memo: "".to_string(),
+                created_at: Utc::now(),
+            },
+        )
+        .await
+        .context(format!("Can't block user {}", target.id))?;
+
+        block_list.blocked_users.insert(target.id);
+        send_message_to_connection(
+            assemble_add_blocked_user(
+                connection_global_world_id,
+                &target.name,
+                &entry.memo,
+                target.id,
+            ),
+            connections,
+        );
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+fn handle_remove_blocked_user(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CRemoveBlockedUser,
+    connections: &View<GlobalConnection>,
+    block_lists: &mut ViewMut<BlockList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestRemoveBlockedUser incoming");
+
+    let mut block_list = block_lists
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find block list {:?}",
+            connection_global_world_id
+        ))?;
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        ensure!(
+            blocked_user::is_blocked(&mut conn, user_id, packet.user_id).await?,
+            "User {} is not blocked by user {}",
+            packet.user_id,
+            user_id
+        );
+        blocked_user::delete(&mut conn, user_id, packet.user_id).await?;
+
+        Ok::<(), anyhow::Error>(())
+    })?;
+
+    block_list.blocked_users.remove(&packet.user_id);
+    send_message_to_connection(
+        assemble_remove_blocked_user(connection_global_world_id, packet.user_id),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_edit_blocked_user_memo(
+    user_id: i32,
+    packet: &CEditBlockedUserMemo,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestEditBlockedUserMemo incoming");
+
+    ensure!(
+        packet.memo.chars().count() <= MAX_MEMO_LENGTH,
+        "Blocked user memo is longer than {} characters",
+        MAX_MEMO_LENGTH
+    );
+
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let mut entry = blocked_user::get(&mut conn, user_id, packet.user_id)
+            .await
+            .context(format!(
+                "User {} is not blocked by user {}",
+                packet.user_id, user_id
+            ))?;
+        entry.memo = packet.memo.clone();
+        blocked_user::update(&mut conn, &entry)
+            .await
+            .context("Can't update blocked user memo")?;
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+fn assemble_add_blocked_user(
+    connection_global_world_id: EntityId,
+    name: &str,
+    memo: &str,
+    user_id: i32,
+) -> EcsMessage {
+    Box::new(ResponseAddBlockedUser {
+        connection_global_world_id,
+        packet: SAddBlockedUser {
+            name: name.to_string(),
+            memo: memo.to_string(),
+            user_id,
+        },
+    })
+}
+
+fn assemble_remove_blocked_user(connection_global_world_id: EntityId, user_id: i32) -> EcsMessage {
+    Box::new(ResponseRemoveBlockedUser {
+        connection_global_world_id,
+        packet: SRemoveBlockedUser { user_id },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
+    use crate::ecs::system::global::is_blocked;
+    use crate::model::entity::User;
+    use crate::model::repository::account;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::tests::db_test;
+    use async_std::sync::{channel, Receiver};
+    use std::time::Instant;
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(pool);
+        world
+    }
+
+    async fn create_user(pool: &PgPool, num: i32) -> Result<User> {
+        let mut conn = pool.acquire().await?;
+        let account = account::create(&mut conn, &get_default_account(num)).await?;
+        user::create(&mut conn, &get_default_user(&account, num)).await
+    }
+
+    fn add_user(world: &World) -> (EntityId, Receiver<EcsMessage>) {
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut block_lists: ViewMut<BlockList>| {
+                entities.add_entity(
+                    (&mut connections, &mut block_lists),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        BlockList::default(),
+                    ),
+                )
+            },
+        );
+
+        (connection_global_world_id, rx_channel)
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(block_manager_system);
+        world.run(cleaner_system);
+    }
+
+    fn block_user(world: &World, connection_global_world_id: EntityId, user: &User, name: &str) {
+        run_message(
+            world,
+            Message::RequestBlockUser {
+                connection_global_world_id,
+                account_id: user.account_id,
+                user_id: user.id,
+                packet: CBlockUser {
+                    name: name.to_string(),
+                },
+            },
+        );
+    }
+
+    fn get_block_list(world: &World, connection_global_world_id: EntityId) -> BlockList {
+        world.run(|block_lists: View<BlockList>| block_lists[connection_global_world_id].clone())
+    }
+
+    #[test]
+    fn test_block_user() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let other = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool.clone());
+            let (user_id, user_rx) = add_user(&world);
+
+            block_user(&world, user_id, &user, "TESTUSER-1");
+
+            match &*user_rx.try_recv()? {
+                Message::ResponseAddBlockedUser { packet, .. } => {
+                    assert_eq!(packet.name, other.name);
+                    assert_eq!(packet.user_id, other.id);
+                }
+                _ => panic!("Message is not a ResponseAddBlockedUser message"),
+            }
+            assert!(get_block_list(&world, user_id)
+                .blocked_users
+                .contains(&other.id));
+
+            // Users can't be blocked twice and users can't block themselves.
+            block_user(&world, user_id, &user, "testuser-1");
+            block_user(&world, user_id, &user, "testuser-0");
+            block_user(&world, user_id, &user, "unknown");
+            assert!(user_rx.is_empty());
+
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                assert!(blocked_user::is_blocked(&mut conn, user.id, other.id).await?);
+                assert_eq!(
+                    blocked_user::get_blocked_user_count(&mut conn, user.id).await?,
+                    1
+                );
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_remove_blocked_user() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let other = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool.clone());
+            let (user_id, user_rx) = add_user(&world);
+            block_user(&world, user_id, &user, &other.name);
+            user_rx.try_recv()?;
+
+            run_message(
+                &world,
+                Message::RequestRemoveBlockedUser {
+                    connection_global_world_id: user_id,
+                    account_id: user.account_id,
+                    user_id: user.id,
+                    packet: CRemoveBlockedUser { user_id: other.id },
+                },
+            );
+
+            match &*user_rx.try_recv()? {
+                Message::ResponseRemoveBlockedUser { packet, .. } => {
+                    assert_eq!(packet.user_id, other.id);
+                }
+                _ => panic!("Message is not a ResponseRemoveBlockedUser message"),
+            }
+            assert!(get_block_list(&world, user_id).blocked_users.is_empty());
+
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                assert!(!blocked_user::is_blocked(&mut conn, user.id, other.id).await?);
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_edit_blocked_user_memo() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let other = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool.clone());
+            let (user_id, _user_rx) = add_user(&world);
+            block_user(&world, user_id, &user, &other.name);
+
+            let edit_memo = |memo: String| {
+                run_message(
+                    &world,
+                    Message::RequestEditBlockedUserMemo {
+                        connection_global_world_id: user_id,
+                        account_id: user.account_id,
+                        user_id: user.id,
+                        packet: CEditBlockedUserMemo {
+                            user_id: other.id,
+                            memo,
+                        },
+                    },
+                )
+            };
+            edit_memo("Gold seller".to_string());
+            edit_memo("a".repeat(65));
+
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                let blocked_user = blocked_user::get(&mut conn, user.id, other.id).await?;
+                assert_eq!(blocked_user.memo, "Gold seller");
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_is_blocked() {
+        let world = World::new();
+        let (user_id, _user_rx) = add_user(&world);
+        let (other_id, _other_rx) = add_user(&world);
+        let (third_id, _third_rx) = add_user(&world);
+        world.run(|mut block_lists: ViewMut<BlockList>| {
+            block_lists[user_id].blocked_users.insert(2);
+        });
+
+        world.run(|block_lists: View<BlockList>| {
+            assert!(is_blocked(user_id, 1, other_id, 2, &block_lists));
+            assert!(is_blocked(other_id, 2, user_id, 1, &block_lists));
+            assert!(!is_blocked(other_id, 2, third_id, 3, &block_lists));
+        });
+    }
+}
@@ -0,0 +1,418 @@
+use crate::config::Configuration;
+use crate::ecs::component::{
+    GlobalConnection, GlobalUserSpawn, LocalWorld, LocalWorldType, UserSpawnStatus,
+};
+use crate::ecs::message::Message::{ResponseCancelSelectChannel, ResponseListChannel};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::GameData;
+use crate::ecs::system::global::send_message_to_connection;
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use shipyard::*;
+use tracing::{debug, error, info, info_span};
+
+/// The channel manager lists the channels of a field and handles the channel change requests
+/// of the users. The local world manager does the actual relocation of the user.
+pub fn channel_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    mut spawns: ViewMut<GlobalUserSpawn>,
+    local_worlds: View<LocalWorld>,
+    config: UniqueView<Configuration>,
+    game_data: UniqueView<GameData>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestListChannel {
+                connection_global_world_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_list_channel(
+                    *connection_global_world_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &local_worlds,
+                    &config,
+                    &game_data,
+                ) {
+                    error!("Ignoring list channel request: {:?}", e);
+                }
+            }
+            Message::RequestSelectChannel {
+                connection_global_world_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_select_channel(
+                    *connection_global_world_id,
+                    &packet,
+                    &connections,
+                    &mut spawns,
+                    &local_worlds,
+                    &config,
+                    &game_data,
+                ) {
+                    error!("Ignoring select channel request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_list_channel(
+    connection_global_world_id: EntityId,
+    packet: &CListChannel,
+    connections: &View<GlobalConnection>,
+    spawns: &ViewMut<GlobalUserSpawn>,
+    local_worlds: &View<LocalWorld>,
+    config: &UniqueView<Configuration>,
+    game_data: &UniqueView<GameData>,
+) -> Result<()> {
+    debug!("Message::RequestListChannel incoming");
+
+    let spawn = spawns.try_get(connection_global_world_id).context(format!(
+        "Can't find user spawn {:?}",
+        connection_global_world_id
+    ))?;
+
+    let user_cap = game_data
+        .zones
+        .channel_user_cap(spawn.zone_id, config.game.channel_user_cap);
+    let mut channels = field_channels(spawn.zone_id, local_worlds)
+        .into_iter()
+        .map(|world| SListChannelEntry {
+            channel: world.channel_num.unwrap_or_default(),
+            density: calculate_density(world.users.len(), user_cap),
+        })
+        .collect::<Vec<SListChannelEntry>>();
+    channels.sort_by_key(|entry| entry.channel);
+
+    send_message_to_connection(
+        assemble_list_channel(
+            connection_global_world_id,
+            packet.unk1,
+            spawn.zone_id,
+            channels,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_select_channel(
+    connection_global_world_id: EntityId,
+    packet: &CSelectChannel,
+    connections: &View<GlobalConnection>,
+    spawns: &mut ViewMut<GlobalUserSpawn>,
+    local_worlds: &View<LocalWorld>,
+    config: &UniqueView<Configuration>,
+    game_data: &UniqueView<GameData>,
+) -> Result<()> {
+    debug!("Message::RequestSelectChannel incoming");
+
+    let mut spawn = spawns.try_get(connection_global_world_id).context(format!(
+        "Can't find user spawn {:?}",
+        connection_global_world_id
+    ))?;
+
+    ensure!(
+        spawn.status == UserSpawnStatus::Spawned,
+        "User {:?} is not spawned and can't change the channel",
+        connection_global_world_id
+    );
+
+    let user_cap = game_data
+        .zones
+        .channel_user_cap(spawn.zone_id, config.game.channel_user_cap);
+    let has_space = field_channels(spawn.zone_id, local_worlds)
+        .into_iter()
+        .any(|world| world.channel_num == Some(packet.channel) && world.users.len() < user_cap);
+
+    if packet.zone != spawn.zone_id || spawn.channel_num == Some(packet.channel) || !has_space {
+        debug!(
+            "Can't change channel from {:?} to {} in zone {}",
+            spawn.channel_num, packet.channel, packet.zone
+        );
+        send_message_to_connection(
+            assemble_cancel_select_channel(connection_global_world_id),
+            connections,
+        );
+        return Ok(());
+    }
+
+    info!(
+        "User {:?} changes from channel {:?} to channel {}",
+        connection_global_world_id, spawn.channel_num, packet.channel
+    );
+
+    spawn.channel_num = Some(packet.channel);
+    spawn.status = UserSpawnStatus::Relocating;
+    spawn.is_relocating = true;
+
+    Ok(())
+}
+
+/// Returns all channels of the field with the given zone ID.
+fn field_channels<'a>(zone_id: i32, local_worlds: &'a View<LocalWorld>) -> Vec<&'a LocalWorld> {
+    local_worlds
+        .iter()
+        .filter(|world| {
+            world.zone_id == zone_id
+                && world.instance_type == LocalWorldType::Field
+                && world.channel_num.is_some()
+        })
+        .collect()
+}
+
+/// Calculates how full a channel is in percent.
+fn calculate_density(users: usize, user_cap: usize) -> i32 {
+    if user_cap == 0 {
+        return 100;
+    }
+    (users * 100 / user_cap).min(100) as i32
+}
+
+fn assemble_list_channel(
+    connection_global_world_id: EntityId,
+    unk1: i32,
+    zone_id: i32,
+    channels: Vec<SListChannelEntry>,
+) -> EcsMessage {
+    Box::new(ResponseListChannel {
+        connection_global_world_id,
+        packet: SListChannel {
+            channels,
+            unk1,
+            zone: zone_id,
+        },
+    })
+}
+
+fn assemble_cancel_select_channel(connection_global_world_id: EntityId) -> EcsMessage {
+    Box::new(ResponseCancelSelectChannel {
+        connection_global_world_id,
+        packet: SCancelSelectChannel {},
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::{Zone, ZoneRegistry};
+    use async_std::sync::{channel, Receiver};
+    use async_std::task;
+    use std::collections::HashSet;
+    use std::time::Instant;
+
+    fn setup() -> (World, EntityId, Receiver<EcsMessage>) {
+        let world = World::new();
+        world.add_unique(Configuration::default());
+        world.add_unique(GameData {
+            zones: ZoneRegistry::new(vec![Zone {
+                id: 5,
+                zone_type: LocalWorldType::Field,
+                channel_capacity: 4,
+                topology_id: 5,
+                spawn_points: vec![],
+            }]),
+            ..Default::default()
+        });
+
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut spawns: ViewMut<GlobalUserSpawn>| {
+                entities.add_entity(
+                    (&mut connections, &mut spawns),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        GlobalUserSpawn {
+                            user_id: 1,
+                            account_id: 1,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_local_world_id: None,
+                            local_world_id: None,
+                            local_world_channel: None,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: Some(1),
+                            is_relocating: false,
+                        },
+                    ),
+                )
+            },
+        );
+
+        (world, connection_global_world_id, rx_channel)
+    }
+
+    fn add_channel(world: &World, channel_num: i32, user_count: usize) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut local_worlds: ViewMut<LocalWorld>| {
+                let users = (0..user_count)
+                    .map(|_| entities.add_entity((), ()))
+                    .collect::<HashSet<EntityId>>();
+                let (local_world_channel, _) = channel(1);
+                entities.add_entity(
+                    &mut local_worlds,
+                    LocalWorld {
+                        instance_type: LocalWorldType::Field,
+                        channel_num: Some(channel_num),
+                        zone_id: 5,
+                        channel: local_world_channel,
+                        join_handle: task::spawn(async { Ok(()) }),
+                        users,
+                        deadline: None,
+                        group_id: None,
+                    },
+                );
+            },
+        );
+    }
+
+    fn add_select_channel_request(
+        world: &World,
+        connection_global_world_id: EntityId,
+        channel_num: i32,
+    ) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::RequestSelectChannel {
+                        connection_global_world_id,
+                        account_id: 1,
+                        user_id: 1,
+                        packet: CSelectChannel {
+                            unk1: 1,
+                            zone: 5,
+                            channel: channel_num,
+                        },
+                    }),
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_list_channel() -> Result<()> {
+        let (world, connection_global_world_id, rx_channel) = setup();
+        add_channel(&world, 2, 1);
+        add_channel(&world, 1, 2);
+
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::RequestListChannel {
+                        connection_global_world_id,
+                        account_id: 1,
+                        user_id: 1,
+                        packet: CListChannel { unk1: 1, zone: 5 },
+                    }),
+                );
+            },
+        );
+
+        world.run(channel_manager_system);
+
+        match &*rx_channel.try_recv()? {
+            Message::ResponseListChannel { packet, .. } => {
+                assert_eq!(packet.zone, 5);
+                assert_eq!(packet.channels.len(), 2);
+                assert_eq!(packet.channels[0].channel, 1);
+                assert_eq!(packet.channels[0].density, 50);
+                assert_eq!(packet.channels[1].channel, 2);
+                assert_eq!(packet.channels[1].density, 25);
+            }
+            _ => panic!("Message is not a ResponseListChannel message"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_channel() -> Result<()> {
+        let (world, connection_global_world_id, rx_channel) = setup();
+        add_channel(&world, 1, 1);
+        add_channel(&world, 2, 3);
+
+        add_select_channel_request(&world, connection_global_world_id, 2);
+        world.run(channel_manager_system);
+
+        assert!(rx_channel.is_empty());
+        world.run(|spawns: View<GlobalUserSpawn>| {
+            let spawn = spawns.try_get(connection_global_world_id)?;
+            assert_eq!(spawn.status, UserSpawnStatus::Relocating);
+            assert_eq!(spawn.channel_num, Some(2));
+            assert!(spawn.is_relocating);
+
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_full_channel() -> Result<()> {
+        let (world, connection_global_world_id, rx_channel) = setup();
+        add_channel(&world, 1, 1);
+        add_channel(&world, 2, 4);
+
+        add_select_channel_request(&world, connection_global_world_id, 2);
+        world.run(channel_manager_system);
+
+        match &*rx_channel.try_recv()? {
+            Message::ResponseCancelSelectChannel { .. } => {}
+            _ => panic!("Message is not a ResponseCancelSelectChannel message"),
+        }
+        world.run(|spawns: View<GlobalUserSpawn>| {
+            let spawn = spawns.try_get(connection_global_world_id)?;
+            assert_eq!(spawn.status, UserSpawnStatus::Spawned);
+            assert_eq!(spawn.channel_num, Some(1));
+            assert!(!spawn.is_relocating);
+
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_unknown_channel() -> Result<()> {
+        let (world, connection_global_world_id, rx_channel) = setup();
+        add_channel(&world, 1, 1);
+
+        add_select_channel_request(&world, connection_global_world_id, 3);
+        world.run(channel_manager_system);
+
+        match &*rx_channel.try_recv()? {
+            Message::ResponseCancelSelectChannel { .. } => {}
+            _ => panic!("Message is not a ResponseCancelSelectChannel message"),
+        }
+        world.run(|spawns: View<GlobalUserSpawn>| {
+            let spawn = spawns.try_get(connection_global_world_id)?;
+            assert_eq!(spawn.status, UserSpawnStatus::Spawned);
+
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        Ok(())
+    }
+}
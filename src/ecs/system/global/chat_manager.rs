@@ -0,0 +1,1058 @@
+use crate::ecs::component::{
+    BlockList, Chatter, GlobalConnection, GlobalUserSpawn, Party, PartyMember, PrivateChannels,
+    UserGuild, UserSpawnStatus,
+};
+use crate::ecs::message::Message::{LocalChat, ResponseChat, ResponseWhisper};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::system::global::{
+    find_online_user_by_name, is_blocked, send_message_to_connection,
+};
+use crate::ecs::system::send_message;
+use crate::model::ChatChannel;
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use shipyard::*;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info_span};
+
+/// Maximal length of a chat message (including the HTML markup of the client).
+const MAX_MESSAGE_LENGTH: usize = 512;
+
+/// Number of messages an user can send inside one rate limit window.
+const RATE_LIMIT_MESSAGES: u32 = 5;
+
+/// Length of a rate limit window.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(5);
+
+/// The chat manager validates the chat messages of the users and delivers the messages of the
+/// global channels. Messages of the local channels (say / area etc.) are forwarded to the
+/// local world of the user. Messages are not delivered to users that blocked the author.
+/// Party and raid messages are delivered to the members of the party, but only the leader can
+/// send notices. Guild messages are delivered to the spawned members of the guild.
+pub fn chat_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    spawns: View<GlobalUserSpawn>,
+    private_channels: View<PrivateChannels>,
+    block_lists: View<BlockList>,
+    parties: View<Party>,
+    party_members: View<PartyMember>,
+    user_guilds: View<UserGuild>,
+    mut chatters: ViewMut<Chatter>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestChat {
+                connection_global_world_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_chat(
+                    *connection_global_world_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &private_channels,
+                    &block_lists,
+                    &parties,
+                    &party_members,
+                    &user_guilds,
+                    &mut chatters,
+                ) {
+                    error!("Ignoring chat request: {:?}", e);
+                }
+            }
+            Message::RequestWhisper {
+                connection_global_world_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_whisper(
+                    *connection_global_world_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &block_lists,
+                    &mut chatters,
+                ) {
+                    error!("Ignoring whisper request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_chat(
+    connection_global_world_id: EntityId,
+    packet: &CChat,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    private_channels: &View<PrivateChannels>,
+    block_lists: &View<BlockList>,
+    parties: &View<Party>,
+    party_members: &View<PartyMember>,
+    user_guilds: &View<UserGuild>,
+    chatters: &mut ViewMut<Chatter>,
+) -> Result<()> {
+    debug!("Message::RequestChat incoming");
+
+    let spawn = spawns.try_get(connection_global_world_id).context(format!(
+        "Can't find user spawn {:?}",
+        connection_global_world_id
+    ))?;
+    ensure!(
+        spawn.status == UserSpawnStatus::Spawned,
+        "User {:?} is not spawned and can't chat",
+        connection_global_world_id
+    );
+
+    // TODO Handle GM commands here once accounts have permissions.
+    let user_name = check_message(connection_global_world_id, &packet.message, chatters)?;
+
+    match packet.channel {
+        ChatChannel::Say
+        | ChatChannel::Area
+        | ChatChannel::Trade
+        | ChatChannel::Greeting
+        | ChatChannel::Emote => {
+            let connection_local_world_id = spawn
+                .connection_local_world_id
+                .context("User has no local world ID")?;
+            let local_world_channel = spawn
+                .local_world_channel
+                .as_ref()
+                .context("User has no local world channel")?;
+            send_message(
+                assemble_local_chat(connection_local_world_id, packet),
+                local_world_channel,
+            );
+        }
+        ChatChannel::Global => {
+            (connections, spawns)
+                .iter()
+                .with_id()
+                .filter(|(id, (_connection, recipient_spawn))| {
+                    recipient_spawn.status == UserSpawnStatus::Spawned
+                        && !recipient_spawn.marked_for_deletion
+                        && !is_blocked(
+                            connection_global_world_id,
+                            spawn.user_id,
+                            *id,
+                            recipient_spawn.user_id,
+                            block_lists,
+                        )
+                })
+                .for_each(|(id, (connection, _spawn))| {
+                    send_message(
+                        assemble_chat(
+                            id,
+                            connection_global_world_id,
+                            &user_name,
+                            packet.channel,
+                            &packet.message,
+                        ),
+                        &connection.channel,
+                    );
+                });
+        }
+        ChatChannel::PrivateChannel(slot) => {
+            let channel_id = *private_channels
+                .try_get(connection_global_world_id)
+                .ok()
+                .and_then(|channels| channels.channels.get(&slot))
+                .context(format!(
+                    "User {:?} didn't join a private channel in slot {}",
+                    connection_global_world_id, slot
+                ))?;
+
+            // Every member has the channel in it's own slot.
+            (connections, spawns, private_channels)
+                .iter()
+                .with_id()
+                .filter(|(id, (_connection, recipient_spawn, _channels))| {
+                    !recipient_spawn.marked_for_deletion
+                        && !is_blocked(
+                            connection_global_world_id,
+                            spawn.user_id,
+                            *id,
+                            recipient_spawn.user_id,
+                            block_lists,
+                        )
+                })
+                .for_each(|(id, (connection, _spawn, channels))| {
+                    if let Some((member_slot, _channel_id)) = channels
+                        .channels
+                        .iter()
+                        .find(|(_slot, member_channel_id)| **member_channel_id == channel_id)
+                    {
+                        send_message(
+                            assemble_chat(
+                                id,
+                                connection_global_world_id,
+                                &user_name,
+                                ChatChannel::PrivateChannel(*member_slot),
+                                &packet.message,
+                            ),
+                            &connection.channel,
+                        );
+                    }
+                });
+        }
+        ChatChannel::Party
+        | ChatChannel::PartyNotice
+        | ChatChannel::Raid
+        | ChatChannel::RaidNotice => {
+            let party_id = party_members
+                .try_get(connection_global_world_id)
+                .context(format!(
+                    "User {:?} is not in a party",
+                    connection_global_world_id
+                ))?
+                .party_id;
+            let party = parties
+                .try_get(party_id)
+                .context(format!("Can't find party {:?}", party_id))?;
+            if let ChatChannel::Raid | ChatChannel::RaidNotice = packet.channel {
+                ensure!(party.is_raid, "Party {:?} is not a raid", party_id);
+            }
+            if let ChatChannel::PartyNotice | ChatChannel::RaidNotice = packet.channel {
+                ensure!(
+                    party.leader_id == connection_global_world_id,
+                    "User {:?} is not the leader of party {:?} and can't send notices",
+                    connection_global_world_id,
+                    party_id
+                );
+            }
+
+            send_to_members(
+                party.members.iter().cloned(),
+                connection_global_world_id,
+                spawn.user_id,
+                &user_name,
+                packet,
+                connections,
+                spawns,
+                block_lists,
+            );
+        }
+        ChatChannel::Guild => {
+            let guild_id = user_guilds
+                .try_get(connection_global_world_id)
+                .context(format!(
+                    "User {:?} is not in a guild",
+                    connection_global_world_id
+                ))?
+                .guild_id;
+
+            send_to_members(
+                user_guilds
+                    .iter()
+                    .with_id()
+                    .filter(|(_id, user_guild)| user_guild.guild_id == guild_id)
+                    .map(|(id, _user_guild)| id),
+                connection_global_world_id,
+                spawn.user_id,
+                &user_name,
+                packet,
+                connections,
+                spawns,
+                block_lists,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Delivers a chat message to the spawned members of a group.
+fn send_to_members<I>(
+    members: I,
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    user_name: &str,
+    packet: &CChat,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    block_lists: &View<BlockList>,
+) where
+    I: Iterator<Item = EntityId>,
+{
+    for member_id in members {
+        let member_spawn = match spawns.try_get(member_id) {
+            Ok(member_spawn)
+                if member_spawn.status == UserSpawnStatus::Spawned
+                    && !member_spawn.marked_for_deletion =>
+            {
+                member_spawn
+            }
+            _ => continue,
+        };
+        if is_blocked(
+            connection_global_world_id,
+            user_id,
+            member_id,
+            member_spawn.user_id,
+            block_lists,
+        ) {
+            continue;
+        }
+        send_message_to_connection(
+            assemble_chat(
+                member_id,
+                connection_global_world_id,
+                user_name,
+                packet.channel,
+                &packet.message,
+            ),
+            connections,
+        );
+    }
+}
+
+fn handle_whisper(
+    connection_global_world_id: EntityId,
+    packet: &CWhisper,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    block_lists: &View<BlockList>,
+    chatters: &mut ViewMut<Chatter>,
+) -> Result<()> {
+    debug!("Message::RequestWhisper incoming");
+
+    let user_name = check_message(connection_global_world_id, &packet.message, chatters)?;
+
+    let recipient_id = find_online_user_by_name(&packet.target, spawns, chatters)
+        .context(format!("Can't find online user {}", packet.target))?;
+    ensure!(
+        recipient_id != connection_global_world_id,
+        "User {:?} can't whisper to itself",
+        connection_global_world_id
+    );
+    let author_user_id = spawns
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find user spawn {:?}",
+            connection_global_world_id
+        ))?
+        .user_id;
+    let recipient_user_id = spawns.try_get(recipient_id)?.user_id;
+    ensure!(
+        !is_blocked(
+            connection_global_world_id,
+            author_user_id,
+            recipient_id,
+            recipient_user_id,
+            block_lists
+        ),
+        "Whispers between user {} and user {} are blocked",
+        author_user_id,
+        recipient_user_id
+    );
+    let recipient_name = chatters.try_get(recipient_id)?.user_name.clone();
+
+    for id in [recipient_id, connection_global_world_id].iter() {
+        send_message_to_connection(
+            assemble_whisper(
+                *id,
+                connection_global_world_id,
+                &user_name,
+                &recipient_name,
+                &packet.message,
+            ),
+            connections,
+        );
+    }
+
+    Ok(())
+}
+
+/// Validates the message and counts it against the rate limit of the user.
+/// Returns the name of the user.
+fn check_message(
+    connection_global_world_id: EntityId,
+    message: &str,
+    chatters: &mut ViewMut<Chatter>,
+) -> Result<String> {
+    ensure!(
+        !message.trim().is_empty(),
+        "User {:?} send an empty message",
+        connection_global_world_id
+    );
+    ensure!(
+        message.chars().count() <= MAX_MESSAGE_LENGTH,
+        "User {:?} send a message that is longer than {} characters",
+        connection_global_world_id,
+        MAX_MESSAGE_LENGTH
+    );
+
+    let mut chatter = chatters
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find chatter {:?}",
+            connection_global_world_id
+        ))?;
+
+    let now = Instant::now();
+    if now.duration_since(chatter.window_start) >= RATE_LIMIT_WINDOW {
+        chatter.window_start = now;
+        chatter.message_count = 0;
+    }
+    ensure!(
+        chatter.message_count < RATE_LIMIT_MESSAGES,
+        "User {:?} exceeded the chat rate limit",
+        connection_global_world_id
+    );
+    chatter.message_count += 1;
+
+    Ok(chatter.user_name.clone())
+}
+
+fn assemble_local_chat(connection_local_world_id: EntityId, packet: &CChat) -> EcsMessage {
+    Box::new(LocalChat {
+        connection_local_world_id,
+        channel: packet.channel,
+        message: packet.message.clone(),
+    })
+}
+
+fn assemble_chat(
+    connection_global_world_id: EntityId,
+    author_id: EntityId,
+    author_name: &str,
+    channel: ChatChannel,
+    message: &str,
+) -> EcsMessage {
+    Box::new(ResponseChat {
+        connection_global_world_id,
+        packet: SChat {
+            author_name: author_name.to_string(),
+            message: message.to_string(),
+            channel,
+            user_id: author_id,
+            unk1: 0,
+            is_gm: false,
+            is_founder: false,
+        },
+    })
+}
+
+fn assemble_whisper(
+    connection_global_world_id: EntityId,
+    author_id: EntityId,
+    author_name: &str,
+    recipient_name: &str,
+    message: &str,
+) -> EcsMessage {
+    Box::new(ResponseWhisper {
+        connection_global_world_id,
+        packet: SWhisper {
+            author_name: author_name.to_string(),
+            recipient: recipient_name.to_string(),
+            message: message.to_string(),
+            user_id: author_id,
+            unk1: 0,
+            is_gm: false,
+            is_founder: false,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::LootingMethod;
+    use async_std::sync::{channel, Receiver, Sender};
+
+    fn setup() -> World {
+        World::new()
+    }
+
+    fn add_user(
+        world: &World,
+        user_name: &str,
+        status: UserSpawnStatus,
+        local_world_channel: Option<Sender<EcsMessage>>,
+    ) -> (EntityId, Receiver<EcsMessage>) {
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut spawns: ViewMut<GlobalUserSpawn>,
+             mut chatters: ViewMut<Chatter>| {
+                let connection_local_world_id = local_world_channel
+                    .as_ref()
+                    .map(|_| entities.add_entity((), ()));
+
+                entities.add_entity(
+                    (&mut connections, &mut spawns, &mut chatters),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        GlobalUserSpawn {
+                            user_id: 1,
+                            account_id: 1,
+                            status,
+                            zone_id: 5,
+                            connection_local_world_id,
+                            local_world_id: None,
+                            local_world_channel,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
+                        },
+                        Chatter {
+                            user_name: user_name.to_string(),
+                            window_start: Instant::now(),
+                            message_count: 0,
+                        },
+                    ),
+                )
+            },
+        );
+
+        (connection_global_world_id, rx_channel)
+    }
+
+    fn add_chat_request(
+        world: &World,
+        connection_global_world_id: EntityId,
+        channel: ChatChannel,
+        message: &str,
+    ) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::RequestChat {
+                        connection_global_world_id,
+                        account_id: 1,
+                        user_id: 1,
+                        packet: CChat {
+                            message: message.to_string(),
+                            channel,
+                        },
+                    }),
+                );
+            },
+        );
+    }
+
+    fn add_private_channels(
+        world: &World,
+        connection_global_world_id: EntityId,
+        slots: &[(i32, i32)],
+    ) {
+        world.run(
+            |entities: EntitiesView, mut private_channels: ViewMut<PrivateChannels>| {
+                entities.add_component(
+                    &mut private_channels,
+                    PrivateChannels {
+                        channels: slots.iter().cloned().collect(),
+                    },
+                    connection_global_world_id,
+                );
+            },
+        );
+    }
+
+    fn add_block_list(
+        world: &World,
+        connection_global_world_id: EntityId,
+        user_id: i32,
+        blocked_users: &[i32],
+    ) {
+        world.run(
+            |entities: EntitiesView,
+             mut spawns: ViewMut<GlobalUserSpawn>,
+             mut block_lists: ViewMut<BlockList>| {
+                spawns[connection_global_world_id].user_id = user_id;
+                entities.add_component(
+                    &mut block_lists,
+                    BlockList {
+                        blocked_users: blocked_users.iter().cloned().collect(),
+                    },
+                    connection_global_world_id,
+                );
+            },
+        );
+    }
+
+    /// Creates a party that is led by the first member.
+    fn add_party(world: &World, members: &[EntityId], is_raid: bool) {
+        world.run(
+            |mut entities: EntitiesViewMut,
+             mut parties: ViewMut<Party>,
+             mut party_members: ViewMut<PartyMember>| {
+                let party_id = entities.add_entity(
+                    &mut parties,
+                    Party {
+                        leader_id: members[0],
+                        members: members.to_vec(),
+                        looting_method: LootingMethod::Leader,
+                        is_raid,
+                    },
+                );
+                for member_id in members {
+                    entities.add_component(
+                        &mut party_members,
+                        PartyMember { party_id },
+                        *member_id,
+                    );
+                }
+            },
+        );
+    }
+
+    fn add_user_guild(world: &World, connection_global_world_id: EntityId, guild_id: i32) {
+        world.run(
+            |entities: EntitiesView, mut user_guilds: ViewMut<UserGuild>| {
+                entities.add_component(
+                    &mut user_guilds,
+                    UserGuild { guild_id },
+                    connection_global_world_id,
+                );
+            },
+        );
+    }
+
+    fn add_whisper_request(world: &World, connection_global_world_id: EntityId, target: &str) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::RequestWhisper {
+                        connection_global_world_id,
+                        account_id: 1,
+                        user_id: 1,
+                        packet: CWhisper {
+                            target: target.to_string(),
+                            message: "<FONT>Hi</FONT>".to_string(),
+                        },
+                    }),
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_global_chat() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+        let (_reader_id, reader_rx) = add_user(&world, "Reader", UserSpawnStatus::Spawned, None);
+        let (_lobby_id, lobby_rx) = add_user(&world, "Lobby", UserSpawnStatus::Requesting, None);
+
+        add_chat_request(&world, author_id, ChatChannel::Global, "<FONT>Hi</FONT>");
+        world.run(chat_manager_system);
+
+        for rx_channel in [author_rx, reader_rx].iter() {
+            match &*rx_channel.try_recv()? {
+                Message::ResponseChat { packet, .. } => {
+                    assert_eq!(packet.author_name, "Author");
+                    assert_eq!(packet.message, "<FONT>Hi</FONT>");
+                    assert_eq!(packet.channel, ChatChannel::Global);
+                    assert_eq!(packet.user_id, author_id);
+                }
+                _ => panic!("Message is not a ResponseChat message"),
+            }
+        }
+        assert!(lobby_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_local_chat_is_forwarded() -> Result<()> {
+        let world = setup();
+        let (local_world_tx, local_world_rx) = channel(1024);
+        let (author_id, author_rx) = add_user(
+            &world,
+            "Author",
+            UserSpawnStatus::Spawned,
+            Some(local_world_tx),
+        );
+
+        add_chat_request(&world, author_id, ChatChannel::Say, "<FONT>Hi</FONT>");
+        world.run(chat_manager_system);
+
+        let connection_local_world_id = world.run(|spawns: View<GlobalUserSpawn>| {
+            spawns[author_id].connection_local_world_id.unwrap()
+        });
+        match &*local_world_rx.try_recv()? {
+            Message::LocalChat {
+                connection_local_world_id: id,
+                channel,
+                message,
+            } => {
+                assert_eq!(*id, connection_local_world_id);
+                assert_eq!(*channel, ChatChannel::Say);
+                assert_eq!(message, "<FONT>Hi</FONT>");
+            }
+            _ => panic!("Message is not a LocalChat message"),
+        }
+        assert!(author_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_chat_message() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+
+        add_chat_request(&world, author_id, ChatChannel::Global, "   ");
+        add_chat_request(
+            &world,
+            author_id,
+            ChatChannel::Global,
+            &"a".repeat(MAX_MESSAGE_LENGTH + 1),
+        );
+        world.run(chat_manager_system);
+
+        assert!(author_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chat_rate_limit() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+
+        for _ in 0..RATE_LIMIT_MESSAGES + 2 {
+            add_chat_request(&world, author_id, ChatChannel::Global, "<FONT>Hi</FONT>");
+        }
+        world.run(chat_manager_system);
+
+        assert_eq!(author_rx.len(), RATE_LIMIT_MESSAGES as usize);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chat_rate_limit_window_reset() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+
+        world.run(|mut chatters: ViewMut<Chatter>| {
+            let mut chatter = (&mut chatters).try_get(author_id).unwrap();
+            chatter.message_count = RATE_LIMIT_MESSAGES;
+            chatter.window_start = Instant::now() - RATE_LIMIT_WINDOW;
+        });
+
+        add_chat_request(&world, author_id, ChatChannel::Global, "<FONT>Hi</FONT>");
+        world.run(chat_manager_system);
+
+        assert_eq!(author_rx.len(), 1);
+        world.run(|chatters: View<Chatter>| {
+            assert_eq!(chatters[author_id].message_count, 1);
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_whisper() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+        let (_recipient_id, recipient_rx) =
+            add_user(&world, "Recipient", UserSpawnStatus::Spawned, None);
+        let (_other_id, other_rx) = add_user(&world, "Other", UserSpawnStatus::Spawned, None);
+
+        add_whisper_request(&world, author_id, "recipient");
+        world.run(chat_manager_system);
+
+        for rx_channel in [author_rx, recipient_rx].iter() {
+            match &*rx_channel.try_recv()? {
+                Message::ResponseWhisper { packet, .. } => {
+                    assert_eq!(packet.author_name, "Author");
+                    assert_eq!(packet.recipient, "Recipient");
+                    assert_eq!(packet.message, "<FONT>Hi</FONT>");
+                    assert_eq!(packet.user_id, author_id);
+                }
+                _ => panic!("Message is not a ResponseWhisper message"),
+            }
+        }
+        assert!(other_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_whisper_offline_user() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+        let (_lobby_id, lobby_rx) = add_user(&world, "Lobby", UserSpawnStatus::Requesting, None);
+
+        add_whisper_request(&world, author_id, "Lobby");
+        add_whisper_request(&world, author_id, "Unknown");
+        add_whisper_request(&world, author_id, "Author");
+        world.run(chat_manager_system);
+
+        assert!(author_rx.is_empty());
+        assert!(lobby_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_whisper_blocked() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+        let (recipient_id, recipient_rx) =
+            add_user(&world, "Recipient", UserSpawnStatus::Spawned, None);
+        let (other_id, other_rx) = add_user(&world, "Other", UserSpawnStatus::Spawned, None);
+        add_block_list(&world, author_id, 1, &[3]);
+        add_block_list(&world, recipient_id, 2, &[1]);
+        add_block_list(&world, other_id, 3, &[]);
+
+        // The recipient blocked the author and the author blocked the other user.
+        add_whisper_request(&world, author_id, "Recipient");
+        add_whisper_request(&world, author_id, "Other");
+        world.run(chat_manager_system);
+
+        assert!(author_rx.is_empty());
+        assert!(recipient_rx.is_empty());
+        assert!(other_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_global_chat_blocked() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+        let (reader_id, reader_rx) = add_user(&world, "Reader", UserSpawnStatus::Spawned, None);
+        add_block_list(&world, author_id, 1, &[]);
+        add_block_list(&world, reader_id, 2, &[1]);
+
+        add_chat_request(&world, author_id, ChatChannel::Global, "<FONT>Hi</FONT>");
+        world.run(chat_manager_system);
+
+        assert_eq!(author_rx.len(), 1);
+        assert!(reader_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_private_channel_chat() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+        let (member_id, member_rx) = add_user(&world, "Member", UserSpawnStatus::Spawned, None);
+        let (other_id, other_rx) = add_user(&world, "Other", UserSpawnStatus::Spawned, None);
+        add_private_channels(&world, author_id, &[(1, 10), (2, 20)]);
+        add_private_channels(&world, member_id, &[(5, 20)]);
+        add_private_channels(&world, other_id, &[(2, 30)]);
+
+        add_chat_request(
+            &world,
+            author_id,
+            ChatChannel::PrivateChannel(2),
+            "<FONT>Hi</FONT>",
+        );
+        world.run(chat_manager_system);
+
+        for (rx_channel, slot) in [(author_rx, 2), (member_rx, 5)].iter() {
+            match &*rx_channel.try_recv()? {
+                Message::ResponseChat { packet, .. } => {
+                    assert_eq!(packet.author_name, "Author");
+                    assert_eq!(packet.message, "<FONT>Hi</FONT>");
+                    assert_eq!(packet.channel, ChatChannel::PrivateChannel(*slot));
+                    assert_eq!(packet.user_id, author_id);
+                }
+                _ => panic!("Message is not a ResponseChat message"),
+            }
+        }
+        assert!(other_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_private_channel_chat_not_joined() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+        add_private_channels(&world, author_id, &[(1, 10)]);
+
+        add_chat_request(
+            &world,
+            author_id,
+            ChatChannel::PrivateChannel(3),
+            "<FONT>Hi</FONT>",
+        );
+        world.run(chat_manager_system);
+
+        assert!(author_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_party_chat() -> Result<()> {
+        let world = setup();
+        let (leader_id, leader_rx) = add_user(&world, "Leader", UserSpawnStatus::Spawned, None);
+        let (member_id, member_rx) = add_user(&world, "Member", UserSpawnStatus::Spawned, None);
+        let (_other_id, other_rx) = add_user(&world, "Other", UserSpawnStatus::Spawned, None);
+        add_party(&world, &[leader_id, member_id], false);
+
+        add_chat_request(&world, member_id, ChatChannel::Party, "<FONT>Hi</FONT>");
+        world.run(chat_manager_system);
+
+        for rx_channel in [leader_rx, member_rx].iter() {
+            match &*rx_channel.try_recv()? {
+                Message::ResponseChat { packet, .. } => {
+                    assert_eq!(packet.author_name, "Member");
+                    assert_eq!(packet.message, "<FONT>Hi</FONT>");
+                    assert_eq!(packet.channel, ChatChannel::Party);
+                    assert_eq!(packet.user_id, member_id);
+                }
+                _ => panic!("Message is not a ResponseChat message"),
+            }
+        }
+        assert!(other_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_party_chat_without_party() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+
+        add_chat_request(&world, author_id, ChatChannel::Party, "<FONT>Hi</FONT>");
+        world.run(chat_manager_system);
+
+        assert!(author_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_party_notice_only_by_leader() -> Result<()> {
+        let world = setup();
+        let (leader_id, leader_rx) = add_user(&world, "Leader", UserSpawnStatus::Spawned, None);
+        let (member_id, member_rx) = add_user(&world, "Member", UserSpawnStatus::Spawned, None);
+        add_party(&world, &[leader_id, member_id], false);
+
+        add_chat_request(
+            &world,
+            member_id,
+            ChatChannel::PartyNotice,
+            "<FONT>Hi</FONT>",
+        );
+        world.run(chat_manager_system);
+        assert!(leader_rx.is_empty());
+        assert!(member_rx.is_empty());
+
+        add_chat_request(
+            &world,
+            leader_id,
+            ChatChannel::PartyNotice,
+            "<FONT>Hi</FONT>",
+        );
+        world.run(chat_manager_system);
+        for rx_channel in [leader_rx, member_rx].iter() {
+            match &*rx_channel.try_recv()? {
+                Message::ResponseChat { packet, .. } => {
+                    assert_eq!(packet.channel, ChatChannel::PartyNotice);
+                    assert_eq!(packet.user_id, leader_id);
+                }
+                _ => panic!("Message is not a ResponseChat message"),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_raid_chat() -> Result<()> {
+        let world = setup();
+        let (leader_id, leader_rx) = add_user(&world, "Leader", UserSpawnStatus::Spawned, None);
+        let (member_id, member_rx) = add_user(&world, "Member", UserSpawnStatus::Spawned, None);
+        add_party(&world, &[leader_id, member_id], false);
+
+        // Raid messages need a raid
+        add_chat_request(&world, member_id, ChatChannel::Raid, "<FONT>Hi</FONT>");
+        world.run(chat_manager_system);
+        assert!(leader_rx.is_empty());
+        assert!(member_rx.is_empty());
+
+        let (raid_leader_id, raid_leader_rx) =
+            add_user(&world, "RaidLeader", UserSpawnStatus::Spawned, None);
+        let (raid_member_id, raid_member_rx) =
+            add_user(&world, "RaidMember", UserSpawnStatus::Spawned, None);
+        add_party(&world, &[raid_leader_id, raid_member_id], true);
+
+        add_chat_request(&world, raid_member_id, ChatChannel::Raid, "<FONT>Hi</FONT>");
+        world.run(chat_manager_system);
+        for rx_channel in [raid_leader_rx, raid_member_rx].iter() {
+            match &*rx_channel.try_recv()? {
+                Message::ResponseChat { packet, .. } => {
+                    assert_eq!(packet.channel, ChatChannel::Raid);
+                    assert_eq!(packet.user_id, raid_member_id);
+                }
+                _ => panic!("Message is not a ResponseChat message"),
+            }
+        }
+        assert!(leader_rx.is_empty());
+        assert!(member_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_guild_chat() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+        let (member_id, member_rx) = add_user(&world, "Member", UserSpawnStatus::Spawned, None);
+        let (other_id, other_rx) = add_user(&world, "Other", UserSpawnStatus::Spawned, None);
+        let (_guildless_id, guildless_rx) =
+            add_user(&world, "Guildless", UserSpawnStatus::Spawned, None);
+        add_user_guild(&world, author_id, 1);
+        add_user_guild(&world, member_id, 1);
+        add_user_guild(&world, other_id, 2);
+
+        add_chat_request(&world, author_id, ChatChannel::Guild, "<FONT>Hi</FONT>");
+        world.run(chat_manager_system);
+
+        for rx_channel in [author_rx, member_rx].iter() {
+            match &*rx_channel.try_recv()? {
+                Message::ResponseChat { packet, .. } => {
+                    assert_eq!(packet.author_name, "Author");
+                    assert_eq!(packet.message, "<FONT>Hi</FONT>");
+                    assert_eq!(packet.channel, ChatChannel::Guild);
+                    assert_eq!(packet.user_id, author_id);
+                }
+                _ => panic!("Message is not a ResponseChat message"),
+            }
+        }
+        assert!(other_rx.is_empty());
+        assert!(guildless_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_guild_chat_without_guild() -> Result<()> {
+        let world = setup();
+        let (author_id, author_rx) = add_user(&world, "Author", UserSpawnStatus::Spawned, None);
+
+        add_chat_request(&world, author_id, ChatChannel::Guild, "<FONT>Hi</FONT>");
+        world.run(chat_manager_system);
+
+        assert!(author_rx.is_empty());
+
+        Ok(())
+    }
+}
@@ -1107,6 +1107,8 @@ mod tests {
                                 local_world_channel: None,
                                 marked_for_deletion: false,
                                 is_alive: false,
+                                channel_num: None,
+                                is_relocating: false,
                             },
                             connection_global_world_id,
                         )
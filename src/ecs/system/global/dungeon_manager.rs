@@ -0,0 +1,708 @@
+use crate::ecs::component::{
+    GlobalConnection, GlobalUserSpawn, InstanceRequest, LocalWorldType, Party, PartyMember,
+    UserSpawnStatus,
+};
+use crate::ecs::message::Message::{ResponseDungeonClearCountList, ResponseDungeonCoolTimeList};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::GameData;
+use crate::ecs::system::global::send_message_to_connection;
+use crate::model::entity::DungeonLockout;
+use crate::model::repository::dungeon_lockout;
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use chrono::{Duration, Utc};
+use shipyard::*;
+use sqlx::{PgConnection, PgPool};
+use tracing::{debug, error, info, info_span};
+
+/// Number of times an user can enter a dungeon until the lockout of the dungeon resets.
+pub const DUNGEON_ENTRY_LIMIT: i32 = 3;
+
+/// Hours between the first entry into a dungeon and the reset of it's lockout.
+const DUNGEON_LOCKOUT_HOURS: i64 = 24;
+
+/// The dungeon manager handles the entry of users and parties into dungeon instances and tracks
+/// the lockouts and clears of the dungeons. Parties share an instance, users without a party get
+/// an instance of their own.
+pub fn dungeon_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    mut spawns: ViewMut<GlobalUserSpawn>,
+    parties: View<Party>,
+    party_members: View<PartyMember>,
+    mut instance_requests: ViewMut<InstanceRequest>,
+    entities: EntitiesView,
+    pool: UniqueView<PgPool>,
+    game_data: UniqueView<GameData>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestEnterDungeon {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_enter_dungeon(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &mut spawns,
+                    &parties,
+                    &party_members,
+                    &mut instance_requests,
+                    &entities,
+                    &pool,
+                    &game_data,
+                ) {
+                    error!("Ignoring enter dungeon request: {:?}", e);
+                }
+            }
+            Message::RequestDungeonCoolTimeList {
+                connection_global_world_id,
+                user_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_dungeon_cool_time_list(
+                    *connection_global_world_id,
+                    *user_id,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring dungeon cool time list request: {:?}", e);
+                }
+            }
+            Message::RequestDungeonClearCountList {
+                connection_global_world_id,
+                user_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_dungeon_clear_count_list(
+                    *connection_global_world_id,
+                    *user_id,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring dungeon clear count list request: {:?}", e);
+                }
+            }
+            Message::DungeonCleared {
+                connection_global_world_id,
+                zone_id,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_dungeon_cleared(
+                    *connection_global_world_id,
+                    *zone_id,
+                    &spawns,
+                    &pool,
+                    &game_data,
+                ) {
+                    error!("Ignoring Message::DungeonCleared: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_enter_dungeon(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CEnterDungeon,
+    spawns: &mut ViewMut<GlobalUserSpawn>,
+    parties: &View<Party>,
+    party_members: &View<PartyMember>,
+    instance_requests: &mut ViewMut<InstanceRequest>,
+    entities: &EntitiesView,
+    pool: &UniqueView<PgPool>,
+    game_data: &UniqueView<GameData>,
+) -> Result<()> {
+    debug!("Message::RequestEnterDungeon incoming");
+
+    ensure!(
+        game_data.zones.zone_type(packet.zone_id) == LocalWorldType::Dungeon,
+        "Zone {} is not a dungeon",
+        packet.zone_id
+    );
+
+    // Parties can only be lead into a dungeon by their leader and share an instance.
+    let (group_id, member_ids) =
+        if let Ok(member) = party_members.try_get(connection_global_world_id) {
+            let party = parties
+                .try_get(member.party_id)
+                .context(format!("Can't find party {:?}", member.party_id))?;
+            ensure!(
+                party.leader_id == connection_global_world_id,
+                "User {} is not the leader of it's party",
+                user_id
+            );
+            (member.party_id, party.members.clone())
+        } else {
+            (connection_global_world_id, vec![connection_global_world_id])
+        };
+
+    let mut user_ids = Vec::with_capacity(member_ids.len());
+    for member_id in member_ids.iter() {
+        let spawn = spawns
+            .try_get(*member_id)
+            .context(format!("Can't find user spawn {:?}", member_id))?;
+        ensure!(
+            spawn.status == UserSpawnStatus::Spawned,
+            "User {} is not spawned",
+            spawn.user_id
+        );
+        user_ids.push(spawn.user_id);
+    }
+
+    task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        ensure_no_dungeon_lockout(&mut conn, &user_ids, packet.zone_id).await?;
+        register_dungeon_entry(&mut conn, &user_ids, packet.zone_id).await?;
+
+        conn.commit().await?;
+
+        Ok::<(), anyhow::Error>(())
+    })?;
+
+    info!(
+        "User {} enters dungeon {} with {} member(s)",
+        user_id,
+        packet.zone_id,
+        member_ids.len()
+    );
+
+    // The local world manager creates the instance once the first member requests it.
+    for member_id in member_ids.into_iter() {
+        let spawn = &mut spawns[member_id];
+        spawn.zone_id = packet.zone_id;
+        spawn.status = UserSpawnStatus::Relocating;
+        spawn.is_relocating = true;
+        entities.add_component(
+            &mut *instance_requests,
+            InstanceRequest { group_id },
+            member_id,
+        );
+    }
+
+    Ok(())
+}
+
+fn handle_dungeon_cool_time_list(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestDungeonCoolTimeList incoming");
+
+    let lockouts = list_dungeon_lockouts(user_id, pool)?;
+    send_message_to_connection(
+        assemble_dungeon_cool_time_list(connection_global_world_id, &lockouts),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_dungeon_clear_count_list(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestDungeonClearCountList incoming");
+
+    let lockouts = list_dungeon_lockouts(user_id, pool)?;
+    send_message_to_connection(
+        assemble_dungeon_clear_count_list(connection_global_world_id, &lockouts),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_dungeon_cleared(
+    connection_global_world_id: EntityId,
+    zone_id: i32,
+    spawns: &ViewMut<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+    game_data: &UniqueView<GameData>,
+) -> Result<()> {
+    debug!("Message::DungeonCleared incoming");
+
+    // Bosses outside of dungeons don't count as clears
+    if game_data.zones.zone_type(zone_id) != LocalWorldType::Dungeon {
+        return Ok(());
+    }
+
+    let user_id = spawns
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find user spawn {:?}",
+            connection_global_world_id
+        ))?
+        .user_id;
+
+    let lockout = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        dungeon_lockout::add_clear(&mut conn, user_id, zone_id)
+            .await
+            .context(format!("Can't update the lockout of user {}", user_id))
+    })?;
+
+    info!(
+        "User {} cleared dungeon {} ({} clears)",
+        user_id, zone_id, lockout.clear_count
+    );
+
+    Ok(())
+}
+
+fn list_dungeon_lockouts(user_id: i32, pool: &UniqueView<PgPool>) -> Result<Vec<DungeonLockout>> {
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        dungeon_lockout::list(&mut conn, user_id)
+            .await
+            .context(format!("Can't query the lockouts of user {}", user_id))
+    })
+}
+
+/// Makes sure that none of the users has reached the entry limit of the dungeon.
+pub async fn ensure_no_dungeon_lockout(
+    conn: &mut PgConnection,
+    user_ids: &[i32],
+    zone_id: i32,
+) -> Result<()> {
+    for user_id in user_ids {
+        let entry_count = dungeon_lockout::get_entry_count(conn, *user_id, zone_id)
+            .await
+            .context(format!("Can't query the lockout of user {}", user_id))?;
+        ensure!(
+            entry_count < DUNGEON_ENTRY_LIMIT,
+            "User {} reached the entry limit of dungeon {}",
+            user_id,
+            zone_id
+        );
+    }
+    Ok(())
+}
+
+/// Counts an entry into the dungeon for all users.
+pub async fn register_dungeon_entry(
+    conn: &mut PgConnection,
+    user_ids: &[i32],
+    zone_id: i32,
+) -> Result<()> {
+    let reset_at = Utc::now() + Duration::hours(DUNGEON_LOCKOUT_HOURS);
+    for user_id in user_ids {
+        dungeon_lockout::add_entry(conn, *user_id, zone_id, reset_at)
+            .await
+            .context(format!("Can't update the lockout of user {}", user_id))?;
+    }
+    Ok(())
+}
+
+fn assemble_dungeon_cool_time_list(
+    connection_global_world_id: EntityId,
+    lockouts: &[DungeonLockout],
+) -> EcsMessage {
+    let now = Utc::now();
+    Box::new(ResponseDungeonCoolTimeList {
+        connection_global_world_id,
+        packet: SDungeonCoolTimeList {
+            dungeons: lockouts
+                .iter()
+                .map(|lockout| {
+                    // Entries before the last reset of the lockout don't count anymore.
+                    let is_active = lockout.reset_at > now;
+                    SDungeonCoolTimeListEntry {
+                        zone_id: lockout.zone_id,
+                        entry_count: if is_active { lockout.entry_count } else { 0 },
+                        max_entry_count: DUNGEON_ENTRY_LIMIT,
+                        reset_time: if is_active {
+                            lockout.reset_at.timestamp()
+                        } else {
+                            0
+                        },
+                    }
+                })
+                .collect(),
+        },
+    })
+}
+
+fn assemble_dungeon_clear_count_list(
+    connection_global_world_id: EntityId,
+    lockouts: &[DungeonLockout],
+) -> EcsMessage {
+    Box::new(ResponseDungeonClearCountList {
+        connection_global_world_id,
+        packet: SDungeonClearCountList {
+            dungeons: lockouts
+                .iter()
+                .filter(|lockout| lockout.clear_count > 0)
+                .map(|lockout| SDungeonClearCountListEntry {
+                    zone_id: lockout.zone_id,
+                    clear_count: lockout.clear_count,
+                })
+                .collect(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::{DeletionList, Zone, ZoneRegistry};
+    use crate::ecs::system::common::cleaner_system;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::model::LootingMethod;
+    use async_std::sync::{channel, Receiver};
+    use std::time::Instant;
+
+    struct TestUser {
+        user: User,
+        connection_global_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(pool);
+        world.add_unique(GameData {
+            zones: ZoneRegistry::new(vec![
+                Zone {
+                    id: 5,
+                    zone_type: LocalWorldType::Field,
+                    channel_capacity: 0,
+                    topology_id: 5,
+                    spawn_points: vec![],
+                },
+                Zone {
+                    id: 9713,
+                    zone_type: LocalWorldType::Dungeon,
+                    channel_capacity: 0,
+                    topology_id: 9713,
+                    spawn_points: vec![],
+                },
+            ]),
+            ..Default::default()
+        });
+        world
+    }
+
+    fn add_user(world: &World, pool: &PgPool, num: i32) -> Result<TestUser> {
+        let user = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let account = account::create(&mut conn, &get_default_account(num)).await?;
+            user::create(&mut conn, &get_default_user(&account, num)).await
+        })?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut spawns: ViewMut<GlobalUserSpawn>| {
+                entities.add_entity(
+                    (&mut connections, &mut spawns),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        GlobalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_local_world_id: None,
+                            local_world_id: None,
+                            local_world_channel: None,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: Some(1),
+                            is_relocating: false,
+                        },
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            user,
+            connection_global_world_id,
+            rx: rx_channel,
+        })
+    }
+
+    fn add_party(world: &World, leader: &TestUser, member: &TestUser) -> EntityId {
+        world.run(
+            |mut entities: EntitiesViewMut,
+             mut parties: ViewMut<Party>,
+             mut party_members: ViewMut<PartyMember>| {
+                let party_id = entities.add_entity(
+                    &mut parties,
+                    Party {
+                        leader_id: leader.connection_global_world_id,
+                        members: vec![
+                            leader.connection_global_world_id,
+                            member.connection_global_world_id,
+                        ],
+                        looting_method: LootingMethod::RoundRobin,
+                        is_raid: false,
+                    },
+                );
+                for id in [
+                    leader.connection_global_world_id,
+                    member.connection_global_world_id,
+                ]
+                .iter()
+                {
+                    entities.add_component(&mut party_members, PartyMember { party_id }, *id);
+                }
+                party_id
+            },
+        )
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(dungeon_manager_system);
+        world.run(cleaner_system);
+    }
+
+    fn enter_dungeon(world: &World, user: &TestUser, zone_id: i32) {
+        run_message(
+            world,
+            Message::RequestEnterDungeon {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CEnterDungeon { zone_id },
+            },
+        );
+    }
+
+    fn get_instance_request(world: &World, user: &TestUser) -> Option<InstanceRequest> {
+        world.run(|instance_requests: View<InstanceRequest>| {
+            instance_requests
+                .try_get(user.connection_global_world_id)
+                .ok()
+                .copied()
+        })
+    }
+
+    fn reset_spawn(world: &World, user: &TestUser) {
+        world.run(
+            |mut spawns: ViewMut<GlobalUserSpawn>,
+             mut instance_requests: ViewMut<InstanceRequest>| {
+                let spawn = (&mut spawns)
+                    .try_get(user.connection_global_world_id)
+                    .unwrap();
+                spawn.zone_id = 5;
+                spawn.status = UserSpawnStatus::Spawned;
+                spawn.is_relocating = false;
+                instance_requests.delete(user.connection_global_world_id);
+            },
+        );
+    }
+
+    #[test]
+    fn test_enter_dungeon() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 0)?;
+
+            // Users can only enter dungeons.
+            enter_dungeon(&world, &user, 5);
+            assert!(get_instance_request(&world, &user).is_none());
+
+            enter_dungeon(&world, &user, 9713);
+            assert_eq!(
+                get_instance_request(&world, &user).unwrap().group_id,
+                user.connection_global_world_id
+            );
+            world.run(|spawns: View<GlobalUserSpawn>| {
+                let spawn = spawns.try_get(user.connection_global_world_id).unwrap();
+                assert_eq!(spawn.zone_id, 9713);
+                assert_eq!(spawn.status, UserSpawnStatus::Relocating);
+                assert!(spawn.is_relocating);
+            });
+
+            // Users that are not spawned can't enter a dungeon.
+            world.run(|mut instance_requests: ViewMut<InstanceRequest>| {
+                instance_requests.delete(user.connection_global_world_id);
+            });
+            enter_dungeon(&world, &user, 9713);
+            assert!(get_instance_request(&world, &user).is_none());
+
+            let entry_count = task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                dungeon_lockout::get_entry_count(&mut conn, user.user.id, 9713).await
+            })?;
+            assert_eq!(entry_count, 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_enter_dungeon_with_party() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let leader = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            let party_id = add_party(&world, &leader, &member);
+
+            // Only the leader can lead the party into a dungeon.
+            enter_dungeon(&world, &member, 9713);
+            assert!(get_instance_request(&world, &member).is_none());
+
+            enter_dungeon(&world, &leader, 9713);
+            for user in [&leader, &member].iter() {
+                assert_eq!(
+                    get_instance_request(&world, user).unwrap().group_id,
+                    party_id
+                );
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_dungeon_lockout() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 0)?;
+
+            for _ in 0..DUNGEON_ENTRY_LIMIT {
+                enter_dungeon(&world, &user, 9713);
+                assert!(get_instance_request(&world, &user).is_some());
+                reset_spawn(&world, &user);
+            }
+
+            enter_dungeon(&world, &user, 9713);
+            assert!(get_instance_request(&world, &user).is_none());
+
+            // A party can't enter if one of it's members is locked out.
+            let leader = add_user(&world, &pool, 1)?;
+            add_party(&world, &leader, &user);
+            enter_dungeon(&world, &leader, 9713);
+            assert!(get_instance_request(&world, &leader).is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_dungeon_cool_time_list() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 0)?;
+            enter_dungeon(&world, &user, 9713);
+
+            run_message(
+                &world,
+                Message::RequestDungeonCoolTimeList {
+                    connection_global_world_id: user.connection_global_world_id,
+                    account_id: user.user.account_id,
+                    user_id: user.user.id,
+                    packet: CDungeonCoolTimeList {},
+                },
+            );
+
+            match &*user.rx.try_recv()? {
+                Message::ResponseDungeonCoolTimeList { packet, .. } => {
+                    assert_eq!(packet.dungeons.len(), 1);
+                    assert_eq!(packet.dungeons[0].zone_id, 9713);
+                    assert_eq!(packet.dungeons[0].entry_count, 1);
+                    assert_eq!(packet.dungeons[0].max_entry_count, DUNGEON_ENTRY_LIMIT);
+                    assert!(packet.dungeons[0].reset_time > Utc::now().timestamp());
+                }
+                _ => panic!("Message is not a ResponseDungeonCoolTimeList message"),
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_dungeon_cleared() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 0)?;
+
+            // Clears of fields are ignored
+            for zone_id in [9713, 9713, 5].iter() {
+                run_message(
+                    &world,
+                    Message::DungeonCleared {
+                        connection_global_world_id: user.connection_global_world_id,
+                        zone_id: *zone_id,
+                    },
+                );
+            }
+
+            run_message(
+                &world,
+                Message::RequestDungeonClearCountList {
+                    connection_global_world_id: user.connection_global_world_id,
+                    account_id: user.user.account_id,
+                    user_id: user.user.id,
+                    packet: CDungeonClearCountList {},
+                },
+            );
+
+            match &*user.rx.try_recv()? {
+                Message::ResponseDungeonClearCountList { packet, .. } => {
+                    assert_eq!(
+                        packet.dungeons,
+                        vec![SDungeonClearCountListEntry {
+                            zone_id: 9713,
+                            clear_count: 2,
+                        }]
+                    );
+                }
+                _ => panic!("Message is not a ResponseDungeonClearCountList message"),
+            }
+
+            Ok(())
+        })
+    }
+}
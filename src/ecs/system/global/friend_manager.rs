@@ -0,0 +1,1437 @@
+use crate::ecs::component::{Chatter, FriendList, GlobalConnection, GlobalUserSpawn};
+use crate::ecs::message::Message::{
+    ResponseAddFriend, ResponseChangeFriendState, ResponseDeleteFriend, ResponseFriendGroupList,
+    ResponseFriendList, ResponseResultChangeFriendMemo,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::system::global::{find_online_user, send_message_to_connection};
+use crate::model::entity::{Friend, FriendGroup, FriendRequest};
+use crate::model::repository::{blocked_user, friend, user};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use chrono::Utc;
+use shipyard::*;
+use sqlx::{PgConnection, PgPool};
+use tracing::{debug, error, info_span};
+
+/// Maximal number of friends an user can have.
+const MAX_FRIENDS: i64 = 100;
+
+/// Maximal number of friend groups an user can have.
+const MAX_FRIEND_GROUPS: usize = 10;
+
+/// Maximal length of the name of a friend group.
+const MAX_GROUP_NAME_LENGTH: usize = 20;
+
+/// Maximal length of a friend memo and the message of a friend request.
+const MAX_MEMO_LENGTH: usize = 64;
+
+/// Group ID the client uses for friends without a group.
+const NO_GROUP_ID: i32 = 0;
+
+/// The friend manager handles the friend list of the users. Friendships are mutual and need to
+/// be accepted by the other user. Friends are notified when an user comes online (spawns for the
+/// first time) or goes offline (the connection manager dropped it's connection).
+pub fn friend_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    spawns: View<GlobalUserSpawn>,
+    chatters: View<Chatter>,
+    mut friend_lists: ViewMut<FriendList>,
+    entities: EntitiesView,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::UserSpawned {
+                connection_global_world_id,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_user_spawned(
+                    *connection_global_world_id,
+                    &connections,
+                    &spawns,
+                    &mut friend_lists,
+                    &entities,
+                    &pool,
+                ) {
+                    error!("Ignoring Message::UserSpawned: {:?}", e);
+                }
+            }
+            Message::RequestAddFriend {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_add_friend(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &chatters,
+                    &friend_lists,
+                    &pool,
+                ) {
+                    error!("Ignoring add friend request: {:?}", e);
+                }
+            }
+            Message::RequestAcceptFriend {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_accept_friend(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &mut friend_lists,
+                    &pool,
+                ) {
+                    error!("Ignoring accept friend request: {:?}", e);
+                }
+            }
+            Message::RequestDeleteFriend {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_delete_friend(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &mut friend_lists,
+                    &pool,
+                ) {
+                    error!("Ignoring delete friend request: {:?}", e);
+                }
+            }
+            Message::RequestChangeFriendMemo {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_change_friend_memo(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring change friend memo request: {:?}", e);
+                }
+            }
+            Message::RequestUpdateFriendInfo {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_update_friend_info(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &friend_lists,
+                    &pool,
+                ) {
+                    error!("Ignoring update friend info request: {:?}", e);
+                }
+            }
+            Message::RequestAddFriendGroup {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_add_friend_group(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring add friend group request: {:?}", e);
+                }
+            }
+            Message::RequestEditFriendGroup {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_edit_friend_group(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring edit friend group request: {:?}", e);
+                }
+            }
+            Message::RequestDeleteFriendGroup {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_delete_friend_group(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &friend_lists,
+                    &pool,
+                ) {
+                    error!("Ignoring delete friend group request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+
+    // Users whose connection was dropped are offline for their friends.
+    let offline_users: Vec<(EntityId, i32)> = (&spawns, &friend_lists)
+        .iter()
+        .with_id()
+        .filter(|(_id, (spawn, _friend_list))| spawn.marked_for_deletion)
+        .map(|(id, (spawn, _friend_list))| (id, spawn.user_id))
+        .collect();
+    for (connection_global_world_id, user_id) in offline_users {
+        id_span!(connection_global_world_id);
+        debug!("User {} went offline", user_id);
+        friend_lists.delete(connection_global_world_id);
+        notify_friends(user_id, false, &connections, &spawns, &friend_lists);
+    }
+}
+
+/// Sends the friend list to an user once it's spawned for the first time and tells it's friends
+/// that the user is online.
+fn handle_user_spawned(
+    connection_global_world_id: EntityId,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    friend_lists: &mut ViewMut<FriendList>,
+    entities: &EntitiesView,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::UserSpawned incoming");
+
+    // Users that changed their local world are already online.
+    if friend_lists.try_get(connection_global_world_id).is_ok() {
+        return Ok(());
+    }
+
+    let spawn = spawns.try_get(connection_global_world_id).context(format!(
+        "Can't find user spawn {:?}",
+        connection_global_world_id
+    ))?;
+
+    let friend_list = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        send_message_to_connection(
+            assemble_friend_group_list(&mut conn, connection_global_world_id, spawn.user_id)
+                .await?,
+            connections,
+        );
+        send_message_to_connection(
+            assemble_friend_list(
+                &mut conn,
+                connection_global_world_id,
+                spawn.user_id,
+                spawns,
+                friend_lists,
+            )
+            .await?,
+            connections,
+        );
+
+        // Deliver the friend requests the user received while it was offline.
+        for request in friend::list_received_requests(&mut conn, spawn.user_id).await? {
+            let requester = user::get_by_id(&mut conn, request.user_id).await?;
+            send_message_to_connection(
+                assemble_add_friend(
+                    connection_global_world_id,
+                    &requester.name,
+                    &request.message,
+                ),
+                connections,
+            );
+        }
+
+        let friends = friend::list(&mut conn, spawn.user_id).await?;
+        Ok::<FriendList, anyhow::Error>(FriendList {
+            friends: friends.iter().map(|friend| friend.friend_id).collect(),
+        })
+    })?;
+
+    entities.add_component(&mut *friend_lists, friend_list, connection_global_world_id);
+    notify_friends(spawn.user_id, true, connections, spawns, friend_lists);
+
+    Ok(())
+}
+
+fn handle_add_friend(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CAddFriend,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    chatters: &View<Chatter>,
+    friend_lists: &ViewMut<FriendList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestAddFriend incoming");
+
+    ensure!(
+        packet.message.chars().count() <= MAX_MEMO_LENGTH,
+        "Friend request message is longer than {} characters",
+        MAX_MEMO_LENGTH
+    );
+    let user_name = &chatters
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find chatter {:?}",
+            connection_global_world_id
+        ))?
+        .user_name;
+
+    let friend_id = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let friend = user::get_by_name(&mut conn, &packet.name)
+            .await
+            .context(format!("Can't find user {}", packet.name))?;
+        ensure!(
+            friend.id != user_id,
+            "User {} can't befriend itself",
+            user_id
+        );
+        // The other user might be offline, so the block list is checked in the database.
+        ensure!(
+            !blocked_user::is_blocked(&mut conn, user_id, friend.id).await?
+                && !blocked_user::is_blocked(&mut conn, friend.id, user_id).await?,
+            "Friend requests between user {} and user {} are blocked",
+            user_id,
+            friend.id
+        );
+        ensure!(
+            !friend::is_friend(&mut conn, user_id, friend.id).await?,
+            "User {} is already a friend of user {}",
+            friend.id,
+            user_id
+        );
+        ensure!(
+            friend::get_friend_count(&mut conn, user_id).await? < MAX_FRIENDS,
+            "User {} already has {} friends",
+            user_id,
+            MAX_FRIENDS
+        );
+
+        friend::upsert_request(
+            &mut conn,
+            &FriendRequest {
+                user_id,
+                friend_id: friend.id,
+                message: packet.message.clone(),
+                created_at: Utc::now(),
+            },
+        )
+        .await
+        .context("Can't create friend request")?;
+
+        Ok::<i32, anyhow::Error>(friend.id)
+    })?;
+
+    // Offline users get the request once they log in.
+    if let Some(friend_connection_id) =
+        find_online_user(friend_id, spawns).filter(|id| friend_lists.try_get(*id).is_ok())
+    {
+        send_message_to_connection(
+            assemble_add_friend(friend_connection_id, user_name, &packet.message),
+            connections,
+        );
+    }
+
+    Ok(())
+}
+
+fn handle_accept_friend(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CAcceptFriend,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    friend_lists: &mut ViewMut<FriendList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestAcceptFriend incoming");
+
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let requester = user::get_by_name(&mut conn, &packet.name)
+            .await
+            .context(format!("Can't find user {}", packet.name))?;
+        friend::get_request(&mut conn, requester.id, user_id)
+            .await
+            .context(format!(
+                "User {} didn't send a friend request to user {}",
+                requester.id, user_id
+            ))?;
+        friend::delete_request(&mut conn, requester.id, user_id).await?;
+        friend::delete_request(&mut conn, user_id, requester.id).await?;
+
+        for (id, friend_id) in [(user_id, requester.id), (requester.id, user_id)].iter() {
+            ensure!(
+                friend::get_friend_count(&mut conn, *id).await? < MAX_FRIENDS,
+                "User {} already has {} friends",
+                id,
+                MAX_FRIENDS
+            );
+            friend::create(
+                &mut conn,
+                &Friend {
+                    user_id: *id,
+                    friend_id: *friend_id,
+                    group_id: None,
+                    memo: "".to_string(),
+                    created_at: Utc::now(),
+                },
+            )
+            .await
+            .context(format!("Can't add user {} as friend of {}", friend_id, id))?;
+        }
+
+        conn.commit().await?;
+
+        let requester_connection_id =
+            find_online_user(requester.id, spawns).filter(|id| friend_lists.try_get(*id).is_ok());
+        if let Ok(friend_list) = friend_lists.try_get(connection_global_world_id) {
+            friend_list.friends.insert(requester.id);
+        }
+        if let Some(id) = requester_connection_id {
+            if let Ok(friend_list) = friend_lists.try_get(id) {
+                friend_list.friends.insert(user_id);
+            }
+        }
+
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+        send_message_to_connection(
+            assemble_friend_list(
+                &mut conn,
+                connection_global_world_id,
+                user_id,
+                spawns,
+                friend_lists,
+            )
+            .await?,
+            connections,
+        );
+        if let Some(id) = requester_connection_id {
+            send_message_to_connection(
+                assemble_friend_list(&mut conn, id, requester.id, spawns, friend_lists).await?,
+                connections,
+            );
+        }
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+fn handle_delete_friend(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CDeleteFriend,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    friend_lists: &mut ViewMut<FriendList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestDeleteFriend incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        ensure!(
+            friend::is_friend(&mut conn, user_id, packet.user_id).await?,
+            "User {} is not a friend of user {}",
+            packet.user_id,
+            user_id
+        );
+        friend::delete(&mut conn, user_id, packet.user_id).await?;
+        friend::delete(&mut conn, packet.user_id, user_id).await?;
+
+        conn.commit().await?;
+
+        Ok::<(), anyhow::Error>(())
+    })?;
+
+    if let Ok(friend_list) = friend_lists.try_get(connection_global_world_id) {
+        friend_list.friends.remove(&packet.user_id);
+    }
+    send_message_to_connection(
+        assemble_delete_friend(connection_global_world_id, packet.user_id),
+        connections,
+    );
+
+    if let Some(friend_connection_id) = find_online_user(packet.user_id, spawns) {
+        if let Ok(friend_list) = friend_lists.try_get(friend_connection_id) {
+            friend_list.friends.remove(&user_id);
+            send_message_to_connection(
+                assemble_delete_friend(friend_connection_id, user_id),
+                connections,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_change_friend_memo(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CChangeFriendMemo,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestChangeFriendMemo incoming");
+
+    ensure!(
+        packet.memo.chars().count() <= MAX_MEMO_LENGTH,
+        "Friend memo is longer than {} characters",
+        MAX_MEMO_LENGTH
+    );
+
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let mut friend = friend::get(&mut conn, user_id, packet.user_id)
+            .await
+            .context(format!(
+                "User {} is not a friend of user {}",
+                packet.user_id, user_id
+            ))?;
+        friend.memo = packet.memo.clone();
+        friend::update(&mut conn, &friend)
+            .await
+            .context("Can't update friend memo")?;
+
+        send_message_to_connection(
+            assemble_result_change_friend_memo(
+                connection_global_world_id,
+                packet.user_id,
+                &packet.memo,
+            ),
+            connections,
+        );
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+/// Moves a friend into another friend group.
+fn handle_update_friend_info(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CUpdateFriendInfo,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    friend_lists: &ViewMut<FriendList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestUpdateFriendInfo incoming");
+
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let mut friend = friend::get(&mut conn, user_id, packet.user_id)
+            .await
+            .context(format!(
+                "User {} is not a friend of user {}",
+                packet.user_id, user_id
+            ))?;
+
+        if packet.group_id == NO_GROUP_ID {
+            friend.group_id = None;
+        } else {
+            get_own_group(&mut conn, user_id, packet.group_id).await?;
+            friend.group_id = Some(packet.group_id);
+        }
+        friend::update(&mut conn, &friend)
+            .await
+            .context("Can't update friend group")?;
+
+        send_message_to_connection(
+            assemble_friend_list(
+                &mut conn,
+                connection_global_world_id,
+                user_id,
+                spawns,
+                friend_lists,
+            )
+            .await?,
+            connections,
+        );
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+fn handle_add_friend_group(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CAddFriendGroup,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestAddFriendGroup incoming");
+
+    check_group_name(&packet.name)?;
+
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        ensure!(
+            friend::list_groups(&mut conn, user_id).await?.len() < MAX_FRIEND_GROUPS,
+            "User {} already has {} friend groups",
+            user_id,
+            MAX_FRIEND_GROUPS
+        );
+        friend::create_group(
+            &mut conn,
+            &FriendGroup {
+                id: -1,
+                user_id,
+                name: packet.name.clone(),
+            },
+        )
+        .await
+        .context("Can't create friend group")?;
+
+        send_message_to_connection(
+            assemble_friend_group_list(&mut conn, connection_global_world_id, user_id).await?,
+            connections,
+        );
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+fn handle_edit_friend_group(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CEditFriendGroup,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestEditFriendGroup incoming");
+
+    check_group_name(&packet.name)?;
+
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let mut group = get_own_group(&mut conn, user_id, packet.group_id).await?;
+        group.name = packet.name.clone();
+        friend::update_group(&mut conn, &group)
+            .await
+            .context("Can't update friend group")?;
+
+        send_message_to_connection(
+            assemble_friend_group_list(&mut conn, connection_global_world_id, user_id).await?,
+            connections,
+        );
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+fn handle_delete_friend_group(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CDeleteFriendGroup,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    friend_lists: &ViewMut<FriendList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestDeleteFriendGroup incoming");
+
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let group = get_own_group(&mut conn, user_id, packet.group_id).await?;
+        friend::delete_group_by_id(&mut conn, group.id)
+            .await
+            .context("Can't delete friend group")?;
+
+        // The friends of the group are moved out of the group.
+        send_message_to_connection(
+            assemble_friend_group_list(&mut conn, connection_global_world_id, user_id).await?,
+            connections,
+        );
+        send_message_to_connection(
+            assemble_friend_list(
+                &mut conn,
+                connection_global_world_id,
+                user_id,
+                spawns,
+                friend_lists,
+            )
+            .await?,
+            connections,
+        );
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+fn check_group_name(name: &str) -> Result<()> {
+    ensure!(!name.trim().is_empty(), "Friend group name is empty");
+    ensure!(
+        name.chars().count() <= MAX_GROUP_NAME_LENGTH,
+        "Friend group name {} is longer than {} characters",
+        name,
+        MAX_GROUP_NAME_LENGTH
+    );
+    Ok(())
+}
+
+/// Finds a friend group and makes sure it belongs to the user.
+async fn get_own_group(
+    conn: &mut PgConnection,
+    user_id: i32,
+    group_id: i32,
+) -> Result<FriendGroup> {
+    let group = friend::get_group_by_id(conn, group_id)
+        .await
+        .context(format!("Can't find friend group {}", group_id))?;
+    ensure!(
+        group.user_id == user_id,
+        "Friend group {} doesn't belong to user {}",
+        group_id,
+        user_id
+    );
+    Ok(group)
+}
+
+/// Tells the online friends of an user that it's online status changed.
+fn notify_friends(
+    user_id: i32,
+    online: bool,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    friend_lists: &ViewMut<FriendList>,
+) {
+    (spawns, friend_lists)
+        .iter()
+        .with_id()
+        .filter(|(_id, (spawn, friend_list))| {
+            !spawn.marked_for_deletion && friend_list.friends.contains(&user_id)
+        })
+        .for_each(|(id, _)| {
+            send_message_to_connection(
+                assemble_change_friend_state(id, user_id, online),
+                connections,
+            );
+        });
+}
+
+async fn assemble_friend_list(
+    conn: &mut PgConnection,
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    spawns: &View<GlobalUserSpawn>,
+    friend_lists: &ViewMut<FriendList>,
+) -> Result<EcsMessage> {
+    let mut friends = Vec::new();
+    for friend in friend::list(conn, user_id).await? {
+        let friend_user = user::get_by_id(conn, friend.friend_id).await?;
+        friends.push(SFriendListEntry {
+            name: friend_user.name,
+            memo: friend.memo,
+            user_id: friend.friend_id,
+            group_id: friend.group_id.unwrap_or(NO_GROUP_ID),
+            level: friend_user.level,
+            class: friend_user.class,
+            online: find_online_user(friend.friend_id, spawns)
+                .filter(|id| friend_lists.try_get(*id).is_ok())
+                .is_some(),
+        });
+    }
+
+    Ok(Box::new(ResponseFriendList {
+        connection_global_world_id,
+        packet: SFriendList { friends },
+    }))
+}
+
+async fn assemble_friend_group_list(
+    conn: &mut PgConnection,
+    connection_global_world_id: EntityId,
+    user_id: i32,
+) -> Result<EcsMessage> {
+    let groups = friend::list_groups(conn, user_id)
+        .await?
+        .into_iter()
+        .map(|group| SFriendGroupListEntry {
+            group_id: group.id,
+            name: group.name,
+        })
+        .collect();
+
+    Ok(Box::new(ResponseFriendGroupList {
+        connection_global_world_id,
+        packet: SFriendGroupList { groups },
+    }))
+}
+
+fn assemble_add_friend(
+    connection_global_world_id: EntityId,
+    user_name: &str,
+    message: &str,
+) -> EcsMessage {
+    Box::new(ResponseAddFriend {
+        connection_global_world_id,
+        packet: SAddFriend {
+            name: user_name.to_string(),
+            message: message.to_string(),
+        },
+    })
+}
+
+fn assemble_delete_friend(connection_global_world_id: EntityId, user_id: i32) -> EcsMessage {
+    Box::new(ResponseDeleteFriend {
+        connection_global_world_id,
+        packet: SDeleteFriend { user_id },
+    })
+}
+
+fn assemble_change_friend_state(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    online: bool,
+) -> EcsMessage {
+    Box::new(ResponseChangeFriendState {
+        connection_global_world_id,
+        packet: SChangeFriendState { user_id, online },
+    })
+}
+
+fn assemble_result_change_friend_memo(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    memo: &str,
+) -> EcsMessage {
+    Box::new(ResponseResultChangeFriendMemo {
+        connection_global_world_id,
+        packet: SResultChangeFriendMemo {
+            user_id,
+            memo: memo.to_string(),
+            success: true,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::UserSpawnStatus;
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
+    use crate::model::entity::{BlockedUser, User};
+    use crate::model::repository::account;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::tests::db_test;
+    use async_std::sync::{channel, Receiver};
+    use std::time::Instant;
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(pool);
+        world
+    }
+
+    async fn create_user(pool: &PgPool, num: i32) -> Result<User> {
+        let mut conn = pool.acquire().await?;
+        let account = account::create(&mut conn, &get_default_account(num)).await?;
+        user::create(&mut conn, &get_default_user(&account, num)).await
+    }
+
+    fn add_user(world: &World, user: &User) -> (EntityId, Receiver<EcsMessage>) {
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut spawns: ViewMut<GlobalUserSpawn>,
+             mut chatters: ViewMut<Chatter>| {
+                entities.add_entity(
+                    (&mut connections, &mut spawns, &mut chatters),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        GlobalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_local_world_id: None,
+                            local_world_id: None,
+                            local_world_channel: None,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
+                        },
+                        Chatter {
+                            user_name: user.name.clone(),
+                            window_start: Instant::now(),
+                            message_count: 0,
+                        },
+                    ),
+                )
+            },
+        );
+
+        run_message(
+            world,
+            Message::UserSpawned {
+                connection_global_world_id,
+            },
+        );
+
+        (connection_global_world_id, rx_channel)
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(friend_manager_system);
+        world.run(cleaner_system);
+    }
+
+    fn make_friends(
+        world: &World,
+        user: &User,
+        user_connection_id: EntityId,
+        friend: &User,
+        friend_connection_id: EntityId,
+    ) {
+        run_message(
+            world,
+            Message::RequestAddFriend {
+                connection_global_world_id: user_connection_id,
+                account_id: user.account_id,
+                user_id: user.id,
+                packet: CAddFriend {
+                    name: friend.name.clone(),
+                    message: "Let's be friends".to_string(),
+                },
+            },
+        );
+        run_message(
+            world,
+            Message::RequestAcceptFriend {
+                connection_global_world_id: friend_connection_id,
+                account_id: friend.account_id,
+                user_id: friend.id,
+                packet: CAcceptFriend {
+                    name: user.name.clone(),
+                },
+            },
+        );
+    }
+
+    fn assert_group_list(message: EcsMessage, group_count: usize) -> Vec<SFriendGroupListEntry> {
+        match &*message {
+            Message::ResponseFriendGroupList { packet, .. } => {
+                assert_eq!(packet.groups.len(), group_count);
+                packet.groups.clone()
+            }
+            _ => panic!("Message is not a ResponseFriendGroupList message"),
+        }
+    }
+
+    fn assert_friend_list(message: EcsMessage, friend_count: usize) -> Vec<SFriendListEntry> {
+        match &*message {
+            Message::ResponseFriendList { packet, .. } => {
+                assert_eq!(packet.friends.len(), friend_count);
+                packet.friends.clone()
+            }
+            _ => panic!("Message is not a ResponseFriendList message"),
+        }
+    }
+
+    fn assert_friend_state(message: EcsMessage, user_id: i32, online: bool) {
+        match &*message {
+            Message::ResponseChangeFriendState { packet, .. } => {
+                assert_eq!(packet.user_id, user_id);
+                assert_eq!(packet.online, online);
+            }
+            _ => panic!("Message is not a ResponseChangeFriendState message"),
+        }
+    }
+
+    #[test]
+    fn test_friend_list_on_spawn() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let world = setup(pool);
+            let (user_id, user_rx) = add_user(&world, &user);
+
+            assert_group_list(user_rx.try_recv()?, 0);
+            assert_friend_list(user_rx.try_recv()?, 0);
+            assert!(user_rx.is_empty());
+
+            // A relocation doesn't send the friend list again.
+            run_message(
+                &world,
+                Message::UserSpawned {
+                    connection_global_world_id: user_id,
+                },
+            );
+            assert!(user_rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_add_and_accept_friend() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let friend = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool.clone());
+            let (user_id, user_rx) = add_user(&world, &user);
+            let (friend_id, friend_rx) = add_user(&world, &friend);
+            user_rx.try_recv()?;
+            user_rx.try_recv()?;
+            friend_rx.try_recv()?;
+            friend_rx.try_recv()?;
+
+            make_friends(&world, &user, user_id, &friend, friend_id);
+
+            match &*friend_rx.try_recv()? {
+                Message::ResponseAddFriend { packet, .. } => {
+                    assert_eq!(packet.name, user.name);
+                    assert_eq!(packet.message, "Let's be friends");
+                }
+                _ => panic!("Message is not a ResponseAddFriend message"),
+            }
+
+            let friends = assert_friend_list(friend_rx.try_recv()?, 1);
+            assert_eq!(friends[0].user_id, user.id);
+            assert_eq!(friends[0].name, user.name);
+            assert!(friends[0].online);
+
+            let friends = assert_friend_list(user_rx.try_recv()?, 1);
+            assert_eq!(friends[0].user_id, friend.id);
+            assert_eq!(friends[0].group_id, NO_GROUP_ID);
+            assert!(friends[0].online);
+
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                assert!(friend::is_friend(&mut conn, user.id, friend.id).await?);
+                assert!(friend::is_friend(&mut conn, friend.id, user.id).await?);
+                assert!(friend::get_request(&mut conn, user.id, friend.id)
+                    .await
+                    .is_err());
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            // A friendship can't be accepted twice.
+            run_message(
+                &world,
+                Message::RequestAcceptFriend {
+                    connection_global_world_id: friend_id,
+                    account_id: friend.account_id,
+                    user_id: friend.id,
+                    packet: CAcceptFriend {
+                        name: user.name.clone(),
+                    },
+                },
+            );
+            assert!(friend_rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_add_friend_blocked() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let friend = task::block_on(async { create_user(&pool, 1).await })?;
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                blocked_user::create(
+                    &mut conn,
+                    &BlockedUser {
+                        user_id: friend.id,
+                        blocked_user_id: user.id,
+                        memo: "".to_string(),
+                        created_at: Utc::now(),
+                    },
+                )
+                .await?;
+                Ok::<(), anyhow::Error>(())
+            })?;
+            let world = setup(pool.clone());
+            let (user_id, _user_rx) = add_user(&world, &user);
+            let (_friend_id, friend_rx) = add_user(&world, &friend);
+            friend_rx.try_recv()?;
+            friend_rx.try_recv()?;
+
+            run_message(
+                &world,
+                Message::RequestAddFriend {
+                    connection_global_world_id: user_id,
+                    account_id: user.account_id,
+                    user_id: user.id,
+                    packet: CAddFriend {
+                        name: friend.name.clone(),
+                        message: "Hi".to_string(),
+                    },
+                },
+            );
+            assert!(friend_rx.is_empty());
+
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                assert!(friend::get_request(&mut conn, user.id, friend.id)
+                    .await
+                    .is_err());
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_friend_request_delivered_on_spawn() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let friend = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool);
+            let (user_id, _user_rx) = add_user(&world, &user);
+
+            run_message(
+                &world,
+                Message::RequestAddFriend {
+                    connection_global_world_id: user_id,
+                    account_id: user.account_id,
+                    user_id: user.id,
+                    packet: CAddFriend {
+                        name: "TESTUSER-1".to_string(),
+                        message: "Hi".to_string(),
+                    },
+                },
+            );
+
+            let (_friend_id, friend_rx) = add_user(&world, &friend);
+            assert_group_list(friend_rx.try_recv()?, 0);
+            assert_friend_list(friend_rx.try_recv()?, 0);
+            match &*friend_rx.try_recv()? {
+                Message::ResponseAddFriend { packet, .. } => {
+                    assert_eq!(packet.name, user.name);
+                    assert_eq!(packet.message, "Hi");
+                }
+                _ => panic!("Message is not a ResponseAddFriend message"),
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_online_state() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let friend = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool);
+            let (user_id, user_rx) = add_user(&world, &user);
+            let (friend_id, friend_rx) = add_user(&world, &friend);
+            make_friends(&world, &user, user_id, &friend, friend_id);
+            while !user_rx.is_empty() {
+                user_rx.try_recv()?;
+            }
+
+            // The connection manager dropped the connection of the friend.
+            world.run(|mut spawns: ViewMut<GlobalUserSpawn>| {
+                spawns[friend_id].marked_for_deletion = true;
+            });
+            world.run(friend_manager_system);
+
+            assert_friend_state(user_rx.try_recv()?, friend.id, false);
+            assert!(world
+                .run(|friend_lists: View<FriendList>| friend_lists.try_get(friend_id).is_err()));
+
+            world.run(|mut all_storages: AllStoragesViewMut| {
+                all_storages.delete(friend_id);
+            });
+            drop(friend_rx);
+
+            let (_friend_id, friend_rx) = add_user(&world, &friend);
+            assert_friend_state(user_rx.try_recv()?, friend.id, true);
+            assert_group_list(friend_rx.try_recv()?, 0);
+            let friends = assert_friend_list(friend_rx.try_recv()?, 1);
+            assert!(friends[0].online);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_delete_friend() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let friend = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool.clone());
+            let (user_id, user_rx) = add_user(&world, &user);
+            let (friend_id, friend_rx) = add_user(&world, &friend);
+            make_friends(&world, &user, user_id, &friend, friend_id);
+            while !user_rx.is_empty() {
+                user_rx.try_recv()?;
+            }
+            while !friend_rx.is_empty() {
+                friend_rx.try_recv()?;
+            }
+
+            run_message(
+                &world,
+                Message::RequestDeleteFriend {
+                    connection_global_world_id: user_id,
+                    account_id: user.account_id,
+                    user_id: user.id,
+                    packet: CDeleteFriend { user_id: friend.id },
+                },
+            );
+
+            match &*user_rx.try_recv()? {
+                Message::ResponseDeleteFriend { packet, .. } => {
+                    assert_eq!(packet.user_id, friend.id)
+                }
+                _ => panic!("Message is not a ResponseDeleteFriend message"),
+            }
+            match &*friend_rx.try_recv()? {
+                Message::ResponseDeleteFriend { packet, .. } => {
+                    assert_eq!(packet.user_id, user.id)
+                }
+                _ => panic!("Message is not a ResponseDeleteFriend message"),
+            }
+
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                assert!(!friend::is_friend(&mut conn, user.id, friend.id).await?);
+                assert!(!friend::is_friend(&mut conn, friend.id, user.id).await?);
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_change_friend_memo() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let friend = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool);
+            let (user_id, user_rx) = add_user(&world, &user);
+            let (friend_id, _friend_rx) = add_user(&world, &friend);
+            make_friends(&world, &user, user_id, &friend, friend_id);
+            while !user_rx.is_empty() {
+                user_rx.try_recv()?;
+            }
+
+            let change_memo = |memo: String| {
+                run_message(
+                    &world,
+                    Message::RequestChangeFriendMemo {
+                        connection_global_world_id: user_id,
+                        account_id: user.account_id,
+                        user_id: user.id,
+                        packet: CChangeFriendMemo {
+                            user_id: friend.id,
+                            memo,
+                        },
+                    },
+                )
+            };
+
+            change_memo("a".repeat(65));
+            assert!(user_rx.is_empty());
+
+            change_memo("Tank".to_string());
+            match &*user_rx.try_recv()? {
+                Message::ResponseResultChangeFriendMemo { packet, .. } => {
+                    assert_eq!(packet.user_id, friend.id);
+                    assert_eq!(packet.memo, "Tank");
+                    assert!(packet.success);
+                }
+                _ => panic!("Message is not a ResponseResultChangeFriendMemo message"),
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_friend_groups() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let user = task::block_on(async { create_user(&pool, 0).await })?;
+            let friend = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool);
+            let (user_id, user_rx) = add_user(&world, &user);
+            let (friend_id, _friend_rx) = add_user(&world, &friend);
+            make_friends(&world, &user, user_id, &friend, friend_id);
+            while !user_rx.is_empty() {
+                user_rx.try_recv()?;
+            }
+
+            run_message(
+                &world,
+                Message::RequestAddFriendGroup {
+                    connection_global_world_id: user_id,
+                    account_id: user.account_id,
+                    user_id: user.id,
+                    packet: CAddFriendGroup {
+                        name: "Guild".to_string(),
+                    },
+                },
+            );
+            let groups = assert_group_list(user_rx.try_recv()?, 1);
+            let group_id = groups[0].group_id;
+            assert_eq!(groups[0].name, "Guild");
+
+            run_message(
+                &world,
+                Message::RequestEditFriendGroup {
+                    connection_global_world_id: user_id,
+                    account_id: user.account_id,
+                    user_id: user.id,
+                    packet: CEditFriendGroup {
+                        group_id,
+                        name: "Raid".to_string(),
+                    },
+                },
+            );
+            let groups = assert_group_list(user_rx.try_recv()?, 1);
+            assert_eq!(groups[0].name, "Raid");
+
+            run_message(
+                &world,
+                Message::RequestUpdateFriendInfo {
+                    connection_global_world_id: user_id,
+                    account_id: user.account_id,
+                    user_id: user.id,
+                    packet: CUpdateFriendInfo {
+                        user_id: friend.id,
+                        group_id,
+                    },
+                },
+            );
+            let friends = assert_friend_list(user_rx.try_recv()?, 1);
+            assert_eq!(friends[0].group_id, group_id);
+
+            // Groups of other users can't be edited.
+            run_message(
+                &world,
+                Message::RequestDeleteFriendGroup {
+                    connection_global_world_id: friend_id,
+                    account_id: friend.account_id,
+                    user_id: friend.id,
+                    packet: CDeleteFriendGroup { group_id },
+                },
+            );
+
+            run_message(
+                &world,
+                Message::RequestDeleteFriendGroup {
+                    connection_global_world_id: user_id,
+                    account_id: user.account_id,
+                    user_id: user.id,
+                    packet: CDeleteFriendGroup { group_id },
+                },
+            );
+            assert_group_list(user_rx.try_recv()?, 0);
+            let friends = assert_friend_list(user_rx.try_recv()?, 1);
+            assert_eq!(friends[0].group_id, NO_GROUP_ID);
+
+            Ok(())
+        })
+    }
+}
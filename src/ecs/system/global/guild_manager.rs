@@ -0,0 +1,2008 @@
+use crate::ecs::component::{
+    BlockList, GlobalConnection, GlobalUserSpawn, GuildInvitation, UserGuild,
+};
+use crate::ecs::message::Message::{
+    ResponseBeginThroughArbiterContract, ResponseCreateGuildResult, ResponseDestroyGuild,
+    ResponseGuildInfo, ResponseGuildMemberList, ResponseImageData, ResponseLeaveGuild,
+    UserGuildChanged,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::GuildLogoCache;
+use crate::ecs::system::global::{find_online_user, is_blocked, send_message_to_connection};
+use crate::ecs::system::send_message;
+use crate::model::entity::{Guild, GuildGroup, GuildMember};
+use crate::model::repository::{guild, guild_logo, user};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use chrono::Utc;
+use lazy_static::lazy_static;
+use regex::Regex;
+use shipyard::*;
+use sqlx::{PgConnection, PgPool};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info_span};
+
+/// Contract type of the guild creation at the guild manager NPC.
+// TODO Verify the contract type once NPC dialogs are implemented.
+const GUILD_CREATE_CONTRACT: i32 = 8;
+
+/// Contract type of a guild invitation.
+const GUILD_INVITE_CONTRACT: i32 = 9;
+
+/// Time an user has to answer a guild invitation.
+const INVITATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximal number of members of a guild.
+const MAX_GUILD_SIZE: i64 = 150;
+
+/// Maximal number of groups of a guild.
+const MAX_GUILD_GROUPS: usize = 10;
+
+/// Authority of a guild group to invite users into the guild.
+const GUILD_AUTHORITY_INVITE: i32 = 1;
+
+/// Authority of a guild group to banish members from the guild.
+const GUILD_AUTHORITY_BANISH: i32 = 2;
+
+/// Rank name shown for the master of a guild.
+const GUILD_MASTER_RANK: &str = "Guild Master";
+
+/// Rank name shown for guild members that are not part of a guild group.
+const GUILD_MEMBER_RANK: &str = "Member";
+
+/// ID of the server the users are playing on.
+const SERVER_ID: i32 = 1;
+
+/// The guild manager handles the persistent guilds of the users. Only the guild master can
+/// manage the guild groups (ranks), while the authority of a group decides if it's members
+/// can invite or banish users. Changes of the guild or rank of an user are forwarded to the
+/// local world of the user, so that other users can see it. Guild logos are served from a cache.
+/// The guild of every spawned user is kept in it's `UserGuild` component.
+pub fn guild_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    spawns: View<GlobalUserSpawn>,
+    block_lists: View<BlockList>,
+    mut invitations: ViewMut<GuildInvitation>,
+    mut user_guilds: ViewMut<UserGuild>,
+    mut entities: EntitiesViewMut,
+    mut logo_cache: UniqueViewMut<GuildLogoCache>,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::UserSpawned {
+                connection_global_world_id,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_user_spawned(
+                    *connection_global_world_id,
+                    &spawns,
+                    &mut user_guilds,
+                    &entities,
+                    &pool,
+                ) {
+                    error!("Ignoring Message::UserSpawned: {:?}", e);
+                }
+            }
+            Message::RequestContract {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } if packet.contract_type == GUILD_CREATE_CONTRACT => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_create_guild(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &mut user_guilds,
+                    &entities,
+                    &pool,
+                ) {
+                    error!("Rejecting create guild request: {:?}", e);
+                    send_message_to_connection(
+                        assemble_create_guild_result(*connection_global_world_id, false),
+                        &connections,
+                    );
+                }
+            }
+            Message::RequestInviteUserToGuild {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_invite_user_to_guild(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &block_lists,
+                    &mut invitations,
+                    &mut entities,
+                    &pool,
+                ) {
+                    error!("Ignoring guild invite request: {:?}", e);
+                }
+            }
+            Message::RequestReplyThroughArbiterContract {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } if packet.contract_type == GUILD_INVITE_CONTRACT => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_reply_guild_invite(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &mut invitations,
+                    &mut user_guilds,
+                    &entities,
+                    &pool,
+                ) {
+                    error!("Ignoring guild invite reply: {:?}", e);
+                }
+            }
+            Message::RequestLeaveGuild {
+                connection_global_world_id,
+                user_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_leave_guild(
+                    *connection_global_world_id,
+                    *user_id,
+                    &connections,
+                    &spawns,
+                    &mut user_guilds,
+                    &pool,
+                ) {
+                    error!("Ignoring leave guild request: {:?}", e);
+                }
+            }
+            Message::RequestBanishGuildMember {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_banish_guild_member(
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &mut user_guilds,
+                    &pool,
+                ) {
+                    error!("Ignoring banish guild member request: {:?}", e);
+                }
+            }
+            Message::RequestChangeGuildChief {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) =
+                    handle_change_guild_chief(*user_id, &packet, &connections, &spawns, &pool)
+                {
+                    error!("Ignoring change guild chief request: {:?}", e);
+                }
+            }
+            Message::RequestDestroyGuild {
+                connection_global_world_id,
+                user_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) =
+                    handle_destroy_guild(*user_id, &connections, &spawns, &mut user_guilds, &pool)
+                {
+                    error!("Ignoring destroy guild request: {:?}", e);
+                }
+            }
+            Message::RequestCreateGuildGroup {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) =
+                    handle_create_guild_group(*user_id, &packet, &connections, &spawns, &pool)
+                {
+                    error!("Ignoring create guild group request: {:?}", e);
+                }
+            }
+            Message::RequestRemoveGuildGroup {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) =
+                    handle_remove_guild_group(*user_id, &packet, &connections, &spawns, &pool)
+                {
+                    error!("Ignoring remove guild group request: {:?}", e);
+                }
+            }
+            Message::RequestSetGuildGroupAuthority {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_set_guild_group_authority(
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &pool,
+                ) {
+                    error!("Ignoring set guild group authority request: {:?}", e);
+                }
+            }
+            Message::RequestChangeGuildGroup {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) =
+                    handle_change_guild_group(*user_id, &packet, &connections, &spawns, &pool)
+                {
+                    error!("Ignoring change guild group request: {:?}", e);
+                }
+            }
+            Message::RequestGuildInfo {
+                connection_global_world_id,
+                user_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_guild_info(
+                    *connection_global_world_id,
+                    *user_id,
+                    &connections,
+                    &spawns,
+                    &pool,
+                ) {
+                    error!("Ignoring guild info request: {:?}", e);
+                }
+            }
+            Message::RequestGetUserGuildLogo {
+                connection_global_world_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_get_user_guild_logo(
+                    *connection_global_world_id,
+                    &packet,
+                    &connections,
+                    &mut logo_cache,
+                    &pool,
+                ) {
+                    error!("Ignoring guild logo request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+
+    // Invitations expire after some time or when the invited user goes offline.
+    let expired_invitations: Vec<EntityId> = (&spawns, &invitations)
+        .iter()
+        .with_id()
+        .filter(|(_id, (spawn, invitation))| {
+            spawn.marked_for_deletion || invitation.created_at.elapsed() > INVITATION_TIMEOUT
+        })
+        .map(|(id, _)| id)
+        .collect();
+    for connection_global_world_id in expired_invitations {
+        invitations.delete(connection_global_world_id);
+    }
+}
+
+/// Remembers the guild of an user once it's spawned.
+fn handle_user_spawned(
+    connection_global_world_id: EntityId,
+    spawns: &View<GlobalUserSpawn>,
+    user_guilds: &mut ViewMut<UserGuild>,
+    entities: &EntitiesViewMut,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::UserSpawned incoming");
+
+    let spawn = spawns.try_get(connection_global_world_id).context(format!(
+        "Can't find user spawn {:?}",
+        connection_global_world_id
+    ))?;
+
+    let member = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+        Ok::<Option<GuildMember>, anyhow::Error>(
+            guild::get_member(&mut conn, spawn.user_id).await.ok(),
+        )
+    })?;
+
+    match member {
+        Some(member) => entities.add_component(
+            user_guilds,
+            UserGuild {
+                guild_id: member.guild_id,
+            },
+            connection_global_world_id,
+        ),
+        None => {
+            user_guilds.delete(connection_global_world_id);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_create_guild(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CRequestContract,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    user_guilds: &mut ViewMut<UserGuild>,
+    entities: &EntitiesViewMut,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestContract incoming");
+
+    ensure!(
+        is_valid_guild_name(&packet.name),
+        "Guild name {} is not valid",
+        packet.name
+    );
+
+    let guild_id = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        ensure!(
+            guild::get_member(&mut conn, user_id).await.is_err(),
+            "User {} is already in a guild",
+            user_id
+        );
+        ensure!(
+            !guild::is_name_taken(&mut conn, &packet.name).await?,
+            "Guild name {} is already taken",
+            packet.name
+        );
+
+        let guild = guild::create(
+            &mut conn,
+            &Guild {
+                id: -1,
+                name: packet.name.clone(),
+                master_id: user_id,
+                created_at: Utc::now(),
+            },
+        )
+        .await
+        .context("Can't create guild")?;
+        guild::add_member(
+            &mut conn,
+            &GuildMember {
+                user_id,
+                guild_id: guild.id,
+                group_id: None,
+                joined_at: Utc::now(),
+            },
+        )
+        .await
+        .context("Can't add guild master to the guild")?;
+        debug!("Guild {} created", guild.id);
+
+        send_message_to_connection(
+            assemble_create_guild_result(connection_global_world_id, true),
+            connections,
+        );
+        send_guild_info(&mut conn, guild.id, connections, spawns).await?;
+        send_guild_tag(&mut conn, user_id, spawns).await?;
+
+        Ok::<i32, anyhow::Error>(guild.id)
+    })?;
+
+    entities.add_component(
+        user_guilds,
+        UserGuild { guild_id },
+        connection_global_world_id,
+    );
+
+    Ok(())
+}
+
+fn handle_invite_user_to_guild(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CInviteUserToGuild,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    block_lists: &View<BlockList>,
+    invitations: &mut ViewMut<GuildInvitation>,
+    entities: &mut EntitiesViewMut,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestInviteUserToGuild incoming");
+
+    let (user_name, guild_id, invitee_id) = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (guild, member) = get_membership(&mut conn, user_id).await?;
+        ensure!(
+            has_authority(&mut conn, &guild, &member, GUILD_AUTHORITY_INVITE).await?,
+            "User {} is not allowed to invite users into guild {}",
+            user_id,
+            guild.id
+        );
+        ensure!(
+            guild::get_member_count(&mut conn, guild.id).await? < MAX_GUILD_SIZE,
+            "Guild {} is full",
+            guild.id
+        );
+
+        let user = user::get_by_id(&mut conn, user_id).await?;
+        let invitee = user::get_by_name(&mut conn, &packet.name)
+            .await
+            .context(format!("Can't find user {}", packet.name))?;
+        ensure!(
+            guild::get_member(&mut conn, invitee.id).await.is_err(),
+            "User {} is already in a guild",
+            invitee.id
+        );
+
+        Ok::<(String, i32, i32), anyhow::Error>((user.name, guild.id, invitee.id))
+    })?;
+
+    let invitee_connection_id = find_online_user(invitee_id, spawns)
+        .context(format!("User {} is not online", invitee_id))?;
+    ensure!(
+        !is_blocked(
+            connection_global_world_id,
+            user_id,
+            invitee_connection_id,
+            invitee_id,
+            block_lists
+        ),
+        "Guild invites between user {} and user {} are blocked",
+        user_id,
+        invitee_id
+    );
+    ensure!(
+        invitations.try_get(invitee_connection_id).is_err(),
+        "User {} already has a pending guild invitation",
+        invitee_id
+    );
+
+    entities.add_component(
+        &mut *invitations,
+        GuildInvitation {
+            inviter_id: connection_global_world_id,
+            guild_id,
+            created_at: Instant::now(),
+        },
+        invitee_connection_id,
+    );
+    send_message_to_connection(
+        assemble_begin_through_arbiter_contract(invitee_connection_id, &user_name, user_id),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_reply_guild_invite(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CReplyThroughArbiterContract,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    invitations: &mut ViewMut<GuildInvitation>,
+    user_guilds: &mut ViewMut<UserGuild>,
+    entities: &EntitiesViewMut,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestReplyThroughArbiterContract incoming");
+
+    let guild_id = invitations
+        .try_get(connection_global_world_id)
+        .context("User has no pending guild invitation")?
+        .guild_id;
+    invitations.delete(connection_global_world_id);
+
+    if !packet.accept {
+        debug!("User declined the guild invitation");
+        return Ok(());
+    }
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        guild::get_by_id(&mut conn, guild_id)
+            .await
+            .context(format!("Guild {} doesn't exist anymore", guild_id))?;
+        ensure!(
+            guild::get_member(&mut conn, user_id).await.is_err(),
+            "User {} is already in a guild",
+            user_id
+        );
+        ensure!(
+            guild::get_member_count(&mut conn, guild_id).await? < MAX_GUILD_SIZE,
+            "Guild {} is full",
+            guild_id
+        );
+
+        guild::add_member(
+            &mut conn,
+            &GuildMember {
+                user_id,
+                guild_id,
+                group_id: None,
+                joined_at: Utc::now(),
+            },
+        )
+        .await
+        .context(format!("Can't add user {} to guild {}", user_id, guild_id))?;
+
+        send_guild_info(&mut conn, guild_id, connections, spawns).await?;
+        send_guild_tag(&mut conn, user_id, spawns).await
+    })?;
+
+    entities.add_component(
+        user_guilds,
+        UserGuild { guild_id },
+        connection_global_world_id,
+    );
+
+    Ok(())
+}
+
+fn handle_leave_guild(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    user_guilds: &mut ViewMut<UserGuild>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestLeaveGuild incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (guild, _member) = get_membership(&mut conn, user_id).await?;
+        ensure!(
+            guild.master_id != user_id,
+            "The guild master {} can't leave guild {}",
+            user_id,
+            guild.id
+        );
+        guild::remove_member(&mut conn, user_id).await?;
+        user_guilds.delete(connection_global_world_id);
+
+        send_message_to_connection(
+            assemble_leave_guild(connection_global_world_id),
+            connections,
+        );
+        send_guild_info(&mut conn, guild.id, connections, spawns).await?;
+        send_guild_tag(&mut conn, user_id, spawns).await
+    })
+}
+
+fn handle_banish_guild_member(
+    user_id: i32,
+    packet: &CBanishGuildMember,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    user_guilds: &mut ViewMut<UserGuild>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestBanishGuildMember incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (guild, member) = get_membership(&mut conn, user_id).await?;
+        ensure!(
+            has_authority(&mut conn, &guild, &member, GUILD_AUTHORITY_BANISH).await?,
+            "User {} is not allowed to banish members of guild {}",
+            user_id,
+            guild.id
+        );
+        let target = get_member_by_name(&mut conn, &guild, &packet.name).await?;
+        ensure!(
+            target.user_id != guild.master_id,
+            "The guild master of guild {} can't be banished",
+            guild.id
+        );
+        ensure!(
+            target.user_id != user_id,
+            "User {} can't banish itself",
+            user_id
+        );
+        guild::remove_member(&mut conn, target.user_id).await?;
+
+        if let Some(target_connection_id) = find_online_user(target.user_id, spawns) {
+            user_guilds.delete(target_connection_id);
+            send_message_to_connection(assemble_leave_guild(target_connection_id), connections);
+        }
+        send_guild_info(&mut conn, guild.id, connections, spawns).await?;
+        send_guild_tag(&mut conn, target.user_id, spawns).await
+    })
+}
+
+fn handle_change_guild_chief(
+    user_id: i32,
+    packet: &CChangeGuildChief,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestChangeGuildChief incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (mut guild, _member) = get_membership(&mut conn, user_id).await?;
+        ensure_master(&guild, user_id)?;
+        let target = get_member_by_name(&mut conn, &guild, &packet.name).await?;
+        ensure!(
+            target.user_id != user_id,
+            "User {} is already the guild master",
+            user_id
+        );
+
+        guild.master_id = target.user_id;
+        let guild = guild::update(&mut conn, &guild).await?;
+
+        send_guild_info(&mut conn, guild.id, connections, spawns).await?;
+        send_guild_tag(&mut conn, user_id, spawns).await?;
+        send_guild_tag(&mut conn, target.user_id, spawns).await
+    })
+}
+
+fn handle_destroy_guild(
+    user_id: i32,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    user_guilds: &mut ViewMut<UserGuild>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestDestroyGuild incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (guild, _member) = get_membership(&mut conn, user_id).await?;
+        ensure_master(&guild, user_id)?;
+        let members = guild::list_members(&mut conn, guild.id).await?;
+        guild::delete_by_id(&mut conn, guild.id).await?;
+        debug!("Guild {} destroyed", guild.id);
+
+        for member in members.iter() {
+            if let Some(member_connection_id) = find_online_user(member.user_id, spawns) {
+                user_guilds.delete(member_connection_id);
+                send_message_to_connection(
+                    assemble_destroy_guild(member_connection_id),
+                    connections,
+                );
+            }
+            send_guild_tag(&mut conn, member.user_id, spawns).await?;
+        }
+
+        Ok(())
+    })
+}
+
+fn handle_create_guild_group(
+    user_id: i32,
+    packet: &CCreateGuildGroup,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestCreateGuildGroup incoming");
+
+    ensure!(
+        is_valid_guild_name(&packet.name),
+        "Guild group name {} is not valid",
+        packet.name
+    );
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (guild, _member) = get_membership(&mut conn, user_id).await?;
+        ensure_master(&guild, user_id)?;
+        ensure!(
+            guild::list_groups(&mut conn, guild.id).await?.len() < MAX_GUILD_GROUPS,
+            "Guild {} has reached the maximal number of groups",
+            guild.id
+        );
+
+        guild::create_group(
+            &mut conn,
+            &GuildGroup {
+                id: -1,
+                guild_id: guild.id,
+                name: packet.name.clone(),
+                authority: 0,
+            },
+        )
+        .await
+        .context("Can't create guild group")?;
+
+        send_guild_info(&mut conn, guild.id, connections, spawns).await
+    })
+}
+
+fn handle_remove_guild_group(
+    user_id: i32,
+    packet: &CRemoveGuildGroup,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestRemoveGuildGroup incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (guild, _member) = get_membership(&mut conn, user_id).await?;
+        ensure_master(&guild, user_id)?;
+        let group = get_group(&mut conn, &guild, packet.group_id).await?;
+
+        // The members of the group fall back to the default rank.
+        let group_member_ids: Vec<i32> = guild::list_members(&mut conn, guild.id)
+            .await?
+            .iter()
+            .filter(|member| member.group_id == Some(group.id))
+            .map(|member| member.user_id)
+            .collect();
+        guild::delete_group_by_id(&mut conn, group.id).await?;
+
+        send_guild_info(&mut conn, guild.id, connections, spawns).await?;
+        for member_id in group_member_ids {
+            send_guild_tag(&mut conn, member_id, spawns).await?;
+        }
+
+        Ok(())
+    })
+}
+
+fn handle_set_guild_group_authority(
+    user_id: i32,
+    packet: &CSetGuildGroupAuthority,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestSetGuildGroupAuthority incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (guild, _member) = get_membership(&mut conn, user_id).await?;
+        ensure_master(&guild, user_id)?;
+        let mut group = get_group(&mut conn, &guild, packet.group_id).await?;
+        group.authority = packet.authority;
+        guild::update_group(&mut conn, &group).await?;
+
+        send_guild_info(&mut conn, guild.id, connections, spawns).await
+    })
+}
+
+fn handle_change_guild_group(
+    user_id: i32,
+    packet: &CChangeGuildGroup,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestChangeGuildGroup incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (guild, _member) = get_membership(&mut conn, user_id).await?;
+        ensure_master(&guild, user_id)?;
+        let mut target = get_member_by_name(&mut conn, &guild, &packet.name).await?;
+        target.group_id = if packet.group_id == 0 {
+            None
+        } else {
+            Some(get_group(&mut conn, &guild, packet.group_id).await?.id)
+        };
+        guild::update_member(&mut conn, &target).await?;
+
+        send_guild_info(&mut conn, guild.id, connections, spawns).await?;
+        send_guild_tag(&mut conn, target.user_id, spawns).await
+    })
+}
+
+fn handle_guild_info(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestGuildInfo incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (guild, _member) = get_membership(&mut conn, user_id).await?;
+        let (info, member_list) = get_guild_packets(&mut conn, &guild, spawns).await?;
+
+        send_message_to_connection(
+            assemble_guild_info(connection_global_world_id, info),
+            connections,
+        );
+        send_message_to_connection(
+            assemble_guild_member_list(connection_global_world_id, member_list),
+            connections,
+        );
+
+        Ok(())
+    })
+}
+
+fn handle_get_user_guild_logo(
+    connection_global_world_id: EntityId,
+    packet: &CGetUserGuildLogo,
+    connections: &View<GlobalConnection>,
+    logo_cache: &mut UniqueViewMut<GuildLogoCache>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestGetUserGuildLogo incoming");
+
+    let (version, data) = match logo_cache.get(packet.guild_id) {
+        Some(entry) => entry,
+        None => {
+            let logo = task::block_on(async {
+                let mut conn = pool
+                    .acquire()
+                    .await
+                    .context("Couldn't acquire connection from pool")?;
+                guild_logo::get_by_guild_id(&mut conn, packet.guild_id)
+                    .await
+                    .context(format!("Guild {} has no logo", packet.guild_id))
+            })?;
+            (
+                logo.version,
+                logo_cache.insert(logo.guild_id, logo.version, logo.data),
+            )
+        }
+    };
+
+    send_message_to_connection(
+        assemble_image_data(
+            connection_global_world_id,
+            guild_logo_name(packet.guild_id, version),
+            &data,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+/// Returns the guild of an user and the membership of the user.
+async fn get_membership(conn: &mut PgConnection, user_id: i32) -> Result<(Guild, GuildMember)> {
+    let member = guild::get_member(conn, user_id)
+        .await
+        .context(format!("User {} is not in a guild", user_id))?;
+    let guild = guild::get_by_id(conn, member.guild_id)
+        .await
+        .context(format!("Can't find guild {}", member.guild_id))?;
+    Ok((guild, member))
+}
+
+async fn get_member_by_name(
+    conn: &mut PgConnection,
+    guild: &Guild,
+    name: &str,
+) -> Result<GuildMember> {
+    guild::get_member_by_user_name(conn, guild.id, name)
+        .await
+        .context(format!(
+            "User {} is not a member of guild {}",
+            name, guild.id
+        ))
+}
+
+async fn get_group(conn: &mut PgConnection, guild: &Guild, group_id: i32) -> Result<GuildGroup> {
+    let group = guild::get_group_by_id(conn, group_id)
+        .await
+        .context(format!("Can't find guild group {}", group_id))?;
+    ensure!(
+        group.guild_id == guild.id,
+        "Guild group {} is not part of guild {}",
+        group_id,
+        guild.id
+    );
+    Ok(group)
+}
+
+/// The guild master has all authorities, other members have the authorities of their group.
+async fn has_authority(
+    conn: &mut PgConnection,
+    guild: &Guild,
+    member: &GuildMember,
+    authority: i32,
+) -> Result<bool> {
+    if guild.master_id == member.user_id {
+        return Ok(true);
+    }
+    Ok(match member.group_id {
+        Some(group_id) => {
+            (guild::get_group_by_id(conn, group_id).await?.authority & authority) != 0
+        }
+        None => false,
+    })
+}
+
+fn ensure_master(guild: &Guild, user_id: i32) -> Result<()> {
+    ensure!(
+        guild.master_id == user_id,
+        "User {} is not the guild master of guild {}",
+        user_id,
+        guild.id
+    );
+    Ok(())
+}
+
+/// Only alphanumeric characters separated by single spaces are allowed.
+fn is_valid_guild_name(text: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r#"^[[:alnum:]]+( [[:alnum:]]+)*$"#).unwrap();
+    }
+    RE.is_match(text)
+}
+
+/// The client caches images by their name, so the name contains the version of the logo.
+fn guild_logo_name(guild_id: i32, version: i32) -> String {
+    format!("guildlogo_{}_{}_{}", SERVER_ID, guild_id, version)
+}
+
+async fn get_guild_packets(
+    conn: &mut PgConnection,
+    guild: &Guild,
+    spawns: &View<GlobalUserSpawn>,
+) -> Result<(SGuildInfo, SGuildMemberList)> {
+    let master = user::get_by_id(conn, guild.master_id)
+        .await
+        .context(format!("Can't find guild master {}", guild.master_id))?;
+
+    let groups = guild::list_groups(conn, guild.id)
+        .await?
+        .into_iter()
+        .map(|group| SGuildInfoGroup {
+            name: group.name,
+            group_id: group.id,
+            authority: group.authority,
+        })
+        .collect();
+
+    let mut members = Vec::new();
+    for member in guild::list_members(conn, guild.id).await? {
+        let user = user::get_by_id(conn, member.user_id).await?;
+        members.push(SGuildMemberListEntry {
+            name: user.name,
+            user_id: user.id,
+            level: user.level,
+            class: user.class,
+            group_id: member.group_id.unwrap_or(0),
+            online: find_online_user(user.id, spawns).is_some(),
+        });
+    }
+
+    Ok((
+        SGuildInfo {
+            groups,
+            name: guild.name.clone(),
+            master_name: master.name,
+            guild_id: guild.id,
+            master_id: guild.master_id,
+            created_at: guild.created_at.timestamp(),
+        },
+        SGuildMemberList { members },
+    ))
+}
+
+/// Sends the guild info and member list to all online members of the guild.
+async fn send_guild_info(
+    conn: &mut PgConnection,
+    guild_id: i32,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+) -> Result<()> {
+    let guild = guild::get_by_id(conn, guild_id)
+        .await
+        .context(format!("Can't find guild {}", guild_id))?;
+    let (info, member_list) = get_guild_packets(conn, &guild, spawns).await?;
+
+    for member in member_list.members.iter().filter(|member| member.online) {
+        if let Some(member_connection_id) = find_online_user(member.user_id, spawns) {
+            send_message_to_connection(
+                assemble_guild_info(member_connection_id, info.clone()),
+                connections,
+            );
+            send_message_to_connection(
+                assemble_guild_member_list(member_connection_id, member_list.clone()),
+                connections,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the name of the guild of the user and the rank of the user inside it.
+/// Both strings are empty if the user is not a member of any guild.
+pub async fn get_guild_tag(conn: &mut PgConnection, user_id: i32) -> Result<(String, String)> {
+    let member = match guild::get_member(conn, user_id).await {
+        Ok(member) => member,
+        Err(_) => return Ok((String::new(), String::new())),
+    };
+    let guild = guild::get_by_id(conn, member.guild_id)
+        .await
+        .context(format!("Can't query guild {}", member.guild_id))?;
+
+    let rank = if guild.master_id == user_id {
+        GUILD_MASTER_RANK.to_string()
+    } else if let Some(group_id) = member.group_id {
+        guild::get_group_by_id(conn, group_id)
+            .await
+            .context(format!("Can't query guild group {}", group_id))?
+            .name
+    } else {
+        GUILD_MEMBER_RANK.to_string()
+    };
+
+    Ok((guild.name, rank))
+}
+
+/// Informs the local world of a spawned user about the current guild name and rank of the user.
+async fn send_guild_tag(
+    conn: &mut PgConnection,
+    user_id: i32,
+    spawns: &View<GlobalUserSpawn>,
+) -> Result<()> {
+    let spawn = match find_online_user(user_id, spawns) {
+        Some(connection_global_world_id) => &spawns[connection_global_world_id],
+        None => return Ok(()),
+    };
+
+    if let (Some(connection_local_world_id), Some(local_world_channel)) =
+        (spawn.connection_local_world_id, &spawn.local_world_channel)
+    {
+        let (guild_name, guild_rank) = get_guild_tag(conn, user_id).await?;
+        send_message(
+            assemble_user_guild_changed(connection_local_world_id, guild_name, guild_rank),
+            local_world_channel,
+        );
+    }
+
+    Ok(())
+}
+
+fn assemble_begin_through_arbiter_contract(
+    connection_global_world_id: EntityId,
+    name: &str,
+    contract_id: i32,
+) -> EcsMessage {
+    Box::new(ResponseBeginThroughArbiterContract {
+        connection_global_world_id,
+        packet: SBeginThroughArbiterContract {
+            name: name.to_string(),
+            data: vec![],
+            contract_type: GUILD_INVITE_CONTRACT,
+            contract_id,
+        },
+    })
+}
+
+fn assemble_create_guild_result(connection_global_world_id: EntityId, ok: bool) -> EcsMessage {
+    Box::new(ResponseCreateGuildResult {
+        connection_global_world_id,
+        packet: SCreateGuildResult { ok },
+    })
+}
+
+fn assemble_guild_info(connection_global_world_id: EntityId, packet: SGuildInfo) -> EcsMessage {
+    Box::new(ResponseGuildInfo {
+        connection_global_world_id,
+        packet,
+    })
+}
+
+fn assemble_guild_member_list(
+    connection_global_world_id: EntityId,
+    packet: SGuildMemberList,
+) -> EcsMessage {
+    Box::new(ResponseGuildMemberList {
+        connection_global_world_id,
+        packet,
+    })
+}
+
+fn assemble_image_data(
+    connection_global_world_id: EntityId,
+    name: String,
+    data: &[u8],
+) -> EcsMessage {
+    Box::new(ResponseImageData {
+        connection_global_world_id,
+        packet: SImageData {
+            name,
+            data: data.to_vec(),
+        },
+    })
+}
+
+fn assemble_leave_guild(connection_global_world_id: EntityId) -> EcsMessage {
+    Box::new(ResponseLeaveGuild {
+        connection_global_world_id,
+        packet: SLeaveGuild {},
+    })
+}
+
+fn assemble_destroy_guild(connection_global_world_id: EntityId) -> EcsMessage {
+    Box::new(ResponseDestroyGuild {
+        connection_global_world_id,
+        packet: SDestroyGuild {},
+    })
+}
+
+fn assemble_user_guild_changed(
+    connection_local_world_id: EntityId,
+    guild_name: String,
+    guild_rank: String,
+) -> EcsMessage {
+    Box::new(UserGuildChanged {
+        connection_local_world_id,
+        guild_name,
+        guild_rank,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::UserSpawnStatus;
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
+    use crate::model::entity::User;
+    use crate::model::repository::account;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::guild_logo::tests::get_default_logo_data;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::tests::db_test;
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver};
+
+    struct TestUser {
+        user: User,
+        connection_global_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(GuildLogoCache::default());
+        world.add_unique(pool);
+        world
+    }
+
+    async fn create_user(pool: &PgPool, num: i32) -> Result<User> {
+        let mut conn = pool.acquire().await?;
+        let account = account::create(&mut conn, &get_default_account(num)).await?;
+        user::create(&mut conn, &get_default_user(&account, num)).await
+    }
+
+    fn add_user(world: &World, pool: &PgPool, num: i32) -> Result<TestUser> {
+        let user = task::block_on(async { create_user(pool, num).await })?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut spawns: ViewMut<GlobalUserSpawn>,
+             mut block_lists: ViewMut<BlockList>| {
+                entities.add_entity(
+                    (&mut connections, &mut spawns, &mut block_lists),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        GlobalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_local_world_id: None,
+                            local_world_id: None,
+                            local_world_channel: None,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
+                        },
+                        BlockList::default(),
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            user,
+            connection_global_world_id,
+            rx: rx_channel,
+        })
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(guild_manager_system);
+        world.run(cleaner_system);
+    }
+
+    fn create_guild(world: &World, master: &TestUser, name: &str) {
+        run_message(
+            world,
+            Message::RequestContract {
+                connection_global_world_id: master.connection_global_world_id,
+                account_id: master.user.account_id,
+                user_id: master.user.id,
+                packet: CRequestContract {
+                    name: name.to_string(),
+                    data: vec![],
+                    contract_type: GUILD_CREATE_CONTRACT,
+                },
+            },
+        );
+    }
+
+    fn invite(world: &World, inviter: &TestUser, invitee: &TestUser) {
+        run_message(
+            world,
+            Message::RequestInviteUserToGuild {
+                connection_global_world_id: inviter.connection_global_world_id,
+                account_id: inviter.user.account_id,
+                user_id: inviter.user.id,
+                packet: CInviteUserToGuild {
+                    name: invitee.user.name.to_uppercase(),
+                },
+            },
+        );
+    }
+
+    fn reply(world: &World, invitee: &TestUser, accept: bool) {
+        run_message(
+            world,
+            Message::RequestReplyThroughArbiterContract {
+                connection_global_world_id: invitee.connection_global_world_id,
+                account_id: invitee.user.account_id,
+                user_id: invitee.user.id,
+                packet: CReplyThroughArbiterContract {
+                    contract_type: GUILD_INVITE_CONTRACT,
+                    contract_id: 0,
+                    accept,
+                },
+            },
+        );
+    }
+
+    fn banish(world: &World, user: &TestUser, target: &TestUser) {
+        run_message(
+            world,
+            Message::RequestBanishGuildMember {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CBanishGuildMember {
+                    name: target.user.name.clone(),
+                },
+            },
+        );
+    }
+
+    fn create_group(world: &World, user: &TestUser, name: &str) {
+        run_message(
+            world,
+            Message::RequestCreateGuildGroup {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CCreateGuildGroup {
+                    name: name.to_string(),
+                },
+            },
+        );
+    }
+
+    fn change_group(world: &World, user: &TestUser, target: &TestUser, group_id: i32) {
+        run_message(
+            world,
+            Message::RequestChangeGuildGroup {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CChangeGuildGroup {
+                    name: target.user.name.clone(),
+                    group_id,
+                },
+            },
+        );
+    }
+
+    fn set_authority(world: &World, user: &TestUser, group_id: i32, authority: i32) {
+        run_message(
+            world,
+            Message::RequestSetGuildGroupAuthority {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CSetGuildGroupAuthority {
+                    group_id,
+                    authority,
+                },
+            },
+        );
+    }
+
+    /// Creates a guild with the master and the given members.
+    fn make_guild(world: &World, master: &TestUser, members: &[&TestUser]) {
+        create_guild(world, master, "Manhunter");
+        for invitee in members.iter() {
+            invite(world, master, invitee);
+            reply(world, invitee, true);
+        }
+        clear_messages(master);
+        members.iter().for_each(|member| clear_messages(member));
+    }
+
+    /// Creates a guild group with the given authority and returns it's ID.
+    fn make_group(world: &World, pool: &PgPool, master: &TestUser, authority: i32) -> Result<i32> {
+        create_group(world, master, "Officers");
+        let group_id = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let (guild, _member) = get_membership(&mut conn, master.user.id).await?;
+            Ok::<i32, anyhow::Error>(guild::list_groups(&mut conn, guild.id).await?[0].id)
+        })?;
+        set_authority(world, master, group_id, authority);
+        clear_messages(master);
+        Ok(group_id)
+    }
+
+    fn clear_messages(user: &TestUser) {
+        while user.rx.try_recv().is_ok() {}
+    }
+
+    fn get_membership_of(pool: &PgPool, user: &TestUser) -> Option<(Guild, GuildMember)> {
+        task::block_on(async {
+            let mut conn = pool.acquire().await.ok()?;
+            get_membership(&mut conn, user.user.id).await.ok()
+        })
+    }
+
+    fn get_user_guild(world: &World, user: &TestUser) -> Option<UserGuild> {
+        world.run(|user_guilds: View<UserGuild>| {
+            user_guilds
+                .try_get(user.connection_global_world_id)
+                .ok()
+                .cloned()
+        })
+    }
+
+    fn assert_guild_info(message: EcsMessage, master: &TestUser) -> SGuildInfo {
+        match &*message {
+            Message::ResponseGuildInfo { packet, .. } => {
+                assert_eq!(packet.name, "Manhunter");
+                assert_eq!(packet.master_name, master.user.name);
+                assert_eq!(packet.master_id, master.user.id);
+                packet.clone()
+            }
+            _ => panic!("Message is not a ResponseGuildInfo message"),
+        }
+    }
+
+    fn assert_member_list(message: EcsMessage, member_count: usize) -> SGuildMemberList {
+        match &*message {
+            Message::ResponseGuildMemberList { packet, .. } => {
+                assert_eq!(packet.members.len(), member_count);
+                packet.clone()
+            }
+            _ => panic!("Message is not a ResponseGuildMemberList message"),
+        }
+    }
+
+    fn assert_create_guild_result(message: EcsMessage, ok: bool) {
+        match &*message {
+            Message::ResponseCreateGuildResult { packet, .. } => assert_eq!(packet.ok, ok),
+            _ => panic!("Message is not a ResponseCreateGuildResult message"),
+        }
+    }
+
+    fn assert_leave_guild(message: EcsMessage) {
+        match &*message {
+            Message::ResponseLeaveGuild { .. } => {}
+            _ => panic!("Message is not a ResponseLeaveGuild message"),
+        }
+    }
+
+    #[test]
+    fn test_create_guild() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+
+            create_guild(&world, &master, "Manhunter");
+
+            assert_create_guild_result(master.rx.try_recv()?, true);
+            let info = assert_guild_info(master.rx.try_recv()?, &master);
+            assert!(info.groups.is_empty());
+            let list = assert_member_list(master.rx.try_recv()?, 1);
+            assert_eq!(list.members[0].user_id, master.user.id);
+            assert_eq!(list.members[0].group_id, 0);
+            assert!(list.members[0].online);
+
+            let (guild, member) = get_membership_of(&pool, &master).unwrap();
+            assert_eq!(guild.name, "Manhunter");
+            assert_eq!(guild.master_id, master.user.id);
+            assert_eq!(member.group_id, None);
+            assert_eq!(
+                get_user_guild(&world, &master),
+                Some(UserGuild { guild_id: guild.id })
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_create_guild_rejected() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let other = add_user(&world, &pool, 1)?;
+            make_guild(&world, &master, &[]);
+
+            // Taken names, invalid names and users that are already in a guild are rejected.
+            create_guild(&world, &other, "MANHUNTER");
+            create_guild(&world, &other, "Man  hunter!");
+            create_guild(&world, &master, "Gantsu");
+            assert_create_guild_result(other.rx.try_recv()?, false);
+            assert_create_guild_result(other.rx.try_recv()?, false);
+            assert_create_guild_result(master.rx.try_recv()?, false);
+            assert!(get_membership_of(&pool, &other).is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_invite_and_accept() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            make_guild(&world, &master, &[]);
+
+            invite(&world, &master, &member);
+
+            match &*member.rx.try_recv()? {
+                Message::ResponseBeginThroughArbiterContract { packet, .. } => {
+                    assert_eq!(packet.name, master.user.name);
+                    assert_eq!(packet.contract_type, GUILD_INVITE_CONTRACT);
+                    assert_eq!(packet.contract_id, master.user.id);
+                }
+                _ => panic!("Message is not a ResponseBeginThroughArbiterContract message"),
+            }
+
+            reply(&world, &member, true);
+
+            for user in [&master, &member].iter() {
+                assert_guild_info(user.rx.try_recv()?, &master);
+                let list = assert_member_list(user.rx.try_recv()?, 2);
+                assert_eq!(list.members[1].name, member.user.name);
+                assert_eq!(list.members[1].level, member.user.level);
+                assert_eq!(list.members[1].class, member.user.class);
+            }
+
+            let (master_guild, _) = get_membership_of(&pool, &master).unwrap();
+            let (member_guild, _) = get_membership_of(&pool, &member).unwrap();
+            assert_eq!(master_guild, member_guild);
+
+            // Members can't be invited again and members without authority can't invite.
+            let third = add_user(&world, &pool, 2)?;
+            invite(&world, &master, &member);
+            invite(&world, &member, &third);
+            assert!(member.rx.is_empty());
+            assert!(third.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_invite_declined_and_blocked() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            make_guild(&world, &master, &[]);
+
+            invite(&world, &master, &member);
+            member.rx.try_recv()?;
+            reply(&world, &member, false);
+            assert!(master.rx.is_empty());
+            assert!(get_membership_of(&pool, &member).is_none());
+
+            // The invitation was consumed by the reply.
+            reply(&world, &member, true);
+            assert!(get_membership_of(&pool, &member).is_none());
+
+            world.run(|mut block_lists: ViewMut<BlockList>| {
+                block_lists[member.connection_global_world_id]
+                    .blocked_users
+                    .insert(master.user.id);
+            });
+            invite(&world, &master, &member);
+            assert!(member.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_invitation_timeout() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            make_guild(&world, &master, &[]);
+
+            invite(&world, &master, &member);
+            member.rx.try_recv()?;
+            world.run(|mut invitations: ViewMut<GuildInvitation>| {
+                invitations[member.connection_global_world_id].created_at =
+                    Instant::now() - INVITATION_TIMEOUT - Duration::from_secs(1);
+            });
+            world.run(guild_manager_system);
+
+            reply(&world, &member, true);
+            assert!(master.rx.is_empty());
+            assert!(get_membership_of(&pool, &member).is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_leave_guild() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            make_guild(&world, &master, &[&member]);
+
+            for user in [&master, &member].iter() {
+                run_message(
+                    &world,
+                    Message::RequestLeaveGuild {
+                        connection_global_world_id: user.connection_global_world_id,
+                        account_id: user.user.account_id,
+                        user_id: user.user.id,
+                        packet: CLeaveGuild {},
+                    },
+                );
+            }
+
+            // The guild master can't leave it's guild.
+            assert!(get_membership_of(&pool, &master).is_some());
+            assert!(get_membership_of(&pool, &member).is_none());
+            assert!(get_user_guild(&world, &master).is_some());
+            assert!(get_user_guild(&world, &member).is_none());
+
+            assert_leave_guild(member.rx.try_recv()?);
+            assert!(member.rx.is_empty());
+            assert_guild_info(master.rx.try_recv()?, &master);
+            assert_member_list(master.rx.try_recv()?, 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_banish_guild_member() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let officer = add_user(&world, &pool, 1)?;
+            let member = add_user(&world, &pool, 2)?;
+            make_guild(&world, &master, &[&officer, &member]);
+
+            // Members without authority can't banish.
+            banish(&world, &member, &officer);
+            assert!(get_membership_of(&pool, &officer).is_some());
+
+            let group_id = make_group(&world, &pool, &master, GUILD_AUTHORITY_BANISH)?;
+            change_group(&world, &master, &officer, group_id);
+            [&master, &officer, &member]
+                .iter()
+                .for_each(|user| clear_messages(user));
+
+            // The guild master can't be banished.
+            banish(&world, &officer, &master);
+            assert!(get_membership_of(&pool, &master).is_some());
+
+            banish(&world, &officer, &member);
+            assert!(get_membership_of(&pool, &member).is_none());
+            assert!(get_user_guild(&world, &member).is_none());
+            assert_leave_guild(member.rx.try_recv()?);
+            for user in [&master, &officer].iter() {
+                assert_guild_info(user.rx.try_recv()?, &master);
+                assert_member_list(user.rx.try_recv()?, 2);
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_change_guild_chief() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            make_guild(&world, &master, &[&member]);
+
+            for (user, target) in [(&member, &member), (&master, &member)].iter() {
+                run_message(
+                    &world,
+                    Message::RequestChangeGuildChief {
+                        connection_global_world_id: user.connection_global_world_id,
+                        account_id: user.user.account_id,
+                        user_id: user.user.id,
+                        packet: CChangeGuildChief {
+                            name: target.user.name.clone(),
+                        },
+                    },
+                );
+            }
+
+            let (guild, _) = get_membership_of(&pool, &master).unwrap();
+            assert_eq!(guild.master_id, member.user.id);
+            for user in [&master, &member].iter() {
+                assert_guild_info(user.rx.try_recv()?, &member);
+                assert_member_list(user.rx.try_recv()?, 2);
+                assert!(user.rx.is_empty());
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_destroy_guild() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            make_guild(&world, &master, &[&member]);
+
+            for user in [&member, &master].iter() {
+                run_message(
+                    &world,
+                    Message::RequestDestroyGuild {
+                        connection_global_world_id: user.connection_global_world_id,
+                        account_id: user.user.account_id,
+                        user_id: user.user.id,
+                        packet: CDestroyGuild {},
+                    },
+                );
+            }
+
+            for user in [&master, &member].iter() {
+                match &*user.rx.try_recv()? {
+                    Message::ResponseDestroyGuild { .. } => {}
+                    _ => panic!("Message is not a ResponseDestroyGuild message"),
+                }
+                assert!(user.rx.is_empty());
+                assert!(get_membership_of(&pool, user).is_none());
+                assert!(get_user_guild(&world, user).is_none());
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_user_guild_is_loaded_on_spawn() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            let guildless = add_user(&world, &pool, 2)?;
+            make_guild(&world, &master, &[&member]);
+            let guild_id = get_user_guild(&world, &master).unwrap().guild_id;
+            assert_eq!(
+                get_user_guild(&world, &member),
+                Some(UserGuild { guild_id })
+            );
+
+            // Users that log in again get their guild back.
+            world.run(|mut user_guilds: ViewMut<UserGuild>| {
+                user_guilds.delete(member.connection_global_world_id);
+            });
+            for user in [&member, &guildless].iter() {
+                run_message(
+                    &world,
+                    Message::UserSpawned {
+                        connection_global_world_id: user.connection_global_world_id,
+                    },
+                );
+            }
+            assert_eq!(
+                get_user_guild(&world, &member),
+                Some(UserGuild { guild_id })
+            );
+            assert!(get_user_guild(&world, &guildless).is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_guild_groups() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            let third = add_user(&world, &pool, 2)?;
+            make_guild(&world, &master, &[&member]);
+
+            // Only the guild master can manage the groups.
+            create_group(&world, &member, "Officers");
+            assert!(member.rx.is_empty());
+
+            let group_id = make_group(&world, &pool, &master, GUILD_AUTHORITY_INVITE)?;
+            clear_messages(&member);
+            change_group(&world, &member, &member, group_id);
+            assert!(member.rx.is_empty());
+
+            change_group(&world, &master, &member, group_id);
+            let info = assert_guild_info(member.rx.try_recv()?, &master);
+            assert_eq!(info.groups.len(), 1);
+            assert_eq!(info.groups[0].name, "Officers");
+            assert_eq!(info.groups[0].group_id, group_id);
+            assert_eq!(info.groups[0].authority, GUILD_AUTHORITY_INVITE);
+            let list = assert_member_list(member.rx.try_recv()?, 2);
+            assert_eq!(list.members[1].group_id, group_id);
+
+            // The group grants the authority to invite.
+            invite(&world, &member, &third);
+            third.rx.try_recv()?;
+
+            run_message(
+                &world,
+                Message::RequestRemoveGuildGroup {
+                    connection_global_world_id: master.connection_global_world_id,
+                    account_id: master.user.account_id,
+                    user_id: master.user.id,
+                    packet: CRemoveGuildGroup { group_id },
+                },
+            );
+            let (_, membership) = get_membership_of(&pool, &member).unwrap();
+            assert_eq!(membership.group_id, None);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_guild_info() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            make_guild(&world, &master, &[&member]);
+
+            run_message(
+                &world,
+                Message::RequestGuildInfo {
+                    connection_global_world_id: member.connection_global_world_id,
+                    account_id: member.user.account_id,
+                    user_id: member.user.id,
+                    packet: CRequestGuildInfo {},
+                },
+            );
+
+            assert_guild_info(member.rx.try_recv()?, &master);
+            assert_member_list(member.rx.try_recv()?, 2);
+            assert!(master.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_guild_tag_is_sent_to_local_world() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+
+            // FIXME Ask upstream project to create a better way to create EntityIds
+            let connection_local_world_id =
+                from_vec::<EntityId>(vec![0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+            let (local_world_tx, local_world_rx) = channel(100);
+            world.run(|mut spawns: ViewMut<GlobalUserSpawn>| {
+                let spawn = &mut spawns[master.connection_global_world_id];
+                spawn.connection_local_world_id = Some(connection_local_world_id);
+                spawn.local_world_channel = Some(local_world_tx);
+            });
+
+            create_guild(&world, &master, "Manhunter");
+
+            match &*local_world_rx.try_recv()? {
+                Message::UserGuildChanged {
+                    connection_local_world_id: id,
+                    guild_name,
+                    guild_rank,
+                } => {
+                    assert_eq!(*id, connection_local_world_id);
+                    assert_eq!(guild_name, "Manhunter");
+                    assert_eq!(guild_rank, "Guild Master");
+                }
+                _ => panic!("Message is not a UserGuildChanged message"),
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_get_user_guild_logo() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let other = add_user(&world, &pool, 1)?;
+            make_guild(&world, &master, &[]);
+            let (guild, _) = get_membership_of(&pool, &master).unwrap();
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                guild_logo::upsert(&mut conn, guild.id, &get_default_logo_data()).await
+            })?;
+
+            let request_logo = |guild_id: i32| {
+                run_message(
+                    &world,
+                    Message::RequestGetUserGuildLogo {
+                        connection_global_world_id: other.connection_global_world_id,
+                        account_id: other.user.account_id,
+                        user_id: other.user.id,
+                        packet: CGetUserGuildLogo {
+                            player_id: master.user.id,
+                            guild_id,
+                        },
+                    },
+                );
+            };
+
+            request_logo(guild.id);
+            match &*other.rx.try_recv()? {
+                Message::ResponseImageData { packet, .. } => {
+                    assert_eq!(packet.name, format!("guildlogo_1_{}_1", guild.id));
+                    assert_eq!(packet.data, get_default_logo_data());
+                }
+                _ => panic!("Message is not a ResponseImageData message"),
+            }
+            world.run(|logo_cache: UniqueView<GuildLogoCache>| {
+                assert_eq!(logo_cache.len(), 1);
+            });
+
+            // The logo is served from the cache.
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                guild_logo::delete_by_guild_id(&mut conn, guild.id).await
+            })?;
+            request_logo(guild.id);
+            match &*other.rx.try_recv()? {
+                Message::ResponseImageData { packet, .. } => {
+                    assert_eq!(packet.data, get_default_logo_data());
+                }
+                _ => panic!("Message is not a ResponseImageData message"),
+            }
+
+            // Guilds without a logo are ignored.
+            request_logo(guild.id + 1);
+            assert!(other.rx.is_empty());
+
+            Ok(())
+        })
+    }
+}
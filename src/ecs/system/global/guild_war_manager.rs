@@ -0,0 +1,1212 @@
+use crate::ecs::component::{GlobalConnection, GlobalUserSpawn, LocalWorld};
+use crate::ecs::message::Message::{
+    GuildWarsChanged, ResponseCheckToDeclareGuildWar, ResponseEndGuildWar,
+    ResponseNotifyGuildWarStatusChange, ResponseStartGuildWar,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::{GuildWarRegistry, Tick};
+use crate::ecs::system::global::{find_online_user, send_message_to_connection};
+use crate::ecs::system::send_message;
+use crate::model::entity::{Guild, GuildWar};
+use crate::model::repository::{guild, guild_war};
+use crate::model::GuildWarState;
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use chrono::{Duration, Utc};
+use shipyard::*;
+use sqlx::{PgConnection, PgPool};
+use tracing::{debug, error, info_span};
+
+/// Number of ticks between the updates of the guild war states (every 5 seconds).
+const UPDATE_INTERVAL: u64 = 50;
+
+/// Maximal number of ongoing wars of a guild.
+const MAX_GUILD_WARS: i64 = 3;
+
+/// Minutes the other guild has to accept a declared war.
+const DECLARATION_TIMEOUT_MIN: i64 = 60;
+
+/// Minutes between the acceptance of a war and it's start.
+const PREPARATION_MIN: i64 = 5;
+
+/// Hours an active war lasts until it expires.
+const WAR_DURATION_HOURS: i64 = 72;
+
+/// The guild war manager handles the wars between guilds. A war is declared by the master of a
+/// guild and needs to be accepted by the master of the other guild. After a preparation time the
+/// war becomes active and ends once one of the guilds gives up or the war expires. The active
+/// wars are send to all local worlds, which allow members of guilds at war to fight each other
+/// and report the kills back.
+pub fn guild_war_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    spawns: View<GlobalUserSpawn>,
+    local_worlds: View<LocalWorld>,
+    mut guild_wars: UniqueViewMut<GuildWarRegistry>,
+    tick: UniqueView<Tick>,
+    pool: UniqueView<PgPool>,
+) {
+    let mut needs_update = tick.count % UPDATE_INTERVAL == 0;
+
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestCheckToDeclareGuildWar {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_check_to_declare_guild_war(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring check to declare guild war request: {:?}", e);
+                }
+            }
+            Message::RequestDeclareGuildWar {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) =
+                    handle_declare_guild_war(*user_id, &packet, &connections, &spawns, &pool)
+                {
+                    error!("Ignoring declare guild war request: {:?}", e);
+                }
+            }
+            Message::RequestAcceptGuildWar {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) =
+                    handle_accept_guild_war(*user_id, &packet, &connections, &spawns, &pool)
+                {
+                    error!("Ignoring accept guild war request: {:?}", e);
+                }
+            }
+            Message::RequestGiveUpGuildWar {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) =
+                    handle_give_up_guild_war(*user_id, &packet, &connections, &spawns, &pool)
+                {
+                    error!("Ignoring give up guild war request: {:?}", e);
+                } else {
+                    needs_update = true;
+                }
+            }
+            Message::GuildWarKill {
+                killer_user_id,
+                victim_user_id,
+            } => {
+                if let Err(e) = handle_guild_war_kill(*killer_user_id, *victim_user_id, &pool) {
+                    error!("Ignoring Message::GuildWarKill: {:?}", e);
+                }
+            }
+            Message::LocalWorldLoaded {
+                successful: true,
+                global_world_id,
+            } => {
+                // New local worlds need to know about the wars that are already active.
+                if let Ok(world) = local_worlds.try_get(*global_world_id) {
+                    send_message(assemble_guild_wars_changed(&guild_wars), &world.channel);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+
+    if needs_update {
+        if let Err(e) =
+            update_guild_wars(&connections, &spawns, &local_worlds, &mut guild_wars, &pool)
+        {
+            error!("Can't update the guild wars: {:?}", e);
+        }
+    }
+}
+
+fn handle_check_to_declare_guild_war(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CCheckToDeclareGuildWar,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestCheckToDeclareGuildWar incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let ok = match check_declaration(&mut conn, user_id, &packet.name).await {
+            Ok(..) => true,
+            Err(e) => {
+                debug!("Guild war can't be declared: {:?}", e);
+                false
+            }
+        };
+
+        send_message_to_connection(
+            assemble_check_to_declare_guild_war(connection_global_world_id, &packet.name, ok),
+            connections,
+        );
+        Ok(())
+    })
+}
+
+fn handle_declare_guild_war(
+    user_id: i32,
+    packet: &CDeclareGuildWar,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestDeclareGuildWar incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (own_guild, target_guild) = check_declaration(&mut conn, user_id, &packet.name).await?;
+        let war = guild_war::create(
+            &mut conn,
+            &GuildWar {
+                id: -1,
+                declaring_guild_id: own_guild.id,
+                target_guild_id: target_guild.id,
+                state: GuildWarState::Declared,
+                declaring_guild_kills: 0,
+                target_guild_kills: 0,
+                surrendered_guild_id: None,
+                declared_at: Utc::now(),
+                updated_at: Utc::now(),
+            },
+        )
+        .await
+        .context("Can't declare guild war")?;
+        debug!(
+            "Guild {} declared war on guild {}",
+            own_guild.id, target_guild.id
+        );
+
+        send_war_status(&mut conn, &war, connections, spawns).await
+    })
+}
+
+fn handle_accept_guild_war(
+    user_id: i32,
+    packet: &CAcceptGuildWar,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestAcceptGuildWar incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (own_guild, enemy_guild, mut war) = get_war(&mut conn, user_id, &packet.name).await?;
+        ensure!(
+            war.state == GuildWarState::Declared && war.target_guild_id == own_guild.id,
+            "Guild {} has no war declaration of guild {} to accept",
+            own_guild.id,
+            enemy_guild.id
+        );
+
+        war.state = GuildWarState::Accepted;
+        war.updated_at = Utc::now();
+        let war = guild_war::update(&mut conn, &war).await?;
+        debug!("Guild war {} accepted", war.id);
+
+        send_war_status(&mut conn, &war, connections, spawns).await
+    })
+}
+
+fn handle_give_up_guild_war(
+    user_id: i32,
+    packet: &CGiveUpGuildWar,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestGiveUpGuildWar incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let (own_guild, _enemy_guild, mut war) = get_war(&mut conn, user_id, &packet.name).await?;
+        let was_declared = war.state == GuildWarState::Declared;
+
+        war.state = GuildWarState::Surrendered;
+        war.surrendered_guild_id = Some(own_guild.id);
+        war.updated_at = Utc::now();
+        let war = guild_war::update(&mut conn, &war).await?;
+        debug!("Guild {} gave up guild war {}", own_guild.id, war.id);
+
+        // Wars that were never accepted don't have a result.
+        if was_declared {
+            send_war_status(&mut conn, &war, connections, spawns).await
+        } else {
+            send_war_end(&mut conn, &war, connections, spawns).await
+        }
+    })
+}
+
+fn handle_guild_war_kill(
+    killer_user_id: i32,
+    victim_user_id: i32,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::GuildWarKill incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let killer = guild::get_member(&mut conn, killer_user_id)
+            .await
+            .context(format!("User {} is not in a guild", killer_user_id))?;
+        let victim = guild::get_member(&mut conn, victim_user_id)
+            .await
+            .context(format!("User {} is not in a guild", victim_user_id))?;
+        let war = guild_war::get_ongoing(&mut conn, killer.guild_id, victim.guild_id)
+            .await
+            .context(format!(
+                "Guilds {} and {} are not at war",
+                killer.guild_id, victim.guild_id
+            ))?;
+        ensure!(
+            war.state == GuildWarState::Active,
+            "Guild war {} is not active",
+            war.id
+        );
+
+        guild_war::add_kill(&mut conn, war.id, killer.guild_id).await?;
+        Ok(())
+    })
+}
+
+/// Moves the wars into their next state once their time is up and informs the local worlds
+/// if the active wars changed.
+fn update_guild_wars(
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    local_worlds: &View<LocalWorld>,
+    guild_wars: &mut UniqueViewMut<GuildWarRegistry>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    let registry = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+        let now = Utc::now();
+
+        for mut war in guild_war::list_by_state(&mut conn, GuildWarState::Declared).await? {
+            if now - war.updated_at > Duration::minutes(DECLARATION_TIMEOUT_MIN) {
+                war.state = GuildWarState::Expired;
+                war.updated_at = now;
+                let war = guild_war::update(&mut conn, &war).await?;
+                debug!("Declaration of guild war {} expired", war.id);
+                send_war_status(&mut conn, &war, connections, spawns).await?;
+            }
+        }
+
+        for mut war in guild_war::list_by_state(&mut conn, GuildWarState::Accepted).await? {
+            if now - war.updated_at > Duration::minutes(PREPARATION_MIN) {
+                war.state = GuildWarState::Active;
+                war.updated_at = now;
+                let war = guild_war::update(&mut conn, &war).await?;
+                debug!("Guild war {} started", war.id);
+                send_war_start(&mut conn, &war, connections, spawns).await?;
+            }
+        }
+
+        let mut registry = GuildWarRegistry::default();
+        for mut war in guild_war::list_by_state(&mut conn, GuildWarState::Active).await? {
+            if now - war.updated_at > Duration::hours(WAR_DURATION_HOURS) {
+                war.state = GuildWarState::Expired;
+                war.updated_at = now;
+                let war = guild_war::update(&mut conn, &war).await?;
+                debug!("Guild war {} expired", war.id);
+                send_war_end(&mut conn, &war, connections, spawns).await?;
+                continue;
+            }
+
+            registry.add_war(war.declaring_guild_id, war.target_guild_id);
+            for guild_id in [war.declaring_guild_id, war.target_guild_id].iter() {
+                for member in guild::list_members(&mut conn, *guild_id).await? {
+                    registry.members.insert(member.user_id, member.guild_id);
+                }
+            }
+        }
+
+        Ok::<GuildWarRegistry, anyhow::Error>(registry)
+    })?;
+
+    if registry != **guild_wars {
+        **guild_wars = registry;
+        for world in local_worlds.iter() {
+            send_message(assemble_guild_wars_changed(guild_wars), &world.channel);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks if the user can declare a war on the guild with the given name. Returns the guild of
+/// the user and the target guild.
+async fn check_declaration(
+    conn: &mut PgConnection,
+    user_id: i32,
+    name: &str,
+) -> Result<(Guild, Guild)> {
+    let own_guild = get_master_guild(conn, user_id).await?;
+    let target_guild = guild::get_by_name(conn, name)
+        .await
+        .context(format!("Can't find guild {}", name))?;
+
+    ensure!(
+        own_guild.id != target_guild.id,
+        "Guild {} can't declare war on itself",
+        own_guild.id
+    );
+    ensure!(
+        guild_war::get_ongoing(conn, own_guild.id, target_guild.id)
+            .await
+            .is_err(),
+        "Guilds {} and {} are already at war",
+        own_guild.id,
+        target_guild.id
+    );
+    for guild_id in [own_guild.id, target_guild.id].iter() {
+        ensure!(
+            guild_war::get_ongoing_count(conn, *guild_id).await? < MAX_GUILD_WARS,
+            "Guild {} reached the maximal number of wars",
+            guild_id
+        );
+    }
+
+    Ok((own_guild, target_guild))
+}
+
+/// Returns the guild of the user, the enemy guild with the given name and their ongoing war.
+async fn get_war(
+    conn: &mut PgConnection,
+    user_id: i32,
+    name: &str,
+) -> Result<(Guild, Guild, GuildWar)> {
+    let own_guild = get_master_guild(conn, user_id).await?;
+    let enemy_guild = guild::get_by_name(conn, name)
+        .await
+        .context(format!("Can't find guild {}", name))?;
+    let war = guild_war::get_ongoing(conn, own_guild.id, enemy_guild.id)
+        .await
+        .context(format!(
+            "Guilds {} and {} are not at war",
+            own_guild.id, enemy_guild.id
+        ))?;
+    Ok((own_guild, enemy_guild, war))
+}
+
+/// Only the guild master can manage the wars of a guild.
+async fn get_master_guild(conn: &mut PgConnection, user_id: i32) -> Result<Guild> {
+    let own_guild = guild::get_by_user_id(conn, user_id)
+        .await
+        .context(format!("User {} is not in a guild", user_id))?;
+    ensure!(
+        own_guild.master_id == user_id,
+        "User {} is not the guild master of guild {}",
+        user_id,
+        own_guild.id
+    );
+    Ok(own_guild)
+}
+
+/// Returns the kills and deaths of the guild in the war.
+fn get_war_score(war: &GuildWar, guild_id: i32) -> (i32, i32) {
+    if war.declaring_guild_id == guild_id {
+        (war.declaring_guild_kills, war.target_guild_kills)
+    } else {
+        (war.target_guild_kills, war.declaring_guild_kills)
+    }
+}
+
+/// Sends a message to all online members of both guilds of the war. The message is assembled
+/// with the ID of the guild of the member and the enemy guild.
+async fn send_to_war_members<F>(
+    conn: &mut PgConnection,
+    war: &GuildWar,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    assemble: F,
+) -> Result<()>
+where
+    F: Fn(EntityId, i32, &Guild) -> EcsMessage,
+{
+    let declaring_guild = guild::get_by_id(conn, war.declaring_guild_id)
+        .await
+        .context(format!("Can't find guild {}", war.declaring_guild_id))?;
+    let target_guild = guild::get_by_id(conn, war.target_guild_id)
+        .await
+        .context(format!("Can't find guild {}", war.target_guild_id))?;
+
+    for &(own_guild, enemy_guild) in [
+        (&declaring_guild, &target_guild),
+        (&target_guild, &declaring_guild),
+    ]
+    .iter()
+    {
+        for member in guild::list_members(conn, own_guild.id).await? {
+            if let Some(member_connection_id) = find_online_user(member.user_id, spawns) {
+                send_message_to_connection(
+                    assemble(member_connection_id, own_guild.id, enemy_guild),
+                    connections,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_war_status(
+    conn: &mut PgConnection,
+    war: &GuildWar,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+) -> Result<()> {
+    send_to_war_members(
+        conn,
+        war,
+        connections,
+        spawns,
+        |connection_global_world_id, _guild_id, enemy_guild| {
+            assemble_notify_guild_war_status_change(
+                connection_global_world_id,
+                enemy_guild,
+                war.state,
+            )
+        },
+    )
+    .await
+}
+
+async fn send_war_start(
+    conn: &mut PgConnection,
+    war: &GuildWar,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+) -> Result<()> {
+    send_to_war_members(
+        conn,
+        war,
+        connections,
+        spawns,
+        |connection_global_world_id, _guild_id, enemy_guild| {
+            assemble_start_guild_war(connection_global_world_id, enemy_guild)
+        },
+    )
+    .await
+}
+
+async fn send_war_end(
+    conn: &mut PgConnection,
+    war: &GuildWar,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+) -> Result<()> {
+    send_to_war_members(
+        conn,
+        war,
+        connections,
+        spawns,
+        |connection_global_world_id, guild_id, enemy_guild| {
+            let (kills, deaths) = get_war_score(war, guild_id);
+            assemble_end_guild_war(
+                connection_global_world_id,
+                enemy_guild,
+                war.state,
+                kills,
+                deaths,
+            )
+        },
+    )
+    .await
+}
+
+fn assemble_check_to_declare_guild_war(
+    connection_global_world_id: EntityId,
+    name: &str,
+    ok: bool,
+) -> EcsMessage {
+    Box::new(ResponseCheckToDeclareGuildWar {
+        connection_global_world_id,
+        packet: SCheckToDeclareGuildWar {
+            name: name.to_string(),
+            ok,
+        },
+    })
+}
+
+fn assemble_notify_guild_war_status_change(
+    connection_global_world_id: EntityId,
+    enemy_guild: &Guild,
+    state: GuildWarState,
+) -> EcsMessage {
+    Box::new(ResponseNotifyGuildWarStatusChange {
+        connection_global_world_id,
+        packet: SNotifyGuildWarStatusChange {
+            guild_name: enemy_guild.name.clone(),
+            guild_id: enemy_guild.id,
+            state,
+        },
+    })
+}
+
+fn assemble_start_guild_war(
+    connection_global_world_id: EntityId,
+    enemy_guild: &Guild,
+) -> EcsMessage {
+    Box::new(ResponseStartGuildWar {
+        connection_global_world_id,
+        packet: SStartGuildWar {
+            guild_name: enemy_guild.name.clone(),
+            guild_id: enemy_guild.id,
+        },
+    })
+}
+
+fn assemble_end_guild_war(
+    connection_global_world_id: EntityId,
+    enemy_guild: &Guild,
+    state: GuildWarState,
+    kills: i32,
+    deaths: i32,
+) -> EcsMessage {
+    Box::new(ResponseEndGuildWar {
+        connection_global_world_id,
+        packet: SEndGuildWar {
+            guild_name: enemy_guild.name.clone(),
+            guild_id: enemy_guild.id,
+            state,
+            kills,
+            deaths,
+        },
+    })
+}
+
+fn assemble_guild_wars_changed(guild_wars: &GuildWarRegistry) -> EcsMessage {
+    Box::new(GuildWarsChanged {
+        guild_wars: guild_wars.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::{LocalWorldType, UserSpawnStatus};
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
+    use crate::model::entity::{GuildMember, User};
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use async_std::sync::{channel, Receiver};
+    use std::collections::HashSet;
+    use std::time::{Duration, Instant};
+
+    struct TestUser {
+        user: User,
+        connection_global_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(GuildWarRegistry::default());
+        world.add_unique(Tick {
+            count: 1,
+            delta: Duration::from_nanos(1000),
+            time: Instant::now(),
+        });
+        world.add_unique(pool);
+        world
+    }
+
+    async fn create_user(pool: &PgPool, num: i32) -> Result<User> {
+        let mut conn = pool.acquire().await?;
+        let account = account::create(&mut conn, &get_default_account(num)).await?;
+        user::create(&mut conn, &get_default_user(&account, num)).await
+    }
+
+    fn add_user(world: &World, pool: &PgPool, num: i32) -> Result<TestUser> {
+        let user = task::block_on(async { create_user(pool, num).await })?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut spawns: ViewMut<GlobalUserSpawn>| {
+                entities.add_entity(
+                    (&mut connections, &mut spawns),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        GlobalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_local_world_id: None,
+                            local_world_id: None,
+                            local_world_channel: None,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
+                        },
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            user,
+            connection_global_world_id,
+            rx: rx_channel,
+        })
+    }
+
+    /// Creates a guild with the master and the given members.
+    fn add_guild(pool: &PgPool, master: &TestUser, members: &[&TestUser]) -> Result<Guild> {
+        task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let guild = guild::create(
+                &mut conn,
+                &Guild {
+                    id: -1,
+                    name: format!("Guild{}", master.user.id),
+                    master_id: master.user.id,
+                    created_at: Utc::now(),
+                },
+            )
+            .await?;
+            for user in [master].iter().chain(members.iter()) {
+                guild::add_member(
+                    &mut conn,
+                    &GuildMember {
+                        user_id: user.user.id,
+                        guild_id: guild.id,
+                        group_id: None,
+                        joined_at: Utc::now(),
+                    },
+                )
+                .await?;
+            }
+            Ok(guild)
+        })
+    }
+
+    fn add_local_world(world: &World) -> (EntityId, Receiver<EcsMessage>) {
+        let (local_world_channel, local_world_rx) = channel(1024);
+        let local_world_id = world.run(
+            |mut entities: EntitiesViewMut, mut local_worlds: ViewMut<LocalWorld>| {
+                entities.add_entity(
+                    &mut local_worlds,
+                    LocalWorld {
+                        instance_type: LocalWorldType::Field,
+                        channel_num: Some(1),
+                        zone_id: 5,
+                        channel: local_world_channel,
+                        join_handle: task::spawn(async { Ok(()) }),
+                        users: HashSet::new(),
+                        deadline: None,
+                        group_id: None,
+                    },
+                )
+            },
+        );
+        (local_world_id, local_world_rx)
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(guild_war_manager_system);
+        world.run(cleaner_system);
+    }
+
+    /// Runs a tick in which the states of the wars are updated.
+    fn run_update(world: &World) {
+        world.run(|mut tick: UniqueViewMut<Tick>| tick.count = UPDATE_INTERVAL);
+        world.run(guild_war_manager_system);
+        world.run(|mut tick: UniqueViewMut<Tick>| tick.count = UPDATE_INTERVAL + 1);
+    }
+
+    fn check_to_declare(world: &World, user: &TestUser, name: &str) {
+        run_message(
+            world,
+            Message::RequestCheckToDeclareGuildWar {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CCheckToDeclareGuildWar {
+                    name: name.to_string(),
+                },
+            },
+        );
+    }
+
+    fn declare(world: &World, user: &TestUser, name: &str) {
+        run_message(
+            world,
+            Message::RequestDeclareGuildWar {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CDeclareGuildWar {
+                    name: name.to_string(),
+                },
+            },
+        );
+    }
+
+    fn accept(world: &World, user: &TestUser, name: &str) {
+        run_message(
+            world,
+            Message::RequestAcceptGuildWar {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CAcceptGuildWar {
+                    name: name.to_string(),
+                },
+            },
+        );
+    }
+
+    fn give_up(world: &World, user: &TestUser, name: &str) {
+        run_message(
+            world,
+            Message::RequestGiveUpGuildWar {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CGiveUpGuildWar {
+                    name: name.to_string(),
+                },
+            },
+        );
+    }
+
+    fn kill(world: &World, killer: &TestUser, victim: &TestUser) {
+        run_message(
+            world,
+            Message::GuildWarKill {
+                killer_user_id: killer.user.id,
+                victim_user_id: victim.user.id,
+            },
+        );
+    }
+
+    fn get_ongoing_war(pool: &PgPool, guild: &Guild, other_guild: &Guild) -> Option<GuildWar> {
+        task::block_on(async {
+            let mut conn = pool.acquire().await.ok()?;
+            guild_war::get_ongoing(&mut conn, guild.id, other_guild.id)
+                .await
+                .ok()
+        })
+    }
+
+    fn get_war_by_id(pool: &PgPool, war_id: i32) -> Result<GuildWar> {
+        task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            guild_war::get_by_id(&mut conn, war_id).await
+        })
+    }
+
+    /// Moves the last state change of the war into the past.
+    fn age_war(pool: &PgPool, war: &GuildWar, minutes: i64) -> Result<()> {
+        task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let mut war = war.clone();
+            war.updated_at = Utc::now() - chrono::Duration::minutes(minutes);
+            guild_war::update(&mut conn, &war).await?;
+            Ok(())
+        })
+    }
+
+    /// Declares and accepts a war between the guilds and lets it become active.
+    fn make_active_war(
+        world: &World,
+        pool: &PgPool,
+        master: &TestUser,
+        other_master: &TestUser,
+        guild: &Guild,
+        other_guild: &Guild,
+    ) -> Result<GuildWar> {
+        declare(world, master, &other_guild.name);
+        accept(world, other_master, &guild.name);
+        let war = get_ongoing_war(pool, guild, other_guild).unwrap();
+        age_war(pool, &war, PREPARATION_MIN + 1)?;
+        run_update(world);
+        clear_messages(master);
+        clear_messages(other_master);
+        get_war_by_id(pool, war.id)
+    }
+
+    fn clear_messages(user: &TestUser) {
+        while user.rx.try_recv().is_ok() {}
+    }
+
+    fn assert_status_change(message: EcsMessage, enemy_guild: &Guild, state: GuildWarState) {
+        match &*message {
+            Message::ResponseNotifyGuildWarStatusChange { packet, .. } => {
+                assert_eq!(packet.guild_name, enemy_guild.name);
+                assert_eq!(packet.guild_id, enemy_guild.id);
+                assert_eq!(packet.state, state);
+            }
+            _ => panic!("Message is not a ResponseNotifyGuildWarStatusChange message"),
+        }
+    }
+
+    fn assert_start(message: EcsMessage, enemy_guild: &Guild) {
+        match &*message {
+            Message::ResponseStartGuildWar { packet, .. } => {
+                assert_eq!(packet.guild_name, enemy_guild.name);
+                assert_eq!(packet.guild_id, enemy_guild.id);
+            }
+            _ => panic!("Message is not a ResponseStartGuildWar message"),
+        }
+    }
+
+    fn assert_end(message: EcsMessage, enemy_guild: &Guild, state: GuildWarState) -> SEndGuildWar {
+        match &*message {
+            Message::ResponseEndGuildWar { packet, .. } => {
+                assert_eq!(packet.guild_name, enemy_guild.name);
+                assert_eq!(packet.guild_id, enemy_guild.id);
+                assert_eq!(packet.state, state);
+                packet.clone()
+            }
+            _ => panic!("Message is not a ResponseEndGuildWar message"),
+        }
+    }
+
+    fn assert_check_result(message: EcsMessage, ok: bool) {
+        match &*message {
+            Message::ResponseCheckToDeclareGuildWar { packet, .. } => assert_eq!(packet.ok, ok),
+            _ => panic!("Message is not a ResponseCheckToDeclareGuildWar message"),
+        }
+    }
+
+    fn assert_guild_wars_changed(message: EcsMessage) -> GuildWarRegistry {
+        match &*message {
+            Message::GuildWarsChanged { guild_wars } => guild_wars.clone(),
+            _ => panic!("Message is not a GuildWarsChanged message"),
+        }
+    }
+
+    #[test]
+    fn test_declare_and_accept_guild_war() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let other_master = add_user(&world, &pool, 1)?;
+            let other_member = add_user(&world, &pool, 2)?;
+            let guild = add_guild(&pool, &master, &[])?;
+            let other_guild = add_guild(&pool, &other_master, &[&other_member])?;
+            let (_local_world_id, local_world_rx) = add_local_world(&world);
+
+            check_to_declare(&world, &master, &other_guild.name.to_uppercase());
+            assert_check_result(master.rx.try_recv()?, true);
+
+            declare(&world, &master, &other_guild.name.to_uppercase());
+            let war = get_ongoing_war(&pool, &guild, &other_guild).unwrap();
+            assert_eq!(war.declaring_guild_id, guild.id);
+            assert_eq!(war.state, GuildWarState::Declared);
+            assert_status_change(master.rx.try_recv()?, &other_guild, GuildWarState::Declared);
+            assert_status_change(other_master.rx.try_recv()?, &guild, GuildWarState::Declared);
+            assert_status_change(other_member.rx.try_recv()?, &guild, GuildWarState::Declared);
+
+            // Only the master of the declared guild can accept the war.
+            accept(&world, &other_member, &guild.name);
+            accept(&world, &master, &other_guild.name);
+            assert_eq!(get_war_by_id(&pool, war.id)?.state, GuildWarState::Declared);
+
+            accept(&world, &other_master, &guild.name);
+            assert_eq!(get_war_by_id(&pool, war.id)?.state, GuildWarState::Accepted);
+            assert_status_change(master.rx.try_recv()?, &other_guild, GuildWarState::Accepted);
+            assert_status_change(other_master.rx.try_recv()?, &guild, GuildWarState::Accepted);
+            clear_messages(&other_member);
+
+            // The war starts after the preparation time.
+            run_update(&world);
+            assert_eq!(get_war_by_id(&pool, war.id)?.state, GuildWarState::Accepted);
+            assert!(master.rx.is_empty());
+            assert!(local_world_rx.is_empty());
+
+            age_war(&pool, &get_war_by_id(&pool, war.id)?, PREPARATION_MIN + 1)?;
+            run_update(&world);
+            assert_eq!(get_war_by_id(&pool, war.id)?.state, GuildWarState::Active);
+            assert_start(master.rx.try_recv()?, &other_guild);
+            assert_start(other_master.rx.try_recv()?, &guild);
+            assert_start(other_member.rx.try_recv()?, &guild);
+
+            let guild_wars = assert_guild_wars_changed(local_world_rx.try_recv()?);
+            assert!(guild_wars.are_enemies(master.user.id, other_member.user.id));
+            assert!(!guild_wars.are_enemies(other_master.user.id, other_member.user.id));
+            world.run(|registry: UniqueView<GuildWarRegistry>| {
+                assert_eq!(*registry, guild_wars);
+            });
+
+            // The local worlds are only informed if the wars changed.
+            run_update(&world);
+            assert!(local_world_rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_declare_guild_war_rejected() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            let other_master = add_user(&world, &pool, 2)?;
+            let guild = add_guild(&pool, &master, &[&member])?;
+            let other_guild = add_guild(&pool, &other_master, &[])?;
+
+            // Members that are not the guild master, unknown guilds and the own guild are rejected.
+            check_to_declare(&world, &member, &other_guild.name);
+            check_to_declare(&world, &master, "Gantsu");
+            check_to_declare(&world, &master, &guild.name);
+            declare(&world, &member, &other_guild.name);
+            declare(&world, &master, &guild.name);
+            assert_check_result(member.rx.try_recv()?, false);
+            assert_check_result(master.rx.try_recv()?, false);
+            assert_check_result(master.rx.try_recv()?, false);
+            assert!(member.rx.is_empty());
+            assert!(master.rx.is_empty());
+            assert!(get_ongoing_war(&pool, &guild, &other_guild).is_none());
+
+            // Guilds can only have one ongoing war with each other.
+            declare(&world, &master, &other_guild.name);
+            clear_messages(&master);
+            declare(&world, &other_master, &guild.name);
+            check_to_declare(&world, &other_master, &guild.name);
+            clear_messages(&other_master);
+            let war = get_ongoing_war(&pool, &guild, &other_guild).unwrap();
+            assert_eq!(war.declaring_guild_id, guild.id);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_declaration_expires() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let other_master = add_user(&world, &pool, 1)?;
+            let guild = add_guild(&pool, &master, &[])?;
+            let other_guild = add_guild(&pool, &other_master, &[])?;
+
+            declare(&world, &master, &other_guild.name);
+            clear_messages(&master);
+            clear_messages(&other_master);
+            let war = get_ongoing_war(&pool, &guild, &other_guild).unwrap();
+
+            age_war(&pool, &war, DECLARATION_TIMEOUT_MIN + 1)?;
+            run_update(&world);
+            assert_eq!(get_war_by_id(&pool, war.id)?.state, GuildWarState::Expired);
+            assert_status_change(master.rx.try_recv()?, &other_guild, GuildWarState::Expired);
+            assert_status_change(other_master.rx.try_recv()?, &guild, GuildWarState::Expired);
+
+            // An expired declaration can't be accepted anymore.
+            accept(&world, &other_master, &guild.name);
+            assert!(get_ongoing_war(&pool, &guild, &other_guild).is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_active_guild_war_expires() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let other_master = add_user(&world, &pool, 1)?;
+            let guild = add_guild(&pool, &master, &[])?;
+            let other_guild = add_guild(&pool, &other_master, &[])?;
+            let (_local_world_id, local_world_rx) = add_local_world(&world);
+            let war = make_active_war(&world, &pool, &master, &other_master, &guild, &other_guild)?;
+            assert_guild_wars_changed(local_world_rx.try_recv()?);
+
+            age_war(&pool, &war, WAR_DURATION_HOURS * 60 + 1)?;
+            run_update(&world);
+            assert_eq!(get_war_by_id(&pool, war.id)?.state, GuildWarState::Expired);
+            assert_end(master.rx.try_recv()?, &other_guild, GuildWarState::Expired);
+            assert_end(other_master.rx.try_recv()?, &guild, GuildWarState::Expired);
+            assert_eq!(
+                assert_guild_wars_changed(local_world_rx.try_recv()?),
+                GuildWarRegistry::default()
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_give_up_guild_war() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let other_master = add_user(&world, &pool, 1)?;
+            let guild = add_guild(&pool, &master, &[])?;
+            let other_guild = add_guild(&pool, &other_master, &[])?;
+            let (_local_world_id, local_world_rx) = add_local_world(&world);
+            let war = make_active_war(&world, &pool, &master, &other_master, &guild, &other_guild)?;
+            assert_guild_wars_changed(local_world_rx.try_recv()?);
+
+            kill(&world, &master, &other_master);
+            kill(&world, &master, &other_master);
+            kill(&world, &other_master, &master);
+
+            give_up(&world, &other_master, &guild.name);
+            let war = get_war_by_id(&pool, war.id)?;
+            assert_eq!(war.state, GuildWarState::Surrendered);
+            assert_eq!(war.surrendered_guild_id, Some(other_guild.id));
+
+            let result = assert_end(
+                master.rx.try_recv()?,
+                &other_guild,
+                GuildWarState::Surrendered,
+            );
+            assert_eq!(result.kills, 2);
+            assert_eq!(result.deaths, 1);
+            let result = assert_end(
+                other_master.rx.try_recv()?,
+                &guild,
+                GuildWarState::Surrendered,
+            );
+            assert_eq!(result.kills, 1);
+            assert_eq!(result.deaths, 2);
+
+            // The local worlds are informed right away.
+            assert_eq!(
+                assert_guild_wars_changed(local_world_rx.try_recv()?),
+                GuildWarRegistry::default()
+            );
+
+            // A new war can be declared after the old one ended.
+            declare(&world, &other_master, &guild.name);
+            assert!(get_ongoing_war(&pool, &guild, &other_guild).is_some());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_guild_war_kill() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let other_master = add_user(&world, &pool, 1)?;
+            let third_master = add_user(&world, &pool, 2)?;
+            let guild = add_guild(&pool, &master, &[])?;
+            let other_guild = add_guild(&pool, &other_master, &[])?;
+            add_guild(&pool, &third_master, &[])?;
+
+            // Kills only count while the war is active.
+            declare(&world, &master, &other_guild.name);
+            kill(&world, &master, &other_master);
+            let war = get_ongoing_war(&pool, &guild, &other_guild).unwrap();
+            assert_eq!(war.declaring_guild_kills, 0);
+
+            accept(&world, &other_master, &guild.name);
+            age_war(&pool, &get_war_by_id(&pool, war.id)?, PREPARATION_MIN + 1)?;
+            run_update(&world);
+
+            kill(&world, &master, &other_master);
+            kill(&world, &master, &third_master);
+            kill(&world, &other_master, &master);
+            let war = get_war_by_id(&pool, war.id)?;
+            assert_eq!(war.declaring_guild_kills, 1);
+            assert_eq!(war.target_guild_kills, 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_local_world_loaded() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let master = add_user(&world, &pool, 0)?;
+            let other_master = add_user(&world, &pool, 1)?;
+            let guild = add_guild(&pool, &master, &[])?;
+            let other_guild = add_guild(&pool, &other_master, &[])?;
+            make_active_war(&world, &pool, &master, &other_master, &guild, &other_guild)?;
+
+            let (local_world_id, local_world_rx) = add_local_world(&world);
+            run_message(
+                &world,
+                Message::LocalWorldLoaded {
+                    successful: true,
+                    global_world_id: local_world_id,
+                },
+            );
+
+            let guild_wars = assert_guild_wars_changed(local_world_rx.try_recv()?);
+            assert!(guild_wars.are_enemies(master.user.id, other_master.user.id));
+
+            Ok(())
+        })
+    }
+}
@@ -1,9 +1,9 @@
 use crate::config::Configuration;
 use crate::ecs::component::{
-    GlobalConnection, GlobalUserSpawn, LocalWorld, LocalWorldType, UserSpawnStatus,
+    GlobalUserSpawn, InstanceRequest, LocalWorld, LocalWorldType, UserSpawnStatus,
 };
 use crate::ecs::message::{EcsMessage, Message};
-use crate::ecs::resource::{DeletionList, GlobalMessageChannel};
+use crate::ecs::resource::{DeletionList, GameData, GlobalMessageChannel};
 use crate::ecs::system::send_message;
 use crate::{ecs, Result};
 use anyhow::{ensure, Context};
@@ -19,13 +19,14 @@ const LOCAL_WORLD_IDLE_LIFETIME_SEC: u64 = 300;
 /// The local world manager handles the lifecycle of a local world.
 pub fn local_world_manager_system(
     incoming_messages: View<EcsMessage>,
-    _connections: View<GlobalConnection>,
     mut user_spawns: ViewMut<GlobalUserSpawn>,
     mut local_worlds: ViewMut<LocalWorld>,
+    mut instance_requests: ViewMut<InstanceRequest>,
     mut entities: EntitiesViewMut,
     config: UniqueView<Configuration>,
     pool: UniqueView<PgPool>,
     global_world_channel: UniqueView<GlobalMessageChannel>,
+    game_data: UniqueView<GameData>,
     mut deletion_list: UniqueViewMut<DeletionList>,
 ) {
     (&incoming_messages)
@@ -48,17 +49,27 @@ pub fn local_world_manager_system(
             _ => { /* Ignore all other messages */ }
         });
 
-    // Look for users that either want to spawn or are marked for deletion.
+    // Look for users that either want to spawn, change their local world or are marked for deletion.
     for (connection_global_world_id, spawn) in (&mut user_spawns).iter().with_id() {
+        if spawn.status == UserSpawnStatus::Relocating && !spawn.marked_for_deletion {
+            if let Err(e) =
+                handle_user_relocating(spawn, connection_global_world_id, &mut local_worlds)
+            {
+                id_span!(connection_global_world_id);
+                error!("Can't relocate user: {:?}", e)
+            }
+        }
         if spawn.status == UserSpawnStatus::Requesting {
             if let Err(e) = handle_user_requesting_spawn(
                 spawn,
                 connection_global_world_id,
                 &mut local_worlds,
+                &mut instance_requests,
                 &mut entities,
                 &config,
                 &global_world_channel,
                 &pool,
+                &game_data,
             ) {
                 // TODO decide how to handle an error while requesting a user spawn
                 id_span!(connection_global_world_id);
@@ -98,70 +109,194 @@ fn handle_user_requesting_spawn(
     mut spawn: &mut GlobalUserSpawn,
     connection_global_world_id: EntityId,
     local_worlds: &mut ViewMut<LocalWorld>,
+    instance_requests: &mut ViewMut<InstanceRequest>,
     entities: &mut EntitiesViewMut,
     config: &UniqueView<Configuration>,
     global_world_channel: &UniqueView<GlobalMessageChannel>,
     pool: &UniqueView<PgPool>,
+    game_data: &UniqueView<GameData>,
 ) -> Result<()> {
-    // TODO once we implement parties / dungeons / pvp arenas, this code needs to be extended
-    let (world_id, channel) = if let Some((world_id, world)) = local_worlds
-        .iter()
-        .with_id()
-        .filter(|(_id, world)| world.zone_id == spawn.zone_id)
-        .next()
-    {
-        world.users.insert(connection_global_world_id);
-        world.deadline = None;
+    // TODO once we implement pvp arenas, this code needs to be extended
+    let instance_type = game_data.zones.zone_type(spawn.zone_id);
+
+    // Groups (e.g. parties and matched dungeon groups) get an instance of their own. Dungeons are
+    // never shared, so users without a group get an instance of their own too.
+    let group_id = if let Ok(request) = instance_requests.try_get(connection_global_world_id) {
+        let group_id = request.group_id;
+        instance_requests.delete(connection_global_world_id);
+        Some(group_id)
+    } else if instance_type == LocalWorldType::Dungeon {
+        Some(connection_global_world_id)
+    } else {
+        None
+    };
 
+    let existing_world_id = if instance_type == LocalWorldType::Field {
+        let user_cap = game_data
+            .zones
+            .channel_user_cap(spawn.zone_id, config.game.channel_user_cap);
+        find_field_channel(spawn, local_worlds, user_cap)
+    } else {
+        local_worlds
+            .iter()
+            .with_id()
+            .filter(|(_id, world)| world.zone_id == spawn.zone_id && world.group_id == group_id)
+            .map(|(id, _world)| id)
+            .next()
+    };
+
+    let world_id = if let Some(world_id) = existing_world_id {
         // Users can spawn right away, since the local world is already up and running.
         spawn.status = UserSpawnStatus::CanSpawn;
-
-        (world_id, world.channel.clone())
+        world_id
     } else {
-        // TODO once we have implemented the datacenter parser, we need to extend this part
-        let world_id = entities.add_entity((), ());
-        let mut local_world = ecs::world::LocalWorld::new(
-            &**config.clone(),
-            &**pool.clone(),
-            world_id,
-            global_world_channel.channel.clone(),
-        );
-        let local_world_channel = local_world.channel.clone();
-        let join_handle = task::spawn_blocking(move || {
-            local_world.run();
-            Ok(())
-        });
-
-        let mut users = HashSet::new();
-        users.insert(connection_global_world_id);
-
-        entities.add_component(
+        let channel_num = if instance_type == LocalWorldType::Field {
+            Some(next_free_channel_num(spawn.zone_id, local_worlds))
+        } else {
+            None
+        };
+        let world_id = spawn_local_world(
+            spawn.zone_id,
+            instance_type,
+            channel_num,
+            group_id,
             local_worlds,
-            LocalWorld {
-                instance_type: LocalWorldType::Field,
-                channel_num: None,
-                zone_id: spawn.zone_id,
-                channel: local_world_channel.clone(),
-                join_handle,
-                users,
-                deadline: None,
-            },
-            world_id,
+            entities,
+            config,
+            global_world_channel,
+            pool,
+            game_data,
         );
 
         // Users need to wait until the new world is loaded
         spawn.status = UserSpawnStatus::Waiting;
-
-        (world_id, local_world_channel)
+        world_id
     };
 
+    let mut world = local_worlds
+        .try_get(world_id)
+        .context(format!("Can't find local world {:?}", world_id))?;
+    world.users.insert(connection_global_world_id);
+    world.deadline = None;
+
     info!(
-        "Spawning user {:?} in local world {:?}",
-        connection_global_world_id, world_id
+        "Spawning user {:?} in local world {:?} (channel {:?})",
+        connection_global_world_id, world_id, world.channel_num
     );
 
+    spawn.channel_num = world.channel_num;
     spawn.local_world_id = Some(world_id);
-    spawn.local_world_channel = Some(channel);
+    spawn.local_world_channel = Some(world.channel.clone());
+    Ok(())
+}
+
+/// Returns the channel of a field a user should be spawned in. Uses the requested channel of the
+/// user if it has space left, else the channel with the least users. Returns None if all channels
+/// are full.
+fn find_field_channel(
+    spawn: &GlobalUserSpawn,
+    local_worlds: &ViewMut<LocalWorld>,
+    user_cap: usize,
+) -> Option<EntityId> {
+    let mut channels = local_worlds
+        .iter()
+        .with_id()
+        .filter(|(_id, world)| {
+            world.zone_id == spawn.zone_id
+                && world.instance_type == LocalWorldType::Field
+                && world.users.len() < user_cap
+        })
+        .collect::<Vec<(EntityId, &LocalWorld)>>();
+
+    if let Some((id, _world)) = channels
+        .iter()
+        .find(|(_id, world)| spawn.channel_num.is_some() && world.channel_num == spawn.channel_num)
+    {
+        return Some(*id);
+    }
+
+    channels.sort_by_key(|(_id, world)| (world.users.len(), world.channel_num));
+    channels.first().map(|(id, _world)| *id)
+}
+
+/// Returns the lowest channel number that is not used by a local world of the given zone.
+fn next_free_channel_num(zone_id: i32, local_worlds: &ViewMut<LocalWorld>) -> i32 {
+    let used = local_worlds
+        .iter()
+        .filter(|world| world.zone_id == zone_id && world.channel_num.is_some())
+        .map(|world| world.channel_num.unwrap())
+        .collect::<Vec<i32>>();
+    (1..).find(|num| !used.contains(num)).unwrap()
+}
+
+fn spawn_local_world(
+    zone_id: i32,
+    instance_type: LocalWorldType,
+    channel_num: Option<i32>,
+    group_id: Option<EntityId>,
+    local_worlds: &mut ViewMut<LocalWorld>,
+    entities: &mut EntitiesViewMut,
+    config: &UniqueView<Configuration>,
+    global_world_channel: &UniqueView<GlobalMessageChannel>,
+    pool: &UniqueView<PgPool>,
+    game_data: &UniqueView<GameData>,
+) -> EntityId {
+    let world_id = entities.add_entity((), ());
+    let mut local_world = ecs::world::LocalWorld::new(
+        &**config.clone(),
+        &**pool.clone(),
+        &**game_data.clone(),
+        world_id,
+        zone_id,
+        global_world_channel.channel.clone(),
+    );
+    let local_world_channel = local_world.channel.clone();
+    let join_handle = task::spawn_blocking(move || {
+        local_world.run();
+        Ok(())
+    });
+
+    entities.add_component(
+        local_worlds,
+        LocalWorld {
+            instance_type,
+            channel_num,
+            zone_id,
+            channel: local_world_channel,
+            join_handle,
+            users: HashSet::new(),
+            deadline: None,
+            group_id,
+        },
+        world_id,
+    );
+
+    info!(
+        "Created local world {:?} for zone {} (channel {:?}, group {:?})",
+        world_id, zone_id, channel_num, group_id
+    );
+
+    world_id
+}
+
+/// De-spawns the user from it's current local world. The user spawner requests a new spawn
+/// once the local world persisted the user data.
+fn handle_user_relocating(
+    spawn: &mut GlobalUserSpawn,
+    connection_global_world_id: EntityId,
+    local_worlds: &mut ViewMut<LocalWorld>,
+) -> Result<()> {
+    handle_user_despawn(spawn, connection_global_world_id, local_worlds)?;
+
+    info!(
+        "Relocating user {:?} out of local world {:?}",
+        connection_global_world_id, spawn.local_world_id
+    );
+
+    spawn.status = UserSpawnStatus::Despawning;
+    spawn.connection_local_world_id = None;
+    spawn.local_world_id = None;
+    spawn.local_world_channel = None;
     Ok(())
 }
 
@@ -259,7 +394,8 @@ mod tests {
     use crate::ecs::component::GlobalConnection;
     use crate::ecs::dto::UserInitializer;
     use crate::ecs::message::Message;
-    use crate::model::entity::{Account, User, UserLocation};
+    use crate::ecs::resource::{Zone, ZoneRegistry};
+    use crate::model::entity::{Account, Inventory, User, UserLocation};
     use crate::model::repository::{account, user};
     use crate::model::tests::db_test;
     use crate::model::{Class, Gender, PasswordHashAlgorithm, Race};
@@ -292,6 +428,16 @@ mod tests {
             channel: tx_channel.clone(),
         });
         world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(GameData {
+            zones: ZoneRegistry::new(vec![Zone {
+                id: 9001,
+                zone_type: LocalWorldType::Dungeon,
+                channel_capacity: 0,
+                topology_id: 9001,
+                spawn_points: vec![],
+            }]),
+            ..Default::default()
+        });
 
         let account = account::create(
             &mut conn,
@@ -324,6 +470,7 @@ mod tests {
                 laurel: 0,
                 achievement_points: 0,
                 playtime: 0,
+                xp: 0,
                 rest_bonus_xp: 0,
                 show_face: false,
                 show_style: false,
@@ -334,6 +481,7 @@ mod tests {
                 delete_at: None,
                 last_logout_at: Utc.ymd(2007, 7, 8).and_hms(9, 10, 11),
                 created_at: Utc.ymd(2009, 7, 8).and_hms(9, 10, 11),
+                is_alive: true,
             },
         )
         .await?;
@@ -364,6 +512,8 @@ mod tests {
                         local_world_channel: None,
                         marked_for_deletion: false,
                         is_alive: false,
+                        channel_num: None,
+                        is_relocating: false,
                     },
                     id,
                 );
@@ -395,7 +545,9 @@ mod tests {
                 let mut local_world = ecs::world::LocalWorld::new(
                     conf,
                     pool,
+                    &GameData::default(),
                     local_world_id,
+                    0,
                     global_world_channel.clone(),
                 );
                 let local_world_channel = local_world.channel.clone();
@@ -409,12 +561,13 @@ mod tests {
                     &mut local_worlds,
                     LocalWorld {
                         instance_type: LocalWorldType::Field,
-                        channel_num: None,
+                        channel_num: Some(1),
                         zone_id: 0,
                         channel: local_world_channel.clone(),
                         join_handle,
                         users,
                         deadline,
+                        group_id: None,
                     },
                     local_world_id,
                 );
@@ -557,6 +710,140 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_user_requesting_spawn_world_type() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let pool = PgPool::new(db_string).await?;
+                let (world, connection_global_world_id, _tx_channel, _rx_channel, _account, _user) =
+                    setup(pool).await?;
+
+                world.run(|mut spawns: ViewMut<GlobalUserSpawn>| {
+                    let mut spawn = (&mut spawns).try_get(connection_global_world_id)?;
+                    spawn.status = UserSpawnStatus::Requesting;
+                    spawn.zone_id = 9001;
+
+                    Ok::<(), anyhow::Error>(())
+                })?;
+
+                world.run(local_world_manager_system);
+
+                world.run(|worlds: View<LocalWorld>| {
+                    assert_eq!(worlds.iter().count(), 1);
+                    let world = worlds.iter().next().unwrap();
+                    assert_eq!(world.zone_id, 9001);
+                    assert_eq!(world.instance_type, LocalWorldType::Dungeon);
+
+                    Ok::<(), anyhow::Error>(())
+                })?;
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_user_requesting_spawn_group_instance() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let pool = PgPool::new(db_string).await?;
+                let (world, connection_global_world_id, _tx_channel, _rx_channel, _account, _user) =
+                    setup(pool).await?;
+
+                let group_id = world.run(
+                    |mut entities: EntitiesViewMut,
+                     mut spawns: ViewMut<GlobalUserSpawn>,
+                     mut instance_requests: ViewMut<InstanceRequest>| {
+                        let group_id = entities.add_entity((), ());
+                        entities.add_component(
+                            &mut instance_requests,
+                            InstanceRequest { group_id },
+                            connection_global_world_id,
+                        );
+                        let mut spawn = (&mut spawns).try_get(connection_global_world_id)?;
+                        spawn.status = UserSpawnStatus::Requesting;
+                        spawn.zone_id = 9001;
+
+                        Ok::<EntityId, anyhow::Error>(group_id)
+                    },
+                )?;
+
+                world.run(local_world_manager_system);
+
+                let group_world_id = world.run(
+                    |worlds: View<LocalWorld>,
+                     spawns: View<GlobalUserSpawn>,
+                     instance_requests: View<InstanceRequest>| {
+                        assert_eq!(worlds.iter().count(), 1);
+                        let (group_world_id, group_world) = worlds.iter().with_id().next().unwrap();
+                        assert_eq!(group_world.zone_id, 9001);
+                        assert_eq!(group_world.group_id, Some(group_id));
+
+                        let spawn = spawns.try_get(connection_global_world_id)?;
+                        assert_eq!(spawn.local_world_id, Some(group_world_id));
+                        assert!(instance_requests
+                            .try_get(connection_global_world_id)
+                            .is_err());
+
+                        Ok::<EntityId, anyhow::Error>(group_world_id)
+                    },
+                )?;
+
+                // Users without a group don't use the instance of the group, but get an instance of their own
+                world.run(|mut spawns: ViewMut<GlobalUserSpawn>| {
+                    let mut spawn = (&mut spawns).try_get(connection_global_world_id)?;
+                    spawn.status = UserSpawnStatus::Requesting;
+
+                    Ok::<(), anyhow::Error>(())
+                })?;
+
+                world.run(local_world_manager_system);
+
+                world.run(|worlds: View<LocalWorld>, spawns: View<GlobalUserSpawn>| {
+                    assert_eq!(worlds.iter().count(), 2);
+                    let spawn = spawns.try_get(connection_global_world_id)?;
+                    assert_ne!(spawn.local_world_id, Some(group_world_id));
+                    assert_eq!(
+                        worlds[spawn.local_world_id.unwrap()].group_id,
+                        Some(connection_global_world_id)
+                    );
+
+                    Ok::<(), anyhow::Error>(())
+                })?;
+
+                // Members of the group use the existing instance
+                world.run(
+                    |entities: EntitiesViewMut,
+                     mut spawns: ViewMut<GlobalUserSpawn>,
+                     mut instance_requests: ViewMut<InstanceRequest>| {
+                        entities.add_component(
+                            &mut instance_requests,
+                            InstanceRequest { group_id },
+                            connection_global_world_id,
+                        );
+                        let mut spawn = (&mut spawns).try_get(connection_global_world_id)?;
+                        spawn.status = UserSpawnStatus::Requesting;
+
+                        Ok::<(), anyhow::Error>(())
+                    },
+                )?;
+
+                world.run(local_world_manager_system);
+
+                world.run(|worlds: View<LocalWorld>, spawns: View<GlobalUserSpawn>| {
+                    assert_eq!(worlds.iter().count(), 2);
+                    let spawn = spawns.try_get(connection_global_world_id)?;
+                    assert_eq!(spawn.status, UserSpawnStatus::CanSpawn);
+                    assert_eq!(spawn.local_world_id, Some(group_world_id));
+
+                    Ok::<(), anyhow::Error>(())
+                })?;
+
+                Ok(())
+            })
+        })
+    }
+
     #[test]
     fn test_user_requesting_spawn_world_reuse() -> Result<()> {
         db_test(|db_string| {
@@ -607,6 +894,258 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_user_requesting_spawn_full_channel() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let pool = PgPool::new(db_string).await?;
+                let (
+                    mut world,
+                    connection_global_world_id,
+                    tx_channel,
+                    _rx_channel,
+                    _account,
+                    _user,
+                ) = setup(pool.clone()).await?;
+
+                let (local_world_id, _local_world_channel) = create_local_world(
+                    &mut world,
+                    &tx_channel,
+                    &Configuration::default(),
+                    &pool,
+                    connection_global_world_id,
+                    None,
+                )?;
+
+                world.run(
+                    |mut entities: EntitiesViewMut,
+                     mut worlds: ViewMut<LocalWorld>,
+                     mut spawns: ViewMut<GlobalUserSpawn>,
+                     mut config: UniqueViewMut<Configuration>| {
+                        config.game.channel_user_cap = 1;
+
+                        // Fill the first channel with another user
+                        let mut local_world = (&mut worlds).try_get(local_world_id)?;
+                        local_world.users.clear();
+                        local_world.users.insert(entities.add_entity((), ()));
+
+                        let mut spawn = (&mut spawns).try_get(connection_global_world_id)?;
+                        spawn.status = UserSpawnStatus::Requesting;
+
+                        Ok::<(), anyhow::Error>(())
+                    },
+                )?;
+
+                world.run(local_world_manager_system);
+
+                world.run(|worlds: View<LocalWorld>, spawns: View<GlobalUserSpawn>| {
+                    assert_eq!(worlds.iter().count(), 2);
+
+                    let spawn = (&spawns).try_get(connection_global_world_id)?;
+                    assert_eq!(spawn.status, UserSpawnStatus::Waiting);
+                    assert_eq!(spawn.channel_num, Some(2));
+                    assert_ne!(spawn.local_world_id, Some(local_world_id));
+
+                    let new_world = worlds.try_get(spawn.local_world_id.unwrap())?;
+                    assert_eq!(new_world.channel_num, Some(2));
+                    assert!(new_world.users.contains(&connection_global_world_id));
+
+                    Ok::<(), anyhow::Error>(())
+                })?;
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_user_requesting_spawn_uncapped_channel() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let pool = PgPool::new(db_string).await?;
+                let (
+                    mut world,
+                    connection_global_world_id,
+                    tx_channel,
+                    _rx_channel,
+                    _account,
+                    _user,
+                ) = setup(pool.clone()).await?;
+
+                let (local_world_id, _local_world_channel) = create_local_world(
+                    &mut world,
+                    &tx_channel,
+                    &Configuration::default(),
+                    &pool,
+                    connection_global_world_id,
+                    None,
+                )?;
+
+                world.run(
+                    |mut entities: EntitiesViewMut,
+                     mut worlds: ViewMut<LocalWorld>,
+                     mut spawns: ViewMut<GlobalUserSpawn>,
+                     mut config: UniqueViewMut<Configuration>| {
+                        config.game.channel_user_cap = 0;
+
+                        let mut local_world = (&mut worlds).try_get(local_world_id)?;
+                        local_world.users.clear();
+                        local_world.users.insert(entities.add_entity((), ()));
+
+                        let mut spawn = (&mut spawns).try_get(connection_global_world_id)?;
+                        spawn.status = UserSpawnStatus::Requesting;
+
+                        Ok::<(), anyhow::Error>(())
+                    },
+                )?;
+
+                world.run(local_world_manager_system);
+
+                world.run(|worlds: View<LocalWorld>, spawns: View<GlobalUserSpawn>| {
+                    // A cap of 0 never fills a channel
+                    assert_eq!(worlds.iter().count(), 1);
+
+                    let spawn = (&spawns).try_get(connection_global_world_id)?;
+                    assert_eq!(spawn.local_world_id, Some(local_world_id));
+
+                    Ok::<(), anyhow::Error>(())
+                })?;
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_user_requesting_spawn_requested_channel() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let pool = PgPool::new(db_string).await?;
+                let (
+                    mut world,
+                    connection_global_world_id,
+                    tx_channel,
+                    _rx_channel,
+                    _account,
+                    _user,
+                ) = setup(pool.clone()).await?;
+
+                let (first_world_id, _first_world_channel) = create_local_world(
+                    &mut world,
+                    &tx_channel,
+                    &Configuration::default(),
+                    &pool,
+                    connection_global_world_id,
+                    None,
+                )?;
+                let (second_world_id, _second_world_channel) = create_local_world(
+                    &mut world,
+                    &tx_channel,
+                    &Configuration::default(),
+                    &pool,
+                    connection_global_world_id,
+                    None,
+                )?;
+
+                world.run(
+                    |mut entities: EntitiesViewMut,
+                     mut worlds: ViewMut<LocalWorld>,
+                     mut spawns: ViewMut<GlobalUserSpawn>| {
+                        let mut first_world = (&mut worlds).try_get(first_world_id)?;
+                        first_world.users.clear();
+
+                        // The requested channel has more users than the first channel
+                        let mut second_world = (&mut worlds).try_get(second_world_id)?;
+                        second_world.channel_num = Some(2);
+                        second_world.users.clear();
+                        second_world.users.insert(entities.add_entity((), ()));
+
+                        let mut spawn = (&mut spawns).try_get(connection_global_world_id)?;
+                        spawn.status = UserSpawnStatus::Requesting;
+                        spawn.channel_num = Some(2);
+
+                        Ok::<(), anyhow::Error>(())
+                    },
+                )?;
+
+                world.run(local_world_manager_system);
+
+                world.run(|worlds: View<LocalWorld>, spawns: View<GlobalUserSpawn>| {
+                    assert_eq!(worlds.iter().count(), 2);
+
+                    let spawn = (&spawns).try_get(connection_global_world_id)?;
+                    assert_eq!(spawn.status, UserSpawnStatus::CanSpawn);
+                    assert_eq!(spawn.channel_num, Some(2));
+                    assert_eq!(spawn.local_world_id, Some(second_world_id));
+
+                    Ok::<(), anyhow::Error>(())
+                })?;
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_user_relocating() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let pool = PgPool::new(db_string).await?;
+                let (
+                    mut world,
+                    connection_global_world_id,
+                    tx_channel,
+                    _rx_channel,
+                    _account,
+                    _user,
+                ) = setup(pool.clone()).await?;
+
+                let (local_world_id, local_world_channel) = create_local_world(
+                    &mut world,
+                    &tx_channel,
+                    &Configuration::default(),
+                    &pool,
+                    connection_global_world_id,
+                    None,
+                )?;
+
+                world.run(
+                    |mut entities: EntitiesViewMut, mut spawns: ViewMut<GlobalUserSpawn>| {
+                        let connection_local_world_id = entities.add_entity((), ());
+                        let mut spawn = (&mut spawns).try_get(connection_global_world_id)?;
+                        spawn.status = UserSpawnStatus::Relocating;
+                        spawn.is_relocating = true;
+                        spawn.channel_num = Some(2);
+                        spawn.connection_local_world_id = Some(connection_local_world_id);
+                        spawn.local_world_id = Some(local_world_id);
+                        spawn.local_world_channel = Some(local_world_channel.clone());
+
+                        Ok::<(), anyhow::Error>(())
+                    },
+                )?;
+
+                world.run(local_world_manager_system);
+
+                world.run(|worlds: View<LocalWorld>, spawns: View<GlobalUserSpawn>| {
+                    let local_world = worlds.try_get(local_world_id)?;
+                    assert!(local_world.users.is_empty());
+                    assert!(local_world.deadline.is_some());
+
+                    let spawn = (&spawns).try_get(connection_global_world_id)?;
+                    assert_eq!(spawn.status, UserSpawnStatus::Despawning);
+                    assert_eq!(spawn.channel_num, Some(2));
+                    assert!(spawn.connection_local_world_id.is_none());
+                    assert!(spawn.local_world_id.is_none());
+                    assert!(spawn.local_world_channel.is_none());
+
+                    Ok::<(), anyhow::Error>(())
+                })?;
+
+                Ok(())
+            })
+        })
+    }
+
     #[test]
     fn test_user_despawn() -> Result<()> {
         db_test(|db_string| {
@@ -651,6 +1190,18 @@ mod tests {
                                     rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
                                 },
                                 is_alive: true,
+                                visibility_range: 4000,
+                                guild_name: "".to_string(),
+                                guild_rank: "".to_string(),
+                                inventory: Inventory {
+                                    user_id: 0,
+                                    size: 40,
+                                    money: 0,
+                                    equipment_preset: 0,
+                                },
+                                items: vec![],
+                                equipment: vec![],
+                                abnormalities: vec![],
                             },
                         }),
                         &local_world_channel,
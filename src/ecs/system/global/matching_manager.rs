@@ -0,0 +1,1197 @@
+use crate::ecs::component::{
+    GlobalConnection, GlobalUserSpawn, InstanceRequest, LocalWorldType, MatchingEntry, Party,
+    PartyMember, ReadyCheck, UserSpawnStatus,
+};
+use crate::ecs::message::Message::{
+    ResponseAddInterPartyMatchPool, ResponseCheckToReadyParty, ResponseCheckToReadyPartyFin,
+    ResponseDelInterPartyMatchPool, ResponseFinInterPartyMatch,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::GameData;
+use crate::ecs::system::global::dungeon_manager::{
+    ensure_no_dungeon_lockout, register_dungeon_entry,
+};
+use crate::ecs::system::global::send_message_to_connection;
+use crate::model::repository::user;
+use crate::model::Role;
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use shipyard::*;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, info_span};
+
+/// Time the members of a matched group have to answer the ready check.
+const READY_CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of tanks in a dungeon group.
+const TANK_SLOTS: usize = 1;
+
+/// Number of healers in a dungeon group.
+const HEALER_SLOTS: usize = 1;
+
+/// Number of damage dealers in a dungeon group.
+const DAMAGE_SLOTS: usize = 3;
+
+/// Number of members of a dungeon group.
+const GROUP_SIZE: usize = TANK_SLOTS + HEALER_SLOTS + DAMAGE_SLOTS;
+
+/// ID of the server the users are playing on.
+const SERVER_ID: i32 = 1;
+
+/// The matching manager queues users and parties for dungeons and forms groups of one tank, one
+/// healer and three damage dealers out of them. The members of a group need to pass a ready check
+/// before they are relocated into a dungeon instance of their own. Entries that decline or don't
+/// answer the ready check leave the queue, the other entries of the group are queued again.
+pub fn matching_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    mut spawns: ViewMut<GlobalUserSpawn>,
+    parties: View<Party>,
+    party_members: View<PartyMember>,
+    mut matching_entries: ViewMut<MatchingEntry>,
+    mut instance_requests: ViewMut<InstanceRequest>,
+    entities: EntitiesView,
+    pool: UniqueView<PgPool>,
+    game_data: UniqueView<GameData>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestAddInterPartyMatchPool {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_add_inter_party_match_pool(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &parties,
+                    &party_members,
+                    &mut matching_entries,
+                    &entities,
+                    &pool,
+                    &game_data,
+                ) {
+                    error!("Ignoring add inter party match pool request: {:?}", e);
+                }
+            }
+            Message::RequestDelInterPartyMatchPool {
+                connection_global_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_del_inter_party_match_pool(
+                    *connection_global_world_id,
+                    &connections,
+                    &mut matching_entries,
+                ) {
+                    error!("Ignoring del inter party match pool request: {:?}", e);
+                }
+            }
+            Message::RequestCheckToReadyPartyAnswer {
+                connection_global_world_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_check_to_ready_party_answer(
+                    *connection_global_world_id,
+                    &packet,
+                    &connections,
+                    &mut spawns,
+                    &mut matching_entries,
+                    &mut instance_requests,
+                    &entities,
+                    &pool,
+                ) {
+                    error!("Ignoring check to ready party answer: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+
+    // Entries leave the queue once one of their members went offline.
+    let offline_entries: Vec<EntityId> = matching_entries
+        .iter()
+        .with_id()
+        .filter(|(_id, entry)| {
+            entry.members.iter().any(|(member_id, _role)| {
+                spawns
+                    .try_get(*member_id)
+                    .map_or(true, |spawn| spawn.marked_for_deletion)
+            })
+        })
+        .map(|(id, _)| id)
+        .collect();
+    for entry_id in offline_entries {
+        debug!("Member of matching entry {:?} went offline", entry_id);
+        if let Err(e) = leave_queue(entry_id, &connections, &mut matching_entries) {
+            error!("Can't remove offline matching entry: {:?}", e);
+        }
+    }
+
+    // Entries that didn't answer the ready check in time leave the queue.
+    let now = Instant::now();
+    let expired_entries: Vec<EntityId> = matching_entries
+        .iter()
+        .with_id()
+        .filter(|(_id, entry)| {
+            entry
+                .ready_check
+                .as_ref()
+                .map_or(false, |check| check.deadline < now && !is_ready(entry))
+        })
+        .map(|(id, _)| id)
+        .collect();
+    for entry_id in expired_entries {
+        debug!("Ready check of matching entry {:?} expired", entry_id);
+        if let Err(e) = leave_queue(entry_id, &connections, &mut matching_entries) {
+            error!("Can't remove expired matching entry: {:?}", e);
+        }
+    }
+
+    for (zone_id, group) in form_groups(&matching_entries) {
+        if let Err(e) = start_ready_check(
+            zone_id,
+            &group,
+            &connections,
+            &spawns,
+            &mut matching_entries,
+        ) {
+            error!("Can't start ready check: {:?}", e);
+        }
+    }
+}
+
+fn handle_add_inter_party_match_pool(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CAddInterPartyMatchPool,
+    connections: &View<GlobalConnection>,
+    spawns: &ViewMut<GlobalUserSpawn>,
+    parties: &View<Party>,
+    party_members: &View<PartyMember>,
+    matching_entries: &mut ViewMut<MatchingEntry>,
+    entities: &EntitiesView,
+    pool: &UniqueView<PgPool>,
+    game_data: &UniqueView<GameData>,
+) -> Result<()> {
+    debug!("Message::RequestAddInterPartyMatchPool incoming");
+
+    ensure!(
+        !packet.instances.is_empty(),
+        "User {} didn't select a dungeon",
+        user_id
+    );
+    let zone_ids: Vec<i32> = packet
+        .instances
+        .iter()
+        .map(|instance| instance.zone_id)
+        .collect();
+    for zone_id in zone_ids.iter() {
+        ensure!(
+            game_data.zones.zone_type(*zone_id) == LocalWorldType::Dungeon,
+            "Zone {} is not a dungeon",
+            zone_id
+        );
+    }
+
+    // Parties can only be queued by their leader.
+    let member_ids = if let Ok(member) = party_members.try_get(connection_global_world_id) {
+        let party = parties
+            .try_get(member.party_id)
+            .context(format!("Can't find party {:?}", member.party_id))?;
+        ensure!(
+            party.leader_id == connection_global_world_id,
+            "User {} is not the leader of it's party",
+            user_id
+        );
+        party.members.clone()
+    } else {
+        vec![connection_global_world_id]
+    };
+
+    for member_id in member_ids.iter() {
+        ensure!(
+            find_entry(*member_id, matching_entries).is_none(),
+            "User {:?} is already queued",
+            member_id
+        );
+    }
+
+    let user_ids = member_ids
+        .iter()
+        .map(|member_id| get_user_id(*member_id, spawns))
+        .collect::<Result<Vec<i32>>>()?;
+    let roles = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        for zone_id in zone_ids.iter() {
+            ensure_no_dungeon_lockout(&mut conn, &user_ids, *zone_id).await?;
+        }
+
+        let mut roles = Vec::with_capacity(user_ids.len());
+        for user_id in user_ids {
+            let user = user::get_by_id(&mut conn, user_id)
+                .await
+                .context(format!("Can't find user {}", user_id))?;
+            roles.push(user.class.role());
+        }
+
+        Ok::<Vec<Role>, anyhow::Error>(roles)
+    })?;
+
+    let members: Vec<(EntityId, Role)> = member_ids.into_iter().zip(roles).collect();
+    ensure!(
+        fits_into_group(&members),
+        "Party of user {} doesn't fit into a dungeon group",
+        user_id
+    );
+
+    for (member_id, _role) in members.iter() {
+        send_message_to_connection(assemble_add_inter_party_match_pool(*member_id), connections);
+    }
+
+    info!(
+        "User {:?} queued {} member(s) for the dungeons {:?}",
+        connection_global_world_id,
+        members.len(),
+        zone_ids
+    );
+
+    entities.add_component(
+        &mut *matching_entries,
+        MatchingEntry {
+            zone_ids,
+            members,
+            queued_at: Instant::now(),
+            ready_check: None,
+        },
+        connection_global_world_id,
+    );
+
+    Ok(())
+}
+
+fn handle_del_inter_party_match_pool(
+    connection_global_world_id: EntityId,
+    connections: &View<GlobalConnection>,
+    matching_entries: &mut ViewMut<MatchingEntry>,
+) -> Result<()> {
+    debug!("Message::RequestDelInterPartyMatchPool incoming");
+
+    let entry_id =
+        find_entry(connection_global_world_id, matching_entries).context("User is not queued")?;
+    leave_queue(entry_id, connections, matching_entries)
+}
+
+fn handle_check_to_ready_party_answer(
+    connection_global_world_id: EntityId,
+    packet: &CCheckToReadyPartyAnswer,
+    connections: &View<GlobalConnection>,
+    spawns: &mut ViewMut<GlobalUserSpawn>,
+    matching_entries: &mut ViewMut<MatchingEntry>,
+    instance_requests: &mut ViewMut<InstanceRequest>,
+    entities: &EntitiesView,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestCheckToReadyPartyAnswer incoming");
+
+    let entry_id =
+        find_entry(connection_global_world_id, matching_entries).context("User is not queued")?;
+    let group_id = matching_entries[entry_id]
+        .ready_check
+        .as_ref()
+        .context("User has no pending ready check")?
+        .group_id;
+
+    if !packet.ready {
+        info!(
+            "User {:?} declined the ready check of group {:?}",
+            connection_global_world_id, group_id
+        );
+        return leave_queue(entry_id, connections, matching_entries);
+    }
+
+    if let Some(check) = matching_entries[entry_id].ready_check.as_mut() {
+        check.ready.insert(connection_global_world_id);
+    }
+
+    let group = group_entries(group_id, matching_entries);
+    send_ready_check(&group, connections, spawns, matching_entries)?;
+
+    if group.iter().all(|id| is_ready(&matching_entries[*id])) {
+        enter_instance(
+            group_id,
+            &group,
+            connections,
+            spawns,
+            matching_entries,
+            instance_requests,
+            entities,
+            pool,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Forms dungeon groups out of the queued entries that are not part of a ready check. Entries
+/// are matched in the order they were queued. Returns the dungeon and the entries of every group.
+fn form_groups(matching_entries: &ViewMut<MatchingEntry>) -> Vec<(i32, Vec<EntityId>)> {
+    let mut queue: Vec<(EntityId, &MatchingEntry)> = matching_entries
+        .iter()
+        .with_id()
+        .filter(|(_id, entry)| entry.ready_check.is_none())
+        .collect();
+    queue.sort_by_key(|(_id, entry)| entry.queued_at);
+
+    let mut groups = Vec::new();
+    let mut index = 0;
+    while index < queue.len() {
+        let formed_group = queue[index].1.zone_ids.iter().find_map(|zone_id| {
+            let mut members: Vec<(EntityId, Role)> = Vec::with_capacity(GROUP_SIZE);
+            let mut group = Vec::new();
+            for (id, entry) in queue
+                .iter()
+                .skip(index)
+                .filter(|(_id, entry)| entry.zone_ids.contains(zone_id))
+            {
+                let mut candidate = members.clone();
+                candidate.extend(entry.members.iter().copied());
+                if fits_into_group(&candidate) {
+                    members = candidate;
+                    group.push(*id);
+                }
+                if members.len() == GROUP_SIZE {
+                    return Some((*zone_id, group));
+                }
+            }
+            None
+        });
+
+        if let Some((zone_id, group)) = formed_group {
+            queue.retain(|(id, _entry)| !group.contains(id));
+            groups.push((zone_id, group));
+        } else {
+            index += 1;
+        }
+    }
+    groups
+}
+
+/// Checks if the given members fit into a dungeon group without exceeding the slots of a role.
+fn fits_into_group(members: &[(EntityId, Role)]) -> bool {
+    [Role::Tank, Role::Healer, Role::Damage].iter().all(|role| {
+        members
+            .iter()
+            .filter(|(_id, member_role)| member_role == role)
+            .count()
+            <= role_slots(*role)
+    })
+}
+
+fn role_slots(role: Role) -> usize {
+    match role {
+        Role::Tank => TANK_SLOTS,
+        Role::Healer => HEALER_SLOTS,
+        Role::Damage => DAMAGE_SLOTS,
+    }
+}
+
+/// Starts the ready check of a newly formed group. The entry that was queued first identifies the group.
+fn start_ready_check(
+    zone_id: i32,
+    group: &[EntityId],
+    connections: &View<GlobalConnection>,
+    spawns: &ViewMut<GlobalUserSpawn>,
+    matching_entries: &mut ViewMut<MatchingEntry>,
+) -> Result<()> {
+    info!("Formed group {:?} for dungeon {}", group[0], zone_id);
+
+    let deadline = Instant::now().checked_add(READY_CHECK_TIMEOUT).unwrap();
+    for entry_id in group.iter() {
+        matching_entries[*entry_id].ready_check = Some(ReadyCheck {
+            group_id: group[0],
+            zone_id,
+            ready: HashSet::new(),
+            deadline,
+        });
+    }
+
+    send_ready_check(group, connections, spawns, matching_entries)
+}
+
+/// Sends the ready state of all members of a group to the members.
+fn send_ready_check(
+    group: &[EntityId],
+    connections: &View<GlobalConnection>,
+    spawns: &ViewMut<GlobalUserSpawn>,
+    matching_entries: &ViewMut<MatchingEntry>,
+) -> Result<()> {
+    let mut packet = SCheckToReadyParty {
+        members: Vec::with_capacity(GROUP_SIZE),
+        zone_id: 0,
+    };
+    for entry_id in group.iter() {
+        let entry = matching_entries
+            .try_get(*entry_id)
+            .context(format!("Can't find matching entry {:?}", entry_id))?;
+        let check = entry
+            .ready_check
+            .as_ref()
+            .context(format!("Matching entry {:?} has no ready check", entry_id))?;
+
+        packet.zone_id = check.zone_id;
+        for (member_id, _role) in entry.members.iter() {
+            packet.members.push(SCheckToReadyPartyEntry {
+                server_id: SERVER_ID,
+                user_id: get_user_id(*member_id, spawns)?,
+                ready: check.ready.contains(member_id),
+            });
+        }
+    }
+
+    for entry_id in group.iter() {
+        for (member_id, _role) in matching_entries[*entry_id].members.iter() {
+            send_message_to_connection(
+                assemble_check_to_ready_party(*member_id, packet.clone()),
+                connections,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Relocates the members of a group that passed it's ready check into a dungeon instance of
+/// their own. The local world manager creates the instance once the first member requests it.
+fn enter_instance(
+    group_id: EntityId,
+    group: &[EntityId],
+    connections: &View<GlobalConnection>,
+    spawns: &mut ViewMut<GlobalUserSpawn>,
+    matching_entries: &mut ViewMut<MatchingEntry>,
+    instance_requests: &mut ViewMut<InstanceRequest>,
+    entities: &EntitiesView,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    let zone_id = matching_entries[group_id]
+        .ready_check
+        .as_ref()
+        .context(format!("Group {:?} has no ready check", group_id))?
+        .zone_id;
+
+    info!("Group {:?} enters dungeon {}", group_id, zone_id);
+
+    // The group already passed the ready check, so a failed update of the lockouts doesn't stop it.
+    let mut user_ids = Vec::with_capacity(GROUP_SIZE);
+    for entry_id in group.iter() {
+        for (member_id, _role) in matching_entries[*entry_id].members.iter() {
+            user_ids.push(get_user_id(*member_id, spawns)?);
+        }
+    }
+    if let Err(e) = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        register_dungeon_entry(&mut conn, &user_ids, zone_id).await?;
+
+        conn.commit().await?;
+
+        Ok::<(), anyhow::Error>(())
+    }) {
+        error!(
+            "Can't register the dungeon entry of group {:?}: {:?}",
+            group_id, e
+        );
+    }
+
+    for entry_id in group.iter() {
+        let entry = matching_entries
+            .try_get(*entry_id)
+            .context(format!("Can't find matching entry {:?}", entry_id))?
+            .clone();
+        matching_entries.delete(*entry_id);
+
+        for (member_id, _role) in entry.members.into_iter() {
+            send_message_to_connection(assemble_check_to_ready_party_fin(member_id), connections);
+            send_message_to_connection(
+                assemble_fin_inter_party_match(member_id, zone_id),
+                connections,
+            );
+
+            match spawns.try_get(member_id) {
+                Ok(spawn) if spawn.status == UserSpawnStatus::Spawned => {
+                    spawn.zone_id = zone_id;
+                    spawn.status = UserSpawnStatus::Relocating;
+                    spawn.is_relocating = true;
+                    entities.add_component(
+                        &mut *instance_requests,
+                        InstanceRequest { group_id },
+                        member_id,
+                    );
+                }
+                _ => error!("Can't relocate user {:?} into the dungeon", member_id),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes an entry from the queue. If the entry is part of a group, the ready check of the group
+/// is canceled and the other entries of the group are queued again.
+fn leave_queue(
+    entry_id: EntityId,
+    connections: &View<GlobalConnection>,
+    matching_entries: &mut ViewMut<MatchingEntry>,
+) -> Result<()> {
+    let entry = matching_entries
+        .try_get(entry_id)
+        .context(format!("Can't find matching entry {:?}", entry_id))?
+        .clone();
+    matching_entries.delete(entry_id);
+
+    for (member_id, _role) in entry.members.iter() {
+        if entry.ready_check.is_some() {
+            send_message_to_connection(assemble_check_to_ready_party_fin(*member_id), connections);
+        }
+        send_message_to_connection(assemble_del_inter_party_match_pool(*member_id), connections);
+    }
+
+    if let Some(check) = entry.ready_check {
+        for group_entry_id in group_entries(check.group_id, matching_entries) {
+            let group_entry = &mut matching_entries[group_entry_id];
+            group_entry.ready_check = None;
+            for (member_id, _role) in group_entry.members.iter() {
+                send_message_to_connection(
+                    assemble_check_to_ready_party_fin(*member_id),
+                    connections,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the matching entry an user is queued with.
+fn find_entry(
+    connection_global_world_id: EntityId,
+    matching_entries: &ViewMut<MatchingEntry>,
+) -> Option<EntityId> {
+    matching_entries
+        .iter()
+        .with_id()
+        .find(|(_id, entry)| {
+            entry
+                .members
+                .iter()
+                .any(|(member_id, _role)| *member_id == connection_global_world_id)
+        })
+        .map(|(id, _)| id)
+}
+
+/// Returns the entries of a group in the order they were queued.
+fn group_entries(group_id: EntityId, matching_entries: &ViewMut<MatchingEntry>) -> Vec<EntityId> {
+    let mut entries: Vec<(EntityId, &MatchingEntry)> = matching_entries
+        .iter()
+        .with_id()
+        .filter(|(_id, entry)| {
+            entry
+                .ready_check
+                .as_ref()
+                .map_or(false, |check| check.group_id == group_id)
+        })
+        .collect();
+    entries.sort_by_key(|(_id, entry)| entry.queued_at);
+    entries.into_iter().map(|(id, _entry)| id).collect()
+}
+
+/// Checks if all members of an entry answered it's ready check.
+fn is_ready(entry: &MatchingEntry) -> bool {
+    entry.ready_check.as_ref().map_or(false, |check| {
+        entry
+            .members
+            .iter()
+            .all(|(member_id, _role)| check.ready.contains(member_id))
+    })
+}
+
+fn get_user_id(
+    connection_global_world_id: EntityId,
+    spawns: &ViewMut<GlobalUserSpawn>,
+) -> Result<i32> {
+    Ok(spawns
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find user spawn {:?}",
+            connection_global_world_id
+        ))?
+        .user_id)
+}
+
+fn assemble_add_inter_party_match_pool(connection_global_world_id: EntityId) -> EcsMessage {
+    Box::new(ResponseAddInterPartyMatchPool {
+        connection_global_world_id,
+        packet: SAddInterPartyMatchPool {},
+    })
+}
+
+fn assemble_del_inter_party_match_pool(connection_global_world_id: EntityId) -> EcsMessage {
+    Box::new(ResponseDelInterPartyMatchPool {
+        connection_global_world_id,
+        packet: SDelInterPartyMatchPool {},
+    })
+}
+
+fn assemble_check_to_ready_party(
+    connection_global_world_id: EntityId,
+    packet: SCheckToReadyParty,
+) -> EcsMessage {
+    Box::new(ResponseCheckToReadyParty {
+        connection_global_world_id,
+        packet,
+    })
+}
+
+fn assemble_check_to_ready_party_fin(connection_global_world_id: EntityId) -> EcsMessage {
+    Box::new(ResponseCheckToReadyPartyFin {
+        connection_global_world_id,
+        packet: SCheckToReadyPartyFin {},
+    })
+}
+
+fn assemble_fin_inter_party_match(
+    connection_global_world_id: EntityId,
+    zone_id: i32,
+) -> EcsMessage {
+    Box::new(ResponseFinInterPartyMatch {
+        connection_global_world_id,
+        packet: SFinInterPartyMatch { zone_id },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::{DeletionList, Zone, ZoneRegistry};
+    use crate::ecs::system::common::cleaner_system;
+    use crate::ecs::system::global::dungeon_manager::DUNGEON_ENTRY_LIMIT;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, dungeon_lockout};
+    use crate::model::tests::db_test;
+    use crate::model::{Class, LootingMethod};
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver};
+
+    struct TestUser {
+        user: User,
+        connection_global_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(pool);
+        world.add_unique(GameData {
+            zones: ZoneRegistry::new(vec![
+                Zone {
+                    id: 5,
+                    zone_type: LocalWorldType::Field,
+                    channel_capacity: 0,
+                    topology_id: 5,
+                    spawn_points: vec![],
+                },
+                Zone {
+                    id: 9001,
+                    zone_type: LocalWorldType::Dungeon,
+                    channel_capacity: 0,
+                    topology_id: 9001,
+                    spawn_points: vec![],
+                },
+            ]),
+            ..Default::default()
+        });
+        world
+    }
+
+    async fn create_user(pool: &PgPool, num: i32, class: Class) -> Result<User> {
+        let mut conn = pool.acquire().await?;
+        let account = account::create(&mut conn, &get_default_account(num)).await?;
+        let mut user = get_default_user(&account, num);
+        user.class = class;
+        user::create(&mut conn, &user).await
+    }
+
+    fn add_user(world: &World, pool: &PgPool, num: i32, class: Class) -> Result<TestUser> {
+        let user = task::block_on(async { create_user(pool, num, class).await })?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut spawns: ViewMut<GlobalUserSpawn>| {
+                entities.add_entity(
+                    (&mut connections, &mut spawns),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        GlobalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_local_world_id: None,
+                            local_world_id: None,
+                            local_world_channel: None,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: Some(1),
+                            is_relocating: false,
+                        },
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            user,
+            connection_global_world_id,
+            rx: rx_channel,
+        })
+    }
+
+    /// Adds a tank, a healer and three damage dealers.
+    fn add_group(world: &World, pool: &PgPool) -> Result<Vec<TestUser>> {
+        Ok(vec![
+            add_user(world, pool, 0, Class::Lancer)?,
+            add_user(world, pool, 1, Class::Priest)?,
+            add_user(world, pool, 2, Class::Warrior)?,
+            add_user(world, pool, 3, Class::Archer)?,
+            add_user(world, pool, 4, Class::Sorcerer)?,
+        ])
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(matching_manager_system);
+        world.run(cleaner_system);
+    }
+
+    fn queue(world: &World, user: &TestUser, zone_id: i32) {
+        run_message(
+            world,
+            Message::RequestAddInterPartyMatchPool {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CAddInterPartyMatchPool {
+                    instances: vec![CAddInterPartyMatchPoolEntry { zone_id }],
+                },
+            },
+        );
+    }
+
+    fn answer(world: &World, user: &TestUser, ready: bool) {
+        run_message(
+            world,
+            Message::RequestCheckToReadyPartyAnswer {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CCheckToReadyPartyAnswer { ready },
+            },
+        );
+    }
+
+    fn clear_messages(user: &TestUser) {
+        while user.rx.try_recv().is_ok() {}
+    }
+
+    fn get_entry(world: &World, user: &TestUser) -> Option<MatchingEntry> {
+        world.run(|matching_entries: View<MatchingEntry>| {
+            matching_entries
+                .try_get(user.connection_global_world_id)
+                .ok()
+                .cloned()
+        })
+    }
+
+    fn assert_add_inter_party_match_pool(message: EcsMessage) {
+        match &*message {
+            Message::ResponseAddInterPartyMatchPool { .. } => {}
+            _ => panic!("Message is not a ResponseAddInterPartyMatchPool message"),
+        }
+    }
+
+    fn assert_del_inter_party_match_pool(message: EcsMessage) {
+        match &*message {
+            Message::ResponseDelInterPartyMatchPool { .. } => {}
+            _ => panic!("Message is not a ResponseDelInterPartyMatchPool message"),
+        }
+    }
+
+    fn assert_check_to_ready_party(message: EcsMessage) -> SCheckToReadyParty {
+        match &*message {
+            Message::ResponseCheckToReadyParty { packet, .. } => packet.clone(),
+            _ => panic!("Message is not a ResponseCheckToReadyParty message"),
+        }
+    }
+
+    fn assert_check_to_ready_party_fin(message: EcsMessage) {
+        match &*message {
+            Message::ResponseCheckToReadyPartyFin { .. } => {}
+            _ => panic!("Message is not a ResponseCheckToReadyPartyFin message"),
+        }
+    }
+
+    #[test]
+    fn test_fits_into_group() {
+        let id =
+            from_vec::<EntityId>(vec![0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]).unwrap();
+        assert!(fits_into_group(&[(id, Role::Tank), (id, Role::Damage)]));
+        assert!(fits_into_group(&[
+            (id, Role::Tank),
+            (id, Role::Healer),
+            (id, Role::Damage),
+            (id, Role::Damage),
+            (id, Role::Damage),
+        ]));
+        assert!(!fits_into_group(&[(id, Role::Healer), (id, Role::Healer)]));
+        assert!(!fits_into_group(&[
+            (id, Role::Damage),
+            (id, Role::Damage),
+            (id, Role::Damage),
+            (id, Role::Damage),
+        ]));
+    }
+
+    #[test]
+    fn test_queue_and_leave() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 0, Class::Lancer)?;
+
+            queue(&world, &user, 9001);
+            assert_add_inter_party_match_pool(user.rx.try_recv()?);
+            let entry = get_entry(&world, &user).unwrap();
+            assert_eq!(entry.zone_ids, vec![9001]);
+            assert_eq!(
+                entry.members,
+                vec![(user.connection_global_world_id, Role::Tank)]
+            );
+            assert!(entry.ready_check.is_none());
+
+            // Users can't queue twice and only for dungeons.
+            queue(&world, &user, 9001);
+            assert!(user.rx.is_empty());
+            let other = add_user(&world, &pool, 1, Class::Warrior)?;
+            queue(&world, &other, 5);
+            assert!(other.rx.is_empty());
+            assert!(get_entry(&world, &other).is_none());
+
+            run_message(
+                &world,
+                Message::RequestDelInterPartyMatchPool {
+                    connection_global_world_id: user.connection_global_world_id,
+                    account_id: user.user.account_id,
+                    user_id: user.user.id,
+                    packet: CDelInterPartyMatchPool {},
+                },
+            );
+            assert_del_inter_party_match_pool(user.rx.try_recv()?);
+            assert!(get_entry(&world, &user).is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_locked_out_user_cant_queue() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 0, Class::Lancer)?;
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                for _ in 0..DUNGEON_ENTRY_LIMIT {
+                    register_dungeon_entry(&mut conn, &[user.user.id], 9001).await?;
+                }
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            queue(&world, &user, 9001);
+            assert!(user.rx.is_empty());
+            assert!(get_entry(&world, &user).is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_party_queue() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let leader = add_user(&world, &pool, 0, Class::Lancer)?;
+            let member = add_user(&world, &pool, 1, Class::Priest)?;
+            world.run(
+                |mut entities: EntitiesViewMut,
+                 mut parties: ViewMut<Party>,
+                 mut party_members: ViewMut<PartyMember>| {
+                    let party_id = entities.add_entity(
+                        &mut parties,
+                        Party {
+                            leader_id: leader.connection_global_world_id,
+                            members: vec![
+                                leader.connection_global_world_id,
+                                member.connection_global_world_id,
+                            ],
+                            looting_method: LootingMethod::RoundRobin,
+                            is_raid: false,
+                        },
+                    );
+                    for id in [
+                        leader.connection_global_world_id,
+                        member.connection_global_world_id,
+                    ]
+                    .iter()
+                    {
+                        entities.add_component(&mut party_members, PartyMember { party_id }, *id);
+                    }
+                },
+            );
+
+            // Only the leader can queue the party.
+            queue(&world, &member, 9001);
+            assert!(member.rx.is_empty());
+
+            queue(&world, &leader, 9001);
+            assert_add_inter_party_match_pool(leader.rx.try_recv()?);
+            assert_add_inter_party_match_pool(member.rx.try_recv()?);
+            assert_eq!(
+                get_entry(&world, &leader).unwrap().members,
+                vec![
+                    (leader.connection_global_world_id, Role::Tank),
+                    (member.connection_global_world_id, Role::Healer)
+                ]
+            );
+
+            // Members of a queued party leave the queue with their party.
+            run_message(
+                &world,
+                Message::RequestDelInterPartyMatchPool {
+                    connection_global_world_id: member.connection_global_world_id,
+                    account_id: member.user.account_id,
+                    user_id: member.user.id,
+                    packet: CDelInterPartyMatchPool {},
+                },
+            );
+            assert_del_inter_party_match_pool(leader.rx.try_recv()?);
+            assert_del_inter_party_match_pool(member.rx.try_recv()?);
+            assert!(get_entry(&world, &leader).is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_group_enters_dungeon() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let group = add_group(&world, &pool)?;
+            let healer = add_user(&world, &pool, 5, Class::Elementalist)?;
+
+            // The second healer doesn't fit into the group.
+            queue(&world, &group[0], 9001);
+            queue(&world, &group[1], 9001);
+            queue(&world, &healer, 9001);
+            queue(&world, &group[2], 9001);
+            queue(&world, &group[3], 9001);
+            assert!(get_entry(&world, &group[0]).unwrap().ready_check.is_none());
+
+            queue(&world, &group[4], 9001);
+            for user in group.iter() {
+                assert_add_inter_party_match_pool(user.rx.try_recv()?);
+                let check = assert_check_to_ready_party(user.rx.try_recv()?);
+                assert_eq!(check.zone_id, 9001);
+                assert_eq!(check.members.len(), 5);
+                assert_eq!(check.members[0].user_id, group[0].user.id);
+                assert!(check.members.iter().all(|member| !member.ready));
+            }
+            clear_messages(&healer);
+            assert!(get_entry(&world, &healer).unwrap().ready_check.is_none());
+
+            for user in group.iter() {
+                answer(&world, user, true);
+            }
+
+            let group_id = group[0].connection_global_world_id;
+            for user in group.iter() {
+                let mut last_check = None;
+                while let Ok(message) = user.rx.try_recv() {
+                    match &*message {
+                        Message::ResponseCheckToReadyParty { packet, .. } => {
+                            last_check = Some(packet.clone())
+                        }
+                        Message::ResponseCheckToReadyPartyFin { .. } => break,
+                        _ => panic!("Unexpected message"),
+                    }
+                }
+                assert!(last_check
+                    .unwrap()
+                    .members
+                    .iter()
+                    .all(|member| member.ready));
+                match &*user.rx.try_recv()? {
+                    Message::ResponseFinInterPartyMatch { packet, .. } => {
+                        assert_eq!(packet.zone_id, 9001);
+                    }
+                    _ => panic!("Message is not a ResponseFinInterPartyMatch message"),
+                }
+                assert!(get_entry(&world, user).is_none());
+
+                world.run(
+                    |spawns: View<GlobalUserSpawn>, instance_requests: View<InstanceRequest>| {
+                        let spawn = spawns.try_get(user.connection_global_world_id)?;
+                        assert_eq!(spawn.zone_id, 9001);
+                        assert_eq!(spawn.status, UserSpawnStatus::Relocating);
+                        assert!(spawn.is_relocating);
+                        let request = instance_requests.try_get(user.connection_global_world_id)?;
+                        assert_eq!(request.group_id, group_id);
+
+                        Ok::<(), anyhow::Error>(())
+                    },
+                )?;
+            }
+            assert!(get_entry(&world, &healer).is_some());
+
+            let entry_count = task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                dungeon_lockout::get_entry_count(&mut conn, group[0].user.id, 9001).await
+            })?;
+            assert_eq!(entry_count, 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_ready_check_declined() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let group = add_group(&world, &pool)?;
+            for user in group.iter() {
+                queue(&world, user, 9001);
+            }
+            group.iter().for_each(clear_messages);
+
+            answer(&world, &group[0], true);
+            group.iter().for_each(clear_messages);
+            answer(&world, &group[1], false);
+
+            assert_check_to_ready_party_fin(group[1].rx.try_recv()?);
+            assert_del_inter_party_match_pool(group[1].rx.try_recv()?);
+            assert!(get_entry(&world, &group[1]).is_none());
+
+            // The other users are queued again.
+            for user in group.iter().filter(|user| user.user.id != group[1].user.id) {
+                assert_check_to_ready_party_fin(user.rx.try_recv()?);
+                assert!(user.rx.is_empty());
+                assert!(get_entry(&world, user).unwrap().ready_check.is_none());
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_ready_check_timeout() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let group = add_group(&world, &pool)?;
+            for user in group.iter() {
+                queue(&world, user, 9001);
+            }
+            answer(&world, &group[0], true);
+            group.iter().for_each(clear_messages);
+
+            world.run(|mut matching_entries: ViewMut<MatchingEntry>| {
+                for entry in (&mut matching_entries).iter() {
+                    if let Some(check) = entry.ready_check.as_mut() {
+                        check.deadline = Instant::now() - Duration::from_secs(1);
+                    }
+                }
+            });
+            world.run(matching_manager_system);
+
+            // Only the user that answered the ready check stays queued.
+            assert_check_to_ready_party_fin(group[0].rx.try_recv()?);
+            assert!(group[0].rx.is_empty());
+            assert!(get_entry(&world, &group[0]).unwrap().ready_check.is_none());
+            for user in group.iter().skip(1) {
+                assert_check_to_ready_party_fin(user.rx.try_recv()?);
+                assert_del_inter_party_match_pool(user.rx.try_recv()?);
+                assert!(get_entry(&world, user).is_none());
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_offline_user_leaves_queue() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 0, Class::Warrior)?;
+            queue(&world, &user, 9001);
+            clear_messages(&user);
+
+            world.run(|mut spawns: ViewMut<GlobalUserSpawn>| {
+                (&mut spawns)
+                    .try_get(user.connection_global_world_id)
+                    .unwrap()
+                    .marked_for_deletion = true;
+            });
+            world.run(matching_manager_system);
+
+            assert_del_inter_party_match_pool(user.rx.try_recv()?);
+            assert!(get_entry(&world, &user).is_none());
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,944 @@
+use crate::ecs::component::GlobalConnection;
+use crate::ecs::message::Message::{
+    ResponseDeleteParcel, ResponseListParcelEx, ResponseParcelReadRecvStatus, ResponseReturnParcel,
+    ResponseShowParcelMessage,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::Tick;
+use crate::ecs::system::global::send_message_to_connection;
+use crate::model::entity::{Parcel, ParcelItem};
+use crate::model::repository::{parcel, parcel_item, user};
+use crate::model::{MAX_PARCEL_ITEMS, PARCEL_EXPIRATION_DAYS};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use chrono::{Duration, Utc};
+use shipyard::*;
+use sqlx::prelude::*;
+use sqlx::{PgConnection, PgPool};
+use tracing::{debug, error, info_span};
+
+/// Number of ticks between the checks for expired parcels (every minute).
+const UPDATE_INTERVAL: u64 = 600;
+
+/// Number of parcels shown on one page of the parcel list.
+const PARCELS_PER_PAGE: usize = 8;
+
+/// The parcel manager handles the in-game mails of the users. Users can list, read, delete and
+/// return the parcels they received, while sending parcels and receiving their attachments is
+/// done by the local world that holds the inventory of the user. Parcels expire after some days.
+/// Expired parcels with attachments are returned to their sender once, all other expired parcels
+/// are deleted together with their attachments. The server itself sends parcels with
+/// `send_system_parcel()`.
+pub fn parcel_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    tick: UniqueView<Tick>,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestListParcel {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_list_parcel(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring list parcel request: {:?}", e);
+                }
+            }
+            Message::RequestShowParcelMessage {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_show_parcel_message(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring show parcel message request: {:?}", e);
+                }
+            }
+            Message::RequestParcelReadRecvStatus {
+                connection_global_world_id,
+                user_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_parcel_read_recv_status(
+                    *connection_global_world_id,
+                    *user_id,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring parcel read receive status request: {:?}", e);
+                }
+            }
+            Message::RequestDeleteParcel {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_delete_parcel(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring delete parcel request: {:?}", e);
+                }
+            }
+            Message::RequestReturnParcel {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_return_parcel(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &pool,
+                ) {
+                    error!("Ignoring return parcel request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+
+    if tick.count % UPDATE_INTERVAL == 0 {
+        if let Err(e) = update_expired_parcels(&pool) {
+            error!("Can't update the expired parcels: {:?}", e);
+        }
+    }
+}
+
+fn handle_list_parcel(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CListParcel,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestListParcel incoming");
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let parcels = parcel::list_by_recipient_user_id(&mut conn, user_id).await?;
+        let page_count = ((parcels.len() + PARCELS_PER_PAGE - 1) / PARCELS_PER_PAGE).max(1);
+        ensure!(
+            packet.page >= 0 && (packet.page as usize) < page_count,
+            "Page {} of the parcel list doesn't exist",
+            packet.page
+        );
+        let unread_count = parcels.iter().filter(|parcel| !parcel.is_read).count();
+
+        let mut entries = Vec::with_capacity(PARCELS_PER_PAGE);
+        for parcel in parcels
+            .iter()
+            .skip(packet.page as usize * PARCELS_PER_PAGE)
+            .take(PARCELS_PER_PAGE)
+        {
+            let items = parcel_item::list_by_parcel_id(&mut conn, parcel.id).await?;
+            entries.push(SListParcelExParcel {
+                sender: parcel.sender_name.clone(),
+                title: parcel.title.clone(),
+                id: parcel.id,
+                money: parcel.money,
+                item_count: items.len() as i32,
+                is_read: parcel.is_read,
+                is_returned: parcel.is_returned,
+                expires_at: parcel.expires_at.timestamp(),
+            });
+        }
+
+        send_message_to_connection(
+            Box::new(ResponseListParcelEx {
+                connection_global_world_id,
+                packet: SListParcelEx {
+                    parcels: entries,
+                    page: packet.page,
+                    page_count: page_count as i32,
+                    unread_count: unread_count as i32,
+                },
+            }),
+            connections,
+        );
+        Ok(())
+    })
+}
+
+fn handle_show_parcel_message(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CShowParcelMessage,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestShowParcelMessage incoming");
+
+    let (parcel, items) = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let mut parcel = get_received_parcel(&mut conn, user_id, packet.id).await?;
+        let items = parcel_item::list_by_parcel_id(&mut conn, parcel.id).await?;
+        if !parcel.is_read {
+            parcel.is_read = true;
+            parcel = parcel::update(&mut conn, &parcel).await?;
+        }
+
+        conn.commit().await?;
+        Ok::<(Parcel, Vec<ParcelItem>), anyhow::Error>((parcel, items))
+    })?;
+
+    send_message_to_connection(
+        assemble_show_parcel_message(connection_global_world_id, &parcel, &items),
+        connections,
+    );
+    Ok(())
+}
+
+fn handle_parcel_read_recv_status(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestParcelReadRecvStatus incoming");
+
+    let unread_count = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+        parcel::get_unread_count(&mut conn, user_id).await
+    })?;
+
+    send_message_to_connection(
+        Box::new(ResponseParcelReadRecvStatus {
+            connection_global_world_id,
+            packet: SParcelReadRecvStatus {
+                unread_count: unread_count as i32,
+            },
+        }),
+        connections,
+    );
+    Ok(())
+}
+
+fn handle_delete_parcel(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CDeleteParcel,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestDeleteParcel incoming");
+
+    let result = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let parcel = get_received_parcel(&mut conn, user_id, packet.id).await?;
+        ensure!(
+            !has_attachments(&mut conn, &parcel).await?,
+            "Parcel {} still has attachments",
+            parcel.id
+        );
+        parcel::delete(&mut conn, parcel.id).await?;
+
+        conn.commit().await?;
+        Ok::<(), anyhow::Error>(())
+    });
+
+    send_message_to_connection(
+        Box::new(ResponseDeleteParcel {
+            connection_global_world_id,
+            packet: SDeleteParcel {
+                id: packet.id,
+                success: result.is_ok(),
+            },
+        }),
+        connections,
+    );
+    result
+}
+
+fn handle_return_parcel(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CReturnParcel,
+    connections: &View<GlobalConnection>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestReturnParcel incoming");
+
+    let result = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let parcel = get_received_parcel(&mut conn, user_id, packet.id).await?;
+        return_parcel(&mut conn, parcel).await?;
+
+        conn.commit().await?;
+        Ok::<(), anyhow::Error>(())
+    });
+
+    send_message_to_connection(
+        Box::new(ResponseReturnParcel {
+            connection_global_world_id,
+            packet: SReturnParcel {
+                id: packet.id,
+                success: result.is_ok(),
+            },
+        }),
+        connections,
+    );
+    result
+}
+
+/// Returns expired parcels with attachments to their sender and deletes all other expired parcels.
+fn update_expired_parcels(pool: &UniqueView<PgPool>) -> Result<()> {
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+        let now = Utc::now();
+
+        for expired_parcel in parcel::list_expired(&mut conn, now).await? {
+            let mut tx = conn.begin().await?;
+
+            // The parcel could have been received or deleted in the meantime.
+            let parcel = match parcel::get_by_id(&mut tx, expired_parcel.id).await {
+                Ok(parcel) if parcel.expires_at <= now => parcel,
+                _ => {
+                    conn = tx.commit().await?;
+                    continue;
+                }
+            };
+
+            let can_return = !parcel.is_returned && parcel.sender_user_id.is_some();
+            if can_return && has_attachments(&mut tx, &parcel).await? {
+                let parcel = return_parcel(&mut tx, parcel).await?;
+                debug!("Returned expired parcel {} to it's sender", parcel.id);
+            } else {
+                parcel::delete(&mut tx, parcel.id).await?;
+                debug!("Deleted expired parcel {}", parcel.id);
+            }
+
+            conn = tx.commit().await?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Get the parcel with the given id if it was received by the user. The parcel is locked until
+/// the end of the transaction.
+async fn get_received_parcel(conn: &mut PgConnection, user_id: i32, id: i64) -> Result<Parcel> {
+    let parcel = parcel::get_by_id(conn, id)
+        .await
+        .context(format!("Can't find parcel {}", id))?;
+    ensure!(
+        parcel.recipient_user_id == user_id,
+        "Parcel {} was not send to user {}",
+        id,
+        user_id
+    );
+    Ok(parcel)
+}
+
+async fn has_attachments(conn: &mut PgConnection, parcel: &Parcel) -> Result<bool> {
+    Ok(parcel.money > 0
+        || !parcel_item::list_by_parcel_id(conn, parcel.id)
+            .await?
+            .is_empty())
+}
+
+/// Sends the parcel back to it's sender. Parcels can only be returned once and parcels of the
+/// server can't be returned at all.
+async fn return_parcel(conn: &mut PgConnection, mut parcel: Parcel) -> Result<Parcel> {
+    ensure!(
+        !parcel.is_returned,
+        "Parcel {} was already returned",
+        parcel.id
+    );
+    let sender_user_id = parcel.sender_user_id.context(format!(
+        "Parcel {} has no sender to return it to",
+        parcel.id
+    ))?;
+    let recipient = user::get_by_id(conn, parcel.recipient_user_id)
+        .await
+        .context(format!("Can't find user {}", parcel.recipient_user_id))?;
+
+    parcel.sender_user_id = Some(recipient.id);
+    parcel.sender_name = recipient.name;
+    parcel.recipient_user_id = sender_user_id;
+    parcel.is_read = false;
+    parcel.is_returned = true;
+    parcel.expires_at = Utc::now() + Duration::days(PARCEL_EXPIRATION_DAYS);
+    parcel::update(conn, &parcel).await
+}
+
+/// Sends a parcel from the server to an user. This is the entry point for rewards of events, GM
+/// mails etc., none of which exist in the server yet. The attached items are given as pairs of
+/// item template ID and amount. Use a transaction as `conn`, so that no parcel is send with only
+/// a part of it's attachments.
+pub async fn send_system_parcel(
+    conn: &mut PgConnection,
+    recipient_user_id: i32,
+    sender_name: &str,
+    title: &str,
+    message: &str,
+    money: i64,
+    items: &[(i32, i32)],
+) -> Result<Parcel> {
+    ensure!(money >= 0, "Can't attach a negative amount of money");
+    ensure!(
+        items.len() <= MAX_PARCEL_ITEMS,
+        "Can't attach more than {} items",
+        MAX_PARCEL_ITEMS
+    );
+    ensure!(
+        items.iter().all(|(_, amount)| *amount > 0),
+        "Attached items need a positive amount"
+    );
+
+    let now = Utc::now();
+    let parcel = parcel::create(
+        conn,
+        &Parcel {
+            id: -1,
+            sender_user_id: None,
+            sender_name: sender_name.to_string(),
+            recipient_user_id,
+            title: title.to_string(),
+            message: message.to_string(),
+            money,
+            is_read: false,
+            is_returned: false,
+            created_at: now,
+            expires_at: now + Duration::days(PARCEL_EXPIRATION_DAYS),
+        },
+    )
+    .await
+    .context(format!("Can't send parcel to user {}", recipient_user_id))?;
+
+    for (template_id, amount) in items {
+        parcel_item::create(
+            conn,
+            &ParcelItem {
+                id: -1,
+                parcel_id: parcel.id,
+                template_id: *template_id,
+                amount: *amount,
+                created_at: now,
+            },
+        )
+        .await?;
+    }
+
+    Ok(parcel)
+}
+
+fn assemble_show_parcel_message(
+    connection_global_world_id: EntityId,
+    parcel: &Parcel,
+    items: &[ParcelItem],
+) -> EcsMessage {
+    Box::new(ResponseShowParcelMessage {
+        connection_global_world_id,
+        packet: SShowParcelMessage {
+            items: items
+                .iter()
+                .map(|item| SShowParcelMessageItem {
+                    id: item.template_id,
+                    db_id: item.id,
+                    amount: item.amount,
+                })
+                .collect(),
+            sender: parcel.sender_name.clone(),
+            title: parcel.title.clone(),
+            message: parcel.message.clone(),
+            id: parcel.id,
+            money: parcel.money,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::{GlobalUserSpawn, UserSpawnStatus};
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
+    use crate::model::entity::User;
+    use crate::model::repository::account;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::parcel::tests::get_default_parcel;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::tests::db_test;
+    use async_std::sync::{channel, Receiver};
+    use std::time::Instant;
+
+    struct TestUser {
+        user: User,
+        connection_global_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(Tick {
+            count: 1,
+            delta: std::time::Duration::from_nanos(1000),
+            time: Instant::now(),
+        });
+        world.add_unique(pool);
+        world
+    }
+
+    async fn create_user(pool: &PgPool, num: i32) -> Result<User> {
+        let mut conn = pool.acquire().await?;
+        let account = account::create(&mut conn, &get_default_account(num)).await?;
+        user::create(&mut conn, &get_default_user(&account, num)).await
+    }
+
+    fn add_user(world: &World, pool: &PgPool, num: i32) -> Result<TestUser> {
+        let user = task::block_on(async { create_user(pool, num).await })?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut spawns: ViewMut<GlobalUserSpawn>| {
+                entities.add_entity(
+                    (&mut connections, &mut spawns),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        GlobalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_local_world_id: None,
+                            local_world_id: None,
+                            local_world_channel: None,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
+                        },
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            user,
+            connection_global_world_id,
+            rx: rx_channel,
+        })
+    }
+
+    /// Creates a parcel with the given money and number of attached items.
+    fn add_parcel(
+        pool: &PgPool,
+        sender: Option<&TestUser>,
+        recipient: &TestUser,
+        money: i64,
+        item_count: usize,
+        expires_in_days: i64,
+    ) -> Result<Parcel> {
+        task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let mut new_parcel = get_default_parcel(sender.map(|s| &s.user), &recipient.user);
+            new_parcel.money = money;
+            new_parcel.expires_at = Utc::now() + Duration::days(expires_in_days);
+            let parcel = parcel::create(&mut conn, &new_parcel).await?;
+            for _ in 0..item_count {
+                parcel_item::create(
+                    &mut conn,
+                    &parcel_item::tests::get_default_parcel_item(&parcel),
+                )
+                .await?;
+            }
+            Ok(parcel)
+        })
+    }
+
+    fn get_parcel(pool: &PgPool, id: i64) -> Result<Parcel> {
+        task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            parcel::get_by_id(&mut conn, id).await
+        })
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(parcel_manager_system);
+        world.run(cleaner_system);
+    }
+
+    /// Runs a tick in which the expired parcels are handled.
+    fn run_update(world: &World) {
+        world.run(|mut tick: UniqueViewMut<Tick>| tick.count = UPDATE_INTERVAL);
+        world.run(parcel_manager_system);
+        world.run(|mut tick: UniqueViewMut<Tick>| tick.count = UPDATE_INTERVAL + 1);
+    }
+
+    fn list_parcel(world: &World, user: &TestUser, page: i32) {
+        run_message(
+            world,
+            Message::RequestListParcel {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CListParcel { page },
+            },
+        );
+    }
+
+    fn show_parcel_message(world: &World, user: &TestUser, id: i64) {
+        run_message(
+            world,
+            Message::RequestShowParcelMessage {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CShowParcelMessage { id },
+            },
+        );
+    }
+
+    fn delete_parcel(world: &World, user: &TestUser, id: i64) {
+        run_message(
+            world,
+            Message::RequestDeleteParcel {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CDeleteParcel { id },
+            },
+        );
+    }
+
+    fn request_return_parcel(world: &World, user: &TestUser, id: i64) {
+        run_message(
+            world,
+            Message::RequestReturnParcel {
+                connection_global_world_id: user.connection_global_world_id,
+                account_id: user.user.account_id,
+                user_id: user.user.id,
+                packet: CReturnParcel { id },
+            },
+        );
+    }
+
+    fn assert_list_parcel_ex(message: EcsMessage) -> SListParcelEx {
+        match &*message {
+            Message::ResponseListParcelEx { packet, .. } => packet.clone(),
+            _ => panic!("Message is not a ResponseListParcelEx message"),
+        }
+    }
+
+    fn assert_show_parcel_message(message: EcsMessage) -> SShowParcelMessage {
+        match &*message {
+            Message::ResponseShowParcelMessage { packet, .. } => packet.clone(),
+            _ => panic!("Message is not a ResponseShowParcelMessage message"),
+        }
+    }
+
+    fn assert_delete_parcel(message: EcsMessage, id: i64, success: bool) {
+        match &*message {
+            Message::ResponseDeleteParcel { packet, .. } => {
+                assert_eq!(packet.id, id);
+                assert_eq!(packet.success, success);
+            }
+            _ => panic!("Message is not a ResponseDeleteParcel message"),
+        }
+    }
+
+    fn assert_return_parcel(message: EcsMessage, id: i64, success: bool) {
+        match &*message {
+            Message::ResponseReturnParcel { packet, .. } => {
+                assert_eq!(packet.id, id);
+                assert_eq!(packet.success, success);
+            }
+            _ => panic!("Message is not a ResponseReturnParcel message"),
+        }
+    }
+
+    #[test]
+    fn test_send_system_parcel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 1)?;
+
+            let parcel = task::block_on(async {
+                let mut conn = pool.begin().await?;
+                let parcel = send_system_parcel(
+                    &mut conn,
+                    user.user.id,
+                    "Event",
+                    "Reward",
+                    "Thanks for playing",
+                    5000,
+                    &[(8005, 20), (10001, 1)],
+                )
+                .await?;
+                conn.commit().await?;
+                Ok::<Parcel, anyhow::Error>(parcel)
+            })?;
+            assert_eq!(parcel.sender_user_id, None);
+
+            list_parcel(&world, &user, 0);
+            let list = assert_list_parcel_ex(user.rx.try_recv()?);
+            assert_eq!(list.unread_count, 1);
+            assert_eq!(list.page_count, 1);
+            assert_eq!(list.parcels.len(), 1);
+            assert_eq!(list.parcels[0].id, parcel.id);
+            assert_eq!(list.parcels[0].sender, "Event");
+            assert_eq!(list.parcels[0].title, "Reward");
+            assert_eq!(list.parcels[0].money, 5000);
+            assert_eq!(list.parcels[0].item_count, 2);
+
+            // Parcels can't hold more items than allowed
+            let too_many_items = vec![(8005, 1); MAX_PARCEL_ITEMS + 1];
+            assert!(task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                send_system_parcel(&mut conn, user.user.id, "Event", "", "", 0, &too_many_items)
+                    .await
+            })
+            .is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_list_parcel_pages() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let sender = add_user(&world, &pool, 1)?;
+            let recipient = add_user(&world, &pool, 2)?;
+            for _ in 0..PARCELS_PER_PAGE + 2 {
+                add_parcel(&pool, Some(&sender), &recipient, 0, 0, 30)?;
+            }
+
+            list_parcel(&world, &recipient, 1);
+            let list = assert_list_parcel_ex(recipient.rx.try_recv()?);
+            assert_eq!(list.page, 1);
+            assert_eq!(list.page_count, 2);
+            assert_eq!(list.parcels.len(), 2);
+            assert_eq!(list.unread_count, PARCELS_PER_PAGE as i32 + 2);
+
+            // Pages after the last page don't exist
+            list_parcel(&world, &recipient, 2);
+            assert!(recipient.rx.is_empty());
+
+            // Users without parcels get an empty first page
+            list_parcel(&world, &sender, 0);
+            let list = assert_list_parcel_ex(sender.rx.try_recv()?);
+            assert!(list.parcels.is_empty());
+            assert_eq!(list.page_count, 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_show_parcel_message() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let sender = add_user(&world, &pool, 1)?;
+            let recipient = add_user(&world, &pool, 2)?;
+            let parcel = add_parcel(&pool, Some(&sender), &recipient, 100, 1, 30)?;
+
+            // Only the recipient can read a parcel
+            show_parcel_message(&world, &sender, parcel.id);
+            assert!(sender.rx.is_empty());
+
+            show_parcel_message(&world, &recipient, parcel.id);
+            let message = assert_show_parcel_message(recipient.rx.try_recv()?);
+            assert_eq!(message.id, parcel.id);
+            assert_eq!(message.sender, sender.user.name);
+            assert_eq!(message.message, "Message");
+            assert_eq!(message.money, 100);
+            assert_eq!(message.items.len(), 1);
+            assert_eq!(message.items[0].id, 8005);
+            assert!(get_parcel(&pool, parcel.id)?.is_read);
+
+            run_message(
+                &world,
+                Message::RequestParcelReadRecvStatus {
+                    connection_global_world_id: recipient.connection_global_world_id,
+                    account_id: recipient.user.account_id,
+                    user_id: recipient.user.id,
+                    packet: CParcelReadRecvStatus {},
+                },
+            );
+            match &*recipient.rx.try_recv()? {
+                Message::ResponseParcelReadRecvStatus { packet, .. } => {
+                    assert_eq!(packet.unread_count, 0)
+                }
+                _ => panic!("Message is not a ResponseParcelReadRecvStatus message"),
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_delete_parcel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let sender = add_user(&world, &pool, 1)?;
+            let recipient = add_user(&world, &pool, 2)?;
+            let parcel = add_parcel(&pool, Some(&sender), &recipient, 0, 0, 30)?;
+            let money_parcel = add_parcel(&pool, Some(&sender), &recipient, 100, 0, 30)?;
+            let item_parcel = add_parcel(&pool, Some(&sender), &recipient, 0, 1, 30)?;
+
+            // Parcels with attachments need to be received first
+            delete_parcel(&world, &recipient, money_parcel.id);
+            assert_delete_parcel(recipient.rx.try_recv()?, money_parcel.id, false);
+            delete_parcel(&world, &recipient, item_parcel.id);
+            assert_delete_parcel(recipient.rx.try_recv()?, item_parcel.id, false);
+
+            // Only the recipient can delete a parcel
+            delete_parcel(&world, &sender, parcel.id);
+            assert_delete_parcel(sender.rx.try_recv()?, parcel.id, false);
+
+            delete_parcel(&world, &recipient, parcel.id);
+            assert_delete_parcel(recipient.rx.try_recv()?, parcel.id, true);
+            assert!(get_parcel(&pool, parcel.id).is_err());
+            assert!(get_parcel(&pool, money_parcel.id).is_ok());
+            assert!(get_parcel(&pool, item_parcel.id).is_ok());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_return_parcel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let sender = add_user(&world, &pool, 1)?;
+            let recipient = add_user(&world, &pool, 2)?;
+            let parcel = add_parcel(&pool, Some(&sender), &recipient, 100, 1, 30)?;
+            let system_parcel = add_parcel(&pool, None, &recipient, 100, 1, 30)?;
+
+            request_return_parcel(&world, &recipient, parcel.id);
+            assert_return_parcel(recipient.rx.try_recv()?, parcel.id, true);
+            let returned_parcel = get_parcel(&pool, parcel.id)?;
+            assert_eq!(returned_parcel.recipient_user_id, sender.user.id);
+            assert_eq!(returned_parcel.sender_user_id, Some(recipient.user.id));
+            assert_eq!(returned_parcel.sender_name, recipient.user.name);
+            assert_eq!(returned_parcel.money, 100);
+            assert!(returned_parcel.is_returned);
+            assert!(!returned_parcel.is_read);
+
+            // Returned parcels can't be returned again
+            request_return_parcel(&world, &sender, parcel.id);
+            assert_return_parcel(sender.rx.try_recv()?, parcel.id, false);
+
+            // Parcels of the server can't be returned
+            request_return_parcel(&world, &recipient, system_parcel.id);
+            assert_return_parcel(recipient.rx.try_recv()?, system_parcel.id, false);
+            assert_eq!(
+                get_parcel(&pool, system_parcel.id)?.recipient_user_id,
+                recipient.user.id
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_expired_parcels() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let sender = add_user(&world, &pool, 1)?;
+            let recipient = add_user(&world, &pool, 2)?;
+            let parcel = add_parcel(&pool, Some(&sender), &recipient, 0, 1, 30)?;
+            let expired_parcel = add_parcel(&pool, Some(&sender), &recipient, 0, 1, -1)?;
+            let expired_empty_parcel = add_parcel(&pool, Some(&sender), &recipient, 0, 0, -1)?;
+            let expired_system_parcel = add_parcel(&pool, None, &recipient, 100, 0, -1)?;
+
+            // Nothing happens between the updates
+            world.run(parcel_manager_system);
+            assert!(get_parcel(&pool, expired_empty_parcel.id).is_ok());
+
+            run_update(&world);
+            assert_eq!(get_parcel(&pool, parcel.id)?, parcel);
+            assert!(get_parcel(&pool, expired_empty_parcel.id).is_err());
+            assert!(get_parcel(&pool, expired_system_parcel.id).is_err());
+
+            let returned_parcel = get_parcel(&pool, expired_parcel.id)?;
+            assert_eq!(returned_parcel.recipient_user_id, sender.user.id);
+            assert!(returned_parcel.is_returned);
+            assert!(returned_parcel.expires_at > Utc::now());
+
+            // Returned parcels are deleted once they expire again
+            let mut expired_returned_parcel = returned_parcel;
+            expired_returned_parcel.expires_at = Utc::now() - Duration::days(1);
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                parcel::update(&mut conn, &expired_returned_parcel).await
+            })?;
+            run_update(&world);
+            assert!(get_parcel(&pool, expired_parcel.id).is_err());
+
+            Ok(())
+        })
+    }
+}
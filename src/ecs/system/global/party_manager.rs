@@ -0,0 +1,1745 @@
+use crate::ecs::component::{
+    BlockList, GlobalConnection, GlobalUserSpawn, Party, PartyInvitation, PartyMember,
+};
+use crate::ecs::message::Message::{
+    ResponseBanParty, ResponseBanPartyMember, ResponseBeginThroughArbiterContract,
+    ResponseChangePartyManager, ResponseLeaveParty, ResponseLeavePartyMember,
+    ResponsePartyLootingMethod, ResponsePartyMemberChangeHp, ResponsePartyMemberIntervalPosUpdate,
+    ResponsePartyMemberList,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::DeletionList;
+use crate::ecs::system::global::{find_online_user, is_blocked, send_message_to_connection};
+use crate::model::repository::user;
+use crate::model::{LootingMethod, Vec3f};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use shipyard::*;
+use sqlx::{PgConnection, PgPool};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info_span};
+
+/// Contract type of a party invitation.
+const PARTY_INVITE_CONTRACT: i32 = 4;
+
+/// Time an user has to answer a party invitation.
+const INVITATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximal number of members of a party.
+const MAX_PARTY_SIZE: usize = 5;
+
+/// Maximal number of members of a raid.
+const MAX_RAID_SIZE: usize = 30;
+
+/// ID of the server the users are playing on.
+const SERVER_ID: i32 = 1;
+
+/// The party manager handles the parties of the users. Parties live in the global world, so that
+/// their members can be in different local worlds. The local worlds report the status of their
+/// users, which the party manager forwards to the other members of the party. Users whose
+/// connection was dropped leave their party.
+pub fn party_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    spawns: View<GlobalUserSpawn>,
+    block_lists: View<BlockList>,
+    mut parties: ViewMut<Party>,
+    mut party_members: ViewMut<PartyMember>,
+    mut invitations: ViewMut<PartyInvitation>,
+    mut entities: EntitiesViewMut,
+    mut deletion_list: UniqueViewMut<DeletionList>,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestContract {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } if packet.contract_type == PARTY_INVITE_CONTRACT => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_party_invite(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &block_lists,
+                    &parties,
+                    &party_members,
+                    &mut invitations,
+                    &mut entities,
+                    &pool,
+                ) {
+                    error!("Ignoring party invite request: {:?}", e);
+                }
+            }
+            Message::RequestReplyThroughArbiterContract {
+                connection_global_world_id,
+                packet,
+                ..
+            } if packet.contract_type == PARTY_INVITE_CONTRACT => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_reply_party_invite(
+                    *connection_global_world_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &mut parties,
+                    &mut party_members,
+                    &mut invitations,
+                    &mut entities,
+                    &pool,
+                ) {
+                    error!("Ignoring party invite reply: {:?}", e);
+                }
+            }
+            Message::RequestLeaveParty {
+                connection_global_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_leave_party(
+                    *connection_global_world_id,
+                    &connections,
+                    &spawns,
+                    &mut parties,
+                    &mut party_members,
+                    &mut deletion_list,
+                    &pool,
+                ) {
+                    error!("Ignoring leave party request: {:?}", e);
+                }
+            }
+            Message::RequestBanPartyMember {
+                connection_global_world_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_ban_party_member(
+                    *connection_global_world_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &mut parties,
+                    &mut party_members,
+                    &mut deletion_list,
+                    &pool,
+                ) {
+                    error!("Ignoring ban party member request: {:?}", e);
+                }
+            }
+            Message::RequestChangePartyManager {
+                connection_global_world_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_change_party_manager(
+                    *connection_global_world_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &mut parties,
+                    &party_members,
+                    &pool,
+                ) {
+                    error!("Ignoring change party manager request: {:?}", e);
+                }
+            }
+            Message::RequestDismissParty {
+                connection_global_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_dismiss_party(
+                    *connection_global_world_id,
+                    &connections,
+                    &parties,
+                    &mut party_members,
+                    &mut deletion_list,
+                ) {
+                    error!("Ignoring dismiss party request: {:?}", e);
+                }
+            }
+            Message::RequestPartyLootingMethod {
+                connection_global_world_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_party_looting_method(
+                    *connection_global_world_id,
+                    &packet,
+                    &connections,
+                    &mut parties,
+                    &party_members,
+                ) {
+                    error!("Ignoring party looting method request: {:?}", e);
+                }
+            }
+            Message::RequestMergePartyToRaid {
+                connection_global_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_merge_party_to_raid(
+                    *connection_global_world_id,
+                    &connections,
+                    &spawns,
+                    &mut parties,
+                    &party_members,
+                    &pool,
+                ) {
+                    error!("Ignoring merge party to raid request: {:?}", e);
+                }
+            }
+            Message::UserLocationReport {
+                connection_global_world_id,
+                zone_id,
+                location,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_user_location_report(
+                    *connection_global_world_id,
+                    *zone_id,
+                    *location,
+                    &connections,
+                    &spawns,
+                    &parties,
+                    &party_members,
+                ) {
+                    error!("Ignoring Message::UserLocationReport: {:?}", e);
+                }
+            }
+            Message::UserHealthReport {
+                connection_global_world_id,
+                hp,
+                max_hp,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_user_health_report(
+                    *connection_global_world_id,
+                    *hp,
+                    *max_hp,
+                    &connections,
+                    &spawns,
+                    &parties,
+                    &party_members,
+                ) {
+                    error!("Ignoring Message::UserHealthReport: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+
+    // Users whose connection was dropped leave their party.
+    let offline_members: Vec<EntityId> = (&spawns, &party_members)
+        .iter()
+        .with_id()
+        .filter(|(_id, (spawn, _member))| spawn.marked_for_deletion)
+        .map(|(id, _)| id)
+        .collect();
+    for connection_global_world_id in offline_members {
+        id_span!(connection_global_world_id);
+        debug!("Party member went offline");
+        if let Err(e) = remove_member(
+            connection_global_world_id,
+            false,
+            &connections,
+            &spawns,
+            &mut parties,
+            &mut party_members,
+            &mut deletion_list,
+            &pool,
+        ) {
+            error!("Can't remove offline user from party: {:?}", e);
+        }
+    }
+
+    // Invitations expire after some time or when the invited user goes offline.
+    let expired_invitations: Vec<EntityId> = (&spawns, &invitations)
+        .iter()
+        .with_id()
+        .filter(|(_id, (spawn, invitation))| {
+            spawn.marked_for_deletion || invitation.created_at.elapsed() > INVITATION_TIMEOUT
+        })
+        .map(|(id, _)| id)
+        .collect();
+    for connection_global_world_id in expired_invitations {
+        invitations.delete(connection_global_world_id);
+    }
+}
+
+fn handle_party_invite(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CRequestContract,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    block_lists: &View<BlockList>,
+    parties: &ViewMut<Party>,
+    party_members: &ViewMut<PartyMember>,
+    invitations: &mut ViewMut<PartyInvitation>,
+    entities: &mut EntitiesViewMut,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestContract incoming");
+
+    // Only the leader can invite users into an existing party.
+    if let Ok(member) = party_members.try_get(connection_global_world_id) {
+        let party = parties
+            .try_get(member.party_id)
+            .context(format!("Can't find party {:?}", member.party_id))?;
+        ensure!(
+            party.leader_id == connection_global_world_id,
+            "User {} is not the leader of it's party",
+            user_id
+        );
+        ensure!(
+            party.members.len() < max_party_size(party),
+            "Party {:?} is full",
+            member.party_id
+        );
+    }
+
+    let (user_name, invitee_id) = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let user = user::get_by_id(&mut conn, user_id).await?;
+        let invitee = user::get_by_name(&mut conn, &packet.name)
+            .await
+            .context(format!("Can't find user {}", packet.name))?;
+        ensure!(
+            invitee.id != user_id,
+            "User {} can't invite itself",
+            user_id
+        );
+
+        Ok::<(String, i32), anyhow::Error>((user.name, invitee.id))
+    })?;
+
+    let invitee_connection_id = find_online_user(invitee_id, spawns)
+        .context(format!("User {} is not online", invitee_id))?;
+    ensure!(
+        !is_blocked(
+            connection_global_world_id,
+            user_id,
+            invitee_connection_id,
+            invitee_id,
+            block_lists
+        ),
+        "Party invites between user {} and user {} are blocked",
+        user_id,
+        invitee_id
+    );
+    ensure!(
+        party_members.try_get(invitee_connection_id).is_err(),
+        "User {} is already in a party",
+        invitee_id
+    );
+    ensure!(
+        invitations.try_get(invitee_connection_id).is_err(),
+        "User {} already has a pending party invitation",
+        invitee_id
+    );
+
+    entities.add_component(
+        &mut *invitations,
+        PartyInvitation {
+            inviter_id: connection_global_world_id,
+            created_at: Instant::now(),
+        },
+        invitee_connection_id,
+    );
+    send_message_to_connection(
+        assemble_begin_through_arbiter_contract(
+            invitee_connection_id,
+            &user_name,
+            &packet.data,
+            user_id,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_reply_party_invite(
+    connection_global_world_id: EntityId,
+    packet: &CReplyThroughArbiterContract,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    parties: &mut ViewMut<Party>,
+    party_members: &mut ViewMut<PartyMember>,
+    invitations: &mut ViewMut<PartyInvitation>,
+    entities: &mut EntitiesViewMut,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestReplyThroughArbiterContract incoming");
+
+    let inviter_id = invitations
+        .try_get(connection_global_world_id)
+        .context("User has no pending party invitation")?
+        .inviter_id;
+    invitations.delete(connection_global_world_id);
+
+    if !packet.accept {
+        debug!("User declined the party invitation");
+        return Ok(());
+    }
+
+    ensure!(
+        spawns
+            .try_get(inviter_id)
+            .map_or(false, |spawn| !spawn.marked_for_deletion),
+        "Inviting user {:?} is not online anymore",
+        inviter_id
+    );
+    ensure!(
+        party_members.try_get(connection_global_world_id).is_err(),
+        "User is already in a party"
+    );
+
+    let inviter_party_id = party_members
+        .try_get(inviter_id)
+        .ok()
+        .map(|member| member.party_id);
+    let party_id = if let Some(party_id) = inviter_party_id {
+        let mut party = parties
+            .try_get(party_id)
+            .context(format!("Can't find party {:?}", party_id))?;
+        ensure!(
+            party.leader_id == inviter_id,
+            "Inviting user {:?} is not the leader of party {:?} anymore",
+            inviter_id,
+            party_id
+        );
+        ensure!(
+            party.members.len() < max_party_size(&party),
+            "Party {:?} is full",
+            party_id
+        );
+        party.members.push(connection_global_world_id);
+        party_id
+    } else {
+        let party_id = entities.add_entity(
+            &mut *parties,
+            Party {
+                leader_id: inviter_id,
+                members: vec![inviter_id, connection_global_world_id],
+                looting_method: LootingMethod::RoundRobin,
+                is_raid: false,
+            },
+        );
+        entities.add_component(&mut *party_members, PartyMember { party_id }, inviter_id);
+        debug!("Party {:?} created", party_id);
+        party_id
+    };
+    entities.add_component(
+        &mut *party_members,
+        PartyMember { party_id },
+        connection_global_world_id,
+    );
+
+    send_party_member_list(&parties[party_id], connections, spawns, pool)
+}
+
+fn handle_leave_party(
+    connection_global_world_id: EntityId,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    parties: &mut ViewMut<Party>,
+    party_members: &mut ViewMut<PartyMember>,
+    deletion_list: &mut UniqueViewMut<DeletionList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestLeaveParty incoming");
+
+    remove_member(
+        connection_global_world_id,
+        false,
+        connections,
+        spawns,
+        parties,
+        party_members,
+        deletion_list,
+        pool,
+    )
+}
+
+fn handle_ban_party_member(
+    connection_global_world_id: EntityId,
+    packet: &CBanPartyMember,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    parties: &mut ViewMut<Party>,
+    party_members: &mut ViewMut<PartyMember>,
+    deletion_list: &mut UniqueViewMut<DeletionList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestBanPartyMember incoming");
+
+    let party_id = get_led_party_id(connection_global_world_id, parties, party_members)?;
+    let member_id = find_member(&parties[party_id], packet.user_id, spawns).context(format!(
+        "User {} is not a member of party {:?}",
+        packet.user_id, party_id
+    ))?;
+    ensure!(
+        member_id != connection_global_world_id,
+        "User can't ban itself from the party"
+    );
+
+    remove_member(
+        member_id,
+        true,
+        connections,
+        spawns,
+        parties,
+        party_members,
+        deletion_list,
+        pool,
+    )
+}
+
+fn handle_change_party_manager(
+    connection_global_world_id: EntityId,
+    packet: &CChangePartyManager,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    parties: &mut ViewMut<Party>,
+    party_members: &ViewMut<PartyMember>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestChangePartyManager incoming");
+
+    let party_id = get_led_party_id(connection_global_world_id, parties, party_members)?;
+    let mut party = parties
+        .try_get(party_id)
+        .context(format!("Can't find party {:?}", party_id))?;
+    party.leader_id = find_member(&party, packet.user_id, spawns).context(format!(
+        "User {} is not a member of party {:?}",
+        packet.user_id, party_id
+    ))?;
+
+    let leader_name = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+        get_user_name(&mut conn, packet.user_id).await
+    })?;
+    for member_id in party.members.iter() {
+        send_message_to_connection(
+            assemble_change_party_manager(*member_id, &leader_name, packet.user_id),
+            connections,
+        );
+    }
+
+    Ok(())
+}
+
+fn handle_dismiss_party(
+    connection_global_world_id: EntityId,
+    connections: &View<GlobalConnection>,
+    parties: &ViewMut<Party>,
+    party_members: &mut ViewMut<PartyMember>,
+    deletion_list: &mut UniqueViewMut<DeletionList>,
+) -> Result<()> {
+    debug!("Message::RequestDismissParty incoming");
+
+    let party_id = get_led_party_id(connection_global_world_id, parties, party_members)?;
+    disband_party(party_id, connections, parties, party_members, deletion_list);
+
+    Ok(())
+}
+
+fn handle_party_looting_method(
+    connection_global_world_id: EntityId,
+    packet: &CPartyLootingMethod,
+    connections: &View<GlobalConnection>,
+    parties: &mut ViewMut<Party>,
+    party_members: &ViewMut<PartyMember>,
+) -> Result<()> {
+    debug!("Message::RequestPartyLootingMethod incoming");
+
+    let party_id = get_led_party_id(connection_global_world_id, parties, party_members)?;
+    let mut party = parties
+        .try_get(party_id)
+        .context(format!("Can't find party {:?}", party_id))?;
+    party.looting_method = packet.looting_method;
+
+    for member_id in party.members.iter() {
+        send_message_to_connection(
+            assemble_party_looting_method(*member_id, packet.looting_method),
+            connections,
+        );
+    }
+
+    Ok(())
+}
+
+fn handle_merge_party_to_raid(
+    connection_global_world_id: EntityId,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    parties: &mut ViewMut<Party>,
+    party_members: &ViewMut<PartyMember>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestMergePartyToRaid incoming");
+
+    let party_id = get_led_party_id(connection_global_world_id, parties, party_members)?;
+    let mut party = parties
+        .try_get(party_id)
+        .context(format!("Can't find party {:?}", party_id))?;
+    ensure!(!party.is_raid, "Party {:?} is already a raid", party_id);
+    party.is_raid = true;
+
+    send_party_member_list(&party, connections, spawns, pool)
+}
+
+fn handle_user_location_report(
+    connection_global_world_id: EntityId,
+    zone_id: i32,
+    location: Vec3f,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    parties: &ViewMut<Party>,
+    party_members: &ViewMut<PartyMember>,
+) -> Result<()> {
+    debug!("Message::UserLocationReport incoming");
+
+    // Users without a party don't share their location.
+    let party_id = match party_members.try_get(connection_global_world_id) {
+        Ok(member) => member.party_id,
+        Err(_) => return Ok(()),
+    };
+    let party = parties
+        .try_get(party_id)
+        .context(format!("Can't find party {:?}", party_id))?;
+    let user_id = get_user_id(connection_global_world_id, spawns)?;
+
+    for member_id in party
+        .members
+        .iter()
+        .filter(|id| **id != connection_global_world_id)
+    {
+        send_message_to_connection(
+            assemble_party_member_interval_pos_update(*member_id, user_id, location, zone_id),
+            connections,
+        );
+    }
+
+    Ok(())
+}
+
+fn handle_user_health_report(
+    connection_global_world_id: EntityId,
+    hp: i64,
+    max_hp: i64,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    parties: &ViewMut<Party>,
+    party_members: &ViewMut<PartyMember>,
+) -> Result<()> {
+    debug!("Message::UserHealthReport incoming");
+
+    // Users without a party don't share their health.
+    let party_id = match party_members.try_get(connection_global_world_id) {
+        Ok(member) => member.party_id,
+        Err(_) => return Ok(()),
+    };
+    let party = parties
+        .try_get(party_id)
+        .context(format!("Can't find party {:?}", party_id))?;
+    let user_id = get_user_id(connection_global_world_id, spawns)?;
+
+    for member_id in party
+        .members
+        .iter()
+        .filter(|id| **id != connection_global_world_id)
+    {
+        send_message_to_connection(
+            assemble_party_member_change_hp(*member_id, user_id, hp, max_hp),
+            connections,
+        );
+    }
+
+    Ok(())
+}
+
+/// Removes an user from it's party. The party is disbanded once only one member is left.
+/// If the leader leaves, the member that joined the party next becomes the new leader.
+fn remove_member(
+    connection_global_world_id: EntityId,
+    is_banned: bool,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    parties: &mut ViewMut<Party>,
+    party_members: &mut ViewMut<PartyMember>,
+    deletion_list: &mut UniqueViewMut<DeletionList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    let party_id = party_members
+        .try_get(connection_global_world_id)
+        .context("User is not in a party")?
+        .party_id;
+    let user_id = get_user_id(connection_global_world_id, spawns)?;
+
+    party_members.delete(connection_global_world_id);
+    parties
+        .try_get(party_id)
+        .context(format!("Can't find party {:?}", party_id))?
+        .members
+        .retain(|id| *id != connection_global_world_id);
+
+    if is_banned {
+        send_message_to_connection(assemble_ban_party(connection_global_world_id), connections);
+    } else {
+        send_message_to_connection(
+            assemble_leave_party(connection_global_world_id),
+            connections,
+        );
+    }
+
+    if parties[party_id].members.len() < 2 {
+        disband_party(party_id, connections, parties, party_members, deletion_list);
+        return Ok(());
+    }
+
+    let mut party = parties
+        .try_get(party_id)
+        .context(format!("Can't find party {:?}", party_id))?;
+    let is_leader_change = party.leader_id == connection_global_world_id;
+    if is_leader_change {
+        party.leader_id = party.members[0];
+    }
+    let leader_user_id = get_user_id(party.leader_id, spawns)?;
+
+    let (user_name, leader_name) = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+        let user_name = get_user_name(&mut conn, user_id).await?;
+        let leader_name = if is_leader_change {
+            Some(get_user_name(&mut conn, leader_user_id).await?)
+        } else {
+            None
+        };
+        Ok::<(String, Option<String>), anyhow::Error>((user_name, leader_name))
+    })?;
+
+    for member_id in party.members.iter() {
+        let message = if is_banned {
+            assemble_ban_party_member(*member_id, &user_name, user_id)
+        } else {
+            assemble_leave_party_member(*member_id, &user_name, user_id)
+        };
+        send_message_to_connection(message, connections);
+
+        if let Some(leader_name) = &leader_name {
+            send_message_to_connection(
+                assemble_change_party_manager(*member_id, leader_name, leader_user_id),
+                connections,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes all members from a party and deletes it.
+fn disband_party(
+    party_id: EntityId,
+    connections: &View<GlobalConnection>,
+    parties: &ViewMut<Party>,
+    party_members: &mut ViewMut<PartyMember>,
+    deletion_list: &mut UniqueViewMut<DeletionList>,
+) {
+    debug!("Party {:?} disbanded", party_id);
+
+    if let Ok(party) = parties.try_get(party_id) {
+        for member_id in party.members.iter() {
+            party_members.delete(*member_id);
+            send_message_to_connection(assemble_leave_party(*member_id), connections);
+        }
+    }
+    deletion_list.0.push(party_id);
+}
+
+/// Returns the party of an user if the user is it's leader.
+fn get_led_party_id(
+    connection_global_world_id: EntityId,
+    parties: &ViewMut<Party>,
+    party_members: &ViewMut<PartyMember>,
+) -> Result<EntityId> {
+    let party_id = party_members
+        .try_get(connection_global_world_id)
+        .context("User is not in a party")?
+        .party_id;
+    let party = parties
+        .try_get(party_id)
+        .context(format!("Can't find party {:?}", party_id))?;
+    ensure!(
+        party.leader_id == connection_global_world_id,
+        "User is not the leader of party {:?}",
+        party_id
+    );
+    Ok(party_id)
+}
+
+/// Finds the connection of a party member by it's user ID.
+fn find_member(party: &Party, user_id: i32, spawns: &View<GlobalUserSpawn>) -> Option<EntityId> {
+    party
+        .members
+        .iter()
+        .find(|id| {
+            spawns
+                .try_get(**id)
+                .map_or(false, |spawn| spawn.user_id == user_id)
+        })
+        .copied()
+}
+
+fn get_user_id(
+    connection_global_world_id: EntityId,
+    spawns: &View<GlobalUserSpawn>,
+) -> Result<i32> {
+    Ok(spawns
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find user spawn {:?}",
+            connection_global_world_id
+        ))?
+        .user_id)
+}
+
+async fn get_user_name(conn: &mut PgConnection, user_id: i32) -> Result<String> {
+    Ok(user::get_by_id(conn, user_id)
+        .await
+        .context(format!("Can't find user {}", user_id))?
+        .name)
+}
+
+fn max_party_size(party: &Party) -> usize {
+    if party.is_raid {
+        MAX_RAID_SIZE
+    } else {
+        MAX_PARTY_SIZE
+    }
+}
+
+/// Sends the member list of a party to all of it's members.
+fn send_party_member_list(
+    party: &Party,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    let packet = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let mut members = Vec::with_capacity(party.members.len());
+        for member_id in party.members.iter() {
+            let member = user::get_by_id(&mut conn, get_user_id(*member_id, spawns)?).await?;
+            members.push(SPartyMemberListEntry {
+                name: member.name,
+                server_id: SERVER_ID,
+                user_id: member.id,
+                level: member.level,
+                class: member.class,
+                online: true,
+            });
+        }
+
+        Ok::<SPartyMemberList, anyhow::Error>(SPartyMemberList {
+            members,
+            is_raid: party.is_raid,
+            leader_server_id: SERVER_ID,
+            leader_user_id: get_user_id(party.leader_id, spawns)?,
+            looting_method: party.looting_method,
+        })
+    })?;
+
+    for member_id in party.members.iter() {
+        send_message_to_connection(
+            assemble_party_member_list(*member_id, packet.clone()),
+            connections,
+        );
+    }
+
+    Ok(())
+}
+
+fn assemble_begin_through_arbiter_contract(
+    connection_global_world_id: EntityId,
+    name: &str,
+    data: &[u8],
+    contract_id: i32,
+) -> EcsMessage {
+    Box::new(ResponseBeginThroughArbiterContract {
+        connection_global_world_id,
+        packet: SBeginThroughArbiterContract {
+            name: name.to_string(),
+            data: data.to_vec(),
+            contract_type: PARTY_INVITE_CONTRACT,
+            contract_id,
+        },
+    })
+}
+
+fn assemble_party_member_list(
+    connection_global_world_id: EntityId,
+    packet: SPartyMemberList,
+) -> EcsMessage {
+    Box::new(ResponsePartyMemberList {
+        connection_global_world_id,
+        packet,
+    })
+}
+
+fn assemble_leave_party(connection_global_world_id: EntityId) -> EcsMessage {
+    Box::new(ResponseLeaveParty {
+        connection_global_world_id,
+        packet: SLeaveParty {},
+    })
+}
+
+fn assemble_leave_party_member(
+    connection_global_world_id: EntityId,
+    name: &str,
+    user_id: i32,
+) -> EcsMessage {
+    Box::new(ResponseLeavePartyMember {
+        connection_global_world_id,
+        packet: SLeavePartyMember {
+            name: name.to_string(),
+            server_id: SERVER_ID,
+            user_id,
+        },
+    })
+}
+
+fn assemble_ban_party(connection_global_world_id: EntityId) -> EcsMessage {
+    Box::new(ResponseBanParty {
+        connection_global_world_id,
+        packet: SBanParty {},
+    })
+}
+
+fn assemble_ban_party_member(
+    connection_global_world_id: EntityId,
+    name: &str,
+    user_id: i32,
+) -> EcsMessage {
+    Box::new(ResponseBanPartyMember {
+        connection_global_world_id,
+        packet: SBanPartyMember {
+            name: name.to_string(),
+            server_id: SERVER_ID,
+            user_id,
+        },
+    })
+}
+
+fn assemble_change_party_manager(
+    connection_global_world_id: EntityId,
+    name: &str,
+    user_id: i32,
+) -> EcsMessage {
+    Box::new(ResponseChangePartyManager {
+        connection_global_world_id,
+        packet: SChangePartyManager {
+            name: name.to_string(),
+            server_id: SERVER_ID,
+            user_id,
+        },
+    })
+}
+
+fn assemble_party_looting_method(
+    connection_global_world_id: EntityId,
+    looting_method: LootingMethod,
+) -> EcsMessage {
+    Box::new(ResponsePartyLootingMethod {
+        connection_global_world_id,
+        packet: SPartyLootingMethod { looting_method },
+    })
+}
+
+fn assemble_party_member_interval_pos_update(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    location: Vec3f,
+    zone_id: i32,
+) -> EcsMessage {
+    Box::new(ResponsePartyMemberIntervalPosUpdate {
+        connection_global_world_id,
+        packet: SPartyMemberIntervalPosUpdate {
+            server_id: SERVER_ID,
+            user_id,
+            location,
+            zone_id,
+        },
+    })
+}
+
+fn assemble_party_member_change_hp(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    current_hp: i64,
+    max_hp: i64,
+) -> EcsMessage {
+    Box::new(ResponsePartyMemberChangeHp {
+        connection_global_world_id,
+        packet: SPartyMemberChangeHp {
+            server_id: SERVER_ID,
+            user_id,
+            current_hp,
+            max_hp,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::{Health, LocalUserSpawn, Location, UserSpawnStatus};
+    use crate::ecs::resource::{GlobalMessageChannel, Tick};
+    use crate::ecs::system::common::cleaner_system;
+    use crate::ecs::system::local::status_reporter_system;
+    use crate::model::entity::User;
+    use crate::model::repository::account;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::tests::db_test;
+    use async_std::sync::{channel, Receiver};
+    use nalgebra::{Point3, Rotation3, Vector3};
+
+    struct TestUser {
+        user: User,
+        connection_global_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(pool);
+        world
+    }
+
+    async fn create_user(pool: &PgPool, num: i32) -> Result<User> {
+        let mut conn = pool.acquire().await?;
+        let account = account::create(&mut conn, &get_default_account(num)).await?;
+        user::create(&mut conn, &get_default_user(&account, num)).await
+    }
+
+    fn add_user(world: &World, pool: &PgPool, num: i32) -> Result<TestUser> {
+        let user = task::block_on(async { create_user(pool, num).await })?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut spawns: ViewMut<GlobalUserSpawn>,
+             mut block_lists: ViewMut<BlockList>| {
+                entities.add_entity(
+                    (&mut connections, &mut spawns, &mut block_lists),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        GlobalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_local_world_id: None,
+                            local_world_id: None,
+                            local_world_channel: None,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
+                        },
+                        BlockList::default(),
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            user,
+            connection_global_world_id,
+            rx: rx_channel,
+        })
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(party_manager_system);
+        world.run(cleaner_system);
+    }
+
+    fn invite(world: &World, inviter: &TestUser, invitee: &TestUser) {
+        run_message(
+            world,
+            Message::RequestContract {
+                connection_global_world_id: inviter.connection_global_world_id,
+                account_id: inviter.user.account_id,
+                user_id: inviter.user.id,
+                packet: CRequestContract {
+                    name: invitee.user.name.to_uppercase(),
+                    data: vec![],
+                    contract_type: PARTY_INVITE_CONTRACT,
+                },
+            },
+        );
+    }
+
+    fn reply(world: &World, invitee: &TestUser, accept: bool) {
+        run_message(
+            world,
+            Message::RequestReplyThroughArbiterContract {
+                connection_global_world_id: invitee.connection_global_world_id,
+                account_id: invitee.user.account_id,
+                user_id: invitee.user.id,
+                packet: CReplyThroughArbiterContract {
+                    contract_type: PARTY_INVITE_CONTRACT,
+                    contract_id: 0,
+                    accept,
+                },
+            },
+        );
+    }
+
+    /// Creates a party that is led by the first user.
+    fn make_party(world: &World, users: &[TestUser]) {
+        for invitee in users.iter().skip(1) {
+            invite(world, &users[0], invitee);
+            reply(world, invitee, true);
+        }
+        users.iter().for_each(clear_messages);
+    }
+
+    fn clear_messages(user: &TestUser) {
+        while user.rx.try_recv().is_ok() {}
+    }
+
+    fn get_party(world: &World, user: &TestUser) -> Option<Party> {
+        world.run(|parties: View<Party>, party_members: View<PartyMember>| {
+            party_members
+                .try_get(user.connection_global_world_id)
+                .ok()
+                .map(|member| parties[member.party_id].clone())
+        })
+    }
+
+    fn assert_member_list(message: EcsMessage, member_count: usize) -> SPartyMemberList {
+        match &*message {
+            Message::ResponsePartyMemberList { packet, .. } => {
+                assert_eq!(packet.members.len(), member_count);
+                packet.clone()
+            }
+            _ => panic!("Message is not a ResponsePartyMemberList message"),
+        }
+    }
+
+    fn assert_leave_party(message: EcsMessage) {
+        match &*message {
+            Message::ResponseLeaveParty { .. } => {}
+            _ => panic!("Message is not a ResponseLeaveParty message"),
+        }
+    }
+
+    fn assert_change_party_manager(message: EcsMessage, leader: &TestUser) {
+        match &*message {
+            Message::ResponseChangePartyManager { packet, .. } => {
+                assert_eq!(packet.name, leader.user.name);
+                assert_eq!(packet.user_id, leader.user.id);
+            }
+            _ => panic!("Message is not a ResponseChangePartyManager message"),
+        }
+    }
+
+    #[test]
+    fn test_invite_and_accept() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let leader = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+
+            invite(&world, &leader, &member);
+
+            match &*member.rx.try_recv()? {
+                Message::ResponseBeginThroughArbiterContract { packet, .. } => {
+                    assert_eq!(packet.name, leader.user.name);
+                    assert_eq!(packet.contract_type, PARTY_INVITE_CONTRACT);
+                    assert_eq!(packet.contract_id, leader.user.id);
+                }
+                _ => panic!("Message is not a ResponseBeginThroughArbiterContract message"),
+            }
+
+            reply(&world, &member, true);
+
+            for user in [&leader, &member].iter() {
+                let list = assert_member_list(user.rx.try_recv()?, 2);
+                assert_eq!(list.members[0].name, leader.user.name);
+                assert_eq!(list.members[1].user_id, member.user.id);
+                assert_eq!(list.members[1].level, member.user.level);
+                assert_eq!(list.members[1].class, member.user.class);
+                assert_eq!(list.leader_user_id, leader.user.id);
+                assert_eq!(list.looting_method, LootingMethod::RoundRobin);
+                assert!(!list.is_raid);
+            }
+
+            let party = get_party(&world, &member).unwrap();
+            assert_eq!(party.leader_id, leader.connection_global_world_id);
+            assert_eq!(
+                party.members,
+                vec![
+                    leader.connection_global_world_id,
+                    member.connection_global_world_id
+                ]
+            );
+
+            // Members can't be invited again and only the leader can invite.
+            let third = add_user(&world, &pool, 2)?;
+            invite(&world, &leader, &member);
+            invite(&world, &member, &third);
+            assert!(member.rx.is_empty());
+            assert!(third.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_invite_declined() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let leader = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+
+            invite(&world, &leader, &member);
+            member.rx.try_recv()?;
+            reply(&world, &member, false);
+
+            assert!(leader.rx.is_empty());
+            assert!(member.rx.is_empty());
+            assert!(get_party(&world, &leader).is_none());
+            assert!(get_party(&world, &member).is_none());
+
+            // The invitation was consumed by the reply.
+            reply(&world, &member, true);
+            assert!(get_party(&world, &member).is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_invite_blocked() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let leader = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+            world.run(|mut block_lists: ViewMut<BlockList>| {
+                block_lists[member.connection_global_world_id]
+                    .blocked_users
+                    .insert(leader.user.id);
+            });
+
+            invite(&world, &leader, &member);
+            invite(&world, &leader, &leader);
+            assert!(member.rx.is_empty());
+            assert!(leader.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_invitation_timeout() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let leader = add_user(&world, &pool, 0)?;
+            let member = add_user(&world, &pool, 1)?;
+
+            invite(&world, &leader, &member);
+            member.rx.try_recv()?;
+            world.run(|mut invitations: ViewMut<PartyInvitation>| {
+                invitations[member.connection_global_world_id].created_at =
+                    Instant::now() - INVITATION_TIMEOUT - Duration::from_secs(1);
+            });
+            world.run(party_manager_system);
+
+            reply(&world, &member, true);
+            assert!(leader.rx.is_empty());
+            assert!(get_party(&world, &member).is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_party_full() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let users = (0..MAX_PARTY_SIZE as i32 + 1)
+                .map(|num| add_user(&world, &pool, num))
+                .collect::<Result<Vec<TestUser>>>()?;
+            make_party(&world, &users[..MAX_PARTY_SIZE]);
+            assert_eq!(
+                get_party(&world, &users[0]).unwrap().members.len(),
+                MAX_PARTY_SIZE
+            );
+
+            let outsider = &users[MAX_PARTY_SIZE];
+            invite(&world, &users[0], outsider);
+            assert!(outsider.rx.is_empty());
+
+            // Raids have room for more members.
+            run_message(
+                &world,
+                Message::RequestMergePartyToRaid {
+                    connection_global_world_id: users[0].connection_global_world_id,
+                    account_id: users[0].user.account_id,
+                    user_id: users[0].user.id,
+                    packet: CMergePartyToRaid {},
+                },
+            );
+            for user in users[..MAX_PARTY_SIZE].iter() {
+                assert!(assert_member_list(user.rx.try_recv()?, MAX_PARTY_SIZE).is_raid);
+            }
+
+            invite(&world, &users[0], outsider);
+            reply(&world, outsider, true);
+            assert_member_list(outsider.rx.try_recv()?, MAX_PARTY_SIZE + 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_leave_party() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let users = (0..3)
+                .map(|num| add_user(&world, &pool, num))
+                .collect::<Result<Vec<TestUser>>>()?;
+            make_party(&world, &users);
+
+            // The leader leaves and the next member becomes the new leader.
+            run_message(
+                &world,
+                Message::RequestLeaveParty {
+                    connection_global_world_id: users[0].connection_global_world_id,
+                    account_id: users[0].user.account_id,
+                    user_id: users[0].user.id,
+                    packet: CLeaveParty {},
+                },
+            );
+
+            assert_leave_party(users[0].rx.try_recv()?);
+            assert!(get_party(&world, &users[0]).is_none());
+            for user in users[1..].iter() {
+                match &*user.rx.try_recv()? {
+                    Message::ResponseLeavePartyMember { packet, .. } => {
+                        assert_eq!(packet.name, users[0].user.name);
+                        assert_eq!(packet.user_id, users[0].user.id);
+                    }
+                    _ => panic!("Message is not a ResponseLeavePartyMember message"),
+                }
+                assert_change_party_manager(user.rx.try_recv()?, &users[1]);
+            }
+            let party = get_party(&world, &users[1]).unwrap();
+            assert_eq!(party.leader_id, users[1].connection_global_world_id);
+
+            // The party is disbanded once only one member is left.
+            run_message(
+                &world,
+                Message::RequestLeaveParty {
+                    connection_global_world_id: users[2].connection_global_world_id,
+                    account_id: users[2].user.account_id,
+                    user_id: users[2].user.id,
+                    packet: CLeaveParty {},
+                },
+            );
+
+            assert_leave_party(users[2].rx.try_recv()?);
+            assert_leave_party(users[1].rx.try_recv()?);
+            assert!(get_party(&world, &users[1]).is_none());
+            assert!(get_party(&world, &users[2]).is_none());
+            world.run(|parties: View<Party>| {
+                assert_eq!(parties.iter().count(), 0);
+            });
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_ban_party_member() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let users = (0..3)
+                .map(|num| add_user(&world, &pool, num))
+                .collect::<Result<Vec<TestUser>>>()?;
+            make_party(&world, &users);
+
+            let ban = |banning: &TestUser, banned: &TestUser| {
+                run_message(
+                    &world,
+                    Message::RequestBanPartyMember {
+                        connection_global_world_id: banning.connection_global_world_id,
+                        account_id: banning.user.account_id,
+                        user_id: banning.user.id,
+                        packet: CBanPartyMember {
+                            server_id: SERVER_ID,
+                            user_id: banned.user.id,
+                        },
+                    },
+                )
+            };
+
+            // Only the leader can ban members.
+            ban(&users[1], &users[2]);
+            ban(&users[0], &users[0]);
+            users.iter().for_each(|user| assert!(user.rx.is_empty()));
+
+            ban(&users[0], &users[2]);
+
+            match &*users[2].rx.try_recv()? {
+                Message::ResponseBanParty { .. } => {}
+                _ => panic!("Message is not a ResponseBanParty message"),
+            }
+            for user in users[..2].iter() {
+                match &*user.rx.try_recv()? {
+                    Message::ResponseBanPartyMember { packet, .. } => {
+                        assert_eq!(packet.name, users[2].user.name);
+                        assert_eq!(packet.user_id, users[2].user.id);
+                    }
+                    _ => panic!("Message is not a ResponseBanPartyMember message"),
+                }
+            }
+            assert!(get_party(&world, &users[2]).is_none());
+            assert_eq!(get_party(&world, &users[0]).unwrap().members.len(), 2);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_change_party_manager() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let users = (0..2)
+                .map(|num| add_user(&world, &pool, num))
+                .collect::<Result<Vec<TestUser>>>()?;
+            make_party(&world, &users);
+
+            run_message(
+                &world,
+                Message::RequestChangePartyManager {
+                    connection_global_world_id: users[0].connection_global_world_id,
+                    account_id: users[0].user.account_id,
+                    user_id: users[0].user.id,
+                    packet: CChangePartyManager {
+                        server_id: SERVER_ID,
+                        user_id: users[1].user.id,
+                    },
+                },
+            );
+
+            for user in users.iter() {
+                assert_change_party_manager(user.rx.try_recv()?, &users[1]);
+            }
+            assert_eq!(
+                get_party(&world, &users[0]).unwrap().leader_id,
+                users[1].connection_global_world_id
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_party_looting_method() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let users = (0..2)
+                .map(|num| add_user(&world, &pool, num))
+                .collect::<Result<Vec<TestUser>>>()?;
+            make_party(&world, &users);
+
+            let change_looting_method = |user: &TestUser| {
+                run_message(
+                    &world,
+                    Message::RequestPartyLootingMethod {
+                        connection_global_world_id: user.connection_global_world_id,
+                        account_id: user.user.account_id,
+                        user_id: user.user.id,
+                        packet: CPartyLootingMethod {
+                            looting_method: LootingMethod::Leader,
+                        },
+                    },
+                )
+            };
+
+            change_looting_method(&users[1]);
+            users.iter().for_each(|user| assert!(user.rx.is_empty()));
+
+            change_looting_method(&users[0]);
+            for user in users.iter() {
+                match &*user.rx.try_recv()? {
+                    Message::ResponsePartyLootingMethod { packet, .. } => {
+                        assert_eq!(packet.looting_method, LootingMethod::Leader);
+                    }
+                    _ => panic!("Message is not a ResponsePartyLootingMethod message"),
+                }
+            }
+            assert_eq!(
+                get_party(&world, &users[0]).unwrap().looting_method,
+                LootingMethod::Leader
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_dismiss_party() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let users = (0..3)
+                .map(|num| add_user(&world, &pool, num))
+                .collect::<Result<Vec<TestUser>>>()?;
+            make_party(&world, &users);
+
+            run_message(
+                &world,
+                Message::RequestDismissParty {
+                    connection_global_world_id: users[0].connection_global_world_id,
+                    account_id: users[0].user.account_id,
+                    user_id: users[0].user.id,
+                    packet: CDismissParty {},
+                },
+            );
+
+            for user in users.iter() {
+                assert_leave_party(user.rx.try_recv()?);
+                assert!(get_party(&world, user).is_none());
+            }
+            world.run(|parties: View<Party>| {
+                assert_eq!(parties.iter().count(), 0);
+            });
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_status_reports() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let users = (0..2)
+                .map(|num| add_user(&world, &pool, num))
+                .collect::<Result<Vec<TestUser>>>()?;
+            let outsider = add_user(&world, &pool, 2)?;
+            make_party(&world, &users);
+
+            let location = Vec3f {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+            };
+            run_message(
+                &world,
+                Message::UserLocationReport {
+                    connection_global_world_id: users[0].connection_global_world_id,
+                    zone_id: 7,
+                    location,
+                },
+            );
+            run_message(
+                &world,
+                Message::UserHealthReport {
+                    connection_global_world_id: users[0].connection_global_world_id,
+                    hp: 1500,
+                    max_hp: 2000,
+                },
+            );
+            run_message(
+                &world,
+                Message::UserLocationReport {
+                    connection_global_world_id: outsider.connection_global_world_id,
+                    zone_id: 7,
+                    location,
+                },
+            );
+
+            match &*users[1].rx.try_recv()? {
+                Message::ResponsePartyMemberIntervalPosUpdate { packet, .. } => {
+                    assert_eq!(packet.user_id, users[0].user.id);
+                    assert_eq!(packet.location, location);
+                    assert_eq!(packet.zone_id, 7);
+                }
+                _ => panic!("Message is not a ResponsePartyMemberIntervalPosUpdate message"),
+            }
+            match &*users[1].rx.try_recv()? {
+                Message::ResponsePartyMemberChangeHp { packet, .. } => {
+                    assert_eq!(packet.user_id, users[0].user.id);
+                    assert_eq!(packet.current_hp, 1500);
+                    assert_eq!(packet.max_hp, 2000);
+                }
+                _ => panic!("Message is not a ResponsePartyMemberChangeHp message"),
+            }
+            assert!(users[0].rx.is_empty());
+            assert!(users[1].rx.is_empty());
+            assert!(outsider.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_status_reports_of_local_world() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let users = (0..2)
+                .map(|num| add_user(&world, &pool, num))
+                .collect::<Result<Vec<TestUser>>>()?;
+            make_party(&world, &users);
+
+            // The first user is spawned in a local world, which reports it's status.
+            let (global_tx_channel, global_rx_channel) = channel(1024);
+            let local_world = World::new();
+            local_world.add_unique(GlobalMessageChannel {
+                channel: global_tx_channel,
+            });
+            local_world.add_unique(Tick {
+                count: 0,
+                delta: Duration::from_millis(33),
+                time: Instant::now(),
+            });
+            local_world.run(
+                |mut entities: EntitiesViewMut,
+                 mut user_spawns: ViewMut<LocalUserSpawn>,
+                 mut locations: ViewMut<Location>,
+                 mut healths: ViewMut<Health>| {
+                    entities.add_entity(
+                        (&mut user_spawns, &mut locations, &mut healths),
+                        (
+                            LocalUserSpawn {
+                                user_id: users[0].user.id,
+                                account_id: users[0].user.account_id,
+                                status: UserSpawnStatus::Spawned,
+                                zone_id: 5,
+                                connection_global_world_id: users[0].connection_global_world_id,
+                                is_alive: true,
+                            },
+                            Location {
+                                point: Point3::new(1.0, 2.0, 3.0),
+                                rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+                            },
+                            Health {
+                                hp: 700,
+                                max_hp: 2000,
+                            },
+                        ),
+                    );
+                },
+            );
+            local_world.run(status_reporter_system);
+
+            while let Ok(message) = global_rx_channel.try_recv() {
+                run_message(&world, *message);
+            }
+
+            match &*users[1].rx.try_recv()? {
+                Message::ResponsePartyMemberIntervalPosUpdate { packet, .. } => {
+                    assert_eq!(packet.user_id, users[0].user.id);
+                    assert_eq!(packet.zone_id, 5);
+                }
+                _ => panic!("Message is not a ResponsePartyMemberIntervalPosUpdate message"),
+            }
+            match &*users[1].rx.try_recv()? {
+                Message::ResponsePartyMemberChangeHp { packet, .. } => {
+                    assert_eq!(packet.user_id, users[0].user.id);
+                    assert_eq!(packet.current_hp, 700);
+                    assert_eq!(packet.max_hp, 2000);
+                }
+                _ => panic!("Message is not a ResponsePartyMemberChangeHp message"),
+            }
+            assert!(users[0].rx.is_empty());
+            assert!(users[1].rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_offline_member_leaves_party() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let users = (0..3)
+                .map(|num| add_user(&world, &pool, num))
+                .collect::<Result<Vec<TestUser>>>()?;
+            make_party(&world, &users);
+
+            world.run(|mut spawns: ViewMut<GlobalUserSpawn>| {
+                spawns[users[2].connection_global_world_id].marked_for_deletion = true;
+            });
+            world.run(party_manager_system);
+
+            for user in users[..2].iter() {
+                match &*user.rx.try_recv()? {
+                    Message::ResponseLeavePartyMember { packet, .. } => {
+                        assert_eq!(packet.user_id, users[2].user.id);
+                    }
+                    _ => panic!("Message is not a ResponseLeavePartyMember message"),
+                }
+            }
+            assert!(get_party(&world, &users[2]).is_none());
+            assert_eq!(get_party(&world, &users[0]).unwrap().members.len(), 2);
+
+            Ok(())
+        })
+    }
+}
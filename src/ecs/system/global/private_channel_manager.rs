@@ -0,0 +1,1200 @@
+use crate::ecs::component::{Chatter, GlobalConnection, GlobalUserSpawn, PrivateChannels};
+use crate::ecs::message::Message::{
+    ResponseJoinPrivateChannel, ResponseLeavePrivateChannel, ResponsePrivateChannelNotice,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::system::global::send_message_to_connection;
+use crate::model::entity::{PrivateChannel, PrivateChannelMember};
+use crate::model::repository::private_channel;
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use chrono::Utc;
+use shipyard::*;
+use sqlx::{PgConnection, PgPool};
+use tracing::{debug, error, info_span};
+
+/// Number of private channels an user can join.
+const MAX_PRIVATE_CHANNELS: i32 = 8;
+
+/// Maximal length of the name of a private channel.
+const MAX_CHANNEL_NAME_LENGTH: usize = 20;
+
+/// Highest password a private channel can have. The client uses four digit passwords.
+const MAX_CHANNEL_PASSWORD: i32 = 9999;
+
+/// Password that the client sends for channels without a password.
+const NO_CHANNEL_PASSWORD: i32 = 0;
+
+/// Events of the private channel notice.
+const NOTICE_EVENT_JOINED: i32 = 1;
+const NOTICE_EVENT_LEFT: i32 = 2;
+const NOTICE_EVENT_KICKED: i32 = 3;
+
+/// The private channel manager handles the password protected chat channels that are created by
+/// the users. Channels and their members are persisted, so users re-join their channels when
+/// they log in again.
+pub fn private_channel_manager_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<GlobalConnection>,
+    spawns: View<GlobalUserSpawn>,
+    chatters: View<Chatter>,
+    mut private_channels: ViewMut<PrivateChannels>,
+    entities: EntitiesView,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::UserSpawned {
+                connection_global_world_id,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_user_spawned(
+                    *connection_global_world_id,
+                    &connections,
+                    &spawns,
+                    &mut private_channels,
+                    &entities,
+                    &pool,
+                ) {
+                    error!("Ignoring Message::UserSpawned: {:?}", e);
+                }
+            }
+            Message::RequestCreatePrivateChannel {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_create_private_channel(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &mut private_channels,
+                    &pool,
+                ) {
+                    error!("Ignoring create private channel request: {:?}", e);
+                }
+            }
+            Message::RequestJoinPrivateChannel {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_join_private_channel(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &chatters,
+                    &mut private_channels,
+                    &pool,
+                ) {
+                    error!("Ignoring join private channel request: {:?}", e);
+                }
+            }
+            Message::RequestEditPrivateChannel {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_edit_private_channel(
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &private_channels,
+                    &pool,
+                ) {
+                    error!("Ignoring edit private channel request: {:?}", e);
+                }
+            }
+            Message::RequestLeavePrivateChannel {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_leave_private_channel(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &chatters,
+                    &mut private_channels,
+                    &pool,
+                ) {
+                    error!("Ignoring leave private channel request: {:?}", e);
+                }
+            }
+            Message::RequestKickChannelMember {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_kick_channel_member(
+                    *user_id,
+                    &packet,
+                    &connections,
+                    &spawns,
+                    &mut private_channels,
+                    &pool,
+                ) {
+                    error!("Ignoring kick channel member request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+/// Loads the private channels of an user once it's spawned for the first time.
+fn handle_user_spawned(
+    connection_global_world_id: EntityId,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    private_channels: &mut ViewMut<PrivateChannels>,
+    entities: &EntitiesView,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::UserSpawned incoming");
+
+    // Users that changed their local world already joined their channels.
+    if private_channels.try_get(connection_global_world_id).is_ok() {
+        return Ok(());
+    }
+
+    let spawn = spawns.try_get(connection_global_world_id).context(format!(
+        "Can't find user spawn {:?}",
+        connection_global_world_id
+    ))?;
+
+    let mut channels = PrivateChannels::default();
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let memberships = private_channel::list_by_user_id(&mut conn, spawn.user_id)
+            .await
+            .context(format!(
+                "Can't query the private channels of user {}",
+                spawn.user_id
+            ))?;
+
+        for membership in memberships {
+            let channel = private_channel::get_by_id(&mut conn, membership.channel_id).await?;
+            send_message_to_connection(
+                assemble_join_private_channel(
+                    &mut conn,
+                    connection_global_world_id,
+                    &channel,
+                    membership.slot,
+                )
+                .await?,
+                connections,
+            );
+            channels
+                .channels
+                .insert(membership.slot, membership.channel_id);
+        }
+
+        Ok::<(), anyhow::Error>(())
+    })?;
+
+    entities.add_component(private_channels, channels, connection_global_world_id);
+
+    Ok(())
+}
+
+fn handle_create_private_channel(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CCreatePrivateChannel,
+    connections: &View<GlobalConnection>,
+    private_channels: &mut ViewMut<PrivateChannels>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestCreatePrivateChannel incoming");
+
+    check_channel_name(&packet.name)?;
+    let password = check_channel_password(packet.password)?;
+
+    let mut channels = private_channels
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find private channels of {:?}",
+            connection_global_world_id
+        ))?;
+    let slot = find_free_slot(channels)?;
+
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        ensure!(
+            !private_channel::is_name_taken(&mut conn, &packet.name).await?,
+            "Private channel name {} is already taken",
+            packet.name
+        );
+
+        let channel = private_channel::create(
+            &mut conn,
+            &PrivateChannel {
+                id: -1,
+                name: packet.name.clone(),
+                password,
+                owner_user_id: user_id,
+                created_at: Utc::now(),
+            },
+        )
+        .await
+        .context("Can't create private channel")?;
+        add_member(&mut conn, &channel, user_id, slot).await?;
+
+        let message =
+            assemble_join_private_channel(&mut conn, connection_global_world_id, &channel, slot)
+                .await?;
+
+        conn.commit().await?;
+
+        channels.channels.insert(slot, channel.id);
+        send_message_to_connection(message, connections);
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+fn handle_join_private_channel(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CJoinPrivateChannel,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    chatters: &View<Chatter>,
+    private_channels: &mut ViewMut<PrivateChannels>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestJoinPrivateChannel incoming");
+
+    let user_name = chatters
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find chatter {:?}",
+            connection_global_world_id
+        ))?
+        .user_name
+        .clone();
+
+    let slot = {
+        let channels = private_channels
+            .try_get(connection_global_world_id)
+            .context(format!(
+                "Can't find private channels of {:?}",
+                connection_global_world_id
+            ))?;
+        find_free_slot(channels)?
+    };
+
+    let channel_id = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let channel = private_channel::get_by_name(&mut conn, &packet.name)
+            .await
+            .context(format!("Can't find private channel {}", packet.name))?;
+        ensure!(
+            channel.password.unwrap_or(NO_CHANNEL_PASSWORD) == packet.password,
+            "Wrong password for private channel {}",
+            channel.id
+        );
+        ensure!(
+            find_slot(private_channels, connection_global_world_id, channel.id).is_none(),
+            "User {} already joined private channel {}",
+            user_id,
+            channel.id
+        );
+
+        add_member(&mut conn, &channel, user_id, slot).await?;
+
+        let message =
+            assemble_join_private_channel(&mut conn, connection_global_world_id, &channel, slot)
+                .await?;
+
+        conn.commit().await?;
+
+        send_message_to_connection(message, connections);
+
+        Ok::<i32, anyhow::Error>(channel.id)
+    })?;
+
+    notify_members(
+        channel_id,
+        NOTICE_EVENT_JOINED,
+        &user_name,
+        connections,
+        spawns,
+        private_channels,
+    );
+
+    let mut channels = private_channels
+        .try_get(connection_global_world_id)
+        .context(format!(
+            "Can't find private channels of {:?}",
+            connection_global_world_id
+        ))?;
+    channels.channels.insert(slot, channel_id);
+
+    Ok(())
+}
+
+fn handle_edit_private_channel(
+    user_id: i32,
+    packet: &CEditPrivateChannel,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    private_channels: &ViewMut<PrivateChannels>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestEditPrivateChannel incoming");
+
+    let password = check_channel_password(packet.password)?;
+
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let mut channel = private_channel::get_by_id(&mut conn, packet.channel_id)
+            .await
+            .context(format!("Can't find private channel {}", packet.channel_id))?;
+        ensure!(
+            channel.owner_user_id == user_id,
+            "User {} is not the owner of private channel {}",
+            user_id,
+            channel.id
+        );
+
+        if channel.name.to_lowercase() != packet.name.to_lowercase() {
+            check_channel_name(&packet.name)?;
+            ensure!(
+                !private_channel::is_name_taken(&mut conn, &packet.name).await?,
+                "Private channel name {} is already taken",
+                packet.name
+            );
+        }
+        channel.name = packet.name.clone();
+        channel.password = password;
+        let channel = private_channel::update(&mut conn, &channel)
+            .await
+            .context("Can't update private channel")?;
+
+        // Refresh the channel information of all online members.
+        let members = online_members(channel.id, spawns, private_channels);
+        let mut messages = Vec::with_capacity(members.len());
+        for (id, slot) in members {
+            messages.push(assemble_join_private_channel(&mut conn, id, &channel, slot).await?);
+        }
+
+        conn.commit().await?;
+
+        for message in messages {
+            send_message_to_connection(message, connections);
+        }
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+fn handle_leave_private_channel(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CLeavePrivateChannel,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    chatters: &View<Chatter>,
+    private_channels: &mut ViewMut<PrivateChannels>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestLeavePrivateChannel incoming");
+
+    let slot = find_slot(
+        private_channels,
+        connection_global_world_id,
+        packet.channel_id,
+    )
+    .context(format!(
+        "User {} is not a member of private channel {}",
+        user_id, packet.channel_id
+    ))?;
+
+    task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let mut channel = private_channel::get_by_id(&mut conn, packet.channel_id)
+            .await
+            .context(format!("Can't find private channel {}", packet.channel_id))?;
+        private_channel::remove_member(&mut conn, channel.id, user_id).await?;
+
+        // The oldest member inherits the channel. Empty channels are deleted.
+        let members = private_channel::list_members(&mut conn, channel.id).await?;
+        if let Some(heir) = members.first() {
+            if channel.owner_user_id == user_id {
+                channel.owner_user_id = heir.user_id;
+                private_channel::update(&mut conn, &channel).await?;
+            }
+        } else {
+            private_channel::delete_by_id(&mut conn, channel.id).await?;
+        }
+
+        conn.commit().await?;
+
+        Ok::<(), anyhow::Error>(())
+    })?;
+
+    if let Ok(mut channels) = private_channels.try_get(connection_global_world_id) {
+        channels.channels.remove(&slot);
+    }
+    send_message_to_connection(
+        assemble_leave_private_channel(connection_global_world_id, packet.channel_id),
+        connections,
+    );
+
+    if let Ok(chatter) = chatters.try_get(connection_global_world_id) {
+        notify_members(
+            packet.channel_id,
+            NOTICE_EVENT_LEFT,
+            &chatter.user_name,
+            connections,
+            spawns,
+            private_channels,
+        );
+    }
+
+    Ok(())
+}
+
+fn handle_kick_channel_member(
+    user_id: i32,
+    packet: &CKickChannelMember,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    private_channels: &mut ViewMut<PrivateChannels>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestKickChannelMember incoming");
+
+    let kicked_user_id = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let channel = private_channel::get_by_id(&mut conn, packet.channel_id)
+            .await
+            .context(format!("Can't find private channel {}", packet.channel_id))?;
+        ensure!(
+            channel.owner_user_id == user_id,
+            "User {} is not the owner of private channel {}",
+            user_id,
+            channel.id
+        );
+
+        let member = private_channel::get_member_by_user_name(&mut conn, channel.id, &packet.name)
+            .await
+            .context(format!(
+                "Can't find member {} in private channel {}",
+                packet.name, channel.id
+            ))?;
+        ensure!(
+            member.user_id != user_id,
+            "User {} can't kick itself from private channel {}",
+            user_id,
+            channel.id
+        );
+        private_channel::remove_member(&mut conn, channel.id, member.user_id).await?;
+
+        conn.commit().await?;
+
+        Ok::<i32, anyhow::Error>(member.user_id)
+    })?;
+
+    // Kicked users that are online leave the channel right away.
+    let kicked_id = spawns
+        .iter()
+        .with_id()
+        .filter(|(_id, spawn)| spawn.user_id == kicked_user_id && !spawn.marked_for_deletion)
+        .map(|(id, _)| id)
+        .next();
+    if let Some(kicked_id) = kicked_id {
+        if let Ok(mut channels) = private_channels.try_get(kicked_id) {
+            channels
+                .channels
+                .retain(|_slot, channel_id| *channel_id != packet.channel_id);
+        }
+        send_message_to_connection(
+            assemble_leave_private_channel(kicked_id, packet.channel_id),
+            connections,
+        );
+    }
+
+    notify_members(
+        packet.channel_id,
+        NOTICE_EVENT_KICKED,
+        &packet.name,
+        connections,
+        spawns,
+        private_channels,
+    );
+
+    Ok(())
+}
+
+/// Makes sure the name only contains letters and numbers.
+fn check_channel_name(name: &str) -> Result<()> {
+    ensure!(!name.is_empty(), "Private channel name is empty");
+    ensure!(
+        name.chars().count() <= MAX_CHANNEL_NAME_LENGTH,
+        "Private channel name {} is longer than {} characters",
+        name,
+        MAX_CHANNEL_NAME_LENGTH
+    );
+    ensure!(
+        name.chars().all(char::is_alphanumeric),
+        "Private channel name {} contains invalid characters",
+        name
+    );
+    Ok(())
+}
+
+/// Converts the password of the client into the password of the channel.
+fn check_channel_password(password: i32) -> Result<Option<i32>> {
+    ensure!(
+        password >= 0 && password <= MAX_CHANNEL_PASSWORD,
+        "Private channel password {} is invalid",
+        password
+    );
+    if password == NO_CHANNEL_PASSWORD {
+        Ok(None)
+    } else {
+        Ok(Some(password))
+    }
+}
+
+/// Returns the first slot that is not used by a channel.
+fn find_free_slot(channels: &PrivateChannels) -> Result<i32> {
+    (1..=MAX_PRIVATE_CHANNELS)
+        .find(|slot| !channels.channels.contains_key(slot))
+        .context(format!(
+            "User already joined {} private channels",
+            MAX_PRIVATE_CHANNELS
+        ))
+}
+
+/// Returns the slot the user uses for the given channel.
+fn find_slot(
+    private_channels: &ViewMut<PrivateChannels>,
+    connection_global_world_id: EntityId,
+    channel_id: i32,
+) -> Option<i32> {
+    private_channels
+        .try_get(connection_global_world_id)
+        .ok()?
+        .channels
+        .iter()
+        .find(|(_slot, id)| **id == channel_id)
+        .map(|(slot, _id)| *slot)
+}
+
+/// Returns the online members of a channel together with the slot they use for it.
+fn online_members(
+    channel_id: i32,
+    spawns: &View<GlobalUserSpawn>,
+    private_channels: &ViewMut<PrivateChannels>,
+) -> Vec<(EntityId, i32)> {
+    let mut members = Vec::new();
+    (spawns, private_channels)
+        .iter()
+        .with_id()
+        .filter(|(_id, (spawn, _channels))| !spawn.marked_for_deletion)
+        .for_each(|(id, (_spawn, channels))| {
+            if let Some((slot, _channel_id)) = channels
+                .channels
+                .iter()
+                .find(|(_slot, member_channel_id)| **member_channel_id == channel_id)
+            {
+                members.push((id, *slot));
+            }
+        });
+    members
+}
+
+fn notify_members(
+    channel_id: i32,
+    event: i32,
+    user_name: &str,
+    connections: &View<GlobalConnection>,
+    spawns: &View<GlobalUserSpawn>,
+    private_channels: &ViewMut<PrivateChannels>,
+) {
+    for (id, _slot) in online_members(channel_id, spawns, private_channels) {
+        send_message_to_connection(
+            assemble_private_channel_notice(id, channel_id, event, user_name),
+            connections,
+        );
+    }
+}
+
+async fn add_member(
+    conn: &mut PgConnection,
+    channel: &PrivateChannel,
+    user_id: i32,
+    slot: i32,
+) -> Result<PrivateChannelMember> {
+    private_channel::add_member(
+        conn,
+        &PrivateChannelMember {
+            channel_id: channel.id,
+            user_id,
+            slot,
+            joined_at: Utc::now(),
+        },
+    )
+    .await
+    .context(format!(
+        "Can't add user {} to private channel {}",
+        user_id, channel.id
+    ))
+}
+
+async fn assemble_join_private_channel(
+    conn: &mut PgConnection,
+    connection_global_world_id: EntityId,
+    channel: &PrivateChannel,
+    slot: i32,
+) -> Result<EcsMessage> {
+    let members = private_channel::list_members(conn, channel.id)
+        .await?
+        .iter()
+        .map(|member| SJoinPrivateChannelMember {
+            user_id: member.user_id,
+        })
+        .collect();
+
+    Ok(Box::new(ResponseJoinPrivateChannel {
+        connection_global_world_id,
+        packet: SJoinPrivateChannel {
+            members,
+            name: channel.name.clone(),
+            slot,
+            channel_id: channel.id,
+        },
+    }))
+}
+
+fn assemble_leave_private_channel(
+    connection_global_world_id: EntityId,
+    channel_id: i32,
+) -> EcsMessage {
+    Box::new(ResponseLeavePrivateChannel {
+        connection_global_world_id,
+        packet: SLeavePrivateChannel { channel_id },
+    })
+}
+
+fn assemble_private_channel_notice(
+    connection_global_world_id: EntityId,
+    channel_id: i32,
+    event: i32,
+    user_name: &str,
+) -> EcsMessage {
+    Box::new(ResponsePrivateChannelNotice {
+        connection_global_world_id,
+        packet: SPrivateChannelNotice {
+            channel_id,
+            event,
+            name: user_name.to_string(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::UserSpawnStatus;
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use async_std::sync::{channel, Receiver};
+    use std::time::Instant;
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(pool);
+        world
+    }
+
+    async fn create_user(pool: &PgPool, num: i32) -> Result<User> {
+        let mut conn = pool.acquire().await?;
+        let account = account::create(&mut conn, &get_default_account(num)).await?;
+        user::create(&mut conn, &get_default_user(&account, num)).await
+    }
+
+    fn add_user(world: &World, user: &User, spawned: bool) -> (EntityId, Receiver<EcsMessage>) {
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<GlobalConnection>,
+             mut spawns: ViewMut<GlobalUserSpawn>,
+             mut chatters: ViewMut<Chatter>| {
+                entities.add_entity(
+                    (&mut connections, &mut spawns, &mut chatters),
+                    (
+                        GlobalConnection {
+                            channel: tx_channel,
+                            is_version_checked: true,
+                            is_authenticated: true,
+                            last_pong: Instant::now(),
+                            waiting_for_pong: false,
+                        },
+                        GlobalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_local_world_id: None,
+                            local_world_id: None,
+                            local_world_channel: None,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
+                        },
+                        Chatter {
+                            user_name: user.name.clone(),
+                            window_start: Instant::now(),
+                            message_count: 0,
+                        },
+                    ),
+                )
+            },
+        );
+
+        if spawned {
+            add_message(
+                world,
+                Message::UserSpawned {
+                    connection_global_world_id,
+                },
+            );
+            world.run(private_channel_manager_system);
+            world.run(cleaner_system);
+        }
+
+        (connection_global_world_id, rx_channel)
+    }
+
+    fn add_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+    }
+
+    fn create_channel(
+        world: &World,
+        connection_global_world_id: EntityId,
+        user: &User,
+        name: &str,
+        password: i32,
+    ) {
+        add_message(
+            world,
+            Message::RequestCreatePrivateChannel {
+                connection_global_world_id,
+                account_id: user.account_id,
+                user_id: user.id,
+                packet: CCreatePrivateChannel {
+                    name: name.to_string(),
+                    password,
+                },
+            },
+        );
+        world.run(private_channel_manager_system);
+        world.run(cleaner_system);
+    }
+
+    fn join_channel(
+        world: &World,
+        connection_global_world_id: EntityId,
+        user: &User,
+        name: &str,
+        password: i32,
+    ) {
+        add_message(
+            world,
+            Message::RequestJoinPrivateChannel {
+                connection_global_world_id,
+                account_id: user.account_id,
+                user_id: user.id,
+                packet: CJoinPrivateChannel {
+                    name: name.to_string(),
+                    password,
+                },
+            },
+        );
+        world.run(private_channel_manager_system);
+        world.run(cleaner_system);
+    }
+
+    fn get_channels(world: &World, connection_global_world_id: EntityId) -> PrivateChannels {
+        world.run(|private_channels: View<PrivateChannels>| {
+            private_channels[connection_global_world_id].clone()
+        })
+    }
+
+    fn assert_joined(message: EcsMessage, name: &str, slot: i32, member_count: usize) -> i32 {
+        match &*message {
+            Message::ResponseJoinPrivateChannel { packet, .. } => {
+                assert_eq!(packet.name, name);
+                assert_eq!(packet.slot, slot);
+                assert_eq!(packet.members.len(), member_count);
+                packet.channel_id
+            }
+            _ => panic!("Message is not a ResponseJoinPrivateChannel message"),
+        }
+    }
+
+    #[test]
+    fn test_create_private_channel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let owner = task::block_on(async { create_user(&pool, 0).await })?;
+            let world = setup(pool.clone());
+            let (owner_id, owner_rx) = add_user(&world, &owner, true);
+
+            create_channel(&world, owner_id, &owner, "Traders", 1234);
+
+            let channel_id = assert_joined(owner_rx.try_recv()?, "Traders", 1, 1);
+            assert_eq!(
+                get_channels(&world, owner_id).channels.get(&1),
+                Some(&channel_id)
+            );
+
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                let channel = private_channel::get_by_id(&mut conn, channel_id).await?;
+                assert_eq!(channel.owner_user_id, owner.id);
+                assert_eq!(channel.password, Some(1234));
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_create_invalid_private_channel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let owner = task::block_on(async { create_user(&pool, 0).await })?;
+            let world = setup(pool);
+            let (owner_id, owner_rx) = add_user(&world, &owner, true);
+
+            create_channel(&world, owner_id, &owner, "", 1234);
+            create_channel(&world, owner_id, &owner, "Trade rs", 1234);
+            create_channel(&world, owner_id, &owner, &"a".repeat(21), 1234);
+            create_channel(&world, owner_id, &owner, "Traders", 10000);
+            create_channel(&world, owner_id, &owner, "Traders", -1);
+
+            assert!(owner_rx.is_empty());
+            assert!(get_channels(&world, owner_id).channels.is_empty());
+
+            create_channel(&world, owner_id, &owner, "Traders", 1234);
+            create_channel(&world, owner_id, &owner, "traders", 1234);
+
+            assert_eq!(owner_rx.len(), 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_join_private_channel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let owner = task::block_on(async { create_user(&pool, 0).await })?;
+            let member = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool);
+            let (owner_id, owner_rx) = add_user(&world, &owner, true);
+            let (member_id, member_rx) = add_user(&world, &member, true);
+
+            create_channel(&world, owner_id, &owner, "Traders", 1234);
+            let channel_id = assert_joined(owner_rx.try_recv()?, "Traders", 1, 1);
+
+            join_channel(&world, member_id, &member, "traders", 4321);
+            assert!(member_rx.is_empty());
+
+            join_channel(&world, member_id, &member, "traders", 1234);
+            assert_joined(member_rx.try_recv()?, "Traders", 1, 2);
+            assert_eq!(
+                get_channels(&world, member_id).channels.get(&1),
+                Some(&channel_id)
+            );
+
+            match &*owner_rx.try_recv()? {
+                Message::ResponsePrivateChannelNotice { packet, .. } => {
+                    assert_eq!(packet.channel_id, channel_id);
+                    assert_eq!(packet.event, NOTICE_EVENT_JOINED);
+                    assert_eq!(packet.name, member.name);
+                }
+                _ => panic!("Message is not a ResponsePrivateChannelNotice message"),
+            }
+
+            // Users can't join a channel twice.
+            join_channel(&world, member_id, &member, "Traders", 1234);
+            assert!(member_rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_private_channels_are_loaded_on_spawn() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let owner = task::block_on(async { create_user(&pool, 0).await })?;
+            let world = setup(pool.clone());
+            let (owner_id, owner_rx) = add_user(&world, &owner, true);
+
+            create_channel(&world, owner_id, &owner, "Traders", 1234);
+            create_channel(&world, owner_id, &owner, "Crafters", 0);
+            assert_eq!(owner_rx.len(), 2);
+
+            // The user logs in again.
+            let world = setup(pool);
+            let (owner_id, owner_rx) = add_user(&world, &owner, true);
+
+            assert_joined(owner_rx.try_recv()?, "Traders", 1, 1);
+            assert_joined(owner_rx.try_recv()?, "Crafters", 2, 1);
+            assert_eq!(get_channels(&world, owner_id).channels.len(), 2);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_leave_private_channel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let owner = task::block_on(async { create_user(&pool, 0).await })?;
+            let member = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool.clone());
+            let (owner_id, owner_rx) = add_user(&world, &owner, true);
+            let (member_id, member_rx) = add_user(&world, &member, true);
+
+            create_channel(&world, owner_id, &owner, "Traders", 0);
+            let channel_id = assert_joined(owner_rx.try_recv()?, "Traders", 1, 1);
+            join_channel(&world, member_id, &member, "Traders", 0);
+            member_rx.try_recv()?;
+            owner_rx.try_recv()?;
+
+            // The member inherits the channel of the owner.
+            add_message(
+                &world,
+                Message::RequestLeavePrivateChannel {
+                    connection_global_world_id: owner_id,
+                    account_id: owner.account_id,
+                    user_id: owner.id,
+                    packet: CLeavePrivateChannel { channel_id },
+                },
+            );
+            world.run(private_channel_manager_system);
+            world.run(cleaner_system);
+
+            match &*owner_rx.try_recv()? {
+                Message::ResponseLeavePrivateChannel { packet, .. } => {
+                    assert_eq!(packet.channel_id, channel_id);
+                }
+                _ => panic!("Message is not a ResponseLeavePrivateChannel message"),
+            }
+            match &*member_rx.try_recv()? {
+                Message::ResponsePrivateChannelNotice { packet, .. } => {
+                    assert_eq!(packet.event, NOTICE_EVENT_LEFT);
+                    assert_eq!(packet.name, owner.name);
+                }
+                _ => panic!("Message is not a ResponsePrivateChannelNotice message"),
+            }
+            assert!(get_channels(&world, owner_id).channels.is_empty());
+
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                let channel = private_channel::get_by_id(&mut conn, channel_id).await?;
+                assert_eq!(channel.owner_user_id, member.id);
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            // The last member deletes the channel.
+            add_message(
+                &world,
+                Message::RequestLeavePrivateChannel {
+                    connection_global_world_id: member_id,
+                    account_id: member.account_id,
+                    user_id: member.id,
+                    packet: CLeavePrivateChannel { channel_id },
+                },
+            );
+            world.run(private_channel_manager_system);
+
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                assert!(private_channel::get_by_id(&mut conn, channel_id)
+                    .await
+                    .is_err());
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_kick_channel_member() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let owner = task::block_on(async { create_user(&pool, 0).await })?;
+            let member = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool);
+            let (owner_id, owner_rx) = add_user(&world, &owner, true);
+            let (member_id, member_rx) = add_user(&world, &member, true);
+
+            create_channel(&world, owner_id, &owner, "Traders", 0);
+            let channel_id = assert_joined(owner_rx.try_recv()?, "Traders", 1, 1);
+            join_channel(&world, member_id, &member, "Traders", 0);
+            member_rx.try_recv()?;
+            owner_rx.try_recv()?;
+
+            // Only the owner can kick members.
+            add_message(
+                &world,
+                Message::RequestKickChannelMember {
+                    connection_global_world_id: member_id,
+                    account_id: member.account_id,
+                    user_id: member.id,
+                    packet: CKickChannelMember {
+                        channel_id,
+                        name: owner.name.clone(),
+                    },
+                },
+            );
+            world.run(private_channel_manager_system);
+            world.run(cleaner_system);
+            assert!(owner_rx.is_empty());
+            assert!(member_rx.is_empty());
+
+            add_message(
+                &world,
+                Message::RequestKickChannelMember {
+                    connection_global_world_id: owner_id,
+                    account_id: owner.account_id,
+                    user_id: owner.id,
+                    packet: CKickChannelMember {
+                        channel_id,
+                        name: member.name.to_uppercase(),
+                    },
+                },
+            );
+            world.run(private_channel_manager_system);
+
+            match &*member_rx.try_recv()? {
+                Message::ResponseLeavePrivateChannel { packet, .. } => {
+                    assert_eq!(packet.channel_id, channel_id);
+                }
+                _ => panic!("Message is not a ResponseLeavePrivateChannel message"),
+            }
+            match &*owner_rx.try_recv()? {
+                Message::ResponsePrivateChannelNotice { packet, .. } => {
+                    assert_eq!(packet.event, NOTICE_EVENT_KICKED);
+                }
+                _ => panic!("Message is not a ResponsePrivateChannelNotice message"),
+            }
+            assert!(get_channels(&world, member_id).channels.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_edit_private_channel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let owner = task::block_on(async { create_user(&pool, 0).await })?;
+            let member = task::block_on(async { create_user(&pool, 1).await })?;
+            let world = setup(pool.clone());
+            let (owner_id, owner_rx) = add_user(&world, &owner, true);
+            let (member_id, member_rx) = add_user(&world, &member, true);
+
+            create_channel(&world, owner_id, &owner, "Traders", 0);
+            let channel_id = assert_joined(owner_rx.try_recv()?, "Traders", 1, 1);
+            join_channel(&world, member_id, &member, "Traders", 0);
+            member_rx.try_recv()?;
+            owner_rx.try_recv()?;
+
+            add_message(
+                &world,
+                Message::RequestEditPrivateChannel {
+                    connection_global_world_id: owner_id,
+                    account_id: owner.account_id,
+                    user_id: owner.id,
+                    packet: CEditPrivateChannel {
+                        channel_id,
+                        name: "Crafters".to_string(),
+                        password: 4321,
+                    },
+                },
+            );
+            world.run(private_channel_manager_system);
+
+            assert_joined(owner_rx.try_recv()?, "Crafters", 1, 2);
+            assert_joined(member_rx.try_recv()?, "Crafters", 1, 2);
+
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                let channel = private_channel::get_by_id(&mut conn, channel_id).await?;
+                assert_eq!(channel.name, "Crafters");
+                assert_eq!(channel.password, Some(4321));
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            Ok(())
+        })
+    }
+}
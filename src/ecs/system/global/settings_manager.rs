@@ -38,14 +38,14 @@ fn handle_set_visible_range(
 ) {
     debug!("Message::RequestSetVisibleRange incoming");
 
-    // TODO The local world need to know of this values. Send this value once the user enters the local world.
+    // The local world receives the visibility range with the UserInitializer once the user spawns.
     if let Ok(mut settings) = (&mut settings).try_get(connection_global_world_id) {
         settings.visibility_range = packet.range;
     } else {
         let user_settings = Settings {
             visibility_range: packet.range,
         };
-        entities.add_entity(settings, user_settings);
+        entities.add_component(settings, user_settings, connection_global_world_id);
     }
 }
 
@@ -54,6 +54,8 @@ mod tests {
     use super::*;
     use crate::ecs::component::GlobalConnection;
     use crate::ecs::message::Message;
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
     use async_std::sync::{channel, Receiver};
     use std::time::Instant;
 
@@ -106,5 +108,39 @@ mod tests {
             .count();
 
         assert_eq!(valid_component_count, 1);
+
+        world.run(|settings: View<Settings>| {
+            let settings = settings.try_get(connection_global_world_id).unwrap();
+            assert_eq!(settings.visibility_range, 4234);
+        });
+    }
+
+    #[test]
+    fn test_update_visible_range() {
+        let (world, connection_global_world_id, _rx_channel) = setup_with_connection();
+        world.add_unique(DeletionList(Vec::default()));
+
+        for range in [4234, 2000].iter() {
+            world.run(
+                |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                    entities.add_entity(
+                        &mut messages,
+                        Box::new(Message::RequestSetVisibleRange {
+                            connection_global_world_id,
+                            account_id: -1,
+                            packet: CSetVisibleRange { range: *range },
+                        }),
+                    );
+                },
+            );
+            world.run(settings_manager_system);
+            world.run(cleaner_system);
+        }
+
+        assert_eq!(world.borrow::<View<Settings>>().iter().count(), 1);
+        world.run(|settings: View<Settings>| {
+            let settings = settings.try_get(connection_global_world_id).unwrap();
+            assert_eq!(settings.visibility_range, 2000);
+        });
     }
 }
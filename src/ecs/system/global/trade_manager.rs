@@ -0,0 +1,308 @@
+use crate::ecs::component::{BlockList, GlobalUserSpawn, UserSpawnStatus};
+use crate::ecs::message::Message::TradeRequest;
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::system::global::{find_online_user, is_blocked};
+use crate::ecs::system::send_message;
+use crate::model::repository::user;
+use crate::model::TRADE_CONTRACT;
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use shipyard::*;
+use sqlx::PgPool;
+use tracing::{debug, error, info_span};
+
+/// The trade manager forwards trade requests to the local world of the requesting user. Trades
+/// itself are handled by the local world, since both users need to be near each other and the
+/// local world holds their inventories.
+pub fn trade_manager_system(
+    incoming_messages: View<EcsMessage>,
+    spawns: View<GlobalUserSpawn>,
+    block_lists: View<BlockList>,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestContract {
+                connection_global_world_id,
+                user_id,
+                packet,
+                ..
+            } if packet.contract_type == TRADE_CONTRACT => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_trade_request(
+                    *connection_global_world_id,
+                    *user_id,
+                    &packet,
+                    &spawns,
+                    &block_lists,
+                    &pool,
+                ) {
+                    error!("Ignoring trade request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other packets */ }
+        });
+}
+
+fn handle_trade_request(
+    connection_global_world_id: EntityId,
+    user_id: i32,
+    packet: &CRequestContract,
+    spawns: &View<GlobalUserSpawn>,
+    block_lists: &View<BlockList>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestContract incoming");
+
+    let partner_id = task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let partner = user::get_by_name(&mut conn, &packet.name)
+            .await
+            .context(format!("Can't find user {}", packet.name))?;
+        ensure!(
+            partner.id != user_id,
+            "User {} can't trade with itself",
+            user_id
+        );
+
+        Ok::<i32, anyhow::Error>(partner.id)
+    })?;
+
+    let partner_connection_id = find_online_user(partner_id, spawns)
+        .context(format!("User {} is not online", partner_id))?;
+    ensure!(
+        !is_blocked(
+            connection_global_world_id,
+            user_id,
+            partner_connection_id,
+            partner_id,
+            block_lists
+        ),
+        "Trades between user {} and user {} are blocked",
+        user_id,
+        partner_id
+    );
+
+    let spawn = spawns.try_get(connection_global_world_id).context(format!(
+        "Can't find user spawn of {:?}",
+        connection_global_world_id
+    ))?;
+    let partner_spawn = &spawns[partner_connection_id];
+    ensure!(
+        spawn.status == UserSpawnStatus::Spawned
+            && partner_spawn.status == UserSpawnStatus::Spawned,
+        "User {} and user {} need to be spawned to trade",
+        user_id,
+        partner_id
+    );
+    ensure!(
+        spawn.local_world_id.is_some() && spawn.local_world_id == partner_spawn.local_world_id,
+        "User {} and user {} are not inside the same local world",
+        user_id,
+        partner_id
+    );
+
+    let connection_local_world_id = spawn
+        .connection_local_world_id
+        .context("User has no local world ID")?;
+    let partner_connection_local_world_id = partner_spawn
+        .connection_local_world_id
+        .context("Partner has no local world ID")?;
+    let local_world_channel = spawn
+        .local_world_channel
+        .as_ref()
+        .context("User has no local world channel")?;
+    send_message(
+        assemble_trade_request(connection_local_world_id, partner_connection_local_world_id),
+        local_world_channel,
+    );
+
+    Ok(())
+}
+
+fn assemble_trade_request(
+    connection_local_world_id: EntityId,
+    partner_connection_local_world_id: EntityId,
+) -> EcsMessage {
+    Box::new(TradeRequest {
+        connection_local_world_id,
+        partner_connection_local_world_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
+    use crate::model::entity::User;
+    use crate::model::repository::account;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::tests::db_test;
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver, Sender};
+
+    struct TestUser {
+        user: User,
+        connection_global_world_id: EntityId,
+        connection_local_world_id: EntityId,
+    }
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(pool);
+        world.add_unique(DeletionList(Vec::default()));
+        world
+    }
+
+    fn add_user(
+        world: &World,
+        pool: &PgPool,
+        num: i32,
+        local_world_id: EntityId,
+        local_world_channel: Sender<EcsMessage>,
+    ) -> Result<TestUser> {
+        let user = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let account = account::create(&mut conn, &get_default_account(num)).await?;
+            user::create(&mut conn, &get_default_user(&account, num)).await
+        })?;
+        let connection_local_world_id =
+            from_vec::<EntityId>(vec![num as u8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+
+        let connection_global_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut spawns: ViewMut<GlobalUserSpawn>,
+             mut block_lists: ViewMut<BlockList>| {
+                entities.add_entity(
+                    (&mut spawns, &mut block_lists),
+                    (
+                        GlobalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_local_world_id: Some(connection_local_world_id),
+                            local_world_id: Some(local_world_id),
+                            local_world_channel: Some(local_world_channel),
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
+                        },
+                        BlockList::default(),
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            user,
+            connection_global_world_id,
+            connection_local_world_id,
+        })
+    }
+
+    fn request_trade(world: &World, user: &TestUser, name: &str) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::RequestContract {
+                        connection_global_world_id: user.connection_global_world_id,
+                        account_id: user.user.account_id,
+                        user_id: user.user.id,
+                        packet: CRequestContract {
+                            name: name.to_string(),
+                            data: vec![],
+                            contract_type: TRADE_CONTRACT,
+                        },
+                    }),
+                );
+            },
+        );
+        world.run(trade_manager_system);
+        world.run(cleaner_system);
+    }
+
+    fn get_local_world_id(num: u8) -> Result<EntityId> {
+        Ok(from_vec::<EntityId>(vec![
+            num, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00,
+        ])?)
+    }
+
+    fn assert_trade_request(rx: &Receiver<EcsMessage>, user: &TestUser, partner: &TestUser) {
+        match &*rx.try_recv().unwrap() {
+            Message::TradeRequest {
+                connection_local_world_id,
+                partner_connection_local_world_id,
+            } => {
+                assert_eq!(*connection_local_world_id, user.connection_local_world_id);
+                assert_eq!(
+                    *partner_connection_local_world_id,
+                    partner.connection_local_world_id
+                );
+            }
+            _ => panic!("Message is not a TradeRequest message"),
+        }
+    }
+
+    #[test]
+    fn test_trade_request_is_forwarded() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let (tx, rx) = channel(1024);
+            let user = add_user(&world, &pool, 1, get_local_world_id(1)?, tx.clone())?;
+            let partner = add_user(&world, &pool, 2, get_local_world_id(1)?, tx)?;
+
+            request_trade(&world, &user, &partner.user.name);
+            assert_trade_request(&rx, &user, &partner);
+            assert!(rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_invalid_trade_request() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let (tx, rx) = channel(1024);
+            let (other_tx, other_rx) = channel(1024);
+            let user = add_user(&world, &pool, 1, get_local_world_id(1)?, tx.clone())?;
+            let partner = add_user(&world, &pool, 2, get_local_world_id(1)?, tx)?;
+            let other_user = add_user(&world, &pool, 3, get_local_world_id(2)?, other_tx)?;
+
+            // Users can't trade with themselves, unknown users or users of other local worlds
+            request_trade(&world, &user, &user.user.name);
+            request_trade(&world, &user, "Unknown");
+            request_trade(&world, &user, &other_user.user.name);
+            request_trade(&world, &other_user, &user.user.name);
+            assert!(rx.is_empty());
+            assert!(other_rx.is_empty());
+
+            // Users that blocked each other can't trade
+            world.run(|mut block_lists: ViewMut<BlockList>| {
+                (&mut block_lists)
+                    .try_get(partner.connection_global_world_id)
+                    .unwrap()
+                    .blocked_users
+                    .insert(user.user.id);
+            });
+            request_trade(&world, &user, &partner.user.name);
+            assert!(rx.is_empty());
+
+            Ok(())
+        })
+    }
+}
@@ -1,10 +1,15 @@
 use crate::ecs::component::GlobalConnection;
+use crate::ecs::dto::EquipmentLook;
 use crate::ecs::message::Message::ResponseGetUserList;
 use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::{GameData, ItemRegistry};
+use crate::ecs::system::global::guild_manager::get_guild_tag;
 use crate::ecs::system::global::send_message_to_connection;
-use crate::model::entity::{User, UserLocation};
-use crate::model::repository::{user, user_location};
-use crate::model::{Vec3a, Vec3f};
+use crate::model::entity::{EquippedItem, Inventory, User, UserLocation};
+use crate::model::repository::{equipped_item, inventory, user, user_location};
+use crate::model::{
+    accrue_rest_bonus_exp, level_stats, max_rest_bonus_exp, Vec3a, Vec3f, DEFAULT_INVENTORY_SIZE,
+};
 use crate::protocol::packet::*;
 use crate::Result;
 use anyhow::{ensure, Context};
@@ -26,6 +31,7 @@ pub fn user_manager_system(
     incoming_messages: View<EcsMessage>,
     connections: View<GlobalConnection>,
     pool: UniqueView<PgPool>,
+    game_data: UniqueView<GameData>,
 ) {
     (&incoming_messages)
         .iter()
@@ -70,12 +76,14 @@ pub fn user_manager_system(
                     *account_id,
                     &connections,
                     &pool,
+                    &game_data.items,
                 ) {
                     error!("Rejecting get user list request: {:?}", e);
                     send_message_to_connection(
                         assemble_user_list_response(
                             *connection_global_world_id,
                             &Vec::new(),
+                            &game_data.items,
                             true,
                             true,
                         ),
@@ -151,6 +159,7 @@ fn handle_user_list(
     account_id: i64,
     connections: &View<GlobalConnection>,
     pool: &UniqueView<PgPool>,
+    item_registry: &ItemRegistry,
 ) -> Result<()> {
     debug!("Get user list message incoming");
 
@@ -163,11 +172,22 @@ fn handle_user_list(
         // Send the user list paged, since we can only send 16kiB of data in one packet
         let mut is_first_page = true;
 
-        let users = user::list(&mut conn, account_id).await?;
+        let mut users = Vec::new();
+        for user in user::list(&mut conn, account_id).await? {
+            let (guild_name, _guild_rank) = get_guild_tag(&mut conn, user.id).await?;
+            let equipment = equipped_item::list_active_by_user_id(&mut conn, user.id).await?;
+            users.push((user, guild_name, equipment));
+        }
 
         if users.len() == 0 {
             send_message_to_connection(
-                assemble_user_list_response(connection_global_world_id, &Vec::new(), true, true),
+                assemble_user_list_response(
+                    connection_global_world_id,
+                    &Vec::new(),
+                    item_registry,
+                    true,
+                    true,
+                ),
                 connections,
             );
         } else {
@@ -185,6 +205,7 @@ fn handle_user_list(
                     assemble_user_list_response(
                         connection_global_world_id,
                         chunk,
+                        item_registry,
                         is_first_page,
                         is_last_page,
                     ),
@@ -451,7 +472,8 @@ async fn create_new_user(
             laurel: -1,
             achievement_points: 0,
             playtime: 0,
-            rest_bonus_xp: 419,
+            xp: 0,
+            rest_bonus_xp: 0,
             show_face: false,
             show_style: false,
             lobby_slot,
@@ -461,6 +483,7 @@ async fn create_new_user(
             delete_at: None,
             last_logout_at: Utc::now(),
             created_at: Utc::now(),
+            is_alive: true,
         },
     )
     .await
@@ -478,6 +501,18 @@ async fn create_new_user(
     .await
     .context("Can't create user location")?;
 
+    inventory::create(
+        &mut conn,
+        &Inventory {
+            user_id: user.id,
+            size: DEFAULT_INVENTORY_SIZE,
+            money: 0,
+            equipment_preset: 0,
+        },
+    )
+    .await
+    .context("Can't create inventory")?;
+
     Ok(())
 }
 
@@ -519,19 +554,23 @@ fn assemble_delete_user_response(connection_global_world_id: EntityId, ok: bool)
 
 fn assemble_user_list_response(
     connection_global_world_id: EntityId,
-    users: &[User],
+    users: &[(User, String, Vec<EquippedItem>)],
+    item_registry: &ItemRegistry,
     is_first_page: bool,
     is_last_page: bool,
 ) -> EcsMessage {
-    // TODO calculate hp/mp/max_rest_bonus/world_id/guard_id/section_id and also return the equip / styles / custom strings / guild / has_broker_sales from db
+    // TODO calculate world_id/guard_id/section_id and also return the custom strings / has_broker_sales from db
     let characters = users
         .into_iter()
         .cloned()
-        .map(move |user| {
+        .map(move |(user, guild_name, equipment)| {
             let delete_time = match user.delete_at {
                 Some(t) => t.timestamp(),
                 None => 0,
             };
+            let look = EquipmentLook::new(&equipment);
+            let mut stats = level_stats(user.level);
+            stats += item_registry.equipment_stats(&equipment);
 
             // FIXME Something is wrong with the custom_strings field! It needs to be set with zero values?!
             // FIXME test the deletion time stamps!
@@ -543,14 +582,14 @@ fn assemble_user_list_response(
                 name: user.name,
                 details: user.details,
                 shape: user.shape,
-                guild_name: "".to_string(),
+                guild_name,
                 db_id: user.id,
                 gender: user.gender,
                 race: user.race,
                 class: user.class,
                 level: user.level,
-                hp: 200,
-                mp: 100,
+                hp: stats.max_hp as i64,
+                mp: stats.max_mp,
                 world_id: 0,
                 guard_id: 0,
                 section_id: 0,
@@ -558,18 +597,18 @@ fn assemble_user_list_response(
                 is_deleting: user.is_deleting,
                 delete_time: 86400,
                 delete_remain_sec: min(delete_time - Utc::now().timestamp(), -1_585_902_611) as i32,
-                weapon: 0,
-                earring1: 0,
-                earring2: 0,
-                body: 0,
-                hand: 0,
-                feet: 0,
+                weapon: look.weapon,
+                earring1: look.earring1,
+                earring2: look.earring2,
+                body: look.body,
+                hand: look.hand,
+                feet: look.feet,
                 unk_item7: 0,
-                ring1: 0,
-                ring2: 0,
-                underwear: 0,
-                head: 0,
-                face: 0,
+                ring1: look.ring1,
+                ring2: look.ring2,
+                underwear: look.underwear,
+                head: look.head,
+                face: look.face,
                 appearance: user.appearance,
                 is_second_character: false,
                 admin_level: 0,
@@ -600,16 +639,20 @@ fn assemble_user_list_response(
                 style_back_dye: 0,
                 style_head_dye: 0,
                 style_face_dye: 0,
-                style_head: 0,
-                style_face: 0,
-                style_back: 0,
-                style_weapon: 0,
-                style_body: 0,
-                style_footprint: 0,
+                style_head: look.style_head,
+                style_face: look.style_face,
+                style_back: look.style_back,
+                style_weapon: look.style_weapon,
+                style_body: look.style_body,
+                style_footprint: look.style_footprint,
                 style_body_dye: 0,
                 weapon_enchant: 0,
-                rest_bonus_xp: user.rest_bonus_xp,
-                max_rest_bonus_xp: 1,
+                rest_bonus_xp: accrue_rest_bonus_exp(
+                    user.level,
+                    user.rest_bonus_xp,
+                    (Utc::now() - user.last_logout_at).num_seconds(),
+                ),
+                max_rest_bonus_xp: max_rest_bonus_exp(user.level),
                 show_face: user.show_face,
                 style_head_scale: 1.0,
                 style_head_rotation: Vec3a::default(),
@@ -660,10 +703,17 @@ mod tests {
     use super::*;
     use crate::ecs::component::GlobalConnection;
     use crate::ecs::message::Message;
+    use crate::ecs::resource::ItemTemplate;
     use crate::model::entity::Account;
     use crate::model::repository::account;
+    use crate::model::repository::equipped_item::tests::get_default_equipped_item;
+    use crate::model::repository::guild;
+    use crate::model::repository::guild::tests::{get_default_guild, get_default_member};
+    use crate::model::repository::inventory::tests::get_default_inventory;
     use crate::model::tests::db_test;
-    use crate::model::{Class, Customization, Gender, PasswordHashAlgorithm, Race};
+    use crate::model::{
+        Class, Customization, EquipmentSlot, Gender, PasswordHashAlgorithm, Race, Stats,
+    };
     use crate::Result;
     use async_std::sync::{channel, Receiver};
     use chrono::TimeZone;
@@ -677,6 +727,20 @@ mod tests {
 
         let world = World::new();
         world.add_unique(pool);
+        world.add_unique(GameData {
+            items: ItemRegistry::new(vec![ItemTemplate {
+                id: 10001,
+                max_stack: 1,
+                equipment_slots: vec![EquipmentSlot::Weapon],
+                stats: Stats {
+                    attack: 100,
+                    max_hp: 150,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }]),
+            ..Default::default()
+        });
 
         let account = account::create(
             &mut conn,
@@ -751,6 +815,7 @@ mod tests {
                 laurel: 0,
                 achievement_points: 0,
                 playtime: 0,
+                xp: 0,
                 rest_bonus_xp: 0,
                 show_face: false,
                 show_style: false,
@@ -761,6 +826,7 @@ mod tests {
                 delete_at: None,
                 last_logout_at: Utc.ymd(2007, 7, 8).and_hms(9, 10, 11),
                 created_at: Utc.ymd(2009, 7, 8).and_hms(9, 10, 11),
+                is_alive: true,
             },
         )
         .await?)
@@ -1012,6 +1078,108 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_get_user_list_with_guild() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let mut conn = task::block_on(async { pool.acquire().await })?;
+            let (world, connection_global_world_id, rx_channel, account) =
+                task::block_on(async { setup_with_connection(pool).await })?;
+
+            task::block_on(async {
+                let user = create_user(&mut conn, account.id, 0).await?;
+                let guild = guild::create(&mut conn, &get_default_guild(&user)).await?;
+                guild::add_member(&mut conn, &get_default_member(&guild, &user)).await?;
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            world.run(
+                |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                    entities.add_entity(
+                        &mut messages,
+                        Box::new(Message::RequestGetUserList {
+                            connection_global_world_id,
+                            account_id: account.id,
+                            packet: CGetUserList {},
+                        }),
+                    );
+                },
+            );
+
+            world.run(user_manager_system);
+
+            match &*rx_channel.try_recv()? {
+                Message::ResponseGetUserList { packet, .. } => {
+                    assert_eq!(packet.characters.len(), 1);
+                    assert_eq!(packet.characters[0].guild_name, "Manhunter");
+                }
+                _ => panic!("Message is not a ResponseGetUserList message"),
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_get_user_list_with_equipment() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let mut conn = task::block_on(async { pool.acquire().await })?;
+            let (world, connection_global_world_id, rx_channel, account) =
+                task::block_on(async { setup_with_connection(pool).await })?;
+
+            task::block_on(async {
+                let user = create_user(&mut conn, account.id, 0).await?;
+                inventory::create(&mut conn, &get_default_inventory(&user)).await?;
+                equipped_item::create(
+                    &mut conn,
+                    &get_default_equipped_item(&user, 100, 0, EquipmentSlot::Weapon),
+                )
+                .await?;
+                let mut style = get_default_equipped_item(&user, 101, 0, EquipmentSlot::StyleBody);
+                style.template_id = 98000;
+                equipped_item::create(&mut conn, &style).await?;
+                // Items of other presets are not shown
+                equipped_item::create(
+                    &mut conn,
+                    &get_default_equipped_item(&user, 102, 1, EquipmentSlot::Body),
+                )
+                .await?;
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            world.run(
+                |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                    entities.add_entity(
+                        &mut messages,
+                        Box::new(Message::RequestGetUserList {
+                            connection_global_world_id,
+                            account_id: account.id,
+                            packet: CGetUserList {},
+                        }),
+                    );
+                },
+            );
+
+            world.run(user_manager_system);
+
+            match &*rx_channel.try_recv()? {
+                Message::ResponseGetUserList { packet, .. } => {
+                    assert_eq!(packet.characters.len(), 1);
+                    let character = &packet.characters[0];
+                    assert_eq!(character.weapon, 10001);
+                    assert_eq!(character.body, 0);
+                    assert_eq!(character.style_body, 98000);
+                    assert_eq!(character.hp, 350);
+                    assert_eq!(character.mp, 100);
+                }
+                _ => panic!("Message is not a ResponseGetUserList message"),
+            }
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_get_empty_user_list() -> Result<()> {
         db_test(|db_string| {
@@ -1113,6 +1281,11 @@ mod tests {
             assert_eq!(user_location.user_id, user_id);
             assert_eq!(user_location.zone_id, 5);
 
+            let inventory =
+                task::block_on(async { inventory::get_by_user_id(&mut conn, user_id).await })?;
+            assert_eq!(inventory.size, DEFAULT_INVENTORY_SIZE);
+            assert_eq!(inventory.money, 0);
+
             Ok(())
         })
     }
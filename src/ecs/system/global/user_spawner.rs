@@ -1,31 +1,49 @@
-use crate::ecs::component::{GlobalConnection, GlobalUserSpawn, UserSpawnStatus};
-use crate::ecs::dto::{UserFinalizer, UserInitializer};
+use crate::ecs::component::{
+    BlockList, Chatter, GlobalConnection, GlobalUserSpawn, LocalWorldType, Settings,
+    UserSpawnStatus,
+};
+use crate::ecs::dto::{EquipmentLook, UserFinalizer, UserInitializer};
 use crate::ecs::message::Message::{
-    PrepareUserSpawn, RegisterLocalWorld, ResponseLoadHint, ResponseLoadTopo, ResponseLogin,
-    UserReadyToConnect,
+    PrepareUserSpawn, RegisterLocalWorld, ResponseCurrentChannel, ResponseLoadHint,
+    ResponseLoadTopo, ResponseLogin, ResponseUserBlockList, UserReadyToConnect,
 };
 use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::{GameData, ZoneRegistry};
+use crate::ecs::system::global::guild_manager::get_guild_tag;
 use crate::ecs::system::global::send_message_to_connection;
 use crate::ecs::system::send_message;
 use crate::model::entity::UserLocation;
-use crate::model::repository::{user, user_location};
-use crate::model::{entity, TemplateID, Vec3f};
+use crate::model::repository::{
+    blocked_user, equipped_item, inventory, item, user, user_abnormality, user_location,
+};
+use crate::model::{
+    accrue_rest_bonus_exp, entity, level_exp, max_rest_bonus_exp, total_exp, TemplateID, Vec3f,
+};
 use crate::protocol::packet::*;
 use crate::Result;
 use anyhow::{bail, ensure, Context};
 use async_std::sync::Sender;
 use async_std::task;
+use chrono::Utc;
 use shipyard::*;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
+use std::time::Instant;
 use tracing::{debug, error, info_span};
 
+/// Visibility range of an user that didn't send it's settings yet.
+const DEFAULT_VISIBILITY_RANGE: u32 = 4000;
+
 /// Handles the global spawn process.
 pub fn user_spawner_system(
     incoming_messages: View<EcsMessage>,
     connections: View<GlobalConnection>,
+    settings: View<Settings>,
     mut spawns: ViewMut<GlobalUserSpawn>,
+    mut chatters: ViewMut<Chatter>,
+    mut block_lists: ViewMut<BlockList>,
     entities: EntitiesView,
     pool: UniqueView<PgPool>,
+    game_data: UniqueView<GameData>,
 ) {
     (&incoming_messages)
         .iter()
@@ -41,6 +59,8 @@ pub fn user_spawner_system(
                     *connection_global_world_id,
                     *account_id,
                     &mut spawns,
+                    &mut chatters,
+                    &mut block_lists,
                     &entities,
                     &pool,
                 ) {
@@ -58,6 +78,7 @@ pub fn user_spawner_system(
                     &mut spawns,
                     &connections,
                     &pool,
+                    &game_data,
                 ) {
                     error!("Ignoring user spawn prepared message: {:?}", e);
                 }
@@ -73,7 +94,7 @@ pub fn user_spawner_system(
             Message::UserDespawned { user_finalizer } => {
                 let connection_global_world_id = user_finalizer.connection_global_world_id;
                 id_span!(connection_global_world_id);
-                if let Err(e) = handle_user_despawned(&user_finalizer, &pool) {
+                if let Err(e) = handle_user_despawned(&user_finalizer, &mut spawns, &pool) {
                     error!("Ignoring user de-spawned message: {:?}", e);
                 }
             }
@@ -85,9 +106,14 @@ pub fn user_spawner_system(
     }) {
         if spawn.status == UserSpawnStatus::CanSpawn {
             id_span!(connection_global_world_id);
-            if let Err(e) =
-                prepare_local_spawn(spawn, connection_global_world_id, &connections, &pool)
-            {
+            if let Err(e) = prepare_local_spawn(
+                spawn,
+                connection_global_world_id,
+                &connections,
+                &settings,
+                &pool,
+                &game_data,
+            ) {
                 error!("Can't prepare local spawn: {:?}", e);
             }
         } else if spawn.status == UserSpawnStatus::SpawnFailed {
@@ -103,7 +129,9 @@ fn prepare_local_spawn(
     spawn: &GlobalUserSpawn,
     connection_global_world_id: EntityId,
     connections: &View<GlobalConnection>,
+    settings: &View<Settings>,
     pool: &UniqueView<PgPool>,
+    game_data: &UniqueView<GameData>,
 ) -> Result<()> {
     ensure!(
         spawn.local_world_channel.is_some(),
@@ -113,6 +141,11 @@ fn prepare_local_spawn(
     let connection = connections
         .try_get(connection_global_world_id)
         .context("Can't find connection component")?;
+    let visibility_range = settings
+        .try_get(connection_global_world_id)
+        .map_or(DEFAULT_VISIBILITY_RANGE, |settings| {
+            settings.visibility_range
+        });
 
     Ok(task::block_on(async {
         let mut conn = pool
@@ -120,14 +153,30 @@ fn prepare_local_spawn(
             .await
             .context("Couldn't acquire connection from pool")?;
 
-        let user = user::get_by_id(&mut conn, spawn.user_id).await?;
+        let mut user = user::get_by_id(&mut conn, spawn.user_id).await?;
+        // Users that only change their local world weren't logged out.
+        if !spawn.is_relocating {
+            user.rest_bonus_xp = accrued_rest_bonus_exp(&user);
+        }
         let location = user_location::get_by_user_id(&mut conn, spawn.user_id).await?;
+        let location = resolve_spawn_location(location, spawn.zone_id, &game_data.zones);
+        let guild_tag = get_guild_tag(&mut conn, spawn.user_id).await?;
+        let inventory = inventory::get_by_user_id(&mut conn, spawn.user_id).await?;
+        let items = item::list_by_user_id(&mut conn, spawn.user_id).await?;
+        let equipment = equipped_item::list_active_by_user_id(&mut conn, spawn.user_id).await?;
+        let abnormalities = user_abnormality::list_by_user_id(&mut conn, spawn.user_id).await?;
         send_message(
             assemble_prepare_user_spawn(
                 connection_global_world_id,
                 connection.channel.clone(),
                 user,
                 location,
+                visibility_range,
+                guild_tag,
+                inventory,
+                items,
+                equipment,
+                abnormalities,
             ),
             &spawn.local_world_channel.clone().unwrap(),
         );
@@ -147,27 +196,75 @@ fn handle_user_spawned(
         connection_global_world_id
     ))?;
     spawn.status = UserSpawnStatus::Spawned;
+    spawn.is_relocating = false;
 
     Ok(())
 }
 
-fn handle_user_despawned(user_finalizer: &UserFinalizer, pool: &UniqueView<PgPool>) -> Result<()> {
+fn handle_user_despawned(
+    user_finalizer: &UserFinalizer,
+    spawns: &mut ViewMut<GlobalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
     debug!("Message::UserDespawned incoming");
 
-    Ok(task::block_on(async {
+    task::block_on(async {
         let mut conn = pool
-            .acquire()
+            .begin()
             .await
             .context("Couldn't acquire connection from pool")?;
 
         user_location::update(&mut conn, &user_finalizer.location)
             .await
             .context("Can't update UserLocation")?;
+        user::update_is_alive(&mut conn, user_finalizer.user_id, user_finalizer.is_alive)
+            .await
+            .context("Can't update the is_alive status of the user")?;
+        if let Some(progress) = &user_finalizer.progress {
+            user::update_progress(
+                &mut conn,
+                user_finalizer.user_id,
+                progress.level,
+                progress.xp,
+                progress.rest_bonus_xp,
+                progress.playtime,
+            )
+            .await
+            .context("Can't update the progress of the user")?;
+        }
+        user::update_last_logout_at(&mut conn, user_finalizer.user_id, Utc::now())
+            .await
+            .context("Can't update the last logout time of the user")?;
+
+        // Only the abnormalities of the latest logout are kept.
+        user_abnormality::delete_by_user_id(&mut conn, user_finalizer.user_id)
+            .await
+            .context("Can't delete the abnormalities of the user")?;
+        for abnormality in &user_finalizer.abnormalities {
+            user_abnormality::create(&mut conn, abnormality)
+                .await
+                .context(format!(
+                    "Can't store abnormality {}",
+                    abnormality.abnormality_id
+                ))?;
+        }
 
-        debug!("UserLocation persisted.");
+        conn.commit().await?;
+
+        debug!("UserLocation, is_alive status, progress and abnormalities persisted.");
 
         Ok::<(), anyhow::Error>(())
-    })?)
+    })?;
+
+    // Users that change their local world can request their new spawn once their data is persisted.
+    if let Ok(mut spawn) = spawns.try_get(user_finalizer.connection_global_world_id) {
+        spawn.is_alive = user_finalizer.is_alive;
+        if spawn.status == UserSpawnStatus::Despawning {
+            spawn.status = UserSpawnStatus::Requesting;
+        }
+    }
+
+    Ok(())
 }
 
 fn handle_select_user(
@@ -175,6 +272,8 @@ fn handle_select_user(
     connection_global_world_id: EntityId,
     account_id: i64,
     spawns: &mut ViewMut<GlobalUserSpawn>,
+    chatters: &mut ViewMut<Chatter>,
+    block_lists: &mut ViewMut<BlockList>,
     entities: &EntitiesView,
     pool: &UniqueView<PgPool>,
 ) -> Result<()> {
@@ -199,20 +298,36 @@ fn handle_select_user(
         }
 
         let location = user_location::get_by_user_id(&mut conn, user.id).await?;
+        let blocked_users = blocked_user::list(&mut conn, user.id).await?;
 
         entities.add_component(
-            spawns,
-            GlobalUserSpawn {
-                connection_local_world_id: None,
-                user_id: user.id,
-                account_id,
-                status: UserSpawnStatus::Requesting,
-                zone_id: location.zone_id,
-                local_world_id: None,
-                local_world_channel: None,
-                marked_for_deletion: false,
-                is_alive: true,
-            },
+            (spawns, chatters, block_lists),
+            (
+                GlobalUserSpawn {
+                    connection_local_world_id: None,
+                    user_id: user.id,
+                    account_id,
+                    status: UserSpawnStatus::Requesting,
+                    zone_id: location.zone_id,
+                    local_world_id: None,
+                    local_world_channel: None,
+                    marked_for_deletion: false,
+                    is_alive: user.is_alive,
+                    channel_num: None,
+                    is_relocating: false,
+                },
+                Chatter {
+                    user_name: user.name,
+                    window_start: Instant::now(),
+                    message_count: 0,
+                },
+                BlockList {
+                    blocked_users: blocked_users
+                        .iter()
+                        .map(|entry| entry.blocked_user_id)
+                        .collect(),
+                },
+            ),
             connection_global_world_id,
         );
 
@@ -226,6 +341,7 @@ fn handle_user_spawn_prepared(
     spawns: &mut ViewMut<GlobalUserSpawn>,
     connections: &View<GlobalConnection>,
     pool: &UniqueView<PgPool>,
+    game_data: &UniqueView<GameData>,
 ) -> Result<()> {
     debug!("Message::UserSpawnPrepared incoming");
 
@@ -258,7 +374,7 @@ fn handle_user_spawn_prepared(
             .await
             .context("Couldn't acquire connection from pool")?;
 
-        let user = user::get_by_id(&mut conn, spawn.user_id)
+        let mut user = user::get_by_id(&mut conn, spawn.user_id)
             .await
             .context(format!("Can't query user {}", spawn.user_id))?;
 
@@ -268,16 +384,47 @@ fn handle_user_spawn_prepared(
                 "Can't query user location for user {}",
                 spawn.user_id
             ))?;
+        let location = resolve_spawn_location(location, spawn.zone_id, &game_data.zones);
+
+        // Users that only change their local world are already logged in
+        if !spawn.is_relocating {
+            let equipment = equipped_item::list_active_by_user_id(&mut conn, spawn.user_id)
+                .await
+                .context(format!(
+                    "Can't query the equipment of user {}",
+                    spawn.user_id
+                ))?;
+            user.rest_bonus_xp = accrued_rest_bonus_exp(&user);
+            send_message_to_connection(
+                assemble_response_login(connection_global_world_id, user, &equipment),
+                connections,
+            );
+            send_message_to_connection(
+                assemble_response_user_block_list(
+                    &mut conn,
+                    connection_global_world_id,
+                    spawn.user_id,
+                )
+                .await?,
+                connections,
+            );
+        }
 
-        send_message_to_connection(
-            assemble_response_login(connection_global_world_id, user),
-            connections,
-        );
+        if let Some(channel_num) = spawn.channel_num {
+            send_message_to_connection(
+                assemble_response_current_channel(
+                    connection_global_world_id,
+                    location.zone_id,
+                    channel_num,
+                ),
+                connections,
+            );
+        }
 
         // TODO Send all other persisted date
 
         send_message_to_connection(
-            assemble_response_load_topo(connection_global_world_id, &location),
+            assemble_response_load_topo(connection_global_world_id, &location, &game_data.zones),
             connections,
         );
         send_message_to_connection(
@@ -295,6 +442,38 @@ fn handle_user_spawn_prepared(
     })?)
 }
 
+/// Returns the rest bonus of an user including the rest bonus it collected since it's last logout.
+/// The collected rest bonus is persisted once the user logs out again.
+fn accrued_rest_bonus_exp(user: &entity::User) -> i64 {
+    accrue_rest_bonus_exp(
+        user.level,
+        user.rest_bonus_xp,
+        (Utc::now() - user.last_logout_at).num_seconds(),
+    )
+}
+
+/// Returns the location the user will be spawned at in the given zone. Users can't log back into
+/// an instanced zone at their last position and users that change their zone (e.g. when entering
+/// a dungeon) have no position in the new zone, so they are spawned at the first spawn point of
+/// the zone.
+fn resolve_spawn_location(
+    mut location: UserLocation,
+    zone_id: i32,
+    zone_registry: &ZoneRegistry,
+) -> UserLocation {
+    let is_zone_change = location.zone_id != zone_id;
+    location.zone_id = zone_id;
+    if let Some(zone) = zone_registry.get(zone_id) {
+        if zone.zone_type != LocalWorldType::Field || is_zone_change {
+            if let Some(spawn_point) = zone.spawn_points.first() {
+                location.point = spawn_point.point;
+                location.rotation = spawn_point.rotation;
+            }
+        }
+    }
+    location
+}
+
 fn assemble_register_local_world(
     connection_local_world_id: EntityId,
     local_world_channel: Sender<EcsMessage>,
@@ -305,7 +484,35 @@ fn assemble_register_local_world(
     })
 }
 
-fn assemble_response_login(connection_global_world_id: EntityId, user: entity::User) -> EcsMessage {
+async fn assemble_response_user_block_list(
+    conn: &mut PgConnection,
+    connection_global_world_id: EntityId,
+    user_id: i32,
+) -> Result<EcsMessage> {
+    let blocked_users = blocked_user::list_entries(conn, user_id)
+        .await?
+        .into_iter()
+        .map(|entry| SUserBlockListEntry {
+            name: entry.name,
+            memo: entry.memo,
+            user_id: entry.blocked_user_id,
+            level: entry.level,
+            class: entry.class,
+        })
+        .collect();
+
+    Ok(Box::new(ResponseUserBlockList {
+        connection_global_world_id,
+        packet: SUserBlockList { blocked_users },
+    }))
+}
+
+fn assemble_response_login(
+    connection_global_world_id: EntityId,
+    user: entity::User,
+    equipment: &[entity::EquippedItem],
+) -> EcsMessage {
+    let look = EquipmentLook::new(equipment);
     Box::new(ResponseLogin {
         connection_global_world_id,
         account_id: user.account_id,
@@ -331,7 +538,7 @@ fn assemble_response_login(connection_global_world_id: EntityId, user: entity::U
             appearance: user.appearance,
             visible: true,
             is_second_character: false,
-            level: 1,
+            level: user.level as i16,
             awakening_level: 0,
             profession_mineral: 0,
             profession_bug: 0,
@@ -340,23 +547,23 @@ fn assemble_response_login(connection_global_world_id: EntityId, user: entity::U
             profession_pet: 0,
             pvp_declared_count: 0,
             pvp_kill_count: 0,
-            total_exp: 0,
-            level_exp: 0,
-            total_level_exp: 0,
+            total_exp: total_exp(user.level, user.xp),
+            level_exp: user.xp,
+            total_level_exp: level_exp(user.level),
             ep_level: 0,
             ep_exp: 0,
             ep_daily_exp: 0,
-            rest_bonus_exp: 0,
-            max_rest_bonus_exp: 0,
+            rest_bonus_exp: user.rest_bonus_xp,
+            max_rest_bonus_exp: max_rest_bonus_exp(user.level),
             exp_bonus_percent: 1.0,
             drop_bonus_percent: 0.0,
-            weapon: 0,
-            body: 0,
-            hand: 0,
-            feet: 0,
-            underwear: 0,
-            head: 0,
-            face: 0,
+            weapon: look.weapon,
+            body: look.body,
+            hand: look.hand,
+            feet: look.feet,
+            underwear: look.underwear,
+            head: look.head,
+            face: look.face,
             server_time: 37990571,
             is_pvp_server: true,
             chat_ban_end_time: 0,
@@ -376,15 +583,15 @@ fn assemble_response_login(connection_global_world_id: EntityId, user: entity::U
             weapon_enchant: 0,
             is_world_event_target: false,
             infamy: 0,
-            show_face: true,
-            style_head: 0,
-            style_face: 0,
-            style_back: 0,
-            style_weapon: 0,
-            style_body: 0,
-            style_footprint: 0,
+            show_face: user.show_face,
+            style_head: look.style_head,
+            style_face: look.style_face,
+            style_back: look.style_back,
+            style_weapon: look.style_weapon,
+            style_body: look.style_body,
+            style_footprint: look.style_footprint,
             style_body_dye: 0,
-            show_style: true,
+            show_style: user.show_style,
             title_count: 0,
             appearance2: user.appearance2,
             scale: 1.0,
@@ -396,11 +603,16 @@ fn assemble_response_login(connection_global_world_id: EntityId, user: entity::U
 fn assemble_response_load_topo(
     connection_global_world_id: EntityId,
     user_location: &UserLocation,
+    zone_registry: &ZoneRegistry,
 ) -> EcsMessage {
+    let zone = zone_registry
+        .get(user_location.zone_id)
+        .map_or(user_location.zone_id, |zone| zone.topology_id);
+
     Box::new(ResponseLoadTopo {
         connection_global_world_id,
         packet: SLoadTopo {
-            zone: user_location.zone_id,
+            zone,
             location: Vec3f {
                 x: user_location.point.x,
                 y: user_location.point.y,
@@ -411,6 +623,21 @@ fn assemble_response_load_topo(
     })
 }
 
+fn assemble_response_current_channel(
+    connection_global_world_id: EntityId,
+    zone_id: i32,
+    channel_num: i32,
+) -> EcsMessage {
+    Box::new(ResponseCurrentChannel {
+        connection_global_world_id,
+        packet: SCurrentChannel {
+            server_id: 1,
+            zone: zone_id,
+            channel: channel_num,
+        },
+    })
+}
+
 fn assemble_response_load_hint(connection_global_world_id: EntityId) -> EcsMessage {
     Box::new(ResponseLoadHint {
         connection_global_world_id,
@@ -424,20 +651,33 @@ fn assemble_user_ready_to_connect(connection_local_world_id: EntityId) -> EcsMes
     })
 }
 
-// TODO we somehow need to track the "is_alive" status between spawns
 fn assemble_prepare_user_spawn(
     connection_global_world_id: EntityId,
     connection_channel: Sender<EcsMessage>,
     user: entity::User,
     location: entity::UserLocation,
+    visibility_range: u32,
+    (guild_name, guild_rank): (String, String),
+    inventory: entity::Inventory,
+    items: Vec<entity::Item>,
+    equipment: Vec<entity::EquippedItem>,
+    abnormalities: Vec<entity::UserAbnormality>,
 ) -> EcsMessage {
+    let is_alive = user.is_alive;
     Box::new(PrepareUserSpawn {
         user_initializer: UserInitializer {
             connection_global_world_id,
             connection_channel,
             user,
             location,
-            is_alive: true,
+            is_alive,
+            visibility_range,
+            guild_name,
+            guild_rank,
+            inventory,
+            items,
+            equipment,
+            abnormalities,
         },
     })
 }
@@ -446,11 +686,15 @@ fn assemble_prepare_user_spawn(
 mod tests {
     use super::*;
     use crate::ecs::component::GlobalConnection;
+    use crate::ecs::dto::UserProgress;
     use crate::ecs::message::Message;
-    use crate::model::entity::{Account, User, UserLocation};
+    use crate::ecs::resource::{SpawnPoint, Zone};
+    use crate::model::entity::{
+        Account, EquippedItem, Inventory, Item, User, UserAbnormality, UserLocation,
+    };
     use crate::model::repository::{account, user};
     use crate::model::tests::db_test;
-    use crate::model::{Class, Gender, PasswordHashAlgorithm, Race};
+    use crate::model::{Class, EquipmentSlot, Gender, PasswordHashAlgorithm, Race};
     use crate::protocol::serde::from_vec;
     use crate::Result;
     use async_std::sync::{channel, Receiver};
@@ -473,6 +717,7 @@ mod tests {
 
         let world = World::new();
         world.add_unique(pool.clone());
+        world.add_unique(GameData::default());
 
         let account = account::create(
             &mut conn,
@@ -505,6 +750,7 @@ mod tests {
                 laurel: 0,
                 achievement_points: 0,
                 playtime: 0,
+                xp: 0,
                 rest_bonus_xp: 0,
                 show_face: false,
                 show_style: false,
@@ -515,6 +761,7 @@ mod tests {
                 delete_at: None,
                 last_logout_at: Utc.ymd(2007, 7, 8).and_hms(9, 10, 11),
                 created_at: Utc.ymd(2009, 7, 8).and_hms(9, 10, 11),
+                is_alive: true,
             },
         )
         .await?;
@@ -530,6 +777,43 @@ mod tests {
         )
         .await?;
 
+        inventory::create(
+            &mut conn,
+            &Inventory {
+                user_id: user.id,
+                size: 40,
+                money: 1000,
+                equipment_preset: 0,
+            },
+        )
+        .await?;
+
+        item::create(
+            &mut conn,
+            &Item {
+                id: -1,
+                user_id: user.id,
+                template_id: 8005,
+                slot: 3,
+                amount: 20,
+                created_at: Utc::now(),
+            },
+        )
+        .await?;
+
+        equipped_item::create(
+            &mut conn,
+            &EquippedItem {
+                id: 100,
+                user_id: user.id,
+                preset: 0,
+                slot: EquipmentSlot::Weapon,
+                template_id: 10001,
+                created_at: Utc::now(),
+            },
+        )
+        .await?;
+
         let (tx_channel, rx_channel) = channel(1024);
 
         let connection_global_world_id = world.run(
@@ -562,6 +846,7 @@ mod tests {
     ) -> Result<(World, EntityId, Receiver<EcsMessage>)> {
         let world = World::new();
         world.add_unique(pool);
+        world.add_unique(GameData::default());
 
         let (tx_channel, rx_channel) = channel(1024);
 
@@ -608,8 +893,8 @@ mod tests {
 
             world.run(user_spawner_system);
 
-            world.run(|spawns: View<GlobalUserSpawn>| {
-                let spawn = spawns.try_get(connection_global_world_id)?;
+            world.run(|spawns: View<GlobalUserSpawn>, chatters: View<Chatter>| {
+                let (spawn, chatter) = (&spawns, &chatters).try_get(connection_global_world_id)?;
                 assert_eq!(spawn.account_id, account.id);
                 assert_eq!(spawn.user_id, user.id);
                 assert_eq!(spawn.zone_id, 0);
@@ -618,6 +903,15 @@ mod tests {
                 assert_eq!(spawn.is_alive, true);
                 assert_eq!(spawn.local_world_id, None);
                 assert_eq!(spawn.connection_local_world_id, None);
+                assert_eq!(chatter.user_name, user.name);
+                assert_eq!(chatter.message_count, 0);
+
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            world.run(|block_lists: View<BlockList>| {
+                let block_list = block_lists.try_get(connection_global_world_id)?;
+                assert!(block_list.blocked_users.is_empty());
 
                 Ok::<(), anyhow::Error>(())
             })?;
@@ -652,6 +946,8 @@ mod tests {
                             local_world_channel: Some(local_world_tx),
                             marked_for_deletion: false,
                             is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
                         },
                         connection_global_world_id,
                     );
@@ -709,10 +1005,27 @@ mod tests {
                     assert_eq!(*account_id, account.id);
                     assert_eq!(packet.id, connection_global_world_id);
                     assert!(packet.alive);
+                    assert_eq!(packet.weapon, 10001);
+                    assert_eq!(packet.body, 0);
+                    assert_eq!(packet.level, 0);
+                    assert_eq!(packet.total_exp, 0);
+                    assert_eq!(packet.total_level_exp, level_exp(1));
+                    assert_eq!(packet.max_rest_bonus_exp, max_rest_bonus_exp(1));
                 }
                 _ => panic!("Message is not a ResponseLogin message"),
             }
 
+            match &*rx_channel.try_recv()? {
+                Message::ResponseUserBlockList {
+                    connection_global_world_id: id,
+                    packet,
+                } => {
+                    assert_eq!(*id, connection_global_world_id);
+                    assert!(packet.blocked_users.is_empty());
+                }
+                _ => panic!("Message is not a ResponseUserBlockList message"),
+            }
+
             match &*rx_channel.try_recv()? {
                 Message::ResponseLoadTopo {
                     connection_global_world_id: id,
@@ -752,6 +1065,80 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_request_user_spawn_prepared_relocating() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let (world, connection_global_world_id, rx_channel, account, user, location) =
+                task::block_on(async { setup(&pool).await })?;
+
+            // FIXME Ask upstream project to create a better way to create EntityIds
+            let local_world_id =
+                from_vec::<EntityId>(vec![0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+            let (local_world_tx, _local_world_rx) = channel(100);
+
+            world.run(
+                |entities: EntitiesViewMut, mut spawns: ViewMut<GlobalUserSpawn>| {
+                    entities.add_component(
+                        &mut spawns,
+                        GlobalUserSpawn {
+                            connection_local_world_id: None,
+                            user_id: user.id,
+                            account_id: account.id,
+                            status: UserSpawnStatus::CanSpawn,
+                            zone_id: location.zone_id,
+                            local_world_id: Some(local_world_id),
+                            local_world_channel: Some(local_world_tx),
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: Some(2),
+                            is_relocating: true,
+                        },
+                        connection_global_world_id,
+                    );
+                },
+            );
+
+            let connection_local_world_id =
+                from_vec::<EntityId>(vec![0x11, 0x00, 0x1D, 0x0, 0x0, 0x80, 0, 0])?;
+
+            world.run(
+                |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                    entities.add_entity(
+                        &mut messages,
+                        Box::new(Message::UserSpawnPrepared {
+                            connection_global_world_id,
+                            connection_local_world_id,
+                        }),
+                    )
+                },
+            );
+
+            world.run(user_spawner_system);
+
+            match &*rx_channel.try_recv()? {
+                Message::RegisterLocalWorld { .. } => {}
+                _ => panic!("Message is not a RegisterLocalWorld message"),
+            }
+
+            // The user is already logged in, so no ResponseLogin is send
+            match &*rx_channel.try_recv()? {
+                Message::ResponseCurrentChannel { packet, .. } => {
+                    assert_eq!(packet.zone, location.zone_id);
+                    assert_eq!(packet.channel, 2);
+                }
+                _ => panic!("Message is not a ResponseCurrentChannel message"),
+            }
+
+            match &*rx_channel.try_recv()? {
+                Message::ResponseLoadTopo { .. } => {}
+                _ => panic!("Message is not a ResponseLoadTopo message"),
+            }
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_user_spawned() -> Result<()> {
         db_test(|db_string| {
@@ -773,6 +1160,8 @@ mod tests {
                             local_world_channel: None,
                             marked_for_deletion: false,
                             is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
                         },
                         connection_global_world_id,
                     );
@@ -814,6 +1203,15 @@ mod tests {
 
             let point = Point3::new(15.0f32, 20.0f32, 25.0f32);
             let rotation = Rotation3::from_axis_angle(&Vector3::z_axis(), 0.5);
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                user::update_last_logout_at(
+                    &mut conn,
+                    user.id,
+                    Utc.ymd(1995, 7, 8).and_hms(9, 10, 11),
+                )
+                .await
+            })?;
 
             world.run(
                 |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
@@ -830,6 +1228,18 @@ mod tests {
                                     rotation: rotation.clone(),
                                 },
                                 is_alive: false,
+                                abnormalities: vec![UserAbnormality {
+                                    user_id: user.id,
+                                    abnormality_id: 4000,
+                                    stacks: 2,
+                                    remaining: 30_000,
+                                }],
+                                progress: Some(UserProgress {
+                                    level: 12,
+                                    xp: 300,
+                                    rest_bonus_xp: 400,
+                                    playtime: 3600,
+                                }),
                             },
                         }),
                     );
@@ -844,6 +1254,82 @@ mod tests {
                 assert_eq!(user_location.point, point);
                 assert_eq!(user_location.rotation, rotation);
 
+                // Users that logged out while being dead need to be revived after the next login
+                let db_user = user::get_by_id(&mut conn, user.id).await?;
+                assert!(!db_user.is_alive);
+                assert_eq!(db_user.level, 12);
+                assert_eq!(db_user.xp, 300);
+                assert_eq!(db_user.rest_bonus_xp, 400);
+                assert_eq!(db_user.playtime, 3600);
+                assert!(db_user.last_logout_at > Utc.ymd(2020, 1, 1).and_hms(0, 0, 0));
+
+                let abnormalities = user_abnormality::list_by_user_id(&mut conn, user.id).await?;
+                assert_eq!(abnormalities.len(), 1);
+                assert_eq!(abnormalities[0].abnormality_id, 4000);
+                assert_eq!(abnormalities[0].stacks, 2);
+                assert_eq!(abnormalities[0].remaining, 30_000);
+
+                Ok::<(), anyhow::Error>(())
+            })?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_user_despawned_relocating() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let (world, connection_global_world_id, _rx_channel, account, user, location) =
+                task::block_on(async { setup(&pool).await })?;
+
+            world.run(
+                |entities: EntitiesViewMut, mut spawns: ViewMut<GlobalUserSpawn>| {
+                    entities.add_component(
+                        &mut spawns,
+                        GlobalUserSpawn {
+                            connection_local_world_id: None,
+                            user_id: user.id,
+                            account_id: account.id,
+                            status: UserSpawnStatus::Despawning,
+                            zone_id: location.zone_id,
+                            local_world_id: None,
+                            local_world_channel: None,
+                            marked_for_deletion: false,
+                            is_alive: true,
+                            channel_num: Some(2),
+                            is_relocating: true,
+                        },
+                        connection_global_world_id,
+                    );
+                },
+            );
+
+            world.run(
+                |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                    entities.add_entity(
+                        &mut messages,
+                        Box::new(Message::UserDespawned {
+                            user_finalizer: UserFinalizer {
+                                connection_global_world_id,
+                                user_id: user.id,
+                                location: location.clone(),
+                                is_alive: true,
+                                abnormalities: vec![],
+                                progress: None,
+                            },
+                        }),
+                    );
+                },
+            );
+
+            world.run(user_spawner_system);
+
+            world.run(|spawns: View<GlobalUserSpawn>| {
+                let spawn = spawns.try_get(connection_global_world_id)?;
+                assert_eq!(spawn.status, UserSpawnStatus::Requesting);
+                assert_eq!(spawn.channel_num, Some(2));
+
                 Ok::<(), anyhow::Error>(())
             })?;
 
@@ -857,6 +1343,23 @@ mod tests {
             let pool = task::block_on(async { PgPool::new(db_string).await })?;
             let (world, connection_global_world_id, _rx_channel, account, user, _location) =
                 task::block_on(async { setup(&pool).await })?;
+            let user = task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                user_abnormality::create(
+                    &mut conn,
+                    &UserAbnormality {
+                        user_id: user.id,
+                        abnormality_id: 4000,
+                        stacks: 1,
+                        remaining: 60_000,
+                    },
+                )
+                .await?;
+                // The user collects rest bonus while it's logged out
+                let last_logout_at = Utc::now() - chrono::Duration::hours(10);
+                user::update_last_logout_at(&mut conn, user.id, last_logout_at).await?;
+                user::get_by_id(&mut conn, user.id).await
+            })?;
 
             // FIXME Ask upstream project to create a better way to create EntityIds
             let local_world_id =
@@ -877,6 +1380,8 @@ mod tests {
                             local_world_channel: Some(local_world_tx),
                             marked_for_deletion: false,
                             is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
                         },
                         connection_global_world_id,
                     );
@@ -891,7 +1396,24 @@ mod tests {
                         user_initializer.connection_global_world_id,
                         connection_global_world_id
                     );
-                    assert_eq!(user_initializer.user, user);
+                    assert_eq!(
+                        user_initializer.user,
+                        User {
+                            rest_bonus_xp: 50,
+                            ..user.clone()
+                        }
+                    );
+                    assert_eq!(user_initializer.inventory.size, 40);
+                    assert_eq!(user_initializer.inventory.money, 1000);
+                    assert_eq!(user_initializer.items.len(), 1);
+                    assert_eq!(user_initializer.items[0].template_id, 8005);
+                    assert_eq!(user_initializer.items[0].slot, 3);
+                    assert_eq!(user_initializer.items[0].amount, 20);
+                    assert_eq!(user_initializer.equipment.len(), 1);
+                    assert_eq!(user_initializer.equipment[0].id, 100);
+                    assert_eq!(user_initializer.equipment[0].slot, EquipmentSlot::Weapon);
+                    assert_eq!(user_initializer.abnormalities.len(), 1);
+                    assert_eq!(user_initializer.abnormalities[0].abnormality_id, 4000);
                 }
                 _ => panic!("Message is not a PrepareUserSpawn message"),
             }
@@ -900,6 +1422,90 @@ mod tests {
         })
     }
 
+    fn get_test_zone_registry() -> ZoneRegistry {
+        ZoneRegistry::new(vec![
+            Zone {
+                id: 5,
+                zone_type: LocalWorldType::Field,
+                channel_capacity: 0,
+                topology_id: 5,
+                spawn_points: vec![SpawnPoint {
+                    point: Point3::new(10.0, 20.0, 30.0),
+                    rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 1.0),
+                }],
+            },
+            Zone {
+                id: 9001,
+                zone_type: LocalWorldType::Dungeon,
+                channel_capacity: 0,
+                topology_id: 9000,
+                spawn_points: vec![SpawnPoint {
+                    point: Point3::new(100.0, 200.0, 300.0),
+                    rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 2.0),
+                }],
+            },
+        ])
+    }
+
+    #[test]
+    fn test_resolve_spawn_location() {
+        let zone_registry = get_test_zone_registry();
+        let mut location = UserLocation {
+            user_id: 1,
+            zone_id: 5,
+            point: Point3::new(1.0, 2.0, 3.0),
+            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.5),
+        };
+
+        // Field zones keep the persisted location
+        let field_location = resolve_spawn_location(location.clone(), 5, &zone_registry);
+        assert_eq!(field_location.point, Point3::new(1.0, 2.0, 3.0));
+
+        // Unknown zones keep the persisted location
+        location.zone_id = 1;
+        let unknown_location = resolve_spawn_location(location.clone(), 1, &zone_registry);
+        assert_eq!(unknown_location.point, Point3::new(1.0, 2.0, 3.0));
+
+        // Instanced zones use the first spawn point
+        location.zone_id = 9001;
+        let dungeon_location = resolve_spawn_location(location.clone(), 9001, &zone_registry);
+        assert_eq!(dungeon_location.zone_id, 9001);
+        assert_eq!(dungeon_location.point, Point3::new(100.0, 200.0, 300.0));
+        assert_eq!(
+            dungeon_location.rotation,
+            Rotation3::from_axis_angle(&Vector3::z_axis(), 2.0)
+        );
+
+        // Users that change their zone use the first spawn point of the new zone
+        location.zone_id = 5;
+        let entered_location = resolve_spawn_location(location, 9001, &zone_registry);
+        assert_eq!(entered_location.zone_id, 9001);
+        assert_eq!(entered_location.point, Point3::new(100.0, 200.0, 300.0));
+    }
+
+    #[test]
+    fn test_assemble_response_load_topo() -> Result<()> {
+        let zone_registry = get_test_zone_registry();
+        let connection_global_world_id =
+            from_vec::<EntityId>(vec![0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+        let location = UserLocation {
+            user_id: 1,
+            zone_id: 9001,
+            point: Point3::new(1.0, 2.0, 3.0),
+            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.5),
+        };
+
+        match &*assemble_response_load_topo(connection_global_world_id, &location, &zone_registry) {
+            Message::ResponseLoadTopo { packet, .. } => {
+                assert_eq!(packet.zone, 9000);
+                assert_eq!(packet.location.x, 1.0);
+            }
+            _ => panic!("Message is not a ResponseLoadTopo message"),
+        }
+
+        Ok(())
+    }
+
     #[test]
     #[should_panic(expected = "SPAWN FAILED")]
     fn test_user_spawn_failed() {
@@ -922,6 +1528,8 @@ mod tests {
                             local_world_channel: None,
                             marked_for_deletion: false,
                             is_alive: true,
+                            channel_num: None,
+                            is_relocating: false,
                         },
                         connection_global_world_id,
                     );
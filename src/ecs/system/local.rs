@@ -1,13 +1,54 @@
 /// All systems used by the local world
+pub mod abnormality;
+pub mod appearance;
+pub mod chat;
+pub mod combat;
+pub mod equipment;
+pub mod experience;
+pub mod guild_war;
+pub mod inventory;
+pub mod movement;
+pub mod npc_ai;
+pub mod npc_spawner;
+pub mod parcel;
+pub mod status_reporter;
+pub mod trade;
 pub mod user_gateway;
+pub mod visibility;
+pub mod warehouse;
 
+pub use abnormality::abnormality_system;
+pub use appearance::appearance_system;
+pub use chat::chat_system;
+pub use combat::combat_system;
+pub use equipment::equipment_system;
+pub use experience::experience_system;
+pub use guild_war::guild_war_system;
+pub use inventory::inventory_system;
+pub use movement::movement_system;
+pub use npc_ai::npc_ai_system;
+pub use npc_spawner::{load_territories, npc_spawner_system};
+pub use parcel::parcel_system;
+pub use status_reporter::status_reporter_system;
+pub use trade::trade_system;
 pub use user_gateway::user_gateway_system;
+pub use visibility::visibility_system;
+pub use warehouse::warehouse_system;
 
-use crate::ecs::component::LocalConnection;
+use crate::ecs::component::{
+    LocalConnection, LocalUserSpawn, UserInventory, UserSpawnStatus, Visibility,
+};
 use crate::ecs::message::EcsMessage;
+use crate::ecs::message::Message::ResponseItemlist;
+use crate::ecs::resource::ItemRegistry;
 use crate::ecs::system::send_message;
+use crate::protocol::packet::{SItemlist, SItemlistItem};
+use shipyard::*;
 use tracing::{debug, error};
 
+/// Container ID of the inventory inside the network protocol.
+pub const INVENTORY_CONTAINER: i32 = 14;
+
 /// Send an outgoing packet message. This function can't be used by "Special Messages".
 pub fn send_message_to_connection<'a, T>(message: EcsMessage, connections: T)
 where
@@ -23,3 +64,180 @@ where
         error!("Message didn't had a local world ID attached");
     }
 }
+
+/// Sends a message to an user and all spawned users that can see it. The message is assembled
+/// with the global and local world ID of the receiving connection.
+pub fn send_to_observers<F>(
+    entity: EntityId,
+    connections: &View<LocalConnection>,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    visibilities: &View<Visibility>,
+    assemble: F,
+) where
+    F: Fn(EntityId, EntityId) -> EcsMessage,
+{
+    (connections, user_spawns, visibilities)
+        .iter()
+        .with_id()
+        .filter(|(_id, (_connection, spawn, _visibility))| spawn.status == UserSpawnStatus::Spawned)
+        .for_each(|(id, (connection, spawn, visibility))| {
+            if id == entity || visibility.visible_entities.contains(&entity) {
+                send_message(
+                    assemble(spawn.connection_global_world_id, id),
+                    &connection.channel,
+                );
+            }
+        });
+}
+
+/// Lists the items inside the inventory of an user.
+pub fn assemble_itemlist(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    inventory: &UserInventory,
+    open: bool,
+) -> EcsMessage {
+    let mut items = inventory
+        .items
+        .values()
+        .map(|item| SItemlistItem {
+            id: item.template_id,
+            db_id: item.id,
+            owner_id: item.user_id as i64,
+            slot: item.slot,
+            amount: item.amount,
+            enchantment: 0,
+            durability: 0,
+            soulbound: false,
+        })
+        .collect::<Vec<SItemlistItem>>();
+    items.sort_by_key(|item| item.slot);
+
+    Box::new(ResponseItemlist {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SItemlist {
+            items,
+            game_id: connection_local_world_id,
+            container: INVENTORY_CONTAINER,
+            pocket: 0,
+            num_pockets: 1,
+            size: inventory.size,
+            money: inventory.money,
+            loot_priority: 0,
+            open,
+            requested: open,
+            first: true,
+            more: false,
+            last_in_batch: true,
+        },
+    })
+}
+
+/// Items with an unknown template are handled as not stackable.
+pub fn max_stack(item_registry: &ItemRegistry, template_id: i32) -> i32 {
+    item_registry
+        .get(template_id)
+        .map_or(1, |template| template.max_stack)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::ecs::component::{LocalConnection, LocalUserSpawn, UserInventory, UserSpawnStatus};
+    use crate::ecs::message::EcsMessage;
+    use crate::model::entity::{Inventory, Item, User};
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::inventory::tests::get_default_inventory;
+    use crate::model::repository::item::tests::get_default_item;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, inventory, item, user};
+    use crate::protocol::serde::from_vec;
+    use crate::Result;
+    use async_std::sync::{channel, Receiver};
+    use async_std::task;
+    use shipyard::*;
+    use sqlx::PgPool;
+    use std::collections::HashMap;
+
+    pub struct TestUser {
+        pub user: User,
+        pub connection_global_world_id: EntityId,
+        pub connection_local_world_id: EntityId,
+        pub rx: Receiver<EcsMessage>,
+    }
+
+    /// Creates an user with 1000 money and the given items (template ID, slot, amount) and
+    /// spawns it inside the local world.
+    pub fn add_user(
+        world: &World,
+        pool: &PgPool,
+        num: i32,
+        items: &[(i32, i32, i32)],
+    ) -> Result<TestUser> {
+        let (user, inventory, items) = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let account = account::create(&mut conn, &get_default_account(num)).await?;
+            let user = user::create(&mut conn, &get_default_user(&account, num)).await?;
+            let inventory = inventory::create(
+                &mut conn,
+                &Inventory {
+                    money: 1000,
+                    ..get_default_inventory(&user)
+                },
+            )
+            .await?;
+
+            let mut created_items = Vec::with_capacity(items.len());
+            for (template_id, slot, amount) in items {
+                let mut new_item = get_default_item(&user, *slot);
+                new_item.template_id = *template_id;
+                new_item.amount = *amount;
+                created_items.push(item::create(&mut conn, &new_item).await?);
+            }
+
+            Ok::<(User, Inventory, Vec<Item>), anyhow::Error>((user, inventory, created_items))
+        })?;
+
+        let connection_global_world_id =
+            from_vec::<EntityId>(vec![num as u8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut inventories: ViewMut<UserInventory>| {
+                entities.add_entity(
+                    (&mut connections, &mut user_spawns, &mut inventories),
+                    (
+                        LocalConnection {
+                            channel: tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_global_world_id,
+                            is_alive: true,
+                        },
+                        UserInventory {
+                            size: inventory.size,
+                            money: inventory.money,
+                            items: items.into_iter().map(|item| (item.slot, item)).collect(),
+                            equipment_preset: inventory.equipment_preset,
+                            equipment: HashMap::new(),
+                        },
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            user,
+            connection_global_world_id,
+            connection_local_world_id,
+            rx: rx_channel,
+        })
+    }
+}
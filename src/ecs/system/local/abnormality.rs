@@ -0,0 +1,1031 @@
+use crate::ecs::component::{
+    Abnormalities, AbnormalityRequest, ActiveAbnormality, Health, LocalConnection, LocalUserSpawn,
+    Location, Visibility,
+};
+use crate::ecs::message::Message::{
+    ResponseAbnormalityBegin, ResponseAbnormalityEnd, ResponseAbnormalityRefresh,
+    ResponseAbnormalityTooltipValue, ResponseCreatureChangeHp, ResponseCreatureLife,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::{AbnormalityRegistry, Tick};
+use crate::ecs::system::local::{send_message_to_connection, send_to_observers};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::Context;
+use shipyard::*;
+use std::time::Duration;
+use tracing::{debug, error, info_span};
+
+/// HP changes that are caused by the periodic effects of abnormalities.
+const CHANGE_BY_ABNORMALITY: i32 = 2;
+
+/// Changes of the abnormalities that are shown to the users once all abnormalities of a tick are
+/// updated.
+enum AbnormalityEvent {
+    Began {
+        target_id: EntityId,
+        source_id: EntityId,
+        abnormality_id: i32,
+        remaining: Duration,
+        stacks: i32,
+    },
+    Refreshed {
+        target_id: EntityId,
+        abnormality_id: i32,
+        remaining: Duration,
+        stacks: i32,
+    },
+    Ended {
+        target_id: EntityId,
+        abnormality_id: i32,
+    },
+    HealthChanged {
+        source_id: EntityId,
+        target_id: EntityId,
+        health: Health,
+        diff: i64,
+    },
+    Died {
+        target_id: EntityId,
+    },
+}
+
+/// Handles the abnormalities (buffs and debuffs) of the users of a local world. Abnormalities are
+/// requested by other systems, stack up to their maximal stacks and are refreshed when they are
+/// applied again. Their remaining duration and periodic effects run on the delta of the tick.
+pub fn abnormality_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<LocalConnection>,
+    mut user_spawns: ViewMut<LocalUserSpawn>,
+    locations: View<Location>,
+    visibilities: View<Visibility>,
+    mut healths: ViewMut<Health>,
+    mut abnormalities: ViewMut<Abnormalities>,
+    registry: UniqueView<AbnormalityRegistry>,
+    tick: UniqueView<Tick>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestLoadTopoFin {
+                connection_global_world_id,
+                connection_local_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_load_topo_fin(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &connections,
+                    &abnormalities,
+                ) {
+                    error!("Ignoring Message::RequestLoadTopoFin: {:?}", e);
+                }
+            }
+            Message::RequestAbnormalityTooltipValue {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_abnormality_tooltip_value(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &registry,
+                ) {
+                    error!("Ignoring abnormality tooltip value request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other packets */ }
+        });
+
+    let mut events = Vec::new();
+    update_abnormalities(
+        tick.delta,
+        &mut user_spawns,
+        &mut healths,
+        &mut abnormalities,
+        &registry,
+        &mut events,
+    );
+    send_events(
+        events,
+        &connections,
+        &user_spawns,
+        &locations,
+        &visibilities,
+    );
+}
+
+/// Requests an abnormality for an user. The abnormality is applied by the abnormality system.
+pub fn request_abnormality(
+    abnormalities: &mut ViewMut<Abnormalities>,
+    target_id: EntityId,
+    abnormality_id: i32,
+    source_id: EntityId,
+) {
+    if let Ok(target_abnormalities) = abnormalities.try_get(target_id) {
+        target_abnormalities.requested.push(AbnormalityRequest {
+            abnormality_id,
+            source_id,
+        });
+    }
+}
+
+/// Shows the abnormalities that survived the last logout to the user.
+fn handle_load_topo_fin(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    connections: &View<LocalConnection>,
+    abnormalities: &ViewMut<Abnormalities>,
+) -> Result<()> {
+    debug!("Message::RequestLoadTopoFin incoming");
+
+    let user_abnormalities = abnormalities
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find abnormalities of {:?}",
+            connection_local_world_id
+        ))?;
+    for (abnormality_id, abnormality) in &user_abnormalities.active {
+        send_message_to_connection(
+            assemble_abnormality_begin(
+                connection_global_world_id,
+                connection_local_world_id,
+                connection_local_world_id,
+                abnormality.source_id,
+                *abnormality_id,
+                abnormality.remaining,
+                abnormality.stacks,
+            ),
+            connections,
+        );
+    }
+
+    Ok(())
+}
+
+fn handle_abnormality_tooltip_value(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CRequestAbnormalityTooltipValue,
+    connections: &View<LocalConnection>,
+    registry: &AbnormalityRegistry,
+) -> Result<()> {
+    debug!("Message::RequestAbnormalityTooltipValue incoming");
+
+    let abnormality = registry
+        .get(packet.id)
+        .context(format!("Can't find abnormality {}", packet.id))?;
+
+    // The tooltips show the health that is changed per period and stack.
+    send_message_to_connection(
+        Box::new(ResponseAbnormalityTooltipValue {
+            connection_global_world_id,
+            connection_local_world_id,
+            packet: SAbnormalityTooltipValue {
+                id: abnormality.id,
+                value: abnormality.hp_per_period.abs() as f32,
+            },
+        }),
+        connections,
+    );
+
+    Ok(())
+}
+
+/// Advances the abnormalities of all users by the delta of the tick and applies the requested
+/// abnormalities afterwards, so that new abnormalities start with their full duration. Dead
+/// users lose all their abnormalities.
+fn update_abnormalities(
+    delta: Duration,
+    user_spawns: &mut ViewMut<LocalUserSpawn>,
+    healths: &mut ViewMut<Health>,
+    abnormalities: &mut ViewMut<Abnormalities>,
+    registry: &AbnormalityRegistry,
+    events: &mut Vec<AbnormalityEvent>,
+) {
+    (&mut *user_spawns, &mut *abnormalities)
+        .iter()
+        .with_id()
+        .for_each(|(id, (spawn, user_abnormalities))| {
+            if spawn.is_alive {
+                advance_abnormalities(id, delta, healths, user_abnormalities, registry, events);
+            }
+            if spawn.is_alive {
+                if let Ok(health) = healths.try_get(id) {
+                    if health.hp == 0 {
+                        spawn.is_alive = false;
+                        events.push(AbnormalityEvent::Died { target_id: id });
+                    }
+                }
+            }
+
+            if spawn.is_alive {
+                apply_requests(id, user_abnormalities, registry, events);
+            } else {
+                user_abnormalities.requested.clear();
+                for (abnormality_id, _abnormality) in user_abnormalities.active.drain() {
+                    events.push(AbnormalityEvent::Ended {
+                        target_id: id,
+                        abnormality_id,
+                    });
+                }
+            }
+        });
+}
+
+/// Runs the periodic effects of the abnormalities of an user and removes the expired ones.
+fn advance_abnormalities(
+    id: EntityId,
+    delta: Duration,
+    healths: &mut ViewMut<Health>,
+    user_abnormalities: &mut Abnormalities,
+    registry: &AbnormalityRegistry,
+    events: &mut Vec<AbnormalityEvent>,
+) {
+    let mut abnormality_ids: Vec<i32> = user_abnormalities.active.keys().copied().collect();
+    abnormality_ids.sort();
+
+    for abnormality_id in abnormality_ids {
+        let abnormality = match user_abnormalities.active.get_mut(&abnormality_id) {
+            Some(abnormality) => abnormality,
+            None => continue,
+        };
+        let elapsed = delta.min(abnormality.remaining);
+        abnormality.remaining -= elapsed;
+
+        if let Some(template) = registry.get(abnormality_id) {
+            let periods = count_periods(abnormality, template.period, elapsed);
+            let diff = template.hp_per_period * i64::from(abnormality.stacks) * periods;
+            if diff != 0 {
+                if let Ok(health) = healths.try_get(id) {
+                    let hp = (health.hp + diff).max(0).min(health.max_hp);
+                    let diff = hp - health.hp;
+                    health.hp = hp;
+                    if diff != 0 {
+                        events.push(AbnormalityEvent::HealthChanged {
+                            source_id: abnormality.source_id,
+                            target_id: id,
+                            health: *health,
+                            diff,
+                        });
+                    }
+                }
+            }
+        } else {
+            error!("Removing unknown abnormality {}", abnormality_id);
+            abnormality.remaining = Duration::from_secs(0);
+        }
+
+        if abnormality.remaining == Duration::from_secs(0) {
+            user_abnormalities.active.remove(&abnormality_id);
+            events.push(AbnormalityEvent::Ended {
+                target_id: id,
+                abnormality_id,
+            });
+        }
+    }
+}
+
+/// Counts the periods that passed in the elapsed time and moves the next period accordingly.
+fn count_periods(abnormality: &mut ActiveAbnormality, period: Duration, elapsed: Duration) -> i64 {
+    if period == Duration::from_secs(0) {
+        return 0;
+    }
+
+    let mut periods = 0;
+    let mut elapsed = elapsed;
+    while elapsed >= abnormality.next_period {
+        elapsed -= abnormality.next_period;
+        abnormality.next_period = period;
+        periods += 1;
+    }
+    abnormality.next_period -= elapsed;
+    periods
+}
+
+/// Applies the requested abnormalities of an user. Abnormalities that are already active gain a
+/// stack and start over with their full duration.
+fn apply_requests(
+    id: EntityId,
+    user_abnormalities: &mut Abnormalities,
+    registry: &AbnormalityRegistry,
+    events: &mut Vec<AbnormalityEvent>,
+) {
+    for request in std::mem::take(&mut user_abnormalities.requested) {
+        let template = match registry.get(request.abnormality_id) {
+            Some(template) => template,
+            None => {
+                error!("Can't apply unknown abnormality {}", request.abnormality_id);
+                continue;
+            }
+        };
+
+        if let Some(abnormality) = user_abnormalities.active.get_mut(&template.id) {
+            abnormality.source_id = request.source_id;
+            abnormality.stacks = (abnormality.stacks + 1).min(template.max_stacks);
+            abnormality.remaining = template.duration;
+            events.push(AbnormalityEvent::Refreshed {
+                target_id: id,
+                abnormality_id: template.id,
+                remaining: abnormality.remaining,
+                stacks: abnormality.stacks,
+            });
+        } else {
+            user_abnormalities.active.insert(
+                template.id,
+                ActiveAbnormality {
+                    source_id: request.source_id,
+                    stacks: 1,
+                    remaining: template.duration,
+                    next_period: template.period,
+                    is_persistent: template.is_persistent,
+                },
+            );
+            events.push(AbnormalityEvent::Began {
+                target_id: id,
+                source_id: request.source_id,
+                abnormality_id: template.id,
+                remaining: template.duration,
+                stacks: 1,
+            });
+        }
+    }
+}
+
+/// Shows the changes of the abnormalities to the affected users and all users that can see them.
+fn send_events(
+    events: Vec<AbnormalityEvent>,
+    connections: &View<LocalConnection>,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    locations: &View<Location>,
+    visibilities: &View<Visibility>,
+) {
+    for event in events {
+        match event {
+            AbnormalityEvent::Began {
+                target_id,
+                source_id,
+                abnormality_id,
+                remaining,
+                stacks,
+            } => {
+                send_to_observers(
+                    target_id,
+                    connections,
+                    user_spawns,
+                    visibilities,
+                    |gid, lid| {
+                        assemble_abnormality_begin(
+                            gid,
+                            lid,
+                            target_id,
+                            source_id,
+                            abnormality_id,
+                            remaining,
+                            stacks,
+                        )
+                    },
+                );
+            }
+            AbnormalityEvent::Refreshed {
+                target_id,
+                abnormality_id,
+                remaining,
+                stacks,
+            } => {
+                send_to_observers(
+                    target_id,
+                    connections,
+                    user_spawns,
+                    visibilities,
+                    |connection_global_world_id, connection_local_world_id| {
+                        Box::new(ResponseAbnormalityRefresh {
+                            connection_global_world_id,
+                            connection_local_world_id,
+                            packet: SAbnormalityRefresh {
+                                target_id,
+                                id: abnormality_id,
+                                duration: remaining.as_millis() as i32,
+                                unk1: 0,
+                                stacks,
+                            },
+                        })
+                    },
+                );
+            }
+            AbnormalityEvent::Ended {
+                target_id,
+                abnormality_id,
+            } => {
+                send_to_observers(
+                    target_id,
+                    connections,
+                    user_spawns,
+                    visibilities,
+                    |connection_global_world_id, connection_local_world_id| {
+                        Box::new(ResponseAbnormalityEnd {
+                            connection_global_world_id,
+                            connection_local_world_id,
+                            packet: SAbnormalityEnd {
+                                target_id,
+                                id: abnormality_id,
+                            },
+                        })
+                    },
+                );
+            }
+            AbnormalityEvent::HealthChanged {
+                source_id,
+                target_id,
+                health,
+                diff,
+            } => {
+                send_to_observers(
+                    target_id,
+                    connections,
+                    user_spawns,
+                    visibilities,
+                    |connection_global_world_id, connection_local_world_id| {
+                        Box::new(ResponseCreatureChangeHp {
+                            connection_global_world_id,
+                            connection_local_world_id,
+                            packet: SCreatureChangeHp {
+                                current_hp: health.hp,
+                                max_hp: health.max_hp,
+                                diff,
+                                change_type: CHANGE_BY_ABNORMALITY,
+                                target_id,
+                                source_id,
+                                crit: false,
+                            },
+                        })
+                    },
+                );
+            }
+            AbnormalityEvent::Died { target_id } => {
+                if let Ok(location) = locations.try_get(target_id) {
+                    send_to_observers(
+                        target_id,
+                        connections,
+                        user_spawns,
+                        visibilities,
+                        |connection_global_world_id, connection_local_world_id| {
+                            Box::new(ResponseCreatureLife {
+                                connection_global_world_id,
+                                connection_local_world_id,
+                                packet: SCreatureLife {
+                                    target_id,
+                                    location: location.point.into(),
+                                    is_alive: false,
+                                },
+                            })
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn assemble_abnormality_begin(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    target_id: EntityId,
+    source_id: EntityId,
+    abnormality_id: i32,
+    remaining: Duration,
+    stacks: i32,
+) -> EcsMessage {
+    Box::new(ResponseAbnormalityBegin {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SAbnormalityBegin {
+            target_id,
+            source_id,
+            id: abnormality_id,
+            duration: remaining.as_millis() as i32,
+            unk1: 0,
+            stacks,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Configuration;
+    use crate::ecs::component::{UserAppearance, UserSpawnStatus, UserStats};
+    use crate::ecs::resource::{
+        AbnormalityTemplate, DeletionList, GlobalMessageChannel, GuildWarRegistry, SkillRegistry,
+        SkillTemplate,
+    };
+    use crate::ecs::system::common::cleaner_system;
+    use crate::ecs::system::local::combat::to_packet_skill_id;
+    use crate::ecs::system::local::combat_system;
+    use crate::model::{Angle, Class, Customization, Gender, Race, TemplateID, Vec3f, BASE_STATS};
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver};
+    use nalgebra::{Point3, Rotation3, Vector3};
+    use std::collections::HashSet;
+    use std::time::Instant;
+
+    const CRYSTAL: i32 = 4000;
+    const POISON: i32 = 4100;
+    const REGENERATION: i32 = 4200;
+
+    const POISON_STRIKE: i32 = 10100;
+
+    struct TestUser {
+        connection_global_world_id: EntityId,
+        connection_local_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn setup() -> World {
+        let (tx_channel, _rx_channel) = channel(1024);
+        let mut config = Configuration::default();
+        config.game.pvp = true;
+
+        let world = World::new();
+        world.add_unique(AbnormalityRegistry::new(vec![
+            AbnormalityTemplate {
+                id: CRYSTAL,
+                duration: Duration::from_secs(60),
+                max_stacks: 1,
+                is_persistent: true,
+                ..AbnormalityTemplate::default()
+            },
+            AbnormalityTemplate {
+                id: POISON,
+                duration: Duration::from_secs(10),
+                max_stacks: 3,
+                period: Duration::from_secs(2),
+                hp_per_period: -20,
+                is_persistent: false,
+            },
+            AbnormalityTemplate {
+                id: REGENERATION,
+                duration: Duration::from_secs(4),
+                max_stacks: 1,
+                period: Duration::from_secs(1),
+                hp_per_period: 10,
+                is_persistent: false,
+            },
+        ]));
+        world.add_unique(SkillRegistry::new(vec![SkillTemplate {
+            id: POISON_STRIKE,
+            damage: 1,
+            range: 50.0,
+            self_abnormalities: vec![REGENERATION],
+            target_abnormalities: vec![POISON],
+            ..SkillTemplate::default()
+        }]));
+        world.add_unique(config);
+        world.add_unique(GuildWarRegistry::default());
+        world.add_unique(GlobalMessageChannel {
+            channel: tx_channel,
+        });
+        world.add_unique(Tick {
+            count: 0,
+            delta: Duration::from_secs(0),
+            time: Instant::now(),
+        });
+        world.add_unique(DeletionList(Vec::default()));
+        world
+    }
+
+    /// Spawns an user with the given active abnormalities and loads it into the world.
+    fn add_user(world: &World, num: i32, active: Vec<(i32, i32)>) -> Result<TestUser> {
+        let connection_global_world_id =
+            from_vec::<EntityId>(vec![num as u8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut locations: ViewMut<Location>,
+             mut appearances: ViewMut<UserAppearance>,
+             mut visibilities: ViewMut<Visibility>,
+             mut stats: ViewMut<UserStats>,
+             mut abnormalities: ViewMut<Abnormalities>| {
+                let id = entities.add_entity(
+                    (
+                        &mut connections,
+                        &mut user_spawns,
+                        &mut locations,
+                        &mut appearances,
+                        &mut visibilities,
+                        &mut stats,
+                    ),
+                    (
+                        LocalConnection {
+                            channel: tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id: num,
+                            account_id: i64::from(num),
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_global_world_id,
+                            is_alive: true,
+                        },
+                        Location {
+                            point: Point3::new(0.0, 0.0, 0.0),
+                            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+                        },
+                        UserAppearance {
+                            name: format!("User{}", num),
+                            template_id: TemplateID {
+                                race: Race::Human,
+                                gender: Gender::Male,
+                                class: Class::Warrior,
+                            },
+                            level: 65,
+                            details: vec![],
+                            shape: vec![],
+                            appearance: Customization::default(),
+                            appearance2: 100,
+                            show_face: true,
+                            show_style: true,
+                            guild_name: "".to_string(),
+                            guild_rank: "".to_string(),
+                        },
+                        Visibility {
+                            range: 100,
+                            visible_entities: HashSet::new(),
+                        },
+                        UserStats {
+                            base: BASE_STATS,
+                            total: BASE_STATS,
+                        },
+                    ),
+                );
+                entities.add_component(
+                    &mut abnormalities,
+                    Abnormalities {
+                        active: active
+                            .iter()
+                            .map(|(abnormality_id, stacks)| {
+                                (
+                                    *abnormality_id,
+                                    ActiveAbnormality {
+                                        source_id: id,
+                                        stacks: *stacks,
+                                        remaining: Duration::from_secs(60),
+                                        next_period: Duration::from_secs(0),
+                                        is_persistent: true,
+                                    },
+                                )
+                            })
+                            .collect(),
+                        requested: Vec::new(),
+                    },
+                    id,
+                );
+                id
+            },
+        );
+
+        send(
+            world,
+            Message::RequestLoadTopoFin {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet: CLoadTopoFin {},
+            },
+        );
+        Ok(TestUser {
+            connection_global_world_id,
+            connection_local_world_id,
+            rx: rx_channel,
+        })
+    }
+
+    /// Lets the users see each other.
+    fn show_each_other(world: &World, users: &[&TestUser]) {
+        world.run(|mut visibilities: ViewMut<Visibility>| {
+            for user in users {
+                let visibility = (&mut visibilities)
+                    .try_get(user.connection_local_world_id)
+                    .unwrap();
+                for other in users {
+                    if other.connection_local_world_id != user.connection_local_world_id {
+                        visibility
+                            .visible_entities
+                            .insert(other.connection_local_world_id);
+                    }
+                }
+            }
+        });
+    }
+
+    fn send(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(combat_system);
+        world.run(abnormality_system);
+        world.run(cleaner_system);
+    }
+
+    /// Runs the abnormality system for a tick with the given delta.
+    fn advance(world: &World, delta: Duration) {
+        world.run(|mut tick: UniqueViewMut<Tick>| tick.delta = delta);
+        world.run(abnormality_system);
+        world.run(|mut tick: UniqueViewMut<Tick>| tick.delta = Duration::from_secs(0));
+    }
+
+    fn request(world: &World, target: &TestUser, source: &TestUser, abnormality_id: i32) {
+        world.run(|mut abnormalities: ViewMut<Abnormalities>| {
+            request_abnormality(
+                &mut abnormalities,
+                target.connection_local_world_id,
+                abnormality_id,
+                source.connection_local_world_id,
+            );
+        });
+        advance(world, Duration::from_secs(0));
+    }
+
+    fn received(rx: &Receiver<EcsMessage>) -> Vec<EcsMessage> {
+        let mut messages = Vec::new();
+        while let Ok(message) = rx.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn find_packet<T, F>(messages: &[EcsMessage], f: F) -> Option<T>
+    where
+        F: Fn(&Message) -> Option<T>,
+    {
+        messages.iter().find_map(|message| f(&**message))
+    }
+
+    fn get_abnormality(
+        world: &World,
+        user: &TestUser,
+        abnormality_id: i32,
+    ) -> Option<ActiveAbnormality> {
+        world.run(|abnormalities: View<Abnormalities>| {
+            abnormalities
+                .try_get(user.connection_local_world_id)
+                .unwrap()
+                .active
+                .get(&abnormality_id)
+                .copied()
+        })
+    }
+
+    fn get_hp(world: &World, user: &TestUser) -> i64 {
+        world.run(|healths: View<Health>| {
+            healths.try_get(user.connection_local_world_id).unwrap().hp
+        })
+    }
+
+    fn set_hp(world: &World, user: &TestUser, hp: i64) {
+        world.run(|mut healths: ViewMut<Health>| {
+            (&mut healths)
+                .try_get(user.connection_local_world_id)
+                .unwrap()
+                .hp = hp;
+        });
+    }
+
+    #[test]
+    fn test_restored_abnormalities_are_shown() -> Result<()> {
+        let world = setup();
+        let user = add_user(&world, 1, vec![(CRYSTAL, 1)])?;
+
+        let messages = received(&user.rx);
+        let begin = find_packet(&messages, |message| match message {
+            ResponseAbnormalityBegin { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(begin.target_id, user.connection_local_world_id);
+        assert_eq!(begin.id, CRYSTAL);
+        assert_eq!(begin.duration, 60000);
+        assert_eq!(begin.stacks, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_and_stack_abnormality() -> Result<()> {
+        let world = setup();
+        let user = add_user(&world, 1, vec![])?;
+        let target = add_user(&world, 2, vec![])?;
+        show_each_other(&world, &[&user, &target]);
+
+        request(&world, &target, &user, POISON);
+        for test_user in [&user, &target].iter() {
+            let messages = received(&test_user.rx);
+            let begin = find_packet(&messages, |message| match message {
+                ResponseAbnormalityBegin { packet, .. } => Some(packet.clone()),
+                _ => None,
+            })
+            .unwrap();
+            assert_eq!(begin.target_id, target.connection_local_world_id);
+            assert_eq!(begin.source_id, user.connection_local_world_id);
+            assert_eq!(begin.id, POISON);
+            assert_eq!(begin.duration, 10000);
+            assert_eq!(begin.stacks, 1);
+        }
+
+        // Applying the abnormality again adds a stack and resets the duration
+        advance(&world, Duration::from_secs(5));
+        for stacks in [2, 3, 3].iter() {
+            request(&world, &target, &user, POISON);
+            let messages = received(&target.rx);
+            let refresh = find_packet(&messages, |message| match message {
+                ResponseAbnormalityRefresh { packet, .. } => Some(packet.clone()),
+                _ => None,
+            })
+            .unwrap();
+            assert_eq!(refresh.id, POISON);
+            assert_eq!(refresh.duration, 10000);
+            assert_eq!(refresh.stacks, *stacks);
+        }
+        assert_eq!(get_abnormality(&world, &target, POISON).unwrap().stacks, 3);
+
+        // Unknown abnormalities are ignored
+        request(&world, &target, &user, 1);
+        assert!(get_abnormality(&world, &target, 1).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_abnormality_expires() -> Result<()> {
+        let world = setup();
+        let user = add_user(&world, 1, vec![(CRYSTAL, 1)])?;
+        received(&user.rx);
+
+        advance(&world, Duration::from_secs(59));
+        assert!(received(&user.rx).is_empty());
+        assert_eq!(
+            get_abnormality(&world, &user, CRYSTAL).unwrap().remaining,
+            Duration::from_secs(1)
+        );
+
+        advance(&world, Duration::from_secs(1));
+        let messages = received(&user.rx);
+        let end = find_packet(&messages, |message| match message {
+            ResponseAbnormalityEnd { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(end.target_id, user.connection_local_world_id);
+        assert_eq!(end.id, CRYSTAL);
+        assert!(get_abnormality(&world, &user, CRYSTAL).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_periodic_effects() -> Result<()> {
+        let world = setup();
+        let user = add_user(&world, 1, vec![])?;
+        let target = add_user(&world, 2, vec![])?;
+
+        // Damage is dealt every period per stack
+        request(&world, &target, &user, POISON);
+        advance(&world, Duration::from_secs(1));
+        assert_eq!(get_hp(&world, &target), 200);
+        advance(&world, Duration::from_secs(1));
+        assert_eq!(get_hp(&world, &target), 180);
+        let messages = received(&target.rx);
+        let hp = find_packet(&messages, |message| match message {
+            ResponseCreatureChangeHp { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(hp.current_hp, 180);
+        assert_eq!(hp.diff, -20);
+        assert_eq!(hp.change_type, CHANGE_BY_ABNORMALITY);
+        assert_eq!(hp.source_id, user.connection_local_world_id);
+
+        request(&world, &target, &user, POISON);
+        advance(&world, Duration::from_secs(4));
+        assert_eq!(get_hp(&world, &target), 100);
+
+        // Healing stops at the maximal health and ends with the abnormality
+        set_hp(&world, &user, 170);
+        request(&world, &user, &user, REGENERATION);
+        for hp in [180, 190, 200, 200].iter() {
+            advance(&world, Duration::from_secs(1));
+            assert_eq!(get_hp(&world, &user), *hp);
+        }
+        assert!(get_abnormality(&world, &user, REGENERATION).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_death_clears_abnormalities() -> Result<()> {
+        let world = setup();
+        let user = add_user(&world, 1, vec![(CRYSTAL, 1)])?;
+
+        set_hp(&world, &user, 30);
+        request(&world, &user, &user, POISON);
+        request(&world, &user, &user, POISON);
+        received(&user.rx);
+
+        advance(&world, Duration::from_secs(2));
+        assert_eq!(get_hp(&world, &user), 0);
+        let messages = received(&user.rx);
+        let life = find_packet(&messages, |message| match message {
+            ResponseCreatureLife { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(life.target_id, user.connection_local_world_id);
+        assert!(!life.is_alive);
+        let ended: Vec<i32> = messages
+            .iter()
+            .filter_map(|message| match &**message {
+                ResponseAbnormalityEnd { packet, .. } => Some(packet.id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ended.len(), 2);
+        assert!(ended.contains(&CRYSTAL));
+        assert!(ended.contains(&POISON));
+
+        // Dead users can't get new abnormalities
+        request(&world, &user, &user, REGENERATION);
+        assert!(get_abnormality(&world, &user, REGENERATION).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skill_applies_abnormalities() -> Result<()> {
+        let world = setup();
+        let user = add_user(&world, 1, vec![])?;
+        let target = add_user(&world, 2, vec![])?;
+
+        send(
+            &world,
+            Message::RequestStartSkill {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CStartSkill {
+                    skill_id: to_packet_skill_id(POISON_STRIKE),
+                    rotation: Angle::default(),
+                    location: Vec3f::default(),
+                    destination: Vec3f::default(),
+                    moving: false,
+                    continuation: false,
+                },
+            },
+        );
+
+        let poison = get_abnormality(&world, &target, POISON).unwrap();
+        assert_eq!(poison.source_id, user.connection_local_world_id);
+        assert!(!poison.is_persistent);
+        assert!(get_abnormality(&world, &user, POISON).is_none());
+        assert!(get_abnormality(&world, &user, REGENERATION).is_some());
+        assert!(get_abnormality(&world, &target, REGENERATION).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_abnormality_tooltip_value() -> Result<()> {
+        let world = setup();
+        let user = add_user(&world, 1, vec![])?;
+        received(&user.rx);
+
+        for id in [POISON, 1].iter() {
+            send(
+                &world,
+                Message::RequestAbnormalityTooltipValue {
+                    connection_global_world_id: user.connection_global_world_id,
+                    connection_local_world_id: user.connection_local_world_id,
+                    packet: CRequestAbnormalityTooltipValue { id: *id },
+                },
+            );
+        }
+
+        let messages = received(&user.rx);
+        assert_eq!(messages.len(), 1);
+        let tooltip = find_packet(&messages, |message| match message {
+            ResponseAbnormalityTooltipValue { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(tooltip.id, POISON);
+        assert_eq!(tooltip.value, 20.0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+use crate::ecs::component::{
+    LocalConnection, LocalUserSpawn, UserAppearance, UserSpawnStatus, Visibility,
+};
+use crate::ecs::message::Message::ResponseGuildName;
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::system::send_message;
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::Context;
+use shipyard::*;
+use tracing::{debug, error, info_span};
+
+/// Updates the appearance of spawned users once the global world reports a change and
+/// shows the change to the user and all users that can see it.
+pub fn appearance_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<LocalConnection>,
+    user_spawns: View<LocalUserSpawn>,
+    visibilities: View<Visibility>,
+    mut appearances: ViewMut<UserAppearance>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::UserGuildChanged {
+                connection_local_world_id,
+                guild_name,
+                guild_rank,
+            } => {
+                id_span!(connection_local_world_id);
+                if let Err(e) = handle_user_guild_changed(
+                    *connection_local_world_id,
+                    &guild_name,
+                    &guild_rank,
+                    &connections,
+                    &user_spawns,
+                    &visibilities,
+                    &mut appearances,
+                ) {
+                    error!("Ignoring Message::UserGuildChanged: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_user_guild_changed(
+    user_id: EntityId,
+    guild_name: &str,
+    guild_rank: &str,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    visibilities: &View<Visibility>,
+    appearances: &mut ViewMut<UserAppearance>,
+) -> Result<()> {
+    debug!("Message::UserGuildChanged incoming");
+
+    let mut appearance = appearances
+        .try_get(user_id)
+        .context(format!("Can't find appearance of {:?}", user_id))?;
+    appearance.guild_name = guild_name.to_string();
+    appearance.guild_rank = guild_rank.to_string();
+
+    // Users that are not spawned yet receive their guild with the spawn packets.
+    let is_spawned = user_spawns
+        .try_get(user_id)
+        .map_or(false, |spawn| spawn.status == UserSpawnStatus::Spawned);
+    if !is_spawned {
+        return Ok(());
+    }
+
+    (connections, user_spawns, visibilities)
+        .iter()
+        .with_id()
+        .filter(|(_id, (_connection, spawn, _visibility))| spawn.status == UserSpawnStatus::Spawned)
+        .for_each(|(id, (connection, spawn, visibility))| {
+            if id == user_id || visibility.visible_entities.contains(&user_id) {
+                send_message(
+                    assemble_guild_name(
+                        spawn.connection_global_world_id,
+                        id,
+                        user_id,
+                        guild_name,
+                        guild_rank,
+                    ),
+                    &connection.channel,
+                );
+            }
+        });
+
+    Ok(())
+}
+
+fn assemble_guild_name(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    game_id: EntityId,
+    guild_name: &str,
+    guild_rank: &str,
+) -> EcsMessage {
+    Box::new(ResponseGuildName {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SGuildName {
+            guild_name: guild_name.to_string(),
+            guild_rank: guild_rank.to_string(),
+            guild_title: "".to_string(),
+            guild_logo: "".to_string(),
+            game_id,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Class, Customization, Gender, Race, TemplateID};
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver};
+    use std::collections::HashSet;
+
+    fn add_user(
+        world: &World,
+        user_id: i32,
+        status: UserSpawnStatus,
+        visible_entities: HashSet<EntityId>,
+    ) -> (EntityId, Receiver<EcsMessage>) {
+        let (connection_tx_channel, connection_rx_channel) = channel(1024);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut appearances: ViewMut<UserAppearance>,
+             mut visibilities: ViewMut<Visibility>| {
+                entities.add_entity(
+                    (
+                        &mut connections,
+                        &mut user_spawns,
+                        &mut appearances,
+                        &mut visibilities,
+                    ),
+                    (
+                        LocalConnection {
+                            channel: connection_tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id,
+                            account_id: 1,
+                            status,
+                            zone_id: 0,
+                            connection_global_world_id: from_vec::<EntityId>(vec![
+                                user_id as u8,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                            ])
+                            .unwrap(),
+                            is_alive: true,
+                        },
+                        UserAppearance {
+                            name: format!("User{}", user_id),
+                            template_id: TemplateID {
+                                race: Race::Human,
+                                gender: Gender::Female,
+                                class: Class::Priest,
+                            },
+                            level: 65,
+                            details: vec![],
+                            shape: vec![],
+                            appearance: Customization::default(),
+                            appearance2: 100,
+                            show_face: true,
+                            show_style: true,
+                            guild_name: "".to_string(),
+                            guild_rank: "".to_string(),
+                        },
+                        Visibility {
+                            range: 2000,
+                            visible_entities,
+                        },
+                    ),
+                )
+            },
+        );
+
+        (connection_local_world_id, connection_rx_channel)
+    }
+
+    fn add_user_guild_changed(world: &World, connection_local_world_id: EntityId) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::UserGuildChanged {
+                        connection_local_world_id,
+                        guild_name: "Manhunter".to_string(),
+                        guild_rank: "Officer".to_string(),
+                    }),
+                );
+            },
+        );
+    }
+
+    fn assert_guild_name(message: EcsMessage, user_id: EntityId) {
+        match &*message {
+            Message::ResponseGuildName { packet, .. } => {
+                assert_eq!(packet.guild_name, "Manhunter");
+                assert_eq!(packet.guild_rank, "Officer");
+                assert_eq!(packet.game_id, user_id);
+            }
+            _ => panic!("Message is not a ResponseGuildName message"),
+        }
+    }
+
+    #[test]
+    fn test_user_guild_changed() -> Result<()> {
+        let world = World::new();
+        let (user_id, user_rx) = add_user(&world, 1, UserSpawnStatus::Spawned, HashSet::new());
+        let mut visible_entities = HashSet::new();
+        visible_entities.insert(user_id);
+        let (_near_id, near_rx) = add_user(&world, 2, UserSpawnStatus::Spawned, visible_entities);
+        let (_far_id, far_rx) = add_user(&world, 3, UserSpawnStatus::Spawned, HashSet::new());
+
+        add_user_guild_changed(&world, user_id);
+        world.run(appearance_system);
+
+        assert_guild_name(user_rx.try_recv()?, user_id);
+        assert_guild_name(near_rx.try_recv()?, user_id);
+        assert!(far_rx.is_empty());
+
+        world.run(|appearances: View<UserAppearance>| {
+            let appearance = appearances.try_get(user_id).unwrap();
+            assert_eq!(appearance.guild_name, "Manhunter");
+            assert_eq!(appearance.guild_rank, "Officer");
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_guild_changed_not_spawned() -> Result<()> {
+        let world = World::new();
+        let (user_id, user_rx) = add_user(&world, 1, UserSpawnStatus::Waiting, HashSet::new());
+
+        add_user_guild_changed(&world, user_id);
+        world.run(appearance_system);
+
+        assert!(user_rx.is_empty());
+        world.run(|appearances: View<UserAppearance>| {
+            assert_eq!(
+                appearances.try_get(user_id).unwrap().guild_name,
+                "Manhunter"
+            );
+        });
+
+        Ok(())
+    }
+}
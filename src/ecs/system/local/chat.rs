@@ -0,0 +1,274 @@
+use crate::ecs::component::{
+    LocalConnection, LocalUserSpawn, UserAppearance, UserSpawnStatus, Visibility,
+};
+use crate::ecs::message::Message::ResponseChat;
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::system::send_message;
+use crate::model::ChatChannel;
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use shipyard::*;
+use tracing::{debug, error, info_span};
+
+/// Delivers the messages of the local chat channels. Say, emote and greeting messages are
+/// delivered to the users that can see the author, area and trade messages to all users of
+/// the local world.
+pub fn chat_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<LocalConnection>,
+    user_spawns: View<LocalUserSpawn>,
+    appearances: View<UserAppearance>,
+    visibilities: View<Visibility>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::LocalChat {
+                connection_local_world_id,
+                channel,
+                message,
+            } => {
+                id_span!(connection_local_world_id);
+                if let Err(e) = handle_local_chat(
+                    *connection_local_world_id,
+                    *channel,
+                    &message,
+                    &connections,
+                    &user_spawns,
+                    &appearances,
+                    &visibilities,
+                ) {
+                    error!("Ignoring Message::LocalChat: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_local_chat(
+    author_id: EntityId,
+    channel: ChatChannel,
+    message: &str,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    appearances: &View<UserAppearance>,
+    visibilities: &View<Visibility>,
+) -> Result<()> {
+    debug!("Message::LocalChat incoming");
+
+    let (author_spawn, author_appearance) = (user_spawns, appearances)
+        .try_get(author_id)
+        .context(format!("Can't find local spawn for {:?}", author_id))?;
+    ensure!(
+        author_spawn.status == UserSpawnStatus::Spawned,
+        "User {:?} is not spawned and can't chat",
+        author_id
+    );
+
+    let is_world_channel = channel == ChatChannel::Area || channel == ChatChannel::Trade;
+    (connections, user_spawns, visibilities)
+        .iter()
+        .with_id()
+        .filter(|(_id, (_connection, spawn, _visibility))| spawn.status == UserSpawnStatus::Spawned)
+        .for_each(|(id, (connection, spawn, visibility))| {
+            if is_world_channel
+                || id == author_id
+                || visibility.visible_entities.contains(&author_id)
+            {
+                send_message(
+                    assemble_chat(
+                        spawn.connection_global_world_id,
+                        author_id,
+                        &author_appearance.name,
+                        channel,
+                        message,
+                    ),
+                    &connection.channel,
+                );
+            }
+        });
+
+    Ok(())
+}
+
+fn assemble_chat(
+    connection_global_world_id: EntityId,
+    author_id: EntityId,
+    author_name: &str,
+    channel: ChatChannel,
+    message: &str,
+) -> EcsMessage {
+    Box::new(ResponseChat {
+        connection_global_world_id,
+        packet: SChat {
+            author_name: author_name.to_string(),
+            message: message.to_string(),
+            channel,
+            user_id: author_id,
+            unk1: 0,
+            is_gm: false,
+            is_founder: false,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Class, Customization, Gender, Race, TemplateID};
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver};
+    use std::collections::HashSet;
+
+    fn add_user(
+        world: &World,
+        user_id: i32,
+        status: UserSpawnStatus,
+        visible_entities: HashSet<EntityId>,
+    ) -> (EntityId, Receiver<EcsMessage>) {
+        let (connection_tx_channel, connection_rx_channel) = channel(1024);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut appearances: ViewMut<UserAppearance>,
+             mut visibilities: ViewMut<Visibility>| {
+                entities.add_entity(
+                    (
+                        &mut connections,
+                        &mut user_spawns,
+                        &mut appearances,
+                        &mut visibilities,
+                    ),
+                    (
+                        LocalConnection {
+                            channel: connection_tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id,
+                            account_id: 1,
+                            status,
+                            zone_id: 0,
+                            connection_global_world_id: from_vec::<EntityId>(vec![
+                                user_id as u8,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                            ])
+                            .unwrap(),
+                            is_alive: true,
+                        },
+                        UserAppearance {
+                            name: format!("User{}", user_id),
+                            template_id: TemplateID {
+                                race: Race::Human,
+                                gender: Gender::Female,
+                                class: Class::Priest,
+                            },
+                            level: 65,
+                            details: vec![],
+                            shape: vec![],
+                            appearance: Customization::default(),
+                            appearance2: 100,
+                            show_face: true,
+                            show_style: true,
+                            guild_name: "".to_string(),
+                            guild_rank: "".to_string(),
+                        },
+                        Visibility {
+                            range: 2000,
+                            visible_entities,
+                        },
+                    ),
+                )
+            },
+        );
+
+        (connection_local_world_id, connection_rx_channel)
+    }
+
+    fn add_local_chat(world: &World, connection_local_world_id: EntityId, channel: ChatChannel) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::LocalChat {
+                        connection_local_world_id,
+                        channel,
+                        message: "<FONT>Hi</FONT>".to_string(),
+                    }),
+                );
+            },
+        );
+    }
+
+    fn assert_chat(message: EcsMessage, author_id: EntityId, channel: ChatChannel) {
+        match &*message {
+            Message::ResponseChat { packet, .. } => {
+                assert_eq!(packet.author_name, "User1");
+                assert_eq!(packet.message, "<FONT>Hi</FONT>");
+                assert_eq!(packet.channel, channel);
+                assert_eq!(packet.user_id, author_id);
+            }
+            _ => panic!("Message is not a ResponseChat message"),
+        }
+    }
+
+    #[test]
+    fn test_say() -> Result<()> {
+        let world = World::new();
+        let (author_id, author_rx) = add_user(&world, 1, UserSpawnStatus::Spawned, HashSet::new());
+        let mut visible_entities = HashSet::new();
+        visible_entities.insert(author_id);
+        let (_near_id, near_rx) = add_user(&world, 2, UserSpawnStatus::Spawned, visible_entities);
+        let (_far_id, far_rx) = add_user(&world, 3, UserSpawnStatus::Spawned, HashSet::new());
+
+        add_local_chat(&world, author_id, ChatChannel::Say);
+        world.run(chat_system);
+
+        assert_chat(author_rx.try_recv()?, author_id, ChatChannel::Say);
+        assert_chat(near_rx.try_recv()?, author_id, ChatChannel::Say);
+        assert!(far_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_area() -> Result<()> {
+        let world = World::new();
+        let (author_id, author_rx) = add_user(&world, 1, UserSpawnStatus::Spawned, HashSet::new());
+        let (_far_id, far_rx) = add_user(&world, 2, UserSpawnStatus::Spawned, HashSet::new());
+        let (_waiting_id, waiting_rx) =
+            add_user(&world, 3, UserSpawnStatus::Waiting, HashSet::new());
+
+        add_local_chat(&world, author_id, ChatChannel::Area);
+        world.run(chat_system);
+
+        assert_chat(author_rx.try_recv()?, author_id, ChatChannel::Area);
+        assert_chat(far_rx.try_recv()?, author_id, ChatChannel::Area);
+        assert!(waiting_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chat_not_spawned() -> Result<()> {
+        let world = World::new();
+        let (author_id, author_rx) = add_user(&world, 1, UserSpawnStatus::Waiting, HashSet::new());
+        let (_other_id, other_rx) = add_user(&world, 2, UserSpawnStatus::Spawned, HashSet::new());
+
+        add_local_chat(&world, author_id, ChatChannel::Area);
+        world.run(chat_system);
+
+        assert!(author_rx.is_empty());
+        assert!(other_rx.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1890 @@
+use crate::config::Configuration;
+use crate::ecs::component::{
+    Abnormalities, ActiveSkill, Ai, ExperienceSource, Health, LocalConnection, LocalUserSpawn,
+    Location, Mana, Npc, NpcSpawn, SkillState, UserAppearance, UserExperience, UserSpawnStatus,
+    UserStats, Visibility,
+};
+use crate::ecs::message::Message::{
+    ResponseActionEnd, ResponseActionStage, ResponseCannotStartSkill, ResponseCreatureChangeHp,
+    ResponseCreatureLife, ResponseEachSkillResult, ResponsePlayerChangeMp,
+    ResponseStartCooltimeSkill,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::{GlobalMessageChannel, GuildWarRegistry, SkillRegistry};
+use crate::ecs::system::local::abnormality::request_abnormality;
+use crate::ecs::system::local::experience::request_experience;
+use crate::ecs::system::local::guild_war::{assemble_guild_war_kill, can_attack_user};
+use crate::ecs::system::local::npc_ai::add_threat;
+use crate::ecs::system::local::{send_message_to_connection, send_to_observers};
+use crate::ecs::system::send_message;
+use crate::model::{Angle, Vec3f};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{bail, ensure, Context};
+use nalgebra::distance;
+use shipyard::*;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info_span};
+
+/// Type of the skills of users inside the skill IDs of the network protocol. The lower 32 bit
+/// hold the ID of the skill template.
+const USER_SKILL_TYPE: i64 = 0x0400_0000_0000_0000;
+
+/// Result type of a skill hit that deals damage.
+const DAMAGE_RESULT: i32 = 1;
+
+/// HP / MP changes that are caused by skills or by the server itself (regeneration, revival).
+const CHANGE_BY_SKILL: i32 = 1;
+const CHANGE_BY_SYSTEM: i32 = 0;
+
+/// Actions end once their duration passed or when the user starts another skill.
+const ACTION_END_FINISHED: i32 = 0;
+const ACTION_END_INTERRUPTED: i32 = 1;
+
+/// Interval in which users regenerate 5% of their mana.
+const MANA_REGENERATION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Changes of the fights that are shown to the users once all messages of a tick are handled.
+enum CombatEvent {
+    ManaChanged {
+        user_id: EntityId,
+        mana: Mana,
+        diff: i32,
+        change_type: i32,
+    },
+    CooldownStarted {
+        user_id: EntityId,
+        skill_id: i32,
+        cooldown: Duration,
+    },
+    ActionStarted {
+        user_id: EntityId,
+        active_skill: ActiveSkill,
+        location: Vec3f,
+        rotation: Angle,
+        destination: Vec3f,
+        moving: bool,
+    },
+    ActionEnded {
+        user_id: EntityId,
+        active_skill: ActiveSkill,
+        end_type: i32,
+    },
+    SkillHit {
+        source_id: EntityId,
+        target_id: EntityId,
+        active_skill: ActiveSkill,
+        damage: i64,
+    },
+    HealthChanged {
+        source_id: EntityId,
+        target_id: EntityId,
+        health: Health,
+        diff: i64,
+        change_type: i32,
+    },
+    LifeChanged {
+        user_id: EntityId,
+        is_alive: bool,
+    },
+    GuildWarKill {
+        killer_user_id: i32,
+        victim_user_id: i32,
+    },
+    NpcKilled {
+        killer_id: EntityId,
+        exp: i64,
+    },
+    BossKilled,
+}
+
+/// Handles the fights between the users of a local world. Skills cost mana, have a cooldown and
+/// hit all users in their range that the user of the skill is allowed to attack and all monsters
+/// in their range. Users without health points left die and stay dead until they revive
+/// themselves. The user that kills a monster receives it's experience. Killing a boss clears the
+/// dungeon for all users of the local world.
+pub fn combat_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<LocalConnection>,
+    mut user_spawns: ViewMut<LocalUserSpawn>,
+    locations: View<Location>,
+    appearances: View<UserAppearance>,
+    visibilities: View<Visibility>,
+    stats: View<UserStats>,
+    (
+        mut healths,
+        mut manas,
+        mut skill_states,
+        mut abnormalities,
+        mut npcs,
+        npc_spawns,
+        mut ais,
+        mut experiences,
+    ): (
+        ViewMut<Health>,
+        ViewMut<Mana>,
+        ViewMut<SkillState>,
+        ViewMut<Abnormalities>,
+        ViewMut<Npc>,
+        View<NpcSpawn>,
+        ViewMut<Ai>,
+        ViewMut<UserExperience>,
+    ),
+    entities: EntitiesView,
+    (skill_registry, config, guild_wars, global_world_channel): (
+        UniqueView<SkillRegistry>,
+        UniqueView<Configuration>,
+        UniqueView<GuildWarRegistry>,
+        UniqueView<GlobalMessageChannel>,
+    ),
+) {
+    let mut events = Vec::new();
+
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestLoadTopoFin {
+                connection_global_world_id,
+                connection_local_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_load_topo_fin(
+                    *connection_local_world_id,
+                    &user_spawns,
+                    &stats,
+                    &mut healths,
+                    &mut manas,
+                    &mut skill_states,
+                    &entities,
+                ) {
+                    error!("Ignoring Message::RequestLoadTopoFin: {:?}", e);
+                }
+            }
+            Message::RequestStartSkill {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_start_skill(
+                    *connection_local_world_id,
+                    &packet,
+                    &user_spawns,
+                    &appearances,
+                    &mut manas,
+                    &mut skill_states,
+                    &mut abnormalities,
+                    &skill_registry,
+                    &mut events,
+                ) {
+                    error!("Ignoring start skill request: {:?}", e);
+                    send_message_to_connection(
+                        assemble_cannot_start_skill(
+                            *connection_global_world_id,
+                            *connection_local_world_id,
+                            packet.skill_id,
+                        ),
+                        &connections,
+                    );
+                }
+            }
+            Message::RequestPressSkill {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_press_skill(
+                    *connection_local_world_id,
+                    &packet,
+                    &user_spawns,
+                    &appearances,
+                    &mut manas,
+                    &mut skill_states,
+                    &mut abnormalities,
+                    &skill_registry,
+                    &mut events,
+                ) {
+                    error!("Ignoring press skill request: {:?}", e);
+                    if packet.press {
+                        send_message_to_connection(
+                            assemble_cannot_start_skill(
+                                *connection_global_world_id,
+                                *connection_local_world_id,
+                                packet.skill_id,
+                            ),
+                            &connections,
+                        );
+                    }
+                }
+            }
+            Message::RequestCancelSkill {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_cancel_skill(
+                    *connection_local_world_id,
+                    &packet,
+                    &mut skill_states,
+                    &mut events,
+                ) {
+                    error!("Ignoring cancel skill request: {:?}", e);
+                }
+            }
+            Message::RequestReviveNow {
+                connection_global_world_id,
+                connection_local_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_revive_now(
+                    *connection_local_world_id,
+                    &mut user_spawns,
+                    &mut healths,
+                    &mut manas,
+                    &mut events,
+                ) {
+                    error!("Ignoring revive now request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other packets */ }
+        });
+
+    let now = Instant::now();
+    update_vitals(
+        now,
+        &user_spawns,
+        &stats,
+        &mut healths,
+        &mut manas,
+        &mut events,
+    );
+    apply_npc_hits(
+        &user_spawns,
+        &locations,
+        &stats,
+        &mut healths,
+        &skill_states,
+        &mut npcs,
+        &npc_spawns,
+        &mut ais,
+        &skill_registry,
+        &mut events,
+    );
+    apply_skill_hits(
+        &mut user_spawns,
+        &locations,
+        &stats,
+        &mut healths,
+        &mut skill_states,
+        &mut abnormalities,
+        &skill_registry,
+        &config,
+        &guild_wars,
+        &mut events,
+    );
+    end_skills(now, &mut skill_states, &mut events);
+
+    for event in &events {
+        if let CombatEvent::NpcKilled { killer_id, exp } = *event {
+            request_experience(&mut experiences, killer_id, exp, ExperienceSource::Kill);
+        }
+    }
+
+    send_events(
+        events,
+        &connections,
+        &user_spawns,
+        &locations,
+        &appearances,
+        &visibilities,
+        &global_world_channel,
+    );
+}
+
+/// Users get their health, mana and skill state once they are loaded into the world. Dead users
+/// stay dead until they revive themselves.
+fn handle_load_topo_fin(
+    connection_local_world_id: EntityId,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    stats: &View<UserStats>,
+    healths: &mut ViewMut<Health>,
+    manas: &mut ViewMut<Mana>,
+    skill_states: &mut ViewMut<SkillState>,
+    entities: &EntitiesView,
+) -> Result<()> {
+    debug!("Message::RequestLoadTopoFin incoming");
+
+    if healths.try_get(connection_local_world_id).is_ok() {
+        return Ok(());
+    }
+
+    let (spawn, user_stats) = (user_spawns, stats)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find stats of {:?}",
+            connection_local_world_id
+        ))?;
+    let max_hp = i64::from(user_stats.total.max_hp);
+    let max_mp = user_stats.total.max_mp;
+
+    entities.add_component(
+        (&mut *healths, &mut *manas, &mut *skill_states),
+        (
+            Health {
+                hp: if spawn.is_alive { max_hp } else { 0 },
+                max_hp,
+            },
+            Mana {
+                mp: max_mp,
+                max_mp,
+                regenerates_at: Instant::now() + MANA_REGENERATION_INTERVAL,
+            },
+            SkillState::default(),
+        ),
+        connection_local_world_id,
+    );
+
+    Ok(())
+}
+
+fn handle_start_skill(
+    connection_local_world_id: EntityId,
+    packet: &CStartSkill,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    appearances: &View<UserAppearance>,
+    manas: &mut ViewMut<Mana>,
+    skill_states: &mut ViewMut<SkillState>,
+    abnormalities: &mut ViewMut<Abnormalities>,
+    skill_registry: &SkillRegistry,
+    events: &mut Vec<CombatEvent>,
+) -> Result<()> {
+    debug!("Message::RequestStartSkill incoming");
+
+    start_skill(
+        connection_local_world_id,
+        packet,
+        user_spawns,
+        appearances,
+        manas,
+        skill_states,
+        abnormalities,
+        skill_registry,
+        events,
+    )
+}
+
+/// Skills that are pressed are used at the current location of the user and end once they are
+/// released.
+fn handle_press_skill(
+    connection_local_world_id: EntityId,
+    packet: &CPressSkill,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    appearances: &View<UserAppearance>,
+    manas: &mut ViewMut<Mana>,
+    skill_states: &mut ViewMut<SkillState>,
+    abnormalities: &mut ViewMut<Abnormalities>,
+    skill_registry: &SkillRegistry,
+    events: &mut Vec<CombatEvent>,
+) -> Result<()> {
+    debug!("Message::RequestPressSkill incoming");
+
+    if packet.press {
+        start_skill(
+            connection_local_world_id,
+            &CStartSkill {
+                skill_id: packet.skill_id,
+                rotation: packet.rotation,
+                location: packet.location,
+                destination: packet.location,
+                moving: false,
+                continuation: false,
+            },
+            user_spawns,
+            appearances,
+            manas,
+            skill_states,
+            abnormalities,
+            skill_registry,
+            events,
+        )
+    } else {
+        end_skill(
+            connection_local_world_id,
+            from_packet_skill_id(packet.skill_id),
+            ACTION_END_FINISHED,
+            skill_states,
+            events,
+        )
+    }
+}
+
+fn handle_cancel_skill(
+    connection_local_world_id: EntityId,
+    packet: &CCancelSkill,
+    skill_states: &mut ViewMut<SkillState>,
+    events: &mut Vec<CombatEvent>,
+) -> Result<()> {
+    debug!("Message::RequestCancelSkill incoming");
+
+    // The client decides why the skill is canceled.
+    end_skill(
+        connection_local_world_id,
+        from_packet_skill_id(packet.skill_id),
+        packet.cancel_type,
+        skill_states,
+        events,
+    )
+}
+
+/// Revives a dead user at it's current location with full health and mana.
+// TODO Consume the used revival item and move users without an item to the nearest village.
+fn handle_revive_now(
+    connection_local_world_id: EntityId,
+    user_spawns: &mut ViewMut<LocalUserSpawn>,
+    healths: &mut ViewMut<Health>,
+    manas: &mut ViewMut<Mana>,
+    events: &mut Vec<CombatEvent>,
+) -> Result<()> {
+    debug!("Message::RequestReviveNow incoming");
+
+    let (spawn, health, mana) = (user_spawns, healths, manas)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find health of {:?}",
+            connection_local_world_id
+        ))?;
+    ensure!(!spawn.is_alive, "User {} is not dead", spawn.user_id);
+
+    spawn.is_alive = true;
+    let hp_diff = health.max_hp - health.hp;
+    health.hp = health.max_hp;
+    let mp_diff = mana.max_mp - mana.mp;
+    mana.mp = mana.max_mp;
+
+    events.push(CombatEvent::LifeChanged {
+        user_id: connection_local_world_id,
+        is_alive: true,
+    });
+    events.push(CombatEvent::HealthChanged {
+        source_id: connection_local_world_id,
+        target_id: connection_local_world_id,
+        health: *health,
+        diff: hp_diff,
+        change_type: CHANGE_BY_SYSTEM,
+    });
+    events.push(CombatEvent::ManaChanged {
+        user_id: connection_local_world_id,
+        mana: *mana,
+        diff: mp_diff,
+        change_type: CHANGE_BY_SYSTEM,
+    });
+
+    Ok(())
+}
+
+/// Starts a skill of an user. The new skill interrupts the skill the user is currently using.
+/// The hits of the skill are applied at the end of the tick, the abnormalities of the skill on the
+/// user itself right away.
+fn start_skill(
+    connection_local_world_id: EntityId,
+    packet: &CStartSkill,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    appearances: &View<UserAppearance>,
+    manas: &mut ViewMut<Mana>,
+    skill_states: &mut ViewMut<SkillState>,
+    abnormalities: &mut ViewMut<Abnormalities>,
+    skill_registry: &SkillRegistry,
+    events: &mut Vec<CombatEvent>,
+) -> Result<()> {
+    let skill_id = from_packet_skill_id(packet.skill_id);
+    let skill = skill_registry
+        .get(skill_id)
+        .context(format!("Can't find skill {}", skill_id))?;
+
+    let (spawn, appearance, mana, skill_state) = (user_spawns, appearances, manas, skill_states)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find skill state of {:?}",
+            connection_local_world_id
+        ))?;
+    ensure!(
+        spawn.status == UserSpawnStatus::Spawned && spawn.is_alive,
+        "User {} needs to be alive to use skills",
+        spawn.user_id
+    );
+    if let Some(class) = skill.class {
+        ensure!(
+            class == appearance.template_id.class,
+            "Skill {} can't be used by class {:?}",
+            skill_id,
+            appearance.template_id.class
+        );
+    }
+
+    let now = Instant::now();
+    if let Some(ready_at) = skill_state.cooldowns.get(&skill_id) {
+        ensure!(*ready_at <= now, "Skill {} is still on cooldown", skill_id);
+    }
+    ensure!(
+        mana.mp >= skill.mp_cost,
+        "User {} doesn't have enough mana to use skill {}",
+        spawn.user_id,
+        skill_id
+    );
+
+    if skill.mp_cost > 0 {
+        mana.mp -= skill.mp_cost;
+        events.push(CombatEvent::ManaChanged {
+            user_id: connection_local_world_id,
+            mana: *mana,
+            diff: -skill.mp_cost,
+            change_type: CHANGE_BY_SKILL,
+        });
+    }
+    if skill.cooldown > Duration::from_secs(0) {
+        skill_state.cooldowns.insert(skill_id, now + skill.cooldown);
+        events.push(CombatEvent::CooldownStarted {
+            user_id: connection_local_world_id,
+            skill_id,
+            cooldown: skill.cooldown,
+        });
+    }
+    if let Some(active_skill) = skill_state.active_skill.take() {
+        events.push(CombatEvent::ActionEnded {
+            user_id: connection_local_world_id,
+            active_skill,
+            end_type: ACTION_END_INTERRUPTED,
+        });
+    }
+
+    skill_state.next_action_id = skill_state.next_action_id.wrapping_add(1);
+    let active_skill = ActiveSkill {
+        skill_id,
+        action_id: skill_state.next_action_id,
+        ends_at: now + skill.duration,
+        hits_applied: false,
+    };
+    skill_state.active_skill = Some(active_skill);
+    events.push(CombatEvent::ActionStarted {
+        user_id: connection_local_world_id,
+        active_skill,
+        location: packet.location,
+        rotation: packet.rotation,
+        destination: packet.destination,
+        moving: packet.moving,
+    });
+    for abnormality_id in &skill.self_abnormalities {
+        request_abnormality(
+            abnormalities,
+            connection_local_world_id,
+            *abnormality_id,
+            connection_local_world_id,
+        );
+    }
+
+    Ok(())
+}
+
+/// Ends the active skill of an user if the user is still using the given skill.
+fn end_skill(
+    connection_local_world_id: EntityId,
+    skill_id: i32,
+    end_type: i32,
+    skill_states: &mut ViewMut<SkillState>,
+    events: &mut Vec<CombatEvent>,
+) -> Result<()> {
+    let skill_state = skill_states
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find skill state of {:?}",
+            connection_local_world_id
+        ))?;
+
+    match skill_state.active_skill {
+        Some(active_skill) if active_skill.skill_id == skill_id => {
+            skill_state.active_skill = None;
+            events.push(CombatEvent::ActionEnded {
+                user_id: connection_local_world_id,
+                active_skill,
+                end_type,
+            });
+            Ok(())
+        }
+        _ => bail!("User isn't using skill {}", skill_id),
+    }
+}
+
+/// Keeps the maximal health and mana in sync with the stats of the users and regenerates the
+/// mana of living users.
+fn update_vitals(
+    now: Instant,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    stats: &View<UserStats>,
+    healths: &mut ViewMut<Health>,
+    manas: &mut ViewMut<Mana>,
+    events: &mut Vec<CombatEvent>,
+) {
+    (user_spawns, stats, &mut *healths, &mut *manas)
+        .iter()
+        .with_id()
+        .for_each(|(id, (spawn, user_stats, health, mana))| {
+            let max_hp = i64::from(user_stats.total.max_hp);
+            if health.max_hp != max_hp {
+                health.max_hp = max_hp;
+                health.hp = health.hp.min(max_hp);
+                events.push(CombatEvent::HealthChanged {
+                    source_id: id,
+                    target_id: id,
+                    health: *health,
+                    diff: 0,
+                    change_type: CHANGE_BY_SYSTEM,
+                });
+            }
+
+            let mut changed = false;
+            let mut diff = 0;
+            if mana.max_mp != user_stats.total.max_mp {
+                mana.max_mp = user_stats.total.max_mp;
+                mana.mp = mana.mp.min(mana.max_mp);
+                changed = true;
+            }
+            if now >= mana.regenerates_at {
+                mana.regenerates_at = now + MANA_REGENERATION_INTERVAL;
+                if spawn.is_alive && mana.mp < mana.max_mp {
+                    diff = (mana.max_mp / 20).max(1).min(mana.max_mp - mana.mp);
+                    mana.mp += diff;
+                    changed = true;
+                }
+            }
+            if changed {
+                events.push(CombatEvent::ManaChanged {
+                    user_id: id,
+                    mana: *mana,
+                    diff,
+                    change_type: CHANGE_BY_SYSTEM,
+                });
+            }
+        });
+}
+
+/// Applies the hits of all skills that were started in this tick. A skill hits all living users
+/// in it's range that the user of the skill is allowed to attack and applies it's abnormalities
+/// to them.
+fn apply_skill_hits(
+    user_spawns: &mut ViewMut<LocalUserSpawn>,
+    locations: &View<Location>,
+    stats: &View<UserStats>,
+    healths: &mut ViewMut<Health>,
+    skill_states: &mut ViewMut<SkillState>,
+    abnormalities: &mut ViewMut<Abnormalities>,
+    skill_registry: &SkillRegistry,
+    config: &Configuration,
+    guild_wars: &GuildWarRegistry,
+    events: &mut Vec<CombatEvent>,
+) {
+    let started_skills: Vec<(EntityId, ActiveSkill)> = (&mut *skill_states)
+        .iter()
+        .with_id()
+        .filter_map(|(id, skill_state)| match &mut skill_state.active_skill {
+            Some(active_skill) if !active_skill.hits_applied => {
+                active_skill.hits_applied = true;
+                Some((id, *active_skill))
+            }
+            _ => None,
+        })
+        .collect();
+
+    for (attacker_id, active_skill) in started_skills {
+        let skill = match skill_registry.get(active_skill.skill_id) {
+            Some(skill) if skill.damage > 0 => skill,
+            _ => continue,
+        };
+        // Users that were killed in this tick can't hit anyone anymore.
+        let (attacker_user_id, attacker_point, attack) =
+            match (&*user_spawns, locations, stats).try_get(attacker_id) {
+                Ok((spawn, location, user_stats)) if spawn.is_alive => {
+                    (spawn.user_id, location.point, user_stats.total.attack)
+                }
+                _ => continue,
+            };
+
+        let targets: Vec<(EntityId, i32, i32)> = (&*user_spawns, locations, stats, &*healths)
+            .iter()
+            .with_id()
+            .filter(|(id, (spawn, location, _user_stats, health))| {
+                *id != attacker_id
+                    && spawn.status == UserSpawnStatus::Spawned
+                    && spawn.is_alive
+                    && health.hp > 0
+                    && distance(&attacker_point, &location.point) <= skill.range
+                    && can_attack_user(config, guild_wars, attacker_user_id, spawn.user_id)
+            })
+            .map(|(id, (spawn, _location, user_stats, _health))| {
+                (id, spawn.user_id, user_stats.total.defence)
+            })
+            .collect();
+
+        for (target_id, target_user_id, defence) in targets {
+            let damage = calculate_damage(skill.damage, attack, defence);
+            let health = match healths.try_get(target_id) {
+                Ok(health) => health,
+                Err(_) => continue,
+            };
+            health.hp = (health.hp - damage).max(0);
+            let health = *health;
+
+            events.push(CombatEvent::SkillHit {
+                source_id: attacker_id,
+                target_id,
+                active_skill,
+                damage,
+            });
+            events.push(CombatEvent::HealthChanged {
+                source_id: attacker_id,
+                target_id,
+                health,
+                diff: -damage,
+                change_type: CHANGE_BY_SKILL,
+            });
+            for abnormality_id in &skill.target_abnormalities {
+                request_abnormality(abnormalities, target_id, *abnormality_id, attacker_id);
+            }
+
+            if health.hp == 0 {
+                if let Ok(spawn) = user_spawns.try_get(target_id) {
+                    spawn.is_alive = false;
+                }
+                if let Ok(skill_state) = skill_states.try_get(target_id) {
+                    skill_state.active_skill = None;
+                }
+                events.push(CombatEvent::LifeChanged {
+                    user_id: target_id,
+                    is_alive: false,
+                });
+                if guild_wars.are_enemies(attacker_user_id, target_user_id) {
+                    events.push(CombatEvent::GuildWarKill {
+                        killer_user_id: attacker_user_id,
+                        victim_user_id: target_user_id,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Applies the hits of all skills that were started in this tick on the monsters in their range.
+/// The damage of a hit counts as threat of the user for the monster. Villagers can't be hit.
+fn apply_npc_hits(
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    locations: &View<Location>,
+    stats: &View<UserStats>,
+    healths: &mut ViewMut<Health>,
+    skill_states: &ViewMut<SkillState>,
+    npcs: &mut ViewMut<Npc>,
+    npc_spawns: &View<NpcSpawn>,
+    ais: &mut ViewMut<Ai>,
+    skill_registry: &SkillRegistry,
+    events: &mut Vec<CombatEvent>,
+) {
+    let started_skills: Vec<(EntityId, ActiveSkill)> = skill_states
+        .iter()
+        .with_id()
+        .filter_map(|(id, skill_state)| match skill_state.active_skill {
+            Some(active_skill) if !active_skill.hits_applied => Some((id, active_skill)),
+            _ => None,
+        })
+        .collect();
+
+    for (attacker_id, active_skill) in started_skills {
+        let skill = match skill_registry.get(active_skill.skill_id) {
+            Some(skill) if skill.damage > 0 => skill,
+            _ => continue,
+        };
+        let (attacker_point, attack) = match (user_spawns, locations, stats).try_get(attacker_id) {
+            Ok((spawn, location, user_stats)) if spawn.is_alive => {
+                (location.point, user_stats.total.attack)
+            }
+            _ => continue,
+        };
+
+        let targets: Vec<(EntityId, i32, i64, bool)> = (&*npcs, locations)
+            .iter()
+            .with_id()
+            .filter(|(_id, (npc, location))| {
+                !npc.is_villager
+                    && npc.is_alive
+                    && distance(&attacker_point, &location.point) <= skill.range
+            })
+            .filter_map(|(id, (npc, _location))| {
+                npc_spawns.try_get(npc.spawn_id).ok().map(|spawn| {
+                    let template = &spawn.template;
+                    (id, template.defence, template.exp, template.is_boss)
+                })
+            })
+            .collect();
+
+        for (target_id, defence, exp, is_boss) in targets {
+            let damage = calculate_damage(skill.damage, attack, defence);
+            let health = match healths.try_get(target_id) {
+                Ok(health) => health,
+                Err(_) => continue,
+            };
+            health.hp = (health.hp - damage).max(0);
+            let health = *health;
+            if let Ok(ai) = ais.try_get(target_id) {
+                add_threat(ai, attacker_id, damage);
+            }
+
+            events.push(CombatEvent::SkillHit {
+                source_id: attacker_id,
+                target_id,
+                active_skill,
+                damage,
+            });
+            events.push(CombatEvent::HealthChanged {
+                source_id: attacker_id,
+                target_id,
+                health,
+                diff: -damage,
+                change_type: CHANGE_BY_SKILL,
+            });
+
+            if health.hp == 0 {
+                if let Ok(npc) = npcs.try_get(target_id) {
+                    npc.is_alive = false;
+                }
+                events.push(CombatEvent::LifeChanged {
+                    user_id: target_id,
+                    is_alive: false,
+                });
+                events.push(CombatEvent::NpcKilled {
+                    killer_id: attacker_id,
+                    exp,
+                });
+                if is_boss {
+                    events.push(CombatEvent::BossKilled);
+                }
+            }
+        }
+    }
+}
+
+/// Ends the skills whose duration passed and forgets the expired cooldowns.
+fn end_skills(now: Instant, skill_states: &mut ViewMut<SkillState>, events: &mut Vec<CombatEvent>) {
+    (&mut *skill_states)
+        .iter()
+        .with_id()
+        .for_each(|(id, skill_state)| {
+            skill_state
+                .cooldowns
+                .retain(|_skill_id, ready_at| *ready_at > now);
+
+            if let Some(active_skill) = skill_state.active_skill {
+                if active_skill.ends_at <= now {
+                    skill_state.active_skill = None;
+                    events.push(CombatEvent::ActionEnded {
+                        user_id: id,
+                        active_skill,
+                        end_type: ACTION_END_FINISHED,
+                    });
+                }
+            }
+        });
+}
+
+/// Defence reduces the damage of a skill. Every hit deals at least one damage.
+pub fn calculate_damage(damage: i32, attack: i32, defence: i32) -> i64 {
+    let attack = i64::from(attack.max(0));
+    let defence = i64::from(defence.max(0));
+    (i64::from(damage) * attack / (attack + defence).max(1)).max(1)
+}
+
+/// Shows the changes of the fights to the users. Changes of the mana and the cooldowns are only
+/// shown to the user itself, all other changes to all users that can see the affected user.
+fn send_events(
+    events: Vec<CombatEvent>,
+    connections: &View<LocalConnection>,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    locations: &View<Location>,
+    appearances: &View<UserAppearance>,
+    visibilities: &View<Visibility>,
+    global_world_channel: &GlobalMessageChannel,
+) {
+    for event in events {
+        match event {
+            CombatEvent::ManaChanged {
+                user_id,
+                mana,
+                diff,
+                change_type,
+            } => {
+                if let Ok(spawn) = user_spawns.try_get(user_id) {
+                    send_message_to_connection(
+                        Box::new(ResponsePlayerChangeMp {
+                            connection_global_world_id: spawn.connection_global_world_id,
+                            connection_local_world_id: user_id,
+                            packet: SPlayerChangeMp {
+                                current_mp: mana.mp,
+                                max_mp: mana.max_mp,
+                                diff,
+                                change_type,
+                                target_id: user_id,
+                                source_id: user_id,
+                            },
+                        }),
+                        connections,
+                    );
+                }
+            }
+            CombatEvent::CooldownStarted {
+                user_id,
+                skill_id,
+                cooldown,
+            } => {
+                if let Ok(spawn) = user_spawns.try_get(user_id) {
+                    send_message_to_connection(
+                        Box::new(ResponseStartCooltimeSkill {
+                            connection_global_world_id: spawn.connection_global_world_id,
+                            connection_local_world_id: user_id,
+                            packet: SStartCooltimeSkill {
+                                skill_id: to_packet_skill_id(skill_id),
+                                cooldown: cooldown.as_millis() as i32,
+                            },
+                        }),
+                        connections,
+                    );
+                }
+            }
+            CombatEvent::ActionStarted {
+                user_id,
+                active_skill,
+                location,
+                rotation,
+                destination,
+                moving,
+            } => {
+                if let Ok(appearance) = appearances.try_get(user_id) {
+                    send_to_observers(
+                        user_id,
+                        connections,
+                        user_spawns,
+                        visibilities,
+                        |connection_global_world_id, connection_local_world_id| {
+                            Box::new(ResponseActionStage {
+                                connection_global_world_id,
+                                connection_local_world_id,
+                                packet: SActionStage {
+                                    user_id,
+                                    location,
+                                    rotation,
+                                    template_id: appearance.template_id.clone(),
+                                    skill_id: to_packet_skill_id(active_skill.skill_id),
+                                    stage: 0,
+                                    speed: 1.0,
+                                    action_id: active_skill.action_id,
+                                    moving,
+                                    destination,
+                                },
+                            })
+                        },
+                    );
+                }
+            }
+            CombatEvent::ActionEnded {
+                user_id,
+                active_skill,
+                end_type,
+            } => {
+                if let Ok((location, appearance)) = (locations, appearances).try_get(user_id) {
+                    send_to_observers(
+                        user_id,
+                        connections,
+                        user_spawns,
+                        visibilities,
+                        |connection_global_world_id, connection_local_world_id| {
+                            Box::new(ResponseActionEnd {
+                                connection_global_world_id,
+                                connection_local_world_id,
+                                packet: SActionEnd {
+                                    user_id,
+                                    location: location.point.into(),
+                                    rotation: Angle::from(location.rotation),
+                                    template_id: appearance.template_id.clone(),
+                                    skill_id: to_packet_skill_id(active_skill.skill_id),
+                                    end_type,
+                                    action_id: active_skill.action_id,
+                                },
+                            })
+                        },
+                    );
+                }
+            }
+            CombatEvent::SkillHit {
+                source_id,
+                target_id,
+                active_skill,
+                damage,
+            } => {
+                if let Ok(appearance) = appearances.try_get(source_id) {
+                    send_to_observers(
+                        target_id,
+                        connections,
+                        user_spawns,
+                        visibilities,
+                        |connection_global_world_id, connection_local_world_id| {
+                            Box::new(ResponseEachSkillResult {
+                                connection_global_world_id,
+                                connection_local_world_id,
+                                packet: SEachSkillResult {
+                                    source_id,
+                                    target_id,
+                                    template_id: appearance.template_id.clone(),
+                                    skill_id: to_packet_skill_id(active_skill.skill_id),
+                                    stage: 0,
+                                    action_id: active_skill.action_id,
+                                    value: damage,
+                                    result_type: DAMAGE_RESULT,
+                                    crit: false,
+                                },
+                            })
+                        },
+                    );
+                }
+            }
+            CombatEvent::HealthChanged {
+                source_id,
+                target_id,
+                health,
+                diff,
+                change_type,
+            } => {
+                send_to_observers(
+                    target_id,
+                    connections,
+                    user_spawns,
+                    visibilities,
+                    |connection_global_world_id, connection_local_world_id| {
+                        Box::new(ResponseCreatureChangeHp {
+                            connection_global_world_id,
+                            connection_local_world_id,
+                            packet: SCreatureChangeHp {
+                                current_hp: health.hp,
+                                max_hp: health.max_hp,
+                                diff,
+                                change_type,
+                                target_id,
+                                source_id,
+                                crit: false,
+                            },
+                        })
+                    },
+                );
+            }
+            CombatEvent::LifeChanged { user_id, is_alive } => {
+                if let Ok(location) = locations.try_get(user_id) {
+                    send_to_observers(
+                        user_id,
+                        connections,
+                        user_spawns,
+                        visibilities,
+                        |connection_global_world_id, connection_local_world_id| {
+                            Box::new(ResponseCreatureLife {
+                                connection_global_world_id,
+                                connection_local_world_id,
+                                packet: SCreatureLife {
+                                    target_id: user_id,
+                                    location: location.point.into(),
+                                    is_alive,
+                                },
+                            })
+                        },
+                    );
+                }
+            }
+            CombatEvent::GuildWarKill {
+                killer_user_id,
+                victim_user_id,
+            } => {
+                send_message(
+                    assemble_guild_war_kill(killer_user_id, victim_user_id),
+                    &global_world_channel.channel,
+                );
+            }
+            CombatEvent::NpcKilled { .. } => { /* Handled by the experience system */ }
+            CombatEvent::BossKilled => send_dungeon_cleared(user_spawns, global_world_channel),
+        }
+    }
+}
+
+/// The local world of a dungeon is the instance of the users inside, so all of them cleared the
+/// dungeon. The dungeon manager ignores the kills of bosses outside of dungeons.
+fn send_dungeon_cleared(
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    global_world_channel: &GlobalMessageChannel,
+) {
+    for spawn in user_spawns
+        .iter()
+        .filter(|spawn| spawn.status == UserSpawnStatus::Spawned)
+    {
+        send_message(
+            Box::new(Message::DungeonCleared {
+                connection_global_world_id: spawn.connection_global_world_id,
+                zone_id: spawn.zone_id,
+            }),
+            &global_world_channel.channel,
+        );
+    }
+}
+
+/// Returns the skill ID of a skill template inside the network protocol.
+pub fn to_packet_skill_id(skill_id: i32) -> i64 {
+    USER_SKILL_TYPE | i64::from(skill_id)
+}
+
+fn from_packet_skill_id(skill_id: i64) -> i32 {
+    (skill_id & 0xFFFF_FFFF) as i32
+}
+
+fn assemble_cannot_start_skill(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    skill_id: i64,
+) -> EcsMessage {
+    Box::new(ResponseCannotStartSkill {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SCannotStartSkill { skill_id },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::{AiState, ExperienceRequest};
+    use crate::ecs::resource::{DeletionList, NpcSpawnTemplate, NpcTemplate, SkillTemplate};
+    use crate::ecs::system::common::cleaner_system;
+    use crate::model::{Class, Customization, Gender, Race, TemplateID, BASE_STATS};
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver};
+    use nalgebra::{Point3, Rotation3, Vector3};
+    use std::collections::HashSet;
+
+    const STRIKE: i32 = 10100;
+    const CHARGE: i32 = 10200;
+    const FINISHER: i32 = 10300;
+    const HEAL: i32 = 10400;
+    const METEOR: i32 = 10500;
+
+    struct TestUser {
+        user_id: i32,
+        connection_global_world_id: EntityId,
+        connection_local_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn get_skills() -> SkillRegistry {
+        SkillRegistry::new(vec![
+            SkillTemplate {
+                id: STRIKE,
+                class: Some(Class::Warrior),
+                mp_cost: 20,
+                cooldown: Duration::from_secs(60),
+                damage: 100,
+                range: 50.0,
+                ..SkillTemplate::default()
+            },
+            SkillTemplate {
+                id: CHARGE,
+                duration: Duration::from_secs(60),
+                ..SkillTemplate::default()
+            },
+            SkillTemplate {
+                id: FINISHER,
+                damage: 10000,
+                range: 50.0,
+                ..SkillTemplate::default()
+            },
+            SkillTemplate {
+                id: HEAL,
+                class: Some(Class::Priest),
+                ..SkillTemplate::default()
+            },
+            SkillTemplate {
+                id: METEOR,
+                mp_cost: 1000,
+                damage: 100,
+                range: 500.0,
+                ..SkillTemplate::default()
+            },
+        ])
+    }
+
+    fn setup(pvp: bool, guild_wars: GuildWarRegistry) -> (World, Receiver<EcsMessage>) {
+        let (tx_channel, rx_channel) = channel(1024);
+        let mut config = Configuration::default();
+        config.game.pvp = pvp;
+
+        let world = World::new();
+        world.add_unique(get_skills());
+        world.add_unique(config);
+        world.add_unique(guild_wars);
+        world.add_unique(GlobalMessageChannel {
+            channel: tx_channel,
+        });
+        world.add_unique(DeletionList(Vec::default()));
+        (world, rx_channel)
+    }
+
+    /// Spawns a warrior at the given x coordinate and loads it into the world.
+    fn add_user(world: &World, num: i32, x: f32, is_alive: bool) -> Result<TestUser> {
+        let connection_global_world_id =
+            from_vec::<EntityId>(vec![num as u8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut locations: ViewMut<Location>,
+             mut appearances: ViewMut<UserAppearance>,
+             mut visibilities: ViewMut<Visibility>,
+             mut stats: ViewMut<UserStats>| {
+                entities.add_entity(
+                    (
+                        &mut connections,
+                        &mut user_spawns,
+                        &mut locations,
+                        &mut appearances,
+                        &mut visibilities,
+                        &mut stats,
+                    ),
+                    (
+                        LocalConnection {
+                            channel: tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id: num,
+                            account_id: i64::from(num),
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_global_world_id,
+                            is_alive,
+                        },
+                        Location {
+                            point: Point3::new(x, 0.0, 0.0),
+                            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+                        },
+                        UserAppearance {
+                            name: format!("User{}", num),
+                            template_id: TemplateID {
+                                race: Race::Human,
+                                gender: Gender::Male,
+                                class: Class::Warrior,
+                            },
+                            level: 65,
+                            details: vec![],
+                            shape: vec![],
+                            appearance: Customization::default(),
+                            appearance2: 100,
+                            show_face: true,
+                            show_style: true,
+                            guild_name: "".to_string(),
+                            guild_rank: "".to_string(),
+                        },
+                        Visibility {
+                            range: 100,
+                            visible_entities: HashSet::new(),
+                        },
+                        UserStats {
+                            base: BASE_STATS,
+                            total: BASE_STATS,
+                        },
+                    ),
+                )
+            },
+        );
+
+        let user = TestUser {
+            user_id: num,
+            connection_global_world_id,
+            connection_local_world_id,
+            rx: rx_channel,
+        };
+        send(
+            world,
+            Message::RequestLoadTopoFin {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet: CLoadTopoFin {},
+            },
+        );
+        Ok(user)
+    }
+
+    /// Lets the users see each other.
+    fn show_each_other(world: &World, users: &[&TestUser]) {
+        world.run(|mut visibilities: ViewMut<Visibility>| {
+            for user in users {
+                let visibility = (&mut visibilities)
+                    .try_get(user.connection_local_world_id)
+                    .unwrap();
+                for other in users {
+                    if other.connection_local_world_id != user.connection_local_world_id {
+                        visibility
+                            .visible_entities
+                            .insert(other.connection_local_world_id);
+                    }
+                }
+            }
+        });
+    }
+
+    fn send(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(combat_system);
+        world.run(cleaner_system);
+    }
+
+    fn start_skill(world: &World, user: &TestUser, skill_id: i32) {
+        send(
+            world,
+            Message::RequestStartSkill {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CStartSkill {
+                    skill_id: to_packet_skill_id(skill_id),
+                    rotation: Angle::default(),
+                    location: Vec3f::default(),
+                    destination: Vec3f::default(),
+                    moving: false,
+                    continuation: false,
+                },
+            },
+        );
+    }
+
+    fn revive(world: &World, user: &TestUser) {
+        send(
+            world,
+            Message::RequestReviveNow {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CReviveNow {
+                    revive_type: 0,
+                    id: 0,
+                },
+            },
+        );
+    }
+
+    fn received(rx: &Receiver<EcsMessage>) -> Vec<EcsMessage> {
+        let mut messages = Vec::new();
+        while let Ok(message) = rx.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn find_packet<T, F>(messages: &[EcsMessage], f: F) -> Option<T>
+    where
+        F: Fn(&Message) -> Option<T>,
+    {
+        messages.iter().find_map(|message| f(&**message))
+    }
+
+    fn get_health(world: &World, user: &TestUser) -> Health {
+        world.run(|healths: View<Health>| *healths.try_get(user.connection_local_world_id).unwrap())
+    }
+
+    fn get_mana(world: &World, user: &TestUser) -> Mana {
+        world.run(|manas: View<Mana>| *manas.try_get(user.connection_local_world_id).unwrap())
+    }
+
+    fn is_alive(world: &World, user: &TestUser) -> bool {
+        world.run(|user_spawns: View<LocalUserSpawn>| {
+            user_spawns
+                .try_get(user.connection_local_world_id)
+                .unwrap()
+                .is_alive
+        })
+    }
+
+    #[test]
+    fn test_load_topo_fin() -> Result<()> {
+        let (world, _global_rx) = setup(true, GuildWarRegistry::default());
+        let user = add_user(&world, 1, 0.0, true)?;
+        let dead_user = add_user(&world, 2, 0.0, false)?;
+
+        assert_eq!(
+            get_health(&world, &user),
+            Health {
+                hp: 200,
+                max_hp: 200
+            }
+        );
+        assert_eq!(get_mana(&world, &user).mp, 100);
+        assert_eq!(get_health(&world, &dead_user).hp, 0);
+        assert_eq!(get_health(&world, &dead_user).max_hp, 200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_skill() -> Result<()> {
+        let (world, _global_rx) = setup(true, GuildWarRegistry::default());
+        let user = add_user(&world, 1, 0.0, true)?;
+        let target = add_user(&world, 2, 10.0, true)?;
+        let bystander = add_user(&world, 3, 1000.0, true)?;
+        show_each_other(&world, &[&user, &target, &bystander]);
+
+        start_skill(&world, &user, STRIKE);
+
+        // Mana and cooldown are only shown to the user itself
+        let user_messages = received(&user.rx);
+        let target_messages = received(&target.rx);
+        let bystander_messages = received(&bystander.rx);
+        let mp = find_packet(&user_messages, |message| match message {
+            ResponsePlayerChangeMp { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(mp.current_mp, 80);
+        assert_eq!(mp.diff, -20);
+        let cooldown = find_packet(&user_messages, |message| match message {
+            ResponseStartCooltimeSkill { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(cooldown.skill_id, to_packet_skill_id(STRIKE));
+        assert_eq!(cooldown.cooldown, 60000);
+        assert_eq!(get_mana(&world, &user).mp, 80);
+        assert!(find_packet(&target_messages, |message| match message {
+            ResponsePlayerChangeMp { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .is_none());
+
+        // All users see the hit of the target, the bystander is out of range
+        for messages in [user_messages, target_messages, bystander_messages].iter() {
+            let stage = find_packet(&messages, |message| match message {
+                ResponseActionStage { packet, .. } => Some(packet.clone()),
+                _ => None,
+            })
+            .unwrap();
+            assert_eq!(stage.user_id, user.connection_local_world_id);
+            assert_eq!(stage.skill_id, to_packet_skill_id(STRIKE));
+
+            let hit = find_packet(&messages, |message| match message {
+                ResponseEachSkillResult { packet, .. } => Some(packet.clone()),
+                _ => None,
+            })
+            .unwrap();
+            assert_eq!(hit.source_id, user.connection_local_world_id);
+            assert_eq!(hit.target_id, target.connection_local_world_id);
+            assert_eq!(hit.value, 50);
+
+            let hp = find_packet(&messages, |message| match message {
+                ResponseCreatureChangeHp { packet, .. } => Some(packet.clone()),
+                _ => None,
+            })
+            .unwrap();
+            assert_eq!(hp.current_hp, 150);
+            assert_eq!(hp.diff, -50);
+        }
+        assert_eq!(get_health(&world, &target).hp, 150);
+        assert_eq!(get_health(&world, &bystander).hp, 200);
+        assert_eq!(get_health(&world, &user).hp, 200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cannot_start_skill() -> Result<()> {
+        let (world, _global_rx) = setup(true, GuildWarRegistry::default());
+        let user = add_user(&world, 1, 0.0, true)?;
+        let dead_user = add_user(&world, 2, 0.0, false)?;
+
+        // Unknown skills, skills of other classes and skills that cost too much mana
+        for skill_id in [1, HEAL, METEOR].iter() {
+            start_skill(&world, &user, *skill_id);
+            let messages = received(&user.rx);
+            assert_eq!(messages.len(), 1);
+            let packet = find_packet(&messages, |message| match message {
+                ResponseCannotStartSkill { packet, .. } => Some(packet.clone()),
+                _ => None,
+            })
+            .unwrap();
+            assert_eq!(packet.skill_id, to_packet_skill_id(*skill_id));
+        }
+        assert_eq!(get_mana(&world, &user).mp, 100);
+
+        // Skills on cooldown
+        start_skill(&world, &user, STRIKE);
+        received(&user.rx);
+        start_skill(&world, &user, STRIKE);
+        let messages = received(&user.rx);
+        assert_eq!(messages.len(), 1);
+        assert!(find_packet(&messages, |message| match message {
+            ResponseCannotStartSkill { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .is_some());
+        assert_eq!(get_mana(&world, &user).mp, 80);
+
+        // Dead users
+        start_skill(&world, &dead_user, CHARGE);
+        let messages = received(&dead_user.rx);
+        assert_eq!(messages.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancel_and_interrupt_skill() -> Result<()> {
+        let (world, _global_rx) = setup(true, GuildWarRegistry::default());
+        let user = add_user(&world, 1, 0.0, true)?;
+
+        start_skill(&world, &user, CHARGE);
+        let messages = received(&user.rx);
+        assert!(find_packet(&messages, |message| match message {
+            ResponseActionEnd { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .is_none());
+
+        // A new skill interrupts the current one
+        start_skill(&world, &user, CHARGE);
+        let messages = received(&user.rx);
+        let end = find_packet(&messages, |message| match message {
+            ResponseActionEnd { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(end.end_type, ACTION_END_INTERRUPTED);
+        assert_eq!(end.action_id, 1);
+
+        // Only the active skill can be canceled
+        for _ in 0..2 {
+            send(
+                &world,
+                Message::RequestCancelSkill {
+                    connection_global_world_id: user.connection_global_world_id,
+                    connection_local_world_id: user.connection_local_world_id,
+                    packet: CCancelSkill {
+                        skill_id: to_packet_skill_id(CHARGE),
+                        cancel_type: 2,
+                    },
+                },
+            );
+        }
+        let messages = received(&user.rx);
+        assert_eq!(messages.len(), 1);
+        let end = find_packet(&messages, |message| match message {
+            ResponseActionEnd { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(end.end_type, 2);
+        assert_eq!(end.action_id, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_death_and_revive() -> Result<()> {
+        let mut guild_wars = GuildWarRegistry::default();
+        guild_wars.add_war(1, 2);
+        guild_wars.members.insert(1, 1);
+        guild_wars.members.insert(2, 2);
+        let (world, global_rx) = setup(false, guild_wars);
+        let user = add_user(&world, 1, 0.0, true)?;
+        let target = add_user(&world, 2, 10.0, true)?;
+        show_each_other(&world, &[&user, &target]);
+
+        start_skill(&world, &user, FINISHER);
+        assert_eq!(get_health(&world, &target).hp, 0);
+        assert!(!is_alive(&world, &target));
+        for test_user in [&user, &target].iter() {
+            let messages = received(&test_user.rx);
+            let life = find_packet(&messages, |message| match message {
+                ResponseCreatureLife { packet, .. } => Some(packet.clone()),
+                _ => None,
+            })
+            .unwrap();
+            assert_eq!(life.target_id, target.connection_local_world_id);
+            assert!(!life.is_alive);
+        }
+
+        // The kill counts for the guild war
+        match &*global_rx.try_recv()? {
+            Message::GuildWarKill {
+                killer_user_id,
+                victim_user_id,
+            } => {
+                assert_eq!(*killer_user_id, user.user_id);
+                assert_eq!(*victim_user_id, target.user_id);
+            }
+            _ => panic!("Message is not a GuildWarKill message"),
+        }
+
+        // Dead users can't be hit again, living users can't revive
+        start_skill(&world, &user, FINISHER);
+        assert!(global_rx.is_empty());
+        revive(&world, &user);
+        assert!(is_alive(&world, &user));
+        assert_eq!(get_health(&world, &user).hp, 200);
+
+        revive(&world, &target);
+        assert!(is_alive(&world, &target));
+        assert_eq!(get_health(&world, &target).hp, 200);
+        assert_eq!(get_mana(&world, &target).mp, 100);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_hits_without_pvp() -> Result<()> {
+        let (world, _global_rx) = setup(false, GuildWarRegistry::default());
+        let user = add_user(&world, 1, 0.0, true)?;
+        let target = add_user(&world, 2, 10.0, true)?;
+        show_each_other(&world, &[&user, &target]);
+
+        start_skill(&world, &user, FINISHER);
+        assert_eq!(get_health(&world, &target).hp, 200);
+        assert!(is_alive(&world, &target));
+
+        Ok(())
+    }
+
+    /// Spawns a NPC at the given x coordinate. All NPCs except villagers get an AI.
+    fn add_npc(world: &World, x: f32, is_villager: bool, is_boss: bool) -> EntityId {
+        world.run(
+            |mut entities: EntitiesViewMut,
+             mut npc_spawns: ViewMut<NpcSpawn>,
+             mut npcs: ViewMut<Npc>,
+             mut locations: ViewMut<Location>,
+             mut healths: ViewMut<Health>,
+             mut ais: ViewMut<Ai>| {
+                let template = NpcTemplate {
+                    hunting_zone_id: 13,
+                    id: 1001,
+                    max_hp: 1000,
+                    is_villager,
+                    attack: 10,
+                    defence: 10,
+                    exp: 300,
+                    is_boss,
+                    ..NpcTemplate::default()
+                };
+                let point = Point3::new(x, 0.0, 0.0);
+                let rotation = Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0);
+                let spawn_id = entities.add_entity(
+                    &mut npc_spawns,
+                    NpcSpawn {
+                        spawn: NpcSpawnTemplate {
+                            hunting_zone_id: 13,
+                            template_id: 1001,
+                            point,
+                            rotation,
+                            respawn_time: Duration::from_secs(30),
+                            patrol_points: vec![],
+                        },
+                        template,
+                        npc_id: None,
+                        respawns_at: None,
+                    },
+                );
+                let npc_id = entities.add_entity(
+                    (&mut npcs, &mut locations, &mut healths),
+                    (
+                        Npc {
+                            hunting_zone_id: 13,
+                            template_id: 1001,
+                            spawn_id,
+                            walk_speed: 50,
+                            run_speed: 100,
+                            is_villager,
+                            is_alive: true,
+                        },
+                        Location { point, rotation },
+                        Health {
+                            hp: 1000,
+                            max_hp: 1000,
+                        },
+                    ),
+                );
+                if !is_villager {
+                    entities.add_component(&mut ais, Ai::new(Instant::now()), npc_id);
+                }
+                npc_id
+            },
+        )
+    }
+
+    #[test]
+    fn test_hit_npcs() -> Result<()> {
+        // Monsters can be hit without PvP
+        let (world, global_rx) = setup(false, GuildWarRegistry::default());
+        let user = add_user(&world, 1, 0.0, true)?;
+        let monster_id = add_npc(&world, 10.0, false, false);
+        let villager_id = add_npc(&world, 10.0, true, false);
+        let far_monster_id = add_npc(&world, 1000.0, false, false);
+        world.run(|mut visibilities: ViewMut<Visibility>| {
+            (&mut visibilities)
+                .try_get(user.connection_local_world_id)
+                .unwrap()
+                .visible_entities
+                .insert(monster_id);
+        });
+        world.run(
+            |entities: EntitiesView, mut experiences: ViewMut<UserExperience>| {
+                entities.add_component(
+                    &mut experiences,
+                    UserExperience {
+                        exp: 0,
+                        rest_bonus_exp: 0,
+                        playtime: 0,
+                        spawned_at: Instant::now(),
+                        requested: Vec::new(),
+                    },
+                    user.connection_local_world_id,
+                );
+            },
+        );
+
+        start_skill(&world, &user, STRIKE);
+        let messages = received(&user.rx);
+        let hit = find_packet(&messages, |message| match message {
+            ResponseEachSkillResult { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(hit.target_id, monster_id);
+        assert_eq!(hit.value, 50);
+
+        world.run(|healths: View<Health>, ais: View<Ai>| {
+            assert_eq!(healths.try_get(monster_id).unwrap().hp, 950);
+            assert_eq!(healths.try_get(villager_id).unwrap().hp, 1000);
+            assert_eq!(healths.try_get(far_monster_id).unwrap().hp, 1000);
+
+            // The damage counts as threat
+            assert_eq!(
+                ais.try_get(monster_id).unwrap().aggro,
+                vec![(user.connection_local_world_id, 50)]
+            );
+            assert!(ais.try_get(far_monster_id).unwrap().aggro.is_empty());
+        });
+
+        // Killed monsters are handled by the NPC spawner
+        start_skill(&world, &user, FINISHER);
+        world.run(|npcs: View<Npc>, healths: View<Health>, ais: View<Ai>| {
+            assert!(!npcs.try_get(monster_id).unwrap().is_alive);
+            assert_eq!(healths.try_get(monster_id).unwrap().hp, 0);
+            assert_eq!(ais.try_get(monster_id).unwrap().state, AiState::Idle);
+        });
+
+        // The killer receives the experience of the monster
+        world.run(|experiences: View<UserExperience>| {
+            assert_eq!(
+                experiences
+                    .try_get(user.connection_local_world_id)
+                    .unwrap()
+                    .requested,
+                vec![ExperienceRequest {
+                    exp: 300,
+                    source: ExperienceSource::Kill,
+                }]
+            );
+        });
+        let messages = received(&user.rx);
+        let life = find_packet(&messages, |message| match message {
+            ResponseCreatureLife { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(life.target_id, monster_id);
+        assert!(!life.is_alive);
+
+        // Only bosses clear dungeons
+        assert!(global_rx.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_kill_boss() -> Result<()> {
+        let (world, global_rx) = setup(false, GuildWarRegistry::default());
+        let user = add_user(&world, 1, 0.0, true)?;
+        let dead_user = add_user(&world, 2, 1000.0, false)?;
+        add_npc(&world, 10.0, false, true);
+
+        start_skill(&world, &user, STRIKE);
+        assert!(global_rx.is_empty());
+
+        // All users of the dungeon instance cleared the dungeon, even the dead ones
+        start_skill(&world, &user, FINISHER);
+        let mut cleared = HashSet::new();
+        while let Ok(message) = global_rx.try_recv() {
+            match &*message {
+                Message::DungeonCleared {
+                    connection_global_world_id,
+                    zone_id,
+                } => {
+                    assert_eq!(*zone_id, 5);
+                    cleared.insert(*connection_global_world_id);
+                }
+                _ => panic!("Message is not a DungeonCleared message"),
+            }
+        }
+        assert_eq!(
+            cleared,
+            [
+                user.connection_global_world_id,
+                dead_user.connection_global_world_id,
+            ]
+            .iter()
+            .copied()
+            .collect::<HashSet<EntityId>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mana_regeneration() -> Result<()> {
+        let (world, _global_rx) = setup(true, GuildWarRegistry::default());
+        let user = add_user(&world, 1, 0.0, true)?;
+
+        world.run(|mut manas: ViewMut<Mana>| {
+            let mana = (&mut manas)
+                .try_get(user.connection_local_world_id)
+                .unwrap();
+            mana.mp = 0;
+            mana.regenerates_at = Instant::now();
+        });
+        world.run(combat_system);
+        assert_eq!(get_mana(&world, &user).mp, 5);
+
+        // The next regeneration happens after the interval
+        world.run(combat_system);
+        assert_eq!(get_mana(&world, &user).mp, 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_calculate_damage() {
+        assert_eq!(calculate_damage(100, 10, 10), 50);
+        assert_eq!(calculate_damage(100, 30, 10), 75);
+        assert_eq!(calculate_damage(1, 1, 100), 1);
+        assert_eq!(calculate_damage(100, 0, 0), 1);
+    }
+}
@@ -0,0 +1,1158 @@
+use crate::ecs::component::{
+    LocalConnection, LocalUserSpawn, UserAppearance, UserInventory, UserSpawnStatus, UserStats,
+    Visibility,
+};
+use crate::ecs::dto::EquipmentLook;
+use crate::ecs::message::Message::ResponseUserExternalChange;
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::{ItemRegistry, ItemTemplate};
+use crate::ecs::system::local::{assemble_itemlist, send_message_to_connection};
+use crate::ecs::system::send_message;
+use crate::model::entity::{EquippedItem, Inventory, Item};
+use crate::model::repository::{equipped_item, inventory, item};
+use crate::model::{level_stats, EquipmentSlot, MAX_EQUIPMENT_PRESETS};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{bail, ensure, Context};
+use async_std::task;
+use shipyard::*;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::{debug, error, info_span};
+
+/// Handles the equipment of the users. Items are checked against the level, class and race of
+/// the user before they are equipped. The stats of the equipped items are aggregated into the
+/// stats of the user.
+pub fn equipment_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<LocalConnection>,
+    user_spawns: View<LocalUserSpawn>,
+    visibilities: View<Visibility>,
+    appearances: View<UserAppearance>,
+    mut inventories: ViewMut<UserInventory>,
+    mut stats: ViewMut<UserStats>,
+    entities: EntitiesView,
+    item_registry: UniqueView<ItemRegistry>,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestLoadTopoFin {
+                connection_global_world_id,
+                connection_local_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_load_topo_fin(
+                    *connection_local_world_id,
+                    &appearances,
+                    &inventories,
+                    &mut stats,
+                    &entities,
+                    &item_registry,
+                ) {
+                    error!("Ignoring Message::RequestLoadTopoFin: {:?}", e);
+                }
+            }
+            Message::RequestEquipItem {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_equip_item(
+                    *connection_local_world_id,
+                    &packet,
+                    &user_spawns,
+                    &appearances,
+                    &mut inventories,
+                    &item_registry,
+                    &pool,
+                )
+                .and_then(|_| {
+                    update_equipment(
+                        *connection_local_world_id,
+                        &connections,
+                        &user_spawns,
+                        &visibilities,
+                        &appearances,
+                        &inventories,
+                        &mut stats,
+                        &entities,
+                        &item_registry,
+                    )
+                }) {
+                    error!("Ignoring equip item request: {:?}", e);
+                }
+            }
+            Message::RequestUnequipItem {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_unequip_item(
+                    *connection_local_world_id,
+                    &packet,
+                    &mut inventories,
+                    &pool,
+                )
+                .and_then(|_| {
+                    update_equipment(
+                        *connection_local_world_id,
+                        &connections,
+                        &user_spawns,
+                        &visibilities,
+                        &appearances,
+                        &inventories,
+                        &mut stats,
+                        &entities,
+                        &item_registry,
+                    )
+                }) {
+                    error!("Ignoring unequip item request: {:?}", e);
+                }
+            }
+            Message::RequestChangeEquipPreset {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_change_equip_preset(
+                    *connection_local_world_id,
+                    &packet,
+                    &user_spawns,
+                    &mut inventories,
+                    &pool,
+                )
+                .and_then(|_| {
+                    update_equipment(
+                        *connection_local_world_id,
+                        &connections,
+                        &user_spawns,
+                        &visibilities,
+                        &appearances,
+                        &inventories,
+                        &mut stats,
+                        &entities,
+                        &item_registry,
+                    )
+                }) {
+                    error!("Ignoring change equipment preset request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_load_topo_fin(
+    connection_local_world_id: EntityId,
+    appearances: &View<UserAppearance>,
+    inventories: &ViewMut<UserInventory>,
+    stats: &mut ViewMut<UserStats>,
+    entities: &EntitiesView,
+    item_registry: &ItemRegistry,
+) -> Result<()> {
+    debug!("Message::RequestLoadTopoFin incoming");
+
+    let (appearance, inventory) = (appearances, inventories)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    update_stats(
+        connection_local_world_id,
+        appearance,
+        inventory,
+        stats,
+        entities,
+        item_registry,
+    );
+
+    Ok(())
+}
+
+fn handle_equip_item(
+    connection_local_world_id: EntityId,
+    packet: &CEquipItem,
+    user_spawns: &View<LocalUserSpawn>,
+    appearances: &View<UserAppearance>,
+    inventories: &mut ViewMut<UserInventory>,
+    item_registry: &ItemRegistry,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestEquipItem incoming");
+
+    let (spawn, appearance, inventory) = (user_spawns, appearances, inventories)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    let item = inventory
+        .items
+        .get(&packet.slot)
+        .context(format!("No item found in slot {}", packet.slot))?
+        .clone();
+    ensure!(
+        item.amount == 1,
+        "Can't equip a stack of {} items",
+        item.amount
+    );
+
+    let template = item_registry
+        .get(item.template_id)
+        .context(format!("Can't find item template {}", item.template_id))?;
+    check_requirements(template, appearance)?;
+    let slot = select_slot(template, inventory)?;
+
+    let equipped_item = EquippedItem {
+        id: item.id,
+        user_id: spawn.user_id,
+        preset: inventory.equipment_preset,
+        slot,
+        template_id: item.template_id,
+        created_at: item.created_at,
+    };
+    // An already equipped item takes the inventory slot of the new item.
+    let replaced_item = inventory.equipment.get(&slot).map(|old_item| Item {
+        id: old_item.id,
+        user_id: old_item.user_id,
+        template_id: old_item.template_id,
+        slot: item.slot,
+        amount: 1,
+        created_at: old_item.created_at,
+    });
+
+    task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        item::delete(&mut conn, item.id).await?;
+        if let Some(replaced_item) = &replaced_item {
+            equipped_item::delete(&mut conn, replaced_item.id).await?;
+            item::restore(&mut conn, replaced_item).await?;
+        }
+        equipped_item::create(&mut conn, &equipped_item).await?;
+
+        conn.commit().await?;
+
+        Ok::<(), anyhow::Error>(())
+    })?;
+
+    inventory.items.remove(&item.slot);
+    if let Some(replaced_item) = replaced_item {
+        inventory.items.insert(replaced_item.slot, replaced_item);
+    }
+    inventory.equipment.insert(slot, equipped_item);
+
+    Ok(())
+}
+
+fn handle_unequip_item(
+    connection_local_world_id: EntityId,
+    packet: &CUnequipItem,
+    inventories: &mut ViewMut<UserInventory>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestUnequipItem incoming");
+
+    let inventory = inventories
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    let equipped_item = inventory
+        .equipment
+        .get(&packet.slot)
+        .context(format!("No item equipped in slot {:?}", packet.slot))?
+        .clone();
+    let slot = (0..inventory.size)
+        .find(|slot| !inventory.items.contains_key(slot))
+        .context("Inventory is full")?;
+
+    let item = Item {
+        id: equipped_item.id,
+        user_id: equipped_item.user_id,
+        template_id: equipped_item.template_id,
+        slot,
+        amount: 1,
+        created_at: equipped_item.created_at,
+    };
+
+    task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        equipped_item::delete(&mut conn, equipped_item.id).await?;
+        item::restore(&mut conn, &item).await?;
+
+        conn.commit().await?;
+
+        Ok::<(), anyhow::Error>(())
+    })?;
+
+    inventory.equipment.remove(&packet.slot);
+    inventory.items.insert(item.slot, item);
+
+    Ok(())
+}
+
+fn handle_change_equip_preset(
+    connection_local_world_id: EntityId,
+    packet: &CChangeEquipPreset,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &mut ViewMut<UserInventory>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestChangeEquipPreset incoming");
+
+    let (spawn, inventory) = (user_spawns, inventories)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    ensure!(
+        packet.preset >= 0 && packet.preset < MAX_EQUIPMENT_PRESETS,
+        "Equipment preset {} doesn't exist",
+        packet.preset
+    );
+    ensure!(
+        packet.preset != inventory.equipment_preset,
+        "Equipment preset {} is already active",
+        packet.preset
+    );
+
+    let equipment = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        inventory::update(
+            &mut conn,
+            &Inventory {
+                user_id: spawn.user_id,
+                size: inventory.size,
+                money: inventory.money,
+                equipment_preset: packet.preset,
+            },
+        )
+        .await?;
+        let equipment =
+            equipped_item::list_by_user_id_and_preset(&mut conn, spawn.user_id, packet.preset)
+                .await?;
+
+        conn.commit().await?;
+
+        Ok::<Vec<EquippedItem>, anyhow::Error>(equipment)
+    })?;
+
+    inventory.equipment_preset = packet.preset;
+    inventory.equipment = equipment
+        .into_iter()
+        .map(|item| (item.slot, item))
+        .collect::<HashMap<EquipmentSlot, EquippedItem>>();
+
+    Ok(())
+}
+
+/// Checks if the user is allowed to equip an item of the given template.
+fn check_requirements(template: &ItemTemplate, appearance: &UserAppearance) -> Result<()> {
+    ensure!(
+        !template.equipment_slots.is_empty(),
+        "Item template {} can't be equipped",
+        template.id
+    );
+    ensure!(
+        appearance.level >= template.required_level,
+        "Item template {} requires level {}",
+        template.id,
+        template.required_level
+    );
+
+    let template_id = &appearance.template_id;
+    if !template.required_classes.is_empty()
+        && !template.required_classes.contains(&template_id.class)
+    {
+        bail!(
+            "Item template {} can't be equipped by class {:?}",
+            template.id,
+            template_id.class
+        );
+    }
+    if !template.required_races.is_empty() && !template.required_races.contains(&template_id.race) {
+        bail!(
+            "Item template {} can't be equipped by race {:?}",
+            template.id,
+            template_id.race
+        );
+    }
+
+    Ok(())
+}
+
+/// Prefers a free slot. Items that can be equipped in multiple slots (rings and earrings)
+/// replace the item inside the first slot if all slots are occupied.
+fn select_slot(template: &ItemTemplate, inventory: &UserInventory) -> Result<EquipmentSlot> {
+    template
+        .equipment_slots
+        .iter()
+        .find(|slot| !inventory.equipment.contains_key(slot))
+        .or_else(|| template.equipment_slots.first())
+        .copied()
+        .context(format!("Item template {} can't be equipped", template.id))
+}
+
+/// Aggregates the stats of an user out of the stats of it's level and it's equipped items.
+pub fn calculate_stats(
+    level: i32,
+    inventory: &UserInventory,
+    item_registry: &ItemRegistry,
+) -> UserStats {
+    let base = level_stats(level);
+    let mut total = base;
+    total += item_registry.equipment_stats(inventory.equipment.values());
+    UserStats { base, total }
+}
+
+/// Aggregates the stats of the user. Users get their stats component once they are loaded into
+/// the world.
+fn update_stats(
+    connection_local_world_id: EntityId,
+    appearance: &UserAppearance,
+    inventory: &UserInventory,
+    stats: &mut ViewMut<UserStats>,
+    entities: &EntitiesView,
+    item_registry: &ItemRegistry,
+) {
+    let user_stats = calculate_stats(appearance.level, inventory, item_registry);
+
+    if let Ok(current_stats) = stats.try_get(connection_local_world_id) {
+        *current_stats = user_stats;
+    } else {
+        entities.add_component(stats, user_stats, connection_local_world_id);
+    }
+}
+
+/// Applies the changed equipment to the stats and shows it to the user and all users
+/// that can see it.
+fn update_equipment(
+    connection_local_world_id: EntityId,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    visibilities: &View<Visibility>,
+    appearances: &View<UserAppearance>,
+    inventories: &ViewMut<UserInventory>,
+    stats: &mut ViewMut<UserStats>,
+    entities: &EntitiesView,
+    item_registry: &ItemRegistry,
+) -> Result<()> {
+    let (spawn, appearance, inventory) = (user_spawns, appearances, inventories)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+
+    update_stats(
+        connection_local_world_id,
+        appearance,
+        inventory,
+        stats,
+        entities,
+        item_registry,
+    );
+
+    send_message_to_connection(
+        assemble_itemlist(
+            spawn.connection_global_world_id,
+            connection_local_world_id,
+            inventory,
+            false,
+        ),
+        connections,
+    );
+
+    // Users that are not spawned yet receive their equipment with the spawn packets.
+    if spawn.status != UserSpawnStatus::Spawned {
+        return Ok(());
+    }
+
+    let look = EquipmentLook::new(inventory.equipment.values());
+    (connections, user_spawns, visibilities)
+        .iter()
+        .with_id()
+        .filter(|(_id, (_connection, spawn, _visibility))| spawn.status == UserSpawnStatus::Spawned)
+        .for_each(|(id, (connection, other_spawn, visibility))| {
+            if id == connection_local_world_id
+                || visibility
+                    .visible_entities
+                    .contains(&connection_local_world_id)
+            {
+                send_message(
+                    assemble_user_external_change(
+                        other_spawn.connection_global_world_id,
+                        id,
+                        connection_local_world_id,
+                        &look,
+                        appearance,
+                    ),
+                    &connection.channel,
+                );
+            }
+        });
+
+    Ok(())
+}
+
+fn assemble_user_external_change(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    game_id: EntityId,
+    look: &EquipmentLook,
+    appearance: &UserAppearance,
+) -> EcsMessage {
+    Box::new(ResponseUserExternalChange {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SUserExternalChange {
+            game_id,
+            weapon: look.weapon,
+            body: look.body,
+            hand: look.hand,
+            feet: look.feet,
+            underwear: look.underwear,
+            head: look.head,
+            face: look.face,
+            weapon_model: 0,
+            body_model: 0,
+            hand_model: 0,
+            feet_model: 0,
+            weapon_dye: 0,
+            body_dye: 0,
+            hand_dye: 0,
+            feet_dye: 0,
+            underwear_dye: 0,
+            style_back_dye: 0,
+            style_head_dye: 0,
+            style_face_dye: 0,
+            weapon_enchant: 0,
+            show_face: appearance.show_face,
+            style_head: look.style_head,
+            style_face: look.style_face,
+            style_back: look.style_back,
+            style_weapon: look.style_weapon,
+            style_body: look.style_body,
+            style_footprint: look.style_footprint,
+            style_body_dye: 0,
+            show_style: appearance.show_style,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::inventory::tests::get_default_inventory;
+    use crate::model::repository::item::tests::get_default_item;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::model::{Class, Customization, Gender, Race, Stats, TemplateID};
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver};
+    use std::collections::HashSet;
+
+    const WEAPON: i32 = 10001;
+    const RING: i32 = 88001;
+    const HIGH_LEVEL_WEAPON: i32 = 10002;
+    const WARRIOR_WEAPON: i32 = 10003;
+    const CASTANIC_WEAPON: i32 = 10004;
+    const POTION: i32 = 8005;
+
+    struct TestUser {
+        user: User,
+        connection_global_world_id: EntityId,
+        connection_local_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(pool);
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(ItemRegistry::new(vec![
+            ItemTemplate {
+                id: WEAPON,
+                max_stack: 1,
+                equipment_slots: vec![EquipmentSlot::Weapon],
+                required_level: 60,
+                stats: Stats {
+                    attack: 100,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ItemTemplate {
+                id: RING,
+                max_stack: 1,
+                equipment_slots: vec![EquipmentSlot::Ring1, EquipmentSlot::Ring2],
+                stats: Stats {
+                    max_hp: 50,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ItemTemplate {
+                id: HIGH_LEVEL_WEAPON,
+                max_stack: 1,
+                equipment_slots: vec![EquipmentSlot::Weapon],
+                required_level: 66,
+                ..Default::default()
+            },
+            ItemTemplate {
+                id: WARRIOR_WEAPON,
+                max_stack: 1,
+                equipment_slots: vec![EquipmentSlot::Weapon],
+                required_classes: vec![Class::Warrior],
+                ..Default::default()
+            },
+            ItemTemplate {
+                id: CASTANIC_WEAPON,
+                max_stack: 1,
+                equipment_slots: vec![EquipmentSlot::Weapon],
+                required_races: vec![Race::Castanic],
+                ..Default::default()
+            },
+            ItemTemplate {
+                id: POTION,
+                max_stack: 50,
+                ..Default::default()
+            },
+        ]));
+        world
+    }
+
+    /// Creates a level 65 human priest with the given items (template ID, slot, amount) and
+    /// spawns it.
+    fn add_user(world: &World, pool: &PgPool, items: &[(i32, i32, i32)]) -> Result<TestUser> {
+        let (user, inventory, items) = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let account = account::create(&mut conn, &get_default_account(0)).await?;
+            let user = user::create(&mut conn, &get_default_user(&account, 0)).await?;
+            let inventory = inventory::create(&mut conn, &get_default_inventory(&user)).await?;
+
+            let mut created_items = Vec::with_capacity(items.len());
+            for (template_id, slot, amount) in items {
+                let mut new_item = get_default_item(&user, *slot);
+                new_item.template_id = *template_id;
+                new_item.amount = *amount;
+                created_items.push(item::create(&mut conn, &new_item).await?);
+            }
+
+            Ok::<(User, Inventory, Vec<Item>), anyhow::Error>((user, inventory, created_items))
+        })?;
+
+        let connection_global_world_id =
+            from_vec::<EntityId>(vec![0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut visibilities: ViewMut<Visibility>,
+             mut appearances: ViewMut<UserAppearance>,
+             mut inventories: ViewMut<UserInventory>| {
+                entities.add_entity(
+                    (
+                        &mut connections,
+                        &mut user_spawns,
+                        &mut visibilities,
+                        &mut appearances,
+                        &mut inventories,
+                    ),
+                    (
+                        LocalConnection {
+                            channel: tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_global_world_id,
+                            is_alive: true,
+                        },
+                        Visibility {
+                            range: 2000,
+                            visible_entities: HashSet::new(),
+                        },
+                        UserAppearance {
+                            name: user.name.clone(),
+                            template_id: TemplateID {
+                                race: Race::Human,
+                                gender: Gender::Female,
+                                class: Class::Priest,
+                            },
+                            level: 65,
+                            details: vec![],
+                            shape: vec![],
+                            appearance: Customization::default(),
+                            appearance2: 100,
+                            show_face: true,
+                            show_style: false,
+                            guild_name: "".to_string(),
+                            guild_rank: "".to_string(),
+                        },
+                        UserInventory {
+                            size: inventory.size,
+                            money: inventory.money,
+                            items: items.into_iter().map(|item| (item.slot, item)).collect(),
+                            equipment_preset: inventory.equipment_preset,
+                            equipment: HashMap::new(),
+                        },
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            user,
+            connection_global_world_id,
+            connection_local_world_id,
+            rx: rx_channel,
+        })
+    }
+
+    /// Adds a spawned user that can see the given user.
+    fn add_observer(world: &World, user: &TestUser) -> Result<Receiver<EcsMessage>> {
+        let connection_global_world_id =
+            from_vec::<EntityId>(vec![0x13, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+        let (tx_channel, rx_channel) = channel(1024);
+        let mut visible_entities = HashSet::new();
+        visible_entities.insert(user.connection_local_world_id);
+
+        world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut visibilities: ViewMut<Visibility>| {
+                entities.add_entity(
+                    (&mut connections, &mut user_spawns, &mut visibilities),
+                    (
+                        LocalConnection {
+                            channel: tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id: user.user.id + 1,
+                            account_id: user.user.account_id + 1,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_global_world_id,
+                            is_alive: true,
+                        },
+                        Visibility {
+                            range: 2000,
+                            visible_entities,
+                        },
+                    ),
+                );
+            },
+        );
+
+        Ok(rx_channel)
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(equipment_system);
+        world.run(cleaner_system);
+    }
+
+    fn equip_item(world: &World, user: &TestUser, slot: i32) {
+        run_message(
+            world,
+            Message::RequestEquipItem {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CEquipItem {
+                    game_id: user.connection_local_world_id,
+                    slot,
+                    unk1: 0,
+                },
+            },
+        );
+    }
+
+    fn unequip_item(world: &World, user: &TestUser, slot: EquipmentSlot) {
+        run_message(
+            world,
+            Message::RequestUnequipItem {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CUnequipItem {
+                    game_id: user.connection_local_world_id,
+                    slot,
+                    unk1: 0,
+                },
+            },
+        );
+    }
+
+    fn change_equip_preset(world: &World, user: &TestUser, preset: i32) {
+        run_message(
+            world,
+            Message::RequestChangeEquipPreset {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CChangeEquipPreset { preset },
+            },
+        );
+    }
+
+    fn assert_itemlist(rx: &Receiver<EcsMessage>) -> Result<SItemlist> {
+        match &*rx.try_recv()? {
+            Message::ResponseItemlist { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseItemlist message"),
+        }
+    }
+
+    fn assert_external_change(rx: &Receiver<EcsMessage>) -> Result<SUserExternalChange> {
+        match &*rx.try_recv()? {
+            Message::ResponseUserExternalChange { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseUserExternalChange message"),
+        }
+    }
+
+    /// Items as (template ID, slot, amount) and the equipment of the active preset as
+    /// (template ID, slot).
+    type Persisted = (Vec<(i32, i32, i32)>, Vec<(i32, EquipmentSlot)>);
+
+    /// Checks that the inventory component matches the database and returns the persisted items.
+    fn assert_persisted(world: &World, pool: &PgPool, user: &TestUser) -> Result<Persisted> {
+        let (db_items, db_equipment) = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let items = item::list_by_user_id(&mut conn, user.user.id).await?;
+            let equipment = equipped_item::list_active_by_user_id(&mut conn, user.user.id).await?;
+            Ok::<(Vec<Item>, Vec<EquippedItem>), anyhow::Error>((items, equipment))
+        })?;
+
+        world.run(|inventories: View<UserInventory>| {
+            let inventory = inventories.try_get(user.connection_local_world_id).unwrap();
+            let mut items = inventory.items.values().cloned().collect::<Vec<Item>>();
+            items.sort_by_key(|item| item.slot);
+            assert_eq!(items, db_items);
+
+            let mut equipment = inventory
+                .equipment
+                .values()
+                .cloned()
+                .collect::<Vec<EquippedItem>>();
+            equipment.sort_by_key(|item| item.slot as i32);
+            let mut sorted_db_equipment = db_equipment.clone();
+            sorted_db_equipment.sort_by_key(|item| item.slot as i32);
+            assert_eq!(equipment, sorted_db_equipment);
+            for (slot, item) in inventory.equipment.iter() {
+                assert_eq!(*slot, item.slot);
+                assert_eq!(item.preset, inventory.equipment_preset);
+            }
+        });
+
+        let mut equipment = db_equipment
+            .iter()
+            .map(|item| (item.template_id, item.slot))
+            .collect::<Vec<(i32, EquipmentSlot)>>();
+        equipment.sort_by_key(|(_template_id, slot)| *slot as i32);
+
+        Ok((
+            db_items
+                .iter()
+                .map(|item| (item.template_id, item.slot, item.amount))
+                .collect(),
+            equipment,
+        ))
+    }
+
+    fn get_total_stats(world: &World, user: &TestUser) -> Stats {
+        world.run(|stats: View<UserStats>| {
+            stats.try_get(user.connection_local_world_id).unwrap().total
+        })
+    }
+
+    #[test]
+    fn test_load_topo_fin() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[(WEAPON, 0, 1)])?;
+            equip_item(&world, &user, 0);
+            world.run(|mut stats: ViewMut<UserStats>| {
+                stats.delete(user.connection_local_world_id);
+            });
+
+            run_message(
+                &world,
+                Message::RequestLoadTopoFin {
+                    connection_global_world_id: user.connection_global_world_id,
+                    connection_local_world_id: user.connection_local_world_id,
+                    packet: CLoadTopoFin {},
+                },
+            );
+
+            let mut expected = level_stats(65);
+            expected.attack += 100;
+            assert_eq!(get_total_stats(&world, &user), expected);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_equip_item() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[(POTION, 0, 5), (WEAPON, 3, 1)])?;
+            let observer_rx = add_observer(&world, &user)?;
+
+            equip_item(&world, &user, 3);
+
+            assert_eq!(assert_itemlist(&user.rx)?.items.len(), 1);
+            let packet = assert_external_change(&user.rx)?;
+            assert_eq!(packet.game_id, user.connection_local_world_id);
+            assert_eq!(packet.weapon, WEAPON);
+            assert!(packet.show_face);
+            assert!(!packet.show_style);
+            assert_eq!(assert_external_change(&observer_rx)?.weapon, WEAPON);
+
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                (vec![(POTION, 0, 5)], vec![(WEAPON, EquipmentSlot::Weapon)])
+            );
+
+            let mut expected = level_stats(65);
+            expected.attack += 100;
+            assert_eq!(get_total_stats(&world, &user), expected);
+
+            // Stacks and items without an equipment slot can't be equipped
+            equip_item(&world, &user, 0);
+            assert!(user.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_equip_item_requirements() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(
+                &world,
+                &pool,
+                &[
+                    (HIGH_LEVEL_WEAPON, 0, 1),
+                    (WARRIOR_WEAPON, 1, 1),
+                    (CASTANIC_WEAPON, 2, 1),
+                    (POTION, 3, 1),
+                ],
+            )?;
+
+            equip_item(&world, &user, 0);
+            equip_item(&world, &user, 1);
+            equip_item(&world, &user, 2);
+            equip_item(&world, &user, 3);
+
+            assert!(user.rx.is_empty());
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                (
+                    vec![
+                        (HIGH_LEVEL_WEAPON, 0, 1),
+                        (WARRIOR_WEAPON, 1, 1),
+                        (CASTANIC_WEAPON, 2, 1),
+                        (POTION, 3, 1)
+                    ],
+                    vec![]
+                )
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_equip_item_replaces_equipped_item() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[(RING, 0, 1), (RING, 1, 1), (WEAPON, 2, 1)])?;
+
+            // Rings fill up the free slots first
+            equip_item(&world, &user, 0);
+            equip_item(&world, &user, 1);
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                (
+                    vec![(WEAPON, 2, 1)],
+                    vec![(RING, EquipmentSlot::Ring1), (RING, EquipmentSlot::Ring2)]
+                )
+            );
+
+            let mut expected = level_stats(65);
+            expected.max_hp += 100;
+            assert_eq!(get_total_stats(&world, &user), expected);
+
+            let equipped_id = world.run(|inventories: View<UserInventory>| {
+                inventories
+                    .try_get(user.connection_local_world_id)
+                    .unwrap()
+                    .equipment[&EquipmentSlot::Ring1]
+                    .id
+            });
+            unequip_item(&world, &user, EquipmentSlot::Ring1);
+            equip_item(&world, &user, 0);
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                (
+                    vec![(WEAPON, 2, 1)],
+                    vec![(RING, EquipmentSlot::Ring1), (RING, EquipmentSlot::Ring2)]
+                )
+            );
+
+            // The replaced ring takes the inventory slot of the new one
+            task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                let mut item = get_default_item(&user.user, 5);
+                item.template_id = RING;
+                let item = item::create(&mut conn, &item).await?;
+                world.run(|mut inventories: ViewMut<UserInventory>| {
+                    (&mut inventories)
+                        .try_get(user.connection_local_world_id)
+                        .unwrap()
+                        .items
+                        .insert(item.slot, item);
+                });
+                Ok::<(), anyhow::Error>(())
+            })?;
+            equip_item(&world, &user, 5);
+            let (items, _equipment) = assert_persisted(&world, &pool, &user)?;
+            assert_eq!(items, vec![(WEAPON, 2, 1), (RING, 5, 1)]);
+            world.run(|inventories: View<UserInventory>| {
+                let inventory = inventories.try_get(user.connection_local_world_id).unwrap();
+                assert_eq!(inventory.items[&5].id, equipped_id);
+            });
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_unequip_item() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[(POTION, 0, 5), (WEAPON, 3, 1)])?;
+            equip_item(&world, &user, 3);
+            assert_itemlist(&user.rx)?;
+            assert_external_change(&user.rx)?;
+
+            // The item is moved into the first free slot
+            unequip_item(&world, &user, EquipmentSlot::Weapon);
+            assert_eq!(assert_itemlist(&user.rx)?.items.len(), 2);
+            assert_eq!(assert_external_change(&user.rx)?.weapon, 0);
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                (vec![(POTION, 0, 5), (WEAPON, 1, 1)], vec![])
+            );
+            assert_eq!(get_total_stats(&world, &user), level_stats(65));
+
+            // Nothing is equipped anymore
+            unequip_item(&world, &user, EquipmentSlot::Weapon);
+            assert!(user.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_unequip_item_full_inventory() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[(POTION, 0, 5), (WEAPON, 1, 1)])?;
+            equip_item(&world, &user, 1);
+            assert_itemlist(&user.rx)?;
+            assert_external_change(&user.rx)?;
+
+            world.run(|mut inventories: ViewMut<UserInventory>| {
+                (&mut inventories)
+                    .try_get(user.connection_local_world_id)
+                    .unwrap()
+                    .size = 1;
+            });
+            unequip_item(&world, &user, EquipmentSlot::Weapon);
+            assert!(user.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_change_equip_preset() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[(WEAPON, 0, 1)])?;
+            equip_item(&world, &user, 0);
+            assert_itemlist(&user.rx)?;
+            assert_external_change(&user.rx)?;
+
+            change_equip_preset(&world, &user, 1);
+            assert_itemlist(&user.rx)?;
+            assert_eq!(assert_external_change(&user.rx)?.weapon, 0);
+            assert_eq!(assert_persisted(&world, &pool, &user)?, (vec![], vec![]));
+            assert_eq!(get_total_stats(&world, &user), level_stats(65));
+            let inventory = task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                inventory::get_by_user_id(&mut conn, user.user.id).await
+            })?;
+            assert_eq!(inventory.equipment_preset, 1);
+
+            change_equip_preset(&world, &user, 0);
+            assert_itemlist(&user.rx)?;
+            assert_eq!(assert_external_change(&user.rx)?.weapon, WEAPON);
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                (vec![], vec![(WEAPON, EquipmentSlot::Weapon)])
+            );
+
+            // Only existing and inactive presets can be selected
+            change_equip_preset(&world, &user, 0);
+            change_equip_preset(&world, &user, MAX_EQUIPMENT_PRESETS);
+            change_equip_preset(&world, &user, -1);
+            assert!(user.rx.is_empty());
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,576 @@
+use crate::ecs::component::{
+    ExperienceRequest, ExperienceSource, Health, LocalConnection, LocalUserSpawn, Mana,
+    UserAppearance, UserExperience, UserInventory, UserStats, Visibility,
+};
+use crate::ecs::message::EcsMessage;
+use crate::ecs::message::Message::{
+    ResponseCreatureChangeHp, ResponsePlayerChangeExp, ResponsePlayerChangeMp, ResponseUserLevelup,
+};
+use crate::ecs::resource::ItemRegistry;
+use crate::ecs::system::local::equipment::calculate_stats;
+use crate::ecs::system::local::{send_message_to_connection, send_to_observers};
+use crate::model::{level_exp, max_rest_bonus_exp, total_exp, MAX_LEVEL};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::Context;
+use shipyard::*;
+use tracing::{debug, error};
+
+/// HP / MP changes that are caused by the server itself.
+const CHANGE_BY_SYSTEM: i32 = 0;
+
+/// Experience an user gained with a single request. The gained experience includes the used
+/// rest bonus.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ExperienceGain {
+    exp: i64,
+    rest_bonus_exp: i64,
+}
+
+/// Gives the requested experience to the users of a local world. Killed monsters give additional
+/// experience out of the rest bonus of the user until it's used up. Users that collected the
+/// experience of their level advance to the next level, which refreshes their stats and refills
+/// their health and mana.
+pub fn experience_system(
+    connections: View<LocalConnection>,
+    user_spawns: ViewMut<LocalUserSpawn>,
+    visibilities: View<Visibility>,
+    inventories: View<UserInventory>,
+    (mut experiences, mut appearances, mut stats, mut healths, mut manas): (
+        ViewMut<UserExperience>,
+        ViewMut<UserAppearance>,
+        ViewMut<UserStats>,
+        ViewMut<Health>,
+        ViewMut<Mana>,
+    ),
+    item_registry: UniqueView<ItemRegistry>,
+) {
+    let mut level_ups = Vec::new();
+
+    (&mut experiences, &mut appearances)
+        .iter()
+        .with_id()
+        .for_each(|(id, (experience, appearance))| {
+            if experience.requested.is_empty() {
+                return;
+            }
+
+            let old_level = appearance.level;
+            for request in std::mem::take(&mut experience.requested) {
+                let gain = add_experience(experience, &mut appearance.level, request);
+                if gain.exp == 0 {
+                    continue;
+                }
+                if let Ok(spawn) = user_spawns.try_get(id) {
+                    send_message_to_connection(
+                        assemble_player_change_exp(
+                            spawn.connection_global_world_id,
+                            id,
+                            gain,
+                            experience,
+                            appearance.level,
+                        ),
+                        &connections,
+                    );
+                }
+            }
+
+            if appearance.level != old_level {
+                debug!(
+                    "User {:?} advanced from level {} to level {}",
+                    id, old_level, appearance.level
+                );
+                level_ups.push((id, appearance.level));
+            }
+        });
+
+    for (id, level) in level_ups {
+        if let Err(e) = level_up(
+            id,
+            level,
+            &connections,
+            &user_spawns,
+            &visibilities,
+            &inventories,
+            &mut stats,
+            &mut healths,
+            &mut manas,
+            &item_registry,
+        ) {
+            error!("Can't level up user {:?}: {:?}", id, e);
+        }
+    }
+}
+
+/// Requests experience for an user. The experience is given by the experience system.
+pub fn request_experience(
+    experiences: &mut ViewMut<UserExperience>,
+    user_id: EntityId,
+    exp: i64,
+    source: ExperienceSource,
+) {
+    if let Ok(experience) = experiences.try_get(user_id) {
+        experience.requested.push(ExperienceRequest { exp, source });
+    }
+}
+
+/// Adds the requested experience to an user and advances it's level. Users at the highest level
+/// don't gain experience anymore.
+fn add_experience(
+    experience: &mut UserExperience,
+    level: &mut i32,
+    request: ExperienceRequest,
+) -> ExperienceGain {
+    if request.exp <= 0 || *level >= MAX_LEVEL {
+        return ExperienceGain::default();
+    }
+
+    let rest_bonus_exp = match request.source {
+        ExperienceSource::Kill => request.exp.min(experience.rest_bonus_exp),
+        ExperienceSource::Quest => 0,
+    };
+    experience.rest_bonus_exp -= rest_bonus_exp;
+    experience.exp += request.exp + rest_bonus_exp;
+
+    while *level < MAX_LEVEL && experience.exp >= level_exp(*level) {
+        experience.exp -= level_exp(*level);
+        *level += 1;
+    }
+    if *level >= MAX_LEVEL {
+        experience.exp = 0;
+    }
+
+    ExperienceGain {
+        exp: request.exp + rest_bonus_exp,
+        rest_bonus_exp,
+    }
+}
+
+/// Refreshes the stats of an user that advanced to a new level and refills it's health and mana.
+/// The new level is shown to the user and all users that can see it. Dead users stay dead.
+fn level_up(
+    id: EntityId,
+    level: i32,
+    connections: &View<LocalConnection>,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    visibilities: &View<Visibility>,
+    inventories: &View<UserInventory>,
+    stats: &mut ViewMut<UserStats>,
+    healths: &mut ViewMut<Health>,
+    manas: &mut ViewMut<Mana>,
+    item_registry: &ItemRegistry,
+) -> Result<()> {
+    let (spawn, inventory) = (user_spawns, inventories)
+        .try_get(id)
+        .context(format!("Can't find inventory of {:?}", id))?;
+
+    send_to_observers(
+        id,
+        connections,
+        user_spawns,
+        visibilities,
+        |connection_global_world_id, connection_local_world_id| {
+            Box::new(ResponseUserLevelup {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet: SUserLevelup {
+                    target_id: id,
+                    level: level as i16,
+                },
+            })
+        },
+    );
+
+    // Users get their stats, health and mana once they are loaded into the world.
+    let user_stats = calculate_stats(level, inventory, item_registry);
+    let (current_stats, health, mana) = match (&mut *stats, &mut *healths, &mut *manas).try_get(id)
+    {
+        Ok(components) => components,
+        Err(_) => return Ok(()),
+    };
+    *current_stats = user_stats;
+
+    let max_hp = i64::from(current_stats.total.max_hp);
+    let hp = if spawn.is_alive { max_hp } else { 0 };
+    let hp_diff = hp - health.hp;
+    *health = Health { hp, max_hp };
+    let health = *health;
+    send_to_observers(
+        id,
+        connections,
+        user_spawns,
+        visibilities,
+        |connection_global_world_id, connection_local_world_id| {
+            Box::new(ResponseCreatureChangeHp {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet: SCreatureChangeHp {
+                    current_hp: health.hp,
+                    max_hp: health.max_hp,
+                    diff: hp_diff,
+                    change_type: CHANGE_BY_SYSTEM,
+                    target_id: id,
+                    source_id: id,
+                    crit: false,
+                },
+            })
+        },
+    );
+
+    mana.max_mp = current_stats.total.max_mp;
+    let mp = if spawn.is_alive {
+        mana.max_mp
+    } else {
+        mana.mp.min(mana.max_mp)
+    };
+    let mp_diff = mp - mana.mp;
+    mana.mp = mp;
+    send_message_to_connection(
+        Box::new(ResponsePlayerChangeMp {
+            connection_global_world_id: spawn.connection_global_world_id,
+            connection_local_world_id: id,
+            packet: SPlayerChangeMp {
+                current_mp: mana.mp,
+                max_mp: mana.max_mp,
+                diff: mp_diff,
+                change_type: CHANGE_BY_SYSTEM,
+                target_id: id,
+                source_id: id,
+            },
+        }),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn assemble_player_change_exp(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    gain: ExperienceGain,
+    experience: &UserExperience,
+    level: i32,
+) -> EcsMessage {
+    Box::new(ResponsePlayerChangeExp {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SPlayerChangeExp {
+            gained_exp: gain.exp,
+            gained_rest_bonus_exp: gain.rest_bonus_exp,
+            total_exp: total_exp(level, experience.exp),
+            level_exp: experience.exp,
+            total_level_exp: level_exp(level),
+            rest_bonus_exp: experience.rest_bonus_exp,
+            max_rest_bonus_exp: max_rest_bonus_exp(level),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::UserSpawnStatus;
+    use crate::ecs::message::Message;
+    use crate::ecs::resource::ItemTemplate;
+    use crate::model::entity::EquippedItem;
+    use crate::model::{
+        level_stats, Class, Customization, EquipmentSlot, Gender, Race, Stats, TemplateID,
+    };
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver};
+    use chrono::Utc;
+    use std::collections::{HashMap, HashSet};
+    use std::time::Instant;
+
+    const WEAPON: i32 = 10001;
+
+    struct TestUser {
+        connection_local_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn setup() -> World {
+        let world = World::new();
+        world.add_unique(ItemRegistry::new(vec![ItemTemplate {
+            id: WEAPON,
+            max_stack: 1,
+            equipment_slots: vec![EquipmentSlot::Weapon],
+            stats: Stats {
+                attack: 100,
+                ..Stats::default()
+            },
+            ..ItemTemplate::default()
+        }]));
+        world
+    }
+
+    /// Spawns a warrior with an equipped weapon and the given level, experience and rest bonus.
+    fn add_user(world: &World, num: i32, level: i32, exp: i64, rest_bonus_exp: i64) -> TestUser {
+        let connection_global_world_id =
+            from_vec::<EntityId>(vec![num as u8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])
+                .unwrap();
+        let (tx_channel, rx_channel) = channel(1024);
+        let mut equipment = HashMap::new();
+        equipment.insert(
+            EquipmentSlot::Weapon,
+            EquippedItem {
+                id: 1,
+                user_id: num,
+                preset: 0,
+                slot: EquipmentSlot::Weapon,
+                template_id: WEAPON,
+                created_at: Utc::now(),
+            },
+        );
+        let base = level_stats(level);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut visibilities: ViewMut<Visibility>,
+             mut inventories: ViewMut<UserInventory>,
+             (mut experiences, mut appearances, mut stats, mut healths, mut manas): (
+                ViewMut<UserExperience>,
+                ViewMut<UserAppearance>,
+                ViewMut<UserStats>,
+                ViewMut<Health>,
+                ViewMut<Mana>,
+            )| {
+                entities.add_entity(
+                    (
+                        &mut connections,
+                        &mut user_spawns,
+                        &mut visibilities,
+                        &mut inventories,
+                        &mut experiences,
+                        &mut appearances,
+                        &mut stats,
+                        &mut healths,
+                        &mut manas,
+                    ),
+                    (
+                        LocalConnection {
+                            channel: tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id: num,
+                            account_id: i64::from(num),
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_global_world_id,
+                            is_alive: true,
+                        },
+                        Visibility {
+                            range: 100,
+                            visible_entities: HashSet::new(),
+                        },
+                        UserInventory {
+                            size: 40,
+                            money: 0,
+                            items: HashMap::new(),
+                            equipment_preset: 0,
+                            equipment,
+                        },
+                        UserExperience {
+                            exp,
+                            rest_bonus_exp,
+                            playtime: 0,
+                            spawned_at: Instant::now(),
+                            requested: Vec::new(),
+                        },
+                        UserAppearance {
+                            name: format!("User{}", num),
+                            template_id: TemplateID {
+                                race: Race::Human,
+                                gender: Gender::Male,
+                                class: Class::Warrior,
+                            },
+                            level,
+                            details: vec![],
+                            shape: vec![],
+                            appearance: Customization::default(),
+                            appearance2: 100,
+                            show_face: true,
+                            show_style: true,
+                            guild_name: "".to_string(),
+                            guild_rank: "".to_string(),
+                        },
+                        UserStats { base, total: base },
+                        Health {
+                            hp: 1,
+                            max_hp: i64::from(base.max_hp),
+                        },
+                        Mana {
+                            mp: 1,
+                            max_mp: base.max_mp,
+                            regenerates_at: Instant::now(),
+                        },
+                    ),
+                )
+            },
+        );
+
+        TestUser {
+            connection_local_world_id,
+            rx: rx_channel,
+        }
+    }
+
+    fn request(world: &World, user: &TestUser, exp: i64, source: ExperienceSource) {
+        world.run(|mut experiences: ViewMut<UserExperience>| {
+            request_experience(
+                &mut experiences,
+                user.connection_local_world_id,
+                exp,
+                source,
+            );
+        });
+        world.run(experience_system);
+    }
+
+    fn get_progress(world: &World, user: &TestUser) -> (i32, i64, i64) {
+        world.run(
+            |experiences: View<UserExperience>, appearances: View<UserAppearance>| {
+                let (experience, appearance) = (&experiences, &appearances)
+                    .try_get(user.connection_local_world_id)
+                    .unwrap();
+                (appearance.level, experience.exp, experience.rest_bonus_exp)
+            },
+        )
+    }
+
+    fn received(rx: &Receiver<EcsMessage>) -> Vec<EcsMessage> {
+        let mut messages = Vec::new();
+        while let Ok(message) = rx.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn find_packet<T, F>(messages: &[EcsMessage], f: F) -> Option<T>
+    where
+        F: Fn(&Message) -> Option<T>,
+    {
+        messages.iter().find_map(|message| f(&**message))
+    }
+
+    fn find_change_exp(messages: &[EcsMessage]) -> Option<SPlayerChangeExp> {
+        find_packet(messages, |message| match message {
+            ResponsePlayerChangeExp { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_rest_bonus_applies_to_kills() {
+        let world = setup();
+        let user = add_user(&world, 1, 10, 1000, 300);
+
+        request(&world, &user, 500, ExperienceSource::Kill);
+        assert_eq!(get_progress(&world, &user), (10, 1800, 0));
+        assert_eq!(
+            find_change_exp(&received(&user.rx)).unwrap(),
+            SPlayerChangeExp {
+                gained_exp: 800,
+                gained_rest_bonus_exp: 300,
+                total_exp: total_exp(10, 1800),
+                level_exp: 1800,
+                total_level_exp: 100_000,
+                rest_bonus_exp: 0,
+                max_rest_bonus_exp: 100_000,
+            }
+        );
+
+        // Quests don't use the rest bonus
+        world.run(|mut experiences: ViewMut<UserExperience>| {
+            (&mut experiences)
+                .try_get(user.connection_local_world_id)
+                .unwrap()
+                .rest_bonus_exp = 300;
+        });
+        request(&world, &user, 500, ExperienceSource::Quest);
+        assert_eq!(get_progress(&world, &user), (10, 2300, 300));
+        let change = find_change_exp(&received(&user.rx)).unwrap();
+        assert_eq!(change.gained_exp, 500);
+        assert_eq!(change.gained_rest_bonus_exp, 0);
+    }
+
+    #[test]
+    fn test_level_up() {
+        let world = setup();
+        let user = add_user(&world, 1, 1, 50, 0);
+        let observer = add_user(&world, 2, 1, 0, 0);
+        world.run(|mut visibilities: ViewMut<Visibility>| {
+            (&mut visibilities)
+                .try_get(observer.connection_local_world_id)
+                .unwrap()
+                .visible_entities
+                .insert(user.connection_local_world_id);
+        });
+
+        // Level 1 needs 100 experience, level 2 needs 800 experience
+        request(&world, &user, 900, ExperienceSource::Quest);
+        assert_eq!(get_progress(&world, &user), (3, 50, 0));
+
+        let mut expected = level_stats(3);
+        expected.attack += 100;
+        world.run(
+            |stats: View<UserStats>, healths: View<Health>, manas: View<Mana>| {
+                let (user_stats, health, mana) = (&stats, &healths, &manas)
+                    .try_get(user.connection_local_world_id)
+                    .unwrap();
+                assert_eq!(user_stats.base, level_stats(3));
+                assert_eq!(user_stats.total, expected);
+                assert_eq!(
+                    *health,
+                    Health {
+                        hp: i64::from(expected.max_hp),
+                        max_hp: i64::from(expected.max_hp),
+                    }
+                );
+                assert_eq!(mana.mp, expected.max_mp);
+                assert_eq!(mana.max_mp, expected.max_mp);
+            },
+        );
+
+        let messages = received(&user.rx);
+        assert_eq!(find_change_exp(&messages).unwrap().total_exp, 950);
+        let mp = find_packet(&messages, |message| match message {
+            ResponsePlayerChangeMp { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(mp.current_mp, expected.max_mp);
+
+        for messages in [messages, received(&observer.rx)].iter() {
+            let levelup = find_packet(messages, |message| match message {
+                ResponseUserLevelup { packet, .. } => Some(packet.clone()),
+                _ => None,
+            })
+            .unwrap();
+            assert_eq!(levelup.target_id, user.connection_local_world_id);
+            assert_eq!(levelup.level, 3);
+            let hp = find_packet(messages, |message| match message {
+                ResponseCreatureChangeHp { packet, .. } => Some(packet.clone()),
+                _ => None,
+            })
+            .unwrap();
+            assert_eq!(hp.current_hp, i64::from(expected.max_hp));
+        }
+    }
+
+    #[test]
+    fn test_no_experience_at_max_level() {
+        let world = setup();
+        let user = add_user(&world, 1, MAX_LEVEL - 1, 0, 0);
+
+        request(&world, &user, 1_000_000_000, ExperienceSource::Kill);
+        assert_eq!(get_progress(&world, &user), (MAX_LEVEL, 0, 0));
+        received(&user.rx);
+
+        request(&world, &user, 100, ExperienceSource::Kill);
+        assert_eq!(get_progress(&world, &user), (MAX_LEVEL, 0, 0));
+        assert!(received(&user.rx).is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::config::Configuration;
+use crate::ecs::message::Message::GuildWarKill;
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::GuildWarRegistry;
+use shipyard::*;
+use tracing::debug;
+
+/// Keeps the guild wars of the local world in sync with the global world.
+pub fn guild_war_system(
+    incoming_messages: View<EcsMessage>,
+    mut guild_wars: UniqueViewMut<GuildWarRegistry>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::GuildWarsChanged {
+                guild_wars: registry,
+            } => {
+                debug!("Message::GuildWarsChanged incoming");
+                *guild_wars = registry.clone();
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+/// Checks if an user is allowed to attack another user. Users can fight each other everywhere
+/// on PvP servers. On other servers only members of guilds at war can fight each other.
+pub fn can_attack_user(
+    config: &Configuration,
+    guild_wars: &GuildWarRegistry,
+    user_id: i32,
+    target_user_id: i32,
+) -> bool {
+    user_id != target_user_id
+        && (config.game.pvp || guild_wars.are_enemies(user_id, target_user_id))
+}
+
+/// Reports the kill of a member of an enemy guild to the global world, which keeps the score
+/// of the war.
+pub fn assemble_guild_war_kill(killer_user_id: i32, victim_user_id: i32) -> EcsMessage {
+    Box::new(GuildWarKill {
+        killer_user_id,
+        victim_user_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_guild_wars() -> GuildWarRegistry {
+        let mut guild_wars = GuildWarRegistry::default();
+        guild_wars.add_war(2, 1);
+        guild_wars.members.insert(10, 1);
+        guild_wars.members.insert(11, 1);
+        guild_wars.members.insert(20, 2);
+        guild_wars.members.insert(30, 3);
+        guild_wars
+    }
+
+    #[test]
+    fn test_guild_wars_changed() {
+        let world = World::new();
+        world.add_unique(GuildWarRegistry::default());
+
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::GuildWarsChanged {
+                        guild_wars: get_guild_wars(),
+                    }),
+                );
+            },
+        );
+        world.run(guild_war_system);
+
+        world.run(|guild_wars: UniqueView<GuildWarRegistry>| {
+            assert_eq!(*guild_wars, get_guild_wars());
+        });
+    }
+
+    #[test]
+    fn test_can_attack_user() {
+        let mut config = Configuration::default();
+        config.game.pvp = false;
+        let guild_wars = get_guild_wars();
+
+        // Only members of guilds at war can fight each other.
+        assert!(can_attack_user(&config, &guild_wars, 10, 20));
+        assert!(can_attack_user(&config, &guild_wars, 20, 11));
+        assert!(!can_attack_user(&config, &guild_wars, 10, 11));
+        assert!(!can_attack_user(&config, &guild_wars, 10, 30));
+        assert!(!can_attack_user(&config, &guild_wars, 10, 40));
+        assert!(!can_attack_user(&config, &guild_wars, 10, 10));
+
+        config.game.pvp = true;
+        assert!(can_attack_user(&config, &guild_wars, 10, 30));
+        assert!(can_attack_user(&config, &guild_wars, 40, 41));
+        assert!(!can_attack_user(&config, &guild_wars, 10, 10));
+    }
+}
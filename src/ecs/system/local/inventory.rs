@@ -0,0 +1,925 @@
+use crate::ecs::component::{LocalConnection, LocalUserSpawn, UserInventory};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::ItemRegistry;
+use crate::ecs::system::local::{
+    assemble_itemlist, max_stack, send_message_to_connection, INVENTORY_CONTAINER,
+};
+use crate::model::entity::{Inventory, Item};
+use crate::model::repository::{inventory, item};
+use crate::model::MAX_INVENTORY_SIZE;
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use shipyard::*;
+use sqlx::PgPool;
+use std::cmp::min;
+use std::collections::HashSet;
+use tracing::{debug, error, info_span};
+
+/// Number of slots that are unlocked by expanding the inventory once.
+const INVENTORY_EXPANSION_SIZE: i32 = 8;
+
+/// Handles the inventory of the users. Changes to the items are persisted before they are applied
+/// to the inventory component.
+pub fn inventory_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<LocalConnection>,
+    user_spawns: View<LocalUserSpawn>,
+    mut inventories: ViewMut<UserInventory>,
+    item_registry: UniqueView<ItemRegistry>,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestShowInven {
+                connection_global_world_id,
+                connection_local_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_show_inven(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &connections,
+                    &inventories,
+                ) {
+                    error!("Ignoring show inventory request: {:?}", e);
+                }
+            }
+            Message::RequestMoveInvenPos {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_move_inven_pos(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &mut inventories,
+                    &item_registry,
+                    &pool,
+                ) {
+                    error!("Ignoring move inventory position request: {:?}", e);
+                }
+            }
+            Message::RequestDelItem {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_del_item(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &mut inventories,
+                    &item_registry,
+                    &pool,
+                ) {
+                    error!("Ignoring delete item request: {:?}", e);
+                }
+            }
+            Message::RequestApplyInvenPocketSort {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_apply_inven_pocket_sort(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &mut inventories,
+                    &item_registry,
+                    &pool,
+                ) {
+                    error!("Ignoring inventory sort request: {:?}", e);
+                }
+            }
+            Message::RequestExpandInvenPocket {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_expand_inven_pocket(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &mut inventories,
+                    &pool,
+                ) {
+                    error!("Ignoring inventory expansion request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_show_inven(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    connections: &View<LocalConnection>,
+    inventories: &ViewMut<UserInventory>,
+) -> Result<()> {
+    debug!("Message::RequestShowInven incoming");
+
+    let inventory = inventories
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+
+    send_message_to_connection(
+        assemble_itemlist(
+            connection_global_world_id,
+            connection_local_world_id,
+            inventory,
+            true,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_move_inven_pos(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CMoveInvenPos,
+    connections: &View<LocalConnection>,
+    inventories: &mut ViewMut<UserInventory>,
+    item_registry: &UniqueView<ItemRegistry>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestMoveInvenPos incoming");
+
+    check_pocket(packet.container, packet.pocket)?;
+
+    let mut inventory = inventories
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    check_slot(&inventory, packet.src_slot)?;
+    check_slot(&inventory, packet.dst_slot)?;
+    ensure!(
+        packet.src_slot != packet.dst_slot,
+        "Can't move an item onto itself"
+    );
+
+    let mut item = inventory
+        .items
+        .get(&packet.src_slot)
+        .context(format!("No item found in slot {}", packet.src_slot))?
+        .clone();
+
+    let mut changed_items = Vec::with_capacity(2);
+    let mut deleted_items = Vec::new();
+    match inventory.items.get(&packet.dst_slot).cloned() {
+        None => {
+            item.slot = packet.dst_slot;
+            changed_items.push(item);
+        }
+        Some(mut other_item)
+            if other_item.template_id == item.template_id
+                && other_item.amount < max_stack(item_registry, item.template_id) =>
+        {
+            // Fill up the stack of the destination slot.
+            let amount = min(
+                max_stack(item_registry, item.template_id) - other_item.amount,
+                item.amount,
+            );
+            other_item.amount += amount;
+            item.amount -= amount;
+            changed_items.push(other_item);
+            if item.amount == 0 {
+                deleted_items.push(item);
+            } else {
+                changed_items.push(item);
+            }
+        }
+        Some(mut other_item) => {
+            other_item.slot = item.slot;
+            item.slot = packet.dst_slot;
+            changed_items.push(item);
+            changed_items.push(other_item);
+        }
+    }
+
+    save_items(pool, &changed_items, &deleted_items)?;
+    apply_items(&mut inventory, changed_items, &deleted_items);
+
+    send_message_to_connection(
+        assemble_itemlist(
+            connection_global_world_id,
+            connection_local_world_id,
+            &inventory,
+            false,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_del_item(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CDelItem,
+    connections: &View<LocalConnection>,
+    inventories: &mut ViewMut<UserInventory>,
+    item_registry: &UniqueView<ItemRegistry>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestDelItem incoming");
+
+    check_pocket(packet.container, packet.pocket)?;
+
+    let mut inventory = inventories
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    let mut item = inventory
+        .items
+        .get(&packet.slot)
+        .context(format!("No item found in slot {}", packet.slot))?
+        .clone();
+
+    let template = item_registry
+        .get(item.template_id)
+        .context(format!("Can't find item template {}", item.template_id))?;
+    ensure!(
+        template.destroyable,
+        "Item template {} can't be destroyed",
+        item.template_id
+    );
+    ensure!(
+        packet.amount > 0 && packet.amount <= item.amount,
+        "Can't delete {} of {} items",
+        packet.amount,
+        item.amount
+    );
+
+    item.amount -= packet.amount;
+    if item.amount == 0 {
+        save_items(pool, &[], &[item.clone()])?;
+        apply_items(&mut inventory, vec![], &[item]);
+    } else {
+        save_items(pool, &[item.clone()], &[])?;
+        apply_items(&mut inventory, vec![item], &[]);
+    }
+
+    send_message_to_connection(
+        assemble_itemlist(
+            connection_global_world_id,
+            connection_local_world_id,
+            &inventory,
+            false,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_apply_inven_pocket_sort(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CApplyInvenPocketSort,
+    connections: &View<LocalConnection>,
+    inventories: &mut ViewMut<UserInventory>,
+    item_registry: &UniqueView<ItemRegistry>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestApplyInvenPocketSort incoming");
+
+    check_pocket(packet.container, packet.pocket)?;
+
+    let mut inventory = inventories
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    let (changed_items, deleted_items) = sort_items(&inventory, item_registry);
+
+    save_items(pool, &changed_items, &deleted_items)?;
+    apply_items(&mut inventory, changed_items, &deleted_items);
+
+    send_message_to_connection(
+        assemble_itemlist(
+            connection_global_world_id,
+            connection_local_world_id,
+            &inventory,
+            false,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_expand_inven_pocket(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CExpandInvenPocket,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &mut ViewMut<UserInventory>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestExpandInvenPocket incoming");
+
+    check_pocket(packet.container, packet.pocket)?;
+
+    let (spawn, inventory) = (user_spawns, inventories)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    let size = inventory.size + INVENTORY_EXPANSION_SIZE;
+    ensure!(
+        size <= MAX_INVENTORY_SIZE,
+        "Inventory of user {} can't be expanded any further",
+        spawn.user_id
+    );
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+        inventory::update(
+            &mut conn,
+            &Inventory {
+                user_id: spawn.user_id,
+                size,
+                money: inventory.money,
+                equipment_preset: inventory.equipment_preset,
+            },
+        )
+        .await?;
+        Ok::<(), anyhow::Error>(())
+    })?;
+    inventory.size = size;
+
+    send_message_to_connection(
+        assemble_itemlist(
+            connection_global_world_id,
+            connection_local_world_id,
+            &inventory,
+            false,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+/// Only a single pocket of the inventory is currently supported.
+fn check_pocket(container: i32, pocket: i32) -> Result<()> {
+    ensure!(
+        container == INVENTORY_CONTAINER && pocket == 0,
+        "Unsupported container {} / pocket {}",
+        container,
+        pocket
+    );
+    Ok(())
+}
+
+fn check_slot(inventory: &UserInventory, slot: i32) -> Result<()> {
+    ensure!(
+        slot >= 0 && slot < inventory.size,
+        "Slot {} is outside of the inventory",
+        slot
+    );
+    Ok(())
+}
+
+/// Merges the stacks of the same item template and orders the items by their template. Returns
+/// the items that changed and the items that need to be deleted, since they were merged into
+/// other stacks.
+fn sort_items(inventory: &UserInventory, item_registry: &ItemRegistry) -> (Vec<Item>, Vec<Item>) {
+    let mut items = inventory.items.values().cloned().collect::<Vec<Item>>();
+    items.sort_by_key(|item| (item.template_id, -item.amount, item.slot));
+
+    let mut sorted_items: Vec<Item> = Vec::with_capacity(items.len());
+    let mut deleted_items = Vec::new();
+    for mut item in items {
+        if let Some(last_item) = sorted_items.last_mut() {
+            let stack_size = max_stack(item_registry, item.template_id);
+            if last_item.template_id == item.template_id && last_item.amount < stack_size {
+                let amount = min(stack_size - last_item.amount, item.amount);
+                last_item.amount += amount;
+                item.amount -= amount;
+            }
+        }
+        if item.amount == 0 {
+            deleted_items.push(item);
+        } else {
+            sorted_items.push(item);
+        }
+    }
+
+    let changed_items = sorted_items
+        .into_iter()
+        .enumerate()
+        .map(|(slot, mut item)| {
+            item.slot = slot as i32;
+            item
+        })
+        .filter(|item| inventory.items.get(&item.slot) != Some(item))
+        .collect();
+
+    (changed_items, deleted_items)
+}
+
+/// Persists the changed items and deletes the given items inside one transaction.
+fn save_items(
+    pool: &UniqueView<PgPool>,
+    changed_items: &[Item],
+    deleted_items: &[Item],
+) -> Result<()> {
+    Ok(task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        for deleted_item in deleted_items {
+            item::delete(&mut conn, deleted_item.id).await?;
+        }
+        for changed_item in changed_items {
+            item::update(&mut conn, changed_item).await?;
+        }
+
+        conn.commit().await?;
+
+        Ok::<(), anyhow::Error>(())
+    })?)
+}
+
+/// Applies already persisted changes to the inventory component.
+fn apply_items(inventory: &mut UserInventory, changed_items: Vec<Item>, deleted_items: &[Item]) {
+    let ids = changed_items
+        .iter()
+        .chain(deleted_items.iter())
+        .map(|item| item.id)
+        .collect::<HashSet<i64>>();
+    inventory
+        .items
+        .retain(|_slot, item| !ids.contains(&item.id));
+    for item in changed_items {
+        inventory.items.insert(item.slot, item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::UserSpawnStatus;
+    use crate::ecs::resource::{DeletionList, ItemTemplate};
+    use crate::ecs::system::common::cleaner_system;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::inventory::tests::get_default_inventory;
+    use crate::model::repository::item::tests::get_default_item;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver};
+    use std::collections::HashMap;
+
+    const POTION: i32 = 8005;
+    const WEAPON: i32 = 10001;
+
+    struct TestUser {
+        user: User,
+        connection_global_world_id: EntityId,
+        connection_local_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(pool);
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(ItemRegistry::new(vec![
+            ItemTemplate {
+                id: POTION,
+                max_stack: 50,
+                destroyable: true,
+                ..Default::default()
+            },
+            ItemTemplate {
+                id: WEAPON,
+                max_stack: 1,
+                destroyable: false,
+                ..Default::default()
+            },
+        ]));
+        world
+    }
+
+    /// Creates an user with the given items (template ID, slot, amount) and spawns it.
+    fn add_user(world: &World, pool: &PgPool, items: &[(i32, i32, i32)]) -> Result<TestUser> {
+        let (user, inventory, items) = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let account = account::create(&mut conn, &get_default_account(0)).await?;
+            let user = user::create(&mut conn, &get_default_user(&account, 0)).await?;
+            let inventory = inventory::create(&mut conn, &get_default_inventory(&user)).await?;
+
+            let mut created_items = Vec::with_capacity(items.len());
+            for (template_id, slot, amount) in items {
+                let mut new_item = get_default_item(&user, *slot);
+                new_item.template_id = *template_id;
+                new_item.amount = *amount;
+                created_items.push(item::create(&mut conn, &new_item).await?);
+            }
+
+            Ok::<(User, Inventory, Vec<Item>), anyhow::Error>((user, inventory, created_items))
+        })?;
+
+        let connection_global_world_id =
+            from_vec::<EntityId>(vec![0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut inventories: ViewMut<UserInventory>| {
+                entities.add_entity(
+                    (&mut connections, &mut user_spawns, &mut inventories),
+                    (
+                        LocalConnection {
+                            channel: tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id: user.id,
+                            account_id: user.account_id,
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_global_world_id,
+                            is_alive: true,
+                        },
+                        UserInventory {
+                            size: inventory.size,
+                            money: inventory.money,
+                            items: items.into_iter().map(|item| (item.slot, item)).collect(),
+                            equipment_preset: inventory.equipment_preset,
+                            equipment: HashMap::new(),
+                        },
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            user,
+            connection_global_world_id,
+            connection_local_world_id,
+            rx: rx_channel,
+        })
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(inventory_system);
+        world.run(cleaner_system);
+    }
+
+    fn move_item(world: &World, user: &TestUser, src_slot: i32, dst_slot: i32) {
+        run_message(
+            world,
+            Message::RequestMoveInvenPos {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CMoveInvenPos {
+                    game_id: user.connection_local_world_id,
+                    container: INVENTORY_CONTAINER,
+                    pocket: 0,
+                    src_slot,
+                    dst_slot,
+                },
+            },
+        );
+    }
+
+    fn delete_item(world: &World, user: &TestUser, slot: i32, amount: i32) {
+        run_message(
+            world,
+            Message::RequestDelItem {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CDelItem {
+                    game_id: user.connection_local_world_id,
+                    container: INVENTORY_CONTAINER,
+                    pocket: 0,
+                    slot,
+                    amount,
+                },
+            },
+        );
+    }
+
+    fn expand_inventory(world: &World, user: &TestUser) {
+        run_message(
+            world,
+            Message::RequestExpandInvenPocket {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CExpandInvenPocket {
+                    game_id: user.connection_local_world_id,
+                    container: INVENTORY_CONTAINER,
+                    pocket: 0,
+                },
+            },
+        );
+    }
+
+    fn assert_itemlist(user: &TestUser) -> Result<SItemlist> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseItemlist { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseItemlist message"),
+        }
+    }
+
+    /// Checks that the inventory component matches the database and returns the items as
+    /// (template ID, slot, amount) ordered by their slot.
+    fn assert_persisted(
+        world: &World,
+        pool: &PgPool,
+        user: &TestUser,
+    ) -> Result<Vec<(i32, i32, i32)>> {
+        let db_items = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            item::list_by_user_id(&mut conn, user.user.id).await
+        })?;
+
+        world.run(|inventories: View<UserInventory>| {
+            let inventory = inventories.try_get(user.connection_local_world_id).unwrap();
+            let mut items = inventory.items.values().cloned().collect::<Vec<Item>>();
+            items.sort_by_key(|item| item.slot);
+            assert_eq!(items, db_items);
+            for (slot, item) in inventory.items.iter() {
+                assert_eq!(*slot, item.slot);
+            }
+        });
+
+        Ok(db_items
+            .iter()
+            .map(|item| (item.template_id, item.slot, item.amount))
+            .collect())
+    }
+
+    #[test]
+    fn test_show_inven() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[(POTION, 3, 20), (WEAPON, 0, 1)])?;
+
+            run_message(
+                &world,
+                Message::RequestShowInven {
+                    connection_global_world_id: user.connection_global_world_id,
+                    connection_local_world_id: user.connection_local_world_id,
+                    packet: CShowInven { unk1: 1 },
+                },
+            );
+
+            let packet = assert_itemlist(&user)?;
+            assert!(packet.open);
+            assert_eq!(packet.game_id, user.connection_local_world_id);
+            assert_eq!(packet.container, INVENTORY_CONTAINER);
+            assert_eq!(packet.size, 40);
+            assert_eq!(packet.items.len(), 2);
+            assert_eq!(packet.items[0].id, WEAPON);
+            assert_eq!(packet.items[0].slot, 0);
+            assert_eq!(packet.items[1].id, POTION);
+            assert_eq!(packet.items[1].slot, 3);
+            assert_eq!(packet.items[1].amount, 20);
+            assert_eq!(packet.items[1].owner_id, user.user.id as i64);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_move_inven_pos() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[(POTION, 3, 20), (WEAPON, 0, 1)])?;
+
+            move_item(&world, &user, 3, 7);
+            assert!(!assert_itemlist(&user)?.open);
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                vec![(WEAPON, 0, 1), (POTION, 7, 20)]
+            );
+
+            // Items swap their slots
+            move_item(&world, &user, 0, 7);
+            assert_itemlist(&user)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                vec![(POTION, 0, 20), (WEAPON, 7, 1)]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_move_inven_pos_merge_stacks() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(
+                &world,
+                &pool,
+                &[(POTION, 0, 40), (POTION, 1, 20), (POTION, 2, 5)],
+            )?;
+
+            // The stack is filled up to it's maximum
+            move_item(&world, &user, 1, 0);
+            assert_itemlist(&user)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                vec![(POTION, 0, 50), (POTION, 1, 10), (POTION, 2, 5)]
+            );
+
+            // The moved stack is used up
+            move_item(&world, &user, 2, 1);
+            assert_itemlist(&user)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                vec![(POTION, 0, 50), (POTION, 1, 15)]
+            );
+
+            // Full stacks are swapped
+            move_item(&world, &user, 1, 0);
+            assert_itemlist(&user)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                vec![(POTION, 0, 15), (POTION, 1, 50)]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_move_inven_pos_invalid_slot() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[(POTION, 3, 20)])?;
+
+            move_item(&world, &user, 3, 40);
+            move_item(&world, &user, 3, -1);
+            move_item(&world, &user, 4, 5);
+            move_item(&world, &user, 3, 3);
+
+            assert!(user.rx.is_empty());
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                vec![(POTION, 3, 20)]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_del_item() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[(POTION, 3, 20), (WEAPON, 0, 1)])?;
+
+            delete_item(&world, &user, 3, 5);
+            assert_itemlist(&user)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                vec![(WEAPON, 0, 1), (POTION, 3, 15)]
+            );
+
+            // Can't delete more items than the stack holds
+            delete_item(&world, &user, 3, 16);
+            assert!(user.rx.is_empty());
+
+            delete_item(&world, &user, 3, 15);
+            assert_itemlist(&user)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                vec![(WEAPON, 0, 1)]
+            );
+
+            // Some items can't be destroyed
+            delete_item(&world, &user, 0, 1);
+            assert!(user.rx.is_empty());
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                vec![(WEAPON, 0, 1)]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_apply_inven_pocket_sort() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(
+                &world,
+                &pool,
+                &[
+                    (WEAPON, 0, 1),
+                    (POTION, 2, 30),
+                    (WEAPON, 5, 1),
+                    (POTION, 7, 30),
+                    (POTION, 9, 5),
+                ],
+            )?;
+
+            run_message(
+                &world,
+                Message::RequestApplyInvenPocketSort {
+                    connection_global_world_id: user.connection_global_world_id,
+                    connection_local_world_id: user.connection_local_world_id,
+                    packet: CApplyInvenPocketSort {
+                        game_id: user.connection_local_world_id,
+                        container: INVENTORY_CONTAINER,
+                        pocket: 0,
+                    },
+                },
+            );
+
+            assert_eq!(assert_itemlist(&user)?.items.len(), 4);
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                vec![
+                    (POTION, 0, 50),
+                    (POTION, 1, 15),
+                    (WEAPON, 2, 1),
+                    (WEAPON, 3, 1)
+                ]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_expand_inven_pocket() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, &[])?;
+
+            expand_inventory(&world, &user);
+            assert_eq!(assert_itemlist(&user)?.size, 48);
+            let inventory = task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                inventory::get_by_user_id(&mut conn, user.user.id).await
+            })?;
+            assert_eq!(inventory.size, 48);
+
+            // The inventory can't grow beyond it's maximal size
+            world.run(|mut inventories: ViewMut<UserInventory>| {
+                (&mut inventories)
+                    .try_get(user.connection_local_world_id)
+                    .unwrap()
+                    .size = MAX_INVENTORY_SIZE;
+            });
+            expand_inventory(&world, &user);
+            assert!(user.rx.is_empty());
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,495 @@
+use crate::ecs::component::{
+    LocalConnection, LocalUserSpawn, Location, UserSpawnStatus, Visibility,
+};
+use crate::ecs::message::Message::ResponseUserLocation;
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::system::send_message;
+use crate::model::{Angle, Vec3f};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use nalgebra::{Point3, Rotation3};
+use shipyard::*;
+use tracing::{debug, error, info_span};
+
+/// Run speed of an user.
+const USER_RUN_SPEED: i16 = 150;
+
+/// Tracks the movement of the users and informs the users that can see them about it.
+pub fn movement_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<LocalConnection>,
+    user_spawns: View<LocalUserSpawn>,
+    visibilities: View<Visibility>,
+    mut locations: ViewMut<Location>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestPlayerLocation {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_player_location(
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &visibilities,
+                    &mut locations,
+                ) {
+                    error!("Ignoring Message::RequestPlayerLocation: {:?}", e);
+                }
+            }
+            Message::RequestNotifyLocationInAction {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                debug!("Message::RequestNotifyLocationInAction incoming");
+                if let Err(e) = update_location(
+                    *connection_local_world_id,
+                    &packet.location,
+                    packet.rotation,
+                    &user_spawns,
+                    &mut locations,
+                ) {
+                    error!("Ignoring Message::RequestNotifyLocationInAction: {:?}", e);
+                }
+            }
+            Message::RequestNotifyLocationInDash {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                debug!("Message::RequestNotifyLocationInDash incoming");
+                if let Err(e) = update_location(
+                    *connection_local_world_id,
+                    &packet.location,
+                    packet.rotation,
+                    &user_spawns,
+                    &mut locations,
+                ) {
+                    error!("Ignoring Message::RequestNotifyLocationInDash: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_player_location(
+    connection_local_world_id: EntityId,
+    packet: &CPlayerLocation,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    visibilities: &View<Visibility>,
+    locations: &mut ViewMut<Location>,
+) -> Result<()> {
+    debug!("Message::RequestPlayerLocation incoming");
+
+    update_location(
+        connection_local_world_id,
+        &packet.location,
+        packet.rotation,
+        user_spawns,
+        locations,
+    )?;
+
+    (connections, user_spawns, visibilities)
+        .iter()
+        .with_id()
+        .filter(|(_id, (_connection, spawn, _visibility))| spawn.status == UserSpawnStatus::Spawned)
+        .for_each(|(id, (connection, spawn, visibility))| {
+            if !visibility
+                .visible_entities
+                .contains(&connection_local_world_id)
+            {
+                return;
+            }
+            send_message(
+                assemble_user_location(
+                    spawn.connection_global_world_id,
+                    id,
+                    connection_local_world_id,
+                    packet,
+                ),
+                &connection.channel,
+            );
+        });
+
+    Ok(())
+}
+
+/// Updates the location of a spawned user.
+fn update_location(
+    connection_local_world_id: EntityId,
+    position: &Vec3f,
+    rotation: Angle,
+    user_spawns: &View<LocalUserSpawn>,
+    locations: &mut ViewMut<Location>,
+) -> Result<()> {
+    let spawn = user_spawns
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find local spawn for {:?}",
+            connection_local_world_id
+        ))?;
+
+    ensure!(
+        spawn.status == UserSpawnStatus::Spawned,
+        "User {:?} is not spawned and can't move",
+        connection_local_world_id
+    );
+
+    ensure!(
+        position.x.is_finite() && position.y.is_finite() && position.z.is_finite(),
+        "User {:?} send an invalid position {:?}",
+        connection_local_world_id,
+        position
+    );
+
+    let mut location = locations
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find location for {:?}",
+            connection_local_world_id
+        ))?;
+    location.point = Point3::from(*position);
+    location.rotation = Rotation3::from(rotation);
+
+    Ok(())
+}
+
+fn assemble_user_location(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    user_id: EntityId,
+    packet: &CPlayerLocation,
+) -> EcsMessage {
+    Box::new(ResponseUserLocation {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SUserLocation {
+            user_id,
+            location: packet.location,
+            rotation: packet.rotation,
+            look_direction: packet.look_direction,
+            speed: USER_RUN_SPEED,
+            destination: packet.destination,
+            move_type: packet.move_type,
+            in_shuttle: packet.in_shuttle,
+            time: packet.time,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::{DeletionList, GlobalMessageChannel, VisibilityGrid};
+    use crate::ecs::system::local::{user_gateway_system, visibility_system};
+    use crate::protocol::serde::from_vec;
+    use async_std::sync::{channel, Receiver};
+    use nalgebra::Vector3;
+    use std::collections::HashSet;
+
+    fn setup() -> (World, Receiver<EcsMessage>) {
+        let (global_tx_channel, global_rx_channel) = channel(1024);
+
+        let world = World::new();
+        world.add_unique(GlobalMessageChannel {
+            channel: global_tx_channel,
+        });
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(VisibilityGrid::default());
+
+        (world, global_rx_channel)
+    }
+
+    fn add_user(
+        world: &World,
+        user_id: i32,
+        point: Point3<f32>,
+        status: UserSpawnStatus,
+    ) -> (EntityId, Receiver<EcsMessage>) {
+        let (connection_tx_channel, connection_rx_channel) = channel(1024);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut locations: ViewMut<Location>,
+             mut visibilities: ViewMut<Visibility>| {
+                entities.add_entity(
+                    (
+                        &mut connections,
+                        &mut user_spawns,
+                        &mut locations,
+                        &mut visibilities,
+                    ),
+                    (
+                        LocalConnection {
+                            channel: connection_tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id,
+                            account_id: 1,
+                            status,
+                            zone_id: 0,
+                            connection_global_world_id: from_vec::<EntityId>(vec![
+                                user_id as u8,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                            ])
+                            .unwrap(),
+                            is_alive: true,
+                        },
+                        Location {
+                            point,
+                            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+                        },
+                        Visibility {
+                            range: 2000,
+                            visible_entities: HashSet::new(),
+                        },
+                    ),
+                )
+            },
+        );
+
+        (connection_local_world_id, connection_rx_channel)
+    }
+
+    fn add_player_location(world: &World, connection_local_world_id: EntityId, x: f32) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::RequestPlayerLocation {
+                        connection_global_world_id: connection_local_world_id,
+                        connection_local_world_id,
+                        packet: CPlayerLocation {
+                            location: Vec3f { x, y: 0.0, z: 0.0 },
+                            rotation: Angle::from_deg(90.0),
+                            look_direction: 0,
+                            destination: Vec3f {
+                                x: x + 10.0,
+                                y: 0.0,
+                                z: 0.0,
+                            },
+                            move_type: 0,
+                            jump_distance: 0,
+                            in_shuttle: false,
+                            time: 1000,
+                        },
+                    }),
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_player_location() -> Result<()> {
+        let (world, _global_rx_channel) = setup();
+        let (mover_id, mover_rx_channel) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let (observer_id, observer_rx_channel) = add_user(
+            &world,
+            2,
+            Point3::new(500.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let (_far_id, far_rx_channel) = add_user(
+            &world,
+            3,
+            Point3::new(10000.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let (_waiting_id, waiting_rx_channel) = add_user(
+            &world,
+            4,
+            Point3::new(100.0, 0.0, 0.0),
+            UserSpawnStatus::Waiting,
+        );
+        world.run(visibility_system);
+
+        add_player_location(&world, mover_id, 100.0);
+        world.run(movement_system);
+
+        world.run(|locations: View<Location>| {
+            let location = locations.try_get(mover_id)?;
+            assert_eq!(location.point, Point3::new(100.0, 0.0, 0.0));
+            assert_eq!(location.rotation, Rotation3::from(Angle::from_deg(90.0)));
+
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        match &*observer_rx_channel.try_recv()? {
+            Message::ResponseUserLocation {
+                connection_local_world_id,
+                packet,
+                ..
+            } => {
+                assert_eq!(*connection_local_world_id, observer_id);
+                assert_eq!(packet.user_id, mover_id);
+                assert_eq!(packet.location.x, 100.0);
+                assert_eq!(packet.destination.x, 110.0);
+                assert_eq!(packet.time, 1000);
+            }
+            _ => panic!("Message is not a ResponseUserLocation message"),
+        }
+
+        assert!(mover_rx_channel.is_empty());
+        assert!(far_rx_channel.is_empty());
+        assert!(waiting_rx_channel.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_player_location_not_spawned() -> Result<()> {
+        let (world, _global_rx_channel) = setup();
+        let (mover_id, _mover_rx_channel) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Waiting,
+        );
+        let (_observer_id, observer_rx_channel) = add_user(
+            &world,
+            2,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        world.run(visibility_system);
+
+        add_player_location(&world, mover_id, 100.0);
+        world.run(movement_system);
+
+        world.run(|locations: View<Location>| {
+            let location = locations.try_get(mover_id)?;
+            assert_eq!(location.point, Point3::new(0.0, 0.0, 0.0));
+
+            Ok::<(), anyhow::Error>(())
+        })?;
+        assert!(observer_rx_channel.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_player_location_invalid_position() -> Result<()> {
+        let (world, _global_rx_channel) = setup();
+        let (mover_id, _mover_rx_channel) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+
+        add_player_location(&world, mover_id, std::f32::NAN);
+        world.run(movement_system);
+
+        world.run(|locations: View<Location>| {
+            let location = locations.try_get(mover_id)?;
+            assert_eq!(location.point, Point3::new(0.0, 0.0, 0.0));
+
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_notify_location_in_action() -> Result<()> {
+        let (world, _global_rx_channel) = setup();
+        let (user_id, _rx_channel) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::RequestNotifyLocationInAction {
+                        connection_global_world_id: user_id,
+                        connection_local_world_id: user_id,
+                        packet: CNotifyLocationInAction {
+                            skill_id: 1,
+                            stage: 0,
+                            location: Vec3f {
+                                x: 1.0,
+                                y: 2.0,
+                                z: 3.0,
+                            },
+                            rotation: Angle::from_deg(180.0),
+                        },
+                    }),
+                );
+            },
+        );
+        world.run(movement_system);
+
+        world.run(|locations: View<Location>| {
+            let location = locations.try_get(user_id)?;
+            assert_eq!(location.point, Point3::new(1.0, 2.0, 3.0));
+
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_despawn_after_movement() -> Result<()> {
+        let (world, global_rx_channel) = setup();
+        let (user_id, _rx_channel) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+
+        add_player_location(&world, user_id, 100.0);
+        world.run(movement_system);
+
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(
+                    &mut messages,
+                    Box::new(Message::UserDespawn {
+                        connection_local_world_id: user_id,
+                    }),
+                );
+            },
+        );
+        world.run(user_gateway_system);
+
+        // The final position of the user is send to the global world to be persisted
+        match &*global_rx_channel.try_recv()? {
+            Message::UserDespawned { user_finalizer } => {
+                assert_eq!(user_finalizer.user_id, 1);
+                assert_eq!(user_finalizer.location.point, Point3::new(100.0, 0.0, 0.0));
+            }
+            _ => panic!("Message is not a UserDespawned message"),
+        }
+
+        Ok(())
+    }
+}
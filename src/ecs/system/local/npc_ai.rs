@@ -0,0 +1,1127 @@
+use crate::ecs::component::{
+    Ai, AiState, Health, LocalConnection, LocalUserSpawn, Location, Npc, NpcSpawn, UserSpawnStatus,
+    UserStats, Visibility,
+};
+use crate::ecs::message::Message::{
+    ResponseCreatureChangeHp, ResponseCreatureLife, ResponseNpcLocation,
+};
+use crate::ecs::resource::{AiRng, NpcSkill, NpcTemplate, Tick};
+use crate::ecs::system::local::combat::calculate_damage;
+use crate::ecs::system::local::send_to_observers;
+use crate::model::Angle;
+use crate::protocol::packet::*;
+use nalgebra::{distance, Point3, Rotation3, Vector3};
+use rand::rngs::StdRng;
+use rand::Rng;
+use shipyard::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Time in milliseconds a monster waits at a patrol point before it walks to the next one.
+const MIN_PATROL_WAIT: u64 = 2000;
+const MAX_PATROL_WAIT: u64 = 6000;
+
+/// Time a monster waits after using a skill before it uses the next one.
+const ATTACK_DELAY: Duration = Duration::from_secs(1);
+
+/// Threat an user gets for coming too close to an aggressive monster.
+const PROXIMITY_THREAT: i64 = 1;
+
+/// Monsters stop a bit inside the range of their skills when they chase a target, so that small
+/// movements of the target don't interrupt the fight.
+const CHASE_RANGE_FACTOR: f32 = 0.8;
+
+/// HP changes that are caused by skills of monsters or by the server itself.
+const CHANGE_BY_SKILL: i32 = 1;
+const CHANGE_BY_SYSTEM: i32 = 0;
+
+// TODO investigate the move types of the NPC location packet.
+const NPC_MOVE_TYPE: i32 = 0;
+
+/// Changes of the monsters that are shown to the users once all monsters acted.
+enum AiEvent {
+    Moved {
+        npc_id: EntityId,
+        point: Point3<f32>,
+        rotation: Rotation3<f32>,
+        speed: i16,
+        destination: Point3<f32>,
+    },
+    HealthChanged {
+        source_id: EntityId,
+        target_id: EntityId,
+        health: Health,
+        diff: i64,
+        change_type: i32,
+    },
+    Died {
+        target_id: EntityId,
+        point: Point3<f32>,
+    },
+}
+
+/// Runs the AI of the monsters of a local world. Idle monsters walk along the patrol points of
+/// their spawn. Monsters fight the user with the highest threat in their aggro table and return
+/// to their spawn once they lost all targets or went further away from it than their leash range.
+pub fn npc_ai_system(
+    connections: View<LocalConnection>,
+    mut user_spawns: ViewMut<LocalUserSpawn>,
+    visibilities: View<Visibility>,
+    stats: View<UserStats>,
+    (npcs, npc_spawns, mut ais): (View<Npc>, View<NpcSpawn>, ViewMut<Ai>),
+    mut locations: ViewMut<Location>,
+    mut healths: ViewMut<Health>,
+    (tick, mut rng): (UniqueView<Tick>, UniqueViewMut<AiRng>),
+) {
+    let mut events = Vec::new();
+
+    let monster_ids: Vec<EntityId> = (&npcs, &ais)
+        .iter()
+        .with_id()
+        .filter(|(_id, (npc, _ai))| npc.is_alive)
+        .map(|(id, _)| id)
+        .collect();
+
+    for npc_id in monster_ids {
+        let spawn = match npcs
+            .try_get(npc_id)
+            .and_then(|npc| npc_spawns.try_get(npc.spawn_id))
+        {
+            Ok(spawn) => spawn,
+            Err(_) => continue,
+        };
+        let ai = match (&mut ais).try_get(npc_id) {
+            Ok(ai) => ai,
+            Err(_) => continue,
+        };
+
+        update_aggro(npc_id, &spawn.template, ai, &user_spawns, &locations);
+        match ai.state {
+            AiState::Idle | AiState::Patrol => patrol(
+                npc_id,
+                spawn,
+                ai,
+                &mut locations,
+                &tick,
+                &mut rng.0,
+                &mut events,
+            ),
+            AiState::Combat => fight(
+                npc_id,
+                spawn,
+                ai,
+                &mut user_spawns,
+                &stats,
+                &mut locations,
+                &mut healths,
+                &tick,
+                &mut rng.0,
+                &mut events,
+            ),
+            AiState::Return => return_to_spawn(
+                npc_id,
+                spawn,
+                ai,
+                &mut locations,
+                &mut healths,
+                &tick,
+                &mut events,
+            ),
+        }
+    }
+
+    send_events(events, &connections, &user_spawns, &visibilities);
+}
+
+/// Adds threat of an user to the aggro table of a monster. Monsters that return to their spawn
+/// ignore all threat.
+pub fn add_threat(ai: &mut Ai, user_id: EntityId, threat: i64) {
+    if ai.state == AiState::Return {
+        return;
+    }
+    match ai.aggro.iter_mut().find(|(id, _threat)| *id == user_id) {
+        Some((_id, current)) => *current += threat,
+        None => ai.aggro.push((user_id, threat)),
+    }
+}
+
+/// Forgets the users that died or left the world. Aggressive monsters that don't fight get
+/// threat on the nearest user inside their aggro range. Monsters with threat start to fight.
+fn update_aggro(
+    npc_id: EntityId,
+    template: &NpcTemplate,
+    ai: &mut Ai,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    locations: &ViewMut<Location>,
+) {
+    ai.aggro.retain(|(user_id, _threat)| {
+        user_spawns.try_get(*user_id).map_or(false, |spawn| {
+            spawn.status == UserSpawnStatus::Spawned && spawn.is_alive
+        })
+    });
+
+    if ai.state != AiState::Idle && ai.state != AiState::Patrol {
+        return;
+    }
+
+    if ai.aggro.is_empty() && template.aggro_range > 0.0 {
+        let point = match locations.try_get(npc_id) {
+            Ok(location) => location.point,
+            Err(_) => return,
+        };
+        let nearest = (user_spawns, locations)
+            .iter()
+            .with_id()
+            .filter(|(_id, (spawn, _location))| {
+                spawn.status == UserSpawnStatus::Spawned && spawn.is_alive
+            })
+            .map(|(id, (_spawn, location))| (id, distance(&point, &location.point)))
+            .filter(|(_id, user_distance)| *user_distance <= template.aggro_range)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        if let Some((user_id, _distance)) = nearest {
+            debug!("User {:?} came too close to NPC {:?}", user_id, npc_id);
+            ai.aggro.push((user_id, PROXIMITY_THREAT));
+        }
+    }
+
+    if !ai.aggro.is_empty() {
+        ai.state = AiState::Combat;
+    }
+}
+
+/// Lets a monster walk from patrol point to patrol point. The monster waits a random time at
+/// every patrol point.
+fn patrol(
+    npc_id: EntityId,
+    spawn: &NpcSpawn,
+    ai: &mut Ai,
+    locations: &mut ViewMut<Location>,
+    tick: &Tick,
+    rng: &mut StdRng,
+    events: &mut Vec<AiEvent>,
+) {
+    let patrol_points = &spawn.spawn.patrol_points;
+    if patrol_points.is_empty() {
+        return;
+    }
+    if ai.state == AiState::Idle {
+        if tick.time < ai.next_action_at {
+            return;
+        }
+        ai.state = AiState::Patrol;
+    }
+
+    let destination = patrol_points[ai.patrol_index % patrol_points.len()];
+    if move_npc(
+        npc_id,
+        ai,
+        locations,
+        destination,
+        0.0,
+        spawn.template.walk_speed,
+        tick.delta,
+        events,
+    ) {
+        ai.patrol_index = (ai.patrol_index + 1) % patrol_points.len();
+        ai.state = AiState::Idle;
+        ai.next_action_at =
+            tick.time + Duration::from_millis(rng.gen_range(MIN_PATROL_WAIT, MAX_PATROL_WAIT + 1));
+    }
+}
+
+/// Lets a monster fight the user with the highest threat. The monster chases it's target until
+/// one of it's skills is in range.
+fn fight(
+    npc_id: EntityId,
+    spawn: &NpcSpawn,
+    ai: &mut Ai,
+    user_spawns: &mut ViewMut<LocalUserSpawn>,
+    stats: &View<UserStats>,
+    locations: &mut ViewMut<Location>,
+    healths: &mut ViewMut<Health>,
+    tick: &Tick,
+    rng: &mut StdRng,
+    events: &mut Vec<AiEvent>,
+) {
+    let template = &spawn.template;
+    let target_id = ai
+        .aggro
+        .iter()
+        .max_by_key(|(_id, threat)| *threat)
+        .map(|(id, _threat)| *id);
+    let point = match locations.try_get(npc_id) {
+        Ok(location) => location.point,
+        Err(_) => return,
+    };
+    let target = target_id.and_then(|target_id| {
+        locations
+            .try_get(target_id)
+            .ok()
+            .map(|location| (target_id, location.point))
+    });
+    let (target_id, target_point) = match target {
+        Some(target) => target,
+        None => {
+            debug!("NPC {:?} lost all targets", npc_id);
+            leave_fight(ai);
+            return;
+        }
+    };
+
+    if template.leash_range > 0.0 && distance(&spawn.spawn.point, &point) > template.leash_range {
+        debug!("NPC {:?} went too far away from it's spawn", npc_id);
+        leave_fight(ai);
+        return;
+    }
+
+    let target_distance = distance(&point, &target_point);
+    let attack_range = template
+        .skills
+        .iter()
+        .map(|skill| skill.range)
+        .fold(0.0, f32::max);
+    if target_distance > attack_range {
+        move_npc(
+            npc_id,
+            ai,
+            locations,
+            target_point,
+            attack_range * CHASE_RANGE_FACTOR,
+            template.run_speed,
+            tick.delta,
+            events,
+        );
+        return;
+    }
+    ai.destination = None;
+
+    let now = tick.time;
+    ai.cooldowns.retain(|_skill_id, ready_at| *ready_at > now);
+    if now < ai.next_action_at {
+        return;
+    }
+    let skill = match choose_skill(&template.skills, target_distance, &ai.cooldowns, now, rng) {
+        Some(skill) => skill,
+        None => return,
+    };
+    ai.next_action_at = now + ATTACK_DELAY;
+    if skill.cooldown > Duration::from_secs(0) {
+        ai.cooldowns.insert(skill.id, now + skill.cooldown);
+    }
+    if let Ok(location) = locations.try_get(npc_id) {
+        location.rotation = face(target_point - point);
+    }
+
+    let defence = stats
+        .try_get(target_id)
+        .map_or(0, |user_stats| user_stats.total.defence);
+    let damage = calculate_damage(skill.damage, template.attack, defence);
+    let health = match healths.try_get(target_id) {
+        Ok(health) => health,
+        Err(_) => return,
+    };
+    health.hp = (health.hp - damage).max(0);
+    debug!(
+        "NPC {:?} hit {:?} with skill {} for {} damage",
+        npc_id, target_id, skill.id, damage
+    );
+    events.push(AiEvent::HealthChanged {
+        source_id: npc_id,
+        target_id,
+        health: *health,
+        diff: -damage,
+        change_type: CHANGE_BY_SKILL,
+    });
+
+    if health.hp == 0 {
+        if let Ok(user_spawn) = user_spawns.try_get(target_id) {
+            user_spawn.is_alive = false;
+        }
+        ai.aggro.retain(|(id, _threat)| *id != target_id);
+        events.push(AiEvent::Died {
+            target_id,
+            point: target_point,
+        });
+    }
+}
+
+fn leave_fight(ai: &mut Ai) {
+    ai.state = AiState::Return;
+    ai.aggro.clear();
+}
+
+/// Lets a monster run back to it's spawn. Monsters are fully healed once they arrived.
+fn return_to_spawn(
+    npc_id: EntityId,
+    spawn: &NpcSpawn,
+    ai: &mut Ai,
+    locations: &mut ViewMut<Location>,
+    healths: &mut ViewMut<Health>,
+    tick: &Tick,
+    events: &mut Vec<AiEvent>,
+) {
+    if !move_npc(
+        npc_id,
+        ai,
+        locations,
+        spawn.spawn.point,
+        0.0,
+        spawn.template.run_speed,
+        tick.delta,
+        events,
+    ) {
+        return;
+    }
+
+    if let Ok(location) = locations.try_get(npc_id) {
+        location.rotation = spawn.spawn.rotation;
+    }
+    ai.state = AiState::Idle;
+    ai.next_action_at = tick.time;
+    ai.cooldowns.clear();
+
+    if let Ok(health) = healths.try_get(npc_id) {
+        if health.hp < health.max_hp {
+            let diff = health.max_hp - health.hp;
+            health.hp = health.max_hp;
+            events.push(AiEvent::HealthChanged {
+                source_id: npc_id,
+                target_id: npc_id,
+                health: *health,
+                diff,
+                change_type: CHANGE_BY_SYSTEM,
+            });
+        }
+    }
+}
+
+/// Moves a NPC with the given speed towards the destination until it's inside the given distance
+/// to it. New destinations are shown to the users. Returns true once the NPC arrived.
+fn move_npc(
+    npc_id: EntityId,
+    ai: &mut Ai,
+    locations: &mut ViewMut<Location>,
+    destination: Point3<f32>,
+    stop_distance: f32,
+    speed: i16,
+    delta: Duration,
+    events: &mut Vec<AiEvent>,
+) -> bool {
+    let location = match locations.try_get(npc_id) {
+        Ok(location) => location,
+        Err(_) => return true,
+    };
+
+    let direction = destination - location.point;
+    let remaining = direction.norm() - stop_distance;
+    if remaining <= 0.0 {
+        ai.destination = None;
+        return true;
+    }
+    let direction = direction.normalize();
+    location.rotation = face(direction);
+
+    if ai.destination != Some(destination) {
+        ai.destination = Some(destination);
+        events.push(AiEvent::Moved {
+            npc_id,
+            point: location.point,
+            rotation: location.rotation,
+            speed,
+            destination: location.point + direction * remaining,
+        });
+    }
+
+    let step = f32::from(speed) * delta.as_secs_f32();
+    if step >= remaining {
+        location.point += direction * remaining;
+        ai.destination = None;
+        true
+    } else {
+        location.point += direction * step;
+        false
+    }
+}
+
+/// Returns the rotation around the z axis that faces the given direction.
+fn face(direction: Vector3<f32>) -> Rotation3<f32> {
+    Rotation3::from_axis_angle(&Vector3::z_axis(), direction.y.atan2(direction.x))
+}
+
+/// Chooses a random skill out of the skills that are in range and not on cooldown. Skills with a
+/// higher weight are chosen more often.
+fn choose_skill<'a>(
+    skills: &'a [NpcSkill],
+    target_distance: f32,
+    cooldowns: &HashMap<i32, Instant>,
+    now: Instant,
+    rng: &mut StdRng,
+) -> Option<&'a NpcSkill> {
+    let usable: Vec<&NpcSkill> = skills
+        .iter()
+        .filter(|skill| {
+            skill.weight > 0
+                && skill.range >= target_distance
+                && cooldowns
+                    .get(&skill.id)
+                    .map_or(true, |ready_at| *ready_at <= now)
+        })
+        .collect();
+    let total_weight: u32 = usable.iter().map(|skill| skill.weight).sum();
+    if total_weight == 0 {
+        return None;
+    }
+
+    let mut roll = rng.gen_range(0, total_weight);
+    for skill in usable {
+        if roll < skill.weight {
+            return Some(skill);
+        }
+        roll -= skill.weight;
+    }
+    None
+}
+
+/// Shows the changes of the monsters to all users that can see the affected entity.
+fn send_events(
+    events: Vec<AiEvent>,
+    connections: &View<LocalConnection>,
+    user_spawns: &ViewMut<LocalUserSpawn>,
+    visibilities: &View<Visibility>,
+) {
+    for event in events {
+        match event {
+            AiEvent::Moved {
+                npc_id,
+                point,
+                rotation,
+                speed,
+                destination,
+            } => {
+                send_to_observers(
+                    npc_id,
+                    connections,
+                    user_spawns,
+                    visibilities,
+                    |connection_global_world_id, connection_local_world_id| {
+                        Box::new(ResponseNpcLocation {
+                            connection_global_world_id,
+                            connection_local_world_id,
+                            packet: SNpcLocation {
+                                npc_id,
+                                location: point.into(),
+                                rotation: Angle::from(rotation),
+                                speed,
+                                destination: destination.into(),
+                                move_type: NPC_MOVE_TYPE,
+                            },
+                        })
+                    },
+                );
+            }
+            AiEvent::HealthChanged {
+                source_id,
+                target_id,
+                health,
+                diff,
+                change_type,
+            } => {
+                send_to_observers(
+                    target_id,
+                    connections,
+                    user_spawns,
+                    visibilities,
+                    |connection_global_world_id, connection_local_world_id| {
+                        Box::new(ResponseCreatureChangeHp {
+                            connection_global_world_id,
+                            connection_local_world_id,
+                            packet: SCreatureChangeHp {
+                                current_hp: health.hp,
+                                max_hp: health.max_hp,
+                                diff,
+                                change_type,
+                                target_id,
+                                source_id,
+                                crit: false,
+                            },
+                        })
+                    },
+                );
+            }
+            AiEvent::Died { target_id, point } => {
+                send_to_observers(
+                    target_id,
+                    connections,
+                    user_spawns,
+                    visibilities,
+                    |connection_global_world_id, connection_local_world_id| {
+                        Box::new(ResponseCreatureLife {
+                            connection_global_world_id,
+                            connection_local_world_id,
+                            packet: SCreatureLife {
+                                target_id,
+                                location: point.into(),
+                                is_alive: false,
+                            },
+                        })
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::message::{EcsMessage, Message};
+    use crate::ecs::resource::NpcSpawnTemplate;
+    use crate::model::BASE_STATS;
+    use crate::protocol::serde::from_vec;
+    use crate::Result;
+    use async_std::sync::{channel, Receiver};
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    const BITE: i32 = 1;
+    const CLAW: i32 = 2;
+    const ROAR: i32 = 3;
+
+    struct TestUser {
+        connection_local_world_id: EntityId,
+        rx: Receiver<EcsMessage>,
+    }
+
+    fn get_template() -> NpcTemplate {
+        NpcTemplate {
+            hunting_zone_id: 13,
+            id: 1001,
+            level: 20,
+            max_hp: 1000,
+            walk_speed: 50,
+            run_speed: 100,
+            is_villager: false,
+            attack: 10,
+            defence: 10,
+            aggro_range: 100.0,
+            leash_range: 1000.0,
+            exp: 500,
+            is_boss: false,
+            skills: vec![NpcSkill {
+                id: BITE,
+                damage: 100,
+                range: 50.0,
+                cooldown: Duration::from_secs(0),
+                weight: 1,
+            }],
+        }
+    }
+
+    fn setup() -> World {
+        let world = World::new();
+        world.add_unique(AiRng(StdRng::seed_from_u64(42)));
+        world.add_unique(Tick {
+            count: 0,
+            delta: Duration::from_millis(100),
+            time: Instant::now(),
+        });
+        world
+    }
+
+    /// Spawns a monster at the origin.
+    fn add_monster(
+        world: &World,
+        template: NpcTemplate,
+        patrol_points: Vec<Point3<f32>>,
+    ) -> EntityId {
+        world.run(
+            |mut entities: EntitiesViewMut,
+             mut npc_spawns: ViewMut<NpcSpawn>,
+             mut npcs: ViewMut<Npc>,
+             mut locations: ViewMut<Location>,
+             mut healths: ViewMut<Health>,
+             mut ais: ViewMut<Ai>,
+             tick: UniqueView<Tick>| {
+                let spawn = NpcSpawnTemplate {
+                    hunting_zone_id: template.hunting_zone_id,
+                    template_id: template.id,
+                    point: Point3::new(0.0, 0.0, 0.0),
+                    rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+                    respawn_time: Duration::from_secs(30),
+                    patrol_points,
+                };
+                let spawn_id = entities.add_entity(
+                    &mut npc_spawns,
+                    NpcSpawn {
+                        spawn,
+                        template: template.clone(),
+                        npc_id: None,
+                        respawns_at: None,
+                    },
+                );
+                let npc_id = entities.add_entity(
+                    (&mut npcs, &mut locations, &mut healths, &mut ais),
+                    (
+                        Npc {
+                            hunting_zone_id: template.hunting_zone_id,
+                            template_id: template.id,
+                            spawn_id,
+                            walk_speed: template.walk_speed,
+                            run_speed: template.run_speed,
+                            is_villager: template.is_villager,
+                            is_alive: true,
+                        },
+                        Location {
+                            point: Point3::new(0.0, 0.0, 0.0),
+                            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+                        },
+                        Health {
+                            hp: template.max_hp,
+                            max_hp: template.max_hp,
+                        },
+                        Ai::new(tick.time),
+                    ),
+                );
+                (&mut npc_spawns).try_get(spawn_id).unwrap().npc_id = Some(npc_id);
+                npc_id
+            },
+        )
+    }
+
+    /// Spawns an user at the given x coordinate that can see the given NPC.
+    fn add_user(world: &World, num: u8, x: f32, npc_id: EntityId) -> Result<TestUser> {
+        let connection_global_world_id =
+            from_vec::<EntityId>(vec![num, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+        let (tx_channel, rx_channel) = channel(1024);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut locations: ViewMut<Location>,
+             mut visibilities: ViewMut<Visibility>,
+             mut stats: ViewMut<UserStats>,
+             mut healths: ViewMut<Health>| {
+                let mut visible_entities = HashSet::new();
+                visible_entities.insert(npc_id);
+                entities.add_entity(
+                    (
+                        &mut connections,
+                        &mut user_spawns,
+                        &mut locations,
+                        &mut visibilities,
+                        &mut stats,
+                        &mut healths,
+                    ),
+                    (
+                        LocalConnection {
+                            channel: tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id: i32::from(num),
+                            account_id: i64::from(num),
+                            status: UserSpawnStatus::Spawned,
+                            zone_id: 5,
+                            connection_global_world_id,
+                            is_alive: true,
+                        },
+                        Location {
+                            point: Point3::new(x, 0.0, 0.0),
+                            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+                        },
+                        Visibility {
+                            range: 100,
+                            visible_entities,
+                        },
+                        UserStats {
+                            base: BASE_STATS,
+                            total: BASE_STATS,
+                        },
+                        Health {
+                            hp: 200,
+                            max_hp: 200,
+                        },
+                    ),
+                )
+            },
+        );
+
+        Ok(TestUser {
+            connection_local_world_id,
+            rx: rx_channel,
+        })
+    }
+
+    /// Runs the AI for a tick and advances the time by the delta of the tick.
+    fn run(world: &World) {
+        world.run(npc_ai_system);
+        world.run(|mut tick: UniqueViewMut<Tick>| {
+            let delta = tick.delta;
+            tick.time += delta;
+        });
+    }
+
+    fn received(rx: &Receiver<EcsMessage>) -> Vec<EcsMessage> {
+        let mut messages = Vec::new();
+        while let Ok(message) = rx.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn find_packet<T, F>(messages: &[EcsMessage], f: F) -> Option<T>
+    where
+        F: Fn(&Message) -> Option<T>,
+    {
+        messages.iter().find_map(|message| f(&**message))
+    }
+
+    fn get_ai(world: &World, npc_id: EntityId) -> Ai {
+        world.run(|ais: View<Ai>| ais.try_get(npc_id).unwrap().clone())
+    }
+
+    fn get_point(world: &World, entity: EntityId) -> Point3<f32> {
+        world.run(|locations: View<Location>| locations.try_get(entity).unwrap().point)
+    }
+
+    fn get_health(world: &World, entity: EntityId) -> Health {
+        world.run(|healths: View<Health>| *healths.try_get(entity).unwrap())
+    }
+
+    fn threaten(world: &World, npc_id: EntityId, user: &TestUser, threat: i64) {
+        world.run(|mut ais: ViewMut<Ai>| {
+            add_threat(
+                (&mut ais).try_get(npc_id).unwrap(),
+                user.connection_local_world_id,
+                threat,
+            );
+        });
+    }
+
+    #[test]
+    fn test_aggro_and_attack() -> Result<()> {
+        let world = setup();
+        let npc_id = add_monster(&world, get_template(), vec![]);
+        let user = add_user(&world, 1, 30.0, npc_id)?;
+        let bystander = add_user(&world, 2, 500.0, npc_id)?;
+
+        // The user came too close and is attacked right away
+        run(&world);
+        let ai = get_ai(&world, npc_id);
+        assert_eq!(ai.state, AiState::Combat);
+        assert_eq!(
+            ai.aggro,
+            vec![(user.connection_local_world_id, PROXIMITY_THREAT)]
+        );
+        let damage = calculate_damage(100, 10, BASE_STATS.defence);
+        assert_eq!(
+            get_health(&world, user.connection_local_world_id).hp,
+            200 - damage
+        );
+
+        let messages = received(&user.rx);
+        let hp = find_packet(&messages, |message| match message {
+            Message::ResponseCreatureChangeHp { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(hp.source_id, npc_id);
+        assert_eq!(hp.target_id, user.connection_local_world_id);
+        assert_eq!(hp.diff, -damage);
+        // The bystander is out of the aggro range and can't see the user
+        assert!(received(&bystander.rx).is_empty());
+        assert_eq!(
+            get_health(&world, bystander.connection_local_world_id).hp,
+            200
+        );
+
+        // The next attack happens after the attack delay
+        run(&world);
+        assert_eq!(
+            get_health(&world, user.connection_local_world_id).hp,
+            200 - damage
+        );
+        for _ in 0..10 {
+            run(&world);
+        }
+        assert_eq!(
+            get_health(&world, user.connection_local_world_id).hp,
+            200 - 2 * damage
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_passive_monster_fights_back() -> Result<()> {
+        let world = setup();
+        let npc_id = add_monster(
+            &world,
+            NpcTemplate {
+                aggro_range: 0.0,
+                ..get_template()
+            },
+            vec![],
+        );
+        let user = add_user(&world, 1, 30.0, npc_id)?;
+
+        run(&world);
+        assert_eq!(get_ai(&world, npc_id).state, AiState::Idle);
+        assert_eq!(get_health(&world, user.connection_local_world_id).hp, 200);
+
+        threaten(&world, npc_id, &user, 50);
+        run(&world);
+        assert_eq!(get_ai(&world, npc_id).state, AiState::Combat);
+        assert!(get_health(&world, user.connection_local_world_id).hp < 200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_highest_threat_is_attacked() -> Result<()> {
+        let world = setup();
+        let npc_id = add_monster(
+            &world,
+            NpcTemplate {
+                aggro_range: 0.0,
+                ..get_template()
+            },
+            vec![],
+        );
+        let user = add_user(&world, 1, 30.0, npc_id)?;
+        let healer = add_user(&world, 2, 30.0, npc_id)?;
+
+        threaten(&world, npc_id, &user, 50);
+        threaten(&world, npc_id, &healer, 30);
+        threaten(&world, npc_id, &healer, 30);
+        run(&world);
+        assert_eq!(get_health(&world, user.connection_local_world_id).hp, 200);
+        assert!(get_health(&world, healer.connection_local_world_id).hp < 200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chase_target() -> Result<()> {
+        let world = setup();
+        let npc_id = add_monster(&world, get_template(), vec![]);
+        let user = add_user(&world, 1, 200.0, npc_id)?;
+        threaten(&world, npc_id, &user, 10);
+
+        // The monster runs 10 units per tick until the target is in range of it's skills
+        run(&world);
+        assert_eq!(get_point(&world, npc_id), Point3::new(10.0, 0.0, 0.0));
+        let messages = received(&user.rx);
+        let location = find_packet(&messages, |message| match message {
+            Message::ResponseNpcLocation { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(location.npc_id, npc_id);
+        assert_eq!(location.speed, 100);
+        assert_eq!(location.destination.x, 160.0);
+
+        // The destination is only shown again once it changes
+        run(&world);
+        assert!(received(&user.rx).is_empty());
+
+        for _ in 0..20 {
+            run(&world);
+        }
+        assert_eq!(get_point(&world, npc_id), Point3::new(160.0, 0.0, 0.0));
+        assert!(get_health(&world, user.connection_local_world_id).hp < 200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_leash_and_return() -> Result<()> {
+        let world = setup();
+        let npc_id = add_monster(
+            &world,
+            NpcTemplate {
+                leash_range: 100.0,
+                ..get_template()
+            },
+            vec![],
+        );
+        let user = add_user(&world, 1, 500.0, npc_id)?;
+        threaten(&world, npc_id, &user, 10);
+        world.run(|mut healths: ViewMut<Health>| {
+            (&mut healths).try_get(npc_id).unwrap().hp = 10;
+        });
+
+        // The monster gives up the chase once it's too far away from it's spawn
+        for _ in 0..12 {
+            run(&world);
+        }
+        let ai = get_ai(&world, npc_id);
+        assert_eq!(ai.state, AiState::Return);
+        assert!(ai.aggro.is_empty());
+        assert_eq!(get_point(&world, npc_id), Point3::new(110.0, 0.0, 0.0));
+
+        // Threat is ignored while it returns
+        threaten(&world, npc_id, &user, 10);
+        assert!(get_ai(&world, npc_id).aggro.is_empty());
+
+        for _ in 0..11 {
+            run(&world);
+        }
+        assert_eq!(get_ai(&world, npc_id).state, AiState::Idle);
+        assert_eq!(get_point(&world, npc_id), Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            get_health(&world, npc_id),
+            Health {
+                hp: 1000,
+                max_hp: 1000
+            }
+        );
+        assert_eq!(get_health(&world, user.connection_local_world_id).hp, 200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patrol() {
+        let world = setup();
+        let npc_id = add_monster(
+            &world,
+            get_template(),
+            vec![Point3::new(50.0, 0.0, 0.0), Point3::new(50.0, 50.0, 0.0)],
+        );
+
+        // The monster walks 5 units per tick to the first patrol point
+        run(&world);
+        assert_eq!(get_ai(&world, npc_id).state, AiState::Patrol);
+        assert_eq!(get_point(&world, npc_id), Point3::new(5.0, 0.0, 0.0));
+        for _ in 0..9 {
+            run(&world);
+        }
+        assert_eq!(get_point(&world, npc_id), Point3::new(50.0, 0.0, 0.0));
+
+        // And waits there for a while
+        let ai = get_ai(&world, npc_id);
+        assert_eq!(ai.state, AiState::Idle);
+        assert_eq!(ai.patrol_index, 1);
+        let now = world.run(|tick: UniqueView<Tick>| tick.time);
+        assert!(ai.next_action_at >= now + Duration::from_millis(MIN_PATROL_WAIT - 100));
+        assert!(ai.next_action_at <= now + Duration::from_millis(MAX_PATROL_WAIT));
+        run(&world);
+        assert_eq!(get_point(&world, npc_id), Point3::new(50.0, 0.0, 0.0));
+
+        // Then walks to the next patrol point
+        world.run(|mut tick: UniqueViewMut<Tick>| tick.time = ai.next_action_at);
+        run(&world);
+        assert_eq!(get_ai(&world, npc_id).state, AiState::Patrol);
+        assert_eq!(get_point(&world, npc_id), Point3::new(50.0, 5.0, 0.0));
+        for _ in 0..9 {
+            run(&world);
+        }
+        assert_eq!(get_point(&world, npc_id), Point3::new(50.0, 50.0, 0.0));
+        assert_eq!(get_ai(&world, npc_id).patrol_index, 0);
+    }
+
+    #[test]
+    fn test_killed_target() -> Result<()> {
+        let world = setup();
+        let npc_id = add_monster(
+            &world,
+            NpcTemplate {
+                skills: vec![NpcSkill {
+                    id: BITE,
+                    damage: 10000,
+                    range: 50.0,
+                    cooldown: Duration::from_secs(0),
+                    weight: 1,
+                }],
+                ..get_template()
+            },
+            vec![],
+        );
+        let user = add_user(&world, 1, 30.0, npc_id)?;
+
+        run(&world);
+        assert_eq!(get_health(&world, user.connection_local_world_id).hp, 0);
+        let is_alive = world.run(|user_spawns: View<LocalUserSpawn>| {
+            user_spawns
+                .try_get(user.connection_local_world_id)
+                .unwrap()
+                .is_alive
+        });
+        assert!(!is_alive);
+        let messages = received(&user.rx);
+        let life = find_packet(&messages, |message| match message {
+            Message::ResponseCreatureLife { packet, .. } => Some(packet.clone()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(life.target_id, user.connection_local_world_id);
+        assert!(!life.is_alive);
+
+        // Dead users don't attract the monster anymore
+        run(&world);
+        assert_eq!(get_ai(&world, npc_id).state, AiState::Return);
+        run(&world);
+        assert_eq!(get_ai(&world, npc_id).state, AiState::Idle);
+        run(&world);
+        assert_eq!(get_ai(&world, npc_id).state, AiState::Idle);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_choose_skill() {
+        let now = Instant::now();
+        let skills = vec![
+            NpcSkill {
+                id: BITE,
+                range: 50.0,
+                weight: 3,
+                ..NpcSkill::default()
+            },
+            NpcSkill {
+                id: CLAW,
+                range: 50.0,
+                weight: 1,
+                ..NpcSkill::default()
+            },
+            NpcSkill {
+                id: ROAR,
+                range: 500.0,
+                weight: 1,
+                ..NpcSkill::default()
+            },
+        ];
+        let mut cooldowns = HashMap::new();
+        cooldowns.insert(ROAR, now + Duration::from_secs(10));
+
+        let choose = |seed: u64| -> Vec<i32> {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..1000)
+                .map(|_| {
+                    choose_skill(&skills, 30.0, &cooldowns, now, &mut rng)
+                        .unwrap()
+                        .id
+                })
+                .collect()
+        };
+
+        // Skills on cooldown are never chosen, skills with a higher weight more often
+        let chosen = choose(42);
+        assert!(chosen.iter().all(|id| *id != ROAR));
+        let bites = chosen.iter().filter(|id| **id == BITE).count();
+        assert!(bites > 600 && bites < 900);
+        // The choice only depends on the seed
+        assert_eq!(chosen, choose(42));
+
+        // Skills out of range are never chosen
+        let mut rng = StdRng::seed_from_u64(42);
+        assert!(choose_skill(&skills, 100.0, &cooldowns, now, &mut rng).is_none());
+        assert_eq!(
+            choose_skill(&skills, 100.0, &HashMap::new(), now, &mut rng).map(|skill| skill.id),
+            Some(ROAR)
+        );
+    }
+}
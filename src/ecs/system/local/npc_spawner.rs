@@ -0,0 +1,289 @@
+use crate::ecs::component::{Ai, Health, Location, Npc, NpcSpawn};
+use crate::ecs::resource::{DeletionList, NpcRegistry, TerritoryRegistry, Tick};
+use shipyard::*;
+use tracing::{debug, error};
+
+/// Spawns the NPCs of the territories of a local world. Dead NPCs are removed and spawned again
+/// once the respawn time of their spawn passed. All NPCs except villagers get an AI.
+pub fn npc_spawner_system(
+    mut entities: EntitiesViewMut,
+    mut npc_spawns: ViewMut<NpcSpawn>,
+    mut npcs: ViewMut<Npc>,
+    mut locations: ViewMut<Location>,
+    mut healths: ViewMut<Health>,
+    mut ais: ViewMut<Ai>,
+    mut deletion_list: UniqueViewMut<DeletionList>,
+    tick: UniqueView<Tick>,
+) {
+    (&mut npc_spawns)
+        .iter()
+        .with_id()
+        .for_each(|(spawn_id, spawn)| {
+            if let Some(npc_id) = spawn.npc_id {
+                match (&npcs).try_get(npc_id) {
+                    Ok(npc) if npc.is_alive => return,
+                    Ok(_npc) => deletion_list.0.push(npc_id),
+                    Err(_) => {}
+                }
+                debug!(
+                    "NPC {:?} of spawn {:?} died, respawning in {:?}",
+                    npc_id, spawn_id, spawn.spawn.respawn_time
+                );
+                spawn.npc_id = None;
+                spawn.respawns_at = Some(tick.time + spawn.spawn.respawn_time);
+                return;
+            }
+
+            if spawn
+                .respawns_at
+                .map_or(true, |respawns_at| respawns_at <= tick.time)
+            {
+                let npc_id = entities.add_entity(
+                    (&mut npcs, &mut locations, &mut healths),
+                    (
+                        Npc {
+                            hunting_zone_id: spawn.template.hunting_zone_id,
+                            template_id: spawn.template.id,
+                            spawn_id,
+                            walk_speed: spawn.template.walk_speed,
+                            run_speed: spawn.template.run_speed,
+                            is_villager: spawn.template.is_villager,
+                            is_alive: true,
+                        },
+                        Location {
+                            point: spawn.spawn.point,
+                            rotation: spawn.spawn.rotation,
+                        },
+                        Health {
+                            hp: spawn.template.max_hp,
+                            max_hp: spawn.template.max_hp,
+                        },
+                    ),
+                );
+                if !spawn.template.is_villager {
+                    entities.add_component(&mut ais, Ai::new(tick.time), npc_id);
+                }
+                debug!("Spawned NPC {:?} of spawn {:?}", npc_id, spawn_id);
+                spawn.npc_id = Some(npc_id);
+                spawn.respawns_at = None;
+            }
+        });
+}
+
+/// Creates the NPC spawns of all territories of the given zone. The NPCs itself are spawned by the
+/// NPC spawner system. Returns the number of created spawns.
+pub fn load_territories(
+    zone_id: i32,
+    entities: &mut EntitiesViewMut,
+    npc_spawns: &mut ViewMut<NpcSpawn>,
+    npc_registry: &NpcRegistry,
+    territories: &TerritoryRegistry,
+) -> usize {
+    let mut count = 0;
+    for territory in territories.get(zone_id) {
+        for spawn in &territory.spawns {
+            let template = match npc_registry.get(spawn.hunting_zone_id, spawn.template_id) {
+                Some(template) => template,
+                None => {
+                    error!(
+                        "Can't find NPC {} of hunting zone {} in territory group {}",
+                        spawn.template_id, spawn.hunting_zone_id, territory.id
+                    );
+                    continue;
+                }
+            };
+
+            entities.add_entity(
+                &mut *npc_spawns,
+                NpcSpawn {
+                    spawn: spawn.clone(),
+                    template: template.clone(),
+                    npc_id: None,
+                    respawns_at: None,
+                },
+            );
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::{NpcSpawnTemplate, NpcTemplate, TerritoryGroup};
+    use crate::ecs::system::common::cleaner_system;
+    use nalgebra::{Point3, Rotation3, Vector3};
+    use std::time::{Duration, Instant};
+
+    fn get_npc_registry() -> NpcRegistry {
+        NpcRegistry::new(vec![
+            NpcTemplate {
+                hunting_zone_id: 13,
+                id: 1001,
+                level: 20,
+                max_hp: 5000,
+                walk_speed: 40,
+                run_speed: 120,
+                is_villager: false,
+                ..NpcTemplate::default()
+            },
+            NpcTemplate {
+                hunting_zone_id: 13,
+                id: 2001,
+                level: 1,
+                max_hp: 100,
+                walk_speed: 50,
+                run_speed: 150,
+                is_villager: true,
+                ..NpcTemplate::default()
+            },
+        ])
+    }
+
+    fn get_spawn(template_id: i32, x: f32) -> NpcSpawnTemplate {
+        NpcSpawnTemplate {
+            hunting_zone_id: 13,
+            template_id,
+            point: Point3::new(x, 0.0, 0.0),
+            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+            respawn_time: Duration::from_secs(30),
+            patrol_points: vec![],
+        }
+    }
+
+    fn setup() -> World {
+        let territories = TerritoryRegistry::new(vec![
+            TerritoryGroup {
+                id: 1,
+                zone_id: 5,
+                spawns: vec![get_spawn(1001, 0.0), get_spawn(2001, 100.0)],
+            },
+            TerritoryGroup {
+                id: 2,
+                zone_id: 5,
+                spawns: vec![get_spawn(3001, 200.0)],
+            },
+            TerritoryGroup {
+                id: 3,
+                zone_id: 9001,
+                spawns: vec![get_spawn(1001, 0.0)],
+            },
+        ]);
+
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(Tick {
+            count: 0,
+            delta: Duration::from_millis(33),
+            time: Instant::now(),
+        });
+
+        let count = world.run(
+            |mut entities: EntitiesViewMut, mut npc_spawns: ViewMut<NpcSpawn>| {
+                load_territories(
+                    5,
+                    &mut entities,
+                    &mut npc_spawns,
+                    &get_npc_registry(),
+                    &territories,
+                )
+            },
+        );
+        // The spawn of the unknown NPC is skipped
+        assert_eq!(count, 2);
+        world
+    }
+
+    fn run(world: &World) {
+        world.run(npc_spawner_system);
+        world.run(cleaner_system);
+    }
+
+    fn advance(world: &World, duration: Duration) {
+        world.run(|mut tick: UniqueViewMut<Tick>| tick.time += duration);
+        run(world);
+    }
+
+    fn get_npcs(world: &World) -> Vec<(EntityId, Npc)> {
+        let mut npcs = world.run(|npcs: View<Npc>| {
+            npcs.iter()
+                .with_id()
+                .map(|(id, npc)| (id, npc.clone()))
+                .collect::<Vec<(EntityId, Npc)>>()
+        });
+        npcs.sort_by_key(|(_id, npc)| npc.template_id);
+        npcs
+    }
+
+    fn kill(world: &World, npc_id: EntityId) {
+        world.run(|mut npcs: ViewMut<Npc>| {
+            (&mut npcs).try_get(npc_id).unwrap().is_alive = false;
+        });
+    }
+
+    #[test]
+    fn test_npcs_are_spawned() {
+        let world = setup();
+        run(&world);
+
+        let npcs = get_npcs(&world);
+        assert_eq!(npcs.len(), 2);
+        assert_eq!(npcs[0].1.template_id, 1001);
+        assert!(!npcs[0].1.is_villager);
+        assert_eq!(npcs[1].1.template_id, 2001);
+        assert!(npcs[1].1.is_villager);
+
+        world.run(
+            |locations: View<Location>, healths: View<Health>, npc_spawns: View<NpcSpawn>| {
+                let (monster_id, monster) = &npcs[0];
+                assert_eq!(
+                    locations.try_get(*monster_id).unwrap().point,
+                    Point3::new(0.0, 0.0, 0.0)
+                );
+                assert_eq!(
+                    *healths.try_get(*monster_id).unwrap(),
+                    Health {
+                        hp: 5000,
+                        max_hp: 5000
+                    }
+                );
+                let spawn = npc_spawns.try_get(monster.spawn_id).unwrap();
+                assert_eq!(spawn.npc_id, Some(*monster_id));
+                assert_eq!(spawn.template.level, 20);
+            },
+        );
+        world.run(|ais: View<Ai>| {
+            assert!(ais.try_get(npcs[0].0).is_ok());
+            assert!(ais.try_get(npcs[1].0).is_err());
+        });
+
+        // Living NPCs are not spawned again
+        run(&world);
+        assert_eq!(get_npcs(&world), npcs);
+    }
+
+    #[test]
+    fn test_dead_npcs_respawn() {
+        let world = setup();
+        run(&world);
+        let npcs = get_npcs(&world);
+        let (monster_id, _monster) = &npcs[0];
+
+        kill(&world, *monster_id);
+        run(&world);
+        let remaining = get_npcs(&world);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].1.template_id, 2001);
+
+        // The NPC is spawned again once the respawn time passed
+        advance(&world, Duration::from_secs(29));
+        assert_eq!(get_npcs(&world).len(), 1);
+        advance(&world, Duration::from_secs(1));
+        let respawned = get_npcs(&world);
+        assert_eq!(respawned.len(), 2);
+        assert_eq!(respawned[0].1.template_id, 1001);
+        assert!(respawned[0].1.is_alive);
+        assert_ne!(respawned[0].0, *monster_id);
+    }
+}
@@ -0,0 +1,1056 @@
+use crate::ecs::component::{
+    LocalConnection, LocalUserSpawn, ParcelDraft, ParcelDraftItem, UserInventory,
+};
+use crate::ecs::message::Message::{
+    ResponseRecvParcel, ResponseSendParcel, ResponseSetSendParcelItem, ResponseSetSendParcelMoney,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::system::local::{assemble_itemlist, send_message_to_connection};
+use crate::model::entity::{Inventory, Item, Parcel, ParcelItem};
+use crate::model::repository::{inventory, item, parcel, parcel_item, user};
+use crate::model::{MAX_PARCEL_ITEMS, PARCEL_EXPIRATION_DAYS};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use chrono::{Duration, Utc};
+use shipyard::*;
+use sqlx::PgPool;
+use tracing::{debug, error, info_span};
+
+/// Money an user needs to pay to send a parcel.
+const PARCEL_POSTAGE: i64 = 100;
+
+/// Sends the parcels the users write and moves the attachments of received parcels into their
+/// inventory. Attachments are moved between the inventory and a parcel inside one transaction,
+/// so that they are never duplicated or lost. Everything else about parcels is handled by the
+/// parcel manager of the global world.
+pub fn parcel_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<LocalConnection>,
+    user_spawns: View<LocalUserSpawn>,
+    mut inventories: ViewMut<UserInventory>,
+    mut drafts: ViewMut<ParcelDraft>,
+    entities: EntitiesView,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestSetSendParcelItem {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_set_send_parcel_item(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &inventories,
+                    &mut drafts,
+                    &entities,
+                ) {
+                    error!("Ignoring set send parcel item request: {:?}", e);
+                }
+            }
+            Message::RequestSetSendParcelMoney {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_set_send_parcel_money(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &inventories,
+                    &mut drafts,
+                    &entities,
+                ) {
+                    error!("Ignoring set send parcel money request: {:?}", e);
+                }
+            }
+            Message::RequestClearSendParcel {
+                connection_global_world_id,
+                connection_local_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                handle_clear_send_parcel(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &connections,
+                    &mut drafts,
+                    &entities,
+                );
+            }
+            Message::RequestCloseSendParcel {
+                connection_global_world_id,
+                connection_local_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                debug!("Message::RequestCloseSendParcel incoming");
+                drafts.delete(*connection_local_world_id);
+            }
+            Message::RequestSendParcel {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_send_parcel(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &mut inventories,
+                    &mut drafts,
+                    &pool,
+                ) {
+                    error!("Ignoring send parcel request: {:?}", e);
+                }
+            }
+            Message::RequestRecvParcel {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_recv_parcel(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &mut inventories,
+                    &pool,
+                ) {
+                    error!("Ignoring receive parcel request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_set_send_parcel_item(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CSetSendParcelItem,
+    connections: &View<LocalConnection>,
+    inventories: &ViewMut<UserInventory>,
+    drafts: &mut ViewMut<ParcelDraft>,
+    entities: &EntitiesView,
+) -> Result<()> {
+    debug!("Message::RequestSetSendParcelItem incoming");
+
+    let inventory = inventories
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    let draft_item = ParcelDraftItem {
+        db_id: packet.db_id,
+        inventory_slot: packet.inventory_slot,
+        amount: packet.amount,
+    };
+    check_draft_item(inventory, &draft_item)?;
+
+    let mut draft = get_draft(connection_local_world_id, drafts);
+    ensure!(
+        draft
+            .items
+            .iter()
+            .all(|item| item.inventory_slot != packet.inventory_slot),
+        "Item in slot {} is already attached",
+        packet.inventory_slot
+    );
+    ensure!(
+        draft.items.len() < MAX_PARCEL_ITEMS,
+        "Can't attach more than {} items",
+        MAX_PARCEL_ITEMS
+    );
+    draft.items.push(draft_item);
+
+    send_message_to_connection(
+        assemble_set_send_parcel_item(
+            connection_global_world_id,
+            connection_local_world_id,
+            &draft,
+            inventory,
+        ),
+        connections,
+    );
+    set_draft(connection_local_world_id, draft, drafts, entities);
+
+    Ok(())
+}
+
+fn handle_set_send_parcel_money(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CSetSendParcelMoney,
+    connections: &View<LocalConnection>,
+    inventories: &ViewMut<UserInventory>,
+    drafts: &mut ViewMut<ParcelDraft>,
+    entities: &EntitiesView,
+) -> Result<()> {
+    debug!("Message::RequestSetSendParcelMoney incoming");
+
+    let inventory = inventories
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    ensure!(
+        packet.money >= 0 && packet.money <= inventory.money,
+        "Can't attach {} money",
+        packet.money
+    );
+
+    let mut draft = get_draft(connection_local_world_id, drafts);
+    draft.money = packet.money;
+    set_draft(connection_local_world_id, draft, drafts, entities);
+
+    send_message_to_connection(
+        Box::new(ResponseSetSendParcelMoney {
+            connection_global_world_id,
+            connection_local_world_id,
+            packet: SSetSendParcelMoney {
+                money: packet.money,
+            },
+        }),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_clear_send_parcel(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    connections: &View<LocalConnection>,
+    drafts: &mut ViewMut<ParcelDraft>,
+    entities: &EntitiesView,
+) {
+    debug!("Message::RequestClearSendParcel incoming");
+
+    set_draft(
+        connection_local_world_id,
+        ParcelDraft::default(),
+        drafts,
+        entities,
+    );
+
+    send_message_to_connection(
+        Box::new(ResponseSetSendParcelItem {
+            connection_global_world_id,
+            connection_local_world_id,
+            packet: SSetSendParcelItem { items: Vec::new() },
+        }),
+        connections,
+    );
+    send_message_to_connection(
+        Box::new(ResponseSetSendParcelMoney {
+            connection_global_world_id,
+            connection_local_world_id,
+            packet: SSetSendParcelMoney { money: 0 },
+        }),
+        connections,
+    );
+}
+
+fn handle_send_parcel(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CSendParcel,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &mut ViewMut<UserInventory>,
+    drafts: &mut ViewMut<ParcelDraft>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestSendParcel incoming");
+
+    let (spawn, inventory) = (user_spawns, inventories)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+
+    // Parcels without attachments don't need a draft.
+    let draft = get_draft(connection_local_world_id, drafts);
+    let result = send_parcel(packet, spawn, inventory, &draft, pool);
+    if result.is_ok() {
+        drafts.delete(connection_local_world_id);
+        send_message_to_connection(
+            assemble_itemlist(
+                connection_global_world_id,
+                connection_local_world_id,
+                &inventory,
+                false,
+            ),
+            connections,
+        );
+    }
+
+    send_message_to_connection(
+        Box::new(ResponseSendParcel {
+            connection_global_world_id,
+            connection_local_world_id,
+            packet: SSendParcel {
+                success: result.is_ok(),
+            },
+        }),
+        connections,
+    );
+    result
+}
+
+/// Moves the attachments of the parcel draft out of the inventory and sends the parcel.
+fn send_parcel(
+    packet: &CSendParcel,
+    spawn: &LocalUserSpawn,
+    inventory: &mut UserInventory,
+    draft: &ParcelDraft,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    ensure!(!packet.title.is_empty(), "Parcels need a title");
+    ensure!(
+        draft.money + PARCEL_POSTAGE <= inventory.money,
+        "User {} can't pay the money of the parcel",
+        spawn.user_id
+    );
+    // The items could have been moved since they were attached.
+    for draft_item in draft.items.iter() {
+        check_draft_item(inventory, draft_item)?;
+    }
+    let money = inventory.money - draft.money - PARCEL_POSTAGE;
+
+    task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let sender = user::get_by_id(&mut conn, spawn.user_id)
+            .await
+            .context(format!("Can't find user {}", spawn.user_id))?;
+        let recipient = user::get_by_name(&mut conn, &packet.recipient)
+            .await
+            .context(format!("Can't find user {}", packet.recipient))?;
+        ensure!(
+            recipient.id != sender.id,
+            "User {} can't send a parcel to itself",
+            sender.id
+        );
+
+        let now = Utc::now();
+        let parcel = parcel::create(
+            &mut conn,
+            &Parcel {
+                id: -1,
+                sender_user_id: Some(sender.id),
+                sender_name: sender.name,
+                recipient_user_id: recipient.id,
+                title: packet.title.clone(),
+                message: packet.message.clone(),
+                money: draft.money,
+                is_read: false,
+                is_returned: false,
+                created_at: now,
+                expires_at: now + Duration::days(PARCEL_EXPIRATION_DAYS),
+            },
+        )
+        .await?;
+
+        for draft_item in draft.items.iter() {
+            let item = &inventory.items[&draft_item.inventory_slot];
+            let new_item = ParcelItem {
+                id: item.id,
+                parcel_id: parcel.id,
+                template_id: item.template_id,
+                amount: draft_item.amount,
+                created_at: item.created_at,
+            };
+            // Attaching the whole stack moves the item itself, so it keeps it's ID.
+            if draft_item.amount == item.amount {
+                item::delete(&mut conn, item.id).await?;
+                parcel_item::restore(&mut conn, &new_item).await?;
+            } else {
+                item::update(
+                    &mut conn,
+                    &Item {
+                        amount: item.amount - draft_item.amount,
+                        ..item.clone()
+                    },
+                )
+                .await?;
+                parcel_item::create(&mut conn, &new_item).await?;
+            }
+        }
+
+        inventory::update(
+            &mut conn,
+            &Inventory {
+                user_id: spawn.user_id,
+                size: inventory.size,
+                money,
+                equipment_preset: inventory.equipment_preset,
+            },
+        )
+        .await?;
+
+        conn.commit().await?;
+        debug!("User {} sent parcel {}", sender.id, parcel.id);
+        Ok::<(), anyhow::Error>(())
+    })?;
+
+    inventory.money = money;
+    for draft_item in draft.items.iter() {
+        let is_whole_stack =
+            inventory.items[&draft_item.inventory_slot].amount == draft_item.amount;
+        if is_whole_stack {
+            inventory.items.remove(&draft_item.inventory_slot);
+        } else if let Some(stack) = inventory.items.get_mut(&draft_item.inventory_slot) {
+            stack.amount -= draft_item.amount;
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_recv_parcel(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CRecvParcel,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &mut ViewMut<UserInventory>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestRecvParcel incoming");
+
+    let (spawn, inventory) = (user_spawns, inventories)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    let free_slots: Vec<i32> = (0..inventory.size)
+        .filter(|slot| !inventory.items.contains_key(slot))
+        .collect();
+
+    let result = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let mut parcel = parcel::get_by_id(&mut conn, packet.id)
+            .await
+            .context(format!("Can't find parcel {}", packet.id))?;
+        ensure!(
+            parcel.recipient_user_id == spawn.user_id,
+            "Parcel {} was not send to user {}",
+            parcel.id,
+            spawn.user_id
+        );
+        let parcel_items = parcel_item::list_by_parcel_id(&mut conn, parcel.id).await?;
+        ensure!(
+            parcel.money > 0 || !parcel_items.is_empty(),
+            "Parcel {} has no attachments",
+            parcel.id
+        );
+        ensure!(
+            parcel_items.len() <= free_slots.len(),
+            "User {} has not enough free inventory slots",
+            spawn.user_id
+        );
+        let money = inventory
+            .money
+            .checked_add(parcel.money)
+            .context("Money of the inventory would overflow")?;
+
+        let mut received_items = Vec::with_capacity(parcel_items.len());
+        for (attached_item, slot) in parcel_items.iter().zip(free_slots.iter()) {
+            parcel_item::delete(&mut conn, attached_item.id).await?;
+            received_items.push(
+                item::restore(
+                    &mut conn,
+                    &Item {
+                        id: attached_item.id,
+                        user_id: spawn.user_id,
+                        template_id: attached_item.template_id,
+                        slot: *slot,
+                        amount: attached_item.amount,
+                        created_at: attached_item.created_at,
+                    },
+                )
+                .await?,
+            );
+        }
+
+        if parcel.money > 0 {
+            inventory::update(
+                &mut conn,
+                &Inventory {
+                    user_id: spawn.user_id,
+                    size: inventory.size,
+                    money,
+                    equipment_preset: inventory.equipment_preset,
+                },
+            )
+            .await?;
+        }
+        parcel.money = 0;
+        parcel.is_read = true;
+        parcel::update(&mut conn, &parcel).await?;
+
+        conn.commit().await?;
+        Ok::<(i64, Vec<Item>), anyhow::Error>((money, received_items))
+    });
+
+    if let Ok((money, received_items)) = &result {
+        inventory.money = *money;
+        for item in received_items {
+            inventory.items.insert(item.slot, item.clone());
+        }
+        send_message_to_connection(
+            assemble_itemlist(
+                connection_global_world_id,
+                connection_local_world_id,
+                &inventory,
+                false,
+            ),
+            connections,
+        );
+    }
+
+    send_message_to_connection(
+        Box::new(ResponseRecvParcel {
+            connection_global_world_id,
+            connection_local_world_id,
+            packet: SRecvParcel {
+                id: packet.id,
+                success: result.is_ok(),
+            },
+        }),
+        connections,
+    );
+    result.map(|_| ())
+}
+
+/// Makes sure that the item that should be attached is inside the inventory.
+fn check_draft_item(inventory: &UserInventory, draft_item: &ParcelDraftItem) -> Result<()> {
+    let item = inventory
+        .items
+        .get(&draft_item.inventory_slot)
+        .context(format!(
+            "No item found in slot {}",
+            draft_item.inventory_slot
+        ))?;
+    ensure!(
+        item.id == draft_item.db_id,
+        "Item {} is not inside slot {}",
+        draft_item.db_id,
+        draft_item.inventory_slot
+    );
+    ensure!(
+        draft_item.amount > 0 && draft_item.amount <= item.amount,
+        "Can't attach {} of {} items",
+        draft_item.amount,
+        item.amount
+    );
+    Ok(())
+}
+
+fn get_draft(connection_local_world_id: EntityId, drafts: &ViewMut<ParcelDraft>) -> ParcelDraft {
+    drafts
+        .try_get(connection_local_world_id)
+        .map_or_else(|_| ParcelDraft::default(), |draft| draft.clone())
+}
+
+fn set_draft(
+    connection_local_world_id: EntityId,
+    draft: ParcelDraft,
+    drafts: &mut ViewMut<ParcelDraft>,
+    entities: &EntitiesView,
+) {
+    if let Ok(current_draft) = drafts.try_get(connection_local_world_id) {
+        *current_draft = draft;
+    } else {
+        entities.add_component(drafts, draft, connection_local_world_id);
+    }
+}
+
+fn assemble_set_send_parcel_item(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    draft: &ParcelDraft,
+    inventory: &UserInventory,
+) -> EcsMessage {
+    Box::new(ResponseSetSendParcelItem {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SSetSendParcelItem {
+            items: draft
+                .items
+                .iter()
+                .filter_map(|draft_item| {
+                    inventory.items.get(&draft_item.inventory_slot).map(|item| {
+                        SSetSendParcelItemItem {
+                            id: item.template_id,
+                            db_id: draft_item.db_id,
+                            inventory_slot: draft_item.inventory_slot,
+                            amount: draft_item.amount,
+                        }
+                    })
+                })
+                .collect(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
+    use crate::ecs::system::global::send_system_parcel;
+    use crate::ecs::system::local::tests::{add_user, TestUser};
+    use crate::model::tests::db_test;
+
+    const POTION: i32 = 8005;
+    const WEAPON: i32 = 10001;
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(pool);
+        world.add_unique(DeletionList(Vec::default()));
+        world
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(parcel_system);
+        world.run(cleaner_system);
+    }
+
+    fn set_item(world: &World, user: &TestUser, inventory_slot: i32, amount: i32) {
+        let db_id = world.run(|inventories: View<UserInventory>| {
+            let inventory = inventories.try_get(user.connection_local_world_id).unwrap();
+            inventory
+                .items
+                .get(&inventory_slot)
+                .map_or(0, |item| item.id)
+        });
+        run_message(
+            world,
+            Message::RequestSetSendParcelItem {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CSetSendParcelItem {
+                    db_id,
+                    inventory_slot,
+                    amount,
+                },
+            },
+        );
+    }
+
+    fn set_money(world: &World, user: &TestUser, money: i64) {
+        run_message(
+            world,
+            Message::RequestSetSendParcelMoney {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CSetSendParcelMoney { money },
+            },
+        );
+    }
+
+    fn send(world: &World, user: &TestUser, recipient: &str) {
+        run_message(
+            world,
+            Message::RequestSendParcel {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CSendParcel {
+                    recipient: recipient.to_string(),
+                    title: "Title".to_string(),
+                    message: "Message".to_string(),
+                },
+            },
+        );
+    }
+
+    fn recv(world: &World, user: &TestUser, id: i64) {
+        run_message(
+            world,
+            Message::RequestRecvParcel {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CRecvParcel { id },
+            },
+        );
+    }
+
+    fn list_parcels(pool: &PgPool, user: &TestUser) -> Result<Vec<(Parcel, Vec<ParcelItem>)>> {
+        task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let mut parcels = Vec::new();
+            for parcel in parcel::list_by_recipient_user_id(&mut conn, user.user.id).await? {
+                let items = parcel_item::list_by_parcel_id(&mut conn, parcel.id).await?;
+                parcels.push((parcel, items));
+            }
+            Ok(parcels)
+        })
+    }
+
+    fn get_inventory(world: &World, user: &TestUser) -> UserInventory {
+        world.run(|inventories: View<UserInventory>| {
+            inventories
+                .try_get(user.connection_local_world_id)
+                .unwrap()
+                .clone()
+        })
+    }
+
+    /// Checks that the inventory component matches the database and returns the money and the
+    /// items of the inventory as (template ID, slot, amount) ordered by their slot.
+    fn assert_persisted(
+        world: &World,
+        pool: &PgPool,
+        user: &TestUser,
+    ) -> Result<(i64, Vec<(i32, i32, i32)>)> {
+        let (db_inventory, db_items) = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let db_inventory = inventory::get_by_user_id(&mut conn, user.user.id).await?;
+            let db_items = item::list_by_user_id(&mut conn, user.user.id).await?;
+            Ok::<(Inventory, Vec<Item>), anyhow::Error>((db_inventory, db_items))
+        })?;
+
+        let inventory = get_inventory(world, user);
+        let mut items = inventory.items.values().cloned().collect::<Vec<Item>>();
+        items.sort_by_key(|item| item.slot);
+        assert_eq!(items, db_items);
+        assert_eq!(inventory.money, db_inventory.money);
+
+        Ok((
+            db_inventory.money,
+            db_items
+                .iter()
+                .map(|item| (item.template_id, item.slot, item.amount))
+                .collect(),
+        ))
+    }
+
+    fn assert_itemlist(user: &TestUser) -> Result<SItemlist> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseItemlist { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseItemlist message"),
+        }
+    }
+
+    fn assert_set_send_parcel_item(user: &TestUser) -> Result<SSetSendParcelItem> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseSetSendParcelItem { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseSetSendParcelItem message"),
+        }
+    }
+
+    fn assert_set_send_parcel_money(user: &TestUser, money: i64) -> Result<()> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseSetSendParcelMoney { packet, .. } => assert_eq!(packet.money, money),
+            _ => panic!("Message is not a ResponseSetSendParcelMoney message"),
+        }
+        Ok(())
+    }
+
+    fn assert_send_parcel(user: &TestUser, success: bool) -> Result<()> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseSendParcel { packet, .. } => assert_eq!(packet.success, success),
+            _ => panic!("Message is not a ResponseSendParcel message"),
+        }
+        Ok(())
+    }
+
+    fn assert_recv_parcel(user: &TestUser, id: i64, success: bool) -> Result<()> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseRecvParcel { packet, .. } => {
+                assert_eq!(packet.id, id);
+                assert_eq!(packet.success, success);
+            }
+            _ => panic!("Message is not a ResponseRecvParcel message"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_send_parcel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let sender = add_user(&world, &pool, 1, &[(POTION, 0, 20), (WEAPON, 1, 1)])?;
+            let recipient = add_user(&world, &pool, 2, &[])?;
+            let weapon_id = get_inventory(&world, &sender).items[&1].id;
+
+            set_item(&world, &sender, 0, 5);
+            assert_eq!(assert_set_send_parcel_item(&sender)?.items.len(), 1);
+            set_item(&world, &sender, 1, 1);
+            let packet = assert_set_send_parcel_item(&sender)?;
+            assert_eq!(packet.items.len(), 2);
+            assert_eq!(packet.items[1].id, WEAPON);
+            assert_eq!(packet.items[1].db_id, weapon_id);
+            set_money(&world, &sender, 300);
+            assert_set_send_parcel_money(&sender, 300)?;
+
+            send(&world, &sender, &recipient.user.name);
+            assert_eq!(assert_itemlist(&sender)?.items.len(), 1);
+            assert_send_parcel(&sender, true)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &sender)?,
+                (1000 - 300 - PARCEL_POSTAGE, vec![(POTION, 0, 15)])
+            );
+
+            let parcels = list_parcels(&pool, &recipient)?;
+            assert_eq!(parcels.len(), 1);
+            let (parcel, items) = &parcels[0];
+            assert_eq!(parcel.sender_user_id, Some(sender.user.id));
+            assert_eq!(parcel.sender_name, sender.user.name);
+            assert_eq!(parcel.title, "Title");
+            assert_eq!(parcel.money, 300);
+            assert_eq!(items.len(), 2);
+            assert_eq!((items[0].template_id, items[0].amount), (POTION, 5));
+
+            // Whole stacks keep their ID
+            assert_eq!(items[1].id, weapon_id);
+
+            // The draft is gone after sending
+            world.run(|drafts: View<ParcelDraft>| {
+                assert!(drafts.try_get(sender.connection_local_world_id).is_err());
+            });
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_send_parcel_without_attachments() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let sender = add_user(&world, &pool, 1, &[])?;
+            let recipient = add_user(&world, &pool, 2, &[])?;
+
+            send(&world, &sender, &recipient.user.name);
+            assert_itemlist(&sender)?;
+            assert_send_parcel(&sender, true)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &sender)?,
+                (1000 - PARCEL_POSTAGE, vec![])
+            );
+            assert_eq!(list_parcels(&pool, &recipient)?.len(), 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_send_parcel_invalid() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let sender = add_user(&world, &pool, 1, &[(POTION, 0, 20)])?;
+            let recipient = add_user(&world, &pool, 2, &[])?;
+
+            // Items can only be attached once and only up to their amount
+            set_item(&world, &sender, 0, 21);
+            set_item(&world, &sender, 3, 1);
+            assert!(sender.rx.is_empty());
+            set_item(&world, &sender, 0, 20);
+            assert_set_send_parcel_item(&sender)?;
+            set_item(&world, &sender, 0, 20);
+            assert!(sender.rx.is_empty());
+
+            // The money and the postage need to be paid
+            set_money(&world, &sender, 1001);
+            assert!(sender.rx.is_empty());
+            set_money(&world, &sender, 1000);
+            assert_set_send_parcel_money(&sender, 1000)?;
+            send(&world, &sender, &recipient.user.name);
+            assert_send_parcel(&sender, false)?;
+
+            // Parcels can't be sent to unknown users or to the sender
+            set_money(&world, &sender, 0);
+            assert_set_send_parcel_money(&sender, 0)?;
+            send(&world, &sender, "Unknown");
+            assert_send_parcel(&sender, false)?;
+            send(&world, &sender, &sender.user.name);
+            assert_send_parcel(&sender, false)?;
+
+            assert!(list_parcels(&pool, &recipient)?.is_empty());
+            assert_eq!(
+                assert_persisted(&world, &pool, &sender)?,
+                (1000, vec![(POTION, 0, 20)])
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_clear_and_close_send_parcel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let sender = add_user(&world, &pool, 1, &[(POTION, 0, 20)])?;
+
+            set_item(&world, &sender, 0, 20);
+            assert_set_send_parcel_item(&sender)?;
+            set_money(&world, &sender, 500);
+            assert_set_send_parcel_money(&sender, 500)?;
+
+            run_message(
+                &world,
+                Message::RequestClearSendParcel {
+                    connection_global_world_id: sender.connection_global_world_id,
+                    connection_local_world_id: sender.connection_local_world_id,
+                    packet: CClearSendParcel {},
+                },
+            );
+            assert!(assert_set_send_parcel_item(&sender)?.items.is_empty());
+            assert_set_send_parcel_money(&sender, 0)?;
+            world.run(|drafts: View<ParcelDraft>| {
+                let draft = drafts.try_get(sender.connection_local_world_id).unwrap();
+                assert_eq!(draft.money, 0);
+                assert!(draft.items.is_empty());
+            });
+
+            run_message(
+                &world,
+                Message::RequestCloseSendParcel {
+                    connection_global_world_id: sender.connection_global_world_id,
+                    connection_local_world_id: sender.connection_local_world_id,
+                    packet: CCloseSendParcel {},
+                },
+            );
+            world.run(|drafts: View<ParcelDraft>| {
+                assert!(drafts.try_get(sender.connection_local_world_id).is_err());
+            });
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_recv_parcel() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 1, &[(POTION, 0, 20)])?;
+            let other_user = add_user(&world, &pool, 2, &[])?;
+
+            let parcel = task::block_on(async {
+                let mut conn = pool.begin().await?;
+                let parcel = send_system_parcel(
+                    &mut conn,
+                    user.user.id,
+                    "Event",
+                    "Reward",
+                    "Thanks for playing",
+                    500,
+                    &[(POTION, 10), (WEAPON, 1)],
+                )
+                .await?;
+                conn.commit().await?;
+                Ok::<Parcel, anyhow::Error>(parcel)
+            })?;
+            let attached_ids: Vec<i64> = list_parcels(&pool, &user)?[0]
+                .1
+                .iter()
+                .map(|item| item.id)
+                .collect();
+
+            // Only the recipient can receive the attachments
+            recv(&world, &other_user, parcel.id);
+            assert_recv_parcel(&other_user, parcel.id, false)?;
+
+            recv(&world, &user, parcel.id);
+            assert_eq!(assert_itemlist(&user)?.items.len(), 3);
+            assert_recv_parcel(&user, parcel.id, true)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                (1500, vec![(POTION, 0, 20), (POTION, 1, 10), (WEAPON, 2, 1)])
+            );
+
+            // The items keep their ID
+            let inventory = get_inventory(&world, &user);
+            assert_eq!(inventory.items[&1].id, attached_ids[0]);
+            assert_eq!(inventory.items[&2].id, attached_ids[1]);
+
+            let parcels = list_parcels(&pool, &user)?;
+            let (received_parcel, items) = &parcels[0];
+            assert_eq!(received_parcel.money, 0);
+            assert!(received_parcel.is_read);
+            assert!(items.is_empty());
+
+            // Attachments can only be received once
+            recv(&world, &user, parcel.id);
+            assert_recv_parcel(&user, parcel.id, false)?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_recv_parcel_inventory_full() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 1, &[(POTION, 0, 20)])?;
+            world.run(|mut inventories: ViewMut<UserInventory>| {
+                (&mut inventories)
+                    .try_get(user.connection_local_world_id)
+                    .unwrap()
+                    .size = 2;
+            });
+
+            let parcel = task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                send_system_parcel(
+                    &mut conn,
+                    user.user.id,
+                    "Event",
+                    "Reward",
+                    "",
+                    500,
+                    &[(POTION, 10), (WEAPON, 1)],
+                )
+                .await
+            })?;
+
+            recv(&world, &user, parcel.id);
+            assert_recv_parcel(&user, parcel.id, false)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                (1000, vec![(POTION, 0, 20)])
+            );
+            assert_eq!(list_parcels(&pool, &user)?[0].1.len(), 2);
+
+            Ok(())
+        })
+    }
+}
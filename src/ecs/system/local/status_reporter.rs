@@ -0,0 +1,189 @@
+use crate::ecs::component::{Health, LocalUserSpawn, Location, UserSpawnStatus};
+use crate::ecs::message::EcsMessage;
+use crate::ecs::message::Message::{UserHealthReport, UserLocationReport};
+use crate::ecs::resource::{GlobalMessageChannel, Tick};
+use crate::ecs::system::send_message;
+use shipyard::*;
+
+/// Number of ticks between two status reports (one second at 30 ticks per second).
+const STATUS_REPORT_INTERVAL: u64 = 30;
+
+/// Reports the status of the spawned users back to the global world in a fixed interval, so that
+/// the global world can share it with users in other local worlds (party members etc.). The
+/// status contains the location and the health of an user.
+pub fn status_reporter_system(
+    user_spawns: View<LocalUserSpawn>,
+    locations: View<Location>,
+    healths: View<Health>,
+    tick: UniqueView<Tick>,
+    global_world_channel: UniqueView<GlobalMessageChannel>,
+) {
+    if tick.count % STATUS_REPORT_INTERVAL != 0 {
+        return;
+    }
+
+    (&user_spawns, &locations)
+        .iter()
+        .with_id()
+        .filter(|(_id, (spawn, _location))| spawn.status == UserSpawnStatus::Spawned)
+        .for_each(|(id, (spawn, location))| {
+            send_message(
+                assemble_user_location_report(
+                    spawn.connection_global_world_id,
+                    spawn.zone_id,
+                    location,
+                ),
+                &global_world_channel.channel,
+            );
+            if let Ok(health) = healths.try_get(id) {
+                send_message(
+                    assemble_user_health_report(spawn.connection_global_world_id, health),
+                    &global_world_channel.channel,
+                );
+            }
+        });
+}
+
+fn assemble_user_location_report(
+    connection_global_world_id: EntityId,
+    zone_id: i32,
+    location: &Location,
+) -> EcsMessage {
+    Box::new(UserLocationReport {
+        connection_global_world_id,
+        zone_id,
+        location: location.point.into(),
+    })
+}
+
+fn assemble_user_health_report(
+    connection_global_world_id: EntityId,
+    health: &Health,
+) -> EcsMessage {
+    Box::new(UserHealthReport {
+        connection_global_world_id,
+        hp: health.hp,
+        max_hp: health.max_hp,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::message::Message;
+    use crate::model::Vec3f;
+    use crate::protocol::serde::from_vec;
+    use crate::Result;
+    use async_std::sync::{channel, Receiver};
+    use nalgebra::{Point3, Rotation3, Vector3};
+    use std::time::{Duration, Instant};
+
+    fn setup(status: UserSpawnStatus) -> (World, EntityId, Receiver<EcsMessage>) {
+        let (global_tx_channel, global_rx_channel) = channel(1024);
+
+        let world = World::new();
+        world.add_unique(GlobalMessageChannel {
+            channel: global_tx_channel,
+        });
+        world.add_unique(Tick {
+            count: 0,
+            delta: Duration::from_nanos(1000),
+            time: Instant::now(),
+        });
+
+        let connection_global_world_id =
+            from_vec::<EntityId>(vec![0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]).unwrap();
+
+        world.run(
+            |mut entities: EntitiesViewMut,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut locations: ViewMut<Location>,
+             mut healths: ViewMut<Health>| {
+                entities.add_entity(
+                    (&mut user_spawns, &mut locations, &mut healths),
+                    (
+                        LocalUserSpawn {
+                            user_id: 1,
+                            account_id: 1,
+                            status,
+                            zone_id: 5,
+                            connection_global_world_id,
+                            is_alive: true,
+                        },
+                        Location {
+                            point: Point3::new(1.0f32, 2.0f32, 3.0f32),
+                            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 1.0),
+                        },
+                        Health {
+                            hp: 1500,
+                            max_hp: 2000,
+                        },
+                    ),
+                );
+            },
+        );
+
+        (world, connection_global_world_id, global_rx_channel)
+    }
+
+    fn run_tick(world: &World, count: u64) {
+        world.run(|mut tick: UniqueViewMut<Tick>| {
+            tick.count = count;
+        });
+        world.run(status_reporter_system);
+    }
+
+    #[test]
+    fn test_user_location_report() -> Result<()> {
+        let (world, connection_global_world_id, global_rx_channel) =
+            setup(UserSpawnStatus::Spawned);
+
+        run_tick(&world, STATUS_REPORT_INTERVAL - 1);
+        assert!(global_rx_channel.is_empty());
+
+        run_tick(&world, STATUS_REPORT_INTERVAL);
+        match &*global_rx_channel.try_recv()? {
+            Message::UserLocationReport {
+                connection_global_world_id: id,
+                zone_id,
+                location,
+            } => {
+                assert_eq!(*id, connection_global_world_id);
+                assert_eq!(*zone_id, 5);
+                assert_eq!(
+                    *location,
+                    Vec3f {
+                        x: 1.0,
+                        y: 2.0,
+                        z: 3.0
+                    }
+                );
+            }
+            _ => panic!("Message is not a UserLocationReport message"),
+        }
+        match &*global_rx_channel.try_recv()? {
+            Message::UserHealthReport {
+                connection_global_world_id: id,
+                hp,
+                max_hp,
+            } => {
+                assert_eq!(*id, connection_global_world_id);
+                assert_eq!(*hp, 1500);
+                assert_eq!(*max_hp, 2000);
+            }
+            _ => panic!("Message is not a UserHealthReport message"),
+        }
+        assert!(global_rx_channel.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_report_for_users_not_spawned() {
+        let (world, _connection_global_world_id, global_rx_channel) =
+            setup(UserSpawnStatus::Waiting);
+
+        run_tick(&world, STATUS_REPORT_INTERVAL);
+        assert!(global_rx_channel.is_empty());
+    }
+}
@@ -0,0 +1,1581 @@
+use crate::ecs::component::{
+    LocalConnection, LocalUserSpawn, Location, TradeItem, TradeSession, TradeStatus,
+    UserAppearance, UserInventory, UserSpawnStatus,
+};
+use crate::ecs::message::Message::{
+    ResponseAcceptContract, ResponseCancelContract, ResponseRejectContract,
+    ResponseRequestContract, ResponseTradeAccept, ResponseTradeBagClose, ResponseTradeBagDone,
+    ResponseTradeBox,
+};
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::system::local::{assemble_itemlist, send_message_to_connection};
+use crate::model::entity::{Inventory, Item};
+use crate::model::repository::{inventory, item};
+use crate::model::{MAX_TRADE_ITEMS, TRADE_CONTRACT};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{ensure, Context};
+use async_std::task;
+use nalgebra::distance;
+use shipyard::*;
+use sqlx::{PgConnection, PgPool};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info_span};
+
+/// Maximal distance between two users that trade with each other.
+const MAX_TRADE_DISTANCE: f32 = 250.0;
+
+/// Time an user has to accept a trade request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Handles the trades between users of the same local world. A trade needs to be accepted by the
+/// requested user. Both users then offer their items and money, lock their offers and confirm
+/// the trade. Once both users confirmed, the offers are swapped inside one transaction.
+pub fn trade_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<LocalConnection>,
+    user_spawns: View<LocalUserSpawn>,
+    locations: View<Location>,
+    appearances: View<UserAppearance>,
+    mut inventories: ViewMut<UserInventory>,
+    mut trades: ViewMut<TradeSession>,
+    entities: EntitiesView,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::TradeRequest {
+                connection_local_world_id,
+                partner_connection_local_world_id,
+            } => {
+                id_span!(connection_local_world_id);
+                if let Err(e) = handle_trade_request(
+                    *connection_local_world_id,
+                    *partner_connection_local_world_id,
+                    &connections,
+                    &user_spawns,
+                    &locations,
+                    &appearances,
+                    &mut trades,
+                    &entities,
+                ) {
+                    error!("Ignoring trade request: {:?}", e);
+                }
+            }
+            Message::RequestAcceptContract {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } if packet.contract_type == TRADE_CONTRACT => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_accept_trade(
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &locations,
+                    &inventories,
+                    &mut trades,
+                ) {
+                    error!("Ignoring accept trade request: {:?}", e);
+                }
+            }
+            Message::RequestRejectContract {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } if packet.contract_type == TRADE_CONTRACT => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_reject_trade(
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &appearances,
+                    &mut trades,
+                ) {
+                    error!("Ignoring reject trade request: {:?}", e);
+                }
+            }
+            Message::RequestCancelContract {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } if packet.contract_type == TRADE_CONTRACT => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_cancel_trade(
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &mut trades,
+                ) {
+                    error!("Ignoring cancel trade request: {:?}", e);
+                }
+            }
+            Message::RequestAddTradeBag {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_add_trade_bag(
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &inventories,
+                    &mut trades,
+                ) {
+                    error!("Ignoring add trade bag request: {:?}", e);
+                }
+            }
+            Message::RequestDelTradeBag {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_del_trade_bag(
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &inventories,
+                    &mut trades,
+                ) {
+                    error!("Ignoring del trade bag request: {:?}", e);
+                }
+            }
+            Message::RequestTradeBagDone {
+                connection_global_world_id,
+                connection_local_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_trade_bag_done(
+                    *connection_local_world_id,
+                    &connections,
+                    &user_spawns,
+                    &mut trades,
+                ) {
+                    error!("Ignoring trade bag done request: {:?}", e);
+                }
+            }
+            Message::RequestCommitVmTrade {
+                connection_global_world_id,
+                connection_local_world_id,
+                ..
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_commit_vm_trade(
+                    *connection_local_world_id,
+                    &connections,
+                    &user_spawns,
+                    &locations,
+                    &mut inventories,
+                    &mut trades,
+                    &pool,
+                ) {
+                    error!("Ignoring commit trade request: {:?}", e);
+                }
+            }
+            Message::UserDespawn {
+                connection_local_world_id,
+            } => {
+                id_span!(connection_local_world_id);
+                cancel_trade(
+                    *connection_local_world_id,
+                    &connections,
+                    &user_spawns,
+                    &mut trades,
+                );
+            }
+            _ => { /* Ignore all other packets */ }
+        });
+
+    // Trade requests expire if they are not accepted in time.
+    let expired_requests: Vec<EntityId> = (&trades)
+        .iter()
+        .with_id()
+        .filter(|(id, session)| {
+            session.sender_id == *id
+                && session.status == TradeStatus::Requested
+                && session.created_at.elapsed() > REQUEST_TIMEOUT
+        })
+        .map(|(id, _)| id)
+        .collect();
+    for connection_local_world_id in expired_requests {
+        cancel_trade(
+            connection_local_world_id,
+            &connections,
+            &user_spawns,
+            &mut trades,
+        );
+    }
+}
+
+/// Cancels the trade of an user. Both users of the trade are informed. Does nothing if the user
+/// isn't trading.
+fn cancel_trade(
+    connection_local_world_id: EntityId,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    trades: &mut ViewMut<TradeSession>,
+) {
+    if let Ok(session) = trades.try_get(connection_local_world_id) {
+        let session = session.clone();
+        let ids = [connection_local_world_id, session.partner_id];
+        send_to_traders(&ids, connections, user_spawns, |global_id, local_id| {
+            Box::new(ResponseCancelContract {
+                connection_global_world_id: global_id,
+                connection_local_world_id: local_id,
+                packet: SCancelContract {
+                    sender_id: session.sender_id,
+                    recipient_id: get_recipient_id(connection_local_world_id, &session),
+                    contract_type: TRADE_CONTRACT,
+                    contract_id: session.contract_id,
+                },
+            })
+        });
+        trades.delete(connection_local_world_id);
+        trades.delete(session.partner_id);
+        debug!("Trade {} was cancelled", session.contract_id);
+    }
+}
+
+fn handle_trade_request(
+    connection_local_world_id: EntityId,
+    partner_id: EntityId,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    locations: &View<Location>,
+    appearances: &View<UserAppearance>,
+    trades: &mut ViewMut<TradeSession>,
+    entities: &EntitiesView,
+) -> Result<()> {
+    debug!("Message::TradeRequest incoming");
+
+    let (spawn, appearance) = (user_spawns, appearances)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find user spawn of {:?}",
+            connection_local_world_id
+        ))?;
+    let (partner_spawn, partner_appearance) = (user_spawns, appearances)
+        .try_get(partner_id)
+        .context(format!("Can't find user spawn of {:?}", partner_id))?;
+    for (id, spawn) in [
+        (connection_local_world_id, spawn),
+        (partner_id, partner_spawn),
+    ]
+    .iter()
+    {
+        ensure!(
+            spawn.status == UserSpawnStatus::Spawned && spawn.is_alive,
+            "User {} can't trade right now",
+            spawn.user_id
+        );
+        ensure!(
+            trades.try_get(*id).is_err(),
+            "User {} is already trading",
+            spawn.user_id
+        );
+    }
+    ensure_nearby(connection_local_world_id, partner_id, locations)?;
+
+    let session = TradeSession {
+        contract_id: spawn.user_id,
+        sender_id: connection_local_world_id,
+        partner_id,
+        status: TradeStatus::Requested,
+        money: 0,
+        items: Vec::new(),
+        created_at: Instant::now(),
+    };
+    entities.add_component(
+        &mut *trades,
+        TradeSession {
+            partner_id: connection_local_world_id,
+            ..session.clone()
+        },
+        partner_id,
+    );
+    entities.add_component(&mut *trades, session, connection_local_world_id);
+
+    send_to_traders(
+        &[connection_local_world_id, partner_id],
+        connections,
+        user_spawns,
+        |global_id, local_id| {
+            Box::new(ResponseRequestContract {
+                connection_global_world_id: global_id,
+                connection_local_world_id: local_id,
+                packet: SRequestContract {
+                    sender_name: appearance.name.clone(),
+                    recipient_name: partner_appearance.name.clone(),
+                    sender_id: connection_local_world_id,
+                    recipient_id: partner_id,
+                    contract_type: TRADE_CONTRACT,
+                    contract_id: spawn.user_id,
+                },
+            })
+        },
+    );
+
+    Ok(())
+}
+
+fn handle_accept_trade(
+    connection_local_world_id: EntityId,
+    packet: &CAcceptContract,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    locations: &View<Location>,
+    inventories: &ViewMut<UserInventory>,
+    trades: &mut ViewMut<TradeSession>,
+) -> Result<()> {
+    debug!("Message::RequestAcceptContract incoming");
+
+    let session = get_session(connection_local_world_id, packet.contract_id, trades)?;
+    ensure!(
+        session.sender_id != connection_local_world_id,
+        "Only the requested user can accept trade {}",
+        session.contract_id
+    );
+    ensure!(
+        session.status == TradeStatus::Requested,
+        "Trade {} was already accepted",
+        session.contract_id
+    );
+    ensure_nearby(connection_local_world_id, session.partner_id, locations)?;
+
+    trades[connection_local_world_id].status = TradeStatus::Open;
+    trades[session.partner_id].status = TradeStatus::Open;
+
+    let ids = [connection_local_world_id, session.partner_id];
+    send_to_traders(&ids, connections, user_spawns, |global_id, local_id| {
+        Box::new(ResponseAcceptContract {
+            connection_global_world_id: global_id,
+            connection_local_world_id: local_id,
+            packet: SAcceptContract {
+                sender_id: session.sender_id,
+                recipient_id: connection_local_world_id,
+                contract_type: TRADE_CONTRACT,
+                contract_id: session.contract_id,
+            },
+        })
+    });
+    send_trade_box(
+        connection_local_world_id,
+        connections,
+        user_spawns,
+        inventories,
+        trades,
+    )
+}
+
+fn handle_reject_trade(
+    connection_local_world_id: EntityId,
+    packet: &CRejectContract,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    appearances: &View<UserAppearance>,
+    trades: &mut ViewMut<TradeSession>,
+) -> Result<()> {
+    debug!("Message::RequestRejectContract incoming");
+
+    let session = get_session(connection_local_world_id, packet.contract_id, trades)?;
+    ensure!(
+        session.sender_id != connection_local_world_id,
+        "Only the requested user can reject trade {}",
+        session.contract_id
+    );
+    ensure!(
+        session.status == TradeStatus::Requested,
+        "Trade {} was already accepted",
+        session.contract_id
+    );
+    let appearance = appearances
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find appearance of {:?}",
+            connection_local_world_id
+        ))?;
+
+    trades.delete(connection_local_world_id);
+    trades.delete(session.partner_id);
+
+    send_to_traders(
+        &[session.partner_id],
+        connections,
+        user_spawns,
+        |global_id, local_id| {
+            Box::new(ResponseRejectContract {
+                connection_global_world_id: global_id,
+                connection_local_world_id: local_id,
+                packet: SRejectContract {
+                    name: appearance.name.clone(),
+                    contract_type: TRADE_CONTRACT,
+                    contract_id: session.contract_id,
+                },
+            })
+        },
+    );
+
+    Ok(())
+}
+
+fn handle_cancel_trade(
+    connection_local_world_id: EntityId,
+    packet: &CCancelContract,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    trades: &mut ViewMut<TradeSession>,
+) -> Result<()> {
+    debug!("Message::RequestCancelContract incoming");
+
+    get_session(connection_local_world_id, packet.contract_id, trades)?;
+    cancel_trade(connection_local_world_id, connections, user_spawns, trades);
+
+    Ok(())
+}
+
+fn handle_add_trade_bag(
+    connection_local_world_id: EntityId,
+    packet: &CAddTradeBag,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &ViewMut<UserInventory>,
+    trades: &mut ViewMut<TradeSession>,
+) -> Result<()> {
+    debug!("Message::RequestAddTradeBag incoming");
+
+    let mut session = get_open_session(connection_local_world_id, trades)?;
+    let inventory = inventories
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    ensure!(
+        packet.amount > 0 || packet.money > 0,
+        "Nothing was added to trade {}",
+        session.contract_id
+    );
+
+    if packet.money > 0 {
+        session.money = session
+            .money
+            .checked_add(packet.money)
+            .context("Offered money would overflow")?;
+        ensure!(
+            session.money <= inventory.money,
+            "User {:?} can't offer more money than it has",
+            connection_local_world_id
+        );
+    }
+
+    if packet.amount > 0 {
+        let trade_item = TradeItem {
+            db_id: packet.db_id,
+            inventory_slot: packet.inventory_slot,
+            amount: packet.amount,
+        };
+        check_trade_item(inventory, &trade_item)?;
+        ensure!(
+            session
+                .items
+                .iter()
+                .all(|item| item.inventory_slot != packet.inventory_slot),
+            "Item in slot {} is already offered",
+            packet.inventory_slot
+        );
+        ensure!(
+            session.items.len() < MAX_TRADE_ITEMS,
+            "Can't offer more than {} items",
+            MAX_TRADE_ITEMS
+        );
+        session.items.push(trade_item);
+    }
+
+    trades[connection_local_world_id] = session;
+    send_trade_box(
+        connection_local_world_id,
+        connections,
+        user_spawns,
+        inventories,
+        trades,
+    )
+}
+
+fn handle_del_trade_bag(
+    connection_local_world_id: EntityId,
+    packet: &CDelTradeBag,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &ViewMut<UserInventory>,
+    trades: &mut ViewMut<TradeSession>,
+) -> Result<()> {
+    debug!("Message::RequestDelTradeBag incoming");
+
+    let mut session = get_open_session(connection_local_world_id, trades)?;
+    ensure!(
+        packet.money >= 0 && packet.money <= session.money,
+        "Can't remove {} money from trade {}",
+        packet.money,
+        session.contract_id
+    );
+    session.money -= packet.money;
+
+    let item_count = session.items.len();
+    session.items.retain(|item| item.db_id != packet.db_id);
+    ensure!(
+        session.items.len() < item_count || packet.money > 0,
+        "Nothing was removed from trade {}",
+        session.contract_id
+    );
+
+    trades[connection_local_world_id] = session;
+    send_trade_box(
+        connection_local_world_id,
+        connections,
+        user_spawns,
+        inventories,
+        trades,
+    )
+}
+
+fn handle_trade_bag_done(
+    connection_local_world_id: EntityId,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    trades: &mut ViewMut<TradeSession>,
+) -> Result<()> {
+    debug!("Message::RequestTradeBagDone incoming");
+
+    let session = get_open_session(connection_local_world_id, trades)?;
+    trades[connection_local_world_id].status = TradeStatus::Locked;
+
+    let ids = [connection_local_world_id, session.partner_id];
+    send_to_traders(&ids, connections, user_spawns, |global_id, local_id| {
+        Box::new(ResponseTradeBagDone {
+            connection_global_world_id: global_id,
+            connection_local_world_id: local_id,
+            packet: STradeBagDone {
+                contract_id: session.contract_id,
+                user_id: connection_local_world_id,
+            },
+        })
+    });
+
+    Ok(())
+}
+
+fn handle_commit_vm_trade(
+    connection_local_world_id: EntityId,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    locations: &View<Location>,
+    inventories: &mut ViewMut<UserInventory>,
+    trades: &mut ViewMut<TradeSession>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestCommitVmTrade incoming");
+
+    let session = trades
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "User {:?} is not trading",
+            connection_local_world_id
+        ))?
+        .clone();
+    let partner_session = trades
+        .try_get(session.partner_id)
+        .context(format!("User {:?} is not trading", session.partner_id))?
+        .clone();
+    ensure!(
+        session.status == TradeStatus::Locked
+            && (partner_session.status == TradeStatus::Locked
+                || partner_session.status == TradeStatus::Confirmed),
+        "Both offers of trade {} need to be locked",
+        session.contract_id
+    );
+
+    trades[connection_local_world_id].status = TradeStatus::Confirmed;
+    let ids = [connection_local_world_id, session.partner_id];
+    send_to_traders(&ids, connections, user_spawns, |global_id, local_id| {
+        Box::new(ResponseTradeAccept {
+            connection_global_world_id: global_id,
+            connection_local_world_id: local_id,
+            packet: STradeAccept {
+                contract_id: session.contract_id,
+                user_id: connection_local_world_id,
+            },
+        })
+    });
+
+    // The trade is committed once the second user confirmed it.
+    if partner_session.status != TradeStatus::Confirmed {
+        return Ok(());
+    }
+
+    let result =
+        ensure_nearby(connection_local_world_id, session.partner_id, locations).and_then(|_| {
+            exchange_offers(
+                connection_local_world_id,
+                &session,
+                &partner_session,
+                user_spawns,
+                inventories,
+                pool,
+            )
+        });
+    trades.delete(connection_local_world_id);
+    trades.delete(session.partner_id);
+
+    if result.is_ok() {
+        debug!("Trade {} was committed", session.contract_id);
+        for id in ids.iter() {
+            if let Ok((spawn, inventory)) = (user_spawns, &*inventories).try_get(*id) {
+                send_message_to_connection(
+                    assemble_itemlist(spawn.connection_global_world_id, *id, inventory, false),
+                    connections,
+                );
+            }
+        }
+    }
+    send_to_traders(&ids, connections, user_spawns, |global_id, local_id| {
+        Box::new(ResponseTradeBagClose {
+            connection_global_world_id: global_id,
+            connection_local_world_id: local_id,
+            packet: STradeBagClose {
+                contract_id: session.contract_id,
+                success: result.is_ok(),
+            },
+        })
+    });
+    result
+}
+
+/// Swaps the offered items and money of both users inside one transaction. Whole stacks keep
+/// their ID, split stacks become a new item.
+fn exchange_offers(
+    connection_local_world_id: EntityId,
+    session: &TradeSession,
+    partner_session: &TradeSession,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &mut ViewMut<UserInventory>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    let partner_id = session.partner_id;
+    let spawn = user_spawns
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find user spawn of {:?}",
+            connection_local_world_id
+        ))?;
+    let partner_spawn = user_spawns
+        .try_get(partner_id)
+        .context(format!("Can't find user spawn of {:?}", partner_id))?;
+    let mut inventory = inventories
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?
+        .clone();
+    let mut partner_inventory = inventories
+        .try_get(partner_id)
+        .context(format!("Can't find inventory of {:?}", partner_id))?
+        .clone();
+
+    // The offered items could have been moved since they were offered.
+    let offer = take_offer(&mut inventory, session)?;
+    let partner_offer = take_offer(&mut partner_inventory, partner_session)?;
+    inventory.money = inventory
+        .money
+        .checked_add(partner_session.money)
+        .context("Money of the inventory would overflow")?;
+    partner_inventory.money = partner_inventory
+        .money
+        .checked_add(session.money)
+        .context("Money of the inventory would overflow")?;
+
+    let free_slots = get_free_slots(&inventory);
+    let partner_free_slots = get_free_slots(&partner_inventory);
+    ensure!(
+        partner_offer.len() <= free_slots.len(),
+        "User {} has not enough free inventory slots",
+        spawn.user_id
+    );
+    ensure!(
+        offer.len() <= partner_free_slots.len(),
+        "User {} has not enough free inventory slots",
+        partner_spawn.user_id
+    );
+
+    let (received_items, partner_received_items) = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        remove_offer(&mut conn, &offer, &inventory).await?;
+        remove_offer(&mut conn, &partner_offer, &partner_inventory).await?;
+        let received_items =
+            receive_offer(&mut conn, &partner_offer, spawn.user_id, &free_slots).await?;
+        let partner_received_items = receive_offer(
+            &mut conn,
+            &offer,
+            partner_spawn.user_id,
+            &partner_free_slots,
+        )
+        .await?;
+
+        for (user_id, user_inventory) in [
+            (spawn.user_id, &inventory),
+            (partner_spawn.user_id, &partner_inventory),
+        ]
+        .iter()
+        {
+            inventory::update(
+                &mut conn,
+                &Inventory {
+                    user_id: *user_id,
+                    size: user_inventory.size,
+                    money: user_inventory.money,
+                    equipment_preset: user_inventory.equipment_preset,
+                },
+            )
+            .await?;
+        }
+
+        conn.commit().await?;
+        Ok::<(Vec<Item>, Vec<Item>), anyhow::Error>((received_items, partner_received_items))
+    })?;
+
+    for item in received_items {
+        inventory.items.insert(item.slot, item);
+    }
+    for item in partner_received_items {
+        partner_inventory.items.insert(item.slot, item);
+    }
+    inventories[connection_local_world_id] = inventory;
+    inventories[partner_id] = partner_inventory;
+
+    Ok(())
+}
+
+/// An offered item. Holds the offered amount and if the whole stack of the inventory is offered.
+struct OfferedItem {
+    item: Item,
+    is_whole_stack: bool,
+}
+
+/// Removes the offered items and money of a trade from the inventory.
+fn take_offer(inventory: &mut UserInventory, session: &TradeSession) -> Result<Vec<OfferedItem>> {
+    ensure!(
+        session.money <= inventory.money,
+        "Can't pay the offered money of trade {}",
+        session.contract_id
+    );
+    inventory.money -= session.money;
+
+    let mut offer = Vec::with_capacity(session.items.len());
+    for trade_item in session.items.iter() {
+        check_trade_item(inventory, trade_item)?;
+        let stack = inventory
+            .items
+            .remove(&trade_item.inventory_slot)
+            .context(format!(
+                "No item found in slot {}",
+                trade_item.inventory_slot
+            ))?;
+        if stack.amount == trade_item.amount {
+            offer.push(OfferedItem {
+                item: stack,
+                is_whole_stack: true,
+            });
+        } else {
+            inventory.items.insert(
+                stack.slot,
+                Item {
+                    amount: stack.amount - trade_item.amount,
+                    ..stack.clone()
+                },
+            );
+            offer.push(OfferedItem {
+                item: Item {
+                    amount: trade_item.amount,
+                    ..stack
+                },
+                is_whole_stack: false,
+            });
+        }
+    }
+    Ok(offer)
+}
+
+/// Persists the removal of the offered items out of the inventory.
+async fn remove_offer(
+    conn: &mut PgConnection,
+    offer: &[OfferedItem],
+    inventory: &UserInventory,
+) -> Result<()> {
+    for offered_item in offer {
+        if offered_item.is_whole_stack {
+            item::delete(conn, offered_item.item.id).await?;
+        } else if let Some(stack) = inventory.items.get(&offered_item.item.slot) {
+            item::update(conn, stack).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Moves the offered items of the other user into the free slots of the inventory.
+async fn receive_offer(
+    conn: &mut PgConnection,
+    offer: &[OfferedItem],
+    user_id: i32,
+    free_slots: &[i32],
+) -> Result<Vec<Item>> {
+    let mut received_items = Vec::with_capacity(offer.len());
+    for (offered_item, slot) in offer.iter().zip(free_slots.iter()) {
+        let new_item = Item {
+            user_id,
+            slot: *slot,
+            ..offered_item.item.clone()
+        };
+        let received_item = if offered_item.is_whole_stack {
+            item::restore(conn, &new_item).await?
+        } else {
+            item::create(conn, &new_item).await?
+        };
+        received_items.push(received_item);
+    }
+    Ok(received_items)
+}
+
+fn get_free_slots(inventory: &UserInventory) -> Vec<i32> {
+    (0..inventory.size)
+        .filter(|slot| !inventory.items.contains_key(slot))
+        .collect()
+}
+
+/// Makes sure that the offered item is inside the inventory.
+fn check_trade_item(inventory: &UserInventory, trade_item: &TradeItem) -> Result<()> {
+    let item = inventory
+        .items
+        .get(&trade_item.inventory_slot)
+        .context(format!(
+            "No item found in slot {}",
+            trade_item.inventory_slot
+        ))?;
+    ensure!(
+        item.id == trade_item.db_id,
+        "Item {} is not inside slot {}",
+        trade_item.db_id,
+        trade_item.inventory_slot
+    );
+    ensure!(
+        trade_item.amount > 0 && trade_item.amount <= item.amount,
+        "Can't offer {} of {} items",
+        trade_item.amount,
+        item.amount
+    );
+    Ok(())
+}
+
+/// Makes sure that both users are near enough to trade with each other.
+fn ensure_nearby(
+    connection_local_world_id: EntityId,
+    partner_id: EntityId,
+    locations: &View<Location>,
+) -> Result<()> {
+    let location = locations
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find location of {:?}",
+            connection_local_world_id
+        ))?;
+    let partner_location = locations
+        .try_get(partner_id)
+        .context(format!("Can't find location of {:?}", partner_id))?;
+    ensure!(
+        distance(&location.point, &partner_location.point) <= MAX_TRADE_DISTANCE,
+        "User {:?} and user {:?} are too far away from each other to trade",
+        connection_local_world_id,
+        partner_id
+    );
+    Ok(())
+}
+
+fn get_session(
+    connection_local_world_id: EntityId,
+    contract_id: i32,
+    trades: &ViewMut<TradeSession>,
+) -> Result<TradeSession> {
+    let session = trades.try_get(connection_local_world_id).context(format!(
+        "User {:?} is not trading",
+        connection_local_world_id
+    ))?;
+    ensure!(
+        session.contract_id == contract_id,
+        "User {:?} is not part of trade {}",
+        connection_local_world_id,
+        contract_id
+    );
+    Ok(session.clone())
+}
+
+/// Returns the trade session of an user whose offer can still be changed.
+fn get_open_session(
+    connection_local_world_id: EntityId,
+    trades: &ViewMut<TradeSession>,
+) -> Result<TradeSession> {
+    let session = trades.try_get(connection_local_world_id).context(format!(
+        "User {:?} is not trading",
+        connection_local_world_id
+    ))?;
+    ensure!(
+        session.status == TradeStatus::Open,
+        "The offer of user {:?} can't be changed",
+        connection_local_world_id
+    );
+    Ok(session.clone())
+}
+
+/// Returns the connection_local_world_id of the requested user of a trade.
+fn get_recipient_id(connection_local_world_id: EntityId, session: &TradeSession) -> EntityId {
+    if session.sender_id == connection_local_world_id {
+        session.partner_id
+    } else {
+        connection_local_world_id
+    }
+}
+
+/// Sends the offers of both users to both users.
+fn send_trade_box(
+    connection_local_world_id: EntityId,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &ViewMut<UserInventory>,
+    trades: &ViewMut<TradeSession>,
+) -> Result<()> {
+    let session = trades.try_get(connection_local_world_id).context(format!(
+        "User {:?} is not trading",
+        connection_local_world_id
+    ))?;
+    let partner_session = trades
+        .try_get(session.partner_id)
+        .context(format!("User {:?} is not trading", session.partner_id))?;
+    let recipient_id = get_recipient_id(connection_local_world_id, session);
+    let (sender_session, recipient_session) = if session.sender_id == connection_local_world_id {
+        (session, partner_session)
+    } else {
+        (partner_session, session)
+    };
+
+    let mut items = Vec::new();
+    for (owner_id, session) in [
+        (session.sender_id, sender_session),
+        (recipient_id, recipient_session),
+    ]
+    .iter()
+    {
+        let inventory = inventories
+            .try_get(*owner_id)
+            .context(format!("Can't find inventory of {:?}", owner_id))?;
+        items.extend(session.items.iter().filter_map(|trade_item| {
+            inventory
+                .items
+                .get(&trade_item.inventory_slot)
+                .map(|item| STradeBoxItem {
+                    owner_id: *owner_id,
+                    id: item.template_id,
+                    db_id: trade_item.db_id,
+                    amount: trade_item.amount,
+                })
+        }));
+    }
+
+    let ids = [connection_local_world_id, session.partner_id];
+    send_to_traders(&ids, connections, user_spawns, |global_id, local_id| {
+        Box::new(ResponseTradeBox {
+            connection_global_world_id: global_id,
+            connection_local_world_id: local_id,
+            packet: STradeBox {
+                items: items.clone(),
+                contract_id: session.contract_id,
+                sender_id: session.sender_id,
+                sender_money: sender_session.money,
+                recipient_id,
+                recipient_money: recipient_session.money,
+            },
+        })
+    });
+
+    Ok(())
+}
+
+/// Sends a message to the given users of a trade. The message is assembled with the
+/// connection_global_world_id and connection_local_world_id of each user.
+fn send_to_traders<F>(
+    ids: &[EntityId],
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    assemble: F,
+) where
+    F: Fn(EntityId, EntityId) -> EcsMessage,
+{
+    for id in ids.iter() {
+        if let Ok(spawn) = user_spawns.try_get(*id) {
+            send_message_to_connection(
+                assemble(spawn.connection_global_world_id, *id),
+                connections,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::DeletionList;
+    use crate::ecs::system::common::cleaner_system;
+    use crate::ecs::system::local::tests::{add_user, TestUser};
+    use crate::model::tests::db_test;
+    use crate::model::{Class, Customization, Gender, Race, TemplateID};
+    use nalgebra::{Point3, Rotation3, Vector3};
+
+    const POTION: i32 = 8005;
+    const WEAPON: i32 = 10001;
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(pool);
+        world.add_unique(DeletionList(Vec::default()));
+        world
+    }
+
+    /// Creates an user like `add_user` and places it at the given x coordinate.
+    fn add_trader(
+        world: &World,
+        pool: &PgPool,
+        num: i32,
+        x: f32,
+        items: &[(i32, i32, i32)],
+    ) -> Result<TestUser> {
+        let user = add_user(world, pool, num, items)?;
+
+        world.run(
+            |entities: EntitiesView,
+             mut locations: ViewMut<Location>,
+             mut appearances: ViewMut<UserAppearance>| {
+                entities.add_component(
+                    (&mut locations, &mut appearances),
+                    (
+                        Location {
+                            point: Point3::new(x, 0.0, 0.0),
+                            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+                        },
+                        UserAppearance {
+                            name: user.user.name.clone(),
+                            template_id: TemplateID {
+                                race: Race::Human,
+                                gender: Gender::Female,
+                                class: Class::Priest,
+                            },
+                            level: 65,
+                            details: vec![],
+                            shape: vec![],
+                            appearance: Customization::default(),
+                            appearance2: 100,
+                            show_face: true,
+                            show_style: true,
+                            guild_name: "".to_string(),
+                            guild_rank: "".to_string(),
+                        },
+                    ),
+                    user.connection_local_world_id,
+                );
+            },
+        );
+
+        Ok(user)
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(trade_system);
+        world.run(cleaner_system);
+    }
+
+    fn request_trade(world: &World, user: &TestUser, partner: &TestUser) {
+        run_message(
+            world,
+            Message::TradeRequest {
+                connection_local_world_id: user.connection_local_world_id,
+                partner_connection_local_world_id: partner.connection_local_world_id,
+            },
+        );
+    }
+
+    fn accept(world: &World, user: &TestUser, contract_id: i32) {
+        run_message(
+            world,
+            Message::RequestAcceptContract {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CAcceptContract {
+                    contract_type: TRADE_CONTRACT,
+                    contract_id,
+                },
+            },
+        );
+    }
+
+    fn reject(world: &World, user: &TestUser, contract_id: i32) {
+        run_message(
+            world,
+            Message::RequestRejectContract {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CRejectContract {
+                    contract_type: TRADE_CONTRACT,
+                    contract_id,
+                },
+            },
+        );
+    }
+
+    fn cancel(world: &World, user: &TestUser, contract_id: i32) {
+        run_message(
+            world,
+            Message::RequestCancelContract {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CCancelContract {
+                    contract_type: TRADE_CONTRACT,
+                    contract_id,
+                },
+            },
+        );
+    }
+
+    fn add_item(world: &World, user: &TestUser, inventory_slot: i32, amount: i32) {
+        let db_id = get_inventory(world, user)
+            .items
+            .get(&inventory_slot)
+            .map_or(0, |item| item.id);
+        add_trade_bag(world, user, db_id, inventory_slot, amount, 0);
+    }
+
+    fn add_trade_bag(
+        world: &World,
+        user: &TestUser,
+        db_id: i64,
+        inventory_slot: i32,
+        amount: i32,
+        money: i64,
+    ) {
+        run_message(
+            world,
+            Message::RequestAddTradeBag {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CAddTradeBag {
+                    db_id,
+                    inventory_slot,
+                    amount,
+                    money,
+                },
+            },
+        );
+    }
+
+    fn del_trade_bag(world: &World, user: &TestUser, db_id: i64, money: i64) {
+        run_message(
+            world,
+            Message::RequestDelTradeBag {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CDelTradeBag { db_id, money },
+            },
+        );
+    }
+
+    fn bag_done(world: &World, user: &TestUser) {
+        run_message(
+            world,
+            Message::RequestTradeBagDone {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CTradeBagDone {},
+            },
+        );
+    }
+
+    fn commit(world: &World, user: &TestUser) {
+        run_message(
+            world,
+            Message::RequestCommitVmTrade {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CCommitVmTrade {},
+            },
+        );
+    }
+
+    /// Opens a trade between both users and drains the messages of the request.
+    fn open_trade(world: &World, user: &TestUser, partner: &TestUser) -> Result<()> {
+        request_trade(world, user, partner);
+        accept(world, partner, user.user.id);
+        for test_user in [user, partner].iter() {
+            assert_request_contract(test_user)?;
+            assert_accept_contract(test_user)?;
+            assert_trade_box(test_user)?;
+        }
+        Ok(())
+    }
+
+    fn get_inventory(world: &World, user: &TestUser) -> UserInventory {
+        world.run(|inventories: View<UserInventory>| {
+            inventories
+                .try_get(user.connection_local_world_id)
+                .unwrap()
+                .clone()
+        })
+    }
+
+    fn is_trading(world: &World, user: &TestUser) -> bool {
+        world.run(|trades: View<TradeSession>| {
+            trades.try_get(user.connection_local_world_id).is_ok()
+        })
+    }
+
+    /// Checks that the inventory component matches the database and returns the money and the
+    /// items of the inventory as (template ID, slot, amount) ordered by their slot.
+    fn assert_persisted(
+        world: &World,
+        pool: &PgPool,
+        user: &TestUser,
+    ) -> Result<(i64, Vec<(i32, i32, i32)>)> {
+        let (db_inventory, db_items) = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let db_inventory = inventory::get_by_user_id(&mut conn, user.user.id).await?;
+            let db_items = item::list_by_user_id(&mut conn, user.user.id).await?;
+            Ok::<(Inventory, Vec<Item>), anyhow::Error>((db_inventory, db_items))
+        })?;
+
+        let inventory = get_inventory(world, user);
+        let mut items = inventory.items.values().cloned().collect::<Vec<Item>>();
+        items.sort_by_key(|item| item.slot);
+        assert_eq!(items, db_items);
+        assert_eq!(inventory.money, db_inventory.money);
+
+        Ok((
+            db_inventory.money,
+            db_items
+                .iter()
+                .map(|item| (item.template_id, item.slot, item.amount))
+                .collect(),
+        ))
+    }
+
+    fn assert_request_contract(user: &TestUser) -> Result<SRequestContract> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseRequestContract { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseRequestContract message"),
+        }
+    }
+
+    fn assert_accept_contract(user: &TestUser) -> Result<SAcceptContract> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseAcceptContract { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseAcceptContract message"),
+        }
+    }
+
+    fn assert_reject_contract(user: &TestUser) -> Result<SRejectContract> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseRejectContract { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseRejectContract message"),
+        }
+    }
+
+    fn assert_cancel_contract(user: &TestUser) -> Result<SCancelContract> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseCancelContract { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseCancelContract message"),
+        }
+    }
+
+    fn assert_trade_box(user: &TestUser) -> Result<STradeBox> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseTradeBox { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseTradeBox message"),
+        }
+    }
+
+    fn assert_trade_bag_done(user: &TestUser, user_id: EntityId) -> Result<()> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseTradeBagDone { packet, .. } => assert_eq!(packet.user_id, user_id),
+            _ => panic!("Message is not a ResponseTradeBagDone message"),
+        }
+        Ok(())
+    }
+
+    fn assert_trade_accept(user: &TestUser, user_id: EntityId) -> Result<()> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseTradeAccept { packet, .. } => assert_eq!(packet.user_id, user_id),
+            _ => panic!("Message is not a ResponseTradeAccept message"),
+        }
+        Ok(())
+    }
+
+    fn assert_trade_bag_close(user: &TestUser, success: bool) -> Result<()> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseTradeBagClose { packet, .. } => assert_eq!(packet.success, success),
+            _ => panic!("Message is not a ResponseTradeBagClose message"),
+        }
+        Ok(())
+    }
+
+    fn assert_itemlist(user: &TestUser) -> Result<SItemlist> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseItemlist { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseItemlist message"),
+        }
+    }
+
+    #[test]
+    fn test_trade() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_trader(&world, &pool, 1, 0.0, &[(POTION, 0, 20), (WEAPON, 1, 1)])?;
+            let partner = add_trader(&world, &pool, 2, 100.0, &[(POTION, 0, 5)])?;
+            let weapon_id = get_inventory(&world, &user).items[&1].id;
+
+            request_trade(&world, &user, &partner);
+            for test_user in [&user, &partner].iter() {
+                let packet = assert_request_contract(test_user)?;
+                assert_eq!(packet.sender_name, user.user.name);
+                assert_eq!(packet.recipient_name, partner.user.name);
+                assert_eq!(packet.contract_id, user.user.id);
+            }
+
+            accept(&world, &partner, user.user.id);
+            for test_user in [&user, &partner].iter() {
+                let packet = assert_accept_contract(test_user)?;
+                assert_eq!(packet.sender_id, user.connection_local_world_id);
+                assert_eq!(packet.recipient_id, partner.connection_local_world_id);
+                assert!(assert_trade_box(test_user)?.items.is_empty());
+            }
+
+            add_item(&world, &user, 0, 5);
+            add_item(&world, &user, 1, 1);
+            add_trade_bag(&world, &user, 0, 0, 0, 300);
+            add_trade_bag(&world, &partner, 0, 0, 0, 100);
+            for _ in 0..3 {
+                assert_trade_box(&user)?;
+                assert_trade_box(&partner)?;
+            }
+            let packet = assert_trade_box(&partner)?;
+            assert_eq!(packet.items.len(), 2);
+            assert_eq!(packet.items[1].owner_id, user.connection_local_world_id);
+            assert_eq!(packet.items[1].id, WEAPON);
+            assert_eq!(packet.items[1].db_id, weapon_id);
+            assert_eq!(packet.sender_money, 300);
+            assert_eq!(packet.recipient_money, 100);
+            assert_trade_box(&user)?;
+
+            bag_done(&world, &user);
+            bag_done(&world, &partner);
+            for test_user in [&user, &partner].iter() {
+                assert_trade_bag_done(test_user, user.connection_local_world_id)?;
+                assert_trade_bag_done(test_user, partner.connection_local_world_id)?;
+            }
+
+            // The trade is committed once both users confirmed it
+            commit(&world, &user);
+            assert_trade_accept(&user, user.connection_local_world_id)?;
+            assert_trade_accept(&partner, user.connection_local_world_id)?;
+            assert!(user.rx.is_empty());
+            commit(&world, &partner);
+            for test_user in [&user, &partner].iter() {
+                assert_trade_accept(test_user, partner.connection_local_world_id)?;
+                assert_itemlist(test_user)?;
+                assert_trade_bag_close(test_user, true)?;
+            }
+
+            assert_eq!(
+                assert_persisted(&world, &pool, &user)?,
+                (800, vec![(POTION, 0, 15)])
+            );
+            assert_eq!(
+                assert_persisted(&world, &pool, &partner)?,
+                (1200, vec![(POTION, 0, 5), (POTION, 1, 5), (WEAPON, 2, 1)])
+            );
+
+            // Whole stacks keep their ID
+            assert_eq!(get_inventory(&world, &partner).items[&2].id, weapon_id);
+            assert!(!is_trading(&world, &user));
+            assert!(!is_trading(&world, &partner));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_reject_and_cancel_trade() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_trader(&world, &pool, 1, 0.0, &[])?;
+            let partner = add_trader(&world, &pool, 2, 0.0, &[])?;
+
+            request_trade(&world, &user, &partner);
+            assert_request_contract(&user)?;
+            assert_request_contract(&partner)?;
+
+            // Only the requested user can reject the trade
+            reject(&world, &user, user.user.id);
+            assert!(user.rx.is_empty());
+            reject(&world, &partner, user.user.id);
+            assert_eq!(assert_reject_contract(&user)?.name, partner.user.name);
+            assert!(partner.rx.is_empty());
+            assert!(!is_trading(&world, &user));
+            assert!(!is_trading(&world, &partner));
+
+            open_trade(&world, &user, &partner)?;
+            cancel(&world, &user, user.user.id);
+            assert_cancel_contract(&user)?;
+            assert_cancel_contract(&partner)?;
+            assert!(!is_trading(&world, &user));
+            assert!(!is_trading(&world, &partner));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_invalid_trade_offer() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_trader(&world, &pool, 1, 0.0, &[(POTION, 0, 20)])?;
+            let partner = add_trader(&world, &pool, 2, 0.0, &[])?;
+            let potion_id = get_inventory(&world, &user).items[&0].id;
+
+            // Offers can only be made inside an accepted trade
+            add_item(&world, &user, 0, 5);
+            request_trade(&world, &user, &partner);
+            add_item(&world, &user, 0, 5);
+            assert_request_contract(&user)?;
+            assert!(user.rx.is_empty());
+            cancel(&world, &user, user.user.id);
+            assert_cancel_contract(&user)?;
+            assert_request_contract(&partner)?;
+            assert_cancel_contract(&partner)?;
+
+            open_trade(&world, &user, &partner)?;
+            add_trade_bag(&world, &user, 0, 0, 0, 2000);
+            add_trade_bag(&world, &user, 0, 0, 0, 0);
+            add_trade_bag(&world, &user, potion_id + 1, 0, 5, 0);
+            add_trade_bag(&world, &user, potion_id, 1, 5, 0);
+            add_item(&world, &user, 0, 21);
+            add_item(&world, &user, 0, -1);
+            del_trade_bag(&world, &user, potion_id, 0);
+            commit(&world, &user);
+            assert!(user.rx.is_empty());
+
+            // Items can't be offered twice
+            add_item(&world, &user, 0, 5);
+            add_item(&world, &user, 0, 5);
+            assert_trade_box(&user)?;
+            assert!(user.rx.is_empty());
+
+            // Offers can't be changed once they are locked
+            bag_done(&world, &user);
+            assert_trade_bag_done(&user, user.connection_local_world_id)?;
+            del_trade_bag(&world, &user, potion_id, 0);
+            add_trade_bag(&world, &user, 0, 0, 0, 100);
+            assert!(user.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_trade_too_far_away() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_trader(&world, &pool, 1, 0.0, &[])?;
+            let partner = add_trader(&world, &pool, 2, MAX_TRADE_DISTANCE + 1.0, &[])?;
+
+            request_trade(&world, &user, &partner);
+            assert!(user.rx.is_empty());
+            assert!(partner.rx.is_empty());
+            assert!(!is_trading(&world, &user));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_trade_is_cancelled_on_despawn() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_trader(&world, &pool, 1, 0.0, &[])?;
+            let partner = add_trader(&world, &pool, 2, 0.0, &[])?;
+
+            open_trade(&world, &user, &partner)?;
+            run_message(
+                &world,
+                Message::UserDespawn {
+                    connection_local_world_id: user.connection_local_world_id,
+                },
+            );
+            assert_cancel_contract(&partner)?;
+            assert!(!is_trading(&world, &user));
+            assert!(!is_trading(&world, &partner));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_trade_request_expires() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_trader(&world, &pool, 1, 0.0, &[])?;
+            let partner = add_trader(&world, &pool, 2, 0.0, &[])?;
+
+            request_trade(&world, &user, &partner);
+            assert_request_contract(&user)?;
+            assert_request_contract(&partner)?;
+
+            world.run(|mut trades: ViewMut<TradeSession>| {
+                trades[user.connection_local_world_id].created_at =
+                    Instant::now() - REQUEST_TIMEOUT - Duration::from_secs(1);
+            });
+            world.run(trade_system);
+            assert_cancel_contract(&user)?;
+            assert_cancel_contract(&partner)?;
+            assert!(!is_trading(&world, &partner));
+
+            Ok(())
+        })
+    }
+}
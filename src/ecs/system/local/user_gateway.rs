@@ -1,17 +1,22 @@
-use crate::ecs::component::{LocalConnection, LocalUserSpawn, Location, UserSpawnStatus};
-use crate::ecs::dto::{UserFinalizer, UserInitializer};
+use crate::ecs::component::{
+    Abnormalities, ActiveAbnormality, LocalConnection, LocalUserSpawn, Location, UserAppearance,
+    UserExperience, UserInventory, UserSpawnStatus, Visibility,
+};
+use crate::ecs::dto::{UserFinalizer, UserInitializer, UserProgress};
 use crate::ecs::message::Message::{
     ResponseSpawnMe, UserDespawned, UserSpawnPrepared, UserSpawned,
 };
 use crate::ecs::message::{EcsMessage, Message};
 use crate::ecs::resource::{DeletionList, GlobalMessageChannel};
 use crate::ecs::system::send_message;
-use crate::model::entity::UserLocation;
-use crate::model::{Angle, Vec3f};
+use crate::model::entity::{UserAbnormality, UserLocation};
+use crate::model::{Angle, TemplateID, Vec3f};
 use crate::protocol::packet::*;
 use crate::Result;
 use anyhow::{ensure, Context};
 use shipyard::*;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info_span};
 
 /// Acts as a gateway for users to pass when spawning / logging out.
@@ -20,6 +25,13 @@ pub fn user_gateway_system(
     mut connections: ViewMut<LocalConnection>,
     mut user_spawns: ViewMut<LocalUserSpawn>,
     mut locations: ViewMut<Location>,
+    mut visibilities: ViewMut<Visibility>,
+    (mut appearances, mut inventories, mut abnormalities, mut experiences): (
+        ViewMut<UserAppearance>,
+        ViewMut<UserInventory>,
+        ViewMut<Abnormalities>,
+        ViewMut<UserExperience>,
+    ),
     mut entities: EntitiesViewMut,
     global_world_channel: UniqueView<GlobalMessageChannel>,
     mut deletion_list: UniqueViewMut<DeletionList>,
@@ -35,6 +47,10 @@ pub fn user_gateway_system(
                     &mut connections,
                     &mut user_spawns,
                     &mut locations,
+                    &mut visibilities,
+                    &mut appearances,
+                    &mut inventories,
+                    (&mut abnormalities, &mut experiences),
                     &mut entities,
                     &global_world_channel,
                 )
@@ -76,6 +92,9 @@ pub fn user_gateway_system(
                     *connection_local_world_id,
                     &mut user_spawns,
                     &mut locations,
+                    &appearances,
+                    &abnormalities,
+                    &experiences,
                     &mut deletion_list,
                     &global_world_channel,
                 ) {
@@ -91,21 +110,33 @@ fn handle_prepare_user_spawn(
     connections: &mut ViewMut<LocalConnection>,
     user_spawns: &mut ViewMut<LocalUserSpawn>,
     locations: &mut ViewMut<Location>,
+    visibilities: &mut ViewMut<Visibility>,
+    appearances: &mut ViewMut<UserAppearance>,
+    inventories: &mut ViewMut<UserInventory>,
+    (abnormalities, experiences): (&mut ViewMut<Abnormalities>, &mut ViewMut<UserExperience>),
     entities: &mut EntitiesViewMut,
     global_world_channel: &UniqueView<GlobalMessageChannel>,
 ) {
     debug!("Message::PrepareUserSpawn incoming");
 
+    let user = &user_initializer.user;
     let connection_local_world_id = entities.add_entity(
-        (connections, user_spawns, locations),
+        (
+            connections,
+            user_spawns,
+            locations,
+            visibilities,
+            appearances,
+            inventories,
+        ),
         (
             LocalConnection {
                 channel: user_initializer.connection_channel.clone(),
             },
             LocalUserSpawn {
                 connection_global_world_id: user_initializer.connection_global_world_id,
-                user_id: user_initializer.user.id,
-                account_id: user_initializer.user.account_id,
+                user_id: user.id,
+                account_id: user.account_id,
                 status: UserSpawnStatus::Waiting,
                 zone_id: user_initializer.location.zone_id,
                 is_alive: user_initializer.is_alive,
@@ -114,9 +145,82 @@ fn handle_prepare_user_spawn(
                 point: user_initializer.location.point.clone(),
                 rotation: user_initializer.location.rotation.clone(),
             },
+            Visibility {
+                range: user_initializer.visibility_range,
+                visible_entities: HashSet::new(),
+            },
+            UserAppearance {
+                name: user.name.clone(),
+                template_id: TemplateID {
+                    race: user.race,
+                    gender: user.gender,
+                    class: user.class,
+                },
+                level: user.level,
+                details: user.details.clone(),
+                shape: user.shape.clone(),
+                appearance: user.appearance.clone(),
+                appearance2: user.appearance2,
+                show_face: user.show_face,
+                show_style: user.show_style,
+                guild_name: user_initializer.guild_name.clone(),
+                guild_rank: user_initializer.guild_rank.clone(),
+            },
+            UserInventory {
+                size: user_initializer.inventory.size,
+                money: user_initializer.inventory.money,
+                items: user_initializer
+                    .items
+                    .iter()
+                    .map(|item| (item.slot, item.clone()))
+                    .collect(),
+                equipment_preset: user_initializer.inventory.equipment_preset,
+                equipment: user_initializer
+                    .equipment
+                    .iter()
+                    .map(|equipped_item| (equipped_item.slot, equipped_item.clone()))
+                    .collect(),
+            },
         ),
     );
 
+    // Abnormalities that survived the logout. Their source is unknown, so the user itself is
+    // used as the source.
+    entities.add_component(
+        abnormalities,
+        Abnormalities {
+            active: user_initializer
+                .abnormalities
+                .iter()
+                .map(|abnormality| {
+                    (
+                        abnormality.abnormality_id,
+                        ActiveAbnormality {
+                            source_id: connection_local_world_id,
+                            stacks: abnormality.stacks,
+                            remaining: Duration::from_millis(abnormality.remaining.max(0) as u64),
+                            next_period: Duration::from_secs(0),
+                            is_persistent: true,
+                        },
+                    )
+                })
+                .collect(),
+            requested: Vec::new(),
+        },
+        connection_local_world_id,
+    );
+    entities.add_component(
+        experiences,
+        UserExperience {
+            exp: user.xp,
+            rest_bonus_exp: user.rest_bonus_xp,
+            playtime: user.playtime,
+            spawned_at: Instant::now(),
+            requested: Vec::new(),
+        },
+        connection_local_world_id,
+    );
+
     send_message(
         assemble_user_spawn_prepared(
             user_initializer.connection_global_world_id,
@@ -189,6 +293,9 @@ fn handle_user_despawn(
     connection_local_world_id: EntityId,
     user_spawns: &mut ViewMut<LocalUserSpawn>,
     locations: &mut ViewMut<Location>,
+    appearances: &ViewMut<UserAppearance>,
+    abnormalities: &ViewMut<Abnormalities>,
+    experiences: &ViewMut<UserExperience>,
     deletion_list: &mut UniqueViewMut<DeletionList>,
     global_world_channel: &UniqueView<GlobalMessageChannel>,
 ) -> Result<()> {
@@ -203,7 +310,14 @@ fn handle_user_despawn(
 
     // Send all user data that needs to be persisted to the global world.
     send_message(
-        assemble_user_despawned(spawn, location),
+        assemble_user_despawned(
+            spawn,
+            location,
+            abnormalities.try_get(connection_local_world_id).ok(),
+            (appearances, experiences)
+                .try_get(connection_local_world_id)
+                .ok(),
+        ),
         &global_world_channel.channel,
     );
 
@@ -244,7 +358,29 @@ fn assemble_user_spawned(connection_global_world_id: EntityId) -> EcsMessage {
     })
 }
 
-fn assemble_user_despawned(spawn: &LocalUserSpawn, location: &Location) -> EcsMessage {
+fn assemble_user_despawned(
+    spawn: &LocalUserSpawn,
+    location: &Location,
+    abnormalities: Option<&Abnormalities>,
+    progress: Option<(&UserAppearance, &UserExperience)>,
+) -> EcsMessage {
+    let mut persistent_abnormalities: Vec<UserAbnormality> = abnormalities
+        .map(|abnormalities| {
+            abnormalities
+                .active
+                .iter()
+                .filter(|(_id, abnormality)| abnormality.is_persistent)
+                .map(|(id, abnormality)| UserAbnormality {
+                    user_id: spawn.user_id,
+                    abnormality_id: *id,
+                    stacks: abnormality.stacks,
+                    remaining: abnormality.remaining.as_millis() as i64,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    persistent_abnormalities.sort_by_key(|abnormality| abnormality.abnormality_id);
+
     Box::new(UserDespawned {
         user_finalizer: UserFinalizer {
             connection_global_world_id: spawn.connection_global_world_id,
@@ -256,6 +392,13 @@ fn assemble_user_despawned(spawn: &LocalUserSpawn, location: &Location) -> EcsMe
                 rotation: location.rotation.clone(),
             },
             is_alive: spawn.is_alive,
+            abnormalities: persistent_abnormalities,
+            progress: progress.map(|(appearance, experience)| UserProgress {
+                level: appearance.level,
+                xp: experience.exp,
+                rest_bonus_xp: experience.rest_bonus_exp,
+                playtime: experience.playtime + experience.spawned_at.elapsed().as_secs() as i64,
+            }),
         },
     })
 }
@@ -273,13 +416,14 @@ fn assemble_user_spawn_prepared(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::entity::{User, UserLocation};
-    use crate::model::{Class, Gender, Race};
+    use crate::model::entity::{EquippedItem, Inventory, Item, User, UserLocation};
+    use crate::model::{Class, EquipmentSlot, Gender, Race};
     use crate::protocol::serde::from_vec;
     use crate::Result;
     use async_std::sync::{channel, Receiver};
     use chrono::{TimeZone, Utc};
     use nalgebra::{Point3, Rotation3, Vector3};
+    use std::collections::HashMap;
 
     fn setup() -> Result<(World, Receiver<EcsMessage>)> {
         let (global_tx_channel, global_rx_channel) = channel(1024);
@@ -357,12 +501,13 @@ mod tests {
             details: vec![],
             appearance: Default::default(),
             appearance2: 0,
-            level: 0,
+            level: 12,
             awakening_level: 0,
             laurel: 0,
             achievement_points: 0,
-            playtime: 0,
-            rest_bonus_xp: 0,
+            playtime: 3600,
+            xp: 300,
+            rest_bonus_xp: 400,
             show_face: false,
             show_style: false,
             lobby_slot: 0,
@@ -372,6 +517,7 @@ mod tests {
             delete_at: None,
             last_logout_at: Utc.ymd(2020, 7, 8).and_hms(9, 10, 11),
             created_at: Utc.ymd(2020, 7, 8).and_hms(9, 10, 11),
+            is_alive: true,
         };
 
         let user_location = UserLocation {
@@ -392,6 +538,37 @@ mod tests {
                             user: user.clone(),
                             location: user_location.clone(),
                             is_alive: true,
+                            visibility_range: 2500,
+                            guild_name: "Manhunter".to_string(),
+                            guild_rank: "Member".to_string(),
+                            inventory: Inventory {
+                                user_id: 1,
+                                size: 48,
+                                money: 500,
+                                equipment_preset: 1,
+                            },
+                            items: vec![Item {
+                                id: 10,
+                                user_id: 1,
+                                template_id: 8005,
+                                slot: 4,
+                                amount: 5,
+                                created_at: Utc.ymd(2020, 7, 8).and_hms(9, 10, 11),
+                            }],
+                            equipment: vec![EquippedItem {
+                                id: 11,
+                                user_id: 1,
+                                preset: 1,
+                                slot: EquipmentSlot::Hand,
+                                template_id: 15005,
+                                created_at: Utc.ymd(2020, 7, 8).and_hms(9, 10, 11),
+                            }],
+                            abnormalities: vec![UserAbnormality {
+                                user_id: 1,
+                                abnormality_id: 4000,
+                                stacks: 2,
+                                remaining: 30_000,
+                            }],
                         },
                     }),
                 );
@@ -403,8 +580,18 @@ mod tests {
         let connection_local_world_id = world.run(
             |connections: View<LocalConnection>,
              spawns: View<LocalUserSpawn>,
-             locations: View<Location>| {
-                let (id, (_connection, spawn, location)) = (&connections, &spawns, &locations)
+             locations: View<Location>,
+             visibilities: View<Visibility>,
+             appearances: View<UserAppearance>,
+             inventories: View<UserInventory>| {
+                let (id, (_connection, spawn, location, visibility, appearance, inventory)) = (
+                    &connections,
+                    &spawns,
+                    &locations,
+                    &visibilities,
+                    &appearances,
+                    &inventories,
+                )
                     .iter()
                     .with_id()
                     .next()
@@ -417,11 +604,47 @@ mod tests {
                 assert_eq!(spawn.is_alive, true);
                 assert_eq!(location.point, user_location.point);
                 assert_eq!(location.rotation, user_location.rotation);
+                assert_eq!(visibility.range, 2500);
+                assert!(visibility.visible_entities.is_empty());
+                assert_eq!(appearance.name, user.name);
+                assert_eq!(appearance.level, user.level);
+                assert_eq!(appearance.guild_name, "Manhunter");
+                assert_eq!(appearance.guild_rank, "Member");
+                assert_eq!(inventory.size, 48);
+                assert_eq!(inventory.money, 500);
+                assert_eq!(inventory.items.len(), 1);
+                assert_eq!(inventory.items[&4].template_id, 8005);
+                assert_eq!(inventory.equipment_preset, 1);
+                assert_eq!(inventory.equipment.len(), 1);
+                assert_eq!(inventory.equipment[&EquipmentSlot::Hand].id, 11);
 
                 Ok::<EntityId, anyhow::Error>(id)
             },
         )?;
 
+        world.run(|abnormalities: View<Abnormalities>| {
+            let abnormalities = abnormalities.try_get(connection_local_world_id)?;
+            assert_eq!(abnormalities.active.len(), 1);
+            let crystal = abnormalities.active[&4000];
+            assert_eq!(crystal.source_id, connection_local_world_id);
+            assert_eq!(crystal.stacks, 2);
+            assert_eq!(crystal.remaining, Duration::from_secs(30));
+            assert!(crystal.is_persistent);
+            assert!(abnormalities.requested.is_empty());
+
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        world.run(|experiences: View<UserExperience>| {
+            let experience = experiences.try_get(connection_local_world_id)?;
+            assert_eq!(experience.exp, 300);
+            assert_eq!(experience.rest_bonus_exp, 400);
+            assert_eq!(experience.playtime, 3600);
+            assert!(experience.requested.is_empty());
+
+            Ok::<(), anyhow::Error>(())
+        })?;
+
         match &*global_rx_channel.try_recv()? {
             Message::UserSpawnPrepared {
                 connection_global_world_id: gid,
@@ -576,6 +799,71 @@ mod tests {
         let (world, connection_local_world_id, global_rx_channel, _connection_rx_channel) =
             setup_with_spawn()?;
 
+        // Only persistent abnormalities survive the logout
+        world.run(
+            |entities: EntitiesViewMut, mut abnormalities: ViewMut<Abnormalities>| {
+                let mut active = HashMap::new();
+                for (id, is_persistent) in [(4000, true), (4100, false)].iter() {
+                    active.insert(
+                        *id,
+                        ActiveAbnormality {
+                            source_id: connection_local_world_id,
+                            stacks: 1,
+                            remaining: Duration::from_secs(20),
+                            next_period: Duration::from_secs(0),
+                            is_persistent: *is_persistent,
+                        },
+                    );
+                }
+                entities.add_component(
+                    &mut abnormalities,
+                    Abnormalities {
+                        active,
+                        requested: Vec::new(),
+                    },
+                    connection_local_world_id,
+                );
+            },
+        );
+
+        // The playtime of the spawn is added to the playtime of the user
+        world.run(
+            |entities: EntitiesViewMut,
+             mut appearances: ViewMut<UserAppearance>,
+             mut experiences: ViewMut<UserExperience>| {
+                entities.add_component(
+                    (&mut appearances, &mut experiences),
+                    (
+                        UserAppearance {
+                            name: "TestUser".to_string(),
+                            template_id: TemplateID {
+                                race: Race::Human,
+                                gender: Gender::Male,
+                                class: Class::Warrior,
+                            },
+                            level: 12,
+                            details: vec![],
+                            shape: vec![],
+                            appearance: Default::default(),
+                            appearance2: 0,
+                            show_face: false,
+                            show_style: false,
+                            guild_name: "".to_string(),
+                            guild_rank: "".to_string(),
+                        },
+                        UserExperience {
+                            exp: 300,
+                            rest_bonus_exp: 400,
+                            playtime: 3600,
+                            spawned_at: Instant::now() - Duration::from_secs(5),
+                            requested: Vec::new(),
+                        },
+                    ),
+                    connection_local_world_id,
+                );
+            },
+        );
+
         world.run(
             |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
                 entities.add_entity(
@@ -609,6 +897,24 @@ mod tests {
                     assert_eq!(user_finalizer.location.point, location.point);
                     assert_eq!(user_finalizer.location.rotation, location.rotation);
                     assert_eq!(user_finalizer.is_alive, spawn.is_alive);
+                    assert_eq!(
+                        user_finalizer.abnormalities,
+                        vec![UserAbnormality {
+                            user_id: spawn.user_id,
+                            abnormality_id: 4000,
+                            stacks: 1,
+                            remaining: 20_000,
+                        }]
+                    );
+                    assert_eq!(
+                        user_finalizer.progress,
+                        Some(UserProgress {
+                            level: 12,
+                            xp: 300,
+                            rest_bonus_xp: 400,
+                            playtime: 3605,
+                        })
+                    );
                 }
                 _ => panic!("Can't find Message::UserDespawned"),
             }
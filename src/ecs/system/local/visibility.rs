@@ -0,0 +1,762 @@
+use crate::ecs::component::{
+    LocalConnection, LocalUserSpawn, Location, Npc, UserAppearance, UserInventory, UserSpawnStatus,
+    Visibility,
+};
+use crate::ecs::dto::EquipmentLook;
+use crate::ecs::message::EcsMessage;
+use crate::ecs::message::Message::{
+    ResponseDespawnNpc, ResponseDespawnUser, ResponseSpawnNpc, ResponseSpawnUser,
+};
+use crate::ecs::resource::{DeletionList, VisibilityGrid};
+use crate::ecs::system::send_message;
+use crate::model::Angle;
+use crate::protocol::packet::*;
+use nalgebra::distance;
+use shipyard::*;
+use std::collections::HashSet;
+use tracing::trace;
+
+/// Despawn type used when an entity leaves the visibility range.
+const DESPAWN_TYPE_OUT_OF_RANGE: u32 = 1;
+
+/// Relation of NPCs to the users. Villagers are friendly, all other NPCs are monsters.
+const NPC_RELATION_VILLAGER: i32 = 12;
+const NPC_RELATION_MONSTER: i32 = 10;
+
+/// Tracks which entities each connection can see and spawns / de-spawns users and NPCs
+/// on the client when they enter or leave the visibility range.
+pub fn visibility_system(
+    connections: View<LocalConnection>,
+    user_spawns: View<LocalUserSpawn>,
+    npcs: View<Npc>,
+    locations: View<Location>,
+    appearances: View<UserAppearance>,
+    inventories: View<UserInventory>,
+    mut visibilities: ViewMut<Visibility>,
+    deletion_list: UniqueView<DeletionList>,
+    mut grid: UniqueViewMut<VisibilityGrid>,
+) {
+    update_grid(&user_spawns, &npcs, &locations, &deletion_list, &mut grid);
+
+    (&connections, &user_spawns, &locations, &mut visibilities)
+        .iter()
+        .with_id()
+        .for_each(|(id, (connection, spawn, location, visibility))| {
+            if spawn.status != UserSpawnStatus::Spawned || deletion_list.0.contains(&id) {
+                return;
+            }
+
+            let range = visibility.range as f32;
+            let mut visible_entities = HashSet::new();
+            for other_id in grid.query(&location.point, range) {
+                if other_id == id {
+                    continue;
+                }
+                if let Ok(other_location) = locations.try_get(other_id) {
+                    if distance(&location.point, &other_location.point) <= range {
+                        visible_entities.insert(other_id);
+                    }
+                }
+            }
+
+            for other_id in visible_entities.difference(&visibility.visible_entities) {
+                if let Ok((other_spawn, other_location, other_appearance)) =
+                    (&user_spawns, &locations, &appearances).try_get(*other_id)
+                {
+                    trace!("User {:?} entered the visibility range", other_id);
+                    let other_look = inventories.try_get(*other_id).map_or_else(
+                        |_| EquipmentLook::default(),
+                        |inventory| EquipmentLook::new(inventory.equipment.values()),
+                    );
+                    send_message(
+                        assemble_spawn_user(
+                            spawn.connection_global_world_id,
+                            id,
+                            *other_id,
+                            other_spawn,
+                            other_location,
+                            other_appearance,
+                            &other_look,
+                        ),
+                        &connection.channel,
+                    );
+                } else if let Ok((npc, other_location)) = (&npcs, &locations).try_get(*other_id) {
+                    trace!("NPC {:?} entered the visibility range", other_id);
+                    send_message(
+                        assemble_spawn_npc(
+                            spawn.connection_global_world_id,
+                            id,
+                            *other_id,
+                            npc,
+                            other_location,
+                        ),
+                        &connection.channel,
+                    );
+                }
+            }
+
+            for other_id in visibility.visible_entities.difference(&visible_entities) {
+                if let Ok((_npc, other_location)) = (&npcs, &locations).try_get(*other_id) {
+                    trace!("NPC {:?} left the visibility range", other_id);
+                    send_message(
+                        assemble_despawn_npc(
+                            spawn.connection_global_world_id,
+                            id,
+                            *other_id,
+                            other_location,
+                        ),
+                        &connection.channel,
+                    );
+                } else {
+                    trace!("User {:?} left the visibility range", other_id);
+                    send_message(
+                        assemble_despawn_user(spawn.connection_global_world_id, id, *other_id),
+                        &connection.channel,
+                    );
+                }
+            }
+
+            visibility.visible_entities = visible_entities;
+        });
+}
+
+/// Moves all spawned users and all NPCs into their current grid cell. Entities that are not
+/// spawned or will be deleted are removed from the grid.
+fn update_grid(
+    user_spawns: &View<LocalUserSpawn>,
+    npcs: &View<Npc>,
+    locations: &View<Location>,
+    deletion_list: &UniqueView<DeletionList>,
+    grid: &mut UniqueViewMut<VisibilityGrid>,
+) {
+    (user_spawns, locations)
+        .iter()
+        .with_id()
+        .for_each(|(id, (spawn, location))| {
+            if spawn.status == UserSpawnStatus::Spawned && !deletion_list.0.contains(&id) {
+                grid.update(id, &location.point);
+            } else {
+                grid.remove(id);
+            }
+        });
+    (npcs, locations)
+        .iter()
+        .with_id()
+        .for_each(|(id, (_npc, location))| {
+            if !deletion_list.0.contains(&id) {
+                grid.update(id, &location.point);
+            } else {
+                grid.remove(id);
+            }
+        });
+}
+
+fn assemble_spawn_user(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    user_id: EntityId,
+    spawn: &LocalUserSpawn,
+    location: &Location,
+    appearance: &UserAppearance,
+    look: &EquipmentLook,
+) -> EcsMessage {
+    Box::new(ResponseSpawnUser {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SSpawnUser {
+            servants: vec![],
+            name: appearance.name.clone(),
+            guild_name: appearance.guild_name.clone(),
+            guild_rank: appearance.guild_rank.clone(),
+            details: appearance.details.clone(),
+            guild_title: "".to_string(),
+            shape: appearance.shape.clone(),
+            guild_logo: "".to_string(),
+            server_id: 1,
+            db_id: spawn.user_id,
+            user_id,
+            location: location.point.into(),
+            rotation: Angle::from(location.rotation),
+            relation: 1,
+            template_id: appearance.template_id.clone(),
+            walk_speed: 50,
+            run_speed: 150,
+            status: 0,
+            visible: true,
+            is_alive: spawn.is_alive,
+            appearance: appearance.appearance.clone(),
+            spawn_fx: false,
+            weapon: look.weapon,
+            body: look.body,
+            hand: look.hand,
+            feet: look.feet,
+            underwear: look.underwear,
+            head: look.head,
+            face: look.face,
+            weapon_model: 0,
+            body_model: 0,
+            hand_model: 0,
+            feet_model: 0,
+            weapon_dye: 0,
+            body_dye: 0,
+            hand_dye: 0,
+            feet_dye: 0,
+            underwear_dye: 0,
+            style_back_dye: 0,
+            style_head_dye: 0,
+            style_face_dye: 0,
+            weapon_enchant: 0,
+            is_world_event_target: false,
+            infamy: 0,
+            show_face: appearance.show_face,
+            style_head: look.style_head,
+            style_face: look.style_face,
+            style_back: look.style_back,
+            style_weapon: look.style_weapon,
+            style_body: look.style_body,
+            style_footprint: look.style_footprint,
+            style_body_dye: 0,
+            show_style: appearance.show_style,
+            title: 0,
+            level: appearance.level as i16,
+            appearance2: appearance.appearance2,
+            scale: 1.0,
+            guild_logo_id: 0,
+        },
+    })
+}
+
+fn assemble_spawn_npc(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    npc_id: EntityId,
+    npc: &Npc,
+    location: &Location,
+) -> EcsMessage {
+    Box::new(ResponseSpawnNpc {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SSpawnNpc {
+            npc_id,
+            target_id: 0,
+            location: location.point.into(),
+            rotation: Angle::from(location.rotation),
+            relation: if npc.is_villager {
+                NPC_RELATION_VILLAGER
+            } else {
+                NPC_RELATION_MONSTER
+            },
+            template_id: npc.template_id,
+            hunting_zone_id: npc.hunting_zone_id as u16,
+            shape_id: 0,
+            walk_speed: npc.walk_speed,
+            run_speed: npc.run_speed,
+            status: 0,
+            mode: 0,
+            hp_level: 5,
+            quest_info: 0,
+            visible: true,
+            villager: npc.is_villager,
+            spawn_type: 1,
+            replace_id: 0,
+            spawn_script: 0,
+            replace_despawn_script: 0,
+            aggressive: false,
+            owner_id: 0,
+            occupied_by_party_id: 0,
+            occupied_by_player_id: 0,
+            bomb: false,
+            by_spawn_event: false,
+            battleground_team: 0,
+            active_cylinder: 0,
+            repairable: false,
+        },
+    })
+}
+
+fn assemble_despawn_npc(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    npc_id: EntityId,
+    location: &Location,
+) -> EcsMessage {
+    Box::new(ResponseDespawnNpc {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SDespawnNpc {
+            npc_id,
+            location: location.point.into(),
+            despawn_type: DESPAWN_TYPE_OUT_OF_RANGE,
+            unk1: 0,
+        },
+    })
+}
+
+fn assemble_despawn_user(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    user_id: EntityId,
+) -> EcsMessage {
+    Box::new(ResponseDespawnUser {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SDespawnUser {
+            user_id,
+            despawn_type: DESPAWN_TYPE_OUT_OF_RANGE,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::message::Message;
+    use crate::model::entity::EquippedItem;
+    use crate::model::{Class, Customization, EquipmentSlot, Gender, Race, TemplateID};
+    use crate::protocol::serde::from_vec;
+    use crate::Result;
+    use async_std::sync::{channel, Receiver};
+    use chrono::Utc;
+    use nalgebra::{Point3, Rotation3, Vector3};
+    use std::collections::HashMap;
+
+    fn setup() -> World {
+        let world = World::new();
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(VisibilityGrid::default());
+        world
+    }
+
+    fn add_user(
+        world: &World,
+        user_id: i32,
+        point: Point3<f32>,
+        status: UserSpawnStatus,
+    ) -> (EntityId, Receiver<EcsMessage>) {
+        let (connection_tx_channel, connection_rx_channel) = channel(1024);
+
+        let connection_local_world_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut connections: ViewMut<LocalConnection>,
+             mut user_spawns: ViewMut<LocalUserSpawn>,
+             mut locations: ViewMut<Location>,
+             mut appearances: ViewMut<UserAppearance>,
+             mut visibilities: ViewMut<Visibility>| {
+                entities.add_entity(
+                    (
+                        &mut connections,
+                        &mut user_spawns,
+                        &mut locations,
+                        &mut appearances,
+                        &mut visibilities,
+                    ),
+                    (
+                        LocalConnection {
+                            channel: connection_tx_channel,
+                        },
+                        LocalUserSpawn {
+                            user_id,
+                            account_id: 1,
+                            status,
+                            zone_id: 0,
+                            connection_global_world_id: from_vec::<EntityId>(vec![
+                                user_id as u8,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                                0x00,
+                            ])
+                            .unwrap(),
+                            is_alive: true,
+                        },
+                        Location {
+                            point,
+                            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+                        },
+                        UserAppearance {
+                            name: format!("User{}", user_id),
+                            template_id: TemplateID {
+                                race: Race::Human,
+                                gender: Gender::Female,
+                                class: Class::Priest,
+                            },
+                            level: 65,
+                            details: vec![],
+                            shape: vec![],
+                            appearance: Customization::default(),
+                            appearance2: 100,
+                            show_face: true,
+                            show_style: true,
+                            guild_name: "".to_string(),
+                            guild_rank: "".to_string(),
+                        },
+                        Visibility {
+                            range: 2000,
+                            visible_entities: HashSet::new(),
+                        },
+                    ),
+                )
+            },
+        );
+
+        (connection_local_world_id, connection_rx_channel)
+    }
+
+    fn set_point(world: &World, entity_id: EntityId, point: Point3<f32>) {
+        world.run(|mut locations: ViewMut<Location>| {
+            (&mut locations).try_get(entity_id).unwrap().point = point;
+        });
+    }
+
+    fn assert_spawn_user(message: EcsMessage, expected_user_id: EntityId, name: &str) {
+        match &*message {
+            Message::ResponseSpawnUser { packet, .. } => {
+                assert_eq!(packet.user_id, expected_user_id);
+                assert_eq!(packet.name, name);
+                assert_eq!(packet.level, 65);
+            }
+            _ => panic!("Message is not a ResponseSpawnUser message"),
+        }
+    }
+
+    fn assert_despawn_user(message: EcsMessage, expected_user_id: EntityId) {
+        match &*message {
+            Message::ResponseDespawnUser { packet, .. } => {
+                assert_eq!(packet.user_id, expected_user_id);
+                assert_eq!(packet.despawn_type, DESPAWN_TYPE_OUT_OF_RANGE);
+            }
+            _ => panic!("Message is not a ResponseDespawnUser message"),
+        }
+    }
+
+    #[test]
+    fn test_users_enter_range() -> Result<()> {
+        let world = setup();
+        let (user1_id, rx_channel1) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let (user2_id, rx_channel2) = add_user(
+            &world,
+            2,
+            Point3::new(1500.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let (_user3_id, rx_channel3) = add_user(
+            &world,
+            3,
+            Point3::new(5000.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+
+        world.run(visibility_system);
+
+        assert_spawn_user(rx_channel1.try_recv()?, user2_id, "User2");
+        assert_spawn_user(rx_channel2.try_recv()?, user1_id, "User1");
+        assert!(rx_channel1.is_empty());
+        assert!(rx_channel2.is_empty());
+        assert!(rx_channel3.is_empty());
+
+        world.run(|visibilities: View<Visibility>| {
+            let visibility = visibilities.try_get(user1_id)?;
+            assert_eq!(visibility.visible_entities.len(), 1);
+            assert!(visibility.visible_entities.contains(&user2_id));
+
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        // Users that are already visible are not spawned again
+        world.run(visibility_system);
+        assert!(rx_channel1.is_empty());
+        assert!(rx_channel2.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spawned_user_shows_equipment() -> Result<()> {
+        let world = setup();
+        let (_user1_id, rx_channel1) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let (user2_id, _rx_channel2) = add_user(
+            &world,
+            2,
+            Point3::new(1500.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+
+        world.run(
+            |entities: EntitiesView, mut inventories: ViewMut<UserInventory>| {
+                let mut equipment = HashMap::new();
+                for (id, slot, template_id) in &[
+                    (1, EquipmentSlot::Weapon, 10001),
+                    (2, EquipmentSlot::StyleBack, 98100),
+                ] {
+                    equipment.insert(
+                        *slot,
+                        EquippedItem {
+                            id: *id,
+                            user_id: 2,
+                            preset: 0,
+                            slot: *slot,
+                            template_id: *template_id,
+                            created_at: Utc::now(),
+                        },
+                    );
+                }
+                entities.add_component(
+                    &mut inventories,
+                    UserInventory {
+                        size: 40,
+                        money: 0,
+                        items: HashMap::new(),
+                        equipment_preset: 0,
+                        equipment,
+                    },
+                    user2_id,
+                );
+            },
+        );
+
+        world.run(visibility_system);
+
+        match &*rx_channel1.try_recv()? {
+            Message::ResponseSpawnUser { packet, .. } => {
+                assert_eq!(packet.user_id, user2_id);
+                assert_eq!(packet.weapon, 10001);
+                assert_eq!(packet.style_back, 98100);
+                assert_eq!(packet.body, 0);
+            }
+            _ => panic!("Message is not a ResponseSpawnUser message"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_leaves_range() -> Result<()> {
+        let world = setup();
+        let (user1_id, rx_channel1) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let (user2_id, rx_channel2) = add_user(
+            &world,
+            2,
+            Point3::new(0.0, 1500.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+
+        world.run(visibility_system);
+        rx_channel1.try_recv()?;
+        rx_channel2.try_recv()?;
+
+        set_point(&world, user2_id, Point3::new(0.0, 2500.0, 0.0));
+        world.run(visibility_system);
+
+        assert_despawn_user(rx_channel1.try_recv()?, user2_id);
+        assert_despawn_user(rx_channel2.try_recv()?, user1_id);
+
+        // Entering the range again spawns the user again
+        set_point(&world, user2_id, Point3::new(0.0, -1999.0, 0.0));
+        world.run(visibility_system);
+
+        assert_spawn_user(rx_channel1.try_recv()?, user2_id, "User2");
+        assert_spawn_user(rx_channel2.try_recv()?, user1_id, "User1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_visibility_range_per_user() -> Result<()> {
+        let world = setup();
+        let (_user1_id, rx_channel1) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let (user2_id, rx_channel2) = add_user(
+            &world,
+            2,
+            Point3::new(1500.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+
+        world.run(|mut visibilities: ViewMut<Visibility>| {
+            (&mut visibilities).try_get(user2_id).unwrap().range = 1000;
+        });
+        world.run(visibility_system);
+
+        assert_spawn_user(rx_channel1.try_recv()?, user2_id, "User2");
+        assert!(rx_channel2.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_spawned_user() -> Result<()> {
+        let world = setup();
+        let (_user1_id, rx_channel1) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let (user2_id, rx_channel2) = add_user(
+            &world,
+            2,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::CanSpawn,
+        );
+
+        world.run(visibility_system);
+
+        assert!(rx_channel1.is_empty());
+        assert!(rx_channel2.is_empty());
+        assert!(!world
+            .borrow::<UniqueView<VisibilityGrid>>()
+            .contains(user2_id));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_despawned_user() -> Result<()> {
+        let world = setup();
+        let (_user1_id, rx_channel1) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let (user2_id, rx_channel2) = add_user(
+            &world,
+            2,
+            Point3::new(100.0, 100.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+
+        world.run(visibility_system);
+        rx_channel1.try_recv()?;
+        rx_channel2.try_recv()?;
+
+        world.run(|mut deletion_list: UniqueViewMut<DeletionList>| {
+            deletion_list.0.push(user2_id);
+        });
+        world.run(visibility_system);
+
+        assert_despawn_user(rx_channel1.try_recv()?, user2_id);
+        assert!(rx_channel2.is_empty());
+        assert_eq!(world.borrow::<UniqueView<VisibilityGrid>>().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_npcs_enter_and_leave_range() -> Result<()> {
+        let world = setup();
+        let (_user_id, rx_channel) = add_user(
+            &world,
+            1,
+            Point3::new(0.0, 0.0, 0.0),
+            UserSpawnStatus::Spawned,
+        );
+        let npc_id = world.run(
+            |mut entities: EntitiesViewMut,
+             mut npcs: ViewMut<Npc>,
+             mut locations: ViewMut<Location>| {
+                let spawn_id = entities.add_entity((), ());
+                entities.add_entity(
+                    (&mut npcs, &mut locations),
+                    (
+                        Npc {
+                            hunting_zone_id: 13,
+                            template_id: 2001,
+                            spawn_id,
+                            walk_speed: 50,
+                            run_speed: 150,
+                            is_villager: true,
+                            is_alive: true,
+                        },
+                        Location {
+                            point: Point3::new(500.0, 0.0, 0.0),
+                            rotation: Rotation3::from_axis_angle(&Vector3::z_axis(), 0.0),
+                        },
+                    ),
+                )
+            },
+        );
+
+        world.run(visibility_system);
+        match &*rx_channel.try_recv()? {
+            Message::ResponseSpawnNpc { packet, .. } => {
+                assert_eq!(packet.npc_id, npc_id);
+                assert_eq!(packet.template_id, 2001);
+                assert_eq!(packet.hunting_zone_id, 13);
+                assert_eq!(packet.relation, NPC_RELATION_VILLAGER);
+                assert!(packet.villager);
+            }
+            _ => panic!("Message is not a ResponseSpawnNpc message"),
+        }
+        assert!(rx_channel.is_empty());
+
+        // NPCs that will be deleted are de-spawned
+        world.run(|mut deletion_list: UniqueViewMut<DeletionList>| {
+            deletion_list.0.push(npc_id);
+        });
+        world.run(visibility_system);
+        match &*rx_channel.try_recv()? {
+            Message::ResponseDespawnNpc { packet, .. } => {
+                assert_eq!(packet.npc_id, npc_id);
+                assert_eq!(packet.despawn_type, DESPAWN_TYPE_OUT_OF_RANGE);
+            }
+            _ => panic!("Message is not a ResponseDespawnNpc message"),
+        }
+        assert!(!world
+            .borrow::<UniqueView<VisibilityGrid>>()
+            .contains(npc_id));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_visibility_grid() {
+        let world = setup();
+        let mut grid = world.borrow::<UniqueViewMut<VisibilityGrid>>();
+        let ids: Vec<EntityId> = (1..=3u8)
+            .map(|i| from_vec::<EntityId>(vec![i, 0, 0, 0, 0, 0, 0, 0]).unwrap())
+            .collect();
+
+        grid.update(ids[0], &Point3::new(0.0, 0.0, 0.0));
+        grid.update(ids[1], &Point3::new(-2500.0, 500.0, 0.0));
+        grid.update(ids[2], &Point3::new(10000.0, 10000.0, 0.0));
+
+        let near = grid.query(&Point3::new(0.0, 0.0, 0.0), 2000.0);
+        assert_eq!(near.len(), 1);
+        assert!(near.contains(&ids[0]));
+
+        // Moving an entity moves it into the new cell
+        grid.update(ids[2], &Point3::new(500.0, 500.0, 0.0));
+        let near = grid.query(&Point3::new(0.0, 0.0, 0.0), 2000.0);
+        assert_eq!(near.len(), 2);
+        assert!(near.contains(&ids[2]));
+        assert_eq!(grid.len(), 3);
+
+        grid.remove(ids[0]);
+        let near = grid.query(&Point3::new(0.0, 0.0, 0.0), 2000.0);
+        assert_eq!(near, vec![ids[2]]);
+        assert_eq!(grid.len(), 2);
+    }
+}
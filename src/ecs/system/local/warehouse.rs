@@ -0,0 +1,1233 @@
+use crate::ecs::component::{LocalConnection, LocalUserSpawn, UserInventory, UserWarehouse};
+use crate::ecs::message::Message::ResponseViewWareEx;
+use crate::ecs::message::{EcsMessage, Message};
+use crate::ecs::resource::ItemRegistry;
+use crate::ecs::system::local::{assemble_itemlist, max_stack, send_message_to_connection};
+use crate::model::entity::{Inventory, Item, Warehouse, WarehouseItem};
+use crate::model::repository::{inventory, item, warehouse, warehouse_item};
+use crate::protocol::packet::*;
+use crate::Result;
+use anyhow::{bail, ensure, Context};
+use async_std::task;
+use shipyard::*;
+use sqlx::PgPool;
+use std::cmp::min;
+use tracing::{debug, error, info_span};
+
+/// Container ID of the warehouse of an user inside the network protocol.
+const USER_WAREHOUSE: i32 = 1;
+/// Container ID of the warehouse that is shared by all users of an account.
+const ACCOUNT_WAREHOUSE: i32 = 9;
+/// Number of slots that are shown on one page of a warehouse.
+const WAREHOUSE_PAGE_SIZE: i32 = 72;
+/// Money an user needs to pay once per spawn to use the warehouse of it's account.
+const WAREHOUSE_COMMISSION: i64 = 100;
+
+/// Opens the shown page of the warehouse window.
+const VIEW_ACTION_OPEN: i32 = 0;
+/// Refreshes the shown page of the warehouse window.
+const VIEW_ACTION_REFRESH: i32 = 1;
+
+/// Handles the warehouses of the users. Items are moved between the inventory and a warehouse
+/// inside one transaction, so that they are never duplicated or lost. A moved stack keeps it's ID
+/// unless it's split.
+pub fn warehouse_system(
+    incoming_messages: View<EcsMessage>,
+    connections: View<LocalConnection>,
+    user_spawns: View<LocalUserSpawn>,
+    mut inventories: ViewMut<UserInventory>,
+    mut warehouses: ViewMut<UserWarehouse>,
+    entities: EntitiesView,
+    item_registry: UniqueView<ItemRegistry>,
+    pool: UniqueView<PgPool>,
+) {
+    (&incoming_messages)
+        .iter()
+        .for_each(|message| match &**message {
+            Message::RequestViewWare {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_view_ware(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &pool,
+                ) {
+                    error!("Ignoring view warehouse request: {:?}", e);
+                }
+            }
+            Message::RequestPutWareItem {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_put_ware_item(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &mut inventories,
+                    &warehouses,
+                    &item_registry,
+                    &pool,
+                ) {
+                    error!("Ignoring put warehouse item request: {:?}", e);
+                }
+            }
+            Message::RequestGetWareItem {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_get_ware_item(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &mut inventories,
+                    &warehouses,
+                    &item_registry,
+                    &pool,
+                ) {
+                    error!("Ignoring get warehouse item request: {:?}", e);
+                }
+            }
+            Message::RequestMoveWarePos {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_move_ware_pos(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &item_registry,
+                    &pool,
+                ) {
+                    error!("Ignoring move warehouse position request: {:?}", e);
+                }
+            }
+            Message::RequestPayWarehouseCommision {
+                connection_global_world_id,
+                connection_local_world_id,
+                packet,
+            } => {
+                id_span!(connection_global_world_id);
+                if let Err(e) = handle_pay_warehouse_commision(
+                    *connection_global_world_id,
+                    *connection_local_world_id,
+                    &packet,
+                    &connections,
+                    &user_spawns,
+                    &mut inventories,
+                    &mut warehouses,
+                    &entities,
+                    &pool,
+                ) {
+                    error!("Ignoring pay warehouse commission request: {:?}", e);
+                }
+            }
+            _ => { /* Ignore all other messages */ }
+        });
+}
+
+fn handle_view_ware(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CViewWare,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestViewWare incoming");
+
+    let spawn = user_spawns
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find user spawn of {:?}",
+            connection_local_world_id
+        ))?;
+    let owner = warehouse_owner(packet.container, spawn)?;
+
+    let (warehouse, items) = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let warehouse = warehouse::get_or_create(&mut conn, spawn.account_id, owner).await?;
+        let items = warehouse_item::list_by_warehouse_id(&mut conn, warehouse.id).await?;
+
+        conn.commit().await?;
+
+        Ok::<(Warehouse, Vec<WarehouseItem>), anyhow::Error>((warehouse, items))
+    })?;
+    check_offset(&warehouse, packet.offset)?;
+
+    send_message_to_connection(
+        assemble_view_ware_ex(
+            connection_global_world_id,
+            connection_local_world_id,
+            packet.container,
+            VIEW_ACTION_OPEN,
+            packet.offset,
+            &warehouse,
+            &items,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_put_ware_item(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CPutWareItem,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &mut ViewMut<UserInventory>,
+    warehouses: &ViewMut<UserWarehouse>,
+    item_registry: &ItemRegistry,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestPutWareItem incoming");
+
+    let (spawn, inventory) = (user_spawns, inventories)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    let owner = warehouse_owner(packet.container, spawn)?;
+    check_commission(packet.container, connection_local_world_id, warehouses)?;
+
+    let item = inventory
+        .items
+        .get(&packet.inventory_slot)
+        .context(format!("No item found in slot {}", packet.inventory_slot))?
+        .clone();
+    ensure!(
+        item.id == packet.db_id,
+        "Item {} is not inside slot {}",
+        packet.db_id,
+        packet.inventory_slot
+    );
+    ensure!(
+        packet.amount > 0 && packet.amount <= item.amount,
+        "Can't move {} of {} items",
+        packet.amount,
+        item.amount
+    );
+
+    let (warehouse, items, amount) = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let warehouse = warehouse::get_or_create(&mut conn, spawn.account_id, owner).await?;
+        check_slot(&warehouse, packet.offset, packet.warehouse_slot)?;
+        let target = warehouse_item::list_by_warehouse_id(&mut conn, warehouse.id)
+            .await?
+            .into_iter()
+            .find(|target| target.slot == packet.warehouse_slot);
+        let amount = transferable_amount(
+            item_registry,
+            item.template_id,
+            packet.amount,
+            target
+                .as_ref()
+                .map(|target| (target.template_id, target.amount)),
+        )?;
+
+        if amount == item.amount {
+            item::delete(&mut conn, item.id).await?;
+        } else {
+            item::update(
+                &mut conn,
+                &Item {
+                    amount: item.amount - amount,
+                    ..item.clone()
+                },
+            )
+            .await?;
+        }
+
+        match target {
+            Some(mut target) => {
+                target.amount += amount;
+                warehouse_item::update(&mut conn, &target).await?;
+            }
+            None => {
+                let new_item = WarehouseItem {
+                    id: item.id,
+                    warehouse_id: warehouse.id,
+                    template_id: item.template_id,
+                    slot: packet.warehouse_slot,
+                    amount,
+                    created_at: item.created_at,
+                };
+                if amount == item.amount {
+                    warehouse_item::restore(&mut conn, &new_item).await?;
+                } else {
+                    warehouse_item::create(&mut conn, &new_item).await?;
+                }
+            }
+        }
+
+        let items = warehouse_item::list_by_warehouse_id(&mut conn, warehouse.id).await?;
+
+        conn.commit().await?;
+
+        Ok::<(Warehouse, Vec<WarehouseItem>, i32), anyhow::Error>((warehouse, items, amount))
+    })?;
+
+    if amount == item.amount {
+        inventory.items.remove(&item.slot);
+    } else if let Some(stack) = inventory.items.get_mut(&item.slot) {
+        stack.amount -= amount;
+    }
+
+    send_message_to_connection(
+        assemble_itemlist(
+            connection_global_world_id,
+            connection_local_world_id,
+            &inventory,
+            false,
+        ),
+        connections,
+    );
+    send_message_to_connection(
+        assemble_view_ware_ex(
+            connection_global_world_id,
+            connection_local_world_id,
+            packet.container,
+            VIEW_ACTION_REFRESH,
+            packet.offset,
+            &warehouse,
+            &items,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_get_ware_item(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CGetWareItem,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &mut ViewMut<UserInventory>,
+    warehouses: &ViewMut<UserWarehouse>,
+    item_registry: &ItemRegistry,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestGetWareItem incoming");
+
+    let (spawn, inventory) = (user_spawns, inventories)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    let owner = warehouse_owner(packet.container, spawn)?;
+    check_commission(packet.container, connection_local_world_id, warehouses)?;
+    ensure!(
+        packet.inventory_slot >= 0 && packet.inventory_slot < inventory.size,
+        "Slot {} is outside of the inventory",
+        packet.inventory_slot
+    );
+    let target = inventory.items.get(&packet.inventory_slot).cloned();
+
+    let (warehouse, items, moved_item) = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let warehouse = warehouse::get_or_create(&mut conn, spawn.account_id, owner).await?;
+        check_slot(&warehouse, packet.offset, packet.warehouse_slot)?;
+        let ware_item = warehouse_item::list_by_warehouse_id(&mut conn, warehouse.id)
+            .await?
+            .into_iter()
+            .find(|ware_item| ware_item.slot == packet.warehouse_slot)
+            .context(format!(
+                "No item found in warehouse slot {}",
+                packet.warehouse_slot
+            ))?;
+        ensure!(
+            ware_item.id == packet.db_id,
+            "Item {} is not inside warehouse slot {}",
+            packet.db_id,
+            packet.warehouse_slot
+        );
+        ensure!(
+            packet.amount > 0 && packet.amount <= ware_item.amount,
+            "Can't move {} of {} items",
+            packet.amount,
+            ware_item.amount
+        );
+        let amount = transferable_amount(
+            item_registry,
+            ware_item.template_id,
+            packet.amount,
+            target
+                .as_ref()
+                .map(|target| (target.template_id, target.amount)),
+        )?;
+
+        if amount == ware_item.amount {
+            warehouse_item::delete(&mut conn, ware_item.id).await?;
+        } else {
+            warehouse_item::update(
+                &mut conn,
+                &WarehouseItem {
+                    amount: ware_item.amount - amount,
+                    ..ware_item.clone()
+                },
+            )
+            .await?;
+        }
+
+        let moved_item = match target {
+            Some(mut target) => {
+                target.amount += amount;
+                item::update(&mut conn, &target).await?
+            }
+            None => {
+                let new_item = Item {
+                    id: ware_item.id,
+                    user_id: spawn.user_id,
+                    template_id: ware_item.template_id,
+                    slot: packet.inventory_slot,
+                    amount,
+                    created_at: ware_item.created_at,
+                };
+                if amount == ware_item.amount {
+                    item::restore(&mut conn, &new_item).await?
+                } else {
+                    item::create(&mut conn, &new_item).await?
+                }
+            }
+        };
+
+        let items = warehouse_item::list_by_warehouse_id(&mut conn, warehouse.id).await?;
+
+        conn.commit().await?;
+
+        Ok::<(Warehouse, Vec<WarehouseItem>, Item), anyhow::Error>((warehouse, items, moved_item))
+    })?;
+
+    inventory.items.insert(moved_item.slot, moved_item);
+
+    send_message_to_connection(
+        assemble_itemlist(
+            connection_global_world_id,
+            connection_local_world_id,
+            &inventory,
+            false,
+        ),
+        connections,
+    );
+    send_message_to_connection(
+        assemble_view_ware_ex(
+            connection_global_world_id,
+            connection_local_world_id,
+            packet.container,
+            VIEW_ACTION_REFRESH,
+            packet.offset,
+            &warehouse,
+            &items,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_move_ware_pos(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CMoveWarePos,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    item_registry: &ItemRegistry,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestMoveWarePos incoming");
+
+    let spawn = user_spawns
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find user spawn of {:?}",
+            connection_local_world_id
+        ))?;
+    let owner = warehouse_owner(packet.container, spawn)?;
+    ensure!(
+        packet.src_slot != packet.dst_slot,
+        "Can't move an item onto itself"
+    );
+
+    let (warehouse, items) = task::block_on(async {
+        let mut conn = pool
+            .begin()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+
+        let warehouse = warehouse::get_or_create(&mut conn, spawn.account_id, owner).await?;
+        check_slot(&warehouse, packet.offset, packet.src_slot)?;
+        check_slot(&warehouse, packet.offset, packet.dst_slot)?;
+        let items = warehouse_item::list_by_warehouse_id(&mut conn, warehouse.id).await?;
+        let mut item = items
+            .iter()
+            .find(|item| item.slot == packet.src_slot)
+            .cloned()
+            .context(format!(
+                "No item found in warehouse slot {}",
+                packet.src_slot
+            ))?;
+
+        match items
+            .iter()
+            .find(|item| item.slot == packet.dst_slot)
+            .cloned()
+        {
+            None => {
+                item.slot = packet.dst_slot;
+                warehouse_item::update(&mut conn, &item).await?;
+            }
+            Some(mut other_item)
+                if other_item.template_id == item.template_id
+                    && other_item.amount < max_stack(item_registry, item.template_id) =>
+            {
+                // Fill up the stack of the destination slot.
+                let amount = min(
+                    max_stack(item_registry, item.template_id) - other_item.amount,
+                    item.amount,
+                );
+                other_item.amount += amount;
+                item.amount -= amount;
+                warehouse_item::update(&mut conn, &other_item).await?;
+                if item.amount == 0 {
+                    warehouse_item::delete(&mut conn, item.id).await?;
+                } else {
+                    warehouse_item::update(&mut conn, &item).await?;
+                }
+            }
+            Some(mut other_item) => {
+                other_item.slot = item.slot;
+                item.slot = packet.dst_slot;
+                warehouse_item::update(&mut conn, &item).await?;
+                warehouse_item::update(&mut conn, &other_item).await?;
+            }
+        }
+
+        let items = warehouse_item::list_by_warehouse_id(&mut conn, warehouse.id).await?;
+
+        conn.commit().await?;
+
+        Ok::<(Warehouse, Vec<WarehouseItem>), anyhow::Error>((warehouse, items))
+    })?;
+
+    send_message_to_connection(
+        assemble_view_ware_ex(
+            connection_global_world_id,
+            connection_local_world_id,
+            packet.container,
+            VIEW_ACTION_REFRESH,
+            packet.offset,
+            &warehouse,
+            &items,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+fn handle_pay_warehouse_commision(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    packet: &CPayWarehouseCommision,
+    connections: &View<LocalConnection>,
+    user_spawns: &View<LocalUserSpawn>,
+    inventories: &mut ViewMut<UserInventory>,
+    warehouses: &mut ViewMut<UserWarehouse>,
+    entities: &EntitiesView,
+    pool: &UniqueView<PgPool>,
+) -> Result<()> {
+    debug!("Message::RequestPayWarehouseCommision incoming");
+
+    ensure!(
+        packet.container == ACCOUNT_WAREHOUSE,
+        "Container {} doesn't need a commission",
+        packet.container
+    );
+    let commission_paid = warehouses
+        .try_get(connection_local_world_id)
+        .map_or(false, |warehouse| warehouse.commission_paid);
+    ensure!(!commission_paid, "Warehouse commission was already paid");
+
+    let (spawn, inventory) = (user_spawns, inventories)
+        .try_get(connection_local_world_id)
+        .context(format!(
+            "Can't find inventory of {:?}",
+            connection_local_world_id
+        ))?;
+    ensure!(
+        inventory.money >= WAREHOUSE_COMMISSION,
+        "User {} can't pay the warehouse commission",
+        spawn.user_id
+    );
+    let money = inventory.money - WAREHOUSE_COMMISSION;
+
+    task::block_on(async {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Couldn't acquire connection from pool")?;
+        inventory::update(
+            &mut conn,
+            &Inventory {
+                user_id: spawn.user_id,
+                size: inventory.size,
+                money,
+                equipment_preset: inventory.equipment_preset,
+            },
+        )
+        .await?;
+        Ok::<(), anyhow::Error>(())
+    })?;
+    inventory.money = money;
+
+    let user_warehouse = UserWarehouse {
+        commission_paid: true,
+    };
+    if let Ok(current_warehouse) = warehouses.try_get(connection_local_world_id) {
+        *current_warehouse = user_warehouse;
+    } else {
+        entities.add_component(warehouses, user_warehouse, connection_local_world_id);
+    }
+
+    send_message_to_connection(
+        assemble_itemlist(
+            connection_global_world_id,
+            connection_local_world_id,
+            &inventory,
+            false,
+        ),
+        connections,
+    );
+
+    Ok(())
+}
+
+/// Returns the user ID of the warehouse owner. The warehouse of the account has no owner.
+fn warehouse_owner(container: i32, spawn: &LocalUserSpawn) -> Result<Option<i32>> {
+    match container {
+        USER_WAREHOUSE => Ok(Some(spawn.user_id)),
+        ACCOUNT_WAREHOUSE => Ok(None),
+        _ => bail!("Unsupported container {}", container),
+    }
+}
+
+/// Only the warehouse of the account needs a commission.
+fn check_commission(
+    container: i32,
+    connection_local_world_id: EntityId,
+    warehouses: &ViewMut<UserWarehouse>,
+) -> Result<()> {
+    if container == ACCOUNT_WAREHOUSE {
+        ensure!(
+            warehouses
+                .try_get(connection_local_world_id)
+                .map_or(false, |warehouse| warehouse.commission_paid),
+            "Warehouse commission wasn't paid"
+        );
+    }
+    Ok(())
+}
+
+fn check_offset(warehouse: &Warehouse, offset: i32) -> Result<()> {
+    ensure!(
+        offset >= 0 && offset < warehouse.size && offset % WAREHOUSE_PAGE_SIZE == 0,
+        "Warehouse has no page at offset {}",
+        offset
+    );
+    Ok(())
+}
+
+/// Items can only be moved inside the shown page.
+fn check_slot(warehouse: &Warehouse, offset: i32, slot: i32) -> Result<()> {
+    check_offset(warehouse, offset)?;
+    ensure!(
+        slot >= offset && slot < offset + WAREHOUSE_PAGE_SIZE && slot < warehouse.size,
+        "Slot {} is outside of the shown page",
+        slot
+    );
+    Ok(())
+}
+
+/// Returns how many items of a stack can be moved into a slot that holds the given target
+/// stack (template ID, amount). Stacks of the same template are filled up.
+fn transferable_amount(
+    item_registry: &ItemRegistry,
+    template_id: i32,
+    amount: i32,
+    target: Option<(i32, i32)>,
+) -> Result<i32> {
+    match target {
+        None => Ok(amount),
+        Some((target_template_id, target_amount)) if target_template_id == template_id => {
+            let free = max_stack(item_registry, template_id) - target_amount;
+            ensure!(free > 0, "Stack of the target slot is full");
+            Ok(min(free, amount))
+        }
+        Some(_) => bail!("Target slot is occupied by an other item"),
+    }
+}
+
+fn assemble_view_ware_ex(
+    connection_global_world_id: EntityId,
+    connection_local_world_id: EntityId,
+    container: i32,
+    action: i32,
+    offset: i32,
+    warehouse: &Warehouse,
+    items: &[WarehouseItem],
+) -> EcsMessage {
+    let items = items
+        .iter()
+        .filter(|item| item.slot >= offset && item.slot < offset + WAREHOUSE_PAGE_SIZE)
+        .map(|item| SViewWareExItem {
+            id: item.template_id,
+            db_id: item.id,
+            slot: item.slot,
+            amount: item.amount,
+            enchantment: 0,
+            soulbound: false,
+        })
+        .collect();
+
+    Box::new(ResponseViewWareEx {
+        connection_global_world_id,
+        connection_local_world_id,
+        packet: SViewWareEx {
+            items,
+            game_id: connection_local_world_id,
+            container,
+            action,
+            offset,
+            size: warehouse.size,
+            money: 0,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::resource::{DeletionList, ItemTemplate};
+    use crate::ecs::system::common::cleaner_system;
+    use crate::ecs::system::local::tests::{add_user, TestUser};
+    use crate::model::tests::db_test;
+
+    const POTION: i32 = 8005;
+    const WEAPON: i32 = 10001;
+
+    fn setup(pool: PgPool) -> World {
+        let world = World::new();
+        world.add_unique(pool);
+        world.add_unique(DeletionList(Vec::default()));
+        world.add_unique(ItemRegistry::new(vec![
+            ItemTemplate {
+                id: POTION,
+                max_stack: 50,
+                destroyable: true,
+                ..Default::default()
+            },
+            ItemTemplate {
+                id: WEAPON,
+                max_stack: 1,
+                destroyable: false,
+                ..Default::default()
+            },
+        ]));
+        world
+    }
+
+    fn run_message(world: &World, message: Message) {
+        world.run(
+            |mut entities: EntitiesViewMut, mut messages: ViewMut<EcsMessage>| {
+                entities.add_entity(&mut messages, Box::new(message));
+            },
+        );
+        world.run(warehouse_system);
+        world.run(cleaner_system);
+    }
+
+    fn view_ware(world: &World, user: &TestUser, container: i32, offset: i32) {
+        run_message(
+            world,
+            Message::RequestViewWare {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CViewWare {
+                    game_id: user.connection_local_world_id,
+                    container,
+                    offset,
+                },
+            },
+        );
+    }
+
+    fn put_item(
+        world: &World,
+        user: &TestUser,
+        container: i32,
+        inventory_slot: i32,
+        amount: i32,
+        warehouse_slot: i32,
+    ) {
+        let (id, db_id) = world.run(|inventories: View<UserInventory>| {
+            let inventory = inventories.try_get(user.connection_local_world_id).unwrap();
+            inventory
+                .items
+                .get(&inventory_slot)
+                .map_or((0, 0), |item| (item.template_id, item.id))
+        });
+        run_message(
+            world,
+            Message::RequestPutWareItem {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CPutWareItem {
+                    game_id: user.connection_local_world_id,
+                    container,
+                    offset: 0,
+                    money: 0,
+                    inventory_slot,
+                    id,
+                    db_id,
+                    amount,
+                    warehouse_slot,
+                },
+            },
+        );
+    }
+
+    fn get_item(
+        world: &World,
+        pool: &PgPool,
+        user: &TestUser,
+        container: i32,
+        warehouse_slot: i32,
+        amount: i32,
+        inventory_slot: i32,
+    ) -> Result<()> {
+        let (id, db_id) = list_warehouse_items(pool, user, container)?
+            .into_iter()
+            .find(|item| item.slot == warehouse_slot)
+            .map_or((0, 0), |item| (item.template_id, item.id));
+        run_message(
+            world,
+            Message::RequestGetWareItem {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CGetWareItem {
+                    game_id: user.connection_local_world_id,
+                    container,
+                    offset: 0,
+                    money: 0,
+                    warehouse_slot,
+                    db_id,
+                    id,
+                    amount,
+                    inventory_slot,
+                },
+            },
+        );
+        Ok(())
+    }
+
+    fn move_item(world: &World, user: &TestUser, container: i32, src_slot: i32, dst_slot: i32) {
+        run_message(
+            world,
+            Message::RequestMoveWarePos {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CMoveWarePos {
+                    game_id: user.connection_local_world_id,
+                    container,
+                    offset: 0,
+                    src_slot,
+                    dst_slot,
+                },
+            },
+        );
+    }
+
+    fn pay_commission(world: &World, user: &TestUser, container: i32) {
+        run_message(
+            world,
+            Message::RequestPayWarehouseCommision {
+                connection_global_world_id: user.connection_global_world_id,
+                connection_local_world_id: user.connection_local_world_id,
+                packet: CPayWarehouseCommision {
+                    game_id: user.connection_local_world_id,
+                    container,
+                },
+            },
+        );
+    }
+
+    fn list_warehouse_items(
+        pool: &PgPool,
+        user: &TestUser,
+        container: i32,
+    ) -> Result<Vec<WarehouseItem>> {
+        let owner = match container {
+            USER_WAREHOUSE => Some(user.user.id),
+            _ => None,
+        };
+        task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            let warehouse =
+                warehouse::get_or_create(&mut conn, user.user.account_id, owner).await?;
+            warehouse_item::list_by_warehouse_id(&mut conn, warehouse.id).await
+        })
+    }
+
+    fn assert_itemlist(user: &TestUser) -> Result<SItemlist> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseItemlist { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseItemlist message"),
+        }
+    }
+
+    fn assert_view_ware_ex(user: &TestUser) -> Result<SViewWareEx> {
+        match &*user.rx.try_recv()? {
+            Message::ResponseViewWareEx { packet, .. } => Ok(packet.clone()),
+            _ => panic!("Message is not a ResponseViewWareEx message"),
+        }
+    }
+
+    /// Items of the inventory and of a warehouse as (template ID, slot, amount).
+    type Persisted = (Vec<(i32, i32, i32)>, Vec<(i32, i32, i32)>);
+
+    /// Checks that the inventory component matches the database and returns the items of the
+    /// inventory and the given warehouse ordered by their slot.
+    fn assert_persisted(
+        world: &World,
+        pool: &PgPool,
+        user: &TestUser,
+        container: i32,
+    ) -> Result<Persisted> {
+        let db_items = task::block_on(async {
+            let mut conn = pool.acquire().await?;
+            item::list_by_user_id(&mut conn, user.user.id).await
+        })?;
+
+        world.run(|inventories: View<UserInventory>| {
+            let inventory = inventories.try_get(user.connection_local_world_id).unwrap();
+            let mut items = inventory.items.values().cloned().collect::<Vec<Item>>();
+            items.sort_by_key(|item| item.slot);
+            assert_eq!(items, db_items);
+        });
+
+        Ok((
+            db_items
+                .iter()
+                .map(|item| (item.template_id, item.slot, item.amount))
+                .collect(),
+            list_warehouse_items(pool, user, container)?
+                .iter()
+                .map(|item| (item.template_id, item.slot, item.amount))
+                .collect(),
+        ))
+    }
+
+    #[test]
+    fn test_view_ware() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 1, &[])?;
+
+            view_ware(&world, &user, USER_WAREHOUSE, 0);
+            let packet = assert_view_ware_ex(&user)?;
+            assert_eq!(packet.game_id, user.connection_local_world_id);
+            assert_eq!(packet.container, USER_WAREHOUSE);
+            assert_eq!(packet.action, VIEW_ACTION_OPEN);
+            assert_eq!(packet.offset, 0);
+            assert_eq!(packet.size, 72);
+            assert!(packet.items.is_empty());
+
+            // Only existing pages and warehouses can be shown
+            view_ware(&world, &user, USER_WAREHOUSE, 72);
+            view_ware(&world, &user, USER_WAREHOUSE, 5);
+            view_ware(&world, &user, 3, 0);
+            assert!(user.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_put_ware_item() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 1, &[(POTION, 0, 20), (WEAPON, 1, 1)])?;
+            let weapon_id = world.run(|inventories: View<UserInventory>| {
+                inventories
+                    .try_get(user.connection_local_world_id)
+                    .unwrap()
+                    .items[&1]
+                    .id
+            });
+
+            // A split stack
+            put_item(&world, &user, USER_WAREHOUSE, 0, 5, 3);
+            assert_eq!(assert_itemlist(&user)?.items.len(), 2);
+            let packet = assert_view_ware_ex(&user)?;
+            assert_eq!(packet.action, VIEW_ACTION_REFRESH);
+            assert_eq!(packet.items.len(), 1);
+            assert_eq!(packet.items[0].slot, 3);
+            assert_eq!(packet.items[0].amount, 5);
+            assert_eq!(
+                assert_persisted(&world, &pool, &user, USER_WAREHOUSE)?,
+                (vec![(POTION, 0, 15), (WEAPON, 1, 1)], vec![(POTION, 3, 5)])
+            );
+
+            // The rest of the stack is merged
+            put_item(&world, &user, USER_WAREHOUSE, 0, 15, 3);
+            assert_itemlist(&user)?;
+            assert_view_ware_ex(&user)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user, USER_WAREHOUSE)?,
+                (vec![(WEAPON, 1, 1)], vec![(POTION, 3, 20)])
+            );
+
+            // Whole stacks keep their ID
+            put_item(&world, &user, USER_WAREHOUSE, 1, 1, 0);
+            assert_itemlist(&user)?;
+            assert_view_ware_ex(&user)?;
+            let items = list_warehouse_items(&pool, &user, USER_WAREHOUSE)?;
+            assert_eq!(items[0].id, weapon_id);
+            assert_eq!(
+                assert_persisted(&world, &pool, &user, USER_WAREHOUSE)?,
+                (vec![], vec![(WEAPON, 0, 1), (POTION, 3, 20)])
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_put_ware_item_invalid() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 1, &[(POTION, 0, 20), (WEAPON, 1, 1)])?;
+            put_item(&world, &user, USER_WAREHOUSE, 1, 1, 3);
+            assert_itemlist(&user)?;
+            assert_view_ware_ex(&user)?;
+
+            // Occupied slot
+            put_item(&world, &user, USER_WAREHOUSE, 0, 20, 3);
+            // Outside of the page
+            put_item(&world, &user, USER_WAREHOUSE, 0, 20, 72);
+            // More items than the stack holds
+            put_item(&world, &user, USER_WAREHOUSE, 0, 21, 4);
+            // Empty inventory slot
+            put_item(&world, &user, USER_WAREHOUSE, 5, 1, 4);
+
+            assert!(user.rx.is_empty());
+            assert_eq!(
+                assert_persisted(&world, &pool, &user, USER_WAREHOUSE)?,
+                (vec![(POTION, 0, 20)], vec![(WEAPON, 3, 1)])
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_get_ware_item() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 1, &[(POTION, 0, 20), (WEAPON, 1, 1)])?;
+            put_item(&world, &user, USER_WAREHOUSE, 0, 20, 0);
+            put_item(&world, &user, USER_WAREHOUSE, 1, 1, 1);
+            while !user.rx.is_empty() {
+                user.rx.try_recv()?;
+            }
+            let weapon_id = list_warehouse_items(&pool, &user, USER_WAREHOUSE)?[1].id;
+
+            get_item(&world, &pool, &user, USER_WAREHOUSE, 0, 5, 7)?;
+            assert_eq!(assert_itemlist(&user)?.items.len(), 1);
+            assert_eq!(assert_view_ware_ex(&user)?.items.len(), 2);
+            assert_eq!(
+                assert_persisted(&world, &pool, &user, USER_WAREHOUSE)?,
+                (vec![(POTION, 7, 5)], vec![(POTION, 0, 15), (WEAPON, 1, 1)])
+            );
+
+            get_item(&world, &pool, &user, USER_WAREHOUSE, 0, 15, 7)?;
+            get_item(&world, &pool, &user, USER_WAREHOUSE, 1, 1, 0)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user, USER_WAREHOUSE)?,
+                (vec![(WEAPON, 0, 1), (POTION, 7, 20)], vec![])
+            );
+            world.run(|inventories: View<UserInventory>| {
+                let inventory = inventories.try_get(user.connection_local_world_id).unwrap();
+                assert_eq!(inventory.items[&0].id, weapon_id);
+            });
+
+            // The inventory slot is occupied by an other item
+            put_item(&world, &user, USER_WAREHOUSE, 7, 20, 0);
+            while !user.rx.is_empty() {
+                user.rx.try_recv()?;
+            }
+            get_item(&world, &pool, &user, USER_WAREHOUSE, 0, 20, 0)?;
+            assert!(user.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_move_ware_pos() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(
+                &world,
+                &pool,
+                1,
+                &[(POTION, 0, 40), (POTION, 1, 20), (WEAPON, 2, 1)],
+            )?;
+            put_item(&world, &user, USER_WAREHOUSE, 0, 40, 0);
+            put_item(&world, &user, USER_WAREHOUSE, 1, 20, 1);
+            put_item(&world, &user, USER_WAREHOUSE, 2, 1, 2);
+            while !user.rx.is_empty() {
+                user.rx.try_recv()?;
+            }
+
+            // The stack is filled up to it's maximum
+            move_item(&world, &user, USER_WAREHOUSE, 1, 0);
+            assert_view_ware_ex(&user)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user, USER_WAREHOUSE)?,
+                (
+                    vec![],
+                    vec![(POTION, 0, 50), (POTION, 1, 10), (WEAPON, 2, 1)]
+                )
+            );
+
+            // Items swap their slots
+            move_item(&world, &user, USER_WAREHOUSE, 2, 0);
+            assert_view_ware_ex(&user)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user, USER_WAREHOUSE)?,
+                (
+                    vec![],
+                    vec![(WEAPON, 0, 1), (POTION, 1, 10), (POTION, 2, 50)]
+                )
+            );
+
+            // Items are moved into empty slots
+            move_item(&world, &user, USER_WAREHOUSE, 1, 10);
+            assert_view_ware_ex(&user)?;
+            assert_eq!(
+                assert_persisted(&world, &pool, &user, USER_WAREHOUSE)?,
+                (
+                    vec![],
+                    vec![(WEAPON, 0, 1), (POTION, 2, 50), (POTION, 10, 10)]
+                )
+            );
+
+            move_item(&world, &user, USER_WAREHOUSE, 5, 6);
+            move_item(&world, &user, USER_WAREHOUSE, 0, 72);
+            assert!(user.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_account_warehouse_commission() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 1, &[(POTION, 0, 20)])?;
+
+            // Items can't be moved before the commission is paid
+            put_item(&world, &user, ACCOUNT_WAREHOUSE, 0, 20, 0);
+            assert!(user.rx.is_empty());
+
+            pay_commission(&world, &user, ACCOUNT_WAREHOUSE);
+            assert_eq!(assert_itemlist(&user)?.money, 900);
+            let inventory = task::block_on(async {
+                let mut conn = pool.acquire().await?;
+                inventory::get_by_user_id(&mut conn, user.user.id).await
+            })?;
+            assert_eq!(inventory.money, 900);
+
+            put_item(&world, &user, ACCOUNT_WAREHOUSE, 0, 20, 0);
+            assert_itemlist(&user)?;
+            assert_eq!(assert_view_ware_ex(&user)?.container, ACCOUNT_WAREHOUSE);
+            assert_eq!(
+                assert_persisted(&world, &pool, &user, ACCOUNT_WAREHOUSE)?,
+                (vec![], vec![(POTION, 0, 20)])
+            );
+            assert!(list_warehouse_items(&pool, &user, USER_WAREHOUSE)?.is_empty());
+
+            // The commission is only paid once and only for the warehouse of the account
+            pay_commission(&world, &user, ACCOUNT_WAREHOUSE);
+            pay_commission(&world, &user, USER_WAREHOUSE);
+            assert!(user.rx.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_pay_warehouse_commision_without_money() -> Result<()> {
+        db_test(|db_string| {
+            let pool = task::block_on(async { PgPool::new(db_string).await })?;
+            let world = setup(pool.clone());
+            let user = add_user(&world, &pool, 1, &[])?;
+            world.run(|mut inventories: ViewMut<UserInventory>| {
+                (&mut inventories)
+                    .try_get(user.connection_local_world_id)
+                    .unwrap()
+                    .money = WAREHOUSE_COMMISSION - 1;
+            });
+
+            pay_commission(&world, &user, ACCOUNT_WAREHOUSE);
+            assert!(user.rx.is_empty());
+            world.run(|warehouses: View<UserWarehouse>| {
+                assert!(warehouses.try_get(user.connection_local_world_id).is_err());
+            });
+
+            Ok(())
+        })
+    }
+}
@@ -1,9 +1,12 @@
 /// Module that handles the world generation and handling
 use crate::config::Configuration;
+use crate::ecs::component::NpcSpawn;
 use crate::ecs::message::{EcsMessage, Message};
 use crate::ecs::resource::*;
 use crate::ecs::system::{common, global, local};
 use async_std::sync::{channel, Sender};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use shipyard::*;
 use sqlx::PgPool;
 use std::ops::Sub;
@@ -22,7 +25,7 @@ pub struct GlobalWorld {
 
 impl GlobalWorld {
     /// Creates a new GlobalWorld.
-    pub fn new(config: &Configuration, pool: &PgPool) -> Self {
+    pub fn new(config: &Configuration, pool: &PgPool, game_data: &GameData) -> Self {
         let world = World::new();
         info!("Creating global world");
 
@@ -40,6 +43,9 @@ impl GlobalWorld {
         });
         world.add_unique(config.clone());
         world.add_unique(pool.clone());
+        world.add_unique(game_data.clone());
+        world.add_unique(GuildLogoCache::default());
+        world.add_unique(GuildWarRegistry::default());
 
         let vec: Vec<EntityId> = Vec::with_capacity(4096);
         world.add_unique(DeletionList(vec));
@@ -72,6 +78,18 @@ impl GlobalWorld {
             .with_system(system!(global::settings_manager_system))
             .with_system(system!(global::user_manager_system))
             .with_system(system!(global::user_spawner_system))
+            .with_system(system!(global::channel_manager_system))
+            .with_system(system!(global::chat_manager_system))
+            .with_system(system!(global::private_channel_manager_system))
+            .with_system(system!(global::friend_manager_system))
+            .with_system(system!(global::block_manager_system))
+            .with_system(system!(global::guild_manager_system))
+            .with_system(system!(global::guild_war_manager_system))
+            .with_system(system!(global::party_manager_system))
+            .with_system(system!(global::matching_manager_system))
+            .with_system(system!(global::dungeon_manager_system))
+            .with_system(system!(global::parcel_manager_system))
+            .with_system(system!(global::trade_manager_system))
             .with_system(system!(global::local_world_manager_system))
             .with_system(system!(common::cleaner_system))
             .build();
@@ -98,6 +116,7 @@ impl GlobalWorld {
 /// LocalWorld handles all combat and instance related messages.
 pub struct LocalWorld {
     pub id: EntityId,
+    pub zone_id: i32,
     pub channel: Sender<EcsMessage>,
     pub world: World,
 }
@@ -107,7 +126,9 @@ impl LocalWorld {
     pub fn new(
         config: &Configuration,
         pool: &PgPool,
+        game_data: &GameData,
         world_id: EntityId,
+        zone_id: i32,
         global_world_channel: Sender<EcsMessage>,
     ) -> Self {
         let world = World::new();
@@ -127,9 +148,17 @@ impl LocalWorld {
         });
         world.add_unique(config.clone());
         world.add_unique(pool.clone());
+        world.add_unique(game_data.items.clone());
+        world.add_unique(game_data.skills.clone());
+        world.add_unique(game_data.abnormalities.clone());
+        world.add_unique(game_data.npcs.clone());
+        world.add_unique(game_data.territories.clone());
 
         let vec: Vec<EntityId> = Vec::with_capacity(4096);
         world.add_unique(DeletionList(vec));
+        world.add_unique(VisibilityGrid::default());
+        world.add_unique(GuildWarRegistry::default());
+        world.add_unique(AiRng(StdRng::from_entropy()));
 
         world.add_unique(Tick {
             count: 0,
@@ -139,6 +168,7 @@ impl LocalWorld {
 
         Self {
             id: world_id,
+            zone_id,
             channel: tx_channel,
             world,
         }
@@ -150,6 +180,7 @@ impl LocalWorld {
         let _enter = span.enter();
 
         let id = self.id;
+        let zone_id = self.zone_id;
         let world = &mut self.world;
 
         // Build the workload
@@ -158,12 +189,42 @@ impl LocalWorld {
             .add_workload(LOCAL_WORLD_TICK)
             .with_system(system!(common::message_receiver_system))
             .with_system(system!(local::user_gateway_system))
+            .with_system(system!(local::movement_system))
+            .with_system(system!(local::npc_spawner_system))
+            .with_system(system!(local::npc_ai_system))
+            .with_system(system!(local::visibility_system))
+            .with_system(system!(local::chat_system))
+            .with_system(system!(local::appearance_system))
+            .with_system(system!(local::inventory_system))
+            .with_system(system!(local::equipment_system))
+            .with_system(system!(local::warehouse_system))
+            .with_system(system!(local::parcel_system))
+            .with_system(system!(local::trade_system))
+            .with_system(system!(local::guild_war_system))
+            .with_system(system!(local::combat_system))
+            .with_system(system!(local::experience_system))
+            .with_system(system!(local::abnormality_system))
+            .with_system(system!(local::status_reporter_system))
             .with_system(system!(common::cleaner_system))
             .with_system(system!(common::shutdown_system))
             .build();
 
         info!("Loading data for local world {:?}", self.id);
-        // TODO Load all additional data that the local world needs
+        let spawn_count = world.run(
+            |mut entities: EntitiesViewMut,
+             mut npc_spawns: ViewMut<NpcSpawn>,
+             npc_registry: UniqueView<NpcRegistry>,
+             territories: UniqueView<TerritoryRegistry>| {
+                local::load_territories(
+                    zone_id,
+                    &mut entities,
+                    &mut npc_spawns,
+                    &npc_registry,
+                    &territories,
+                )
+            },
+        );
+        info!("Loaded {} NPC spawns for zone {}", spawn_count, zone_id);
         info!("Finished loading data for local world {:?}", self.id);
 
         // Inform the global world that we finished loading and can accept messages
@@ -32,4 +32,10 @@ pub enum AlmeticaError {
 
     #[error("invalid login provided")]
     InvalidLogin,
+
+    #[error("invalid guild logo provided")]
+    InvalidGuildLogo,
+
+    #[error("user is missing the authority to change the guild")]
+    MissingGuildAuthority,
 }
@@ -8,6 +8,7 @@ use nalgebra::{Point3, Rotation3, Unit, Vector3};
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::ops::AddAssign;
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
 pub enum Region {
@@ -79,6 +80,25 @@ pub enum Class {
     Valkyrie = 12,
 }
 
+impl Class {
+    /// Returns the role an user of the class fills in a party.
+    pub fn role(self) -> Role {
+        match self {
+            Class::Lancer | Class::Fighter => Role::Tank,
+            Class::Priest | Class::Elementalist => Role::Healer,
+            _ => Role::Damage,
+        }
+    }
+}
+
+/// Role of an user in a party. Used by the dungeon matching to form groups.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Tank,
+    Healer,
+    Damage,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, sqlx::Type, PartialEq)]
 #[sqlx(rename = "servant_type")]
 pub enum ServantType {
@@ -392,6 +412,358 @@ impl<'de> Deserialize<'de> for TemplateID {
     }
 }
 
+/// Channels of the chat. Used in the network protocol.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChatChannel {
+    Say,
+    Party,
+    Guild,
+    Area,
+    Trade,
+    Greeting,
+    PrivateChannel(i32), // Private channel 1-8
+    PartyNotice,
+    RaidNotice,
+    Emote,
+    Global,
+    Raid,
+}
+
+impl Serialize for ChatChannel {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let value = match self {
+            ChatChannel::Say => 0,
+            ChatChannel::Party => 1,
+            ChatChannel::Guild => 2,
+            ChatChannel::Area => 3,
+            ChatChannel::Trade => 4,
+            ChatChannel::Greeting => 9,
+            ChatChannel::PrivateChannel(index) => 10 + index,
+            ChatChannel::PartyNotice => 21,
+            ChatChannel::RaidNotice => 25,
+            ChatChannel::Emote => 26,
+            ChatChannel::Global => 27,
+            ChatChannel::Raid => 32,
+        };
+        serializer.serialize_i32(value)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChatChannel {
+    fn deserialize<D>(deserializer: D) -> Result<ChatChannel, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = deserializer.deserialize_i32(I32Visitor)?;
+        let channel = match value {
+            0 => ChatChannel::Say,
+            1 => ChatChannel::Party,
+            2 => ChatChannel::Guild,
+            3 => ChatChannel::Area,
+            4 => ChatChannel::Trade,
+            9 => ChatChannel::Greeting,
+            11..=18 => ChatChannel::PrivateChannel(value - 10),
+            21 => ChatChannel::PartyNotice,
+            25 => ChatChannel::RaidNotice,
+            26 => ChatChannel::Emote,
+            27 => ChatChannel::Global,
+            32 => ChatChannel::Raid,
+            _ => return Err(de::Error::custom(format!("unknown chat channel {}", value))),
+        };
+        Ok(channel)
+    }
+}
+
+/// Looting methods of a party. Used in the network protocol.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub enum LootingMethod {
+    FreeForAll = 0,
+    RoundRobin = 1,
+    Leader = 2,
+}
+
+/// Maximal size of a guild logo. Logos are send to the client in one packet, which can only
+/// hold 16 KiB of data.
+pub const MAX_GUILD_LOGO_SIZE: usize = 16_000;
+
+/// Size of the header of an image in the TERA image format.
+const GUILD_LOGO_HEADER_SIZE: usize = 12;
+
+/// Checks if the data is a guild logo in the TERA image format. The image data follows a header
+/// made of the "TERA" magic, the version of the format and the edge length of the quadratic image.
+pub fn is_valid_guild_logo(data: &[u8]) -> bool {
+    if data.len() <= GUILD_LOGO_HEADER_SIZE || data.len() > MAX_GUILD_LOGO_SIZE {
+        return false;
+    }
+    let version = LittleEndian::read_u32(&data[4..8]);
+    let edge_length = LittleEndian::read_u32(&data[8..12]);
+    &data[0..4] == b"TERA" && version == 1 && edge_length == 64
+}
+
+/// Number of inventory slots a new user starts with.
+pub const DEFAULT_INVENTORY_SIZE: i32 = 40;
+
+/// Maximal number of inventory slots an user can unlock by expanding the inventory.
+pub const MAX_INVENTORY_SIZE: i32 = 120;
+
+/// Number of equipment presets an user can switch between.
+pub const MAX_EQUIPMENT_PRESETS: i32 = 3;
+
+/// Days until a parcel expires. Expired parcels with attachments are returned to their sender.
+pub const PARCEL_EXPIRATION_DAYS: i64 = 30;
+
+/// Maximal number of items that can be attached to a parcel.
+pub const MAX_PARCEL_ITEMS: usize = 8;
+
+/// Contract type of a trade between two users.
+pub const TRADE_CONTRACT: i32 = 3;
+
+/// Maximal number of items an user can offer in a trade.
+pub const MAX_TRADE_ITEMS: usize = 18;
+
+/// Slots an item can be equipped in. Used in the network protocol.
+#[derive(Clone, Copy, Debug, sqlx::Type, PartialEq, Eq, Hash)]
+#[sqlx(rename = "equipment_slot")]
+pub enum EquipmentSlot {
+    #[sqlx(rename = "weapon")]
+    Weapon,
+    #[sqlx(rename = "body")]
+    Body,
+    #[sqlx(rename = "hand")]
+    Hand,
+    #[sqlx(rename = "feet")]
+    Feet,
+    #[sqlx(rename = "earring1")]
+    Earring1,
+    #[sqlx(rename = "earring2")]
+    Earring2,
+    #[sqlx(rename = "ring1")]
+    Ring1,
+    #[sqlx(rename = "ring2")]
+    Ring2,
+    #[sqlx(rename = "necklace")]
+    Necklace,
+    #[sqlx(rename = "underwear")]
+    Underwear,
+    #[sqlx(rename = "head")]
+    Head,
+    #[sqlx(rename = "face")]
+    Face,
+    #[sqlx(rename = "style_head")]
+    StyleHead,
+    #[sqlx(rename = "style_face")]
+    StyleFace,
+    #[sqlx(rename = "style_back")]
+    StyleBack,
+    #[sqlx(rename = "style_weapon")]
+    StyleWeapon,
+    #[sqlx(rename = "style_body")]
+    StyleBody,
+    #[sqlx(rename = "style_footprint")]
+    StyleFootprint,
+}
+
+impl EquipmentSlot {
+    /// Returns true for the slots of cosmetic items.
+    pub fn is_style(self) -> bool {
+        match self {
+            EquipmentSlot::StyleHead
+            | EquipmentSlot::StyleFace
+            | EquipmentSlot::StyleBack
+            | EquipmentSlot::StyleWeapon
+            | EquipmentSlot::StyleBody
+            | EquipmentSlot::StyleFootprint => true,
+            _ => false,
+        }
+    }
+}
+
+impl Serialize for EquipmentSlot {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let value = match self {
+            EquipmentSlot::Weapon => 1,
+            EquipmentSlot::Body => 3,
+            EquipmentSlot::Hand => 4,
+            EquipmentSlot::Feet => 5,
+            EquipmentSlot::Earring1 => 6,
+            EquipmentSlot::Earring2 => 7,
+            EquipmentSlot::Ring1 => 8,
+            EquipmentSlot::Ring2 => 9,
+            EquipmentSlot::Necklace => 10,
+            EquipmentSlot::Underwear => 11,
+            EquipmentSlot::Head => 12,
+            EquipmentSlot::Face => 13,
+            EquipmentSlot::StyleHead => 14,
+            EquipmentSlot::StyleFace => 15,
+            EquipmentSlot::StyleBack => 16,
+            EquipmentSlot::StyleWeapon => 17,
+            EquipmentSlot::StyleBody => 18,
+            EquipmentSlot::StyleFootprint => 19,
+        };
+        serializer.serialize_i32(value)
+    }
+}
+
+impl<'de> Deserialize<'de> for EquipmentSlot {
+    fn deserialize<D>(deserializer: D) -> Result<EquipmentSlot, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = deserializer.deserialize_i32(I32Visitor)?;
+        let slot = match value {
+            1 => EquipmentSlot::Weapon,
+            3 => EquipmentSlot::Body,
+            4 => EquipmentSlot::Hand,
+            5 => EquipmentSlot::Feet,
+            6 => EquipmentSlot::Earring1,
+            7 => EquipmentSlot::Earring2,
+            8 => EquipmentSlot::Ring1,
+            9 => EquipmentSlot::Ring2,
+            10 => EquipmentSlot::Necklace,
+            11 => EquipmentSlot::Underwear,
+            12 => EquipmentSlot::Head,
+            13 => EquipmentSlot::Face,
+            14 => EquipmentSlot::StyleHead,
+            15 => EquipmentSlot::StyleFace,
+            16 => EquipmentSlot::StyleBack,
+            17 => EquipmentSlot::StyleWeapon,
+            18 => EquipmentSlot::StyleBody,
+            19 => EquipmentSlot::StyleFootprint,
+            _ => {
+                return Err(de::Error::custom(format!(
+                    "unknown equipment slot {}",
+                    value
+                )))
+            }
+        };
+        Ok(slot)
+    }
+}
+
+/// Combat stats of an user or an item.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub attack: i32,
+    pub defence: i32,
+    pub impact: i32,
+    pub balance: i32,
+    pub max_hp: i32,
+    pub max_mp: i32,
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Stats) {
+        self.attack += other.attack;
+        self.defence += other.defence;
+        self.impact += other.impact;
+        self.balance += other.balance;
+        self.max_hp += other.max_hp;
+        self.max_mp += other.max_mp;
+    }
+}
+
+/// Stats of a level 1 user without any equipment.
+pub const BASE_STATS: Stats = Stats {
+    attack: 10,
+    defence: 10,
+    impact: 10,
+    balance: 10,
+    max_hp: 200,
+    max_mp: 100,
+};
+
+/// Stats an user gains with every level.
+const STATS_PER_LEVEL: Stats = Stats {
+    attack: 2,
+    defence: 1,
+    impact: 1,
+    balance: 1,
+    max_hp: 40,
+    max_mp: 5,
+};
+
+/// Stats of an user of the given level without any equipment.
+pub fn level_stats(level: i32) -> Stats {
+    let levels = level.max(1) - 1;
+    Stats {
+        attack: BASE_STATS.attack + levels * STATS_PER_LEVEL.attack,
+        defence: BASE_STATS.defence + levels * STATS_PER_LEVEL.defence,
+        impact: BASE_STATS.impact + levels * STATS_PER_LEVEL.impact,
+        balance: BASE_STATS.balance + levels * STATS_PER_LEVEL.balance,
+        max_hp: BASE_STATS.max_hp + levels * STATS_PER_LEVEL.max_hp,
+        max_mp: BASE_STATS.max_mp + levels * STATS_PER_LEVEL.max_mp,
+    }
+}
+
+/// Highest level an user can reach. Users at the highest level don't gain experience anymore.
+pub const MAX_LEVEL: i32 = 70;
+
+/// Experience an user needs to advance from the given level to the next level.
+pub fn level_exp(level: i32) -> i64 {
+    let level = i64::from(level.max(1).min(MAX_LEVEL));
+    100 * level * level * level
+}
+
+/// Experience an user collected over all levels.
+pub fn total_exp(level: i32, exp: i64) -> i64 {
+    (1..level.min(MAX_LEVEL)).map(level_exp).sum::<i64>() + exp
+}
+
+/// Users collect rest bonus experience while they are logged out. Every hour adds a part of the
+/// experience of the current level until the rest bonus is full.
+const REST_BONUS_PERCENT_PER_HOUR: i64 = 5;
+
+/// Maximal rest bonus experience of an user of the given level.
+pub fn max_rest_bonus_exp(level: i32) -> i64 {
+    level_exp(level)
+}
+
+/// Adds the rest bonus experience an user collected while being logged out for the given number
+/// of seconds.
+pub fn accrue_rest_bonus_exp(level: i32, rest_bonus_exp: i64, offline_seconds: i64) -> i64 {
+    let max = max_rest_bonus_exp(level);
+    let hours = offline_seconds.max(0) / 3600;
+    let accrued = level_exp(level)
+        .saturating_mul(REST_BONUS_PERCENT_PER_HOUR)
+        .saturating_mul(hours)
+        / 100;
+    rest_bonus_exp.saturating_add(accrued).min(max).max(0)
+}
+
+/// States of a guild war. A declared war needs to be accepted by the other guild and becomes
+/// active after a preparation time. Active wars end when one guild gives up or the war expires.
+/// Used in the network protocol.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, sqlx::Type, PartialEq)]
+#[sqlx(rename = "guild_war_state")]
+pub enum GuildWarState {
+    #[sqlx(rename = "declared")]
+    Declared = 0,
+    #[sqlx(rename = "accepted")]
+    Accepted = 1,
+    #[sqlx(rename = "active")]
+    Active = 2,
+    #[sqlx(rename = "surrendered")]
+    Surrendered = 3,
+    #[sqlx(rename = "expired")]
+    Expired = 4,
+}
+
+impl GuildWarState {
+    /// Returns true if the war is neither surrendered nor expired.
+    pub fn is_ongoing(self) -> bool {
+        match self {
+            GuildWarState::Declared | GuildWarState::Accepted | GuildWarState::Active => true,
+            GuildWarState::Surrendered | GuildWarState::Expired => false,
+        }
+    }
+}
+
 /// Supported password hash algorithms.
 #[derive(Clone, Debug, sqlx::Type, PartialEq)]
 #[sqlx(rename = "password_hash_algorithm")]
@@ -602,6 +974,125 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_class_role() {
+        assert_eq!(Class::Lancer.role(), Role::Tank);
+        assert_eq!(Class::Fighter.role(), Role::Tank);
+        assert_eq!(Class::Priest.role(), Role::Healer);
+        assert_eq!(Class::Elementalist.role(), Role::Healer);
+        assert_eq!(Class::Warrior.role(), Role::Damage);
+        assert_eq!(Class::Sorcerer.role(), Role::Damage);
+    }
+
+    #[test]
+    fn test_is_valid_guild_logo() {
+        let mut data = vec![
+            0x54, 0x45, 0x52, 0x41, 0x1, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0,
+        ];
+        assert!(!is_valid_guild_logo(&data));
+        data.extend_from_slice(&[0xff; 64]);
+        assert!(is_valid_guild_logo(&data));
+
+        let mut wrong_magic = data.clone();
+        wrong_magic[0] = 0x0;
+        assert!(!is_valid_guild_logo(&wrong_magic));
+
+        let mut wrong_edge_length = data.clone();
+        wrong_edge_length[8] = 0x80;
+        assert!(!is_valid_guild_logo(&wrong_edge_length));
+
+        data.resize(MAX_GUILD_LOGO_SIZE + 1, 0xff);
+        assert!(!is_valid_guild_logo(&data));
+    }
+
+    #[test]
+    fn test_chat_channel_serialization() -> Result<()> {
+        let data = to_vec(&ChatChannel::Global)?;
+        assert_eq!(LittleEndian::read_i32(&data), 27);
+        let data = to_vec(&ChatChannel::PrivateChannel(3))?;
+        assert_eq!(LittleEndian::read_i32(&data), 13);
+        Ok(())
+    }
+
+    #[test]
+    fn test_chat_channel_deserialization() -> Result<()> {
+        let mut data = vec![0u8; 4];
+        LittleEndian::write_i32(&mut data, 18);
+        let value: ChatChannel = from_vec(data)?;
+        assert_eq!(value, ChatChannel::PrivateChannel(8));
+
+        let mut data = vec![0u8; 4];
+        LittleEndian::write_i32(&mut data, 19);
+        assert!(from_vec::<ChatChannel>(data).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_equipment_slot_serialization() -> Result<()> {
+        let data = to_vec(&EquipmentSlot::Weapon)?;
+        assert_eq!(LittleEndian::read_i32(&data), 1);
+        let data = to_vec(&EquipmentSlot::StyleFootprint)?;
+        assert_eq!(LittleEndian::read_i32(&data), 19);
+        Ok(())
+    }
+
+    #[test]
+    fn test_equipment_slot_deserialization() -> Result<()> {
+        let mut data = vec![0u8; 4];
+        LittleEndian::write_i32(&mut data, 3);
+        let value: EquipmentSlot = from_vec(data)?;
+        assert_eq!(value, EquipmentSlot::Body);
+
+        let mut data = vec![0u8; 4];
+        LittleEndian::write_i32(&mut data, 2);
+        assert!(from_vec::<EquipmentSlot>(data).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_add_assign() {
+        let mut stats = BASE_STATS;
+        stats += Stats {
+            attack: 5,
+            max_hp: 100,
+            ..Default::default()
+        };
+        assert_eq!(stats.attack, 15);
+        assert_eq!(stats.defence, 10);
+        assert_eq!(stats.max_hp, 300);
+    }
+
+    #[test]
+    fn test_level_stats() {
+        assert_eq!(level_stats(1), BASE_STATS);
+        assert_eq!(level_stats(0), BASE_STATS);
+        let stats = level_stats(11);
+        assert_eq!(stats.attack, 30);
+        assert_eq!(stats.defence, 20);
+        assert_eq!(stats.max_hp, 600);
+        assert_eq!(stats.max_mp, 150);
+    }
+
+    #[test]
+    fn test_level_exp() {
+        assert_eq!(level_exp(1), 100);
+        assert_eq!(level_exp(2), 800);
+        assert_eq!(level_exp(MAX_LEVEL + 1), level_exp(MAX_LEVEL));
+        assert_eq!(total_exp(1, 50), 50);
+        assert_eq!(total_exp(3, 50), 950);
+    }
+
+    #[test]
+    fn test_accrue_rest_bonus_exp() {
+        // 5% of the experience of the level per full hour
+        assert_eq!(accrue_rest_bonus_exp(10, 0, 3599), 0);
+        assert_eq!(accrue_rest_bonus_exp(10, 0, 3600), 5000);
+        assert_eq!(accrue_rest_bonus_exp(10, 1000, 7200), 11_000);
+        // Up to the experience of one level
+        assert_eq!(accrue_rest_bonus_exp(10, 0, 100 * 3600), 100_000);
+        assert_eq!(accrue_rest_bonus_exp(10, 0, -3600), 0);
+    }
+
     #[test]
     fn test_angle_basic_deg() {
         for i in 0..3600 {
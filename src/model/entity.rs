@@ -54,6 +54,7 @@ pub struct User {
     pub laurel: i32,
     pub achievement_points: i32,
     pub playtime: i64, // Playtime in seconds.
+    pub xp: i64,       // Experience inside the current level.
     pub rest_bonus_xp: i64,
     pub show_face: bool,
     pub show_style: bool,
@@ -64,6 +65,7 @@ pub struct User {
     pub delete_at: Option<DateTime<Utc>>,
     pub last_logout_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
+    pub is_alive: bool,
 }
 
 /// The location of an users.
@@ -74,3 +76,217 @@ pub struct UserLocation {
     pub point: Point3<f32>,
     pub rotation: Rotation3<f32>,
 }
+
+/// A password protected chat channel that is created by an user.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct PrivateChannel {
+    pub id: i32,
+    pub name: String,
+    pub password: Option<i32>, // Four digit PIN of the client. Not hashed, see the migration.
+    pub owner_user_id: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The membership of an user in a private channel.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct PrivateChannelMember {
+    pub channel_id: i32,
+    pub user_id: i32,
+    pub slot: i32, // Slot of the channel in the chat of the user (1-8).
+    pub joined_at: DateTime<Utc>,
+}
+
+/// A friend of an user. Friendships are mutual, so every friendship has two entries.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct Friend {
+    pub user_id: i32,
+    pub friend_id: i32,
+    pub group_id: Option<i32>,
+    pub memo: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A group an user can sort it's friends into.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct FriendGroup {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+}
+
+/// A friend request of an user that the other user didn't accept yet.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct FriendRequest {
+    pub user_id: i32,   // User that send the request
+    pub friend_id: i32, // User that receives the request
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An user that is blocked by another user.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct BlockedUser {
+    pub user_id: i32,         // User that blocked the other user
+    pub blocked_user_id: i32, // User that is blocked
+    pub memo: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A blocked user together with the data of the user that the block list shows.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct BlockedUserEntry {
+    pub blocked_user_id: i32,
+    pub name: String,
+    pub level: i32,
+    pub class: Class,
+    pub memo: String,
+}
+
+/// The lockout of an user for a dungeon. Users can only enter a dungeon a limited number of times
+/// until the lockout resets.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct DungeonLockout {
+    pub user_id: i32,
+    pub zone_id: i32,
+    pub entry_count: i32, // Entries since the last reset
+    pub clear_count: i32, // Total number of clears
+    pub reset_at: DateTime<Utc>,
+}
+
+/// A guild of users. The guild master has all rights in the guild.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct Guild {
+    pub id: i32,
+    pub name: String,
+    pub master_id: i32, // User ID of the guild master
+    pub created_at: DateTime<Utc>,
+}
+
+/// A group (rank) inside a guild. The authority defines the rights of the members of the group.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct GuildGroup {
+    pub id: i32,
+    pub guild_id: i32,
+    pub name: String,
+    pub authority: i32, // Bitmask of the rights of the members
+}
+
+/// The membership of an user in a guild. An user can only be a member of one guild.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct GuildMember {
+    pub user_id: i32,
+    pub guild_id: i32,
+    pub group_id: Option<i32>, // Members without a group have no special rights
+    pub joined_at: DateTime<Utc>,
+}
+
+/// The logo of a guild in the TERA image format.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct GuildLogo {
+    pub guild_id: i32,
+    pub data: Vec<u8>,
+    pub version: i32, // Increased on every update, so that clients don't use an outdated logo
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A war between two guilds. Members of guilds at war can fight each other outside of PvP zones.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct GuildWar {
+    pub id: i32,
+    pub declaring_guild_id: i32,
+    pub target_guild_id: i32,
+    pub state: GuildWarState,
+    pub declaring_guild_kills: i32,
+    pub target_guild_kills: i32,
+    pub surrendered_guild_id: Option<i32>, // Set if one of the guilds gave up the war
+    pub declared_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>, // Time of the last change of the state
+}
+
+/// The inventory of an user. The items inside the inventory are stored as `Item`.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct Inventory {
+    pub user_id: i32,
+    pub size: i32, // Number of usable slots
+    pub money: i64,
+    pub equipment_preset: i32,
+}
+
+/// An item inside the inventory of an user.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct Item {
+    pub id: i64,
+    pub user_id: i32,
+    pub template_id: i32, // ID of the item inside the datacenter
+    pub slot: i32,
+    pub amount: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An item that is equipped by an user. Equipped items keep the ID they had inside the inventory.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct EquippedItem {
+    pub id: i64,
+    pub user_id: i32,
+    pub preset: i32,
+    pub slot: EquipmentSlot,
+    pub template_id: i32, // ID of the item inside the datacenter
+    pub created_at: DateTime<Utc>,
+}
+
+/// A warehouse that stores items. Warehouses without an user are shared by all users of an account.
+/// The items inside a warehouse are stored as `WarehouseItem`.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct Warehouse {
+    pub id: i32,
+    pub account_id: i64,
+    pub user_id: Option<i32>,
+    pub size: i32, // Number of usable slots
+}
+
+/// An item inside a warehouse.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct WarehouseItem {
+    pub id: i64,
+    pub warehouse_id: i32,
+    pub template_id: i32, // ID of the item inside the datacenter
+    pub slot: i32,
+    pub amount: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An in-game mail. Parcels without a sender user are sent by the server. The attached items are
+/// stored as `ParcelItem`.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct Parcel {
+    pub id: i64,
+    pub sender_user_id: Option<i32>,
+    pub sender_name: String,
+    pub recipient_user_id: i32,
+    pub title: String,
+    pub message: String,
+    pub money: i64, // Attached money
+    pub is_read: bool,
+    pub is_returned: bool, // Set if the parcel was sent back to it's sender
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// An item attached to a parcel.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct ParcelItem {
+    pub id: i64,
+    pub parcel_id: i64,
+    pub template_id: i32, // ID of the item inside the datacenter
+    pub amount: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An abnormality (buff or debuff) of an user that survives a relog.
+#[derive(Clone, Debug, sqlx::FromRow, PartialEq)]
+pub struct UserAbnormality {
+    pub user_id: i32,
+    pub abnormality_id: i32, // ID of the abnormality inside the datacenter
+    pub stacks: i32,
+    pub remaining: i64, // Remaining duration in milliseconds
+}
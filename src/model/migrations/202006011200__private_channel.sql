@@ -0,0 +1,24 @@
+-- The password of a channel is the four digit PIN of the client and is stored as plain number.
+-- Hashing wouldn't protect it, since all 10000 possible PINs can be tried against any hash in
+-- a moment, and slow hashes like the one of the account passwords would stall the global world
+-- on every join. Channel passwords only keep strangers out of a chat and guard no account.
+CREATE TABLE "private_channel"
+(
+    "id"            SERIAL PRIMARY KEY,
+    "name"          TEXT NOT NULL,
+    "password"      INT,
+    "owner_user_id" INT  NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "created_at"    TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX "private_channel_name_idx" ON "private_channel" (LOWER("name"));
+
+CREATE TABLE "private_channel_member"
+(
+    "channel_id" INT NOT NULL REFERENCES "private_channel" ON DELETE CASCADE,
+    "user_id"    INT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "slot"       INT NOT NULL,
+    "joined_at"  TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("channel_id", "user_id"),
+    UNIQUE ("user_id", "slot")
+);
@@ -0,0 +1,25 @@
+CREATE TABLE "friend_group"
+(
+    "id"      SERIAL PRIMARY KEY,
+    "user_id" INT  NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "name"    TEXT NOT NULL
+);
+
+CREATE TABLE "friend"
+(
+    "user_id"    INT  NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "friend_id"  INT  NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "group_id"   INT           REFERENCES "friend_group" ON DELETE SET NULL,
+    "memo"       TEXT NOT NULL DEFAULT '',
+    "created_at" TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("user_id", "friend_id")
+);
+
+CREATE TABLE "friend_request"
+(
+    "user_id"    INT  NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "friend_id"  INT  NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "message"    TEXT NOT NULL DEFAULT '',
+    "created_at" TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("user_id", "friend_id")
+);
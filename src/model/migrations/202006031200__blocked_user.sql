@@ -0,0 +1,8 @@
+CREATE TABLE "blocked_user"
+(
+    "user_id"         INT  NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "blocked_user_id" INT  NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "memo"            TEXT NOT NULL DEFAULT '',
+    "created_at"      TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("user_id", "blocked_user_id")
+);
@@ -0,0 +1,9 @@
+CREATE TABLE "dungeon_lockout"
+(
+    "user_id"     INT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "zone_id"     INT NOT NULL,
+    "entry_count" INT NOT NULL DEFAULT 0,
+    "clear_count" INT NOT NULL DEFAULT 0,
+    "reset_at"    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("user_id", "zone_id")
+);
@@ -0,0 +1,25 @@
+CREATE TABLE "guild"
+(
+    "id"         SERIAL PRIMARY KEY,
+    "name"       TEXT NOT NULL,
+    "master_id"  INT  NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "created_at" TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX "guild_name_idx" ON "guild" (LOWER("name"));
+
+CREATE TABLE "guild_group"
+(
+    "id"        SERIAL PRIMARY KEY,
+    "guild_id"  INT  NOT NULL REFERENCES "guild" ON DELETE CASCADE,
+    "name"      TEXT NOT NULL,
+    "authority" INT  NOT NULL DEFAULT 0
+);
+
+CREATE TABLE "guild_member"
+(
+    "user_id"   INT NOT NULL PRIMARY KEY REFERENCES "user" ON DELETE CASCADE,
+    "guild_id"  INT NOT NULL REFERENCES "guild" ON DELETE CASCADE,
+    "group_id"  INT          REFERENCES "guild_group" ON DELETE SET NULL,
+    "joined_at" TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
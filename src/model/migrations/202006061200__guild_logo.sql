@@ -0,0 +1,7 @@
+CREATE TABLE "guild_logo"
+(
+    "guild_id"   INT   NOT NULL PRIMARY KEY REFERENCES "guild" ON DELETE CASCADE,
+    "data"       BYTEA NOT NULL,
+    "version"    INT   NOT NULL DEFAULT 1,
+    "updated_at" TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,17 @@
+CREATE TYPE "guild_war_state" AS ENUM ('declared', 'accepted', 'active', 'surrendered', 'expired');
+
+CREATE TABLE "guild_war"
+(
+    "id"                    SERIAL PRIMARY KEY,
+    "declaring_guild_id"    INT               NOT NULL REFERENCES "guild" ON DELETE CASCADE,
+    "target_guild_id"       INT               NOT NULL REFERENCES "guild" ON DELETE CASCADE,
+    "state"                 "guild_war_state" NOT NULL DEFAULT 'declared',
+    "declaring_guild_kills" INT               NOT NULL DEFAULT 0,
+    "target_guild_kills"    INT               NOT NULL DEFAULT 0,
+    "surrendered_guild_id"  INT,
+    "declared_at"           TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    "updated_at"            TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX "guild_war_declaring_guild_id_idx" ON "guild_war" ("declaring_guild_id");
+CREATE INDEX "guild_war_target_guild_id_idx" ON "guild_war" ("target_guild_id");
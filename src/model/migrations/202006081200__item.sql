@@ -0,0 +1,21 @@
+CREATE TABLE "inventory"
+(
+    "user_id" INT    NOT NULL PRIMARY KEY REFERENCES "user" ON DELETE CASCADE,
+    "size"    INT    NOT NULL DEFAULT 40,
+    "money"   BIGINT NOT NULL DEFAULT 0
+);
+
+-- Users that were created before the inventory existed get an empty one.
+INSERT INTO "inventory" ("user_id") SELECT "id" FROM "user";
+
+CREATE TABLE "item"
+(
+    "id"          BIGSERIAL PRIMARY KEY,
+    "user_id"     INT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "template_id" INT NOT NULL,
+    "slot"        INT NOT NULL,
+    "amount"      INT NOT NULL DEFAULT 1,
+    "created_at"  TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    -- Deferred, so that items can swap their slots inside a transaction.
+    CONSTRAINT "item_user_id_slot_key" UNIQUE ("user_id", "slot") DEFERRABLE INITIALLY DEFERRED
+);
@@ -0,0 +1,15 @@
+CREATE TYPE "equipment_slot" AS ENUM ('weapon', 'body', 'hand', 'feet', 'earring1', 'earring2', 'ring1', 'ring2', 'necklace', 'underwear', 'head', 'face', 'style_head', 'style_face', 'style_back', 'style_weapon', 'style_body', 'style_footprint');
+
+ALTER TABLE "inventory" ADD COLUMN "equipment_preset" INT NOT NULL DEFAULT 0;
+
+-- Equipped items keep the ID they had inside the inventory.
+CREATE TABLE "equipped_item"
+(
+    "id"          BIGINT PRIMARY KEY DEFAULT nextval('item_id_seq'),
+    "user_id"     INT              NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "preset"      INT              NOT NULL DEFAULT 0,
+    "slot"        "equipment_slot" NOT NULL,
+    "template_id" INT              NOT NULL,
+    "created_at"  TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "equipped_item_user_id_preset_slot_key" UNIQUE ("user_id", "preset", "slot") DEFERRABLE INITIALLY DEFERRED
+);
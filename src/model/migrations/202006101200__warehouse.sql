@@ -0,0 +1,24 @@
+-- Warehouses without an user are shared by all users of an account.
+CREATE TABLE "warehouse"
+(
+    "id"         SERIAL PRIMARY KEY,
+    "account_id" BIGINT NOT NULL REFERENCES "account" ON DELETE CASCADE,
+    "user_id"    INT REFERENCES "user" ON DELETE CASCADE,
+    "size"       INT NOT NULL DEFAULT 72
+);
+
+CREATE UNIQUE INDEX "warehouse_account_id_key" ON "warehouse" ("account_id") WHERE "user_id" IS NULL;
+CREATE UNIQUE INDEX "warehouse_user_id_key" ON "warehouse" ("user_id") WHERE "user_id" IS NOT NULL;
+
+-- Items keep their ID when they are moved between the inventory and a warehouse.
+CREATE TABLE "warehouse_item"
+(
+    "id"           BIGINT PRIMARY KEY DEFAULT nextval('item_id_seq'),
+    "warehouse_id" INT NOT NULL REFERENCES "warehouse" ON DELETE CASCADE,
+    "template_id"  INT NOT NULL,
+    "slot"         INT NOT NULL,
+    "amount"       INT NOT NULL DEFAULT 1,
+    "created_at"   TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    -- Deferred, so that items can swap their slots inside a transaction.
+    CONSTRAINT "warehouse_item_warehouse_id_slot_key" UNIQUE ("warehouse_id", "slot") DEFERRABLE INITIALLY DEFERRED
+);
@@ -0,0 +1,30 @@
+-- Parcels without a sender user are system parcels (rewards of events, GM mails etc.).
+CREATE TABLE "parcel"
+(
+    "id"                BIGSERIAL PRIMARY KEY,
+    "sender_user_id"    INT REFERENCES "user" ON DELETE SET NULL,
+    "sender_name"       TEXT    NOT NULL,
+    "recipient_user_id" INT     NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "title"             TEXT    NOT NULL,
+    "message"           TEXT    NOT NULL,
+    "money"             BIGINT  NOT NULL DEFAULT 0,
+    "is_read"           BOOLEAN NOT NULL DEFAULT FALSE,
+    "is_returned"       BOOLEAN NOT NULL DEFAULT FALSE,
+    "created_at"        TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    "expires_at"        TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX "parcel_recipient_user_id_idx" ON "parcel" ("recipient_user_id");
+CREATE INDEX "parcel_expires_at_idx" ON "parcel" ("expires_at");
+
+-- Items keep their ID when they are attached to a parcel and when they are received.
+CREATE TABLE "parcel_item"
+(
+    "id"          BIGINT PRIMARY KEY DEFAULT nextval('item_id_seq'),
+    "parcel_id"   BIGINT NOT NULL REFERENCES "parcel" ON DELETE CASCADE,
+    "template_id" INT    NOT NULL,
+    "amount"      INT    NOT NULL DEFAULT 1,
+    "created_at"  TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX "parcel_item_parcel_id_idx" ON "parcel_item" ("parcel_id");
@@ -0,0 +1,2 @@
+-- Users that logged out while being dead are still dead after the next login.
+ALTER TABLE "user" ADD COLUMN "is_alive" BOOLEAN NOT NULL DEFAULT TRUE;
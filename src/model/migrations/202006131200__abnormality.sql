@@ -0,0 +1,9 @@
+-- Abnormalities that survive a relog. The remaining duration only runs out while the user is online.
+CREATE TABLE "user_abnormality"
+(
+    "user_id"        INT    NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "abnormality_id" INT    NOT NULL,
+    "stacks"         INT    NOT NULL DEFAULT 1,
+    "remaining"      BIGINT NOT NULL,
+    PRIMARY KEY ("user_id", "abnormality_id")
+);
@@ -0,0 +1,2 @@
+-- Experience of an user inside its current level.
+ALTER TABLE "user" ADD COLUMN "xp" BIGINT NOT NULL DEFAULT 0;
@@ -1,6 +1,21 @@
 /// Holds the logic to interact with the database. A `conn` can either be a ```sqlx::PgConnection```
 /// or a ```sqlx::Transaction``` by using ```&mut *tx```.
 pub mod account;
+pub mod blocked_user;
+pub mod dungeon_lockout;
+pub mod equipped_item;
+pub mod friend;
+pub mod guild;
+pub mod guild_logo;
+pub mod guild_war;
+pub mod inventory;
+pub mod item;
 pub mod loginticket;
+pub mod parcel;
+pub mod parcel_item;
+pub mod private_channel;
 pub mod user;
+pub mod user_abnormality;
 pub mod user_location;
+pub mod warehouse;
+pub mod warehouse_item;
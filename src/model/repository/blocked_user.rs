@@ -0,0 +1,253 @@
+/// Handles the block list of an user.
+use crate::model::entity::{BlockedUser, BlockedUserEntry};
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Creates a new blocked user entry.
+pub async fn create(conn: &mut PgConnection, blocked_user: &BlockedUser) -> Result<BlockedUser> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "blocked_user" ("user_id", "blocked_user_id", "memo") VALUES ($1, $2, $3) RETURNING *"#,
+    )
+    .bind(&blocked_user.user_id)
+    .bind(&blocked_user.blocked_user_id)
+    .bind(&blocked_user.memo)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Updates the memo of a blocked user entry.
+pub async fn update(conn: &mut PgConnection, blocked_user: &BlockedUser) -> Result<BlockedUser> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "blocked_user" SET
+            "memo" = $1
+            WHERE "user_id" = $2 AND "blocked_user_id" = $3
+            RETURNING *"#,
+    )
+    .bind(&blocked_user.memo)
+    .bind(&blocked_user.user_id)
+    .bind(&blocked_user.blocked_user_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Finds the blocked user entry of an user.
+pub async fn get(
+    conn: &mut PgConnection,
+    user_id: i32,
+    blocked_user_id: i32,
+) -> Result<BlockedUser> {
+    Ok(sqlx::query_as::<_, BlockedUser>(
+        r#"SELECT * FROM "blocked_user" WHERE "user_id" = $1 AND "blocked_user_id" = $2"#,
+    )
+    .bind(user_id)
+    .bind(blocked_user_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get all users an user has blocked.
+pub async fn list(conn: &mut PgConnection, user_id: i32) -> Result<Vec<BlockedUser>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "blocked_user" WHERE "user_id" = $1 ORDER BY "created_at", "blocked_user_id""#,
+    )
+    .bind(user_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Get all users an user has blocked together with their name, level and class.
+pub async fn list_entries(conn: &mut PgConnection, user_id: i32) -> Result<Vec<BlockedUserEntry>> {
+    Ok(sqlx::query_as(
+        r#"SELECT "b"."blocked_user_id", "u"."name", "u"."level", "u"."class", "b"."memo"
+        FROM "blocked_user" "b"
+        INNER JOIN "user" "u" ON "u"."id" = "b"."blocked_user_id"
+        WHERE "b"."user_id" = $1
+        ORDER BY "b"."created_at", "b"."blocked_user_id""#,
+    )
+    .bind(user_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Get the number of users an user has blocked.
+pub async fn get_blocked_user_count(conn: &mut PgConnection, user_id: i32) -> Result<i64> {
+    let (count,): (i64,) =
+        sqlx::query_as(r#"SELECT COUNT(1) FROM "blocked_user" WHERE "user_id" = $1"#)
+            .bind(user_id)
+            .fetch_one(conn)
+            .await?;
+    Ok(count)
+}
+
+/// Checks if an user has blocked the other user.
+pub async fn is_blocked(
+    conn: &mut PgConnection,
+    user_id: i32,
+    blocked_user_id: i32,
+) -> Result<bool> {
+    let (found,): (bool,) = sqlx::query_as(
+        r#"SELECT EXISTS(SELECT 1 FROM "blocked_user" WHERE "user_id" = $1 AND "blocked_user_id" = $2)"#,
+    )
+    .bind(user_id)
+    .bind(blocked_user_id)
+    .fetch_one(conn)
+    .await?;
+    Ok(found)
+}
+
+/// Deletes the blocked user entry of an user.
+pub async fn delete(conn: &mut PgConnection, user_id: i32, blocked_user_id: i32) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "blocked_user" WHERE "user_id" = $1 AND "blocked_user_id" = $2"#)
+        .bind(user_id)
+        .bind(blocked_user_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use chrono::prelude::*;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection, num: i32) -> Result<User> {
+        let account = account::create(conn, &get_default_account(num)).await?;
+        user::create(conn, &get_default_user(&account, num)).await
+    }
+
+    fn get_default_blocked_user(user: &User, blocked_user: &User) -> BlockedUser {
+        BlockedUser {
+            user_id: user.id,
+            blocked_user_id: blocked_user.id,
+            memo: "Spammer".to_string(),
+            created_at: Utc.ymd(1995, 7, 8).and_hms(9, 10, 11),
+        }
+    }
+
+    #[test]
+    fn test_create_blocked_user() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let blocked_user = setup(&mut conn, 1).await?;
+                let org_blocked_user = get_default_blocked_user(&user, &blocked_user);
+
+                let db_blocked_user = create(&mut conn, &org_blocked_user).await?;
+
+                assert_eq!(org_blocked_user.user_id, db_blocked_user.user_id);
+                assert_eq!(
+                    org_blocked_user.blocked_user_id,
+                    db_blocked_user.blocked_user_id
+                );
+                assert_eq!(org_blocked_user.memo, db_blocked_user.memo);
+                assert_ne!(org_blocked_user.created_at, db_blocked_user.created_at);
+
+                assert!(is_blocked(&mut conn, user.id, blocked_user.id).await?);
+                assert!(!is_blocked(&mut conn, blocked_user.id, user.id).await?);
+                assert!(create(&mut conn, &org_blocked_user).await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_blocked_user() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let blocked_user = setup(&mut conn, 1).await?;
+                let mut db_blocked_user =
+                    create(&mut conn, &get_default_blocked_user(&user, &blocked_user)).await?;
+
+                db_blocked_user.memo = "Gold seller".to_string();
+                update(&mut conn, &db_blocked_user).await?;
+
+                assert_eq!(
+                    get(&mut conn, user.id, blocked_user.id).await?,
+                    db_blocked_user
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_list_blocked_users() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let blocked_user1 = setup(&mut conn, 1).await?;
+                let blocked_user2 = setup(&mut conn, 2).await?;
+                let db_blocked_user1 =
+                    create(&mut conn, &get_default_blocked_user(&user, &blocked_user1)).await?;
+                let db_blocked_user2 =
+                    create(&mut conn, &get_default_blocked_user(&user, &blocked_user2)).await?;
+                create(&mut conn, &get_default_blocked_user(&blocked_user1, &user)).await?;
+
+                assert_eq!(
+                    list_entries(&mut conn, user.id).await?,
+                    vec![
+                        BlockedUserEntry {
+                            blocked_user_id: blocked_user1.id,
+                            name: blocked_user1.name.clone(),
+                            level: blocked_user1.level,
+                            class: blocked_user1.class,
+                            memo: db_blocked_user1.memo.clone(),
+                        },
+                        BlockedUserEntry {
+                            blocked_user_id: blocked_user2.id,
+                            name: blocked_user2.name.clone(),
+                            level: blocked_user2.level,
+                            class: blocked_user2.class,
+                            memo: db_blocked_user2.memo.clone(),
+                        },
+                    ]
+                );
+                assert_eq!(
+                    list(&mut conn, user.id).await?,
+                    vec![db_blocked_user1, db_blocked_user2]
+                );
+                assert_eq!(get_blocked_user_count(&mut conn, user.id).await?, 2);
+                assert_eq!(
+                    get_blocked_user_count(&mut conn, blocked_user2.id).await?,
+                    0
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_delete_blocked_user() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let blocked_user = setup(&mut conn, 1).await?;
+                create(&mut conn, &get_default_blocked_user(&user, &blocked_user)).await?;
+
+                delete(&mut conn, user.id, blocked_user.id).await?;
+
+                assert!(!is_blocked(&mut conn, user.id, blocked_user.id).await?);
+                assert!(get(&mut conn, user.id, blocked_user.id).await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+}
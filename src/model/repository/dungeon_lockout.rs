@@ -0,0 +1,179 @@
+/// Handles the dungeon lockouts of an user.
+use crate::model::entity::DungeonLockout;
+use crate::Result;
+use chrono::{DateTime, Utc};
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Counts an entry of an user into a dungeon. Once the lockout has been reset, the entry count
+/// starts again and the next reset happens at the given time.
+pub async fn add_entry(
+    conn: &mut PgConnection,
+    user_id: i32,
+    zone_id: i32,
+    reset_at: DateTime<Utc>,
+) -> Result<DungeonLockout> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "dungeon_lockout" ("user_id", "zone_id", "entry_count", "reset_at") VALUES ($1, $2, 1, $3)
+            ON CONFLICT ("user_id", "zone_id") DO UPDATE SET
+            "entry_count" = CASE WHEN "dungeon_lockout"."reset_at" > CURRENT_TIMESTAMP THEN "dungeon_lockout"."entry_count" + 1 ELSE 1 END,
+            "reset_at" = CASE WHEN "dungeon_lockout"."reset_at" > CURRENT_TIMESTAMP THEN "dungeon_lockout"."reset_at" ELSE $3 END
+            RETURNING *"#,
+    )
+    .bind(user_id)
+    .bind(zone_id)
+    .bind(reset_at)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Counts a clear of a dungeon by an user.
+pub async fn add_clear(
+    conn: &mut PgConnection,
+    user_id: i32,
+    zone_id: i32,
+) -> Result<DungeonLockout> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "dungeon_lockout" ("user_id", "zone_id", "clear_count") VALUES ($1, $2, 1)
+            ON CONFLICT ("user_id", "zone_id") DO UPDATE SET
+            "clear_count" = "dungeon_lockout"."clear_count" + 1
+            RETURNING *"#,
+    )
+    .bind(user_id)
+    .bind(zone_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get the number of entries of an user into a dungeon since the last reset of the lockout.
+pub async fn get_entry_count(conn: &mut PgConnection, user_id: i32, zone_id: i32) -> Result<i32> {
+    let (count,): (i32,) = sqlx::query_as(
+        r#"SELECT COALESCE((SELECT "entry_count" FROM "dungeon_lockout"
+            WHERE "user_id" = $1 AND "zone_id" = $2 AND "reset_at" > CURRENT_TIMESTAMP), 0)"#,
+    )
+    .bind(user_id)
+    .bind(zone_id)
+    .fetch_one(conn)
+    .await?;
+    Ok(count)
+}
+
+/// Get all dungeon lockouts of an user.
+pub async fn list(conn: &mut PgConnection, user_id: i32) -> Result<Vec<DungeonLockout>> {
+    Ok(
+        sqlx::query_as(
+            r#"SELECT * FROM "dungeon_lockout" WHERE "user_id" = $1 ORDER BY "zone_id""#,
+        )
+        .bind(user_id)
+        .fetch_all(conn)
+        .await?,
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use chrono::Duration;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection, num: i32) -> Result<User> {
+        let account = account::create(conn, &get_default_account(num)).await?;
+        user::create(conn, &get_default_user(&account, num)).await
+    }
+
+    #[test]
+    fn test_add_entry() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let reset_at = Utc::now() + Duration::hours(1);
+
+                let lockout = add_entry(&mut conn, user.id, 9713, reset_at).await?;
+                assert_eq!(lockout.entry_count, 1);
+                assert_eq!(lockout.clear_count, 0);
+
+                let lockout =
+                    add_entry(&mut conn, user.id, 9713, reset_at + Duration::hours(1)).await?;
+                assert_eq!(lockout.entry_count, 2);
+                assert_eq!(lockout.reset_at.timestamp(), reset_at.timestamp());
+
+                assert_eq!(get_entry_count(&mut conn, user.id, 9713).await?, 2);
+                assert_eq!(get_entry_count(&mut conn, user.id, 9714).await?, 0);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_add_entry_after_reset() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+
+                add_entry(&mut conn, user.id, 9713, Utc::now() - Duration::hours(1)).await?;
+                add_entry(&mut conn, user.id, 9713, Utc::now() - Duration::hours(1)).await?;
+                assert_eq!(get_entry_count(&mut conn, user.id, 9713).await?, 0);
+
+                let reset_at = Utc::now() + Duration::hours(1);
+                let lockout = add_entry(&mut conn, user.id, 9713, reset_at).await?;
+                assert_eq!(lockout.entry_count, 1);
+                assert_eq!(lockout.reset_at.timestamp(), reset_at.timestamp());
+                assert_eq!(get_entry_count(&mut conn, user.id, 9713).await?, 1);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_add_clear() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+
+                let lockout = add_clear(&mut conn, user.id, 9713).await?;
+                assert_eq!(lockout.clear_count, 1);
+                assert_eq!(lockout.entry_count, 0);
+
+                add_entry(&mut conn, user.id, 9713, Utc::now() + Duration::hours(1)).await?;
+                let lockout = add_clear(&mut conn, user.id, 9713).await?;
+                assert_eq!(lockout.clear_count, 2);
+                assert_eq!(lockout.entry_count, 1);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_list_dungeon_lockouts() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let other_user = setup(&mut conn, 1).await?;
+                let reset_at = Utc::now() + Duration::hours(1);
+
+                let lockout2 = add_entry(&mut conn, user.id, 9714, reset_at).await?;
+                let lockout1 = add_clear(&mut conn, user.id, 9713).await?;
+                add_entry(&mut conn, other_user.id, 9713, reset_at).await?;
+
+                assert_eq!(list(&mut conn, user.id).await?, vec![lockout1, lockout2]);
+                assert!(list(&mut conn, 1_000_000).await?.is_empty());
+
+                Ok(())
+            })
+        })
+    }
+}
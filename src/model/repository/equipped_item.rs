@@ -0,0 +1,226 @@
+/// Handles the equipped items of an user.
+use crate::model::entity::EquippedItem;
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Equips an item. The equipped item keeps the ID and creation date of the item.
+pub async fn create(conn: &mut PgConnection, item: &EquippedItem) -> Result<EquippedItem> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "equipped_item" ("id", "user_id", "preset", "slot", "template_id", "created_at") VALUES ($1, $2, $3, $4, $5, $6) RETURNING *"#,
+    )
+    .bind(&item.id)
+    .bind(&item.user_id)
+    .bind(&item.preset)
+    .bind(&item.slot)
+    .bind(&item.template_id)
+    .bind(&item.created_at)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get the equipped items of the given preset of an user.
+pub async fn list_by_user_id_and_preset(
+    conn: &mut PgConnection,
+    user_id: i32,
+    preset: i32,
+) -> Result<Vec<EquippedItem>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "equipped_item" WHERE "user_id" = $1 AND "preset" = $2 ORDER BY "slot""#,
+    )
+    .bind(user_id)
+    .bind(preset)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Get the equipped items of the active preset of an user.
+pub async fn list_active_by_user_id(
+    conn: &mut PgConnection,
+    user_id: i32,
+) -> Result<Vec<EquippedItem>> {
+    Ok(sqlx::query_as(
+        r#"SELECT "equipped_item".* FROM "equipped_item"
+        JOIN "inventory" ON "inventory"."user_id" = "equipped_item"."user_id" AND "inventory"."equipment_preset" = "equipped_item"."preset"
+        WHERE "equipped_item"."user_id" = $1 ORDER BY "equipped_item"."slot""#,
+    )
+    .bind(user_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Removes an item from the equipment.
+pub async fn delete(conn: &mut PgConnection, id: i64) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "equipped_item" WHERE "id" = $1"#)
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::inventory::tests::get_default_inventory;
+    use crate::model::repository::item::tests::get_default_item;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, inventory, item, user};
+    use crate::model::tests::db_test;
+    use crate::model::EquipmentSlot;
+    use crate::Result;
+    use async_std::task;
+    use chrono::Utc;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection) -> Result<User> {
+        let account = account::create(conn, &get_default_account(0)).await?;
+        let user = user::create(conn, &get_default_user(&account, 0)).await?;
+        inventory::create(conn, &get_default_inventory(&user)).await?;
+        Ok(user)
+    }
+
+    pub fn get_default_equipped_item(
+        user: &User,
+        id: i64,
+        preset: i32,
+        slot: EquipmentSlot,
+    ) -> EquippedItem {
+        EquippedItem {
+            id,
+            user_id: user.id,
+            preset,
+            slot,
+            template_id: 10001,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_create_equipped_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+
+                let equipped_item = create(
+                    &mut conn,
+                    &get_default_equipped_item(&user, 100, 0, EquipmentSlot::Weapon),
+                )
+                .await?;
+                assert_eq!(equipped_item.id, 100);
+                assert_eq!(equipped_item.slot, EquipmentSlot::Weapon);
+                assert_eq!(equipped_item.template_id, 10001);
+
+                // Every slot of a preset can only hold one item
+                assert!(create(
+                    &mut conn,
+                    &get_default_equipped_item(&user, 101, 0, EquipmentSlot::Weapon)
+                )
+                .await
+                .is_err());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_equip_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+                let item = item::create(&mut conn, &get_default_item(&user, 0)).await?;
+
+                let mut tx = conn.begin().await?;
+                item::delete(&mut tx, item.id).await?;
+                let mut equipped_item =
+                    get_default_equipped_item(&user, item.id, 0, EquipmentSlot::Body);
+                equipped_item.template_id = item.template_id;
+                equipped_item.created_at = item.created_at;
+                let equipped_item = create(&mut tx, &equipped_item).await?;
+                let mut conn = tx.commit().await?;
+
+                assert_eq!(equipped_item.id, item.id);
+                assert_eq!(equipped_item.created_at, item.created_at);
+                assert!(item::list_by_user_id(&mut conn, user.id).await?.is_empty());
+                assert_eq!(
+                    list_active_by_user_id(&mut conn, user.id).await?,
+                    vec![equipped_item]
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_list_equipped_items() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+
+                assert!(list_active_by_user_id(&mut conn, user.id).await?.is_empty());
+
+                let feet = create(
+                    &mut conn,
+                    &get_default_equipped_item(&user, 100, 0, EquipmentSlot::Feet),
+                )
+                .await?;
+                let weapon = create(
+                    &mut conn,
+                    &get_default_equipped_item(&user, 101, 0, EquipmentSlot::Weapon),
+                )
+                .await?;
+                let other_weapon = create(
+                    &mut conn,
+                    &get_default_equipped_item(&user, 102, 1, EquipmentSlot::Weapon),
+                )
+                .await?;
+
+                assert_eq!(
+                    list_active_by_user_id(&mut conn, user.id).await?,
+                    vec![weapon.clone(), feet.clone()]
+                );
+                assert_eq!(
+                    list_by_user_id_and_preset(&mut conn, user.id, 1).await?,
+                    vec![other_weapon.clone()]
+                );
+
+                // Switch the active preset
+                let mut user_inventory = inventory::get_by_user_id(&mut conn, user.id).await?;
+                user_inventory.equipment_preset = 1;
+                inventory::update(&mut conn, &user_inventory).await?;
+                assert_eq!(
+                    list_active_by_user_id(&mut conn, user.id).await?,
+                    vec![other_weapon]
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_delete_equipped_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+                let equipped_item = create(
+                    &mut conn,
+                    &get_default_equipped_item(&user, 100, 0, EquipmentSlot::Weapon),
+                )
+                .await?;
+
+                delete(&mut conn, equipped_item.id).await?;
+                assert!(list_active_by_user_id(&mut conn, user.id).await?.is_empty());
+
+                Ok(())
+            })
+        })
+    }
+}
@@ -0,0 +1,403 @@
+/// Handles the friends, friend groups and friend requests of an user.
+use crate::model::entity::{Friend, FriendGroup, FriendRequest};
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Creates a new friend entry.
+pub async fn create(conn: &mut PgConnection, friend: &Friend) -> Result<Friend> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "friend" ("user_id", "friend_id", "group_id", "memo") VALUES ($1, $2, $3, $4) RETURNING *"#,
+    )
+    .bind(&friend.user_id)
+    .bind(&friend.friend_id)
+    .bind(&friend.group_id)
+    .bind(&friend.memo)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Updates the group and memo of a friend entry.
+pub async fn update(conn: &mut PgConnection, friend: &Friend) -> Result<Friend> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "friend" SET
+            "group_id" = $1,
+            "memo" = $2
+            WHERE "user_id" = $3 AND "friend_id" = $4
+            RETURNING *"#,
+    )
+    .bind(&friend.group_id)
+    .bind(&friend.memo)
+    .bind(&friend.user_id)
+    .bind(&friend.friend_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Finds the friend entry of an user.
+pub async fn get(conn: &mut PgConnection, user_id: i32, friend_id: i32) -> Result<Friend> {
+    Ok(sqlx::query_as::<_, Friend>(
+        r#"SELECT * FROM "friend" WHERE "user_id" = $1 AND "friend_id" = $2"#,
+    )
+    .bind(user_id)
+    .bind(friend_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get all friends of an user.
+pub async fn list(conn: &mut PgConnection, user_id: i32) -> Result<Vec<Friend>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "friend" WHERE "user_id" = $1 ORDER BY "created_at", "friend_id""#,
+    )
+    .bind(user_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Get the friend count of an user.
+pub async fn get_friend_count(conn: &mut PgConnection, user_id: i32) -> Result<i64> {
+    let (count,): (i64,) = sqlx::query_as(r#"SELECT COUNT(1) FROM "friend" WHERE "user_id" = $1"#)
+        .bind(user_id)
+        .fetch_one(conn)
+        .await?;
+    Ok(count)
+}
+
+/// Checks if the users are friends.
+pub async fn is_friend(conn: &mut PgConnection, user_id: i32, friend_id: i32) -> Result<bool> {
+    let (found,): (bool,) = sqlx::query_as(
+        r#"SELECT EXISTS(SELECT 1 FROM "friend" WHERE "user_id" = $1 AND "friend_id" = $2)"#,
+    )
+    .bind(user_id)
+    .bind(friend_id)
+    .fetch_one(conn)
+    .await?;
+    Ok(found)
+}
+
+/// Deletes the friend entry of an user.
+pub async fn delete(conn: &mut PgConnection, user_id: i32, friend_id: i32) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "friend" WHERE "user_id" = $1 AND "friend_id" = $2"#)
+        .bind(user_id)
+        .bind(friend_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Creates a new friend group.
+pub async fn create_group(conn: &mut PgConnection, group: &FriendGroup) -> Result<FriendGroup> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "friend_group" ("user_id", "name") VALUES ($1, $2) RETURNING *"#,
+    )
+    .bind(&group.user_id)
+    .bind(&group.name)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Updates the name of a friend group.
+pub async fn update_group(conn: &mut PgConnection, group: &FriendGroup) -> Result<FriendGroup> {
+    Ok(
+        sqlx::query_as(r#"UPDATE "friend_group" SET "name" = $1 WHERE "id" = $2 RETURNING *"#)
+            .bind(&group.name)
+            .bind(&group.id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Finds a friend group by id.
+pub async fn get_group_by_id(conn: &mut PgConnection, id: i32) -> Result<FriendGroup> {
+    Ok(
+        sqlx::query_as::<_, FriendGroup>(r#"SELECT * FROM "friend_group" WHERE "id" = $1"#)
+            .bind(id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Get all friend groups of an user.
+pub async fn list_groups(conn: &mut PgConnection, user_id: i32) -> Result<Vec<FriendGroup>> {
+    Ok(
+        sqlx::query_as(r#"SELECT * FROM "friend_group" WHERE "user_id" = $1 ORDER BY "id""#)
+            .bind(user_id)
+            .fetch_all(conn)
+            .await?,
+    )
+}
+
+/// Deletes a friend group. The friends of the group are moved out of the group.
+pub async fn delete_group_by_id(conn: &mut PgConnection, id: i32) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "friend_group" WHERE "id" = $1"#)
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Creates a friend request or replaces the message of an existing request.
+pub async fn upsert_request(
+    conn: &mut PgConnection,
+    request: &FriendRequest,
+) -> Result<FriendRequest> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "friend_request" ("user_id", "friend_id", "message") VALUES ($1, $2, $3)
+        ON CONFLICT ("user_id", "friend_id") DO UPDATE SET "message" = $3, "created_at" = CURRENT_TIMESTAMP
+        RETURNING *"#,
+    )
+    .bind(&request.user_id)
+    .bind(&request.friend_id)
+    .bind(&request.message)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Finds the friend request an user send to another user.
+pub async fn get_request(
+    conn: &mut PgConnection,
+    user_id: i32,
+    friend_id: i32,
+) -> Result<FriendRequest> {
+    Ok(sqlx::query_as::<_, FriendRequest>(
+        r#"SELECT * FROM "friend_request" WHERE "user_id" = $1 AND "friend_id" = $2"#,
+    )
+    .bind(user_id)
+    .bind(friend_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get all friend requests an user received.
+pub async fn list_received_requests(
+    conn: &mut PgConnection,
+    friend_id: i32,
+) -> Result<Vec<FriendRequest>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "friend_request" WHERE "friend_id" = $1 ORDER BY "created_at""#,
+    )
+    .bind(friend_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Deletes the friend request an user send to another user.
+pub async fn delete_request(conn: &mut PgConnection, user_id: i32, friend_id: i32) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "friend_request" WHERE "user_id" = $1 AND "friend_id" = $2"#)
+        .bind(user_id)
+        .bind(friend_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use chrono::prelude::*;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection, num: i32) -> Result<User> {
+        let account = account::create(conn, &get_default_account(num)).await?;
+        user::create(conn, &get_default_user(&account, num)).await
+    }
+
+    fn get_default_friend(user: &User, friend: &User) -> Friend {
+        Friend {
+            user_id: user.id,
+            friend_id: friend.id,
+            group_id: None,
+            memo: "".to_string(),
+            created_at: Utc.ymd(1995, 7, 8).and_hms(9, 10, 11),
+        }
+    }
+
+    fn get_default_request(user: &User, friend: &User) -> FriendRequest {
+        FriendRequest {
+            user_id: user.id,
+            friend_id: friend.id,
+            message: "Hi".to_string(),
+            created_at: Utc.ymd(1995, 7, 8).and_hms(9, 10, 11),
+        }
+    }
+
+    #[test]
+    fn test_create_friend() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let friend = setup(&mut conn, 1).await?;
+                let org_friend = get_default_friend(&user, &friend);
+
+                let db_friend = create(&mut conn, &org_friend).await?;
+
+                assert_eq!(org_friend.user_id, db_friend.user_id);
+                assert_eq!(org_friend.friend_id, db_friend.friend_id);
+                assert_eq!(org_friend.group_id, db_friend.group_id);
+                assert_eq!(org_friend.memo, db_friend.memo);
+                assert_ne!(org_friend.created_at, db_friend.created_at);
+
+                assert!(is_friend(&mut conn, user.id, friend.id).await?);
+                assert!(!is_friend(&mut conn, friend.id, user.id).await?);
+                assert!(create(&mut conn, &org_friend).await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_friend() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let friend = setup(&mut conn, 1).await?;
+                let mut db_friend = create(&mut conn, &get_default_friend(&user, &friend)).await?;
+                let group = create_group(
+                    &mut conn,
+                    &FriendGroup {
+                        id: -1,
+                        user_id: user.id,
+                        name: "Guild".to_string(),
+                    },
+                )
+                .await?;
+
+                db_friend.group_id = Some(group.id);
+                db_friend.memo = "Tank".to_string();
+                update(&mut conn, &db_friend).await?;
+
+                assert_eq!(get(&mut conn, user.id, friend.id).await?, db_friend);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_list_friends() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let friend1 = setup(&mut conn, 1).await?;
+                let friend2 = setup(&mut conn, 2).await?;
+                let db_friend1 = create(&mut conn, &get_default_friend(&user, &friend1)).await?;
+                let db_friend2 = create(&mut conn, &get_default_friend(&user, &friend2)).await?;
+                create(&mut conn, &get_default_friend(&friend1, &user)).await?;
+
+                assert_eq!(
+                    list(&mut conn, user.id).await?,
+                    vec![db_friend1, db_friend2]
+                );
+                assert_eq!(get_friend_count(&mut conn, user.id).await?, 2);
+                assert_eq!(get_friend_count(&mut conn, friend2.id).await?, 0);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_delete_friend() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let friend = setup(&mut conn, 1).await?;
+                create(&mut conn, &get_default_friend(&user, &friend)).await?;
+                create(&mut conn, &get_default_friend(&friend, &user)).await?;
+
+                delete(&mut conn, user.id, friend.id).await?;
+
+                assert!(!is_friend(&mut conn, user.id, friend.id).await?);
+                assert!(is_friend(&mut conn, friend.id, user.id).await?);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_friend_groups() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let friend = setup(&mut conn, 1).await?;
+
+                let mut group = create_group(
+                    &mut conn,
+                    &FriendGroup {
+                        id: -1,
+                        user_id: user.id,
+                        name: "Guild".to_string(),
+                    },
+                )
+                .await?;
+                assert_ne!(group.id, -1);
+
+                group.name = "Raid".to_string();
+                update_group(&mut conn, &group).await?;
+                assert_eq!(get_group_by_id(&mut conn, group.id).await?, group);
+                assert_eq!(list_groups(&mut conn, user.id).await?, vec![group.clone()]);
+
+                let mut db_friend = get_default_friend(&user, &friend);
+                db_friend.group_id = Some(group.id);
+                create(&mut conn, &db_friend).await?;
+
+                delete_group_by_id(&mut conn, group.id).await?;
+
+                assert!(list_groups(&mut conn, user.id).await?.is_empty());
+                assert_eq!(get(&mut conn, user.id, friend.id).await?.group_id, None);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_friend_requests() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let friend = setup(&mut conn, 1).await?;
+
+                let db_request =
+                    upsert_request(&mut conn, &get_default_request(&user, &friend)).await?;
+                assert_eq!(db_request.message, "Hi");
+
+                let mut request = get_default_request(&user, &friend);
+                request.message = "Hello".to_string();
+                let db_request = upsert_request(&mut conn, &request).await?;
+                assert_eq!(db_request.message, "Hello");
+
+                assert_eq!(
+                    get_request(&mut conn, user.id, friend.id).await?,
+                    db_request
+                );
+                assert_eq!(
+                    list_received_requests(&mut conn, friend.id).await?,
+                    vec![db_request]
+                );
+                assert!(list_received_requests(&mut conn, user.id).await?.is_empty());
+
+                delete_request(&mut conn, user.id, friend.id).await?;
+                assert!(get_request(&mut conn, user.id, friend.id).await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+}
@@ -0,0 +1,451 @@
+/// Handles the guilds, their groups and their members.
+use crate::model::entity::{Guild, GuildGroup, GuildMember};
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Creates a new guild.
+pub async fn create(conn: &mut PgConnection, guild: &Guild) -> Result<Guild> {
+    Ok(
+        sqlx::query_as(r#"INSERT INTO "guild" ("name", "master_id") VALUES ($1, $2) RETURNING *"#)
+            .bind(&guild.name)
+            .bind(&guild.master_id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Updates the name and the master of a guild.
+pub async fn update(conn: &mut PgConnection, guild: &Guild) -> Result<Guild> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "guild" SET
+            "name" = $1,
+            "master_id" = $2
+            WHERE "id" = $3
+            RETURNING *"#,
+    )
+    .bind(&guild.name)
+    .bind(&guild.master_id)
+    .bind(&guild.id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Finds a guild by id.
+pub async fn get_by_id(conn: &mut PgConnection, id: i32) -> Result<Guild> {
+    Ok(
+        sqlx::query_as::<_, Guild>(r#"SELECT * FROM "guild" WHERE "id" = $1"#)
+            .bind(id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Finds a guild by it's name (case insensitive).
+pub async fn get_by_name(conn: &mut PgConnection, name: &str) -> Result<Guild> {
+    Ok(
+        sqlx::query_as::<_, Guild>(r#"SELECT * FROM "guild" WHERE LOWER("name") = LOWER($1)"#)
+            .bind(name)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Finds the guild an user is a member of.
+pub async fn get_by_user_id(conn: &mut PgConnection, user_id: i32) -> Result<Guild> {
+    Ok(sqlx::query_as::<_, Guild>(
+        r#"SELECT "g".* FROM "guild" "g"
+        INNER JOIN "guild_member" "m" ON "m"."guild_id" = "g"."id"
+        WHERE "m"."user_id" = $1"#,
+    )
+    .bind(user_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Checks if a guild with the given name already exists (case insensitive).
+pub async fn is_name_taken(conn: &mut PgConnection, name: &str) -> Result<bool> {
+    let (found,): (bool,) =
+        sqlx::query_as(r#"SELECT EXISTS(SELECT 1 FROM "guild" WHERE LOWER("name") = LOWER($1))"#)
+            .bind(name)
+            .fetch_one(conn)
+            .await?;
+    Ok(found)
+}
+
+/// Deletes a guild with the given id. The groups and members are deleted with it.
+pub async fn delete_by_id(conn: &mut PgConnection, id: i32) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "guild" WHERE "id" = $1"#)
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Adds a member to a guild.
+pub async fn add_member(conn: &mut PgConnection, member: &GuildMember) -> Result<GuildMember> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "guild_member" ("user_id", "guild_id", "group_id") VALUES ($1, $2, $3) RETURNING *"#,
+    )
+    .bind(&member.user_id)
+    .bind(&member.guild_id)
+    .bind(&member.group_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Updates the group of a guild member.
+pub async fn update_member(conn: &mut PgConnection, member: &GuildMember) -> Result<GuildMember> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "guild_member" SET
+            "group_id" = $1
+            WHERE "user_id" = $2
+            RETURNING *"#,
+    )
+    .bind(&member.group_id)
+    .bind(&member.user_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Finds the guild membership of an user.
+pub async fn get_member(conn: &mut PgConnection, user_id: i32) -> Result<GuildMember> {
+    Ok(
+        sqlx::query_as::<_, GuildMember>(r#"SELECT * FROM "guild_member" WHERE "user_id" = $1"#)
+            .bind(user_id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Finds the member of a guild by the name of the user (case insensitive).
+pub async fn get_member_by_user_name(
+    conn: &mut PgConnection,
+    guild_id: i32,
+    user_name: &str,
+) -> Result<GuildMember> {
+    Ok(sqlx::query_as(
+        r#"SELECT "m".* FROM "guild_member" "m"
+        INNER JOIN "user" "u" ON "u"."id" = "m"."user_id"
+        WHERE "m"."guild_id" = $1 AND LOWER("u"."name") = LOWER($2)"#,
+    )
+    .bind(guild_id)
+    .bind(user_name)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get all members of a guild ordered by the time they joined.
+pub async fn list_members(conn: &mut PgConnection, guild_id: i32) -> Result<Vec<GuildMember>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "guild_member" WHERE "guild_id" = $1 ORDER BY "joined_at", "user_id""#,
+    )
+    .bind(guild_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Get the number of members of a guild.
+pub async fn get_member_count(conn: &mut PgConnection, guild_id: i32) -> Result<i64> {
+    let (count,): (i64,) =
+        sqlx::query_as(r#"SELECT COUNT(1) FROM "guild_member" WHERE "guild_id" = $1"#)
+            .bind(guild_id)
+            .fetch_one(conn)
+            .await?;
+    Ok(count)
+}
+
+/// Removes an user from it's guild.
+pub async fn remove_member(conn: &mut PgConnection, user_id: i32) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "guild_member" WHERE "user_id" = $1"#)
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Creates a new guild group.
+pub async fn create_group(conn: &mut PgConnection, group: &GuildGroup) -> Result<GuildGroup> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "guild_group" ("guild_id", "name", "authority") VALUES ($1, $2, $3) RETURNING *"#,
+    )
+    .bind(&group.guild_id)
+    .bind(&group.name)
+    .bind(&group.authority)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Updates the name and the authority of a guild group.
+pub async fn update_group(conn: &mut PgConnection, group: &GuildGroup) -> Result<GuildGroup> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "guild_group" SET
+            "name" = $1,
+            "authority" = $2
+            WHERE "id" = $3
+            RETURNING *"#,
+    )
+    .bind(&group.name)
+    .bind(&group.authority)
+    .bind(&group.id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Finds a guild group by id.
+pub async fn get_group_by_id(conn: &mut PgConnection, id: i32) -> Result<GuildGroup> {
+    Ok(
+        sqlx::query_as::<_, GuildGroup>(r#"SELECT * FROM "guild_group" WHERE "id" = $1"#)
+            .bind(id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Get all groups of a guild.
+pub async fn list_groups(conn: &mut PgConnection, guild_id: i32) -> Result<Vec<GuildGroup>> {
+    Ok(
+        sqlx::query_as(r#"SELECT * FROM "guild_group" WHERE "guild_id" = $1 ORDER BY "id""#)
+            .bind(guild_id)
+            .fetch_all(conn)
+            .await?,
+    )
+}
+
+/// Deletes a guild group. The members of the group are moved out of the group.
+pub async fn delete_group_by_id(conn: &mut PgConnection, id: i32) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "guild_group" WHERE "id" = $1"#)
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use chrono::prelude::*;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection, num: i32) -> Result<User> {
+        let account = account::create(conn, &get_default_account(num)).await?;
+        user::create(conn, &get_default_user(&account, num)).await
+    }
+
+    pub fn get_default_guild(master: &User) -> Guild {
+        Guild {
+            id: -1,
+            name: "Manhunter".to_string(),
+            master_id: master.id,
+            created_at: Utc.ymd(1995, 7, 8).and_hms(9, 10, 11),
+        }
+    }
+
+    pub fn get_default_group(guild: &Guild, name: &str, authority: i32) -> GuildGroup {
+        GuildGroup {
+            id: -1,
+            guild_id: guild.id,
+            name: name.to_string(),
+            authority,
+        }
+    }
+
+    pub fn get_default_member(guild: &Guild, user: &User) -> GuildMember {
+        GuildMember {
+            user_id: user.id,
+            guild_id: guild.id,
+            group_id: None,
+            joined_at: Utc.ymd(1995, 7, 8).and_hms(9, 10, 11),
+        }
+    }
+
+    #[test]
+    fn test_create_guild() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let master = setup(&mut conn, 0).await?;
+                let org_guild = get_default_guild(&master);
+
+                let db_guild = create(&mut conn, &org_guild).await?;
+
+                assert_ne!(org_guild.id, db_guild.id);
+                assert_eq!(org_guild.name, db_guild.name);
+                assert_eq!(org_guild.master_id, db_guild.master_id);
+                assert_ne!(org_guild.created_at, db_guild.created_at);
+
+                assert_eq!(get_by_id(&mut conn, db_guild.id).await?, db_guild);
+                assert_eq!(get_by_name(&mut conn, "MANHUNTER").await?, db_guild);
+                assert!(get_by_name(&mut conn, "Womanhunter").await.is_err());
+                assert!(is_name_taken(&mut conn, "MANHUNTER").await?);
+                assert!(!is_name_taken(&mut conn, "Womanhunter").await?);
+
+                // Guild names are unique (case insensitive).
+                let mut other_guild = get_default_guild(&master);
+                other_guild.name = "manhunter".to_string();
+                assert!(create(&mut conn, &other_guild).await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_guild() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let master = setup(&mut conn, 0).await?;
+                let other = setup(&mut conn, 1).await?;
+                let mut db_guild = create(&mut conn, &get_default_guild(&master)).await?;
+
+                db_guild.name = "Manhunter OG".to_string();
+                db_guild.master_id = other.id;
+                update(&mut conn, &db_guild).await?;
+
+                assert_eq!(get_by_id(&mut conn, db_guild.id).await?, db_guild);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_guild_members() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let master = setup(&mut conn, 0).await?;
+                let member = setup(&mut conn, 1).await?;
+                let other = setup(&mut conn, 2).await?;
+                let db_guild = create(&mut conn, &get_default_guild(&master)).await?;
+
+                let db_master =
+                    add_member(&mut conn, &get_default_member(&db_guild, &master)).await?;
+                let db_member =
+                    add_member(&mut conn, &get_default_member(&db_guild, &member)).await?;
+
+                // Users can only be a member of one guild.
+                let mut other_guild = get_default_guild(&other);
+                other_guild.name = "Womanhunter".to_string();
+                let db_other_guild = create(&mut conn, &other_guild).await?;
+                assert!(
+                    add_member(&mut conn, &get_default_member(&db_other_guild, &member))
+                        .await
+                        .is_err()
+                );
+
+                assert_eq!(get_member(&mut conn, member.id).await?, db_member);
+                assert_eq!(get_by_user_id(&mut conn, member.id).await?, db_guild);
+                assert!(get_by_user_id(&mut conn, other.id).await.is_err());
+                assert_eq!(
+                    get_member_by_user_name(&mut conn, db_guild.id, "TESTUSER-1").await?,
+                    db_member
+                );
+                assert_eq!(
+                    list_members(&mut conn, db_guild.id).await?,
+                    vec![db_master, db_member]
+                );
+                assert_eq!(get_member_count(&mut conn, db_guild.id).await?, 2);
+
+                remove_member(&mut conn, member.id).await?;
+
+                assert!(get_member(&mut conn, member.id).await.is_err());
+                assert_eq!(get_member_count(&mut conn, db_guild.id).await?, 1);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_guild_groups() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let master = setup(&mut conn, 0).await?;
+                let db_guild = create(&mut conn, &get_default_guild(&master)).await?;
+
+                let mut db_officers =
+                    create_group(&mut conn, &get_default_group(&db_guild, "Officers", 3)).await?;
+                let db_recruits =
+                    create_group(&mut conn, &get_default_group(&db_guild, "Recruits", 0)).await?;
+                assert_eq!(db_officers.guild_id, db_guild.id);
+                assert_eq!(db_officers.authority, 3);
+
+                db_officers.name = "Council".to_string();
+                db_officers.authority = 1;
+                update_group(&mut conn, &db_officers).await?;
+                assert_eq!(
+                    get_group_by_id(&mut conn, db_officers.id).await?,
+                    db_officers
+                );
+                assert_eq!(
+                    list_groups(&mut conn, db_guild.id).await?,
+                    vec![db_officers.clone(), db_recruits]
+                );
+
+                // Members are moved out of a deleted group.
+                let mut member = get_default_member(&db_guild, &master);
+                member.group_id = Some(db_officers.id);
+                add_member(&mut conn, &member).await?;
+                delete_group_by_id(&mut conn, db_officers.id).await?;
+
+                assert!(get_group_by_id(&mut conn, db_officers.id).await.is_err());
+                assert_eq!(get_member(&mut conn, master.id).await?.group_id, None);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_guild_member() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let master = setup(&mut conn, 0).await?;
+                let db_guild = create(&mut conn, &get_default_guild(&master)).await?;
+                let db_group =
+                    create_group(&mut conn, &get_default_group(&db_guild, "Officers", 3)).await?;
+                let mut db_member =
+                    add_member(&mut conn, &get_default_member(&db_guild, &master)).await?;
+
+                db_member.group_id = Some(db_group.id);
+                update_member(&mut conn, &db_member).await?;
+
+                assert_eq!(get_member(&mut conn, master.id).await?, db_member);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_delete_guild() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let master = setup(&mut conn, 0).await?;
+                let db_guild = create(&mut conn, &get_default_guild(&master)).await?;
+                create_group(&mut conn, &get_default_group(&db_guild, "Officers", 3)).await?;
+                add_member(&mut conn, &get_default_member(&db_guild, &master)).await?;
+
+                delete_by_id(&mut conn, db_guild.id).await?;
+
+                assert!(get_by_id(&mut conn, db_guild.id).await.is_err());
+                assert!(get_member(&mut conn, master.id).await.is_err());
+                assert!(list_groups(&mut conn, db_guild.id).await?.is_empty());
+
+                Ok(())
+            })
+        })
+    }
+}
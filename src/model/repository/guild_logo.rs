@@ -0,0 +1,115 @@
+/// Handles the logos of the guilds.
+use crate::model::entity::GuildLogo;
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Sets the logo of a guild. The version of the logo is increased if the guild already had one.
+pub async fn upsert(conn: &mut PgConnection, guild_id: i32, data: &[u8]) -> Result<GuildLogo> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "guild_logo" ("guild_id", "data") VALUES ($1, $2)
+            ON CONFLICT ("guild_id") DO UPDATE SET
+            "data" = EXCLUDED."data",
+            "version" = "guild_logo"."version" + 1,
+            "updated_at" = CURRENT_TIMESTAMP
+            RETURNING *"#,
+    )
+    .bind(guild_id)
+    .bind(data)
+    .fetch_one(conn)
+    .await?)
+}
+
+pub async fn get_by_guild_id(conn: &mut PgConnection, guild_id: i32) -> Result<GuildLogo> {
+    Ok(
+        sqlx::query_as::<_, GuildLogo>(r#"SELECT * FROM "guild_logo" WHERE "guild_id" = $1"#)
+            .bind(guild_id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+pub async fn delete_by_guild_id(conn: &mut PgConnection, guild_id: i32) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "guild_logo" WHERE "guild_id" = $1"#)
+        .bind(guild_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::Guild;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::guild::tests::get_default_guild;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, guild, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection, num: i32) -> Result<Guild> {
+        let account = account::create(conn, &get_default_account(num)).await?;
+        let user = user::create(conn, &get_default_user(&account, num)).await?;
+        let mut guild = get_default_guild(&user);
+        guild.name = format!("Manhunter{}", num);
+        guild::create(conn, &guild).await
+    }
+
+    pub fn get_default_logo_data() -> Vec<u8> {
+        let mut data = vec![
+            0x54, 0x45, 0x52, 0x41, 0x1, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0,
+        ];
+        data.extend_from_slice(&[0xff; 64]);
+        data
+    }
+
+    #[test]
+    fn test_upsert_guild_logo() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let guild = setup(&mut conn, 0).await?;
+
+                let logo = upsert(&mut conn, guild.id, &get_default_logo_data()).await?;
+                assert_eq!(logo.guild_id, guild.id);
+                assert_eq!(logo.data, get_default_logo_data());
+                assert_eq!(logo.version, 1);
+
+                let updated_logo = upsert(&mut conn, guild.id, &[0x54, 0x45, 0x52, 0x41]).await?;
+                assert_eq!(updated_logo.data, vec![0x54, 0x45, 0x52, 0x41]);
+                assert_eq!(updated_logo.version, 2);
+                assert!(updated_logo.updated_at >= logo.updated_at);
+
+                assert_eq!(get_by_guild_id(&mut conn, guild.id).await?, updated_logo);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_delete_guild_logo() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let guild = setup(&mut conn, 0).await?;
+                let other_guild = setup(&mut conn, 1).await?;
+                upsert(&mut conn, guild.id, &get_default_logo_data()).await?;
+                upsert(&mut conn, other_guild.id, &get_default_logo_data()).await?;
+
+                delete_by_guild_id(&mut conn, guild.id).await?;
+                assert!(get_by_guild_id(&mut conn, guild.id).await.is_err());
+                assert!(get_by_guild_id(&mut conn, other_guild.id).await.is_ok());
+
+                // Logos are deleted together with their guild.
+                guild::delete_by_id(&mut conn, other_guild.id).await?;
+                assert!(get_by_guild_id(&mut conn, other_guild.id).await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+}
@@ -0,0 +1,277 @@
+/// Handles the wars between guilds.
+use crate::model::entity::GuildWar;
+use crate::model::GuildWarState;
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Declares a new war.
+pub async fn create(conn: &mut PgConnection, war: &GuildWar) -> Result<GuildWar> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "guild_war" ("declaring_guild_id", "target_guild_id", "state") VALUES ($1, $2, $3) RETURNING *"#,
+    )
+    .bind(&war.declaring_guild_id)
+    .bind(&war.target_guild_id)
+    .bind(&war.state)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Updates the state of a war.
+pub async fn update(conn: &mut PgConnection, war: &GuildWar) -> Result<GuildWar> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "guild_war" SET
+            "state" = $1,
+            "surrendered_guild_id" = $2,
+            "updated_at" = $3
+            WHERE "id" = $4
+            RETURNING *"#,
+    )
+    .bind(&war.state)
+    .bind(&war.surrendered_guild_id)
+    .bind(&war.updated_at)
+    .bind(&war.id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Finds a war by id.
+pub async fn get_by_id(conn: &mut PgConnection, id: i32) -> Result<GuildWar> {
+    Ok(
+        sqlx::query_as::<_, GuildWar>(r#"SELECT * FROM "guild_war" WHERE "id" = $1"#)
+            .bind(id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Finds the war between two guilds that is neither surrendered nor expired. It doesn't matter
+/// which of the guilds declared the war.
+pub async fn get_ongoing(
+    conn: &mut PgConnection,
+    guild_id: i32,
+    other_guild_id: i32,
+) -> Result<GuildWar> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "guild_war"
+        WHERE (("declaring_guild_id" = $1 AND "target_guild_id" = $2)
+            OR ("declaring_guild_id" = $2 AND "target_guild_id" = $1))
+        AND "state" IN ('declared', 'accepted', 'active')"#,
+    )
+    .bind(guild_id)
+    .bind(other_guild_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get the number of wars of a guild that are neither surrendered nor expired.
+pub async fn get_ongoing_count(conn: &mut PgConnection, guild_id: i32) -> Result<i64> {
+    let (count,): (i64,) = sqlx::query_as(
+        r#"SELECT COUNT(1) FROM "guild_war"
+        WHERE ("declaring_guild_id" = $1 OR "target_guild_id" = $1)
+        AND "state" IN ('declared', 'accepted', 'active')"#,
+    )
+    .bind(guild_id)
+    .fetch_one(conn)
+    .await?;
+    Ok(count)
+}
+
+/// Get all wars with the given state ordered by the time they were declared.
+pub async fn list_by_state(conn: &mut PgConnection, state: GuildWarState) -> Result<Vec<GuildWar>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "guild_war" WHERE "state" = $1 ORDER BY "declared_at", "id""#,
+    )
+    .bind(state)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Counts a kill for the given guild of the war.
+pub async fn add_kill(conn: &mut PgConnection, id: i32, guild_id: i32) -> Result<GuildWar> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "guild_war" SET
+            "declaring_guild_kills" = "declaring_guild_kills" + CASE WHEN "declaring_guild_id" = $2 THEN 1 ELSE 0 END,
+            "target_guild_kills" = "target_guild_kills" + CASE WHEN "target_guild_id" = $2 THEN 1 ELSE 0 END
+            WHERE "id" = $1
+            RETURNING *"#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::Guild;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::guild::tests::get_default_guild;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, guild, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use chrono::{Duration, Utc};
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection, num: i32) -> Result<Guild> {
+        let account = account::create(conn, &get_default_account(num)).await?;
+        let user = user::create(conn, &get_default_user(&account, num)).await?;
+        let mut guild = get_default_guild(&user);
+        guild.name = format!("Manhunter{}", num);
+        guild::create(conn, &guild).await
+    }
+
+    pub fn get_default_war(declaring_guild: &Guild, target_guild: &Guild) -> GuildWar {
+        GuildWar {
+            id: -1,
+            declaring_guild_id: declaring_guild.id,
+            target_guild_id: target_guild.id,
+            state: GuildWarState::Declared,
+            declaring_guild_kills: 0,
+            target_guild_kills: 0,
+            surrendered_guild_id: None,
+            declared_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_create_guild_war() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let guild = setup(&mut conn, 0).await?;
+                let other_guild = setup(&mut conn, 1).await?;
+
+                let war = create(&mut conn, &get_default_war(&guild, &other_guild)).await?;
+                assert_eq!(war.declaring_guild_id, guild.id);
+                assert_eq!(war.target_guild_id, other_guild.id);
+                assert_eq!(war.state, GuildWarState::Declared);
+                assert_eq!(war.declaring_guild_kills, 0);
+                assert_eq!(war.target_guild_kills, 0);
+                assert_eq!(war.surrendered_guild_id, None);
+
+                assert_eq!(get_by_id(&mut conn, war.id).await?, war);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_guild_war() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let guild = setup(&mut conn, 0).await?;
+                let other_guild = setup(&mut conn, 1).await?;
+                let mut war = create(&mut conn, &get_default_war(&guild, &other_guild)).await?;
+
+                war.state = GuildWarState::Surrendered;
+                war.surrendered_guild_id = Some(other_guild.id);
+                war.updated_at = Utc::now() - Duration::hours(1);
+                let updated_war = update(&mut conn, &war).await?;
+                assert_eq!(updated_war.state, GuildWarState::Surrendered);
+                assert_eq!(updated_war.surrendered_guild_id, Some(other_guild.id));
+                assert_eq!(
+                    updated_war.updated_at.timestamp(),
+                    war.updated_at.timestamp()
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_get_ongoing_guild_war() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let guild = setup(&mut conn, 0).await?;
+                let other_guild = setup(&mut conn, 1).await?;
+                let third_guild = setup(&mut conn, 2).await?;
+
+                let mut war = create(&mut conn, &get_default_war(&guild, &other_guild)).await?;
+                assert_eq!(get_ongoing(&mut conn, guild.id, other_guild.id).await?, war);
+                assert_eq!(get_ongoing(&mut conn, other_guild.id, guild.id).await?, war);
+                assert!(get_ongoing(&mut conn, guild.id, third_guild.id)
+                    .await
+                    .is_err());
+                assert_eq!(get_ongoing_count(&mut conn, guild.id).await?, 1);
+                assert_eq!(get_ongoing_count(&mut conn, other_guild.id).await?, 1);
+                assert_eq!(get_ongoing_count(&mut conn, third_guild.id).await?, 0);
+
+                war.state = GuildWarState::Expired;
+                update(&mut conn, &war).await?;
+                assert!(get_ongoing(&mut conn, guild.id, other_guild.id)
+                    .await
+                    .is_err());
+                assert_eq!(get_ongoing_count(&mut conn, guild.id).await?, 0);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_list_guild_wars_by_state() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let guild = setup(&mut conn, 0).await?;
+                let other_guild = setup(&mut conn, 1).await?;
+                let third_guild = setup(&mut conn, 2).await?;
+
+                let war = create(&mut conn, &get_default_war(&guild, &other_guild)).await?;
+                let mut active_war =
+                    create(&mut conn, &get_default_war(&third_guild, &guild)).await?;
+                active_war.state = GuildWarState::Active;
+                let active_war = update(&mut conn, &active_war).await?;
+
+                assert_eq!(
+                    list_by_state(&mut conn, GuildWarState::Declared).await?,
+                    vec![war]
+                );
+                assert_eq!(
+                    list_by_state(&mut conn, GuildWarState::Active).await?,
+                    vec![active_war]
+                );
+                assert!(list_by_state(&mut conn, GuildWarState::Expired)
+                    .await?
+                    .is_empty());
+
+                // Wars are deleted together with their guilds.
+                guild::delete_by_id(&mut conn, guild.id).await?;
+                assert!(list_by_state(&mut conn, GuildWarState::Declared)
+                    .await?
+                    .is_empty());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_add_guild_war_kill() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let guild = setup(&mut conn, 0).await?;
+                let other_guild = setup(&mut conn, 1).await?;
+                let war = create(&mut conn, &get_default_war(&guild, &other_guild)).await?;
+
+                add_kill(&mut conn, war.id, guild.id).await?;
+                add_kill(&mut conn, war.id, other_guild.id).await?;
+                let war = add_kill(&mut conn, war.id, guild.id).await?;
+                assert_eq!(war.declaring_guild_kills, 2);
+                assert_eq!(war.target_guild_kills, 1);
+
+                Ok(())
+            })
+        })
+    }
+}
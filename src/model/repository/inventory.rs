@@ -0,0 +1,117 @@
+/// Handles the inventory of an user.
+use crate::model::entity::Inventory;
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Creates the inventory of a new user.
+pub async fn create(conn: &mut PgConnection, inventory: &Inventory) -> Result<Inventory> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "inventory" ("user_id", "size", "money", "equipment_preset") VALUES ($1, $2, $3, $4) RETURNING *"#,
+    )
+    .bind(&inventory.user_id)
+    .bind(&inventory.size)
+    .bind(&inventory.money)
+    .bind(&inventory.equipment_preset)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get the inventory of an user.
+pub async fn get_by_user_id(conn: &mut PgConnection, user_id: i32) -> Result<Inventory> {
+    Ok(
+        sqlx::query_as::<_, Inventory>(r#"SELECT * FROM "inventory" WHERE "user_id" = $1"#)
+            .bind(user_id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Updates the size, the money and the active equipment preset of an inventory.
+pub async fn update(conn: &mut PgConnection, inventory: &Inventory) -> Result<Inventory> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "inventory" SET "size" = $1, "money" = $2, "equipment_preset" = $3 WHERE "user_id" = $4 RETURNING *"#,
+    )
+    .bind(&inventory.size)
+    .bind(&inventory.money)
+    .bind(&inventory.equipment_preset)
+    .bind(&inventory.user_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection) -> Result<User> {
+        let account = account::create(conn, &get_default_account(0)).await?;
+        user::create(conn, &get_default_user(&account, 0)).await
+    }
+
+    pub fn get_default_inventory(user: &User) -> Inventory {
+        Inventory {
+            user_id: user.id,
+            size: 40,
+            money: 0,
+            equipment_preset: 0,
+        }
+    }
+
+    #[test]
+    fn test_create_inventory() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+
+                let inventory = create(&mut conn, &get_default_inventory(&user)).await?;
+                assert_eq!(inventory, get_default_inventory(&user));
+                assert_eq!(get_by_user_id(&mut conn, user.id).await?, inventory);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_inventory() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+                let mut inventory = create(&mut conn, &get_default_inventory(&user)).await?;
+
+                inventory.size = 48;
+                inventory.money = 1_000_000;
+                inventory.equipment_preset = 2;
+                assert_eq!(update(&mut conn, &inventory).await?, inventory);
+                assert_eq!(get_by_user_id(&mut conn, user.id).await?, inventory);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_get_missing_inventory() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+
+                assert!(get_by_user_id(&mut conn, user.id).await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+}
@@ -0,0 +1,230 @@
+/// Handles the items inside the inventory of an user.
+use crate::model::entity::Item;
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Creates a new item.
+pub async fn create(conn: &mut PgConnection, item: &Item) -> Result<Item> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "item" ("user_id", "template_id", "slot", "amount") VALUES ($1, $2, $3, $4) RETURNING *"#,
+    )
+    .bind(&item.user_id)
+    .bind(&item.template_id)
+    .bind(&item.slot)
+    .bind(&item.amount)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Creates an item that keeps the ID and creation date it had before. Used when an item returns
+/// into the inventory (e.g. when it's unequipped).
+pub async fn restore(conn: &mut PgConnection, item: &Item) -> Result<Item> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "item" ("id", "user_id", "template_id", "slot", "amount", "created_at") VALUES ($1, $2, $3, $4, $5, $6) RETURNING *"#,
+    )
+    .bind(&item.id)
+    .bind(&item.user_id)
+    .bind(&item.template_id)
+    .bind(&item.slot)
+    .bind(&item.amount)
+    .bind(&item.created_at)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Updates the slot and the amount of an item. Items can swap their slots with an other item if
+/// both are updated inside the same transaction.
+pub async fn update(conn: &mut PgConnection, item: &Item) -> Result<Item> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "item" SET "slot" = $1, "amount" = $2 WHERE "id" = $3 RETURNING *"#,
+    )
+    .bind(&item.slot)
+    .bind(&item.amount)
+    .bind(&item.id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Finds an item by id.
+pub async fn get_by_id(conn: &mut PgConnection, id: i64) -> Result<Item> {
+    Ok(
+        sqlx::query_as::<_, Item>(r#"SELECT * FROM "item" WHERE "id" = $1"#)
+            .bind(id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Get all items of an user ordered by their slot.
+pub async fn list_by_user_id(conn: &mut PgConnection, user_id: i32) -> Result<Vec<Item>> {
+    Ok(
+        sqlx::query_as(r#"SELECT * FROM "item" WHERE "user_id" = $1 ORDER BY "slot""#)
+            .bind(user_id)
+            .fetch_all(conn)
+            .await?,
+    )
+}
+
+pub async fn delete(conn: &mut PgConnection, id: i64) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "item" WHERE "id" = $1"#)
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use chrono::Utc;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection) -> Result<User> {
+        let account = account::create(conn, &get_default_account(0)).await?;
+        user::create(conn, &get_default_user(&account, 0)).await
+    }
+
+    pub fn get_default_item(user: &User, slot: i32) -> Item {
+        Item {
+            id: -1,
+            user_id: user.id,
+            template_id: 8005,
+            slot,
+            amount: 1,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_create_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+
+                let item = create(&mut conn, &get_default_item(&user, 3)).await?;
+                assert_eq!(item.user_id, user.id);
+                assert_eq!(item.template_id, 8005);
+                assert_eq!(item.slot, 3);
+                assert_eq!(item.amount, 1);
+                assert_eq!(get_by_id(&mut conn, item.id).await?, item);
+
+                // Every slot can only hold one item
+                assert!(create(&mut conn, &get_default_item(&user, 3))
+                    .await
+                    .is_err());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_restore_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+                let item = create(&mut conn, &get_default_item(&user, 3)).await?;
+                delete(&mut conn, item.id).await?;
+
+                assert_eq!(restore(&mut conn, &item).await?, item);
+                assert_eq!(get_by_id(&mut conn, item.id).await?, item);
+
+                // The ID can't be used twice
+                let mut other_item = item.clone();
+                other_item.slot = 4;
+                assert!(restore(&mut conn, &other_item).await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+                let mut item = create(&mut conn, &get_default_item(&user, 0)).await?;
+
+                item.slot = 5;
+                item.amount = 20;
+                assert_eq!(update(&mut conn, &item).await?, item);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_swap_items() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+                let mut item = create(&mut conn, &get_default_item(&user, 0)).await?;
+                let mut other_item = create(&mut conn, &get_default_item(&user, 1)).await?;
+
+                let mut tx = conn.begin().await?;
+                item.slot = 1;
+                other_item.slot = 0;
+                update(&mut tx, &item).await?;
+                update(&mut tx, &other_item).await?;
+                let mut conn = tx.commit().await?;
+
+                let items = list_by_user_id(&mut conn, user.id).await?;
+                assert_eq!(items, vec![other_item, item]);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_list_items_by_user_id() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+
+                assert!(list_by_user_id(&mut conn, user.id).await?.is_empty());
+
+                let item = create(&mut conn, &get_default_item(&user, 7)).await?;
+                let other_item = create(&mut conn, &get_default_item(&user, 2)).await?;
+                assert_eq!(
+                    list_by_user_id(&mut conn, user.id).await?,
+                    vec![other_item, item]
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_delete_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn).await?;
+                let item = create(&mut conn, &get_default_item(&user, 0)).await?;
+
+                delete(&mut conn, item.id).await?;
+                assert!(get_by_id(&mut conn, item.id).await.is_err());
+                assert!(list_by_user_id(&mut conn, user.id).await?.is_empty());
+
+                Ok(())
+            })
+        })
+    }
+}
@@ -0,0 +1,275 @@
+/// Handles the parcels (in-game mails) of users.
+use crate::model::entity::Parcel;
+use crate::Result;
+use chrono::{DateTime, Utc};
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Creates a new parcel.
+pub async fn create(conn: &mut PgConnection, parcel: &Parcel) -> Result<Parcel> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "parcel" ("sender_user_id", "sender_name", "recipient_user_id", "title", "message", "money", "expires_at") VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *"#,
+    )
+    .bind(&parcel.sender_user_id)
+    .bind(&parcel.sender_name)
+    .bind(&parcel.recipient_user_id)
+    .bind(&parcel.title)
+    .bind(&parcel.message)
+    .bind(&parcel.money)
+    .bind(&parcel.expires_at)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Updates the recipient, the money and the state of a parcel.
+pub async fn update(conn: &mut PgConnection, parcel: &Parcel) -> Result<Parcel> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "parcel" SET
+            "sender_user_id" = $1,
+            "sender_name" = $2,
+            "recipient_user_id" = $3,
+            "money" = $4,
+            "is_read" = $5,
+            "is_returned" = $6,
+            "expires_at" = $7
+            WHERE "id" = $8
+            RETURNING *"#,
+    )
+    .bind(&parcel.sender_user_id)
+    .bind(&parcel.sender_name)
+    .bind(&parcel.recipient_user_id)
+    .bind(&parcel.money)
+    .bind(&parcel.is_read)
+    .bind(&parcel.is_returned)
+    .bind(&parcel.expires_at)
+    .bind(&parcel.id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Finds a parcel by id. The parcel is locked until the end of the transaction, so that it's
+/// attachments can't be received and returned concurrently.
+pub async fn get_by_id(conn: &mut PgConnection, id: i64) -> Result<Parcel> {
+    Ok(
+        sqlx::query_as::<_, Parcel>(r#"SELECT * FROM "parcel" WHERE "id" = $1 FOR UPDATE"#)
+            .bind(id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Get all parcels of an user ordered by the newest first.
+pub async fn list_by_recipient_user_id(
+    conn: &mut PgConnection,
+    user_id: i32,
+) -> Result<Vec<Parcel>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "parcel" WHERE "recipient_user_id" = $1 ORDER BY "created_at" DESC, "id" DESC"#,
+    )
+    .bind(user_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Get the number of parcels of an user that were not read yet.
+pub async fn get_unread_count(conn: &mut PgConnection, user_id: i32) -> Result<i64> {
+    let (count,): (i64,) = sqlx::query_as(
+        r#"SELECT COUNT(1) FROM "parcel" WHERE "recipient_user_id" = $1 AND NOT "is_read""#,
+    )
+    .bind(user_id)
+    .fetch_one(conn)
+    .await?;
+    Ok(count)
+}
+
+/// Get all parcels that expired before the given time.
+pub async fn list_expired(conn: &mut PgConnection, now: DateTime<Utc>) -> Result<Vec<Parcel>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "parcel" WHERE "expires_at" <= $1 ORDER BY "expires_at", "id""#,
+    )
+    .bind(now)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Deletes a parcel and all of it's attached items.
+pub async fn delete(conn: &mut PgConnection, id: i64) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "parcel" WHERE "id" = $1"#)
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, parcel_item, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use chrono::{Duration, Utc};
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection) -> Result<(User, User)> {
+        let account = account::create(conn, &get_default_account(0)).await?;
+        let sender = user::create(conn, &get_default_user(&account, 0)).await?;
+        let recipient = user::create(conn, &get_default_user(&account, 1)).await?;
+        Ok((sender, recipient))
+    }
+
+    pub fn get_default_parcel(sender: Option<&User>, recipient: &User) -> Parcel {
+        Parcel {
+            id: -1,
+            sender_user_id: sender.map(|user| user.id),
+            sender_name: sender.map_or("System".to_string(), |user| user.name.clone()),
+            recipient_user_id: recipient.id,
+            title: "Title".to_string(),
+            message: "Message".to_string(),
+            money: 0,
+            is_read: false,
+            is_returned: false,
+            created_at: Utc::now(),
+            expires_at: Utc::now() + Duration::days(30),
+        }
+    }
+
+    #[test]
+    fn test_create_parcel() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let (sender, recipient) = setup(&mut conn).await?;
+
+                let mut new_parcel = get_default_parcel(Some(&sender), &recipient);
+                new_parcel.money = 1000;
+                let parcel = create(&mut conn, &new_parcel).await?;
+                assert_eq!(parcel.sender_user_id, Some(sender.id));
+                assert_eq!(parcel.sender_name, sender.name);
+                assert_eq!(parcel.recipient_user_id, recipient.id);
+                assert_eq!(parcel.money, 1000);
+                assert!(!parcel.is_read);
+                assert!(!parcel.is_returned);
+                assert_eq!(get_by_id(&mut conn, parcel.id).await?, parcel);
+
+                let system_parcel =
+                    create(&mut conn, &get_default_parcel(None, &recipient)).await?;
+                assert_eq!(system_parcel.sender_user_id, None);
+                assert_eq!(system_parcel.sender_name, "System");
+
+                assert_eq!(
+                    list_by_recipient_user_id(&mut conn, recipient.id).await?,
+                    vec![system_parcel, parcel]
+                );
+                assert!(list_by_recipient_user_id(&mut conn, sender.id)
+                    .await?
+                    .is_empty());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_parcel() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let (sender, recipient) = setup(&mut conn).await?;
+                let mut parcel =
+                    create(&mut conn, &get_default_parcel(Some(&sender), &recipient)).await?;
+                assert_eq!(get_unread_count(&mut conn, recipient.id).await?, 1);
+
+                parcel.is_read = true;
+                let mut parcel = update(&mut conn, &parcel).await?;
+                assert!(parcel.is_read);
+                assert_eq!(get_unread_count(&mut conn, recipient.id).await?, 0);
+
+                // Returned parcels are unread again
+                parcel.sender_user_id = Some(recipient.id);
+                parcel.sender_name = recipient.name.clone();
+                parcel.recipient_user_id = sender.id;
+                parcel.is_read = false;
+                parcel.is_returned = true;
+                let parcel = update(&mut conn, &parcel).await?;
+                assert_eq!(parcel.recipient_user_id, sender.id);
+                assert!(parcel.is_returned);
+                assert_eq!(get_unread_count(&mut conn, sender.id).await?, 1);
+                assert_eq!(get_unread_count(&mut conn, recipient.id).await?, 0);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_list_expired_parcels() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let (sender, recipient) = setup(&mut conn).await?;
+
+                let mut expired_parcel = get_default_parcel(Some(&sender), &recipient);
+                expired_parcel.expires_at = Utc::now() - Duration::days(1);
+                let expired_parcel = create(&mut conn, &expired_parcel).await?;
+                create(&mut conn, &get_default_parcel(Some(&sender), &recipient)).await?;
+
+                assert_eq!(
+                    list_expired(&mut conn, Utc::now()).await?,
+                    vec![expired_parcel]
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_delete_parcel() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let (sender, recipient) = setup(&mut conn).await?;
+                let parcel =
+                    create(&mut conn, &get_default_parcel(Some(&sender), &recipient)).await?;
+                parcel_item::create(
+                    &mut conn,
+                    &parcel_item::tests::get_default_parcel_item(&parcel),
+                )
+                .await?;
+
+                // The attached items are deleted together with the parcel
+                delete(&mut conn, parcel.id).await?;
+                assert!(get_by_id(&mut conn, parcel.id).await.is_err());
+                assert!(parcel_item::list_by_parcel_id(&mut conn, parcel.id)
+                    .await?
+                    .is_empty());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_sender_deleted() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let (sender, recipient) = setup(&mut conn).await?;
+                let parcel =
+                    create(&mut conn, &get_default_parcel(Some(&sender), &recipient)).await?;
+
+                // Parcels stay with the recipient, but can't be returned anymore
+                user::delete_by_id(&mut conn, sender.id).await?;
+                let parcel = get_by_id(&mut conn, parcel.id).await?;
+                assert_eq!(parcel.sender_user_id, None);
+                assert_eq!(parcel.sender_name, sender.name);
+
+                Ok(())
+            })
+        })
+    }
+}
@@ -0,0 +1,156 @@
+/// Handles the items attached to a parcel.
+use crate::model::entity::ParcelItem;
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Attaches a new item to a parcel.
+pub async fn create(conn: &mut PgConnection, item: &ParcelItem) -> Result<ParcelItem> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "parcel_item" ("parcel_id", "template_id", "amount") VALUES ($1, $2, $3) RETURNING *"#,
+    )
+    .bind(&item.parcel_id)
+    .bind(&item.template_id)
+    .bind(&item.amount)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Attaches an item that keeps the ID and creation date it had inside the inventory.
+pub async fn restore(conn: &mut PgConnection, item: &ParcelItem) -> Result<ParcelItem> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "parcel_item" ("id", "parcel_id", "template_id", "amount", "created_at") VALUES ($1, $2, $3, $4, $5) RETURNING *"#,
+    )
+    .bind(&item.id)
+    .bind(&item.parcel_id)
+    .bind(&item.template_id)
+    .bind(&item.amount)
+    .bind(&item.created_at)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get all items attached to a parcel in the order they were attached.
+pub async fn list_by_parcel_id(conn: &mut PgConnection, parcel_id: i64) -> Result<Vec<ParcelItem>> {
+    Ok(
+        sqlx::query_as(r#"SELECT * FROM "parcel_item" WHERE "parcel_id" = $1 ORDER BY "id""#)
+            .bind(parcel_id)
+            .fetch_all(conn)
+            .await?,
+    )
+}
+
+pub async fn delete(conn: &mut PgConnection, id: i64) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "parcel_item" WHERE "id" = $1"#)
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::Parcel;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::item::tests::get_default_item;
+    use crate::model::repository::parcel::tests::get_default_parcel;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, item, parcel, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use chrono::Utc;
+    use sqlx::PgConnection;
+
+    pub fn get_default_parcel_item(parcel: &Parcel) -> ParcelItem {
+        ParcelItem {
+            id: -1,
+            parcel_id: parcel.id,
+            template_id: 8005,
+            amount: 1,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_create_parcel_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let account = account::create(&mut conn, &get_default_account(0)).await?;
+                let user = user::create(&mut conn, &get_default_user(&account, 0)).await?;
+                let parcel = parcel::create(&mut conn, &get_default_parcel(None, &user)).await?;
+
+                let item = create(&mut conn, &get_default_parcel_item(&parcel)).await?;
+                let mut other_item = get_default_parcel_item(&parcel);
+                other_item.template_id = 10001;
+                other_item.amount = 20;
+                let other_item = create(&mut conn, &other_item).await?;
+
+                assert_eq!(item.parcel_id, parcel.id);
+                assert_eq!(item.template_id, 8005);
+                assert_eq!(other_item.amount, 20);
+                assert_eq!(
+                    list_by_parcel_id(&mut conn, parcel.id).await?,
+                    vec![item, other_item]
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_restore_parcel_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let account = account::create(&mut conn, &get_default_account(0)).await?;
+                let user = user::create(&mut conn, &get_default_user(&account, 0)).await?;
+                let parcel = parcel::create(&mut conn, &get_default_parcel(None, &user)).await?;
+                let inventory_item = item::create(&mut conn, &get_default_item(&user, 0)).await?;
+
+                // The item keeps it's ID when it's moved out of the inventory
+                let mut tx = conn.begin().await?;
+                item::delete(&mut tx, inventory_item.id).await?;
+                let item = restore(
+                    &mut tx,
+                    &ParcelItem {
+                        id: inventory_item.id,
+                        parcel_id: parcel.id,
+                        template_id: inventory_item.template_id,
+                        amount: inventory_item.amount,
+                        created_at: inventory_item.created_at,
+                    },
+                )
+                .await?;
+                let mut conn = tx.commit().await?;
+
+                assert_eq!(item.id, inventory_item.id);
+                assert_eq!(item.created_at, inventory_item.created_at);
+                assert_eq!(list_by_parcel_id(&mut conn, parcel.id).await?, vec![item]);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_delete_parcel_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let account = account::create(&mut conn, &get_default_account(0)).await?;
+                let user = user::create(&mut conn, &get_default_user(&account, 0)).await?;
+                let parcel = parcel::create(&mut conn, &get_default_parcel(None, &user)).await?;
+                let item = create(&mut conn, &get_default_parcel_item(&parcel)).await?;
+
+                delete(&mut conn, item.id).await?;
+                assert!(list_by_parcel_id(&mut conn, parcel.id).await?.is_empty());
+
+                Ok(())
+            })
+        })
+    }
+}
@@ -0,0 +1,336 @@
+/// Handles the private chat channels and their members.
+use crate::model::entity::{PrivateChannel, PrivateChannelMember};
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Creates a new private channel.
+pub async fn create(conn: &mut PgConnection, channel: &PrivateChannel) -> Result<PrivateChannel> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "private_channel" ("name", "password", "owner_user_id") VALUES ($1, $2, $3) RETURNING *"#,
+    )
+    .bind(&channel.name)
+    .bind(&channel.password)
+    .bind(&channel.owner_user_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Updates a private channel.
+pub async fn update(conn: &mut PgConnection, channel: &PrivateChannel) -> Result<PrivateChannel> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "private_channel" SET
+            "name" = $1,
+            "password" = $2,
+            "owner_user_id" = $3
+            WHERE "id" = $4
+            RETURNING *"#,
+    )
+    .bind(&channel.name)
+    .bind(&channel.password)
+    .bind(&channel.owner_user_id)
+    .bind(&channel.id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Finds a private channel by id.
+pub async fn get_by_id(conn: &mut PgConnection, id: i32) -> Result<PrivateChannel> {
+    Ok(
+        sqlx::query_as::<_, PrivateChannel>(r#"SELECT * FROM "private_channel" WHERE "id" = $1"#)
+            .bind(id)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
+/// Finds a private channel by name (case insensitive).
+pub async fn get_by_name(conn: &mut PgConnection, name: &str) -> Result<PrivateChannel> {
+    Ok(sqlx::query_as::<_, PrivateChannel>(
+        r#"SELECT * FROM "private_channel" WHERE LOWER("name") = LOWER($1)"#,
+    )
+    .bind(name)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Checks if a private channel with the given name already exists (case insensitive).
+pub async fn is_name_taken(conn: &mut PgConnection, name: &str) -> Result<bool> {
+    let (found,): (bool,) = sqlx::query_as(
+        r#"SELECT EXISTS(SELECT 1 FROM "private_channel" WHERE LOWER("name") = LOWER($1))"#,
+    )
+    .bind(name)
+    .fetch_one(conn)
+    .await?;
+    Ok(found)
+}
+
+/// Deletes a private channel with the given id. The members are deleted with it.
+pub async fn delete_by_id(conn: &mut PgConnection, id: i32) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "private_channel" WHERE "id" = $1"#)
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Adds a member to a private channel.
+pub async fn add_member(
+    conn: &mut PgConnection,
+    member: &PrivateChannelMember,
+) -> Result<PrivateChannelMember> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "private_channel_member" ("channel_id", "user_id", "slot") VALUES ($1, $2, $3) RETURNING *"#,
+    )
+    .bind(&member.channel_id)
+    .bind(&member.user_id)
+    .bind(&member.slot)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Removes a member from a private channel.
+pub async fn remove_member(conn: &mut PgConnection, channel_id: i32, user_id: i32) -> Result<()> {
+    sqlx::query(
+        r#"DELETE FROM "private_channel_member" WHERE "channel_id" = $1 AND "user_id" = $2"#,
+    )
+    .bind(channel_id)
+    .bind(user_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Get all members of a private channel ordered by the time they joined.
+pub async fn list_members(
+    conn: &mut PgConnection,
+    channel_id: i32,
+) -> Result<Vec<PrivateChannelMember>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "private_channel_member" WHERE "channel_id" = $1 ORDER BY "joined_at", "user_id""#,
+    )
+    .bind(channel_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Get all private channel memberships of an user.
+pub async fn list_by_user_id(
+    conn: &mut PgConnection,
+    user_id: i32,
+) -> Result<Vec<PrivateChannelMember>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "private_channel_member" WHERE "user_id" = $1 ORDER BY "slot""#,
+    )
+    .bind(user_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Finds the member of a private channel by the name of the user (case insensitive).
+pub async fn get_member_by_user_name(
+    conn: &mut PgConnection,
+    channel_id: i32,
+    user_name: &str,
+) -> Result<PrivateChannelMember> {
+    Ok(sqlx::query_as(
+        r#"SELECT "m".* FROM "private_channel_member" "m"
+        INNER JOIN "user" "u" ON "u"."id" = "m"."user_id"
+        WHERE "m"."channel_id" = $1 AND LOWER("u"."name") = LOWER($2)"#,
+    )
+    .bind(channel_id)
+    .bind(user_name)
+    .fetch_one(conn)
+    .await?)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use chrono::prelude::*;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection, num: i32) -> Result<User> {
+        let account = account::create(conn, &get_default_account(num)).await?;
+        user::create(conn, &get_default_user(&account, num)).await
+    }
+
+    pub fn get_default_private_channel(owner: &User) -> PrivateChannel {
+        PrivateChannel {
+            id: -1,
+            name: "Traders".to_string(),
+            password: Some(1234),
+            owner_user_id: owner.id,
+            created_at: Utc.ymd(1995, 7, 8).and_hms(9, 10, 11),
+        }
+    }
+
+    fn get_member(channel: &PrivateChannel, user: &User, slot: i32) -> PrivateChannelMember {
+        PrivateChannelMember {
+            channel_id: channel.id,
+            user_id: user.id,
+            slot,
+            joined_at: Utc.ymd(1995, 7, 8).and_hms(9, 10, 11),
+        }
+    }
+
+    #[test]
+    fn test_create_private_channel() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let owner = setup(&mut conn, 0).await?;
+                let org_channel = get_default_private_channel(&owner);
+
+                let db_channel = create(&mut conn, &org_channel).await?;
+
+                assert_ne!(org_channel.id, db_channel.id);
+                assert_eq!(org_channel.name, db_channel.name);
+                assert_eq!(org_channel.password, db_channel.password);
+                assert_eq!(org_channel.owner_user_id, db_channel.owner_user_id);
+                assert_ne!(org_channel.created_at, db_channel.created_at);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_create_private_channel_with_taken_name() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let owner = setup(&mut conn, 0).await?;
+                let mut channel = get_default_private_channel(&owner);
+                create(&mut conn, &channel).await?;
+
+                channel.name = "TRADERS".to_string();
+                assert!(is_name_taken(&mut conn, &channel.name).await?);
+                assert!(create(&mut conn, &channel).await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_private_channel() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let owner = setup(&mut conn, 0).await?;
+                let new_owner = setup(&mut conn, 1).await?;
+                let mut channel = create(&mut conn, &get_default_private_channel(&owner)).await?;
+
+                channel.name = "Crafters".to_string();
+                channel.password = None;
+                channel.owner_user_id = new_owner.id;
+                update(&mut conn, &channel).await?;
+
+                let db_channel = get_by_id(&mut conn, channel.id).await?;
+                assert_eq!(db_channel, channel);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_get_private_channel_by_name() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let owner = setup(&mut conn, 0).await?;
+                let channel = create(&mut conn, &get_default_private_channel(&owner)).await?;
+
+                let db_channel = get_by_name(&mut conn, "traders").await?;
+                assert_eq!(db_channel, channel);
+                assert!(get_by_name(&mut conn, "crafters").await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_delete_private_channel() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let owner = setup(&mut conn, 0).await?;
+                let channel = create(&mut conn, &get_default_private_channel(&owner)).await?;
+                add_member(&mut conn, &get_member(&channel, &owner, 1)).await?;
+
+                delete_by_id(&mut conn, channel.id).await?;
+
+                assert!(get_by_id(&mut conn, channel.id).await.is_err());
+                assert!(list_by_user_id(&mut conn, owner.id).await?.is_empty());
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_members() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let owner = setup(&mut conn, 0).await?;
+                let member = setup(&mut conn, 1).await?;
+                let channel = create(&mut conn, &get_default_private_channel(&owner)).await?;
+
+                let db_owner = add_member(&mut conn, &get_member(&channel, &owner, 1)).await?;
+                let db_member = add_member(&mut conn, &get_member(&channel, &member, 3)).await?;
+                assert_eq!(db_member.channel_id, channel.id);
+                assert_eq!(db_member.user_id, member.id);
+                assert_eq!(db_member.slot, 3);
+
+                let members = list_members(&mut conn, channel.id).await?;
+                assert_eq!(members, vec![db_owner.clone(), db_member.clone()]);
+
+                let memberships = list_by_user_id(&mut conn, member.id).await?;
+                assert_eq!(memberships, vec![db_member.clone()]);
+
+                let found = get_member_by_user_name(&mut conn, channel.id, "TESTUSER-1").await?;
+                assert_eq!(found, db_member);
+
+                remove_member(&mut conn, channel.id, member.id).await?;
+                let members = list_members(&mut conn, channel.id).await?;
+                assert_eq!(members, vec![db_owner]);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_member_slot_is_unique() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let owner = setup(&mut conn, 0).await?;
+                let channel = create(&mut conn, &get_default_private_channel(&owner)).await?;
+                let mut other_channel = get_default_private_channel(&owner);
+                other_channel.name = "Crafters".to_string();
+                let other_channel = create(&mut conn, &other_channel).await?;
+
+                add_member(&mut conn, &get_member(&channel, &owner, 1)).await?;
+                assert!(
+                    add_member(&mut conn, &get_member(&other_channel, &owner, 1))
+                        .await
+                        .is_err()
+                );
+
+                Ok(())
+            })
+        })
+    }
+}
@@ -1,6 +1,7 @@
 /// Handles the users of an account (the characters).
 use crate::model::entity::User;
 use crate::Result;
+use chrono::{DateTime, Utc};
 use sqlx::prelude::*;
 use sqlx::PgConnection;
 
@@ -8,7 +9,7 @@ use sqlx::PgConnection;
 pub async fn create(conn: &mut PgConnection, user: &User) -> Result<User> {
     Ok(sqlx::query_as(
         r#"INSERT INTO "user"
-        VALUES (DEFAULT, $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, DEFAULT, DEFAULT)
+        VALUES (DEFAULT, $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, DEFAULT, DEFAULT, $23, $24)
         RETURNING *"#,
     )
     .bind(&user.account_id)
@@ -33,6 +34,8 @@ pub async fn create(conn: &mut PgConnection, user: &User) -> Result<User> {
     .bind(&user.tutorial_state)
     .bind(&user.is_deleting)
     .bind(&user.delete_at)
+    .bind(&user.is_alive)
+    .bind(&user.xp)
     .fetch_one(conn)
     .await?)
 }
@@ -62,8 +65,10 @@ pub async fn update(conn: &mut PgConnection, user: &User) -> Result<User> {
             "tutorial_state" = $19,
             "is_deleting" = $20,
             "delete_at" = $21,
-            "last_logout_at" = $22
-            WHERE "id" = $23
+            "last_logout_at" = $22,
+            "is_alive" = $23,
+            "xp" = $24
+            WHERE "id" = $25
             RETURNING *"#,
     )
     .bind(&user.name)
@@ -88,6 +93,8 @@ pub async fn update(conn: &mut PgConnection, user: &User) -> Result<User> {
     .bind(&user.is_deleting)
     .bind(&user.delete_at)
     .bind(&user.last_logout_at)
+    .bind(&user.is_alive)
+    .bind(&user.xp)
     .bind(&user.id)
     .fetch_one(conn)
     .await?)
@@ -103,6 +110,52 @@ pub async fn update_lobby_slot(conn: &mut PgConnection, id: i32, position: i32)
     Ok(())
 }
 
+/// Updates the is_alive status of an user with the given ID.
+pub async fn update_is_alive(conn: &mut PgConnection, id: i32, is_alive: bool) -> Result<()> {
+    sqlx::query(r#"UPDATE "user" SET "is_alive" = $1 WHERE "id" = $2"#)
+        .bind(&is_alive)
+        .bind(&id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Updates the level, experience, rest bonus and playtime of an user with the given ID.
+pub async fn update_progress(
+    conn: &mut PgConnection,
+    id: i32,
+    level: i32,
+    xp: i64,
+    rest_bonus_xp: i64,
+    playtime: i64,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE "user" SET "level" = $1, "xp" = $2, "rest_bonus_xp" = $3, "playtime" = $4 WHERE "id" = $5"#,
+    )
+    .bind(&level)
+    .bind(&xp)
+    .bind(&rest_bonus_xp)
+    .bind(&playtime)
+    .bind(&id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Updates the last logout time of an user with the given ID.
+pub async fn update_last_logout_at(
+    conn: &mut PgConnection,
+    id: i32,
+    last_logout_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(r#"UPDATE "user" SET "last_logout_at" = $1 WHERE "id" = $2"#)
+        .bind(&last_logout_at)
+        .bind(&id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
 /// Finds an user by id.
 pub async fn get_by_id(conn: &mut PgConnection, id: i32) -> Result<User> {
     Ok(
@@ -113,6 +166,16 @@ pub async fn get_by_id(conn: &mut PgConnection, id: i32) -> Result<User> {
     )
 }
 
+/// Finds an user by name (case insensitive).
+pub async fn get_by_name(conn: &mut PgConnection, name: &str) -> Result<User> {
+    Ok(
+        sqlx::query_as::<_, User>(r#"SELECT * FROM "user" WHERE LOWER("name") = LOWER($1)"#)
+            .bind(name)
+            .fetch_one(conn)
+            .await?,
+    )
+}
+
 /// Get the user count of an account.
 pub async fn get_user_count(conn: &mut PgConnection, account_id: i64) -> Result<i64> {
     let (count,): (i64,) = sqlx::query_as(r#"SELECT COUNT(1) FROM "user" WHERE "account_id" = $1"#)
@@ -192,6 +255,7 @@ pub mod tests {
             laurel: 0,
             achievement_points: 0,
             playtime: 0,
+            xp: 0,
             rest_bonus_xp: 0,
             show_face: false,
             show_style: false,
@@ -202,6 +266,7 @@ pub mod tests {
             delete_at: None,
             last_logout_at: Utc.ymd(1995, 7, 8).and_hms(9, 10, 11),
             created_at: Utc.ymd(1995, 7, 8).and_hms(9, 10, 11),
+            is_alive: true,
         }
     }
 
@@ -230,6 +295,7 @@ pub mod tests {
                 assert_eq!(org_user.laurel, db_user.laurel);
                 assert_eq!(org_user.achievement_points, db_user.achievement_points);
                 assert_eq!(org_user.playtime, db_user.playtime);
+                assert_eq!(org_user.xp, db_user.xp);
                 assert_eq!(org_user.rest_bonus_xp, db_user.rest_bonus_xp);
                 assert_eq!(org_user.show_face, db_user.show_face);
                 assert_eq!(org_user.show_style, db_user.show_style);
@@ -240,6 +306,7 @@ pub mod tests {
                 assert_eq!(org_user.delete_at, db_user.delete_at);
                 assert_ne!(org_user.last_logout_at, db_user.last_logout_at);
                 assert_ne!(org_user.created_at, db_user.created_at);
+                assert_eq!(org_user.is_alive, db_user.is_alive);
 
                 Ok(())
             })
@@ -300,6 +367,66 @@ pub mod tests {
         })
     }
 
+    #[test]
+    fn test_update_is_alive() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let account = create_account(&mut conn).await?;
+                let db_user = create(&mut conn, &get_default_user(&account, 0)).await?;
+                assert!(db_user.is_alive);
+
+                update_is_alive(&mut conn, db_user.id, false).await?;
+                assert!(!get_by_id(&mut conn, db_user.id).await?.is_alive);
+
+                update_is_alive(&mut conn, db_user.id, true).await?;
+                assert!(get_by_id(&mut conn, db_user.id).await?.is_alive);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_progress() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let account = create_account(&mut conn).await?;
+                let db_user = create(&mut conn, &get_default_user(&account, 0)).await?;
+
+                update_progress(&mut conn, db_user.id, 12, 3400, 500, 7200).await?;
+                let updated_db_user = get_by_id(&mut conn, db_user.id).await?;
+                assert_eq!(updated_db_user.level, 12);
+                assert_eq!(updated_db_user.xp, 3400);
+                assert_eq!(updated_db_user.rest_bonus_xp, 500);
+                assert_eq!(updated_db_user.playtime, 7200);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_update_last_logout_at() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let account = create_account(&mut conn).await?;
+                let db_user = create(&mut conn, &get_default_user(&account, 0)).await?;
+
+                let logout_at = Utc.ymd(2020, 6, 14).and_hms(12, 0, 0);
+                update_last_logout_at(&mut conn, db_user.id, logout_at).await?;
+                assert_eq!(
+                    get_by_id(&mut conn, db_user.id).await?.last_logout_at,
+                    logout_at
+                );
+
+                Ok(())
+            })
+        })
+    }
+
     #[test]
     fn test_update_get_by_id() -> Result<()> {
         db_test(|db_string| {
@@ -320,6 +447,23 @@ pub mod tests {
         })
     }
 
+    #[test]
+    fn test_get_by_name() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let account = create_account(&mut conn).await?;
+                let db_user = create(&mut conn, &get_default_user(&account, 0)).await?;
+
+                let found_db_user = get_by_name(&mut conn, "TestUser-0").await?;
+                assert_eq!(found_db_user.id, db_user.id);
+                assert!(get_by_name(&mut conn, "testuser-1").await.is_err());
+
+                Ok(())
+            })
+        })
+    }
+
     #[test]
     fn test_list_users() -> Result<()> {
         db_test(|db_string| {
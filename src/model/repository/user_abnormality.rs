@@ -0,0 +1,123 @@
+/// Handles the abnormalities of an user that survive a relog.
+use crate::model::entity::UserAbnormality;
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Stores an abnormality of an user.
+pub async fn create(
+    conn: &mut PgConnection,
+    abnormality: &UserAbnormality,
+) -> Result<UserAbnormality> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "user_abnormality" ("user_id", "abnormality_id", "stacks", "remaining") VALUES ($1, $2, $3, $4) RETURNING *"#,
+    )
+    .bind(&abnormality.user_id)
+    .bind(&abnormality.abnormality_id)
+    .bind(&abnormality.stacks)
+    .bind(&abnormality.remaining)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get all stored abnormalities of an user.
+pub async fn list_by_user_id(
+    conn: &mut PgConnection,
+    user_id: i32,
+) -> Result<Vec<UserAbnormality>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "user_abnormality" WHERE "user_id" = $1 ORDER BY "abnormality_id""#,
+    )
+    .bind(user_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Deletes all stored abnormalities of an user.
+pub async fn delete_by_user_id(conn: &mut PgConnection, user_id: i32) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "user_abnormality" WHERE "user_id" = $1"#)
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::User;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection, num: i32) -> Result<User> {
+        let account = account::create(conn, &get_default_account(num)).await?;
+        user::create(conn, &get_default_user(&account, num)).await
+    }
+
+    pub fn get_default_user_abnormality(user: &User, abnormality_id: i32) -> UserAbnormality {
+        UserAbnormality {
+            user_id: user.id,
+            abnormality_id,
+            stacks: 1,
+            remaining: 60_000,
+        }
+    }
+
+    #[test]
+    fn test_create_user_abnormality() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+
+                let abnormality =
+                    create(&mut conn, &get_default_user_abnormality(&user, 4000)).await?;
+                assert_eq!(abnormality, get_default_user_abnormality(&user, 4000));
+
+                // An user can only have one entry per abnormality
+                assert!(
+                    create(&mut conn, &get_default_user_abnormality(&user, 4000))
+                        .await
+                        .is_err()
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_list_and_delete_user_abnormalities() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let user = setup(&mut conn, 0).await?;
+                let other_user = setup(&mut conn, 1).await?;
+
+                let food = create(&mut conn, &get_default_user_abnormality(&user, 4001)).await?;
+                let crystal = create(&mut conn, &get_default_user_abnormality(&user, 4000)).await?;
+                let other =
+                    create(&mut conn, &get_default_user_abnormality(&other_user, 4000)).await?;
+
+                assert_eq!(
+                    list_by_user_id(&mut conn, user.id).await?,
+                    vec![crystal, food]
+                );
+
+                delete_by_user_id(&mut conn, user.id).await?;
+                assert!(list_by_user_id(&mut conn, user.id).await?.is_empty());
+                assert_eq!(
+                    list_by_user_id(&mut conn, other_user.id).await?,
+                    vec![other]
+                );
+
+                Ok(())
+            })
+        })
+    }
+}
@@ -0,0 +1,91 @@
+/// Handles the warehouses of accounts and users.
+use crate::model::entity::Warehouse;
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Get the warehouse of an user or the shared warehouse of an account if no user is given.
+/// Warehouses are created on first use. The warehouse is locked until the end of the transaction,
+/// so that items can't be moved into the same slot concurrently.
+pub async fn get_or_create(
+    conn: &mut PgConnection,
+    account_id: i64,
+    user_id: Option<i32>,
+) -> Result<Warehouse> {
+    sqlx::query(
+        r#"INSERT INTO "warehouse" ("account_id", "user_id") VALUES ($1, $2) ON CONFLICT DO NOTHING"#,
+    )
+    .bind(account_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "warehouse" WHERE "account_id" = $1 AND "user_id" IS NOT DISTINCT FROM $2 FOR UPDATE"#,
+    )
+    .bind(account_id)
+    .bind(user_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::{Account, User};
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, user};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection) -> Result<(Account, User)> {
+        let account = account::create(conn, &get_default_account(0)).await?;
+        let user = user::create(conn, &get_default_user(&account, 0)).await?;
+        Ok((account, user))
+    }
+
+    #[test]
+    fn test_get_or_create_account_warehouse() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let (account, _user) = setup(&mut conn).await?;
+
+                let warehouse = get_or_create(&mut conn, account.id, None).await?;
+                assert_eq!(warehouse.account_id, account.id);
+                assert_eq!(warehouse.user_id, None);
+                assert_eq!(warehouse.size, 72);
+
+                // Every account only has one shared warehouse
+                assert_eq!(get_or_create(&mut conn, account.id, None).await?, warehouse);
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_get_or_create_user_warehouse() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let (account, user) = setup(&mut conn).await?;
+
+                let account_warehouse = get_or_create(&mut conn, account.id, None).await?;
+                let warehouse = get_or_create(&mut conn, account.id, Some(user.id)).await?;
+                assert_ne!(warehouse.id, account_warehouse.id);
+                assert_eq!(warehouse.account_id, account.id);
+                assert_eq!(warehouse.user_id, Some(user.id));
+                assert_eq!(
+                    get_or_create(&mut conn, account.id, Some(user.id)).await?,
+                    warehouse
+                );
+
+                Ok(())
+            })
+        })
+    }
+}
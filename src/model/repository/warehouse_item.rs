@@ -0,0 +1,219 @@
+/// Handles the items inside a warehouse.
+use crate::model::entity::WarehouseItem;
+use crate::Result;
+use sqlx::prelude::*;
+use sqlx::PgConnection;
+
+/// Creates a new item inside a warehouse.
+pub async fn create(conn: &mut PgConnection, item: &WarehouseItem) -> Result<WarehouseItem> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "warehouse_item" ("warehouse_id", "template_id", "slot", "amount") VALUES ($1, $2, $3, $4) RETURNING *"#,
+    )
+    .bind(&item.warehouse_id)
+    .bind(&item.template_id)
+    .bind(&item.slot)
+    .bind(&item.amount)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Creates an item that keeps the ID and creation date it had inside the inventory.
+pub async fn restore(conn: &mut PgConnection, item: &WarehouseItem) -> Result<WarehouseItem> {
+    Ok(sqlx::query_as(
+        r#"INSERT INTO "warehouse_item" ("id", "warehouse_id", "template_id", "slot", "amount", "created_at") VALUES ($1, $2, $3, $4, $5, $6) RETURNING *"#,
+    )
+    .bind(&item.id)
+    .bind(&item.warehouse_id)
+    .bind(&item.template_id)
+    .bind(&item.slot)
+    .bind(&item.amount)
+    .bind(&item.created_at)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Updates the slot and the amount of an item. Items can swap their slots with an other item if
+/// both are updated inside the same transaction.
+pub async fn update(conn: &mut PgConnection, item: &WarehouseItem) -> Result<WarehouseItem> {
+    Ok(sqlx::query_as(
+        r#"UPDATE "warehouse_item" SET "slot" = $1, "amount" = $2 WHERE "id" = $3 RETURNING *"#,
+    )
+    .bind(&item.slot)
+    .bind(&item.amount)
+    .bind(&item.id)
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Get all items of a warehouse ordered by their slot.
+pub async fn list_by_warehouse_id(
+    conn: &mut PgConnection,
+    warehouse_id: i32,
+) -> Result<Vec<WarehouseItem>> {
+    Ok(sqlx::query_as(
+        r#"SELECT * FROM "warehouse_item" WHERE "warehouse_id" = $1 ORDER BY "slot""#,
+    )
+    .bind(warehouse_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+pub async fn delete(conn: &mut PgConnection, id: i64) -> Result<()> {
+    sqlx::query(r#"DELETE FROM "warehouse_item" WHERE "id" = $1"#)
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::entity::Warehouse;
+    use crate::model::repository::account::tests::get_default_account;
+    use crate::model::repository::item::tests::get_default_item;
+    use crate::model::repository::user::tests::get_default_user;
+    use crate::model::repository::{account, item, user, warehouse};
+    use crate::model::tests::db_test;
+    use crate::Result;
+    use async_std::task;
+    use chrono::Utc;
+    use sqlx::PgConnection;
+
+    async fn setup(conn: &mut PgConnection) -> Result<Warehouse> {
+        let account = account::create(conn, &get_default_account(0)).await?;
+        warehouse::get_or_create(conn, account.id, None).await
+    }
+
+    pub fn get_default_warehouse_item(warehouse: &Warehouse, slot: i32) -> WarehouseItem {
+        WarehouseItem {
+            id: -1,
+            warehouse_id: warehouse.id,
+            template_id: 8005,
+            slot,
+            amount: 1,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_create_warehouse_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let warehouse = setup(&mut conn).await?;
+
+                let item = create(&mut conn, &get_default_warehouse_item(&warehouse, 3)).await?;
+                assert_eq!(item.warehouse_id, warehouse.id);
+                assert_eq!(item.template_id, 8005);
+                assert_eq!(item.slot, 3);
+                assert_eq!(item.amount, 1);
+                assert_eq!(
+                    list_by_warehouse_id(&mut conn, warehouse.id).await?,
+                    vec![item]
+                );
+
+                // Every slot can only hold one item
+                assert!(
+                    create(&mut conn, &get_default_warehouse_item(&warehouse, 3))
+                        .await
+                        .is_err()
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_restore_warehouse_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let warehouse = setup(&mut conn).await?;
+                let user = user::create(
+                    &mut conn,
+                    &get_default_user(
+                        &account::get_by_id(&mut conn, warehouse.account_id).await?,
+                        0,
+                    ),
+                )
+                .await?;
+                let inventory_item = item::create(&mut conn, &get_default_item(&user, 0)).await?;
+
+                // The item keeps it's ID when it's moved out of the inventory
+                let mut tx = conn.begin().await?;
+                item::delete(&mut tx, inventory_item.id).await?;
+                let item = restore(
+                    &mut tx,
+                    &WarehouseItem {
+                        id: inventory_item.id,
+                        warehouse_id: warehouse.id,
+                        template_id: inventory_item.template_id,
+                        slot: 5,
+                        amount: inventory_item.amount,
+                        created_at: inventory_item.created_at,
+                    },
+                )
+                .await?;
+                let mut conn = tx.commit().await?;
+
+                assert_eq!(item.id, inventory_item.id);
+                assert_eq!(item.created_at, inventory_item.created_at);
+                assert_eq!(
+                    list_by_warehouse_id(&mut conn, warehouse.id).await?,
+                    vec![item]
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_swap_warehouse_items() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let warehouse = setup(&mut conn).await?;
+                let mut item =
+                    create(&mut conn, &get_default_warehouse_item(&warehouse, 0)).await?;
+                let mut other_item =
+                    create(&mut conn, &get_default_warehouse_item(&warehouse, 1)).await?;
+
+                let mut tx = conn.begin().await?;
+                item.slot = 1;
+                item.amount = 20;
+                other_item.slot = 0;
+                update(&mut tx, &item).await?;
+                update(&mut tx, &other_item).await?;
+                let mut conn = tx.commit().await?;
+
+                assert_eq!(
+                    list_by_warehouse_id(&mut conn, warehouse.id).await?,
+                    vec![other_item, item]
+                );
+
+                Ok(())
+            })
+        })
+    }
+
+    #[test]
+    fn test_delete_warehouse_item() -> Result<()> {
+        db_test(|db_string| {
+            task::block_on(async {
+                let mut conn = PgConnection::connect(db_string).await?;
+                let warehouse = setup(&mut conn).await?;
+                let item = create(&mut conn, &get_default_warehouse_item(&warehouse, 0)).await?;
+
+                delete(&mut conn, item.id).await?;
+                assert!(list_by_warehouse_id(&mut conn, warehouse.id)
+                    .await?
+                    .is_empty());
+
+                Ok(())
+            })
+        })
+    }
+}
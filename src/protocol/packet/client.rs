@@ -1,10 +1,122 @@
 /// Module for client network packages.
-use crate::model::{Class, Customization, Gender, Race, Region};
+use crate::model::{
+    Angle, ChatChannel, Class, Customization, EquipmentSlot, Gender, LootingMethod, Race, Region,
+    Vec3f,
+};
 use serde::{Deserialize, Serialize};
+use shipyard::EntityId;
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CAcceptContract {
+    pub contract_type: i32,
+    pub contract_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CAcceptFriend {
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CAcceptGuildWar {
+    pub name: String, // Name of the guild that declared the war
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CAddFriend {
+    pub name: String,
+    pub message: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CAddFriendGroup {
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CAddInterPartyMatchPool {
+    pub instances: Vec<CAddInterPartyMatchPoolEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CAddInterPartyMatchPoolEntry {
+    pub zone_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CAddTradeBag {
+    pub db_id: i64,
+    pub inventory_slot: i32,
+    pub amount: i32,
+    pub money: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CApplyInvenPocketSort {
+    pub game_id: EntityId,
+    pub container: i32,
+    pub pocket: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CBanishGuildMember {
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CBanPartyMember {
+    pub server_id: i32,
+    pub user_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CBlockUser {
+    pub name: String,
+}
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct CCanCreateUser {}
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CCancelContract {
+    pub contract_type: i32,
+    pub contract_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CCancelSkill {
+    pub skill_id: i64,
+    pub cancel_type: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CChangeEquipPreset {
+    pub preset: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CChangeFriendMemo {
+    pub user_id: i32,
+    pub memo: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CChangeGuildChief {
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CChangeGuildGroup {
+    pub name: String,
+    pub group_id: i32, // 0 moves the member out of it's group
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CChangePartyManager {
+    pub server_id: i32,
+    pub user_id: i32,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct CChangeUserLobbySlotId {
     pub user_positions: Vec<CChangeUserLobbySlotIdEntry>,
@@ -16,6 +128,22 @@ pub struct CChangeUserLobbySlotIdEntry {
     pub lobby_slot: i32,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CChat {
+    pub message: String,
+    pub channel: ChatChannel,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CCheckToDeclareGuildWar {
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CCheckToReadyPartyAnswer {
+    pub ready: bool,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct CCheckVersion {
     pub version: Vec<CCheckVersionEntry>,
@@ -32,6 +160,15 @@ pub struct CCheckUserName {
     pub name: String,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CClearSendParcel {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CCloseSendParcel {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CCommitVmTrade {}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct CCreateUser {
     pub name: String,
@@ -47,11 +184,115 @@ pub struct CCreateUser {
     pub appearance2: i32,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CCreateGuildGroup {
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CCreatePrivateChannel {
+    pub name: String,
+    pub password: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDeclareGuildWar {
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDelInterPartyMatchPool {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDelItem {
+    pub game_id: EntityId,
+    pub container: i32,
+    pub pocket: i32,
+    pub slot: i32,
+    pub amount: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDelTradeBag {
+    pub db_id: i64,
+    pub money: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDeleteFriend {
+    pub user_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDeleteFriendGroup {
+    pub group_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDeleteParcel {
+    pub id: i64,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct CDeleteUser {
     pub database_id: i32,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDestroyGuild {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDismissParty {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDungeonClearCountList {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CDungeonCoolTimeList {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CEditBlockedUserMemo {
+    pub user_id: i32,
+    pub memo: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CEditFriendGroup {
+    pub group_id: i32,
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CEditPrivateChannel {
+    pub channel_id: i32,
+    pub name: String,
+    pub password: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CEnterDungeon {
+    pub zone_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CEquipItem {
+    pub game_id: EntityId,
+    pub slot: i32, // Inventory slot of the item
+    pub unk1: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CExpandInvenPocket {
+    pub game_id: EntityId,
+    pub container: i32,
+    pub pocket: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CGiveUpGuildWar {
+    pub name: String, // Name of the enemy guild
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct CGetUserList {}
 
@@ -61,6 +302,58 @@ pub struct CGetUserGuildLogo {
     pub guild_id: i32,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CGetWareItem {
+    pub game_id: EntityId,
+    pub container: i32,
+    pub offset: i32, // First slot of the shown page
+    pub money: i64,
+    pub warehouse_slot: i32,
+    pub db_id: i64,
+    pub id: i32, // Item template ID
+    pub amount: i32,
+    pub inventory_slot: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CInviteUserToGuild {
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CJoinPrivateChannel {
+    pub name: String,
+    pub password: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CKickChannelMember {
+    pub channel_id: i32,
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CLeaveGuild {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CLeaveParty {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CLeavePrivateChannel {
+    pub channel_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CListChannel {
+    pub unk1: i32,
+    pub zone: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CListParcel {
+    pub page: i32, // Zero based
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct CLoadTopoFin {}
 
@@ -75,9 +368,155 @@ pub struct CLoginArbiter {
     pub patch_version: i32,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CMergePartyToRaid {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CMoveInvenPos {
+    pub game_id: EntityId,
+    pub container: i32,
+    pub pocket: i32,
+    pub src_slot: i32,
+    pub dst_slot: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CMoveWarePos {
+    pub game_id: EntityId,
+    pub container: i32,
+    pub offset: i32, // First slot of the shown page
+    pub src_slot: i32,
+    pub dst_slot: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CNotifyLocationInAction {
+    pub skill_id: i64,
+    pub stage: i32,
+    pub location: Vec3f,
+    pub rotation: Angle,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CNotifyLocationInDash {
+    pub skill_id: i64,
+    pub stage: i32,
+    pub location: Vec3f,
+    pub rotation: Angle,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CParcelReadRecvStatus {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CPartyLootingMethod {
+    pub looting_method: LootingMethod,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CPayWarehouseCommision {
+    pub game_id: EntityId,
+    pub container: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CPlayerLocation {
+    pub location: Vec3f,
+    pub rotation: Angle,
+    pub look_direction: i16,
+    pub destination: Vec3f,
+    pub move_type: i32,
+    pub jump_distance: i16,
+    pub in_shuttle: bool,
+    pub time: u32, // Client timestamp in ms
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct CPong {}
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CPressSkill {
+    pub skill_id: i64,
+    pub press: bool, // False once the key of the skill is released
+    pub location: Vec3f,
+    pub rotation: Angle,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CPutWareItem {
+    pub game_id: EntityId,
+    pub container: i32,
+    pub offset: i32, // First slot of the shown page
+    pub money: i64,
+    pub inventory_slot: i32,
+    pub id: i32, // Item template ID
+    pub db_id: i64,
+    pub amount: i32,
+    pub warehouse_slot: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CRecvParcel {
+    pub id: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CRejectContract {
+    pub contract_type: i32,
+    pub contract_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CRemoveBlockedUser {
+    pub user_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CRemoveGuildGroup {
+    pub group_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CReplyThroughArbiterContract {
+    pub contract_type: i32,
+    pub contract_id: i32,
+    pub accept: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CRequestAbnormalityTooltipValue {
+    pub id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CRequestContract {
+    pub name: String,
+    #[serde(with = "serde_bytes")]
+    pub data: Vec<u8>,
+    pub contract_type: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CRequestGuildInfo {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CReturnParcel {
+    pub id: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CReviveNow {
+    pub revive_type: i32,
+    pub id: i32, // Template ID of the used item, 0 if no item is used
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CSelectChannel {
+    pub unk1: i32,
+    pub zone: i32,
+    pub channel: i32,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct CSelectUser {
     pub database_id: i32,
@@ -85,26 +524,290 @@ pub struct CSelectUser {
 }
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
-pub struct CSetVisibleRange {
-    pub range: u32,
+pub struct CSendParcel {
+    pub recipient: String,
+    pub title: String,
+    pub message: String,
 }
 
-#[cfg(test)]
-#[macro_use]
-mod tests {
-    use crate::model::{Class, Customization, Gender, Race, Region};
-    use crate::protocol::serde::{from_vec, to_vec, Result};
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CSetGuildGroupAuthority {
+    pub group_id: i32,
+    pub authority: i32,
+}
 
-    use super::*;
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CSetSendParcelItem {
+    pub db_id: i64,
+    pub inventory_slot: i32,
+    pub amount: i32,
+}
 
-    packet_test!(
-        name: test_can_create_user,
-        data: vec![],
-        expected: CCanCreateUser {}
-    );
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CSetSendParcelMoney {
+    pub money: i64,
+}
 
-    packet_test!(
-        name: test_change_user_lobby_slot_id,
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CSetVisibleRange {
+    pub range: u32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CShowInven {
+    pub unk1: u32, // TODO try to identify the usage of the field
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CShowParcelMessage {
+    pub id: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CStartSkill {
+    pub skill_id: i64,
+    pub rotation: Angle,
+    pub location: Vec3f,
+    pub destination: Vec3f,
+    pub moving: bool,
+    pub continuation: bool, // TODO try to identify the usage of the field
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CTradeBagDone {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CUnequipItem {
+    pub game_id: EntityId,
+    pub slot: EquipmentSlot,
+    pub unk1: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CUpdateFriendInfo {
+    pub user_id: i32,
+    pub group_id: i32, // 0 = No group
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CViewWare {
+    pub game_id: EntityId,
+    pub container: i32,
+    pub offset: i32, // First slot of the requested page
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct CWhisper {
+    pub target: String,
+    pub message: String,
+}
+
+#[cfg(test)]
+#[macro_use]
+mod tests {
+    use crate::model::{
+        Angle, ChatChannel, Class, Customization, Gender, LootingMethod, Race, Region, Vec3f,
+    };
+    use crate::protocol::serde::{from_vec, to_vec, Result};
+
+    use super::*;
+
+    packet_test!(
+        name: test_accept_contract,
+        data: vec![0x3, 0x0, 0x0, 0x0, 0x12, 0x4, 0x0, 0x0],
+        expected: CAcceptContract {
+            contract_type: 3,
+            contract_id: 1042,
+        }
+    );
+
+    packet_test!(
+        name: test_accept_friend,
+        data: vec![
+            0x6, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0,
+        ],
+        expected: CAcceptFriend {
+            name: "Test".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_accept_guild_war,
+        data: vec![
+            0x6, 0x0, 0x47, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0, 0x0, 0x0,
+        ],
+        expected: CAcceptGuildWar {
+            name: "Gantsu".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_add_friend,
+        data: vec![
+            0x8, 0x0, 0x12, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0, 0x48, 0x0,
+            0x69, 0x0, 0x0, 0x0,
+        ],
+        expected: CAddFriend {
+            name: "Test".to_string(),
+            message: "Hi".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_add_friend_group,
+        data: vec![
+            0x6, 0x0, 0x52, 0x0, 0x61, 0x0, 0x69, 0x0, 0x64, 0x0, 0x0, 0x0,
+        ],
+        expected: CAddFriendGroup {
+            name: "Raid".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_add_inter_party_match_pool,
+        data: vec![
+            0x2, 0x0, 0x8, 0x0, 0x8, 0x0, 0x10, 0x0, 0x29, 0x23, 0x0, 0x0, 0x10, 0x0, 0x0, 0x0,
+            0x2a, 0x23, 0x0, 0x0,
+        ],
+        expected: CAddInterPartyMatchPool {
+            instances: vec![
+                CAddInterPartyMatchPoolEntry { zone_id: 9001 },
+                CAddInterPartyMatchPoolEntry { zone_id: 9002 },
+            ],
+        }
+    );
+
+    packet_test!(
+        name: test_add_trade_bag,
+        data: vec![
+            0xd, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0, 0x14, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CAddTradeBag {
+            db_id: 2317,
+            inventory_slot: 3,
+            amount: 20,
+            money: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_apply_inven_pocket_sort,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0xe, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CApplyInvenPocketSort {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 14,
+            pocket: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_banish_guild_member,
+        data: vec![
+            0x6, 0x0, 0x53, 0x0, 0x70, 0x0, 0x61, 0x0, 0x6d, 0x0, 0x6d, 0x0, 0x65, 0x0, 0x72, 0x0,
+            0x0, 0x0,
+        ],
+        expected: CBanishGuildMember {
+            name: "Spammer".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_ban_party_member,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0,
+        ],
+        expected: CBanPartyMember {
+            server_id: 1,
+            user_id: 12,
+        }
+    );
+
+    packet_test!(
+        name: test_block_user,
+        data: vec![
+            0x6, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0,
+        ],
+        expected: CBlockUser {
+            name: "Test".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_can_create_user,
+        data: vec![],
+        expected: CCanCreateUser {}
+    );
+
+    packet_test!(
+        name: test_cancel_contract,
+        data: vec![0x3, 0x0, 0x0, 0x0, 0x12, 0x4, 0x0, 0x0],
+        expected: CCancelContract {
+            contract_type: 3,
+            contract_id: 1042,
+        }
+    );
+
+    packet_test!(
+        name: test_cancel_skill,
+        data: vec![0x3b, 0x9c, 0x1, 0x0, 0x0, 0x0, 0x0, 0x4, 0x2, 0x0, 0x0, 0x0],
+        expected: CCancelSkill {
+            skill_id: 288_230_376_151_817_275,
+            cancel_type: 2,
+        }
+    );
+
+    packet_test!(
+        name: test_change_equip_preset,
+        data: vec![0x2, 0x0, 0x0, 0x0],
+        expected: CChangeEquipPreset { preset: 2 }
+    );
+
+    packet_test!(
+        name: test_change_friend_memo,
+        data: vec![
+            0xc, 0x0, 0x0, 0x0, 0xa, 0x0, 0x54, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x6b, 0x0, 0x0, 0x0,
+        ],
+        expected: CChangeFriendMemo {
+            user_id: 12,
+            memo: "Tank".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_change_guild_chief,
+        data: vec![0x6, 0x0, 0x4e, 0x0, 0x79, 0x0, 0x78, 0x0, 0x0, 0x0],
+        expected: CChangeGuildChief {
+            name: "Nyx".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_change_guild_group,
+        data: vec![
+            0xa, 0x0, 0x7, 0x0, 0x0, 0x0, 0x4e, 0x0, 0x79, 0x0, 0x78, 0x0, 0x0, 0x0,
+        ],
+        expected: CChangeGuildGroup {
+            name: "Nyx".to_string(),
+            group_id: 7,
+        }
+    );
+
+    packet_test!(
+        name: test_change_party_manager,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0,
+        ],
+        expected: CChangePartyManager {
+            server_id: 1,
+            user_id: 12,
+        }
+    );
+
+    packet_test!(
+        name: test_change_user_lobby_slot_id,
         data: vec![2, 0, 8, 0, 8, 0, 20, 0, 5, 0, 0, 0, 1, 0, 0, 0, 20, 0, 0, 0, 6, 0, 0, 0, 2, 0, 0, 0],
         expected: CChangeUserLobbySlotId {
             user_positions: vec![
@@ -120,6 +823,35 @@ mod tests {
         }
     );
 
+    packet_test!(
+        name: test_chat,
+        data: vec![
+            0xa, 0x0, 0x1b, 0x0, 0x0, 0x0, 0x3c, 0x0, 0x46, 0x0, 0x4f, 0x0, 0x4e, 0x0, 0x54, 0x0,
+            0x3e, 0x0, 0x48, 0x0, 0x69, 0x0, 0x3c, 0x0, 0x2f, 0x0, 0x46, 0x0, 0x4f, 0x0, 0x4e, 0x0,
+            0x54, 0x0, 0x3e, 0x0, 0x0, 0x0,
+        ],
+        expected: CChat {
+            message: "<FONT>Hi</FONT>".to_string(),
+            channel: ChatChannel::Global,
+        }
+    );
+
+    packet_test!(
+        name: test_check_to_declare_guild_war,
+        data: vec![
+            0x6, 0x0, 0x47, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0, 0x0, 0x0,
+        ],
+        expected: CCheckToDeclareGuildWar {
+            name: "Gantsu".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_check_to_ready_party_answer,
+        data: vec![0x1],
+        expected: CCheckToReadyPartyAnswer { ready: true }
+    );
+
     packet_test!(
         name: test_check_version,
         data: vec![
@@ -151,6 +883,24 @@ mod tests {
         }
     );
 
+    packet_test!(
+        name: test_clear_send_parcel,
+        data: vec![],
+        expected: CClearSendParcel {}
+    );
+
+    packet_test!(
+        name: test_close_send_parcel,
+        data: vec![],
+        expected: CCloseSendParcel {}
+    );
+
+    packet_test!(
+        name: test_commit_vm_trade,
+        data: vec![],
+        expected: CCommitVmTrade {}
+    );
+
     packet_test!(
         name: test_create_user,
         data: vec![
@@ -184,6 +934,91 @@ mod tests {
         }
     );
 
+    packet_test!(
+        name: test_create_guild_group,
+        data: vec![
+            0x6, 0x0, 0x4f, 0x0, 0x66, 0x0, 0x66, 0x0, 0x69, 0x0, 0x63, 0x0, 0x65, 0x0, 0x72, 0x0,
+            0x73, 0x0, 0x0, 0x0,
+        ],
+        expected: CCreateGuildGroup {
+            name: "Officers".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_create_private_channel,
+        data: vec![
+            0xa, 0x0, 0xd2, 0x4, 0x0, 0x0, 0x54, 0x0, 0x72, 0x0, 0x61, 0x0, 0x64, 0x0, 0x65, 0x0,
+            0x72, 0x0, 0x73, 0x0, 0x0, 0x0,
+        ],
+        expected: CCreatePrivateChannel {
+            name: "Traders".to_string(),
+            password: 1234,
+        }
+    );
+
+    packet_test!(
+        name: test_declare_guild_war,
+        data: vec![
+            0x6, 0x0, 0x47, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0, 0x0, 0x0,
+        ],
+        expected: CDeclareGuildWar {
+            name: "Gantsu".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_del_inter_party_match_pool,
+        data: vec![],
+        expected: CDelInterPartyMatchPool {}
+    );
+
+    packet_test!(
+        name: test_del_item,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0xe, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xc, 0x0, 0x0, 0x0, 0x5, 0x0, 0x0, 0x0,
+        ],
+        expected: CDelItem {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 14,
+            pocket: 0,
+            slot: 12,
+            amount: 5,
+        }
+    );
+
+    packet_test!(
+        name: test_del_trade_bag,
+        data: vec![
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xf0, 0x49, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CDelTradeBag {
+            db_id: 0,
+            money: 150000,
+        }
+    );
+
+    packet_test!(
+        name: test_delete_friend,
+        data: vec![0xc, 0x0, 0x0, 0x0],
+        expected: CDeleteFriend { user_id: 12 }
+    );
+
+    packet_test!(
+        name: test_delete_friend_group,
+        data: vec![0x3, 0x0, 0x0, 0x0],
+        expected: CDeleteFriendGroup { group_id: 3 }
+    );
+
+    packet_test!(
+        name: test_delete_parcel,
+        data: vec![
+            0x4, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CDeleteParcel { id: 5124 }
+    );
+
     packet_test!(
         name: test_delete_user,
         data: vec![0x13, 0x12, 0x11, 0x32],
@@ -192,6 +1027,95 @@ mod tests {
         }
     );
 
+    packet_test!(
+        name: test_destroy_guild,
+        data: vec![],
+        expected: CDestroyGuild {}
+    );
+
+    packet_test!(
+        name: test_dismiss_party,
+        data: vec![],
+        expected: CDismissParty {}
+    );
+
+    packet_test!(
+        name: test_dungeon_clear_count_list,
+        data: vec![],
+        expected: CDungeonClearCountList {}
+    );
+
+    packet_test!(
+        name: test_dungeon_cool_time_list,
+        data: vec![],
+        expected: CDungeonCoolTimeList {}
+    );
+
+    packet_test!(
+        name: test_edit_blocked_user_memo,
+        data: vec![
+            0xc, 0x0, 0x0, 0x0, 0xa, 0x0, 0x53, 0x0, 0x70, 0x0, 0x61, 0x0, 0x6d, 0x0, 0x0, 0x0,
+        ],
+        expected: CEditBlockedUserMemo {
+            user_id: 12,
+            memo: "Spam".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_edit_friend_group,
+        data: vec![
+            0x3, 0x0, 0x0, 0x0, 0xa, 0x0, 0x52, 0x0, 0x61, 0x0, 0x69, 0x0, 0x64, 0x0, 0x0, 0x0,
+        ],
+        expected: CEditFriendGroup {
+            group_id: 3,
+            name: "Raid".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_edit_private_channel,
+        data: vec![
+            0xc, 0x0, 0x0, 0x0, 0xe, 0x0, 0xe1, 0x10, 0x0, 0x0, 0x54, 0x0, 0x72, 0x0, 0x61, 0x0,
+            0x64, 0x0, 0x65, 0x0, 0x72, 0x0, 0x73, 0x0, 0x0, 0x0,
+        ],
+        expected: CEditPrivateChannel {
+            channel_id: 12,
+            name: "Traders".to_string(),
+            password: 4321,
+        }
+    );
+
+    packet_test!(
+        name: test_enter_dungeon,
+        data: vec![0xf1, 0x25, 0x0, 0x0],
+        expected: CEnterDungeon { zone_id: 9713 }
+    );
+
+    packet_test!(
+        name: test_equip_item,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0xc, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CEquipItem {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            slot: 12,
+            unk1: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_expand_inven_pocket,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0xe, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CExpandInvenPocket {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 14,
+            pocket: 0,
+        }
+    );
+
     packet_test!(
         name: test_get_user_guild_logo,
         data: vec![0x1, 0x2f, 0x31, 0x1, 0x75, 0xe, 0x0, 0x0],
@@ -201,12 +1125,108 @@ mod tests {
         }
     );
 
+    packet_test!(
+        name: test_get_ware_item,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0x1, 0x0, 0x0, 0x0, 0x48, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x4b, 0x0, 0x0, 0x0, 0xd, 0x9, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x45, 0x1f, 0x0, 0x0, 0x14, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0,
+        ],
+        expected: CGetWareItem {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 1,
+            offset: 72,
+            money: 0,
+            warehouse_slot: 75,
+            db_id: 2317,
+            id: 8005,
+            amount: 20,
+            inventory_slot: 3,
+        }
+    );
+
+    packet_test!(
+        name: test_give_up_guild_war,
+        data: vec![
+            0x6, 0x0, 0x47, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0, 0x0, 0x0,
+        ],
+        expected: CGiveUpGuildWar {
+            name: "Gantsu".to_string(),
+        }
+    );
+
     packet_test!(
         name: test_get_user_list,
         data: vec![],
         expected: CGetUserList {}
     );
 
+    packet_test!(
+        name: test_invite_user_to_guild,
+        data: vec![0x6, 0x0, 0x4e, 0x0, 0x79, 0x0, 0x78, 0x0, 0x0, 0x0],
+        expected: CInviteUserToGuild {
+            name: "Nyx".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_join_private_channel,
+        data: vec![
+            0xa, 0x0, 0xd2, 0x4, 0x0, 0x0, 0x54, 0x0, 0x72, 0x0, 0x61, 0x0, 0x64, 0x0, 0x65, 0x0,
+            0x72, 0x0, 0x73, 0x0, 0x0, 0x0,
+        ],
+        expected: CJoinPrivateChannel {
+            name: "Traders".to_string(),
+            password: 1234,
+        }
+    );
+
+    packet_test!(
+        name: test_kick_channel_member,
+        data: vec![
+            0xc, 0x0, 0x0, 0x0, 0xa, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0,
+        ],
+        expected: CKickChannelMember {
+            channel_id: 12,
+            name: "Test".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_leave_guild,
+        data: vec![],
+        expected: CLeaveGuild {}
+    );
+
+    packet_test!(
+        name: test_leave_party,
+        data: vec![],
+        expected: CLeaveParty {}
+    );
+
+    packet_test!(
+        name: test_leave_private_channel,
+        data: vec![0xc, 0x0, 0x0, 0x0],
+        expected: CLeavePrivateChannel { channel_id: 12 }
+    );
+
+    packet_test!(
+        name: test_list_channel,
+        data: vec![0x1, 0x0, 0x0, 0x0, 0xd, 0x0, 0x0, 0x0],
+        expected: CListChannel {
+            unk1: 1,
+            zone: 13,
+        }
+    );
+
+    packet_test!(
+        name: test_list_parcel,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0,
+        ],
+        expected: CListParcel { page: 1 }
+    );
+
     packet_test!(
         name: test_load_topo_fin,
         data: vec![],
@@ -238,12 +1258,271 @@ mod tests {
         }
     );
 
+    packet_test!(
+        name: test_merge_party_to_raid,
+        data: vec![],
+        expected: CMergePartyToRaid {}
+    );
+
+    packet_test!(
+        name: test_move_inven_pos,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0xe, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x3, 0x0, 0x0, 0x0, 0x11, 0x0, 0x0, 0x0,
+        ],
+        expected: CMoveInvenPos {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 14,
+            pocket: 0,
+            src_slot: 3,
+            dst_slot: 17,
+        }
+    );
+
+    packet_test!(
+        name: test_move_ware_pos,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x2, 0x0, 0x0, 0x0, 0x7, 0x0, 0x0, 0x0,
+        ],
+        expected: CMoveWarePos {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 9,
+            offset: 0,
+            src_slot: 2,
+            dst_slot: 7,
+        }
+    );
+
+    packet_test!(
+        name: test_notify_location_in_action,
+        data: vec![
+            0x3b, 0x9c, 0x1, 0x0, 0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0, 0x10, 0x7e, 0x46,
+            0x0, 0xa0, 0x9c, 0x44, 0x0, 0xd0, 0x89, 0xc5, 0x0, 0x40,
+        ],
+        expected: CNotifyLocationInAction {
+            skill_id: 288_230_376_151_817_275,
+            stage: 1,
+            location: Vec3f {
+                x: 16260.0,
+                y: 1253.0,
+                z: -4410.0,
+            },
+            rotation: Angle::from_deg(90.0),
+        }
+    );
+
+    packet_test!(
+        name: test_notify_location_in_dash,
+        data: vec![
+            0x3b, 0x9c, 0x1, 0x0, 0x0, 0x0, 0x0, 0x4, 0x0, 0x0, 0x0, 0x0, 0x0, 0x10, 0x7e, 0x46,
+            0x0, 0xa0, 0x9c, 0x44, 0x0, 0xd0, 0x89, 0xc5, 0x0, 0x80,
+        ],
+        expected: CNotifyLocationInDash {
+            skill_id: 288_230_376_151_817_275,
+            stage: 0,
+            location: Vec3f {
+                x: 16260.0,
+                y: 1253.0,
+                z: -4410.0,
+            },
+            rotation: Angle::from_deg(180.0),
+        }
+    );
+
+    packet_test!(
+        name: test_parcel_read_recv_status,
+        data: vec![],
+        expected: CParcelReadRecvStatus {}
+    );
+
+    packet_test!(
+        name: test_party_looting_method,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0,
+        ],
+        expected: CPartyLootingMethod {
+            looting_method: LootingMethod::RoundRobin,
+        }
+    );
+
+    packet_test!(
+        name: test_pay_warehouse_commision,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0x9, 0x0, 0x0, 0x0,
+        ],
+        expected: CPayWarehouseCommision {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 9,
+        }
+    );
+
+    packet_test!(
+        name: test_player_location,
+        data: vec![
+            0x0, 0x10, 0x7e, 0x46, 0x0, 0xa0, 0x9c, 0x44, 0x0, 0xd0, 0x89, 0xc5, 0x0, 0x40, 0x0,
+            0x0, 0x0, 0x14, 0x7e, 0x46, 0x0, 0xa0, 0x9c, 0x44, 0x0, 0xd0, 0x89, 0xc5, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0xe8, 0x3, 0x0, 0x0,
+        ],
+        expected: CPlayerLocation {
+            location: Vec3f {
+                x: 16260.0,
+                y: 1253.0,
+                z: -4410.0,
+            },
+            rotation: Angle::from_deg(90.0),
+            look_direction: 0,
+            destination: Vec3f {
+                x: 16261.0,
+                y: 1253.0,
+                z: -4410.0,
+            },
+            move_type: 0,
+            jump_distance: 0,
+            in_shuttle: false,
+            time: 1000,
+        }
+    );
+
     packet_test!(
         name: test_pong,
         data: vec![],
         expected: CPong {}
     );
 
+    packet_test!(
+        name: test_press_skill,
+        data: vec![
+            0x3b, 0x9c, 0x1, 0x0, 0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x7a, 0x44, 0x0, 0x0, 0xfa,
+            0xc4, 0x0, 0x0, 0x16, 0x43, 0x0, 0x40,
+        ],
+        expected: CPressSkill {
+            skill_id: 288_230_376_151_817_275,
+            press: true,
+            location: Vec3f {
+                x: 1000.0,
+                y: -2000.0,
+                z: 150.0,
+            },
+            rotation: Angle::from_deg(90.0),
+        }
+    );
+
+    packet_test!(
+        name: test_put_ware_item,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0, 0x45, 0x1f, 0x0, 0x0,
+            0xd, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x14, 0x0, 0x0, 0x0, 0x5, 0x0, 0x0, 0x0,
+        ],
+        expected: CPutWareItem {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 1,
+            offset: 0,
+            money: 0,
+            inventory_slot: 3,
+            id: 8005,
+            db_id: 2317,
+            amount: 20,
+            warehouse_slot: 5,
+        }
+    );
+
+    packet_test!(
+        name: test_recv_parcel,
+        data: vec![
+            0x4, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CRecvParcel { id: 5124 }
+    );
+
+    packet_test!(
+        name: test_reject_contract,
+        data: vec![0x3, 0x0, 0x0, 0x0, 0x12, 0x4, 0x0, 0x0],
+        expected: CRejectContract {
+            contract_type: 3,
+            contract_id: 1042,
+        }
+    );
+
+    packet_test!(
+        name: test_remove_blocked_user,
+        data: vec![
+            0xc, 0x0, 0x0, 0x0,
+        ],
+        expected: CRemoveBlockedUser { user_id: 12 }
+    );
+
+    packet_test!(
+        name: test_remove_guild_group,
+        data: vec![0x7, 0x0, 0x0, 0x0],
+        expected: CRemoveGuildGroup { group_id: 7 }
+    );
+
+    packet_test!(
+        name: test_reply_through_arbiter_contract,
+        data: vec![
+            0x4, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x1,
+        ],
+        expected: CReplyThroughArbiterContract {
+            contract_type: 4,
+            contract_id: 12,
+            accept: true,
+        }
+    );
+
+    packet_test!(
+        name: test_request_abnormality_tooltip_value,
+        data: vec![0xa0, 0xf, 0x0, 0x0],
+        expected: CRequestAbnormalityTooltipValue { id: 4000 }
+    );
+
+    packet_test!(
+        name: test_request_contract,
+        data: vec![
+            0xe, 0x0, 0x18, 0x0, 0x2, 0x0, 0x4, 0x0, 0x0, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0,
+            0x74, 0x0, 0x0, 0x0, 0x1, 0x2,
+        ],
+        expected: CRequestContract {
+            name: "Test".to_string(),
+            data: vec![0x1, 0x2],
+            contract_type: 4,
+        }
+    );
+
+    packet_test!(
+        name: test_request_guild_info,
+        data: vec![],
+        expected: CRequestGuildInfo {}
+    );
+
+    packet_test!(
+        name: test_return_parcel,
+        data: vec![
+            0x4, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CReturnParcel { id: 5124 }
+    );
+
+    packet_test!(
+        name: test_revive_now,
+        data: vec![0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0],
+        expected: CReviveNow {
+            revive_type: 0,
+            id: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_select_channel,
+        data: vec![0x1, 0x0, 0x0, 0x0, 0xd, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0],
+        expected: CSelectChannel {
+            unk1: 1,
+            zone: 13,
+            channel: 2,
+        }
+    );
+
     packet_test!(
         name: test_select_user,
         data: vec![0x3, 0x2f, 0x32, 0x1, 0x0],
@@ -253,6 +1532,48 @@ mod tests {
         }
     );
 
+    packet_test!(
+        name: test_send_parcel,
+        data: vec![
+            0xa, 0x0, 0x14, 0x0, 0x1a, 0x0, 0x45, 0x0, 0x6c, 0x0, 0x69, 0x0, 0x6e, 0x0, 0x0, 0x0,
+            0x48, 0x0, 0x69, 0x0, 0x0, 0x0, 0x47, 0x0, 0x69, 0x0, 0x66, 0x0, 0x74, 0x0, 0x0, 0x0,
+        ],
+        expected: CSendParcel {
+            recipient: "Elin".to_string(),
+            title: "Hi".to_string(),
+            message: "Gift".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_set_guild_group_authority,
+        data: vec![0x7, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0],
+        expected: CSetGuildGroupAuthority {
+            group_id: 7,
+            authority: 3,
+        }
+    );
+
+    packet_test!(
+        name: test_set_send_parcel_item,
+        data: vec![
+            0xd, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0, 0x14, 0x0, 0x0, 0x0,
+        ],
+        expected: CSetSendParcelItem {
+            db_id: 2317,
+            inventory_slot: 3,
+            amount: 20,
+        }
+    );
+
+    packet_test!(
+        name: test_set_send_parcel_money,
+        data: vec![
+            0xf0, 0x49, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CSetSendParcelMoney { money: 150000 }
+    );
+
     packet_test!(
         name: test_set_visible_range,
         data: vec![0xd0, 0x7, 0x0, 0x0],
@@ -260,4 +1581,95 @@ mod tests {
             range: 2000,
         }
     );
+
+    packet_test!(
+        name: test_show_inven,
+        data: vec![0x1, 0x0, 0x0, 0x0],
+        expected: CShowInven { unk1: 1 }
+    );
+
+    packet_test!(
+        name: test_show_parcel_message,
+        data: vec![
+            0x4, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CShowParcelMessage { id: 5124 }
+    );
+
+    packet_test!(
+        name: test_start_skill,
+        data: vec![
+            0x3b, 0x9c, 0x1, 0x0, 0x0, 0x0, 0x0, 0x4, 0x0, 0x40, 0x0, 0x0, 0x7a, 0x44, 0x0, 0x0,
+            0xfa, 0xc4, 0x0, 0x0, 0x16, 0x43, 0x0, 0x80, 0x89, 0x44, 0x0, 0x0, 0xfa, 0xc4, 0x0, 0x0,
+            0x16, 0x43, 0x0, 0x0,
+        ],
+        expected: CStartSkill {
+            skill_id: 288_230_376_151_817_275,
+            rotation: Angle::from_deg(90.0),
+            location: Vec3f {
+                x: 1000.0,
+                y: -2000.0,
+                z: 150.0,
+            },
+            destination: Vec3f {
+                x: 1100.0,
+                y: -2000.0,
+                z: 150.0,
+            },
+            moving: false,
+            continuation: false,
+        }
+    );
+
+    packet_test!(
+        name: test_trade_bag_done,
+        data: vec![],
+        expected: CTradeBagDone {}
+    );
+
+    packet_test!(
+        name: test_unequip_item,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0x3, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: CUnequipItem {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            slot: EquipmentSlot::Body,
+            unk1: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_update_friend_info,
+        data: vec![0xc, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0],
+        expected: CUpdateFriendInfo {
+            user_id: 12,
+            group_id: 3,
+        }
+    );
+
+    packet_test!(
+        name: test_view_ware,
+        data: vec![
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0x1, 0x0, 0x0, 0x0, 0x48, 0x0, 0x0, 0x0,
+        ],
+        expected: CViewWare {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 1,
+            offset: 72,
+        }
+    );
+
+    packet_test!(
+        name: test_whisper,
+        data: vec![
+            0x8, 0x0, 0x12, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0, 0x3c, 0x0,
+            0x46, 0x0, 0x4f, 0x0, 0x4e, 0x0, 0x54, 0x0, 0x3e, 0x0, 0x48, 0x0, 0x69, 0x0, 0x3c, 0x0,
+            0x2f, 0x0, 0x46, 0x0, 0x4f, 0x0, 0x4e, 0x0, 0x54, 0x0, 0x3e, 0x0, 0x0, 0x0,
+        ],
+        expected: CWhisper {
+            target: "Test".to_string(),
+            message: "<FONT>Hi</FONT>".to_string(),
+        }
+    );
 }
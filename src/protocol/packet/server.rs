@@ -1,10 +1,75 @@
 /// Module for server network packages.
 use crate::model::{
-    Angle, Class, Customization, Gender, Race, Region, ServantType, TemplateID, Vec3a, Vec3f,
+    Angle, ChatChannel, Class, Customization, Gender, GuildWarState, LootingMethod, Race, Region,
+    ServantType, TemplateID, Vec3a, Vec3f,
 };
 use serde::{Deserialize, Serialize};
 use shipyard::EntityId;
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SAbnormalityBegin {
+    pub target_id: EntityId,
+    pub source_id: EntityId,
+    pub id: i32,
+    pub duration: i32, // Duration in ms
+    pub unk1: i32,
+    pub stacks: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SAbnormalityEnd {
+    pub target_id: EntityId,
+    pub id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SAbnormalityRefresh {
+    pub target_id: EntityId,
+    pub id: i32,
+    pub duration: i32, // Duration in ms
+    pub unk1: i32,
+    pub stacks: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SAbnormalityTooltipValue {
+    pub id: i32,
+    pub value: f32, // Replaces the placeholder inside the tooltip of the abnormality
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SAcceptContract {
+    pub sender_id: EntityId,
+    pub recipient_id: EntityId,
+    pub contract_type: i32,
+    pub contract_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SActionEnd {
+    pub user_id: EntityId,
+    pub location: Vec3f,
+    pub rotation: Angle,
+    pub template_id: TemplateID,
+    pub skill_id: i64,
+    pub end_type: i32,
+    pub action_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SActionStage {
+    pub user_id: EntityId,
+    pub location: Vec3f,
+    pub rotation: Angle,
+    pub template_id: TemplateID,
+    pub skill_id: i64,
+    pub stage: i32,
+    pub speed: f32,
+    pub action_id: i32,
+    pub moving: bool,
+    pub destination: Vec3f,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct SAccountPackageList {
     pub account_benefits: Vec<SAccountPackageListEntry>,
@@ -16,11 +81,108 @@ pub struct SAccountPackageListEntry {
     pub expiration_date: i64,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SAddBlockedUser {
+    pub name: String,
+    pub memo: String,
+    pub user_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SAddFriend {
+    pub name: String,
+    pub message: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SAddInterPartyMatchPool {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SBanParty {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SBanPartyMember {
+    pub name: String,
+    pub server_id: i32,
+    pub user_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SBeginThroughArbiterContract {
+    pub name: String, // Name of the requesting user
+    #[serde(with = "serde_bytes")]
+    pub data: Vec<u8>,
+    pub contract_type: i32,
+    pub contract_id: i32,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct SCanCreateUser {
     pub ok: bool,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCancelContract {
+    pub sender_id: EntityId,
+    pub recipient_id: EntityId,
+    pub contract_type: i32,
+    pub contract_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCannotStartSkill {
+    pub skill_id: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCancelSelectChannel {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SChangePartyManager {
+    pub name: String,
+    pub server_id: i32,
+    pub user_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SChangeFriendState {
+    pub user_id: i32,
+    pub online: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SChat {
+    pub author_name: String,
+    pub message: String,
+    pub channel: ChatChannel,
+    pub user_id: EntityId,
+    pub unk1: u8,
+    pub is_gm: bool,
+    pub is_founder: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCheckToDeclareGuildWar {
+    pub name: String,
+    pub ok: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCheckToReadyParty {
+    pub members: Vec<SCheckToReadyPartyEntry>,
+    pub zone_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCheckToReadyPartyEntry {
+    pub server_id: i32,
+    pub user_id: i32,
+    pub ready: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCheckToReadyPartyFin {}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct SCheckVersion {
     pub ok: bool,
@@ -31,16 +193,155 @@ pub struct SCheckUserName {
     pub ok: bool,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCreateGuildResult {
+    pub ok: bool,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct SCreateUser {
     pub ok: bool,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCreatureChangeHp {
+    pub current_hp: i64,
+    pub max_hp: i64,
+    pub diff: i64,
+    pub change_type: i32,
+    pub target_id: EntityId,
+    pub source_id: EntityId,
+    pub crit: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCreatureLife {
+    pub target_id: EntityId,
+    pub location: Vec3f,
+    pub is_alive: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SCurrentChannel {
+    pub server_id: i32,
+    pub zone: i32,
+    pub channel: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SDelInterPartyMatchPool {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SDeleteFriend {
+    pub user_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SDeleteParcel {
+    pub id: i64,
+    pub success: bool,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct SDeleteUser {
     pub ok: bool,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SDespawnNpc {
+    pub npc_id: EntityId,
+    pub location: Vec3f,
+    pub despawn_type: u32, // TODO investigate the exact values
+    pub unk1: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SDespawnUser {
+    pub user_id: EntityId,
+    pub despawn_type: u32, // TODO investigate the exact values
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SDestroyGuild {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SDungeonClearCountList {
+    pub dungeons: Vec<SDungeonClearCountListEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SDungeonClearCountListEntry {
+    pub zone_id: i32,
+    pub clear_count: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SDungeonCoolTimeList {
+    pub dungeons: Vec<SDungeonCoolTimeListEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SDungeonCoolTimeListEntry {
+    pub zone_id: i32,
+    pub entry_count: i32,
+    pub max_entry_count: i32,
+    pub reset_time: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SEachSkillResult {
+    pub source_id: EntityId,
+    pub target_id: EntityId,
+    pub template_id: TemplateID,
+    pub skill_id: i64,
+    pub stage: i32,
+    pub action_id: i32,
+    pub value: i64,
+    pub result_type: i32,
+    pub crit: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SEndGuildWar {
+    pub guild_name: String, // Name of the enemy guild
+    pub guild_id: i32,
+    pub state: GuildWarState,
+    pub kills: i32,
+    pub deaths: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SFinInterPartyMatch {
+    pub zone_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SFriendGroupList {
+    pub groups: Vec<SFriendGroupListEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SFriendGroupListEntry {
+    pub group_id: i32,
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SFriendList {
+    pub friends: Vec<SFriendListEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SFriendListEntry {
+    pub name: String,
+    pub memo: String,
+    pub user_id: i32,
+    pub group_id: i32, // 0 = No group
+    pub level: i32,
+    pub class: Class,
+    pub online: bool,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct SGetUserList {
     pub characters: Vec<SGetUserListCharacter>,
@@ -162,13 +463,45 @@ pub struct SGetUserListCharacterCustomString {
     pub id: i32,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SGuildInfo {
+    pub groups: Vec<SGuildInfoGroup>,
+    pub name: String,
+    pub master_name: String,
+    pub guild_id: i32,
+    pub master_id: i32,
+    pub created_at: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SGuildInfoGroup {
+    pub name: String,
+    pub group_id: i32,
+    pub authority: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SGuildMemberList {
+    pub members: Vec<SGuildMemberListEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SGuildMemberListEntry {
+    pub name: String,
+    pub user_id: i32,
+    pub level: i32,
+    pub class: Class,
+    pub group_id: i32, // 0 if the member is not part of a group
+    pub online: bool,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct SGuildName {
     pub guild_name: String,
     pub guild_rank: String,
     pub guild_title: String,
     pub guild_logo: String,
-    pub game_id: u64,
+    pub game_id: EntityId,
 }
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
@@ -191,6 +524,99 @@ pub struct SItemCustomStringEntry {
     pub id: i32,
 }
 
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SItemlist {
+    pub items: Vec<SItemlistItem>,
+    pub game_id: EntityId,
+    pub container: i32, // 14 = inventory
+    pub pocket: i32,
+    pub num_pockets: i32,
+    pub size: i32, // Number of usable slots of the pocket
+    pub money: i64,
+    pub loot_priority: i32,
+    pub open: bool, // Opens the inventory window
+    pub requested: bool,
+    pub first: bool,
+    pub more: bool,
+    pub last_in_batch: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SItemlistItem {
+    pub id: i32, // Item template ID
+    pub db_id: i64,
+    pub owner_id: i64,
+    pub slot: i32,
+    pub amount: i32,
+    pub enchantment: i32,
+    pub durability: i32,
+    pub soulbound: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SJoinPrivateChannel {
+    pub members: Vec<SJoinPrivateChannelMember>,
+    pub name: String,
+    pub slot: i32,
+    pub channel_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SJoinPrivateChannelMember {
+    pub user_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SLeaveGuild {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SLeaveParty {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SLeavePartyMember {
+    pub name: String,
+    pub server_id: i32,
+    pub user_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SLeavePrivateChannel {
+    pub channel_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SListChannel {
+    pub channels: Vec<SListChannelEntry>,
+    pub unk1: i32,
+    pub zone: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SListChannelEntry {
+    pub channel: i32,
+    pub density: i32, // Fill level of the channel in percent
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SListParcelEx {
+    pub parcels: Vec<SListParcelExParcel>,
+    pub page: i32,
+    pub page_count: i32,
+    pub unread_count: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SListParcelExParcel {
+    pub sender: String,
+    pub title: String,
+    pub id: i64,
+    pub money: i64,
+    pub item_count: i32,
+    pub is_read: bool,
+    pub is_returned: bool,
+    pub expires_at: i64,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct SLoadingScreenControlInfo {
     pub custom_screen_enabled: bool,
@@ -317,454 +743,2091 @@ pub struct SLoginArbiter {
 }
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
-pub struct SPing {}
+pub struct SNotifyGuildWarStatusChange {
+    pub guild_name: String, // Name of the enemy guild
+    pub guild_id: i32,
+    pub state: GuildWarState,
+}
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
-pub struct SRemainPlayTime {
-    // 1 = P2P (active subscription)
-    // 2 = P2P (no active subscription),
-    // 3 = F2P (free-play event)
-    // 4 = F2P (legacy restriction),
-    // 5 = Premium, 6 = Basic
-    pub account_type: u32,
-    pub minutes_left: u32,
+pub struct SNpcLocation {
+    pub npc_id: EntityId,
+    pub location: Vec3f,
+    pub rotation: Angle,
+    pub speed: i16,
+    pub destination: Vec3f,
+    pub move_type: i32, // TODO investigate the exact values
 }
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
-pub struct SSelectUser {
-    unk1: u8, // TODO try to identify the usage of the fields
-    unk2: u16,
-    unk3: u64,
+pub struct SParcelReadRecvStatus {
+    pub unread_count: i32,
 }
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
-pub struct SSpawnMe {
-    pub user_id: EntityId,
-    pub location: Vec3f,
-    pub rotation: Angle,
-    pub is_alive: bool,
-    pub is_lord: bool, // TODO try to identify the usage of the field
+pub struct SPartyLootingMethod {
+    pub looting_method: LootingMethod,
 }
 
-#[cfg(test)]
-#[macro_use]
-mod tests {
-    use super::*;
-    use crate::protocol::serde::{from_vec, to_vec, Result};
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SPartyMemberChangeHp {
+    pub server_id: i32,
+    pub user_id: i32,
+    pub current_hp: i64,
+    pub max_hp: i64,
+}
 
-    packet_test!(
-        name: test_account_package_list,
-        data: vec![
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SPartyMemberIntervalPosUpdate {
+    pub server_id: i32,
+    pub user_id: i32,
+    pub location: Vec3f,
+    pub zone_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SPartyMemberList {
+    pub members: Vec<SPartyMemberListEntry>,
+    pub is_raid: bool,
+    pub leader_server_id: i32,
+    pub leader_user_id: i32,
+    pub looting_method: LootingMethod,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SPartyMemberListEntry {
+    pub name: String,
+    pub server_id: i32,
+    pub user_id: i32,
+    pub level: i32,
+    pub class: Class,
+    pub online: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SPing {}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SPlayerChangeExp {
+    pub gained_exp: i64,
+    pub gained_rest_bonus_exp: i64,
+    pub total_exp: i64,
+    pub level_exp: i64,
+    pub total_level_exp: i64,
+    pub rest_bonus_exp: i64,
+    pub max_rest_bonus_exp: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SPlayerChangeMp {
+    pub current_mp: i32,
+    pub max_mp: i32,
+    pub diff: i32,
+    pub change_type: i32,
+    pub target_id: EntityId,
+    pub source_id: EntityId,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SPrivateChannelNotice {
+    pub channel_id: i32,
+    pub event: i32,
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SRecvParcel {
+    pub id: i64,
+    pub success: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SRejectContract {
+    pub name: String, // Name of the user that rejected the contract
+    pub contract_type: i32,
+    pub contract_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SRemainPlayTime {
+    // 1 = P2P (active subscription)
+    // 2 = P2P (no active subscription),
+    // 3 = F2P (free-play event)
+    // 4 = F2P (legacy restriction),
+    // 5 = Premium, 6 = Basic
+    pub account_type: u32,
+    pub minutes_left: u32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SRemoveBlockedUser {
+    pub user_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SRequestContract {
+    pub sender_name: String,
+    pub recipient_name: String,
+    pub sender_id: EntityId,
+    pub recipient_id: EntityId,
+    pub contract_type: i32,
+    pub contract_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SResultChangeFriendMemo {
+    pub user_id: i32,
+    pub memo: String,
+    pub success: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SReturnParcel {
+    pub id: i64,
+    pub success: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SSelectUser {
+    unk1: u8, // TODO try to identify the usage of the fields
+    unk2: u16,
+    unk3: u64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SSendParcel {
+    pub success: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SSetSendParcelItem {
+    pub items: Vec<SSetSendParcelItemItem>,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SSetSendParcelItemItem {
+    pub id: i32, // Item template ID
+    pub db_id: i64,
+    pub inventory_slot: i32,
+    pub amount: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SSetSendParcelMoney {
+    pub money: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SShowParcelMessage {
+    pub items: Vec<SShowParcelMessageItem>,
+    pub sender: String,
+    pub title: String,
+    pub message: String,
+    pub id: i64,
+    pub money: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SShowParcelMessageItem {
+    pub id: i32, // Item template ID
+    pub db_id: i64,
+    pub amount: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SSpawnMe {
+    pub user_id: EntityId,
+    pub location: Vec3f,
+    pub rotation: Angle,
+    pub is_alive: bool,
+    pub is_lord: bool, // TODO try to identify the usage of the field
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SSpawnNpc {
+    pub npc_id: EntityId,
+    pub target_id: u64, // 0 = no target
+    pub location: Vec3f,
+    pub rotation: Angle,
+    pub relation: i32, // TODO investigate the exact values
+    pub template_id: i32,
+    pub hunting_zone_id: u16,
+    pub shape_id: i32,
+    pub walk_speed: i16,
+    pub run_speed: i16,
+    pub status: i32,
+    pub mode: i32,
+    pub hp_level: i16,
+    pub quest_info: i16,
+    pub visible: bool,
+    pub villager: bool,
+    pub spawn_type: i32,
+    pub replace_id: u64,
+    pub spawn_script: i32,
+    pub replace_despawn_script: i32,
+    pub aggressive: bool,
+    pub owner_id: u64, // 0 = no owner
+    pub occupied_by_party_id: i32,
+    pub occupied_by_player_id: i32,
+    pub bomb: bool,
+    pub by_spawn_event: bool,
+    pub battleground_team: i32,
+    pub active_cylinder: i32,
+    pub repairable: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SSpawnUser {
+    pub servants: Vec<SLoginServantEntry>, // Same layout as the servants in S_LOGIN
+    pub name: String,
+    pub guild_name: String,
+    pub guild_rank: String,
+    #[serde(with = "serde_bytes")]
+    pub details: Vec<u8>,
+    pub guild_title: String,
+    #[serde(with = "serde_bytes")]
+    pub shape: Vec<u8>,
+    pub guild_logo: String,
+    pub server_id: i32,
+    pub db_id: i32,
+    pub user_id: EntityId,
+    pub location: Vec3f,
+    pub rotation: Angle,
+    pub relation: i32, // TODO investigate the exact values
+    pub template_id: TemplateID,
+    pub walk_speed: i16,
+    pub run_speed: i16,
+    pub status: i32,
+    pub visible: bool,
+    pub is_alive: bool,
+    pub appearance: Customization,
+    pub spawn_fx: bool,
+    pub weapon: i32,
+    pub body: i32,
+    pub hand: i32,
+    pub feet: i32,
+    pub underwear: i32,
+    pub head: i32,
+    pub face: i32,
+    pub weapon_model: i32,
+    pub body_model: i32,
+    pub hand_model: i32,
+    pub feet_model: i32,
+    pub weapon_dye: i32,
+    pub body_dye: i32,
+    pub hand_dye: i32,
+    pub feet_dye: i32,
+    pub underwear_dye: i32,
+    pub style_back_dye: i32,
+    pub style_head_dye: i32,
+    pub style_face_dye: i32,
+    pub weapon_enchant: i32,
+    pub is_world_event_target: bool,
+    pub infamy: i32,
+    pub show_face: bool,
+    pub style_head: i32,
+    pub style_face: i32,
+    pub style_back: i32,
+    pub style_weapon: i32,
+    pub style_body: i32,
+    pub style_footprint: i32,
+    pub style_body_dye: i32,
+    pub show_style: bool,
+    pub title: i32, // achievement ID
+    pub level: i16,
+    pub appearance2: i32,
+    pub scale: f32,
+    pub guild_logo_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SStartCooltimeSkill {
+    pub skill_id: i64,
+    pub cooldown: i32, // Cooldown in ms
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SStartGuildWar {
+    pub guild_name: String, // Name of the enemy guild
+    pub guild_id: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct STradeAccept {
+    pub contract_id: i32,
+    pub user_id: EntityId, // User that confirmed the trade
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct STradeBagClose {
+    pub contract_id: i32,
+    pub success: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct STradeBagDone {
+    pub contract_id: i32,
+    pub user_id: EntityId, // User that locked it's offer
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct STradeBox {
+    pub items: Vec<STradeBoxItem>,
+    pub contract_id: i32,
+    pub sender_id: EntityId,
+    pub sender_money: i64,
+    pub recipient_id: EntityId,
+    pub recipient_money: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct STradeBoxItem {
+    pub owner_id: EntityId,
+    pub id: i32,
+    pub db_id: i64,
+    pub amount: i32,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SUserBlockList {
+    pub blocked_users: Vec<SUserBlockListEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SUserBlockListEntry {
+    pub name: String,
+    pub memo: String,
+    pub user_id: i32,
+    pub level: i32,
+    pub class: Class,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SUserExternalChange {
+    pub game_id: EntityId,
+    pub weapon: i32,
+    pub body: i32,
+    pub hand: i32,
+    pub feet: i32,
+    pub underwear: i32,
+    pub head: i32,
+    pub face: i32,
+    pub weapon_model: i32,
+    pub body_model: i32,
+    pub hand_model: i32,
+    pub feet_model: i32,
+    pub weapon_dye: i32,
+    pub body_dye: i32,
+    pub hand_dye: i32,
+    pub feet_dye: i32,
+    pub underwear_dye: i32,
+    pub style_back_dye: i32,
+    pub style_head_dye: i32,
+    pub style_face_dye: i32,
+    pub weapon_enchant: i32,
+    pub show_face: bool,
+    pub style_head: i32,
+    pub style_face: i32,
+    pub style_back: i32,
+    pub style_weapon: i32,
+    pub style_body: i32,
+    pub style_footprint: i32,
+    pub style_body_dye: i32,
+    pub show_style: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SUserLevelup {
+    pub target_id: EntityId,
+    pub level: i16,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SUserLocation {
+    pub user_id: EntityId,
+    pub location: Vec3f,
+    pub rotation: Angle,
+    pub look_direction: i16,
+    pub speed: i16,
+    pub destination: Vec3f,
+    pub move_type: i32,
+    pub in_shuttle: bool,
+    pub time: u32, // Client timestamp in ms
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SViewWareEx {
+    pub items: Vec<SViewWareExItem>,
+    pub game_id: EntityId,
+    pub container: i32,
+    pub action: i32, // 0 = Open the window, 1 = Refresh the shown page
+    pub offset: i32, // First slot of the shown page
+    pub size: i32,   // Number of usable slots
+    pub money: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SViewWareExItem {
+    pub id: i32, // Item template ID
+    pub db_id: i64,
+    pub slot: i32,
+    pub amount: i32,
+    pub enchantment: i32,
+    pub soulbound: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct SWhisper {
+    pub author_name: String,
+    pub recipient: String,
+    pub message: String,
+    pub user_id: EntityId,
+    pub unk1: u8,
+    pub is_gm: bool,
+    pub is_founder: bool,
+}
+
+#[cfg(test)]
+#[macro_use]
+mod tests {
+    use super::*;
+    use crate::protocol::serde::{from_vec, to_vec, Result};
+
+    packet_test!(
+        name: test_abnormality_begin,
+        data: vec![
+            0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xa0, 0xf, 0x0, 0x0, 0x60, 0xea, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0,
+        ],
+        expected: SAbnormalityBegin {
+            target_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            source_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            id: 4000,
+            duration: 60000,
+            unk1: 0,
+            stacks: 1,
+        }
+    );
+
+    packet_test!(
+        name: test_abnormality_end,
+        data: vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xa0, 0xf, 0x0, 0x0],
+        expected: SAbnormalityEnd {
+            target_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            id: 4000,
+        }
+    );
+
+    packet_test!(
+        name: test_abnormality_refresh,
+        data: vec![
+            0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xa0, 0xf, 0x0, 0x0, 0x60, 0xea, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0,
+        ],
+        expected: SAbnormalityRefresh {
+            target_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            id: 4000,
+            duration: 60000,
+            unk1: 0,
+            stacks: 2,
+        }
+    );
+
+    packet_test!(
+        name: test_abnormality_tooltip_value,
+        data: vec![0xa0, 0xf, 0x0, 0x0, 0x0, 0x0, 0xc8, 0x41],
+        expected: SAbnormalityTooltipValue {
+            id: 4000,
+            value: 25.0,
+        }
+    );
+
+    packet_test!(
+        name: test_accept_contract,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x3, 0x0, 0x0, 0x0, 0x12, 0x4, 0x0, 0x0,
+        ],
+        expected: SAcceptContract {
+            sender_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            recipient_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            contract_type: 3,
+            contract_id: 1042,
+        }
+    );
+
+    packet_test!(
+        name: test_account_package_list,
+        data: vec![
             0x1, 0x0, 0x8, 0x0, 0x8, 0x0, 0x0, 0x0, 0xb2, 0x1, 0x0, 0x0, 0xff, 0xff, 0xff, 0x7f,
             0x0, 0x0, 0x0, 0x0,
         ],
-        expected: SAccountPackageList {
-            account_benefits: vec![SAccountPackageListEntry {
-                package_id: 434,
-                expiration_date: 2_147_483_647,
-            }]
+        expected: SAccountPackageList {
+            account_benefits: vec![SAccountPackageListEntry {
+                package_id: 434,
+                expiration_date: 2_147_483_647,
+            }]
+        }
+    );
+
+    packet_test!(
+        name: test_action_end,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x7a, 0x44, 0x0, 0x0, 0xfa, 0xc4,
+            0x0, 0x0, 0x16, 0x43, 0x0, 0x40, 0xd9, 0x27, 0x0, 0x0, 0x3b, 0x9c, 0x1, 0x0, 0x0, 0x0,
+            0x0, 0x4, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0,
+        ],
+        expected: SActionEnd {
+            user_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            location: Vec3f {
+                x: 1000.0,
+                y: -2000.0,
+                z: 150.0,
+            },
+            rotation: Angle::from_deg(90.0),
+            template_id: TemplateID {
+                race: Race::Human,
+                gender: Gender::Female,
+                class: Class::Warrior,
+            },
+            skill_id: 288_230_376_151_817_275,
+            end_type: 0,
+            action_id: 1,
+        }
+    );
+
+    packet_test!(
+        name: test_action_stage,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x7a, 0x44, 0x0, 0x0, 0xfa, 0xc4,
+            0x0, 0x0, 0x16, 0x43, 0x0, 0x40, 0xd9, 0x27, 0x0, 0x0, 0x3b, 0x9c, 0x1, 0x0, 0x0, 0x0,
+            0x0, 0x4, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x7a, 0x44, 0x0, 0x0, 0xfa, 0xc4, 0x0, 0x0, 0x16, 0x43,
+        ],
+        expected: SActionStage {
+            user_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            location: Vec3f {
+                x: 1000.0,
+                y: -2000.0,
+                z: 150.0,
+            },
+            rotation: Angle::from_deg(90.0),
+            template_id: TemplateID {
+                race: Race::Human,
+                gender: Gender::Female,
+                class: Class::Warrior,
+            },
+            skill_id: 288_230_376_151_817_275,
+            stage: 0,
+            speed: 1.0,
+            action_id: 1,
+            moving: false,
+            destination: Vec3f {
+                x: 1000.0,
+                y: -2000.0,
+                z: 150.0,
+            },
+        }
+    );
+
+    packet_test!(
+        name: test_add_blocked_user,
+        data: vec![
+            0xc, 0x0, 0x16, 0x0, 0xc, 0x0, 0x0, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0,
+            0x0, 0x0, 0x53, 0x0, 0x70, 0x0, 0x61, 0x0, 0x6d, 0x0, 0x0, 0x0,
+        ],
+        expected: SAddBlockedUser {
+            name: "Test".to_string(),
+            memo: "Spam".to_string(),
+            user_id: 12,
+        }
+    );
+
+    packet_test!(
+        name: test_add_friend,
+        data: vec![
+            0x8, 0x0, 0x12, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0, 0x48, 0x0,
+            0x69, 0x0, 0x0, 0x0,
+        ],
+        expected: SAddFriend {
+            name: "Test".to_string(),
+            message: "Hi".to_string(),
+        }
+    );
+
+    packet_test!(
+        name: test_add_inter_party_match_pool,
+        data: vec![],
+        expected: SAddInterPartyMatchPool {}
+    );
+
+    packet_test!(
+        name: test_ban_party,
+        data: vec![],
+        expected: SBanParty {}
+    );
+
+    packet_test!(
+        name: test_ban_party_member,
+        data: vec![
+            0xe, 0x0, 0x1, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0,
+            0x74, 0x0, 0x0, 0x0,
+        ],
+        expected: SBanPartyMember {
+            name: "Test".to_string(),
+            server_id: 1,
+            user_id: 12,
+        }
+    );
+
+    packet_test!(
+        name: test_begin_through_arbiter_contract,
+        data: vec![
+            0x12, 0x0, 0x1c, 0x0, 0x2, 0x0, 0x4, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x54, 0x0,
+            0x65, 0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0, 0x1, 0x2,
+        ],
+        expected: SBeginThroughArbiterContract {
+            name: "Test".to_string(),
+            data: vec![0x1, 0x2],
+            contract_type: 4,
+            contract_id: 12,
+        }
+    );
+
+    packet_test!(
+        name: test_can_create_user,
+        data: vec![
+            0x1,
+        ],
+        expected: SCanCreateUser {
+            ok: true,
+        }
+    );
+
+    packet_test!(
+        name: test_cancel_contract,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x3, 0x0, 0x0, 0x0, 0x12, 0x4, 0x0, 0x0,
+        ],
+        expected: SCancelContract {
+            sender_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            recipient_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            contract_type: 3,
+            contract_id: 1042,
+        }
+    );
+
+    packet_test!(
+        name: test_cancel_select_channel,
+        data: vec![],
+        expected: SCancelSelectChannel {}
+    );
+
+    packet_test!(
+        name: test_cannot_start_skill,
+        data: vec![0x3b, 0x9c, 0x1, 0x0, 0x0, 0x0, 0x0, 0x4],
+        expected: SCannotStartSkill {
+            skill_id: 288_230_376_151_817_275,
+        }
+    );
+
+    packet_test!(
+        name: test_change_party_manager,
+        data: vec![
+            0xe, 0x0, 0x1, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0,
+            0x74, 0x0, 0x0, 0x0,
+        ],
+        expected: SChangePartyManager {
+            name: "Test".to_string(),
+            server_id: 1,
+            user_id: 12,
+        }
+    );
+
+    packet_test!(
+        name: test_change_friend_state,
+        data: vec![0xc, 0x0, 0x0, 0x0, 0x1],
+        expected: SChangeFriendState {
+            user_id: 12,
+            online: true,
+        }
+    );
+
+    packet_test!(
+        name: test_chat,
+        data: vec![
+            0x17, 0x0, 0x21, 0x0, 0xc, 0x0, 0x0, 0x0, 0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1,
+            0x0, 0x0, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0, 0x3c, 0x0, 0x46,
+            0x0, 0x4f, 0x0, 0x4e, 0x0, 0x54, 0x0, 0x3e, 0x0, 0x48, 0x0, 0x69, 0x0, 0x3c, 0x0, 0x2f,
+            0x0, 0x46, 0x0, 0x4f, 0x0, 0x4e, 0x0, 0x54, 0x0, 0x3e, 0x0, 0x0, 0x0,
+        ],
+        expected: SChat {
+            author_name: "Test".to_string(),
+            message: "<FONT>Hi</FONT>".to_string(),
+            channel: ChatChannel::PrivateChannel(2),
+            user_id: from_vec::<EntityId>(vec![0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1])?,
+            unk1: 0,
+            is_gm: false,
+            is_founder: false,
+        }
+    );
+
+    packet_test!(
+        name: test_check_to_declare_guild_war,
+        data: vec![
+            0x7, 0x0, 0x1, 0x47, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0, 0x0, 0x0,
+        ],
+        expected: SCheckToDeclareGuildWar {
+            name: "Gantsu".to_string(),
+            ok: true,
+        }
+    );
+
+    packet_test!(
+        name: test_check_to_ready_party,
+        data: vec![
+            0x2, 0x0, 0xc, 0x0, 0x29, 0x23, 0x0, 0x0, 0xc, 0x0, 0x19, 0x0, 0x1, 0x0, 0x0, 0x0,
+            0xc, 0x0, 0x0, 0x0, 0x1, 0x19, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0xd, 0x0, 0x0,
+            0x0, 0x0,
+        ],
+        expected: SCheckToReadyParty {
+            members: vec![
+                SCheckToReadyPartyEntry {
+                    server_id: 1,
+                    user_id: 12,
+                    ready: true,
+                },
+                SCheckToReadyPartyEntry {
+                    server_id: 1,
+                    user_id: 13,
+                    ready: false,
+                },
+            ],
+            zone_id: 9001,
+        }
+    );
+
+    packet_test!(
+        name: test_check_to_ready_party_fin,
+        data: vec![],
+        expected: SCheckToReadyPartyFin {}
+    );
+
+    packet_test!(
+        name: test_check_username,
+        data: vec![
+            0x1
+        ],
+        expected: SCheckUserName {
+            ok: true,
+        }
+    );
+
+    packet_test!(
+        name: test_check_version,
+        data: vec![
+            0x1
+        ],
+        expected: SCheckVersion {
+            ok: true,
+        }
+    );
+
+    packet_test!(
+        name: test_create_guild_result,
+        data: vec![0x1],
+        expected: SCreateGuildResult { ok: true }
+    );
+
+    packet_test!(
+        name: test_create_user,
+        data: vec![
+            0x1
+        ],
+        expected: SCreateUser {
+            ok: true,
+        }
+    );
+
+    packet_test!(
+        name: test_creature_change_hp,
+        data: vec![
+            0x96, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc8, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xce, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x1, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: SCreatureChangeHp {
+            current_hp: 150,
+            max_hp: 200,
+            diff: -50,
+            change_type: 1,
+            target_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            source_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            crit: false,
+        }
+    );
+
+    packet_test!(
+        name: test_creature_life,
+        data: vec![
+            0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x7a, 0x44, 0x0, 0x0, 0xfa, 0xc4,
+            0x0, 0x0, 0x16, 0x43, 0x0,
+        ],
+        expected: SCreatureLife {
+            target_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            location: Vec3f {
+                x: 1000.0,
+                y: -2000.0,
+                z: 150.0,
+            },
+            is_alive: false,
+        }
+    );
+
+    packet_test!(
+        name: test_current_channel,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0, 0xd, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0,
+        ],
+        expected: SCurrentChannel {
+            server_id: 1,
+            zone: 13,
+            channel: 2,
+        }
+    );
+
+    packet_test!(
+        name: test_del_inter_party_match_pool,
+        data: vec![],
+        expected: SDelInterPartyMatchPool {}
+    );
+
+    packet_test!(
+        name: test_delete_friend,
+        data: vec![0xc, 0x0, 0x0, 0x0],
+        expected: SDeleteFriend { user_id: 12 }
+    );
+
+    packet_test!(
+        name: test_delete_parcel,
+        data: vec![
+            0x4, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1,
+        ],
+        expected: SDeleteParcel {
+            id: 5124,
+            success: true,
+        }
+    );
+
+    packet_test!(
+        name: test_delete_user,
+        data: vec![
+            0x1
+        ],
+        expected: SDeleteUser {
+            ok: true,
+        }
+    );
+
+    packet_test!(
+        name: test_despawn_npc,
+        data: vec![
+            0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0, 0x40,
+            0x0, 0x0, 0x40, 0x40, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: SDespawnNpc {
+            npc_id: from_vec::<EntityId>(vec![0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1])?,
+            location: Vec3f{x: 1.0, y: 2.0, z: 3.0},
+            despawn_type: 1,
+            unk1: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_despawn_user,
+        data: vec![
+            0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1, 0x1, 0x0, 0x0, 0x0,
+        ],
+        expected: SDespawnUser {
+            user_id: from_vec::<EntityId>(vec![0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1])?,
+            despawn_type: 1,
+        }
+    );
+
+    packet_test!(
+        name: test_destroy_guild,
+        data: vec![],
+        expected: SDestroyGuild {}
+    );
+
+    packet_test!(
+        name: test_dungeon_clear_count_list,
+        data: vec![
+            0x2, 0x0, 0x8, 0x0, 0x8, 0x0, 0x14, 0x0, 0xf1, 0x25, 0x0, 0x0, 0x4, 0x0, 0x0, 0x0,
+            0x14, 0x0, 0x0, 0x0, 0xf2, 0x25, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0,
+        ],
+        expected: SDungeonClearCountList {
+            dungeons: vec![
+                SDungeonClearCountListEntry {
+                    zone_id: 9713,
+                    clear_count: 4,
+                },
+                SDungeonClearCountListEntry {
+                    zone_id: 9714,
+                    clear_count: 1,
+                },
+            ],
+        }
+    );
+
+    packet_test!(
+        name: test_dungeon_cool_time_list,
+        data: vec![
+            0x1, 0x0, 0x8, 0x0, 0x8, 0x0, 0x0, 0x0, 0xf1, 0x25, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0,
+            0x3, 0x0, 0x0, 0x0, 0x40, 0xe2, 0xd8, 0x5e, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: SDungeonCoolTimeList {
+            dungeons: vec![SDungeonCoolTimeListEntry {
+                zone_id: 9713,
+                entry_count: 2,
+                max_entry_count: 3,
+                reset_time: 1_591_272_000,
+            }],
+        }
+    );
+
+    packet_test!(
+        name: test_each_skill_result,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xd9, 0x27, 0x0, 0x0, 0x3b, 0x9c, 0x1, 0x0, 0x0, 0x0, 0x0, 0x4, 0x0, 0x0, 0x0, 0x0,
+            0x1, 0x0, 0x0, 0x0, 0x32, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0,
+            0x0,
+        ],
+        expected: SEachSkillResult {
+            source_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            target_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            template_id: TemplateID {
+                race: Race::Human,
+                gender: Gender::Female,
+                class: Class::Warrior,
+            },
+            skill_id: 288_230_376_151_817_275,
+            stage: 0,
+            action_id: 1,
+            value: 50,
+            result_type: 1,
+            crit: false,
+        }
+    );
+
+    packet_test!(
+        name: test_end_guild_war,
+        data: vec![
+            0x16, 0x0, 0x2, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x7, 0x0, 0x0,
+            0x0, 0x47, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0, 0x0, 0x0,
+        ],
+        expected: SEndGuildWar {
+            guild_name: "Gantsu".to_string(),
+            guild_id: 2,
+            state: GuildWarState::Surrendered,
+            kills: 12,
+            deaths: 7,
+        }
+    );
+
+    packet_test!(
+        name: test_item_custom_string1,
+        data: vec![
+            0x0, 0x0, 0x0, 0x0, 0x11, 0x7f, 0x1c, 0x0, 0x0, 0x80, 0x0, 0x2,
+        ],
+        expected: SItemCustomString {
+            custom_strings: Vec::with_capacity(0),
+            game_id: 144_255_925_566_078_737,
+        }
+    );
+
+    packet_test!(
+        name: test_item_custom_string2,
+        data: vec![
+            0x1, 0x0, 0x10, 0x0, 0x11, 0x21, 0x11, 0x0, 0x0, 0x80, 0x0, 0x3, 0x10, 0x0, 0x0, 0x0,
+            0x1a, 0x0, 0x22, 0x11, 0x2, 0x0, 0x50, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0,
+            0x75, 0x0, 0x0, 0x0,
+        ],
+        expected: SItemCustomString {
+            custom_strings: vec![SItemCustomStringEntry {
+                string: "Pantsu".to_string(),
+                id: 135_458,
+            }],
+            game_id: 216_313_519_603_261_713,
+        }
+    );
+
+    packet_test!(
+        name: test_fin_inter_party_match,
+        data: vec![0x29, 0x23, 0x0, 0x0],
+        expected: SFinInterPartyMatch { zone_id: 9001 }
+    );
+
+    packet_test!(
+        name: test_friend_group_list,
+        data: vec![
+            0x1, 0x0, 0x8, 0x0, 0x8, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0, 0x12, 0x0, 0x52, 0x0,
+            0x61, 0x0, 0x69, 0x0, 0x64, 0x0, 0x0, 0x0,
+        ],
+        expected: SFriendGroupList {
+            groups: vec![SFriendGroupListEntry {
+                group_id: 3,
+                name: "Raid".to_string(),
+            }],
+        }
+    );
+
+    packet_test!(
+        name: test_friend_list,
+        data: vec![
+            0x1, 0x0, 0x8, 0x0, 0x8, 0x0, 0x0, 0x0, 0x21, 0x0, 0x2b, 0x0, 0xc, 0x0, 0x0, 0x0,
+            0x3, 0x0, 0x0, 0x0, 0x41, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x1, 0x54, 0x0, 0x65,
+            0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0, 0x54, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x6b, 0x0, 0x0,
+            0x0,
+        ],
+        expected: SFriendList {
+            friends: vec![SFriendListEntry {
+                name: "Test".to_string(),
+                memo: "Tank".to_string(),
+                user_id: 12,
+                group_id: 3,
+                level: 65,
+                class: Class::Lancer,
+                online: true,
+            }],
+        }
+    );
+
+    packet_test!(
+        name: test_get_user_list,
+        data: vec![
+            0x1, 0x0, 0x23, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x28, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x18, 0x0, 0x0, 0x0, 0x23, 0x0, 0x0, 0x0, 0x1, 0x0, 0xeb, 0x1, 0x3, 0x2, 0x15, 0x2,
+            0x20, 0x0, 0x35, 0x2, 0x40, 0x0, 0x75, 0x2, 0x3, 0x85, 0x1e, 0x0, 0x1, 0x0, 0x0, 0x0, 0x4, 0x0, 0x0, 0x0, 0x1,
+            0x0, 0x0, 0x0, 0x41, 0x0, 0x0, 0x0, 0x17, 0xd9, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0xd0, 0x7, 0x0, 0x0, 0x1, 0x0,
+            0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x8, 0x0, 0x0, 0x0, 0xf1, 0xe, 0x6b, 0x5e, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x51,
+            0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0xed, 0xb, 0x79, 0xa1, 0xd1, 0x6e, 0x0, 0x0, 0x8f, 0x78, 0x1, 0x0, 0x8e, 0x78,
+            0x1, 0x0, 0x19, 0x78, 0x1, 0x0, 0x1b, 0x78, 0x1, 0x0, 0x1d, 0x78, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x88, 0x78,
+            0x1, 0x0, 0x87, 0x78, 0x1, 0x0, 0x5b, 0xbb, 0x2, 0x0, 0x88, 0xc3, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x2, 0x3,
+            0x4, 0x5, 0x6, 0x7, 0x8, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x6d, 0xba,
+            0x77, 0xa1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x7a, 0xb3, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x2d, 0x98, 0x2, 0x0, 0x61, 0xb6, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x3c, 0x19, 0x19, 0x19, 0xf, 0x0, 0x0, 0x0,
+            0x40, 0x46, 0x74, 0x11, 0x0, 0x0, 0x0, 0x0, 0x4c, 0x46, 0x74, 0x11, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x80,
+            0x3f, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x64, 0x0, 0x0, 0x0, 0xfd, 0x34, 0x0,
+            0x0, 0x2, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0xa9, 0x11, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xeb, 0x1, 0x0,
+            0x0, 0xf5, 0x1, 0x68, 0xe1, 0x3, 0x0, 0x50, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0, 0x0,
+            0x0, 0x41, 0x0, 0x6c, 0x0, 0x6d, 0x0, 0x65, 0x0, 0x74, 0x0, 0x69, 0x0, 0x63, 0x0, 0x61, 0x0, 0x0, 0x0, 0x0,
+            0x7, 0x0, 0xc, 0x0, 0x0, 0x0, 0x0, 0x1a, 0x18, 0x14, 0x0, 0x0, 0xd, 0x7, 0x0, 0x10, 0x0, 0x10, 0x10, 0x0, 0x0,
+            0x0, 0xe, 0x11, 0x1d, 0xc, 0x18, 0x1a, 0x10, 0x7, 0x3, 0x1, 0x13, 0x10, 0x13, 0x13, 0x10, 0x13, 0x13, 0x13,
+            0x10, 0x10, 0x10, 0x10, 0xf, 0xf, 0xf, 0x10, 0x13, 0xa, 0x0, 0x16, 0x17, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x55, 0x0, 0x6e, 0x0, 0x6c, 0x0, 0x69, 0x0,
+            0x6d, 0x0, 0x69, 0x0, 0x74, 0x0, 0x65, 0x0, 0x64, 0x0, 0x20, 0x0, 0x50, 0x0, 0x6f, 0x0, 0x77, 0x0, 0x65, 0x0,
+            0x72, 0x0, 0x0, 0x0,
+        ],
+        expected: SGetUserList {
+            characters: vec![SGetUserListCharacter {
+                custom_strings: vec![SGetUserListCharacterCustomString {
+                    string: "Pantsu".to_string(),
+                    id: 254_312,
+                }],
+                name: "Almetica".to_string(),
+                details: vec![
+                    0, 7, 0, 12, 0, 0, 0, 0, 26, 24, 20, 0, 0, 13, 7, 0, 16, 0, 16, 16, 0, 0, 0, 14, 17, 29, 12, 24, 26,
+                    16, 7, 3,
+                ],
+                shape: vec![
+                    1, 19, 16, 19, 19, 16, 19, 19, 19, 16, 16, 16, 16, 15, 15, 15, 16, 19, 10, 0, 22, 23, 9, 0, 0, 0, 0, 0,
+                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                    0, 0,
+                ],
+                guild_name: "Unlimited Power".to_string(),
+                db_id: 2_000_131,
+                gender: Gender::Female,
+                race: Race::ElinPopori,
+                class: Class::Lancer,
+                level: 65,
+                hp: 121_111,
+                mp: 2000,
+                world_id: 1,
+                guard_id: 2,
+                section_id: 8,
+                last_logout_time: 1_584_074_481,
+                is_deleting: false,
+                delete_time: 86400,
+                delete_remain_sec: -1_585_902_611,
+                weapon: 28369,
+                earring1: 96399,
+                earring2: 96398,
+                body: 96281,
+                hand: 96283,
+                feet: 96285,
+                unk_item7: 0,
+                ring1: 96392,
+                ring2: 96391,
+                underwear: 179_035,
+                head: 50056,
+                face: 0,
+                appearance: Customization(vec![1,2,3,4,5,6,7,8]),
+                is_second_character: false,
+                admin_level: 0,
+                is_banned: false,
+                ban_end_time: 0,
+                ban_remain_sec: -1_585_989_011,
+                rename_needed: 0,
+                weapon_model: 0,
+                unk_model2: 0,
+                unk_model3: 0,
+                body_model: 0,
+                hand_model: 0,
+                feet_model: 0,
+                unk_model7: 0,
+                unk_model8: 0,
+                unk_model9: 0,
+                unk_model10: 0,
+                unk_dye1: 0,
+                unk_dye2: 0,
+                weapon_dye: 0,
+                body_dye: 0,
+                hand_dye: 0,
+                feet_dye: 0,
+                unk_dye7: 0,
+                unk_dye8: 0,
+                unk_dye9: 0,
+                underwear_dye: 0,
+                style_back_dye: 0,
+                style_head_dye: 0,
+                style_face_dye: 0,
+                style_head: 177_018,
+                style_face: 0,
+                style_back: 0,
+                style_weapon: 170_029,
+                style_body: 177_761,
+                style_footprint: 0,
+                style_body_dye: 421_075_260,
+                weapon_enchant: 15,
+                rest_bonus_xp: 292_832_832,
+                max_rest_bonus_xp: 292_832_844,
+                show_face: true,
+                style_head_scale: 1.0,
+                style_head_rotation: Vec3a { x: 0, y: 0, z: 0 },
+                style_head_translation: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
+                style_head_translation_debug: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
+                style_faces_scale: 1.0,
+                style_face_rotation: Vec3a { x: 0, y: 0, z: 0 },
+                style_face_translation: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
+                style_face_translation_debug: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
+                style_back_scale: 1.0,
+                style_back_rotation: Vec3a { x: 0, y: 0, z: 0 },
+                style_back_translation: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
+                style_back_translation_debug: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
+                used_style_head_transform: false,
+                is_new_character: false,
+                tutorial_state: 0,
+                show_style: true,
+                appearance2: 100,
+                achievement_points: 13565,
+                laurel: 2,
+                lobby_slot: 1,
+                guild_logo_id: 4521,
+                awakening_level: 0,
+                has_broker_sales: false,
+            }],
+            veteran: false,
+            bonus_buf_sec: 0,
+            max_characters: 12,
+            first: true,
+            more: false,
+            left_del_time_account_over: 0,
+            deletion_section_classify_level: 40,
+            delete_character_expire_hour1: 0,
+            delete_character_expire_hour2: 24,
+        }
+    );
+
+    packet_test!(
+        name: test_guild_info,
+        data: vec![
+            0x1, 0x0, 0x1c, 0x0, 0x3c, 0x0, 0x50, 0x0, 0xc, 0x0, 0x0, 0x0, 0x17, 0x0, 0x0, 0x0,
+            0x40, 0xe2, 0xd8, 0x5e, 0x0, 0x0, 0x0, 0x0, 0x1c, 0x0, 0x0, 0x0, 0x2a, 0x0, 0x7, 0x0,
+            0x0, 0x0, 0x3, 0x0, 0x0, 0x0, 0x4f, 0x0, 0x66, 0x0, 0x66, 0x0, 0x69, 0x0, 0x63, 0x0,
+            0x65, 0x0, 0x72, 0x0, 0x73, 0x0, 0x0, 0x0, 0x4d, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x68, 0x0,
+            0x75, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x65, 0x0, 0x72, 0x0, 0x0, 0x0, 0x4e, 0x0, 0x79, 0x0,
+            0x78, 0x0, 0x0, 0x0,
+        ],
+        expected: SGuildInfo {
+            groups: vec![SGuildInfoGroup {
+                name: "Officers".to_string(),
+                group_id: 7,
+                authority: 3,
+            }],
+            name: "Manhunter".to_string(),
+            master_name: "Nyx".to_string(),
+            guild_id: 12,
+            master_id: 23,
+            created_at: 1_591_272_000,
+        }
+    );
+
+    packet_test!(
+        name: test_guild_member_list,
+        data: vec![
+            0x2, 0x0, 0x8, 0x0, 0x8, 0x0, 0x1f, 0x0, 0x36, 0x0, 0x17, 0x0, 0x0, 0x0, 0x41, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x7, 0x0, 0x0, 0x0, 0x1, 0x1f, 0x0, 0x0, 0x0, 0x3e,
+            0x0, 0x18, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x6, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x4e, 0x0, 0x79, 0x0, 0x78, 0x0, 0x0, 0x0, 0x56, 0x0, 0x65, 0x0, 0x78, 0x0,
+            0x0, 0x0,
+        ],
+        expected: SGuildMemberList {
+            members: vec![
+                SGuildMemberListEntry {
+                    name: "Nyx".to_string(),
+                    user_id: 23,
+                    level: 65,
+                    class: Class::Warrior,
+                    group_id: 7,
+                    online: true,
+                },
+                SGuildMemberListEntry {
+                    name: "Vex".to_string(),
+                    user_id: 24,
+                    level: 1,
+                    class: Class::Priest,
+                    group_id: 0,
+                    online: false,
+                },
+            ],
+        }
+    );
+
+    packet_test!(
+        name: test_guild_name,
+        data: vec![
+            0x14, 0x0, 0x2e, 0x0, 0x48, 0x0, 0x58, 0x0, 0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3,
+            0x4d, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x68, 0x0, 0x75, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x65, 0x0,
+            0x72, 0x0, 0x20, 0x0, 0x4f, 0x0, 0x47, 0x0, 0x0, 0x0, 0x46, 0x0, 0x6f, 0x0, 0x72, 0x0,
+            0x20, 0x0, 0x74, 0x0, 0x68, 0x0, 0x65, 0x0, 0x20, 0x0, 0x77, 0x0, 0x69, 0x0, 0x6e, 0x0,
+            0x21, 0x0, 0x0, 0x0, 0x47, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0,
+            0x7e, 0x0, 0x0, 0x0, 0x67, 0x0, 0x75, 0x0, 0x69, 0x0, 0x6c, 0x0, 0x64, 0x0, 0x6c, 0x0,
+            0x6f, 0x0, 0x67, 0x0, 0x6f, 0x0, 0x5f, 0x0, 0x39, 0x0, 0x39, 0x0, 0x5f, 0x0, 0x31, 0x0,
+            0x31, 0x0, 0x31, 0x0, 0x31, 0x0, 0x5f, 0x0, 0x39, 0x0, 0x31, 0x0, 0x0, 0x0,
+        ],
+        expected: SGuildName {
+            guild_name: "Manhunter OG".to_string(),
+            guild_rank: "For the win!".to_string(),
+            guild_title: "Gantsu~".to_string(),
+            guild_logo: "guildlogo_99_1111_91".to_string(),
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+        }
+    );
+
+    packet_test!(
+        name: test_image_data,
+        data: vec![
+            0xa, 0x0, 0x36, 0x0, 0xe, 0x00, 0x67, 0x0, 0x75, 0x0, 0x69, 0x0, 0x6c, 0x0, 0x64, 0x0,
+            0x6c, 0x0, 0x6f, 0x0, 0x67, 0x0, 0x6f, 0x0, 0x5f, 0x0, 0x32, 0x0, 0x38, 0x0, 0x5f, 0x0,
+            0x35, 0x0, 0x35, 0x0, 0x35, 0x0, 0x31, 0x0, 0x39, 0x0, 0x5f, 0x0, 0x36, 0x0, 0x30, 0x0,
+            0x0, 0x0, 0x54, 0x45, 0x52, 0x41, 0x1, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: SImageData {
+            name: "guildlogo_28_55519_60".to_string(),
+            data: vec![
+                0x54, 0x45, 0x52, 0x41, 0x1, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0,
+            ],
+        }
+    );
+
+    packet_test!(
+        name: test_itemlist,
+        data: vec![
+            0x2, 0x0, 0x31, 0x0, 0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0xe, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x28, 0x0, 0x0, 0x0, 0xdc, 0x5, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x1, 0x1, 0x0, 0x1, 0x31, 0x0, 0x5a,
+            0x0, 0x45, 0x1f, 0x0, 0x0, 0xd, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x5a, 0x0, 0x0, 0x0, 0x11, 0x27, 0x0, 0x0, 0xe, 0x9,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x4, 0x0,
+            0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x64, 0x0, 0x0, 0x0, 0x1,
+        ],
+        expected: SItemlist {
+            items: vec![
+                SItemlistItem {
+                    id: 8005,
+                    db_id: 2317,
+                    owner_id: 12,
+                    slot: 3,
+                    amount: 20,
+                    enchantment: 0,
+                    durability: 0,
+                    soulbound: false,
+                },
+                SItemlistItem {
+                    id: 10001,
+                    db_id: 2318,
+                    owner_id: 12,
+                    slot: 4,
+                    amount: 1,
+                    enchantment: 0,
+                    durability: 100,
+                    soulbound: true,
+                },
+            ],
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 14,
+            pocket: 0,
+            num_pockets: 1,
+            size: 40,
+            money: 1500,
+            loot_priority: 0,
+            open: true,
+            requested: true,
+            first: true,
+            more: false,
+            last_in_batch: true,
+        }
+    );
+
+    packet_test!(
+        name: test_join_private_channel,
+        data: vec![
+            0x2, 0x0, 0x12, 0x0, 0x22, 0x0, 0x2, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x12, 0x0,
+            0x1a, 0x0, 0x1, 0x0, 0x0, 0x0, 0x1a, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x54, 0x0,
+            0x72, 0x0, 0x61, 0x0, 0x64, 0x0, 0x65, 0x0, 0x72, 0x0, 0x73, 0x0, 0x0, 0x0,
+        ],
+        expected: SJoinPrivateChannel {
+            members: vec![
+                SJoinPrivateChannelMember { user_id: 1 },
+                SJoinPrivateChannelMember { user_id: 2 },
+            ],
+            name: "Traders".to_string(),
+            slot: 2,
+            channel_id: 12,
+        }
+    );
+
+    packet_test!(
+        name: test_leave_guild,
+        data: vec![],
+        expected: SLeaveGuild {}
+    );
+
+    packet_test!(
+        name: test_leave_party,
+        data: vec![],
+        expected: SLeaveParty {}
+    );
+
+    packet_test!(
+        name: test_leave_party_member,
+        data: vec![
+            0xe, 0x0, 0x1, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0,
+            0x74, 0x0, 0x0, 0x0,
+        ],
+        expected: SLeavePartyMember {
+            name: "Test".to_string(),
+            server_id: 1,
+            user_id: 12,
+        }
+    );
+
+    packet_test!(
+        name: test_leave_private_channel,
+        data: vec![0xc, 0x0, 0x0, 0x0],
+        expected: SLeavePrivateChannel { channel_id: 12 }
+    );
+
+    packet_test!(
+        name: test_list_channel,
+        data: vec![
+            0x2, 0x0, 0x10, 0x0, 0x0, 0x0, 0x0, 0x0, 0xd, 0x0, 0x0, 0x0, 0x10, 0x0, 0x1c, 0x0, 0x1,
+            0x0, 0x0, 0x0, 0x32, 0x0, 0x0, 0x0, 0x1c, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x5, 0x0,
+            0x0, 0x0,
+        ],
+        expected: SListChannel {
+            channels: vec![
+                SListChannelEntry {
+                    channel: 1,
+                    density: 50,
+                },
+                SListChannelEntry {
+                    channel: 2,
+                    density: 5,
+                },
+            ],
+            unk1: 0,
+            zone: 13,
+        }
+    );
+
+    packet_test!(
+        name: test_list_parcel_ex,
+        data: vec![
+            0x2, 0x0, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0,
+            0x14, 0x0, 0x3a, 0x0, 0x60, 0x0, 0x6a, 0x0, 0x4, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xf0, 0x49, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x74,
+            0xe1, 0x5e, 0x0, 0x0, 0x0, 0x0, 0x3a, 0x0, 0x0, 0x0, 0x70, 0x0, 0x76, 0x0, 0x5, 0x14,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x1, 0x1, 0x80, 0xc5, 0xe2, 0x5e, 0x0, 0x0, 0x0, 0x0, 0x45, 0x0, 0x6c, 0x0,
+            0x69, 0x0, 0x6e, 0x0, 0x0, 0x0, 0x48, 0x0, 0x69, 0x0, 0x0, 0x0, 0x47, 0x0, 0x4d, 0x0,
+            0x0, 0x0, 0x4f, 0x0, 0x6b, 0x0, 0x0, 0x0,
+        ],
+        expected: SListParcelEx {
+            parcels: vec![
+                SListParcelExParcel {
+                    sender: "Elin".to_string(),
+                    title: "Hi".to_string(),
+                    id: 5124,
+                    money: 150000,
+                    item_count: 1,
+                    is_read: false,
+                    is_returned: false,
+                    expires_at: 1591833600,
+                },
+                SListParcelExParcel {
+                    sender: "GM".to_string(),
+                    title: "Ok".to_string(),
+                    id: 5125,
+                    money: 0,
+                    item_count: 0,
+                    is_read: true,
+                    is_returned: true,
+                    expires_at: 1591920000,
+                },
+            ],
+            page: 0,
+            page_count: 1,
+            unread_count: 1,
+        }
+    );
+
+    packet_test!(
+        name: test_loading_screen_control_info,
+        data: vec![
+            0x1,
+        ],
+        expected: SLoadingScreenControlInfo {
+            custom_screen_enabled: true,
+        }
+    );
+
+    packet_test!(
+        name: test_load_hint,
+        data: vec![
+            0x0, 0x0, 0x0, 0x0
+        ],
+        expected: SLoadHint {
+            unk1: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_load_topo,
+        data: vec![
+            0x5, 0x0, 0x0, 0x0, 0x0, 0x10, 0x7e, 0x46, 0x0, 0xa0, 0x9c, 0x44, 0x0,
+            0xd0, 0x89, 0xc5, 0x0
+        ],
+        expected: SLoadTopo {
+            zone: 5,
+            location: Vec3f{x: 16260.0, y: 1253.0, z: -4410.0},
+            disable_loading_screen: false,
+        }
+    );
+
+    packet_test!(
+        name: test_login,
+        data: vec![
+            0x2, 0x0, 0x3d, 0x1, 0x75, 0x1, 0x85, 0x1, 0x20, 0x0, 0xa5, 0x1, 0x40, 0x0, 0xfa,
+            0x2a, 0x0, 0x0, 0x3a, 0x22, 0x1d, 0x0, 0x0, 0x80, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0,
+            0xe4, 0x98, 0x98, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x32, 0x0, 0x0,
+            0x0, 0xb3, 0x0, 0x0, 0x0, 0x65, 0xa, 0x0, 0x0, 0x7, 0x8, 0x4, 0x0, 0x1, 0x0, 0x41,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x5e, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x5e, 0x1, 0x0,
+            0x0, 0x5e, 0x1, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xf,
+            0xe1, 0xef, 0x3e, 0x0, 0x0, 0x0, 0x0, 0x45, 0xf6, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x96, 0x7, 0x44, 0x8d, 0x0, 0x0, 0x0, 0x0, 0xa, 0x1, 0x0, 0x0, 0x9c, 0xca, 0x16,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x40, 0x46, 0x74, 0x11, 0x0, 0x0, 0x0,
+            0x0, 0x4c, 0x46, 0x74, 0x11, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0,
+            0x0, 0x0, 0xd1, 0x6e, 0x0, 0x0, 0x19, 0x78, 0x1, 0x0, 0x1b, 0x78, 0x1, 0x0, 0x1d,
+            0x78, 0x1, 0x0, 0x5b, 0xbb, 0x2, 0x0, 0x88, 0xc3, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xab, 0xb0, 0x43, 0x2, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0xa, 0x3, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0xf, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x7a,
+            0xb3, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x2d, 0x98, 0x2, 0x0, 0x61,
+            0xb6, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x3c, 0x19, 0x19, 0x19, 0x1, 0x1e, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x64, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0,
+            0x0, 0x3d, 0x1, 0x59, 0x1, 0xbb, 0xc9, 0x3, 0x0, 0x0, 0x0, 0x0, 0x0, 0x60, 0x2,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x64, 0x0, 0x0, 0x0, 0xff, 0xff, 0xff, 0xff, 0x59,
+            0x1, 0x0, 0x0, 0xff, 0xf4, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x61, 0x2, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x64, 0x0, 0x0, 0x0, 0xff, 0xff, 0xff, 0xff, 0x4d, 0x0, 0x69, 0x0,
+            0x6e, 0x0, 0x65, 0x0, 0x72, 0x0, 0x76, 0x0, 0x61, 0x0, 0x0, 0x0, 0x0, 0x7, 0x0,
+            0xc, 0x0, 0x0, 0x0, 0x0, 0x1a, 0x18, 0x14, 0x0, 0x0, 0xd, 0x7, 0x0, 0x10, 0x0,
+            0x10, 0x10, 0x0, 0x0, 0x0, 0xe, 0x11, 0x1d, 0xc, 0x18, 0x1a, 0x10, 0x7, 0x3, 0x1,
+            0x12, 0x10, 0x13, 0x13, 0x10, 0x13, 0x13, 0x13, 0x10, 0x10, 0x11, 0x10, 0xf, 0xf,
+            0xf, 0x10, 0x13, 0xa, 0x0, 0x16, 0x18, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0,
+        ],
+        expected: SLogin {
+            servants: vec![SLoginServantEntry {
+                database_id: 248251,
+                id: 608,
+                servant_type: ServantType::Pet,
+                energy: 100,
+                slot: -1
+            },
+            SLoginServantEntry {
+                database_id: 128255,
+                id: 609,
+                servant_type: ServantType::Pet,
+                energy: 100,
+                slot: -1
+            }],
+            name: "Minerva".to_string(),
+            details: vec![0, 7, 0, 12, 0, 0, 0, 0, 26, 24, 20, 0, 0, 13, 7, 0, 16, 0, 16, 16, 0, 0, 0, 14, 17, 29, 12, 24, 26, 16, 7, 3],
+            shape: vec![1, 18, 16, 19, 19, 16, 19, 19, 19, 16, 16, 17, 16, 15, 15, 15, 16, 19, 10, 0, 22, 24, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            template_id: TemplateID{race: Race::ElinPopori, gender: Gender::Female, class: Class::Lancer},
+            id: from_vec::<EntityId>(vec![0x3A,0x22,0x1D,0x0,0x0,0x80,0,0])?,
+            server_id: 1,
+            db_id: 10000612,
+            action_mode: 0,
+            alive: true,
+            status: 0,
+            walk_speed: 50,
+            run_speed: 179,
+            appearance: Customization(vec![101, 10, 0, 0, 7, 8, 4, 0]),
+            visible: true,
+            is_second_character: false,
+            level: 65,
+            awakening_level: 0,
+            profession_mineral: 350,
+            profession_bug: 0,
+            profession_herb: 350,
+            profession_energy: 350,
+            profession_pet: 1,
+            pvp_declared_count: 0,
+            pvp_kill_count: 0,
+            total_exp: 1055908111,
+            level_exp: 128581,
+            total_level_exp: 2370045846,
+            ep_level: 266,
+            ep_exp: 1493660,
+            ep_daily_exp: 0,
+            rest_bonus_exp: 292832832,
+            max_rest_bonus_exp: 292832844,
+            exp_bonus_percent: 1.0,
+            drop_bonus_percent: 0.0,
+            weapon: 28369,
+            body: 96281,
+            hand: 96283,
+            feet: 96285,
+            underwear: 179035,
+            head: 50056,
+            face: 0,
+            server_time: 37990571,
+            is_pvp_server: true,
+            chat_ban_end_time: 0,
+            title: 778,
+            weapon_model: 0,
+            body_model: 0,
+            hand_model: 0,
+            feet_model: 0,
+            weapon_dye: 0,
+            body_dye: 0,
+            hand_dye: 0,
+            feet_dye: 0,
+            underwear_dye: 0,
+            style_back_dye: 0,
+            style_head_dye: 0,
+            style_face_dye: 0,
+            weapon_enchant: 15,
+            is_world_event_target: false,
+            infamy: 0,
+            show_face: true,
+            style_head: 177018,
+            style_face: 0,
+            style_back: 0,
+            style_weapon: 170029,
+            style_body: 177761,
+            style_footprint: 0,
+            style_body_dye: 421075260,
+            show_style: true,
+            title_count: 30,
+            appearance2: 100,
+            scale: 1.0,
+            guild_logo_id: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_login_account_info,
+        data: vec![
+            0x12, 0x0, 0xfe, 0x5c, 0x7, 0x0, 0x0, 0x0, 0x0, 0x0, 0xfe, 0xfe, 0xfe, 0xfe, 0x50, 0x0,
+            0x6c, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x65, 0x0, 0x74, 0x0, 0x44, 0x0, 0x42, 0x0, 0x5f, 0x0,
+            0x32, 0x0, 0x37, 0x0, 0x0, 0x0,
+        ],
+        expected: SLoginAccountInfo {
+            server_name: "PlanetDB_27".to_string(),
+            account_id: 482_558,
+            integrity_iv: 4278124286,
+        }
+    );
+
+    packet_test!(
+        name: test_login_arbiter,
+        data: vec![
+            0x1, 0x0, 0x2, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x6, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: SLoginArbiter {
+            success: true,
+            login_queue: false,
+            status: 65538,
+            unk1: 0,
+            region: Region::Europe,
+            pvp_disabled: true,
+            unk2: 0,
+            unk3: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_notify_guild_war_status_change,
+        data: vec![
+            0xe, 0x0, 0x2, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x47, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0, 0x0, 0x0,
+        ],
+        expected: SNotifyGuildWarStatusChange {
+            guild_name: "Gantsu".to_string(),
+            guild_id: 2,
+            state: GuildWarState::Accepted,
+        }
+    );
+
+    packet_test!(
+        name: test_npc_location,
+        data: vec![
+            0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0, 0x40,
+            0x0, 0x0, 0x40, 0x40, 0x0, 0x40, 0x96, 0x0, 0x0, 0x0, 0x80, 0x40, 0x0, 0x0, 0xa0, 0x40,
+            0x0, 0x0, 0xc0, 0x40, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: SNpcLocation {
+            npc_id: from_vec::<EntityId>(vec![0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1])?,
+            location: Vec3f{x: 1.0, y: 2.0, z: 3.0},
+            rotation: Angle::from_deg(90.0),
+            speed: 150,
+            destination: Vec3f{x: 4.0, y: 5.0, z: 6.0},
+            move_type: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_parcel_read_recv_status,
+        data: vec![
+            0x3, 0x0, 0x0, 0x0,
+        ],
+        expected: SParcelReadRecvStatus { unread_count: 3 }
+    );
+
+    packet_test!(
+        name: test_party_looting_method,
+        data: vec![
+            0x2, 0x0, 0x0, 0x0,
+        ],
+        expected: SPartyLootingMethod {
+            looting_method: LootingMethod::Leader,
         }
     );
 
     packet_test!(
-        name: test_can_create_user,
+        name: test_party_member_change_hp,
         data: vec![
-            0x1,
+            0x1, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0xdc, 0x5, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xd0, 0x7, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
         ],
-        expected: SCanCreateUser {
-            ok: true,
+        expected: SPartyMemberChangeHp {
+            server_id: 1,
+            user_id: 12,
+            current_hp: 1500,
+            max_hp: 2000,
         }
     );
 
     packet_test!(
-        name: test_check_username,
+        name: test_party_member_interval_pos_update,
         data: vec![
-            0x1
+            0x1, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0, 0x40,
+            0x0, 0x0, 0x40, 0x40, 0x5, 0x0, 0x0, 0x0,
         ],
-        expected: SCheckUserName {
-            ok: true,
+        expected: SPartyMemberIntervalPosUpdate {
+            server_id: 1,
+            user_id: 12,
+            location: Vec3f {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+            },
+            zone_id: 5,
         }
     );
 
     packet_test!(
-        name: test_check_version,
+        name: test_party_member_list,
         data: vec![
-            0x1
+            0x1, 0x0, 0x15, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0,
+            0x0, 0x15, 0x0, 0x0, 0x0, 0x2c, 0x0, 0x1, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x41,
+            0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x1, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0,
+            0x0, 0x0,
         ],
-        expected: SCheckVersion {
-            ok: true,
+        expected: SPartyMemberList {
+            members: vec![SPartyMemberListEntry {
+                name: "Test".to_string(),
+                server_id: 1,
+                user_id: 12,
+                level: 65,
+                class: Class::Lancer,
+                online: true,
+            }],
+            is_raid: false,
+            leader_server_id: 1,
+            leader_user_id: 12,
+            looting_method: LootingMethod::RoundRobin,
         }
     );
 
     packet_test!(
-        name: test_create_user,
+        name: test_ping,
+        data: vec![],
+        expected: SPing {}
+    );
+
+    packet_test!(
+        name: test_player_change_exp,
         data: vec![
-            0x1
+            0xdc, 0x5, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xf4, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x3c, 0x6d, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0xfc, 0x8, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xa0, 0x86, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x94, 0x11, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xa0, 0x86, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0,
         ],
-        expected: SCreateUser {
-            ok: true,
+        expected: SPlayerChangeExp {
+            gained_exp: 1500,
+            gained_rest_bonus_exp: 500,
+            total_exp: 93_500,
+            level_exp: 2_300,
+            total_level_exp: 100_000,
+            rest_bonus_exp: 4_500,
+            max_rest_bonus_exp: 100_000,
         }
     );
 
     packet_test!(
-        name: test_delete_user,
+        name: test_player_change_mp,
         data: vec![
-            0x1
+            0x5a, 0x0, 0x0, 0x0, 0x64, 0x0, 0x0, 0x0, 0xf6, 0xff, 0xff, 0xff, 0x0, 0x0, 0x0, 0x0,
+            0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
         ],
-        expected: SDeleteUser {
-            ok: true,
+        expected: SPlayerChangeMp {
+            current_mp: 90,
+            max_mp: 100,
+            diff: -10,
+            change_type: 0,
+            target_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            source_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
         }
     );
 
     packet_test!(
-        name: test_item_custom_string1,
+        name: test_private_channel_notice,
         data: vec![
-            0x0, 0x0, 0x0, 0x0, 0x11, 0x7f, 0x1c, 0x0, 0x0, 0x80, 0x0, 0x2,
+            0xc, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0xe, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0,
+            0x74, 0x0, 0x0, 0x0,
         ],
-        expected: SItemCustomString {
-            custom_strings: Vec::with_capacity(0),
-            game_id: 144_255_925_566_078_737,
+        expected: SPrivateChannelNotice {
+            channel_id: 12,
+            event: 1,
+            name: "Test".to_string(),
         }
     );
 
     packet_test!(
-        name: test_item_custom_string2,
+        name: test_recv_parcel,
         data: vec![
-            0x1, 0x0, 0x10, 0x0, 0x11, 0x21, 0x11, 0x0, 0x0, 0x80, 0x0, 0x3, 0x10, 0x0, 0x0, 0x0,
-            0x1a, 0x0, 0x22, 0x11, 0x2, 0x0, 0x50, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0,
-            0x75, 0x0, 0x0, 0x0,
+            0x4, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1,
         ],
-        expected: SItemCustomString {
-            custom_strings: vec![SItemCustomStringEntry {
-                string: "Pantsu".to_string(),
-                id: 135_458,
-            }],
-            game_id: 216_313_519_603_261_713,
+        expected: SRecvParcel {
+            id: 5124,
+            success: true,
         }
     );
 
     packet_test!(
-        name: test_get_user_list,
+        name: test_reject_contract,
         data: vec![
-            0x1, 0x0, 0x23, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x28, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x18, 0x0, 0x0, 0x0, 0x23, 0x0, 0x0, 0x0, 0x1, 0x0, 0xeb, 0x1, 0x3, 0x2, 0x15, 0x2,
-            0x20, 0x0, 0x35, 0x2, 0x40, 0x0, 0x75, 0x2, 0x3, 0x85, 0x1e, 0x0, 0x1, 0x0, 0x0, 0x0, 0x4, 0x0, 0x0, 0x0, 0x1,
-            0x0, 0x0, 0x0, 0x41, 0x0, 0x0, 0x0, 0x17, 0xd9, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0xd0, 0x7, 0x0, 0x0, 0x1, 0x0,
-            0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x8, 0x0, 0x0, 0x0, 0xf1, 0xe, 0x6b, 0x5e, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x51,
-            0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0xed, 0xb, 0x79, 0xa1, 0xd1, 0x6e, 0x0, 0x0, 0x8f, 0x78, 0x1, 0x0, 0x8e, 0x78,
-            0x1, 0x0, 0x19, 0x78, 0x1, 0x0, 0x1b, 0x78, 0x1, 0x0, 0x1d, 0x78, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x88, 0x78,
-            0x1, 0x0, 0x87, 0x78, 0x1, 0x0, 0x5b, 0xbb, 0x2, 0x0, 0x88, 0xc3, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x2, 0x3,
-            0x4, 0x5, 0x6, 0x7, 0x8, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x6d, 0xba,
-            0x77, 0xa1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x7a, 0xb3, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x2d, 0x98, 0x2, 0x0, 0x61, 0xb6, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x3c, 0x19, 0x19, 0x19, 0xf, 0x0, 0x0, 0x0,
-            0x40, 0x46, 0x74, 0x11, 0x0, 0x0, 0x0, 0x0, 0x4c, 0x46, 0x74, 0x11, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x80,
-            0x3f, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x64, 0x0, 0x0, 0x0, 0xfd, 0x34, 0x0,
-            0x0, 0x2, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0xa9, 0x11, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xeb, 0x1, 0x0,
-            0x0, 0xf5, 0x1, 0x68, 0xe1, 0x3, 0x0, 0x50, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0, 0x0,
-            0x0, 0x41, 0x0, 0x6c, 0x0, 0x6d, 0x0, 0x65, 0x0, 0x74, 0x0, 0x69, 0x0, 0x63, 0x0, 0x61, 0x0, 0x0, 0x0, 0x0,
-            0x7, 0x0, 0xc, 0x0, 0x0, 0x0, 0x0, 0x1a, 0x18, 0x14, 0x0, 0x0, 0xd, 0x7, 0x0, 0x10, 0x0, 0x10, 0x10, 0x0, 0x0,
-            0x0, 0xe, 0x11, 0x1d, 0xc, 0x18, 0x1a, 0x10, 0x7, 0x3, 0x1, 0x13, 0x10, 0x13, 0x13, 0x10, 0x13, 0x13, 0x13,
-            0x10, 0x10, 0x10, 0x10, 0xf, 0xf, 0xf, 0x10, 0x13, 0xa, 0x0, 0x16, 0x17, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x55, 0x0, 0x6e, 0x0, 0x6c, 0x0, 0x69, 0x0,
-            0x6d, 0x0, 0x69, 0x0, 0x74, 0x0, 0x65, 0x0, 0x64, 0x0, 0x20, 0x0, 0x50, 0x0, 0x6f, 0x0, 0x77, 0x0, 0x65, 0x0,
-            0x72, 0x0, 0x0, 0x0,
+            0xe, 0x0, 0x3, 0x0, 0x0, 0x0, 0x12, 0x4, 0x0, 0x0, 0x45, 0x0, 0x6c, 0x0, 0x69, 0x0,
+            0x6e, 0x0, 0x0, 0x0,
         ],
-        expected: SGetUserList {
-            characters: vec![SGetUserListCharacter {
-                custom_strings: vec![SGetUserListCharacterCustomString {
-                    string: "Pantsu".to_string(),
-                    id: 254_312,
-                }],
-                name: "Almetica".to_string(),
-                details: vec![
-                    0, 7, 0, 12, 0, 0, 0, 0, 26, 24, 20, 0, 0, 13, 7, 0, 16, 0, 16, 16, 0, 0, 0, 14, 17, 29, 12, 24, 26,
-                    16, 7, 3,
-                ],
-                shape: vec![
-                    1, 19, 16, 19, 19, 16, 19, 19, 19, 16, 16, 16, 16, 15, 15, 15, 16, 19, 10, 0, 22, 23, 9, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0,
-                ],
-                guild_name: "Unlimited Power".to_string(),
-                db_id: 2_000_131,
-                gender: Gender::Female,
-                race: Race::ElinPopori,
-                class: Class::Lancer,
-                level: 65,
-                hp: 121_111,
-                mp: 2000,
-                world_id: 1,
-                guard_id: 2,
-                section_id: 8,
-                last_logout_time: 1_584_074_481,
-                is_deleting: false,
-                delete_time: 86400,
-                delete_remain_sec: -1_585_902_611,
-                weapon: 28369,
-                earring1: 96399,
-                earring2: 96398,
-                body: 96281,
-                hand: 96283,
-                feet: 96285,
-                unk_item7: 0,
-                ring1: 96392,
-                ring2: 96391,
-                underwear: 179_035,
-                head: 50056,
-                face: 0,
-                appearance: Customization(vec![1,2,3,4,5,6,7,8]),
-                is_second_character: false,
-                admin_level: 0,
-                is_banned: false,
-                ban_end_time: 0,
-                ban_remain_sec: -1_585_989_011,
-                rename_needed: 0,
-                weapon_model: 0,
-                unk_model2: 0,
-                unk_model3: 0,
-                body_model: 0,
-                hand_model: 0,
-                feet_model: 0,
-                unk_model7: 0,
-                unk_model8: 0,
-                unk_model9: 0,
-                unk_model10: 0,
-                unk_dye1: 0,
-                unk_dye2: 0,
-                weapon_dye: 0,
-                body_dye: 0,
-                hand_dye: 0,
-                feet_dye: 0,
-                unk_dye7: 0,
-                unk_dye8: 0,
-                unk_dye9: 0,
-                underwear_dye: 0,
-                style_back_dye: 0,
-                style_head_dye: 0,
-                style_face_dye: 0,
-                style_head: 177_018,
-                style_face: 0,
-                style_back: 0,
-                style_weapon: 170_029,
-                style_body: 177_761,
-                style_footprint: 0,
-                style_body_dye: 421_075_260,
-                weapon_enchant: 15,
-                rest_bonus_xp: 292_832_832,
-                max_rest_bonus_xp: 292_832_844,
-                show_face: true,
-                style_head_scale: 1.0,
-                style_head_rotation: Vec3a { x: 0, y: 0, z: 0 },
-                style_head_translation: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
-                style_head_translation_debug: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
-                style_faces_scale: 1.0,
-                style_face_rotation: Vec3a { x: 0, y: 0, z: 0 },
-                style_face_translation: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
-                style_face_translation_debug: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
-                style_back_scale: 1.0,
-                style_back_rotation: Vec3a { x: 0, y: 0, z: 0 },
-                style_back_translation: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
-                style_back_translation_debug: Vec3f { x: 0.0, y: 0.0, z: 0.0 },
-                used_style_head_transform: false,
-                is_new_character: false,
-                tutorial_state: 0,
-                show_style: true,
-                appearance2: 100,
-                achievement_points: 13565,
-                laurel: 2,
-                lobby_slot: 1,
-                guild_logo_id: 4521,
-                awakening_level: 0,
-                has_broker_sales: false,
-            }],
-            veteran: false,
-            bonus_buf_sec: 0,
-            max_characters: 12,
-            first: true,
-            more: false,
-            left_del_time_account_over: 0,
-            deletion_section_classify_level: 40,
-            delete_character_expire_hour1: 0,
-            delete_character_expire_hour2: 24,
+        expected: SRejectContract {
+            name: "Elin".to_string(),
+            contract_type: 3,
+            contract_id: 1042,
+        }
+    );
+
+    packet_test!(
+        name: test_remain_play_time,
+        data: vec![
+            0x6, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: SRemainPlayTime {
+            account_type: 6,
+            minutes_left: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_remove_blocked_user,
+        data: vec![
+            0xc, 0x0, 0x0, 0x0,
+        ],
+        expected: SRemoveBlockedUser { user_id: 12 }
+    );
+
+    packet_test!(
+        name: test_request_contract,
+        data: vec![
+            0x20, 0x0, 0x2a, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0, 0x12, 0x4, 0x0, 0x0, 0x45, 0x0, 0x6c, 0x0,
+            0x69, 0x0, 0x6e, 0x0, 0x0, 0x0, 0x50, 0x0, 0x6f, 0x0, 0x70, 0x0, 0x6f, 0x0, 0x72, 0x0,
+            0x69, 0x0, 0x0, 0x0,
+        ],
+        expected: SRequestContract {
+            sender_name: "Elin".to_string(),
+            recipient_name: "Popori".to_string(),
+            sender_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            recipient_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            contract_type: 3,
+            contract_id: 1042,
+        }
+    );
+
+    packet_test!(
+        name: test_result_change_friend_memo,
+        data: vec![
+            0xc, 0x0, 0x0, 0x0, 0xb, 0x0, 0x1, 0x54, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x6b, 0x0, 0x0,
+            0x0,
+        ],
+        expected: SResultChangeFriendMemo {
+            user_id: 12,
+            memo: "Tank".to_string(),
+            success: true,
+        }
+    );
+
+    packet_test!(
+        name: test_return_parcel,
+        data: vec![
+            0x4, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        ],
+        expected: SReturnParcel {
+            id: 5124,
+            success: false,
+        }
+    );
+
+    packet_test!(
+        name: test_select_user,
+        data: vec![
+            0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x1
+        ],
+        expected: SSelectUser {
+            unk1: 1,
+            unk2: 0,
+            unk3: 72339069014638592,
         }
     );
 
     packet_test!(
-        name: test_guild_name,
+        name: test_send_parcel,
+        data: vec![
+            0x1,
+        ],
+        expected: SSendParcel { success: true }
+    );
+
+    packet_test!(
+        name: test_set_send_parcel_item,
         data: vec![
-            0x14, 0x0, 0x2e, 0x0, 0x48, 0x0, 0x58, 0x0, 0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3,
-            0x4d, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x68, 0x0, 0x75, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x65, 0x0,
-            0x72, 0x0, 0x20, 0x0, 0x4f, 0x0, 0x47, 0x0, 0x0, 0x0, 0x46, 0x0, 0x6f, 0x0, 0x72, 0x0,
-            0x20, 0x0, 0x74, 0x0, 0x68, 0x0, 0x65, 0x0, 0x20, 0x0, 0x77, 0x0, 0x69, 0x0, 0x6e, 0x0,
-            0x21, 0x0, 0x0, 0x0, 0x47, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0,
-            0x7e, 0x0, 0x0, 0x0, 0x67, 0x0, 0x75, 0x0, 0x69, 0x0, 0x6c, 0x0, 0x64, 0x0, 0x6c, 0x0,
-            0x6f, 0x0, 0x67, 0x0, 0x6f, 0x0, 0x5f, 0x0, 0x39, 0x0, 0x39, 0x0, 0x5f, 0x0, 0x31, 0x0,
-            0x31, 0x0, 0x31, 0x0, 0x31, 0x0, 0x5f, 0x0, 0x39, 0x0, 0x31, 0x0, 0x0, 0x0,
+            0x1, 0x0, 0x8, 0x0, 0x8, 0x0, 0x0, 0x0, 0x45, 0x1f, 0x0, 0x0, 0xd, 0x9, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x3, 0x0, 0x0, 0x0, 0x14, 0x0, 0x0, 0x0,
         ],
-        expected: SGuildName {
-            guild_name: "Manhunter OG".to_string(),
-            guild_rank: "For the win!".to_string(),
-            guild_title: "Gantsu~".to_string(),
-            guild_logo: "guildlogo_99_1111_91".to_string(),
-            game_id: 216_313_519_606_268_719,
+        expected: SSetSendParcelItem {
+            items: vec![SSetSendParcelItemItem {
+                id: 8005,
+                db_id: 2317,
+                inventory_slot: 3,
+                amount: 20,
+            }],
         }
     );
 
     packet_test!(
-        name: test_image_data,
+        name: test_set_send_parcel_money,
         data: vec![
-            0xa, 0x0, 0x36, 0x0, 0xe, 0x00, 0x67, 0x0, 0x75, 0x0, 0x69, 0x0, 0x6c, 0x0, 0x64, 0x0,
-            0x6c, 0x0, 0x6f, 0x0, 0x67, 0x0, 0x6f, 0x0, 0x5f, 0x0, 0x32, 0x0, 0x38, 0x0, 0x5f, 0x0,
-            0x35, 0x0, 0x35, 0x0, 0x35, 0x0, 0x31, 0x0, 0x39, 0x0, 0x5f, 0x0, 0x36, 0x0, 0x30, 0x0,
-            0x0, 0x0, 0x54, 0x45, 0x52, 0x41, 0x1, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xf0, 0x49, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0,
         ],
-        expected: SImageData {
-            name: "guildlogo_28_55519_60".to_string(),
-            data: vec![
-                0x54, 0x45, 0x52, 0x41, 0x1, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0,
-            ],
-        }
+        expected: SSetSendParcelMoney { money: 150000 }
     );
 
     packet_test!(
-        name: test_loading_screen_control_info,
+        name: test_show_parcel_message,
         data: vec![
-            0x1,
+            0x1, 0x0, 0x1e, 0x0, 0x32, 0x0, 0x3c, 0x0, 0x42, 0x0, 0x4, 0x14, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0xf0, 0x49, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1e, 0x0, 0x0, 0x0, 0x45, 0x1f,
+            0x0, 0x0, 0xd, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x14, 0x0, 0x0, 0x0, 0x45, 0x0,
+            0x6c, 0x0, 0x69, 0x0, 0x6e, 0x0, 0x0, 0x0, 0x48, 0x0, 0x69, 0x0, 0x0, 0x0, 0x47, 0x0,
+            0x69, 0x0, 0x66, 0x0, 0x74, 0x0, 0x0, 0x0,
         ],
-        expected: SLoadingScreenControlInfo {
-            custom_screen_enabled: true,
+        expected: SShowParcelMessage {
+            items: vec![SShowParcelMessageItem {
+                id: 8005,
+                db_id: 2317,
+                amount: 20,
+            }],
+            sender: "Elin".to_string(),
+            title: "Hi".to_string(),
+            message: "Gift".to_string(),
+            id: 5124,
+            money: 150000,
         }
     );
 
     packet_test!(
-        name: test_load_hint,
+        name: test_spawn_me,
         data: vec![
-            0x0, 0x0, 0x0, 0x0
+            0x11, 0x0, 0x1d, 0x0, 0x0, 0x80, 0x0, 0x0, 0x0, 0x10, 0x7e, 0x46, 0x0, 0xa0, 0x9c,
+            0x44, 0x0, 0xd0, 0x89, 0xc5, 0x34, 0xf3, 0x1, 0x0,
         ],
-        expected: SLoadHint {
-            unk1: 0,
+        expected: SSpawnMe {
+            user_id: from_vec::<EntityId>(vec![0x11,0x00,0x1D,0x0,0x0,0x80,0,0])?,
+            location: Vec3f{x: 16260.0, y: 1253.0, z: -4410.0},
+            rotation: Angle::from_deg(342.005),
+            is_alive: true,
+            is_lord: false,
         }
     );
 
     packet_test!(
-        name: test_load_topo,
+        name: test_spawn_npc,
         data: vec![
-            0x5, 0x0, 0x0, 0x0, 0x0, 0x10, 0x7e, 0x46, 0x0, 0xa0, 0x9c, 0x44, 0x0,
-            0xd0, 0x89, 0xc5, 0x0
+            0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x40, 0x40, 0x0, 0x40, 0xc, 0x0,
+            0x0, 0x0, 0xd1, 0x7, 0x0, 0x0, 0xd, 0x0, 0x0, 0x0, 0x0, 0x0, 0x32, 0x0, 0x96, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x5, 0x0, 0x0, 0x0, 0x1, 0x1, 0x1, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
         ],
-        expected: SLoadTopo {
-            zone: 5,
-            location: Vec3f{x: 16260.0, y: 1253.0, z: -4410.0},
-            disable_loading_screen: false,
+        expected: SSpawnNpc {
+            npc_id: from_vec::<EntityId>(vec![0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1])?,
+            target_id: 0,
+            location: Vec3f{x: 1.0, y: 2.0, z: 3.0},
+            rotation: Angle::from_deg(90.0),
+            relation: 12,
+            template_id: 2001,
+            hunting_zone_id: 13,
+            shape_id: 0,
+            walk_speed: 50,
+            run_speed: 150,
+            status: 0,
+            mode: 0,
+            hp_level: 5,
+            quest_info: 0,
+            visible: true,
+            villager: true,
+            spawn_type: 1,
+            replace_id: 0,
+            spawn_script: 0,
+            replace_despawn_script: 0,
+            aggressive: false,
+            owner_id: 0,
+            occupied_by_party_id: 0,
+            occupied_by_player_id: 0,
+            bomb: false,
+            by_spawn_event: false,
+            battleground_team: 0,
+            active_cylinder: 0,
+            repairable: false,
         }
     );
 
     packet_test!(
-        name: test_login,
+        name: test_spawn_user,
         data: vec![
-            0x2, 0x0, 0x3d, 0x1, 0x75, 0x1, 0x85, 0x1, 0x20, 0x0, 0xa5, 0x1, 0x40, 0x0, 0xfa,
-            0x2a, 0x0, 0x0, 0x3a, 0x22, 0x1d, 0x0, 0x0, 0x80, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0,
-            0xe4, 0x98, 0x98, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x32, 0x0, 0x0,
-            0x0, 0xb3, 0x0, 0x0, 0x0, 0x65, 0xa, 0x0, 0x0, 0x7, 0x8, 0x4, 0x0, 0x1, 0x0, 0x41,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x5e, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x5e, 0x1, 0x0,
-            0x0, 0x5e, 0x1, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xf,
-            0xe1, 0xef, 0x3e, 0x0, 0x0, 0x0, 0x0, 0x45, 0xf6, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x96, 0x7, 0x44, 0x8d, 0x0, 0x0, 0x0, 0x0, 0xa, 0x1, 0x0, 0x0, 0x9c, 0xca, 0x16,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x40, 0x46, 0x74, 0x11, 0x0, 0x0, 0x0,
-            0x0, 0x4c, 0x46, 0x74, 0x11, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0,
-            0x0, 0x0, 0xd1, 0x6e, 0x0, 0x0, 0x19, 0x78, 0x1, 0x0, 0x1b, 0x78, 0x1, 0x0, 0x1d,
-            0x78, 0x1, 0x0, 0x5b, 0xbb, 0x2, 0x0, 0x88, 0xc3, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0xab, 0xb0, 0x43, 0x2, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0xa, 0x3, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0xd8, 0x0, 0xe2, 0x0, 0xe4, 0x0, 0xe6, 0x0, 0x3, 0x0, 0xe9, 0x0,
+            0xeb, 0x0, 0x2, 0x0, 0xed, 0x0, 0x1, 0x0, 0x0, 0x0, 0x7, 0x0, 0x0, 0x0, 0x2b, 0x1,
+            0x0, 0x0, 0x0, 0x80, 0x0, 0x1, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0,
+            0x40, 0x40, 0x0, 0x40, 0x1, 0x0, 0x0, 0x0, 0xdf, 0x27, 0x0, 0x0, 0x32, 0x0, 0x96, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x1, 0x1, 0x65, 0x1, 0x4, 0x1, 0x0, 0x1c, 0x0, 0x0, 0x0, 0x11,
+            0x27, 0x0, 0x0, 0x9c, 0x3a, 0x0, 0x0, 0x9d, 0x3a, 0x0, 0x0, 0x9e, 0x3a, 0x0, 0x0, 0x0,
             0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
             0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0xf, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x7a,
-            0xb3, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x2d, 0x98, 0x2, 0x0, 0x61,
-            0xb6, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x3c, 0x19, 0x19, 0x19, 0x1, 0x1e, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x64, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f, 0x0, 0x0, 0x0,
-            0x0, 0x3d, 0x1, 0x59, 0x1, 0xbb, 0xc9, 0x3, 0x0, 0x0, 0x0, 0x0, 0x0, 0x60, 0x2,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x64, 0x0, 0x0, 0x0, 0xff, 0xff, 0xff, 0xff, 0x59,
-            0x1, 0x0, 0x0, 0xff, 0xf4, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x61, 0x2, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x64, 0x0, 0x0, 0x0, 0xff, 0xff, 0xff, 0xff, 0x4d, 0x0, 0x69, 0x0,
-            0x6e, 0x0, 0x65, 0x0, 0x72, 0x0, 0x76, 0x0, 0x61, 0x0, 0x0, 0x0, 0x0, 0x7, 0x0,
-            0xc, 0x0, 0x0, 0x0, 0x0, 0x1a, 0x18, 0x14, 0x0, 0x0, 0xd, 0x7, 0x0, 0x10, 0x0,
-            0x10, 0x10, 0x0, 0x0, 0x0, 0xe, 0x11, 0x1d, 0xc, 0x18, 0x1a, 0x10, 0x7, 0x3, 0x1,
-            0x12, 0x10, 0x13, 0x13, 0x10, 0x13, 0x13, 0x13, 0x10, 0x10, 0x11, 0x10, 0xf, 0xf,
-            0xf, 0x10, 0x13, 0xa, 0x0, 0x16, 0x18, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
             0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
             0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0,
+            0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x41, 0x0, 0x64, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x3f,
+            0x0, 0x0, 0x0, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x1, 0x2, 0x3, 0x0, 0x0, 0x4, 0x5, 0x0, 0x0,
         ],
-        expected: SLogin {
-            servants: vec![SLoginServantEntry {
-                database_id: 248251,
-                id: 608,
-                servant_type: ServantType::Pet,
-                energy: 100,
-                slot: -1
-            },
-            SLoginServantEntry {
-                database_id: 128255,
-                id: 609,
-                servant_type: ServantType::Pet,
-                energy: 100,
-                slot: -1
-            }],
-            name: "Minerva".to_string(),
-            details: vec![0, 7, 0, 12, 0, 0, 0, 0, 26, 24, 20, 0, 0, 13, 7, 0, 16, 0, 16, 16, 0, 0, 0, 14, 17, 29, 12, 24, 26, 16, 7, 3],
-            shape: vec![1, 18, 16, 19, 19, 16, 19, 19, 19, 16, 16, 17, 16, 15, 15, 15, 16, 19, 10, 0, 22, 24, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-            template_id: TemplateID{race: Race::ElinPopori, gender: Gender::Female, class: Class::Lancer},
-            id: from_vec::<EntityId>(vec![0x3A,0x22,0x1D,0x0,0x0,0x80,0,0])?,
+        expected: SSpawnUser {
+            servants: vec![],
+            name: "Test".to_string(),
+            guild_name: "".to_string(),
+            guild_rank: "".to_string(),
+            details: vec![0x1, 0x2, 0x3],
+            guild_title: "".to_string(),
+            shape: vec![0x4, 0x5],
+            guild_logo: "".to_string(),
             server_id: 1,
-            db_id: 10000612,
-            action_mode: 0,
-            alive: true,
-            status: 0,
+            db_id: 7,
+            user_id: from_vec::<EntityId>(vec![0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1])?,
+            location: Vec3f{x: 1.0, y: 2.0, z: 3.0},
+            rotation: Angle::from_deg(90.0),
+            relation: 1,
+            template_id: TemplateID {
+                race: Race::Human,
+                gender: Gender::Female,
+                class: Class::Priest,
+            },
             walk_speed: 50,
-            run_speed: 179,
-            appearance: Customization(vec![101, 10, 0, 0, 7, 8, 4, 0]),
+            run_speed: 150,
+            status: 0,
             visible: true,
-            is_second_character: false,
-            level: 65,
-            awakening_level: 0,
-            profession_mineral: 350,
-            profession_bug: 0,
-            profession_herb: 350,
-            profession_energy: 350,
-            profession_pet: 1,
-            pvp_declared_count: 0,
-            pvp_kill_count: 0,
-            total_exp: 1055908111,
-            level_exp: 128581,
-            total_level_exp: 2370045846,
-            ep_level: 266,
-            ep_exp: 1493660,
-            ep_daily_exp: 0,
-            rest_bonus_exp: 292832832,
-            max_rest_bonus_exp: 292832844,
-            exp_bonus_percent: 1.0,
-            drop_bonus_percent: 0.0,
-            weapon: 28369,
-            body: 96281,
-            hand: 96283,
-            feet: 96285,
-            underwear: 179035,
-            head: 50056,
+            is_alive: true,
+            appearance: Customization(vec![0x65, 0x1, 0x4, 0x1, 0x0, 0x1c, 0x0, 0x0]),
+            spawn_fx: false,
+            weapon: 10001,
+            body: 15004,
+            hand: 15005,
+            feet: 15006,
+            underwear: 0,
+            head: 0,
             face: 0,
-            server_time: 37990571,
-            is_pvp_server: true,
-            chat_ban_end_time: 0,
-            title: 778,
             weapon_model: 0,
             body_model: 0,
             hand_model: 0,
@@ -777,19 +2840,20 @@ mod tests {
             style_back_dye: 0,
             style_head_dye: 0,
             style_face_dye: 0,
-            weapon_enchant: 15,
+            weapon_enchant: 0,
             is_world_event_target: false,
             infamy: 0,
             show_face: true,
-            style_head: 177018,
+            style_head: 0,
             style_face: 0,
             style_back: 0,
-            style_weapon: 170029,
-            style_body: 177761,
+            style_weapon: 0,
+            style_body: 0,
             style_footprint: 0,
-            style_body_dye: 421075260,
+            style_body_dye: 0,
             show_style: true,
-            title_count: 30,
+            title: 0,
+            level: 65,
             appearance2: 100,
             scale: 1.0,
             guild_logo_id: 0,
@@ -797,77 +2861,227 @@ mod tests {
     );
 
     packet_test!(
-        name: test_login_account_info,
+        name: test_start_cooltime_skill,
+        data: vec![0x3b, 0x9c, 0x1, 0x0, 0x0, 0x0, 0x0, 0x4, 0x88, 0x13, 0x0, 0x0],
+        expected: SStartCooltimeSkill {
+            skill_id: 288_230_376_151_817_275,
+            cooldown: 5000,
+        }
+    );
+
+    packet_test!(
+        name: test_start_guild_war,
         data: vec![
-            0x12, 0x0, 0xfe, 0x5c, 0x7, 0x0, 0x0, 0x0, 0x0, 0x0, 0xfe, 0xfe, 0xfe, 0xfe, 0x50, 0x0,
-            0x6c, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x65, 0x0, 0x74, 0x0, 0x44, 0x0, 0x42, 0x0, 0x5f, 0x0,
-            0x32, 0x0, 0x37, 0x0, 0x0, 0x0,
+            0xa, 0x0, 0x2, 0x0, 0x0, 0x0, 0x47, 0x0, 0x61, 0x0, 0x6e, 0x0, 0x74, 0x0, 0x73, 0x0, 0x75, 0x0, 0x0, 0x0,
         ],
-        expected: SLoginAccountInfo {
-            server_name: "PlanetDB_27".to_string(),
-            account_id: 482_558,
-            integrity_iv: 4278124286,
+        expected: SStartGuildWar {
+            guild_name: "Gantsu".to_string(),
+            guild_id: 2,
         }
     );
 
     packet_test!(
-        name: test_login_arbiter,
+        name: test_trade_accept,
+        data: vec![0x12, 0x4, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0],
+        expected: STradeAccept {
+            contract_id: 1042,
+            user_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+        }
+    );
+
+    packet_test!(
+        name: test_trade_bag_close,
+        data: vec![0x12, 0x4, 0x0, 0x0, 0x1],
+        expected: STradeBagClose {
+            contract_id: 1042,
+            success: true,
+        }
+    );
+
+    packet_test!(
+        name: test_trade_bag_done,
+        data: vec![0x12, 0x4, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0],
+        expected: STradeBagDone {
+            contract_id: 1042,
+            user_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+        }
+    );
+
+    packet_test!(
+        name: test_trade_box,
         data: vec![
-            0x1, 0x0, 0x2, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x6, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0,
+            0x1, 0x0, 0x2c, 0x0, 0x12, 0x4, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0xf0, 0x49, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x2c, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x45, 0x1f, 0x0, 0x0, 0xd, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x14, 0x0, 0x0, 0x0,
         ],
-        expected: SLoginArbiter {
-            success: true,
-            login_queue: false,
-            status: 65538,
-            unk1: 0,
-            region: Region::Europe,
-            pvp_disabled: true,
-            unk2: 0,
-            unk3: 0,
+        expected: STradeBox {
+            items: vec![STradeBoxItem {
+                owner_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+                id: 8005,
+                db_id: 2317,
+                amount: 20,
+            }],
+            contract_id: 1042,
+            sender_id: from_vec::<EntityId>(vec![0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            sender_money: 0,
+            recipient_id: from_vec::<EntityId>(vec![0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])?,
+            recipient_money: 150000,
         }
     );
 
     packet_test!(
-        name: test_ping,
-        data: vec![],
-        expected: SPing {}
+        name: test_user_block_list,
+        data: vec![
+            0x1, 0x0, 0x8, 0x0, 0x8, 0x0, 0x0, 0x0, 0x1c, 0x0, 0x26, 0x0, 0xc, 0x0, 0x0, 0x0,
+            0x41, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0,
+            0x0, 0x0, 0x53, 0x0, 0x70, 0x0, 0x61, 0x0, 0x6d, 0x0, 0x0, 0x0,
+        ],
+        expected: SUserBlockList {
+            blocked_users: vec![SUserBlockListEntry {
+                name: "Test".to_string(),
+                memo: "Spam".to_string(),
+                user_id: 12,
+                level: 65,
+                class: Class::Lancer,
+            }],
+        }
     );
 
     packet_test!(
-        name: test_remain_play_time,
+        name: test_user_external_change,
         data: vec![
-            0x6, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0x11, 0x27, 0x0, 0x0, 0x9c, 0x3a, 0x0, 0x0,
+            0x9d, 0x3a, 0x0, 0x0, 0x9e, 0x3a, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xd0, 0x7e, 0x1, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x0, 0x1,
         ],
-        expected: SRemainPlayTime {
-            account_type: 6,
-            minutes_left: 0,
+        expected: SUserExternalChange {
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            weapon: 10001,
+            body: 15004,
+            hand: 15005,
+            feet: 15006,
+            underwear: 0,
+            head: 0,
+            face: 0,
+            weapon_model: 0,
+            body_model: 0,
+            hand_model: 0,
+            feet_model: 0,
+            weapon_dye: 0,
+            body_dye: 0,
+            hand_dye: 0,
+            feet_dye: 0,
+            underwear_dye: 0,
+            style_back_dye: 0,
+            style_head_dye: 0,
+            style_face_dye: 0,
+            weapon_enchant: 0,
+            show_face: true,
+            style_head: 0,
+            style_face: 0,
+            style_back: 0,
+            style_weapon: 0,
+            style_body: 98000,
+            style_footprint: 0,
+            style_body_dye: 0,
+            show_style: true,
         }
     );
 
     packet_test!(
-        name: test_select_user,
+        name: test_user_levelup,
         data: vec![
-            0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x1
+            0xd, 0x0, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1, 0xb, 0x0,
         ],
-        expected: SSelectUser {
-            unk1: 1,
-            unk2: 0,
-            unk3: 72339069014638592,
+        expected: SUserLevelup {
+            target_id: from_vec::<EntityId>(vec![0xd, 0x0, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1])?,
+            level: 11,
         }
     );
 
     packet_test!(
-        name: test_spawn_me,
+        name: test_user_location,
         data: vec![
             0x11, 0x0, 0x1d, 0x0, 0x0, 0x80, 0x0, 0x0, 0x0, 0x10, 0x7e, 0x46, 0x0, 0xa0, 0x9c,
-            0x44, 0x0, 0xd0, 0x89, 0xc5, 0x34, 0xf3, 0x1, 0x0,
+            0x44, 0x0, 0xd0, 0x89, 0xc5, 0x0, 0x40, 0x0, 0x0, 0x96, 0x0, 0x0, 0x14, 0x7e, 0x46,
+            0x0, 0xa0, 0x9c, 0x44, 0x0, 0xd0, 0x89, 0xc5, 0x0, 0x0, 0x0, 0x0, 0x0, 0xe8, 0x3, 0x0,
+            0x0,
         ],
-        expected: SSpawnMe {
+        expected: SUserLocation {
             user_id: from_vec::<EntityId>(vec![0x11,0x00,0x1D,0x0,0x0,0x80,0,0])?,
             location: Vec3f{x: 16260.0, y: 1253.0, z: -4410.0},
-            rotation: Angle::from_deg(342.005),
-            is_alive: true,
-            is_lord: false,
+            rotation: Angle::from_deg(90.0),
+            look_direction: 0,
+            speed: 150,
+            destination: Vec3f{x: 16261.0, y: 1253.0, z: -4410.0},
+            move_type: 0,
+            in_shuttle: false,
+            time: 1000,
+        }
+    );
+
+    packet_test!(
+        name: test_view_ware_ex,
+        data: vec![
+            0x2, 0x0, 0x28, 0x0, 0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3, 0x1, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x48, 0x0, 0x0, 0x0, 0x90, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x28, 0x0, 0x45, 0x0, 0x45, 0x1f, 0x0, 0x0, 0xd, 0x9, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0, 0x48, 0x0, 0x0, 0x0, 0x14, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x45, 0x0, 0x0, 0x0, 0x11, 0x27, 0x0, 0x0, 0xe, 0x9, 0x0, 0x0, 0x0, 0x0, 0x0,
+            0x0, 0x50, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1,
+        ],
+        expected: SViewWareEx {
+            items: vec![
+                SViewWareExItem {
+                    id: 8005,
+                    db_id: 2317,
+                    slot: 72,
+                    amount: 20,
+                    enchantment: 0,
+                    soulbound: false,
+                },
+                SViewWareExItem {
+                    id: 10001,
+                    db_id: 2318,
+                    slot: 80,
+                    amount: 1,
+                    enchantment: 0,
+                    soulbound: true,
+                },
+            ],
+            game_id: from_vec::<EntityId>(vec![0x2f, 0x3, 0x3f, 0x0, 0x0, 0x80, 0x0, 0x3])?,
+            container: 1,
+            action: 0,
+            offset: 72,
+            size: 144,
+            money: 0,
+        }
+    );
+
+    packet_test!(
+        name: test_whisper,
+        data: vec![
+            0x15, 0x0, 0x1f, 0x0, 0x27, 0x0, 0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1, 0x0, 0x0,
+            0x0, 0x54, 0x0, 0x65, 0x0, 0x73, 0x0, 0x74, 0x0, 0x0, 0x0, 0x41, 0x0, 0x62, 0x0, 0x63,
+            0x0, 0x0, 0x0, 0x3c, 0x0, 0x46, 0x0, 0x4f, 0x0, 0x4e, 0x0, 0x54, 0x0, 0x3e, 0x0, 0x48,
+            0x0, 0x69, 0x0, 0x3c, 0x0, 0x2f, 0x0, 0x46, 0x0, 0x4f, 0x0, 0x4e, 0x0, 0x54, 0x0, 0x3e,
+            0x0, 0x0, 0x0,
+        ],
+        expected: SWhisper {
+            author_name: "Test".to_string(),
+            recipient: "Abc".to_string(),
+            message: "<FONT>Hi</FONT>".to_string(),
+            user_id: from_vec::<EntityId>(vec![0x2b, 0x1, 0x0, 0x0, 0x0, 0x80, 0x0, 0x1])?,
+            unk1: 0,
+            is_gm: false,
+            is_founder: false,
         }
     );
 }
@@ -3,11 +3,11 @@ pub mod request;
 pub mod response;
 use crate::config::Configuration;
 use crate::crypt::password_hash::verify_hash;
-use crate::model::repository::{account, loginticket};
-use crate::model::PasswordHashAlgorithm;
+use crate::model::repository::{account, guild, guild_logo, loginticket, user};
+use crate::model::{is_valid_guild_logo, PasswordHashAlgorithm};
 use crate::webserver::response::{AuthResponse, ServerListEntry, ServerListResponse};
 use crate::{AlmeticaError, Result};
-use anyhow::ensure;
+use anyhow::{bail, ensure};
 use async_std::task;
 use http_types::StatusCode;
 use serde::Serialize;
@@ -29,6 +29,7 @@ pub async fn run(pool: PgPool, config: Configuration) -> Result<()> {
     let mut webserver = Server::with_state(WebServerState { config, pool });
     webserver.at("/server/*").get(server_list_endpoint);
     webserver.at("/auth").post(auth_endpoint);
+    webserver.at("/guild/logo").post(guild_logo_endpoint);
     webserver.listen(listen_string).await?;
     Ok(())
 }
@@ -95,9 +96,76 @@ async fn auth_endpoint(mut req: Request<WebServerState>) -> tide::Result<Respons
     Ok(valid_login_response(ticket))
 }
 
+/// Handles the upload of guild logos. Only the guild master can change the logo of a guild.
+async fn guild_logo_endpoint(mut req: Request<WebServerState>) -> tide::Result<Response> {
+    let upload_request: request::GuildLogoUpload = match req.body_form().await {
+        Ok(upload) => upload,
+        Err(e) => {
+            error!("Couldn't deserialize guild logo upload request: {:?}", e);
+            return Ok(Response::new(StatusCode::BadRequest));
+        }
+    };
+
+    let pool = &req.state().pool;
+    let user_name = upload_request.username.clone();
+
+    if let Err(e) = upload_guild_logo(pool, upload_request).await {
+        let status_code = match e.downcast_ref::<AlmeticaError>() {
+            Some(AlmeticaError::InvalidLogin) => StatusCode::Unauthorized,
+            Some(AlmeticaError::InvalidGuildLogo) => StatusCode::BadRequest,
+            Some(AlmeticaError::MissingGuildAuthority) => StatusCode::Forbidden,
+            Some(..) | None => {
+                error!("Can't upload guild logo: {}", e);
+                StatusCode::InternalServerError
+            }
+        };
+        info!("Rejected guild logo upload of user {}: {}", user_name, e);
+        return Ok(Response::new(status_code));
+    }
+
+    info!("User {} uploaded a guild logo", user_name);
+
+    Ok(Response::new(StatusCode::Ok))
+}
+
 // TODO write a test for the login() function
 /// Tries to login with the given credentials. Returns the login ticket if successful.
 async fn login(pool: &PgPool, account_name: &str, password: String) -> Result<Vec<u8>> {
+    let account_id = verify_credentials(pool, account_name, password).await?;
+
+    let mut conn = pool.acquire().await?;
+    let ticket = loginticket::upsert_ticket(&mut conn, account_id).await?;
+    Ok(ticket.ticket)
+}
+
+/// Sets the logo of the guild that is led by the given user. The user needs to be part
+/// of the account of the given credentials.
+async fn upload_guild_logo(pool: &PgPool, upload_request: request::GuildLogoUpload) -> Result<()> {
+    let account_id =
+        verify_credentials(pool, &upload_request.accountname, upload_request.password).await?;
+
+    let logo = match base64::decode(&upload_request.logo) {
+        Ok(logo) => logo,
+        Err(..) => bail!(AlmeticaError::InvalidGuildLogo),
+    };
+    ensure!(is_valid_guild_logo(&logo), AlmeticaError::InvalidGuildLogo);
+
+    let mut conn = pool.acquire().await?;
+    let user = match user::get_by_name(&mut conn, &upload_request.username).await {
+        Ok(user) if user.account_id == account_id => user,
+        _ => bail!(AlmeticaError::MissingGuildAuthority),
+    };
+    let guild = match guild::get_by_user_id(&mut conn, user.id).await {
+        Ok(guild) if guild.master_id == user.id => guild,
+        _ => bail!(AlmeticaError::MissingGuildAuthority),
+    };
+
+    guild_logo::upsert(&mut conn, guild.id, &logo).await?;
+    Ok(())
+}
+
+/// Verifies the credentials of an account. Returns the ID of the account if they are valid.
+async fn verify_credentials(pool: &PgPool, account_name: &str, password: String) -> Result<i64> {
     let mut conn = pool.acquire().await?;
     let (account_id, password_hash, password_algorithm) =
         match account::get_by_name(&mut conn, account_name).await {
@@ -116,8 +184,7 @@ async fn login(pool: &PgPool, account_name: &str, password: String) -> Result<Ve
     ensure!(account_id.is_some(), AlmeticaError::InvalidLogin);
     ensure!(is_valid, AlmeticaError::InvalidLogin);
 
-    let ticket = loginticket::upsert_ticket(&mut conn, account_id.unwrap()).await?;
-    Ok(ticket.ticket)
+    Ok(account_id.unwrap())
 }
 
 fn create_response(resp: &impl Serialize, status_code: StatusCode) -> Response {
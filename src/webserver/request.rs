@@ -5,3 +5,11 @@ pub struct Login {
     pub accountname: String,
     pub password: String,
 }
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GuildLogoUpload {
+    pub accountname: String,
+    pub password: String,
+    pub username: String,
+    pub logo: String, // base64 encoded logo in the TERA image format
+}